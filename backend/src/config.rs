@@ -0,0 +1,644 @@
+// Configuration module - process settings read from the environment once at startup
+//
+// `Config::from_env` validates every setting and collects all problems rather than stopping at the
+// first, each naming its variable and the value expected. Services take their settings from it
+// when they are built, and AppState keeps it for routes; only the test harnesses (contract and
+// integration tests) and chaos injection read the environment themselves.
+
+use crate::middleware::ingest::{IngestAuth, DEFAULT_MAX_SKEW_SECS};
+use crate::middleware::rate_limit::{Limits, RateLimits, GROUPS};
+use crate::services::auth_config::{AuthConfig, NONCE_TTL, REFRESH_TTL, TOKEN_TTL};
+use crate::services::data_plans::DataPlans;
+use crate::services::dex_programs::{configured_programs, ProgramConfig};
+use crate::services::feature_flags::FEATURES;
+use crate::services::follows::DEFAULT_MAX_FOLLOWS;
+use crate::services::jupiter::{DEFAULT_PRICE_API_URL, DEFAULT_SWAP_API_URL};
+use crate::services::maintenance::MaintenanceWindow;
+use crate::services::memory_budget::{
+    MemoryLimits, DEFAULT_DEDUP_CAPACITY, DEFAULT_DEDUP_TTL_SECS, DEFAULT_MAX_PAIRS, DEFAULT_PAIR_BUDGET_BYTES,
+};
+use crate::services::message_signing::MessageSigning;
+use crate::services::orderbook::DEFAULT_PHOENIX_MARKETS;
+use crate::services::preflight::StartupMode;
+use crate::services::query_limits::QueryLimits;
+use crate::services::recent_trades::DEFAULT_TRADES_PER_PAIR;
+use crate::services::sharding::{IngestShard, SHARD_INGEST_SOURCE};
+use crate::services::spikes::SpikeSettings;
+use crate::services::swap::{FeeSchedule, SlippagePolicy};
+use crate::services::token_registry::read_token_file;
+use crate::services::whales;
+use crate::utils::jwt::DEFAULT_JWT_SECRET;
+use crate::websocket::manager::Heartbeat;
+use ed25519_dalek::SigningKey;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+const MIN_JWT_SECRET_BYTES: usize = 32;
+
+/// A setting that is set but unusable
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub var: &'static str,
+    pub problem: String,
+    /// What a valid value looks like
+    pub expected: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}; expected {}", self.var, self.problem, self.expected)
+    }
+}
+
+/// ClickHouse connection, read concurrency and trade batching
+#[derive(Debug, Clone)]
+pub struct ClickHouseConfig {
+    /// CLICKHOUSE_URL with protocol and port, default http://localhost:8123
+    pub url: String,
+    /// CLICKHOUSE_USERNAME, default "default"
+    pub username: String,
+    /// CLICKHOUSE_PASSWORD, default empty
+    pub password: String,
+    /// Optional replica for analytics reads; CLICKHOUSE_READ_URL, CLICKHOUSE_READ_USERNAME and
+    /// CLICKHOUSE_READ_PASSWORD, the credentials defaulting to the primary's
+    pub read_replica: Option<ReadReplica>,
+    /// CLICKHOUSE_MAX_CONCURRENT_READS, default 16
+    pub max_concurrent_reads: usize,
+    /// CLICKHOUSE_MAX_CONCURRENT_STREAMS, default 4; ND-JSON /api/trades streams, apart from the reads
+    pub max_concurrent_streams: usize,
+    /// CLICKHOUSE_READ_QUEUE_TIMEOUT_MS, default 2000
+    pub read_queue_timeout: Duration,
+    /// CLICKHOUSE_BATCH_ROWS, default 500
+    pub batch_rows: usize,
+    /// CLICKHOUSE_BATCH_MS, default 1000
+    pub batch_interval: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadReplica {
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// QUICKNODE_RPC_URL (required); JSON-RPC over HTTP, and the log subscription over WebSocket
+    pub rpc_url: String,
+    /// PHOENIX_MARKETS, the market accounts served by /api/orderbook; default SOL/USDC
+    pub phoenix_markets: Vec<String>,
+    /// JUPITER_PRICE_API_URL, default Jupiter's Price API V3
+    pub jupiter_price_api_url: String,
+    /// JUPITER_SWAP_API_URL, default Jupiter's Swap API V6
+    pub jupiter_swap_api_url: String,
+    pub clickhouse: ClickHouseConfig,
+    /// JWT_SECRET, at least 32 bytes; required in release builds, the built-in development secret
+    /// when unset in debug builds
+    pub jwt_secret: String,
+    /// JWT_PREVIOUS_SECRET, the secret before a rotation; tokens it signed are still accepted
+    pub jwt_previous_secret: Option<String>,
+    /// PORT, default 3000
+    pub port: u16,
+    /// STARTUP_MODE, default full; preflight switches a degraded start to API-only
+    pub startup_mode: StartupMode,
+    /// DEX_PROGRAMS_FILE, else DEX_PROGRAMS, else the built-in list
+    pub dex_programs: Vec<ProgramConfig>,
+    /// INGEST_SHARD as index/count, default 0/1 (every program)
+    pub ingest_shard: IngestShard,
+    /// TOKEN_REGISTRY_FILE; the built-in tokens and pairs when unset
+    pub token_registry_file: Option<PathBuf>,
+    /// WS_HEARTBEAT_SECS (default 30) and WS_MAX_MISSED_HEARTBEATS (default 3)
+    pub heartbeat: Heartbeat,
+    /// SESSION_RETENTION_DAYS, how long expired sessions are kept; default 90
+    pub session_retention_days: u64,
+    /// DIGEST_HOUR_UTC, when daily digests are sent; default 0
+    pub digest_hour_utc: u32,
+    /// DIGEST_WEBHOOK_URL, where each digest is also POSTed
+    pub digest_webhook_url: Option<String>,
+    /// ADMIN_API_KEY, the X-Admin-Key for /admin routes; unset disables them
+    pub admin_api_key: Option<String>,
+    /// INGEST_SECRETS (source:secret,...) and INGEST_MAX_SKEW_SECS (default 300)
+    pub ingest: IngestAuth,
+    /// INGEST_FORWARD_URL, the hub this shard forwards its trades to; requires the shard ingest secret
+    pub ingest_forward_url: Option<String>,
+    /// WS_SIGNING_KEY, the server's base58 Ed25519 key for `ed25519` envelopes
+    pub ws_signing_key: Option<SigningKey>,
+    /// WS_HMAC_SECRETS (api_key:secret,...), the secrets of `hmac` envelopes
+    pub ws_hmac_secrets: HashMap<String, String>,
+    /// JWT_EXPIRY_SECS, REFRESH_TOKEN_TTL_SECS, NONCE_TTL_SECS and AUTH_OFFCHAIN_RAW_FALLBACK (default true)
+    pub auth: AuthConfig,
+    pub memory: MemoryLimits,
+    pub maintenance: MaintenanceWindow,
+    pub spikes: SpikeSettings,
+    /// WHALE_ALERT_MIN_USD, default 50000
+    pub whale_alert_min_usd: f64,
+    pub slippage: SlippagePolicy,
+    /// PLATFORM_FEE_BPS and PLATFORM_FEE_ACCOUNT, plus PARTNER_FEES overrides
+    pub fees: FeeSchedule,
+    /// DATA_PLAN_DELAYS, API_KEY_PLANS and DEFAULT_DATA_PLAN
+    pub data_plans: DataPlans,
+    /// DISABLED_FEATURES, features switched off in this deployment
+    pub disabled_features: Vec<String>,
+    /// RATE_LIMIT_TRADES, RATE_LIMIT_OHLCV, RATE_LIMIT_NONCE, RATE_LIMIT_VERIFY and RATE_LIMIT_TRUST_FORWARDED
+    pub rate_limits: RateLimits,
+    pub query_limits: QueryLimits,
+    /// RESPONSE_CACHE_TTL_MS, how long identical API responses are shared; default 2000, 1000 to 5000 or 0 (off)
+    pub response_cache_ttl: Duration,
+    /// RECENT_TRADES_PER_PAIR, default 500; 0 turns the cache off
+    pub recent_trades_per_pair: usize,
+    /// CIRCUIT_BREAKER_DEVIATION_PCT, default 25
+    pub circuit_breaker_deviation_pct: f64,
+    /// MAX_FOLLOWS_PER_WALLET, default 50
+    pub max_follows_per_wallet: usize,
+    /// FOLLOW_WEBHOOK_URL, where followed trades with `notify` set are POSTed
+    pub follow_webhook_url: Option<String>,
+    /// EXPORT_DIR, default the system temp directory's trade_exports
+    pub export_dir: PathBuf,
+    /// NOTIFICATION_TEMPLATES_FILE; the built-in templates when unset
+    pub notification_templates_file: Option<PathBuf>,
+    /// INSTANCE_ID, else HOSTNAME, else a random id; this replica's name in leases and heartbeats
+    pub instance_id: String,
+}
+
+/// Reads settings from a set of variables, collecting every problem
+struct Loader {
+    vars: HashMap<String, String>,
+    errors: Vec<ConfigError>,
+}
+
+impl Loader {
+    fn new(vars: HashMap<String, String>) -> Self {
+        Self { vars, errors: Vec::new() }
+    }
+
+    /// A variable's value as set, empty included
+    fn raw(&self, name: &str) -> Option<String> {
+        self.vars.get(name).cloned()
+    }
+
+    /// A variable's value; empty counts as unset
+    fn var(&self, name: &str) -> Option<String> {
+        self.raw(name).filter(|v| !v.trim().is_empty())
+    }
+
+    fn error(&mut self, var: &'static str, problem: impl Into<String>, expected: impl Into<String>) {
+        self.errors.push(ConfigError { var, problem: problem.into(), expected: expected.into() });
+    }
+
+    /// `default` when unset; an error (and `default`) when set but not accepted by `valid`
+    fn parse<T: FromStr>(&mut self, name: &'static str, default: T, valid: impl Fn(&T) -> bool, expected: &str) -> T {
+        let Some(raw) = self.var(name) else { return default };
+        match raw.trim().parse::<T>() {
+            Ok(value) if valid(&value) => value,
+            _ => {
+                self.error(name, format!("'{}' is not valid", raw.trim()), expected);
+                default
+            }
+        }
+    }
+
+    /// 0 turns the recent trades cache off, so every /api/trades request queries ClickHouse
+    fn recent_trades_per_pair(&mut self) -> usize {
+        self.parse("RECENT_TRADES_PER_PAIR", DEFAULT_TRADES_PER_PAIR, |_| true, "a number of trades, or 0 to always query ClickHouse")
+    }
+
+    fn clickhouse(&mut self) -> ClickHouseConfig {
+        let url = self.var("CLICKHOUSE_URL").unwrap_or_else(|| "http://localhost:8123".to_string());
+        let username = self.var("CLICKHOUSE_USERNAME").unwrap_or_else(|| "default".to_string());
+        let password = self.raw("CLICKHOUSE_PASSWORD").unwrap_or_default();
+        let http_url = |url: &str| url.starts_with("http://") || url.starts_with("https://");
+        if !http_url(&url) {
+            self.error("CLICKHOUSE_URL", format!("'{}' has no http(s) scheme", url), "the HTTP interface with protocol and port, e.g. https://instance.clickhouse.cloud:8443");
+        }
+
+        let read_replica = self.var("CLICKHOUSE_READ_URL").map(|read_url| {
+            if !http_url(&read_url) {
+                self.error("CLICKHOUSE_READ_URL", format!("'{}' has no http(s) scheme", read_url), "the replica's HTTP interface with protocol and port");
+            }
+            ReadReplica {
+                url: read_url,
+                username: self.var("CLICKHOUSE_READ_USERNAME").unwrap_or_else(|| username.clone()),
+                password: self.var("CLICKHOUSE_READ_PASSWORD").unwrap_or_else(|| password.clone()),
+            }
+        });
+
+        ClickHouseConfig {
+            url,
+            username,
+            password,
+            read_replica,
+            max_concurrent_reads: self.parse("CLICKHOUSE_MAX_CONCURRENT_READS", 16, |n| *n > 0, "a positive number of queries"),
+            max_concurrent_streams: self.parse("CLICKHOUSE_MAX_CONCURRENT_STREAMS", 4, |n| *n > 0, "a positive number of streams"),
+            read_queue_timeout: Duration::from_millis(self.parse("CLICKHOUSE_READ_QUEUE_TIMEOUT_MS", 2000, |_| true, "milliseconds")),
+            batch_rows: self.parse("CLICKHOUSE_BATCH_ROWS", 500, |n| *n > 0, "a positive number of rows"),
+            batch_interval: Duration::from_millis(self.parse("CLICKHOUSE_BATCH_MS", 1000, |_| true, "milliseconds")),
+        }
+    }
+
+    fn rpc_url(&mut self) -> String {
+        let Some(rpc_url) = self.var("QUICKNODE_RPC_URL") else {
+            self.error("QUICKNODE_RPC_URL", "not set", "your QuickNode Solana endpoint, e.g. https://your-endpoint.solana-mainnet.quiknode.pro/your-api-key/");
+            return String::new();
+        };
+        if !["http://", "https://", "ws://", "wss://"].iter().any(|scheme| rpc_url.starts_with(scheme)) {
+            self.error("QUICKNODE_RPC_URL", format!("'{}' has no http(s) or ws(s) scheme", rpc_url), "your QuickNode Solana endpoint URL");
+        }
+        rpc_url
+    }
+
+    fn phoenix_markets(&mut self) -> Vec<String> {
+        // Set but empty follows no markets
+        let Some(raw) = self.raw("PHOENIX_MARKETS") else {
+            return DEFAULT_PHOENIX_MARKETS.iter().map(|m| m.to_string()).collect();
+        };
+        let markets: Vec<String> = raw.split(',').map(str::trim).filter(|m| !m.is_empty()).map(String::from).collect();
+        for market in &markets {
+            if !bs58::decode(market).into_vec().is_ok_and(|bytes| bytes.len() == 32) {
+                self.error("PHOENIX_MARKETS", format!("'{}' is not a base58 account address", market), "comma-separated Phoenix market accounts");
+            }
+        }
+        markets
+    }
+
+    /// `release` is whether this is a release build, which never signs with the development secret
+    fn jwt_secret(&mut self, release: bool) -> String {
+        let expected = format!("a random string of at least {} bytes, e.g. from `openssl rand -hex 32`", MIN_JWT_SECRET_BYTES);
+        let Some(secret) = self.raw("JWT_SECRET").filter(|v| !v.is_empty()) else {
+            if release {
+                self.error("JWT_SECRET", "not set (required in release builds)", expected);
+            }
+            return DEFAULT_JWT_SECRET.to_string();
+        };
+        if secret.len() < MIN_JWT_SECRET_BYTES {
+            self.error("JWT_SECRET", format!("{} bytes is too short to resist brute force", secret.len()), expected);
+        }
+        secret
+    }
+
+    fn jwt_previous_secret(&mut self, current: &str) -> Option<String> {
+        let previous = self.raw("JWT_PREVIOUS_SECRET").filter(|v| !v.is_empty())?;
+        if previous.len() < MIN_JWT_SECRET_BYTES {
+            self.error("JWT_PREVIOUS_SECRET", format!("{} bytes is too short to resist brute force", previous.len()), "the JWT_SECRET used before the rotation");
+        } else if previous == current {
+            self.error("JWT_PREVIOUS_SECRET", "is the same as JWT_SECRET", "the JWT_SECRET used before the rotation, or unset");
+        }
+        Some(previous)
+    }
+
+    fn dex_programs(&mut self) -> Vec<ProgramConfig> {
+        let file = self.var("DEX_PROGRAMS_FILE");
+        let var_name = if file.is_some() { "DEX_PROGRAMS_FILE" } else { "DEX_PROGRAMS" };
+        match configured_programs(file.as_deref(), self.var("DEX_PROGRAMS").as_deref()) {
+            Ok(programs) => programs,
+            Err(e) => {
+                self.error(var_name, format!("{:#}", e), "a program list of base58 program IDs and names (see Supported DEX Programs)");
+                Vec::new()
+            }
+        }
+    }
+
+    fn ingest_shard(&mut self) -> IngestShard {
+        let Some(raw) = self.var("INGEST_SHARD") else { return IngestShard::default() };
+        IngestShard::parse(&raw).unwrap_or_else(|| {
+            self.error("INGEST_SHARD", format!("'{}' is not a shard", raw.trim()), "index/count with index < count, e.g. 0/3");
+            IngestShard::default()
+        })
+    }
+
+    fn token_registry_file(&mut self) -> Option<PathBuf> {
+        let path = PathBuf::from(self.var("TOKEN_REGISTRY_FILE")?);
+        if let Err(e) = read_token_file(&path) {
+            self.error("TOKEN_REGISTRY_FILE", format!("{:#}", e), "a readable token list (see Supported Trading Pairs)");
+        }
+        Some(path)
+    }
+
+    /// An optional http(s) URL
+    fn http_url(&mut self, name: &'static str, expected: &str) -> Option<String> {
+        let url = self.var(name)?;
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            self.error(name, format!("'{}' has no http(s) scheme", url), expected);
+        }
+        Some(url)
+    }
+
+    fn ingest(&mut self) -> IngestAuth {
+        let secrets = self.var("INGEST_SECRETS").map_or_else(HashMap::new, |raw| {
+            IngestAuth::parse_secrets(&raw).unwrap_or_else(|e| {
+                self.error("INGEST_SECRETS", e, "comma-separated source:secret entries");
+                HashMap::new()
+            })
+        });
+        IngestAuth {
+            secrets,
+            max_skew_secs: self.parse("INGEST_MAX_SKEW_SECS", DEFAULT_MAX_SKEW_SECS, |secs| *secs > 0, "a positive number of seconds"),
+        }
+    }
+
+    fn ingest_forward_url(&mut self, ingest: &IngestAuth) -> Option<String> {
+        let url = self.http_url("INGEST_FORWARD_URL", "the hub's base URL, e.g. http://hub:3000")?;
+        if !ingest.secrets.contains_key(SHARD_INGEST_SOURCE) {
+            self.error(
+                "INGEST_SECRETS",
+                format!("has no '{}' secret, which INGEST_FORWARD_URL signs with", SHARD_INGEST_SOURCE),
+                format!("a {}:secret entry shared with the hub", SHARD_INGEST_SOURCE),
+            );
+        }
+        Some(url)
+    }
+
+    fn ws_signing_key(&mut self) -> Option<SigningKey> {
+        let raw = self.var("WS_SIGNING_KEY")?;
+        MessageSigning::parse_server_key(&raw)
+            .map_err(|e| self.error("WS_SIGNING_KEY", e, "a base58 Ed25519 secret key (32-byte seed or 64-byte keypair)"))
+            .ok()
+    }
+
+    fn ws_hmac_secrets(&mut self) -> HashMap<String, String> {
+        let Some(raw) = self.var("WS_HMAC_SECRETS") else { return HashMap::new() };
+        MessageSigning::parse_hmac_secrets(&raw).unwrap_or_else(|e| {
+            self.error("WS_HMAC_SECRETS", e, "comma-separated api_key:secret entries");
+            HashMap::new()
+        })
+    }
+
+    /// A lifetime in seconds within its (env var, default, min, max) bounds
+    fn ttl(&mut self, (name, default, min, max): (&'static str, i64, i64, i64)) -> chrono::Duration {
+        let expected = format!("seconds from {} to {}", min, max);
+        chrono::Duration::seconds(self.parse(name, default, |secs| (min..=max).contains(secs), &expected))
+    }
+
+    fn auth(&mut self) -> AuthConfig {
+        let token_ttl = self.ttl(TOKEN_TTL);
+        let refresh_ttl = self.ttl(REFRESH_TTL);
+        if refresh_ttl < token_ttl {
+            self.error(
+                "REFRESH_TOKEN_TTL_SECS",
+                format!("{}s is shorter than JWT_EXPIRY_SECS ({}s)", refresh_ttl.num_seconds(), token_ttl.num_seconds()),
+                "at least JWT_EXPIRY_SECS",
+            );
+        }
+        AuthConfig {
+            token_ttl,
+            refresh_ttl,
+            nonce_ttl: self.ttl(NONCE_TTL),
+            offchain_raw_fallback: match self.var("AUTH_OFFCHAIN_RAW_FALLBACK").map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+                None | Some("true" | "1" | "yes") => true,
+                Some("false" | "0" | "no") => false,
+                Some(other) => {
+                    self.error("AUTH_OFFCHAIN_RAW_FALLBACK", format!("'{}' is not valid", other), "true or false");
+                    true
+                }
+            },
+        }
+    }
+
+    fn memory(&mut self) -> MemoryLimits {
+        MemoryLimits {
+            per_pair_bytes: self.parse("PAIR_MEMORY_BUDGET_BYTES", DEFAULT_PAIR_BUDGET_BYTES, |bytes| *bytes >= 1024, "at least 1024 bytes"),
+            max_pairs: self.parse("PAIR_CACHE_MAX_PAIRS", DEFAULT_MAX_PAIRS, |n| *n > 0, "a positive number of pairs"),
+            dedup_capacity: self.parse("SIGNATURE_DEDUP_CAPACITY", DEFAULT_DEDUP_CAPACITY, |n| *n > 0, "a positive number of signatures"),
+            dedup_ttl: Duration::from_secs(self.parse("SIGNATURE_DEDUP_TTL_SECS", DEFAULT_DEDUP_TTL_SECS, |secs| *secs > 0, "a positive number of seconds")),
+        }
+    }
+
+    fn maintenance(&mut self) -> MaintenanceWindow {
+        MaintenanceWindow {
+            start_hour: self.parse("MAINTENANCE_WINDOW_START_HOUR", 3, |hour| *hour < 24, "an hour from 0 to 23"),
+            hours: self.parse("MAINTENANCE_WINDOW_HOURS", 2, |hours| (1..=24).contains(hours), "1 to 24 hours"),
+            parts_threshold: self.parse("MAINTENANCE_PARTS_THRESHOLD", 50, |_| true, "a number of parts"),
+        }
+    }
+
+    fn spikes(&mut self) -> SpikeSettings {
+        let baseline_minutes = self.parse("SPIKE_BASELINE_MINUTES", 60, |minutes| *minutes >= 2, "at least 2 minutes");
+        let expected = format!("2 to {} minutes (SPIKE_BASELINE_MINUTES)", baseline_minutes);
+        SpikeSettings {
+            threshold: self.parse("SPIKE_Z_THRESHOLD", 4.0, |z: &f64| z.is_finite() && *z > 0.0, "a positive z-score"),
+            baseline_minutes,
+            min_baseline_minutes: self.parse("SPIKE_MIN_BASELINE_MINUTES", 15.min(baseline_minutes), |minutes| (2..=baseline_minutes).contains(minutes), &expected),
+            min_trades: self.parse("SPIKE_MIN_TRADES", 5, |_| true, "a number of trades"),
+        }
+    }
+
+    fn slippage(&mut self) -> SlippagePolicy {
+        let pct = |pct: &f64| pct.is_finite() && *pct >= 0.0;
+        SlippagePolicy {
+            max_slippage_bps: self.parse("MAX_SLIPPAGE_BPS", 300, |bps| *bps <= 10_000, "basis points from 0 to 10000"),
+            max_price_impact_pct: self.parse("MAX_PRICE_IMPACT_PCT", 5.0, pct, "a percentage"),
+            warn_price_impact_pct: self.parse("WARN_PRICE_IMPACT_PCT", 1.0, pct, "a percentage"),
+        }
+    }
+
+    fn fees(&mut self) -> FeeSchedule {
+        let fee_bps = self.parse("PLATFORM_FEE_BPS", 0, |bps| *bps <= 10_000, "basis points from 0 to 10000");
+        let fee_account = self.var("PLATFORM_FEE_ACCOUNT");
+        if fee_bps > 0 && fee_account.is_none() {
+            self.error("PLATFORM_FEE_ACCOUNT", "not set while PLATFORM_FEE_BPS is", "the token account that collects the platform fee");
+        }
+        FeeSchedule::new(fee_bps, fee_account, &self.var("PARTNER_FEES").unwrap_or_default()).unwrap_or_else(|e| {
+            self.error("PARTNER_FEES", e, "comma-separated name:api_key:fee_bps:fee_account entries");
+            FeeSchedule::default()
+        })
+    }
+
+    fn data_plans(&mut self) -> DataPlans {
+        let delays = self.var("DATA_PLAN_DELAYS").unwrap_or_default();
+        let api_key_plans = self.var("API_KEY_PLANS").unwrap_or_default();
+        DataPlans::parse(&delays, &api_key_plans, self.var("DEFAULT_DATA_PLAN").as_deref()).unwrap_or_else(|(name, problem)| {
+            self.error(name, problem, "plans defined in DATA_PLAN_DELAYS as plan:seconds, assigned with API_KEY_PLANS as api_key:plan");
+            DataPlans::default()
+        })
+    }
+
+    fn disabled_features(&mut self) -> Vec<String> {
+        let raw = self.var("DISABLED_FEATURES").unwrap_or_default();
+        let mut disabled = Vec::new();
+        for feature in raw.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            if FEATURES.contains(&feature) {
+                disabled.push(feature.to_string());
+            } else {
+                self.error("DISABLED_FEATURES", format!("'{}' is not a feature", feature), format!("comma-separated features from {}", FEATURES.join(", ")));
+            }
+        }
+        disabled
+    }
+
+    fn rate_limits(&mut self) -> RateLimits {
+        let groups = GROUPS
+            .iter()
+            .map(|(_, name, _, defaults)| {
+                let Some(raw) = self.var(name) else { return *defaults };
+                Limits::parse(&raw, *defaults).unwrap_or_else(|e| {
+                    self.error(name, e, "requests per minute as ip=120,wallet=600 (0 = unlimited)");
+                    *defaults
+                })
+            })
+            .collect();
+        let trusted_proxies = match self.var("RATE_LIMIT_TRUST_FORWARDED").as_deref().map(str::trim) {
+            Some("true") => 1,
+            Some("false") | None => 0,
+            Some(_) => self.parse("RATE_LIMIT_TRUST_FORWARDED", 0, |_| true, "true, false or the number of proxies in front"),
+        };
+        RateLimits { groups, trusted_proxies }
+    }
+
+    fn query_limits(&mut self) -> QueryLimits {
+        QueryLimits {
+            max_trades_limit: self.parse("MAX_TRADES_LIMIT", 1_000, |n| *n > 0, "a positive number of trades"),
+            max_stream_limit: self.parse("MAX_TRADES_STREAM_LIMIT", 100_000, |n| *n > 0, "a positive number of trades"),
+            max_page_size: self.parse("MAX_TRADES_PAGE_SIZE", 500, |n| *n > 0, "a positive number of trades"),
+            max_slot_range: self.parse("MAX_SLOT_RANGE", 216_000, |n| *n > 0, "a positive number of slots"),
+            max_candles: self.parse("MAX_OHLCV_CANDLES", 5_000, |n| *n > 0, "a positive number of candles"),
+            max_backtest_pairs: self.parse("MAX_BACKTEST_PAIRS", 10, |n| *n > 0, "a positive number of pairs"),
+        }
+    }
+
+    fn startup_mode(&mut self) -> StartupMode {
+        let Some(raw) = self.var("STARTUP_MODE") else { return StartupMode::Full };
+        StartupMode::parse(raw.trim()).unwrap_or_else(|| {
+            self.error("STARTUP_MODE", format!("'{}' is not a startup mode", raw.trim()), "full, degraded or api-only");
+            StartupMode::Full
+        })
+    }
+}
+
+impl Config {
+    /// Read and validate every setting; all problems found are returned together
+    pub fn from_env() -> Result<Self, Vec<ConfigError>> {
+        // Values that aren't valid UTF-8 count as unset
+        let vars = std::env::vars_os().filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+        Self::from_vars(vars.collect())
+    }
+
+    /// As `from_env`, from the given variables instead of the process environment
+    pub fn from_vars(vars: HashMap<String, String>) -> Result<Self, Vec<ConfigError>> {
+        let mut loader = Loader::new(vars);
+        let jwt_secret = loader.jwt_secret(!cfg!(debug_assertions));
+        let ingest = loader.ingest();
+        let config = Self {
+            rpc_url: loader.rpc_url(),
+            phoenix_markets: loader.phoenix_markets(),
+            jupiter_price_api_url: loader.http_url("JUPITER_PRICE_API_URL", "the Jupiter Price API V3 base URL").unwrap_or_else(|| DEFAULT_PRICE_API_URL.to_string()),
+            jupiter_swap_api_url: loader.http_url("JUPITER_SWAP_API_URL", "the Jupiter Swap API V6 base URL").unwrap_or_else(|| DEFAULT_SWAP_API_URL.to_string()),
+            clickhouse: loader.clickhouse(),
+            jwt_previous_secret: loader.jwt_previous_secret(&jwt_secret),
+            jwt_secret,
+            port: loader.parse("PORT", 3000, |port| *port > 0, "a TCP port number"),
+            startup_mode: loader.startup_mode(),
+            dex_programs: loader.dex_programs(),
+            ingest_shard: loader.ingest_shard(),
+            token_registry_file: loader.token_registry_file(),
+            heartbeat: Heartbeat {
+                interval: Duration::from_secs(loader.parse("WS_HEARTBEAT_SECS", 30, |secs| *secs > 0, "a positive number of seconds")),
+                max_missed: loader.parse("WS_MAX_MISSED_HEARTBEATS", 3, |missed| *missed > 0, "a positive number of pings"),
+            },
+            session_retention_days: loader.parse("SESSION_RETENTION_DAYS", 90, |_| true, "a number of days"),
+            digest_hour_utc: loader.parse("DIGEST_HOUR_UTC", 0, |hour| *hour < 24, "an hour from 0 to 23"),
+            digest_webhook_url: loader.http_url("DIGEST_WEBHOOK_URL", "the webhook URL digests are POSTed to"),
+            admin_api_key: loader.var("ADMIN_API_KEY"),
+            ingest_forward_url: loader.ingest_forward_url(&ingest),
+            ingest,
+            ws_signing_key: loader.ws_signing_key(),
+            ws_hmac_secrets: loader.ws_hmac_secrets(),
+            auth: loader.auth(),
+            memory: loader.memory(),
+            maintenance: loader.maintenance(),
+            spikes: loader.spikes(),
+            whale_alert_min_usd: loader.parse("WHALE_ALERT_MIN_USD", whales::DEFAULT_MIN_USD, |usd: &f64| usd.is_finite() && *usd >= 0.0, "a USD amount"),
+            slippage: loader.slippage(),
+            fees: loader.fees(),
+            data_plans: loader.data_plans(),
+            disabled_features: loader.disabled_features(),
+            rate_limits: loader.rate_limits(),
+            query_limits: loader.query_limits(),
+            response_cache_ttl: Duration::from_millis(loader.parse("RESPONSE_CACHE_TTL_MS", 2_000, |ms| *ms == 0 || (1_000..=5_000).contains(ms), "milliseconds from 1000 to 5000, or 0 to disable the cache")),
+            recent_trades_per_pair: loader.recent_trades_per_pair(),
+            circuit_breaker_deviation_pct: loader.parse("CIRCUIT_BREAKER_DEVIATION_PCT", 25.0, |pct: &f64| pct.is_finite() && *pct > 0.0, "a positive percentage"),
+            max_follows_per_wallet: loader.parse("MAX_FOLLOWS_PER_WALLET", DEFAULT_MAX_FOLLOWS, |n| *n > 0, "a positive number of wallets"),
+            follow_webhook_url: loader.http_url("FOLLOW_WEBHOOK_URL", "the webhook URL followed trades are POSTed to"),
+            export_dir: loader.var("EXPORT_DIR").map_or_else(|| std::env::temp_dir().join("trade_exports"), PathBuf::from),
+            notification_templates_file: loader.var("NOTIFICATION_TEMPLATES_FILE").map(PathBuf::from),
+            instance_id: loader.var("INSTANCE_ID").or_else(|| loader.var("HOSTNAME")).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        };
+        if loader.errors.is_empty() { Ok(config) } else { Err(loader.errors) }
+    }
+
+    /// Whether JWT_SECRET was left unset (or set to the development secret)
+    pub fn uses_default_jwt_secret(&self) -> bool {
+        self.jwt_secret == DEFAULT_JWT_SECRET
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loader(vars: &[(&str, &str)]) -> Loader {
+        Loader::new(vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect())
+    }
+
+    #[test]
+    fn parse_takes_the_default_when_unset() {
+        let mut loader = loader(&[("CONFIG_TEST_BLANK", "  ")]);
+        assert_eq!(loader.parse("CONFIG_TEST_UNSET", 7u32, |n| *n > 0, "a positive number"), 7);
+        assert_eq!(loader.parse("CONFIG_TEST_BLANK", 7u32, |n| *n > 0, "a positive number"), 7);
+        assert!(loader.errors.is_empty());
+    }
+
+    #[test]
+    fn parse_accepts_values_within_bounds() {
+        let mut loader = loader(&[("CONFIG_TEST_IN_BOUNDS", " 23 ")]);
+        assert_eq!(loader.parse("CONFIG_TEST_IN_BOUNDS", 0u32, |hour| *hour < 24, "an hour"), 23);
+        assert!(loader.errors.is_empty());
+    }
+
+    #[test]
+    fn invalid_values_are_all_reported_and_fall_back_to_the_default() {
+        let mut loader = loader(&[("CONFIG_TEST_OUT_OF_BOUNDS", "24"), ("CONFIG_TEST_NOT_A_NUMBER", "soon")]);
+        assert_eq!(loader.parse("CONFIG_TEST_OUT_OF_BOUNDS", 0u32, |hour| *hour < 24, "an hour from 0 to 23"), 0);
+        assert_eq!(loader.parse("CONFIG_TEST_NOT_A_NUMBER", 5u64, |_| true, "a number of seconds"), 5);
+
+        let reported: Vec<String> = loader.errors.iter().map(ConfigError::to_string).collect();
+        assert_eq!(
+            reported,
+            [
+                "CONFIG_TEST_OUT_OF_BOUNDS: '24' is not valid; expected an hour from 0 to 23",
+                "CONFIG_TEST_NOT_A_NUMBER: 'soon' is not valid; expected a number of seconds",
+            ]
+        );
+    }
+
+    #[test]
+    fn recent_trades_cache_can_be_turned_off() {
+        let mut loader = loader(&[("RECENT_TRADES_PER_PAIR", "0")]);
+        assert_eq!(loader.recent_trades_per_pair(), 0);
+        assert!(loader.errors.is_empty());
+    }
+
+    #[test]
+    fn unset_jwt_secret_falls_back_to_the_development_secret_only_in_debug_builds() {
+        let mut debug = loader(&[("JWT_SECRET", "")]);
+        assert_eq!(debug.jwt_secret(false), DEFAULT_JWT_SECRET);
+        assert!(debug.errors.is_empty());
+
+        let mut release = loader(&[]);
+        release.jwt_secret(true);
+        let reported: Vec<String> = release.errors.iter().map(ConfigError::to_string).collect();
+        assert_eq!(reported.len(), 1);
+        assert!(reported[0].starts_with("JWT_SECRET: not set (required in release builds)"), "{}", reported[0]);
+    }
+
+    #[test]
+    fn short_or_unrotated_jwt_secrets_are_reported() {
+        let secret = "s".repeat(MIN_JWT_SECRET_BYTES);
+        let mut loader = loader(&[("JWT_SECRET", "short"), ("JWT_PREVIOUS_SECRET", secret.as_str())]);
+        loader.jwt_secret(true);
+        assert_eq!(loader.jwt_previous_secret(&secret), Some(secret.clone()));
+        let problems: Vec<&str> = loader.errors.iter().map(|e| e.var).collect();
+        assert_eq!(problems, ["JWT_SECRET", "JWT_PREVIOUS_SECRET"]);
+    }
+}
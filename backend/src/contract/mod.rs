@@ -0,0 +1,308 @@
+// Wire contract tests module
+//
+// Golden fixtures in contract/fixtures/ record the REST and WebSocket payload shapes the server
+// emits: field names, JSON types, and field order. A payload satisfies its contract when every
+// required field is present with the fixture's type and the known fields keep the fixture's
+// relative order. Unknown fields are tolerated, since additive changes don't bump the schema
+// version (see models::schema).
+//
+// The local tests check the fixtures against the serializers in this crate. The `#[ignore]`d
+// tests check a running deployment:
+//   CONTRACT_BASE_URL=https://api.example.com cargo test contract -- --ignored
+// CONTRACT_PAIR selects the pair queried (default SOL/USDC).
+
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde_json::Value;
+
+/// Fixture files, by contract name
+const FIXTURES: &[(&str, &str)] = &[
+    ("trade", include_str!("fixtures/trade.json")),
+    ("ohlcv_candle", include_str!("fixtures/ohlcv_candle.json")),
+    ("price_tick", include_str!("fixtures/price_tick.json")),
+    ("midprice_history", include_str!("fixtures/midprice_history.json")),
+    ("midprice_point", include_str!("fixtures/midprice_point.json")),
+];
+
+/// One payload contract loaded from its golden fixture
+#[derive(Debug, serde::Deserialize)]
+pub struct Contract {
+    pub schema_version: u32,
+    /// Fields in wire order
+    pub fields: Vec<String>,
+    /// Fields that may be omitted (skipped when unset)
+    pub optional: Vec<String>,
+    /// Example payload; field types are taken from it
+    pub payload: serde_json::Map<String, Value>,
+}
+
+impl Contract {
+    /// Load a contract by name; panics on unknown names or malformed fixtures
+    pub fn load(name: &str) -> Self {
+        let (_, raw) = FIXTURES
+            .iter()
+            .find(|(fixture, _)| *fixture == name)
+            .unwrap_or_else(|| panic!("no contract fixture named {}", name));
+        serde_json::from_str(raw).unwrap_or_else(|e| panic!("malformed contract fixture {}: {}", name, e))
+    }
+
+    /// Violations of this contract by a payload whose keys arrived in `order`
+    pub fn check(&self, payload: &Value, order: &KeyOrder) -> Vec<String> {
+        let Some(object) = payload.as_object() else {
+            return vec![format!("expected an object, got {}", json_type(payload))];
+        };
+
+        let mut violations = Vec::new();
+        for field in &self.fields {
+            match (object.get(field), self.payload.get(field)) {
+                (None, _) if self.optional.contains(field) => {}
+                (None, _) => violations.push(format!("missing field `{}`", field)),
+                (Some(actual), Some(expected)) if json_type(actual) != json_type(expected) => {
+                    violations.push(format!(
+                        "field `{}` is {}, expected {}",
+                        field,
+                        json_type(actual),
+                        json_type(expected)
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(version) = object.get("schema_version")
+            && version.as_u64() != Some(self.schema_version as u64)
+        {
+            violations.push(format!("schema_version is {}, expected {}", version, self.schema_version));
+        }
+
+        // Known fields must keep their relative order; unknown ones may appear anywhere
+        let known: Vec<&String> = order.0.iter().filter(|key| self.fields.contains(key)).collect();
+        let expected: Vec<&String> = self.fields.iter().filter(|field| order.0.contains(field)).collect();
+        if known != expected {
+            violations.push(format!("field order is {:?}, expected {:?}", known, expected));
+        }
+
+        violations
+    }
+
+    /// Check a raw JSON object against this contract
+    pub fn check_raw(&self, raw: &str) -> Vec<String> {
+        match (serde_json::from_str::<Value>(raw), serde_json::from_str::<KeyOrder>(raw)) {
+            (Ok(payload), Ok(order)) => self.check(&payload, &order),
+            (Err(e), _) | (_, Err(e)) => vec![format!("invalid JSON object: {}", e)],
+        }
+    }
+}
+
+/// Keys of a JSON object in the order they appear on the wire
+/// (serde_json::Value doesn't keep insertion order)
+#[derive(Debug, Default)]
+pub struct KeyOrder(pub Vec<String>);
+
+impl<'de> Deserialize<'de> for KeyOrder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyOrderVisitor;
+
+        impl<'de> Visitor<'de> for KeyOrderVisitor {
+            type Value = KeyOrder;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<KeyOrder, A::Error> {
+                let mut keys = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    map.next_value::<IgnoredAny>()?;
+                    keys.push(key);
+                }
+                Ok(KeyOrder(keys))
+            }
+        }
+
+        deserializer.deserialize_map(KeyOrderVisitor)
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Check every element of a raw JSON array against a contract, prefixing violations with the index
+pub fn check_raw_array(contract: &Contract, raw: &str) -> Vec<String> {
+    let (payloads, orders) = match (serde_json::from_str::<Vec<Value>>(raw), serde_json::from_str::<Vec<KeyOrder>>(raw)) {
+        (Ok(payloads), Ok(orders)) => (payloads, orders),
+        (Err(e), _) | (_, Err(e)) => return vec![format!("invalid JSON array of objects: {}", e)],
+    };
+    payloads
+        .iter()
+        .zip(&orders)
+        .enumerate()
+        .flat_map(|(i, (payload, order))| {
+            contract.check(payload, order).into_iter().map(move |v| format!("[{}] {}", i, v))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::schema::{self, CURRENT_SCHEMA_VERSION};
+    use crate::models::trade::Trade;
+    use serde_json::json;
+
+    fn sample_trade() -> Trade {
+        Trade {
+            id: "sig".to_string(),
+            timestamp: chrono::DateTime::from_timestamp_millis(1_704_067_200_123).unwrap(),
+            base_symbol: "SOL".to_string(),
+            quote_symbol: "USDC".to_string(),
+            base_mint: "So11111111111111111111111111111111111111112".to_string(),
+            quote_mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(),
+            price: 150.25,
+            amount: 12.5,
+            side: "buy".to_string(),
+            total_value: 1878.125,
+            dex_program: "Raydium".to_string(),
+            slot: 281_474_976,
+            sequence: 281_474_976 << 16,
+            trader: Some("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string()),
+            inverted: false,
+            spread_bps: Some(3.2),
+        }
+    }
+
+    /// The wire form of a trade, as built by /api/trades and the trade stream broadcast
+    fn trade_wire(trade: &Trade) -> String {
+        schema::with_schema_version(json!(trade), CURRENT_SCHEMA_VERSION).to_string()
+    }
+
+    #[test]
+    fn fixtures_are_self_consistent() {
+        for (name, raw) in FIXTURES {
+            let contract = Contract::load(name);
+            let fixture: Value = serde_json::from_str(raw).unwrap();
+            let payload_raw = fixture["payload"].to_string();
+            assert_eq!(contract.check_raw(&payload_raw), Vec::<String>::new(), "fixture {}", name);
+            let mut listed = contract.fields.clone();
+            listed.sort();
+            let mut present: Vec<String> = contract.payload.keys().cloned().collect();
+            present.sort();
+            assert_eq!(listed, present, "fixture {} payload must have exactly its listed fields", name);
+        }
+    }
+
+    #[test]
+    fn trade_matches_contract() {
+        let contract = Contract::load("trade");
+        assert_eq!(contract.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(contract.check_raw(&trade_wire(&sample_trade())), Vec::<String>::new());
+
+        // Unset optional fields are skipped, not nulled
+        let mut trade = sample_trade();
+        trade.trader = None;
+        trade.spread_bps = None;
+        assert_eq!(contract.check_raw(&trade_wire(&trade)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn violations_are_reported() {
+        let contract = Contract::load("ohlcv_candle");
+        let violations = contract.check_raw(r#"{"high":1,"close":1,"low":1,"open":"1","time":1,"extra":true}"#);
+        assert_eq!(violations.len(), 3, "{:?}", violations);
+        assert!(violations.iter().any(|v| v.contains("missing field `volume`")));
+        assert!(violations.iter().any(|v| v.contains("field `open` is string")));
+        assert!(violations.iter().any(|v| v.starts_with("field order")));
+    }
+
+    /// Base URL of the deployment under test
+    fn live_base_url() -> String {
+        std::env::var("CONTRACT_BASE_URL")
+            .expect("set CONTRACT_BASE_URL to run contract tests against a deployment")
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    fn live_pair() -> String {
+        std::env::var("CONTRACT_PAIR").unwrap_or_else(|_| "SOL/USDC".to_string())
+    }
+
+    async fn live_get(path: &str, query: &[(&str, &str)]) -> String {
+        let response = reqwest::Client::new()
+            .get(format!("{}{}", live_base_url(), path))
+            .query(query)
+            .send()
+            .await
+            .unwrap_or_else(|e| panic!("GET {} failed: {}", path, e));
+        assert!(response.status().is_success(), "GET {} returned {}", path, response.status());
+        response.text().await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs CONTRACT_BASE_URL"]
+    async fn live_trades() {
+        let raw = live_get("/api/trades", &[("pair", &live_pair()), ("limit", "50")]).await;
+        assert_eq!(check_raw_array(&Contract::load("trade"), &raw), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs CONTRACT_BASE_URL"]
+    async fn live_ohlcv() {
+        let raw = live_get("/api/ohlcv", &[("pair", &live_pair()), ("interval", "1m")]).await;
+        assert_eq!(check_raw_array(&Contract::load("ohlcv_candle"), &raw), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs CONTRACT_BASE_URL"]
+    async fn live_midprice_history() {
+        let raw = live_get("/api/midprice/history", &[("pair", &live_pair())]).await;
+        assert_eq!(Contract::load("midprice_history").check_raw(&raw), Vec::<String>::new());
+
+        #[derive(serde::Deserialize)]
+        struct History {
+            points: Value,
+        }
+        let points = serde_json::from_str::<History>(&raw).unwrap().points.to_string();
+        assert_eq!(check_raw_array(&Contract::load("midprice_point"), &points), Vec::<String>::new());
+    }
+
+    /// Waits for a price tick (broadcast every 5s) and checks it, plus any trades seen meanwhile
+    #[tokio::test]
+    #[ignore = "needs CONTRACT_BASE_URL"]
+    async fn live_ws_messages() {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let ws_url = format!("{}/ws/trades", live_base_url().replacen("http", "ws", 1));
+        let (mut stream, _) = tokio_tungstenite::connect_async(ws_url.as_str())
+            .await
+            .unwrap_or_else(|e| panic!("WS connect to {} failed: {}", ws_url, e));
+
+        let price_tick = Contract::load("price_tick");
+        let trade = Contract::load("trade");
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(30);
+        loop {
+            let message = tokio::time::timeout_at(deadline, stream.next())
+                .await
+                .expect("no price tick within 30s")
+                .expect("WS closed before a price tick arrived")
+                .unwrap();
+            let Message::Text(raw) = message else { continue };
+            let Ok(value) = serde_json::from_str::<Value>(&raw) else { continue };
+            match value.get("side").and_then(Value::as_str) {
+                Some("price") => {
+                    assert_eq!(price_tick.check_raw(&raw), Vec::<String>::new(), "{}", raw);
+                    break;
+                }
+                Some("buy" | "sell") => assert_eq!(trade.check_raw(&raw), Vec::<String>::new(), "{}", raw),
+                _ => {}
+            }
+        }
+    }
+}
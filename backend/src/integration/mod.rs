@@ -0,0 +1,254 @@
+// End-to-end integration tests module
+//
+// Each test starts a throwaway ClickHouse container, boots the full service against it (table
+// creation and migrations included), feeds recorded getTransaction results through the same
+// parse -> trade stream -> writer/broadcast pipeline as live QuickNode data, and checks what the
+// REST and WebSocket APIs return against the wire contracts in contract/fixtures/.
+//
+// Needs a Docker daemon; containers are managed by testcontainers, and the tests only build with
+// the `integration` feature:
+//   cargo test --features integration integration
+// CLICKHOUSE_TEST_IMAGE overrides the image (default clickhouse/clickhouse-server:24.8).
+// The service's settings are passed to it directly rather than through the environment. Solana RPC
+// is pointed at a closed port, and Jupiter at a local stub quoting fixed USD prices.
+
+use crate::contract::{check_raw_array, Contract};
+use crate::services::quicknode_ws::QuickNodeWebSocket;
+use crate::state::AppState;
+use axum::extract::Query;
+use axum::routing::get;
+use axum::{Json, Router};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use testcontainers::core::wait::HttpWaitStrategy;
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
+use tokio::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+const DEFAULT_IMAGE: &str = "clickhouse/clickhouse-server:24.8";
+/// How long the container may take to start and answer /ping
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a fixture trade may take to show up in an API
+const PIPELINE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Recorded getTransaction results, by name
+const FIXTURES: &[(&str, &str)] = &[("raydium_jup_buy", include_str!("fixtures/raydium_jup_buy.json"))];
+
+/// USD prices quoted by the Jupiter stub; raydium_jup_buy fills at 0.005 SOL per JUP, on the oracle
+const ORACLE_USD_PRICES: &[(&str, f64)] = &[
+    ("JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN", 1.0),
+    ("So11111111111111111111111111111111111111112", 200.0),
+];
+
+/// A ClickHouse server in a Docker container, removed when dropped (or, through testcontainers'
+/// watchdog, when the test process is interrupted)
+struct ClickHouseContainer {
+    _container: ContainerAsync<GenericImage>,
+    url: String,
+}
+
+impl ClickHouseContainer {
+    async fn start() -> Self {
+        let image = std::env::var("CLICKHOUSE_TEST_IMAGE").unwrap_or_else(|_| DEFAULT_IMAGE.to_string());
+        let (name, tag) = image.rsplit_once(':').unwrap_or((&image, "latest"));
+        let container = GenericImage::new(name, tag)
+            .with_exposed_port(8123.tcp())
+            .with_wait_for(WaitFor::http(HttpWaitStrategy::new("/ping").with_expected_status_code(200u16)))
+            .with_env_var("CLICKHOUSE_SKIP_USER_SETUP", "1")
+            .with_startup_timeout(STARTUP_TIMEOUT)
+            .start()
+            .await
+            .unwrap_or_else(|e| panic!("failed to start {}: {}", image, e));
+        let port = container.get_host_port_ipv4(8123.tcp()).await.expect("ClickHouse port not mapped");
+        Self { _container: container, url: format!("http://127.0.0.1:{}", port) }
+    }
+}
+
+/// Jupiter's Price API V3 on an ephemeral port, quoting ORACLE_USD_PRICES; returns its base URL.
+/// Other mints are left out of the response, as Jupiter does for unknown ones
+async fn start_jupiter_stub() -> String {
+    async fn prices(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+        let ids = query.get("ids").map(String::as_str).unwrap_or_default();
+        let prices = ids
+            .split(',')
+            .filter_map(|id| ORACLE_USD_PRICES.iter().find(|(mint, _)| *mint == id))
+            .map(|(mint, price)| (mint.to_string(), json!({ "usdPrice": price })))
+            .collect();
+        Json(Value::Object(prices))
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let app = Router::new().route("/price/v3", get(prices));
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+/// The full service on an ephemeral port, backed by its own ClickHouse
+struct Harness {
+    state: Arc<AppState>,
+    base_url: String,
+    _clickhouse: ClickHouseContainer,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        let clickhouse = ClickHouseContainer::start().await;
+        let jupiter = start_jupiter_stub().await;
+
+        let vars = [
+            ("CLICKHOUSE_URL", clickhouse.url.clone()),
+            ("CLICKHOUSE_BATCH_MS", "50".to_string()),
+            // Nothing listens on the discard port; RPC-backed services log their failures and carry on
+            ("QUICKNODE_RPC_URL", "http://127.0.0.1:9".to_string()),
+            ("JUPITER_PRICE_API_URL", format!("{}/price/v3", jupiter)),
+            // No swap routes: quotes fail as they would with Jupiter down
+            ("JUPITER_SWAP_API_URL", format!("{}/v6", jupiter)),
+        ];
+        let vars = vars.into_iter().map(|(name, value)| (name.to_string(), value)).collect();
+        let config = crate::Config::from_vars(vars).unwrap_or_else(|errors| {
+            panic!("Invalid test config: {}", errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))
+        });
+        let state = crate::start_services(config).await.expect("Failed to start services");
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let app = crate::router(state.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+                .await
+                .unwrap()
+        });
+
+        Self { state, base_url, _clickhouse: clickhouse }
+    }
+
+    /// Parse a fixture transaction as the live stream would and feed the trade into the pipeline,
+    /// stamped as landing now; returns the trade's id
+    async fn ingest_fixture(&self, name: &str) -> String {
+        let (_, raw) = FIXTURES
+            .iter()
+            .find(|(fixture, _)| *fixture == name)
+            .unwrap_or_else(|| panic!("no transaction fixture named {}", name));
+        let mut tx_json: Value = serde_json::from_str(raw).unwrap();
+        tx_json["blockTime"] = json!(chrono::Utc::now().timestamp());
+        let signature = tx_json["transaction"]["signatures"][0].as_str().unwrap().to_string();
+        let slot = tx_json["slot"].as_u64().unwrap();
+
+        let trade = QuickNodeWebSocket::trade_from_transaction(
+            &signature,
+            slot,
+            tx_json,
+            &self.state.dex_programs,
+            &self.state.trade_sanity,
+        )
+        .await
+        .unwrap_or_else(|| panic!("fixture {} did not parse into a trade", name));
+        self.state.ingest_tx.send(trade).await.expect("trade stream is not running");
+        signature
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, &str)]) -> String {
+        let response = reqwest::Client::new()
+            .get(format!("{}{}", self.base_url, path))
+            .query(query)
+            .send()
+            .await
+            .unwrap_or_else(|e| panic!("GET {} failed: {}", path, e));
+        assert!(response.status().is_success(), "GET {} returned {}", path, response.status());
+        response.text().await.unwrap()
+    }
+
+    /// GET a JSON array until it's non-empty
+    async fn get_until_nonempty(&self, path: &str, query: &[(&str, &str)]) -> String {
+        let deadline = Instant::now() + PIPELINE_TIMEOUT;
+        loop {
+            let raw = self.get(path, query).await;
+            if serde_json::from_str::<Vec<Value>>(&raw).is_ok_and(|items| !items.is_empty()) {
+                return raw;
+            }
+            assert!(Instant::now() < deadline, "GET {} stayed empty for {:?}", path, PIPELINE_TIMEOUT);
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fixture_trade_is_stored_and_served() {
+    let harness = Harness::start().await;
+    let id = harness.ingest_fixture("raydium_jup_buy").await;
+
+    let raw = harness.get_until_nonempty("/api/trades", &[("pair", "JUP/SOL")]).await;
+    assert_eq!(check_raw_array(&Contract::load("trade"), &raw), Vec::<String>::new());
+    let trades: Vec<Value> = serde_json::from_str(&raw).unwrap();
+    assert_eq!(trades.len(), 1, "{}", raw);
+    let trade = &trades[0];
+    assert_eq!(trade["id"], json!(id));
+    assert_eq!(trade["side"], json!("buy"));
+    assert_eq!(trade["dex_program"], json!("Raydium"));
+    assert_eq!(trade["trader"], json!("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM"));
+    assert_eq!(trade["amount"].as_f64(), Some(1000.0));
+    assert!((trade["price"].as_f64().unwrap() - 0.005).abs() < 1e-12, "{}", raw);
+    // Checked against the stubbed oracle, which quotes the same price
+    assert!(trade["spread_bps"].as_f64().is_some_and(|bps| bps.abs() < 1e-6), "{}", raw);
+
+    let raw = harness.get_until_nonempty("/api/ohlcv", &[("pair", "JUP/SOL"), ("interval", "1m")]).await;
+    assert_eq!(check_raw_array(&Contract::load("ohlcv_candle"), &raw), Vec::<String>::new());
+    let candles: Vec<Value> = serde_json::from_str(&raw).unwrap();
+    assert_eq!(candles.last().unwrap()["volume"].as_f64(), Some(1000.0), "{}", raw);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fixture_trade_is_broadcast() {
+    let harness = Harness::start().await;
+    let ws_url = format!("{}/ws/trades", harness.base_url.replacen("http", "ws", 1));
+    let (mut stream, _) = tokio_tungstenite::connect_async(ws_url.as_str()).await.unwrap();
+
+    stream
+        .send(Message::Text(
+            json!({ "type": "subscribe", "channel": "trades", "pair": "JUP/SOL", "request_id": 1 }).to_string(),
+        ))
+        .await
+        .unwrap();
+    let mut id = None;
+    let contract = Contract::load("trade");
+    let deadline = Instant::now() + PIPELINE_TIMEOUT;
+    loop {
+        let message = tokio::time::timeout_at(deadline, stream.next())
+            .await
+            .expect("fixture trade was not broadcast in time")
+            .expect("WS closed before the trade arrived")
+            .unwrap();
+        let Message::Text(raw) = message else { continue };
+        let value: Value = serde_json::from_str(&raw).unwrap();
+        // Ingest once the subscription is in place, so the broadcast can't be missed
+        if value["type"] == json!("ack") && value["request_id"] == json!(1) {
+            id = Some(harness.ingest_fixture("raydium_jup_buy").await);
+            continue;
+        }
+        if let Some(id) = &id
+            && value["id"] == json!(id)
+        {
+            assert_eq!(contract.check_raw(&raw), Vec::<String>::new(), "{}", raw);
+            assert_eq!(value["base_symbol"], json!("JUP"));
+            assert_eq!(value["quote_symbol"], json!("SOL"));
+            break;
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn nonce_is_consumed_once() {
+    let harness = Harness::start().await;
+    let checks = &harness.state.signature_checks;
+    let expires_at = (chrono::Utc::now().timestamp() + 300) as usize;
+
+    // Two replicas racing for the same nonce: exactly one signs in
+    let (first, second) = tokio::join!(checks.consume("race", expires_at), checks.consume("race", expires_at));
+    assert_eq!([first.unwrap(), second.unwrap()].iter().filter(|won| **won).count(), 1);
+    assert!(!checks.consume("race", expires_at).await.unwrap());
+    assert!(checks.consume("another", expires_at).await.unwrap());
+}
@@ -1,5 +1,6 @@
 // Main application entry point
 
+mod config;
 mod routes;
 mod middleware;
 mod websocket;
@@ -7,60 +8,297 @@ mod services;
 mod models;
 mod utils;
 mod state;
+#[cfg(test)]
+pub mod contract;
+#[cfg(all(test, feature = "integration"))]
+mod integration;
 
+use anyhow::Context;
 use axum::{routing::get, Router};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use websocket::ConnectionManager;
-use services::{TradeStreamService, ClickHouseService};
+use config::Config;
+use services::{SolanaService, JupiterService, TradeStreamService, ClickHouseService, DexProgramRegistry, ExportService, PortfolioService, SubmissionService, SimulationService, SwapService, DigestService, CircuitBreaker, TokenRegistry, HolderStatsService, SlotClockService, MaintenanceService, TradeSanity, DataQuality, TradeAggregator, JobLeases, FeatureFlags, OrderBookService, VenueLatency, StreamHealth, MemoryBudget, NotificationTemplates, ClusterMembership, RecentTrades, WalletFollows};
 use state::AppState;
 use dotenv::dotenv;
 
+/// How long shutdown waits for buffered trades to be stored
+const SHUTDOWN_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
 #[tokio::main]
 async fn main() {
     // Load environment variables
     dotenv().ok();
     
+    // Load and validate the configuration before anything starts; the listener is bound here,
+    // on 0.0.0.0 to allow access from Docker containers
+    let preflight = services::preflight::run().await;
+    preflight.print();
+    let Some((listener, config)) = preflight.into_start() else {
+        std::process::exit(1);
+    };
+    let addr = listener.local_addr().expect("Listener has no local address");
+
+    let app = match start_services(config).await {
+        Ok(app_state) => router(app_state),
+        Err(e) => {
+            eprintln!("❌ Startup failed: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("🚀 Server starting on http://{}", addr);
+    println!("📡 WebSocket endpoint: ws://{}/ws/trades", addr);
+
+    // Peer addresses key the per-IP rate limits
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(utils::shutdown::signal())
+        .await
+        .unwrap();
+    // Store the trade writer's queued trades before exiting
+    utils::shutdown::drain(SHUTDOWN_DRAIN_TIMEOUT).await;
+}
+
+/// Connect to ClickHouse (creating and migrating tables), build the services and spawn their
+/// background tasks. Ingestion starts at once when full, once the RPC answers when degraded, and
+/// never when API-only
+async fn start_services(config: Config) -> anyhow::Result<Arc<AppState>> {
+    let config = Arc::new(config);
+    let mode = config.startup_mode;
+    utils::jwt::set_secrets(&config.jwt_secret, config.jwt_previous_secret.as_deref());
+    middleware::ingest::set_auth(config.ingest.clone());
+    config.auth.log();
+    config.data_plans.log();
+    
+    // JSON-RPC client shared (by clone) by the services that read the chain
+    let solana = SolanaService::new(&config.rpc_url);
+    // Jupiter price and swap APIs, likewise shared by clone
+    let jupiter = JupiterService::new(&config.jupiter_price_api_url, &config.jupiter_swap_api_url);
+    
     // Initialize ClickHouse service
     let clickhouse = Arc::new(
-        ClickHouseService::new().await
-            .expect("Failed to initialize ClickHouse service")
+        ClickHouseService::new(&config.clickhouse).await
+            .context("Failed to initialize ClickHouse service")?
     );
     
     // Initialize WebSocket connection manager
-    let ws_manager = Arc::new(ConnectionManager::new());
+    let ws_manager = Arc::new(ConnectionManager::new(config.heartbeat));
+    
+    // Registry of monitored DEX programs (DEX_PROGRAMS_FILE/DEX_PROGRAMS, changed at runtime via /admin/venues)
+    // With INGEST_SHARD set, this process only subscribes to its share of the programs
+    let dex_programs = Arc::new(DexProgramRegistry::new(config.ingest_shard, config.dex_programs.clone()));
+    
+    // Periodic jobs (session cleanup, optimize, digests) claim a ClickHouse lease per run so one replica runs each
+    let job_leases = Arc::new(JobLeases::new(clickhouse.clone(), config.instance_id.clone()));
+    
+    // Wallet balance cache (invalidated when the trade stream sees a wallet's fill)
+    let portfolio = Arc::new(
+        PortfolioService::new(solana.clone(), jupiter.clone())
+            .context("Failed to initialize portfolio service")?
+    );
+    
+    // Per-pair memory budget and cold-pair eviction shared by the in-memory caches
+    let memory_budget = Arc::new(MemoryBudget::new(config.memory));
+    
+    // Per-pair quarantine when parsed prices diverge from the oracle (cleared via /admin)
+    let circuit_breaker = Arc::new(CircuitBreaker::new(memory_budget.clone(), config.circuit_breaker_deviation_pct));
+    let trade_sanity = Arc::new(TradeSanity::new(memory_budget.clone()));
+    
+    // Rolling 24h stats per pair, fed by the trade stream (ClickHouse only seeds cold pairs)
+    let aggregator = Arc::new(TradeAggregator::new(clickhouse.clone(), memory_budget.clone()));
+    tokio::spawn(aggregator.clone().warm_up());
+    
+    // Newest trades per pair for first-page /api/trades loads, served once the trade stream stores here
+    let recent_trades = Arc::new(RecentTrades::new(clickhouse.clone(), memory_budget.clone(), config.recent_trades_per_pair));
+    
+    // Supported tokens/pairs with on-chain risk signals and first/last trade times
+    let tokens = Arc::new(
+        TokenRegistry::new(clickhouse.clone(), solana.clone(), config.token_registry_file.clone())
+            .context("Failed to initialize token registry")?
+    );
+    tokio::spawn(tokens.clone().start());
+    
+    // Per-venue blockTime→ingest and confirmed→finalized latency of streamed trades
+    let venue_latency = Arc::new(VenueLatency::new(solana.clone()));
+    
+    // QuickNode connection and subscription state for /api/health/stream
+    let stream_health = Arc::new(StreamHealth::new());
     
     // Start trade stream service (fetches from QuickNode/Jupiter and broadcasts)
     let ws_manager_for_stream = ws_manager.clone();
     let clickhouse_for_stream = clickhouse.clone();
-    tokio::spawn(async move {
-        match TradeStreamService::new(ws_manager_for_stream, clickhouse_for_stream).await {
+    let dex_programs_for_stream = dex_programs.clone();
+    let portfolio_for_stream = portfolio.clone();
+    let circuit_breaker_for_stream = circuit_breaker.clone();
+    let tokens_for_stream = tokens.clone();
+    let sanity_for_stream = trade_sanity.clone();
+    let aggregator_for_stream = aggregator.clone();
+    let recent_trades_for_stream = recent_trades.clone();
+    let job_leases_for_stream = job_leases.clone();
+    let latency_for_stream = venue_latency.clone();
+    let health_for_stream = stream_health.clone();
+    // Externally parsed trades (POST /api/ingest/trades) join the stream's pipeline
+    let (ingest_tx, ingest_rx) = tokio::sync::mpsc::channel(services::trade_stream::INGEST_QUEUE_CAPACITY);
+    // Synthetic load (POST /admin/load-test) enters the same way
+    let load_test = Arc::new(services::load_test::LoadTestService::new(ingest_tx.clone()));
+    let load_test_for_stream = load_test.clone();
+    let memory_budget_for_stream = memory_budget.clone();
+    // Alert and digest message text, overridable per channel with NOTIFICATION_TEMPLATES_FILE
+    let notification_templates = Arc::new(NotificationTemplates::load(config.notification_templates_file.as_deref()));
+    let templates_for_stream = notification_templates.clone();
+    // Copy-trading follows; followed wallets' trades are pushed to their followers
+    let follows = Arc::new(WalletFollows::new(clickhouse.clone(), ws_manager.clone(), notification_templates.clone(), config.max_follows_per_wallet, config.follow_webhook_url.clone()));
+    tokio::spawn(follows.clone().start());
+    let follows_for_stream = follows.clone();
+    let solana_for_stream = solana.clone();
+    let jupiter_for_stream = jupiter.clone();
+    let config_for_stream = config.clone();
+    // Live Phoenix order books (accountSubscribe on the market accounts)
+    let orderbooks = Arc::new(OrderBookService::new(ws_manager.clone(), solana.clone(), config.phoenix_markets.clone()));
+    let orderbooks_for_stream = orderbooks.clone();
+    // Ingestion: the trade stream, venue latency polling and order books, once trades has millisecond
+    // timestamps (see migrate_timestamps). Degraded, it waits for
+    // the RPC to answer; API-only, the ingest queue has no consumer, so POST /api/ingest/trades
+    // rejects every trade
+    let ingestion = async move {
+        services::maintenance::migrate_timestamps(&clickhouse_for_stream, &job_leases_for_stream).await;
+        health_for_stream.ingestion_started().await;
+        tokio::spawn(latency_for_stream.clone().start());
+        tokio::spawn(orderbooks_for_stream.start());
+        match TradeStreamService::new(ws_manager_for_stream, clickhouse_for_stream, dex_programs_for_stream, portfolio_for_stream, circuit_breaker_for_stream, tokens_for_stream, sanity_for_stream, aggregator_for_stream, job_leases_for_stream, latency_for_stream, health_for_stream, load_test_for_stream, memory_budget_for_stream, templates_for_stream, recent_trades_for_stream, follows_for_stream, solana_for_stream.clone(), jupiter_for_stream, config_for_stream).await {
             Ok(stream_service) => {
-                stream_service.start().await;
+                stream_service.start(ingest_rx).await;
             }
             Err(e) => {
                 eprintln!("❌ Failed to start trade stream service: {}", e);
                 eprintln!("⚠️  Make sure QUICKNODE_RPC_URL and CLICKHOUSE_URL are set in environment variables");
             }
         }
-    });
+    };
+    match mode {
+        services::preflight::StartupMode::Full => {
+            tokio::spawn(ingestion);
+        }
+        services::preflight::StartupMode::Degraded => {
+            let solana = solana.clone();
+            tokio::spawn(async move {
+                services::preflight::wait_for_rpc(&solana).await;
+                ingestion.await;
+            });
+        }
+        services::preflight::StartupMode::ApiOnly => {}
+    }
+
+    // Per-pair data-quality scores (rejections, oracle deviation, gaps), refreshed every minute
+    let data_quality = Arc::new(DataQuality::new(clickhouse.clone(), trade_sanity.clone()));
+    tokio::spawn(data_quality.clone().start());
+
+    // Market overview for the `markets` WS channel
+    tokio::spawn(services::markets::start(aggregator.clone(), ws_manager.clone()));
+    
+    // Per-pair WebSocket subscriber samples for /admin/ws-usage
+    tokio::spawn(services::ws_usage::start(clickhouse.clone(), ws_manager.clone()));
+    
+    // Daily per-wallet digests (portfolio change + watched pairs)
+    let digests = Arc::new(DigestService::new(clickhouse.clone(), portfolio.clone(), ws_manager.clone(), job_leases.clone(), notification_templates.clone(), config.digest_hour_utc, config.digest_webhook_url.clone()));
+    tokio::spawn(digests.start());
+
+    // Heartbeats for /api/cluster: this replica's roles, version and health
+    let cluster = Arc::new(ClusterMembership::new(
+        clickhouse.clone(),
+        job_leases.clone(),
+        dex_programs.clone(),
+        stream_health.clone(),
+        ws_manager.clone(),
+        config.ingest_forward_url.is_some(),
+    ));
+    tokio::spawn(cluster.clone().start());
+
+    // Part-count checks and OPTIMIZE during the low-traffic window
+    let maintenance = Arc::new(MaintenanceService::new(clickhouse.clone(), job_leases.clone(), config.maintenance));
+    tokio::spawn(maintenance.clone().start());
+
+    // Endpoints that can be switched off at runtime (DISABLED_FEATURES, /admin/features)
+    let features = Arc::new(FeatureFlags::new(&config.disabled_features));
 
     // Shared state for routes
-    let app_state = Arc::new(AppState {
+    Ok(Arc::new(AppState {
         clickhouse: clickhouse.clone(),
-    });
+        dex_programs: dex_programs.clone(),
+        ws_manager: ws_manager.clone(),
+        exports: Arc::new(ExportService::new(clickhouse.clone(), ws_manager.clone(), config.export_dir.clone())),
+        portfolio: portfolio.clone(),
+        submissions: Arc::new(SubmissionService::new(solana.clone(), ws_manager.clone())),
+        simulations: Arc::new(SimulationService::new(solana.clone())),
+        swaps: Arc::new(
+            SwapService::new(clickhouse.clone(), jupiter, config.slippage.clone(), config.fees.clone())
+                .context("Failed to initialize swap service")?
+        ),
+        circuit_breaker: circuit_breaker.clone(),
+        tokens: tokens.clone(),
+        holders: Arc::new(HolderStatsService::new(solana.clone())),
+        slot_clock: Arc::new(SlotClockService::new(solana)),
+        query_limits: config.query_limits,
+        auth: config.auth,
+        revocations: Arc::new(services::revocations::TokenRevocations::new()),
+        signature_checks: Arc::new(services::signature_checks::SignatureChecks::new(clickhouse.clone())),
+        maintenance,
+        trade_sanity,
+        data_quality,
+        aggregator,
+        recent_trades,
+        response_cache: Arc::new(services::response_cache::ResponseCache::new(config.response_cache_ttl)),
+        job_leases,
+        data_plans: Arc::new(config.data_plans.clone()),
+        message_signing: Arc::new(services::message_signing::MessageSigning::new(config.ws_signing_key.clone(), config.ws_hmac_secrets.clone())),
+        features: features.clone(),
+        orderbooks,
+        venue_latency,
+        stream_health,
+        load_test,
+        memory_budget,
+        notification_templates,
+        follows,
+        cluster,
+        ingest_tx,
+        config,
+    }))
+}
 
-    let app = Router::new()
-        .nest("/auth", routes::auth::routes().with_state(app_state.clone()))
-        .nest("/api", routes::trades::routes().with_state(app_state.clone()))
-        .route("/ws/trades", get(websocket::websocket_handler).with_state(ws_manager.clone()))
-        .layer(middleware::create_cors_layer());
+/// All routes over the shared state
+fn router(app_state: Arc<AppState>) -> Router {
+    let features = app_state.features.clone();
+    let feature_gate = |feature: &'static str| {
+        axum::middleware::from_fn_with_state((features.clone(), feature), middleware::require_feature)
+    };
 
-    // Bind to 0.0.0.0 to allow access from Docker containers
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));  
-    println!("🚀 Server starting on http://{}", addr);
-    println!("📡 WebSocket endpoint: ws://{}/ws/trades", addr);
-
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    Router::new()
+        .nest("/auth", routes::auth::routes().with_state(app_state.clone()))
+        .nest("/api", routes::trades::routes()
+            .merge(routes::bootstrap::routes())
+            .merge(routes::exports::routes().route_layer(feature_gate("exports")))
+            .merge(routes::wallet::routes())
+            .merge(routes::transactions::routes())
+            .merge(routes::swap::routes())
+            .merge(routes::tokens::routes())
+            .merge(routes::slots::routes())
+            .merge(routes::backtest::routes().route_layer(feature_gate("backtest")))
+            .merge(routes::snapshots::routes().route_layer(feature_gate("snapshots")))
+            .merge(routes::orderbook::routes())
+            .merge(routes::alerts::routes())
+            .merge(routes::whales::routes())
+            .merge(routes::stream::routes())
+            .merge(routes::venues::routes())
+            .merge(routes::analytics::routes())
+            .merge(routes::cluster::routes())
+            .merge(routes::version::routes())
+            .merge(routes::ingest::routes(app_state.clone()))
+            .with_state(app_state.clone()))
+        .nest("/admin", routes::admin::routes()
+            .with_state(app_state.clone())
+            .layer(axum::middleware::from_fn_with_state(app_state.clone(), middleware::require_admin)))
+        .route("/ws/trades", get(websocket::websocket_handler).with_state(app_state.clone()))
+        .layer(middleware::RateLimitLayer::new(Arc::new(middleware::RateLimiter::new(&app_state.config.rate_limits))))
+        .layer(middleware::create_cors_layer())
 }
\ No newline at end of file
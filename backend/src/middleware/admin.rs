@@ -0,0 +1,48 @@
+// Admin authentication middleware module
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+use crate::state::AppState;
+
+/// Require a matching `x-admin-key` header (ADMIN_API_KEY, see Config) for admin routes
+/// Admin routes are disabled entirely when ADMIN_API_KEY is not set
+pub async fn require_admin(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let admin_key = match state.config.admin_api_key.as_deref() {
+        Some(key) => key,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({
+                    "error": "Admin API disabled",
+                    "message": "Set ADMIN_API_KEY to enable admin endpoints"
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    let provided = request
+        .headers()
+        .get("x-admin-key")
+        .and_then(|v| v.to_str().ok());
+
+    if provided != Some(admin_key) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "message": "Missing or invalid x-admin-key header"
+            })),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
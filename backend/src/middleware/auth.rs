@@ -1,3 +1,55 @@
 // Authentication middleware module
 
-// JWT authentication middleware will be implemented here
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+use crate::state::AppState;
+use crate::utils::jwt;
+
+/// Authenticated wallet extracted from an `Authorization: Bearer <jwt>` header
+/// The token must verify, not be revoked, and its session must still exist in ClickHouse
+pub struct AuthUser {
+    pub wallet: String,
+    pub token: String,
+    pub claims: jwt::Claims,
+}
+
+fn auth_error(status: StatusCode, error: &str, message: &str) -> Response {
+    (status, Json(json!({ "error": error, "message": message }))).into_response()
+}
+
+impl FromRequestParts<Arc<AppState>> for AuthUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| auth_error(StatusCode::UNAUTHORIZED, "Unauthorized", "Missing bearer token"))?;
+
+        let claims = jwt::verify_token(token)
+            .map_err(|e| auth_error(StatusCode::UNAUTHORIZED, "Unauthorized", &format!("Invalid token: {}", e)))?;
+        if claims.scope.is_some() {
+            return Err(auth_error(StatusCode::UNAUTHORIZED, "Unauthorized", "Not a session token"));
+        }
+        if state.revocations.is_revoked(token).await {
+            return Err(auth_error(StatusCode::UNAUTHORIZED, "Unauthorized", "Session expired or revoked"));
+        }
+
+        match state.clickhouse.validate_session(&claims.sub, token).await {
+            Ok(true) => Ok(AuthUser { wallet: claims.sub.clone(), token: token.to_string(), claims }),
+            Ok(false) => Err(auth_error(StatusCode::UNAUTHORIZED, "Unauthorized", "Session expired or revoked")),
+            Err(e) => {
+                eprintln!("❌ Session validation failed: {}", e);
+                Err(auth_error(StatusCode::SERVICE_UNAVAILABLE, "Session check unavailable", "Failed to validate session"))
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Feature flag middleware module
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::services::feature_flags::{FeatureFlags, FeatureOff};
+
+/// Response for a request to a switched-off feature: 404 when this deployment doesn't offer it,
+/// 503 while an admin has it paused
+pub fn feature_off_response(feature: &str, off: FeatureOff) -> Response {
+    match off {
+        FeatureOff::NotOffered => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "Feature disabled",
+                "feature": feature,
+                "message": format!("{} is not enabled on this deployment", feature)
+            })),
+        )
+            .into_response(),
+        FeatureOff::Paused(reason) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "error": "Feature paused",
+                "feature": feature,
+                "message": reason.unwrap_or_else(|| format!("{} is temporarily disabled", feature))
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// Gate a route group on a feature flag
+/// Usage: `.route_layer(from_fn_with_state((flags, "backtest"), require_feature))`
+pub async fn require_feature(
+    State((flags, feature)): State<(Arc<FeatureFlags>, &'static str)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match flags.check(feature).await {
+        Ok(()) => next.run(request).await,
+        Err(off) => feature_off_response(feature, off),
+    }
+}
@@ -0,0 +1,269 @@
+// Signed ingest middleware module
+//
+// Inbound ingest requests (bulk trade ingest, provider webhooks) are authenticated per source:
+//   x-ingest-source:    source name, selecting its secret from INGEST_SECRETS
+//   x-ingest-timestamp: unix seconds when the request was signed
+//   x-ingest-signature: hex HMAC-SHA256 of "{timestamp}.{raw body}" with the source's secret
+// Requests outside the allowed clock skew are rejected, and each signature is accepted only
+// once within that window, on any replica (replay protection, see SignatureChecks).
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Largest ingest body buffered for verification
+const MAX_INGEST_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Used when the settings were never set (INGEST_MAX_SKEW_SECS unset)
+pub const DEFAULT_MAX_SKEW_SECS: i64 = 300;
+
+/// Ingest authentication settings, read with the rest of the Config and set at startup
+#[derive(Debug, Clone, Default)]
+pub struct IngestAuth {
+    /// Per-source secrets from INGEST_SECRETS ("source:secret,source:secret")
+    pub secrets: HashMap<String, String>,
+    /// Allowed difference between the signed timestamp and server time; INGEST_MAX_SKEW_SECS, default 300
+    pub max_skew_secs: i64,
+}
+
+impl IngestAuth {
+    /// INGEST_SECRETS entries; an error names the first malformed one
+    pub fn parse_secrets(raw: &str) -> Result<HashMap<String, String>, String> {
+        raw.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once(':') {
+                Some((source, secret)) if !source.is_empty() && !secret.is_empty() => Ok((source.to_string(), secret.to_string())),
+                _ => Err(format!("'{}' is not source:secret", entry)),
+            })
+            .collect()
+    }
+}
+
+/// Where accepted signatures are recorded, so each is used once (SignatureChecks, shared by replicas)
+pub trait SignatureLedger: Send + Sync {
+    /// Record `signature` until `forget_at` (unix seconds); false if it was already recorded
+    fn consume<'a>(&'a self, signature: &'a str, forget_at: i64) -> BoxFuture<'a, anyhow::Result<bool>>;
+}
+
+static AUTH: OnceLock<IngestAuth> = OnceLock::new();
+
+/// Set the ingest secrets and skew; only the first call takes effect
+pub fn set_auth(auth: IngestAuth) {
+    let _ = AUTH.set(auth);
+}
+
+/// The secret of an ingest source, if INGEST_SECRETS configures one
+pub fn source_secret(source: &str) -> Option<String> {
+    AUTH.get()?.secrets.get(source).cloned()
+}
+
+fn max_skew_secs() -> i64 {
+    AUTH.get().map_or(DEFAULT_MAX_SKEW_SECS, |auth| auth.max_skew_secs)
+}
+
+fn reject(status: StatusCode, error: &str, message: &str) -> Response {
+    (status, Json(json!({ "error": error, "message": message }))).into_response()
+}
+
+/// Verify an ingest request signed by one of `allowed_sources`, consuming its signature in `ledger`
+/// Each endpoint wraps this in a small middleware fn naming the sources it accepts
+pub async fn verify_ingest_signature(
+    allowed_sources: &'static [&'static str],
+    ledger: &dyn SignatureLedger,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+    let (Some(source), Some(timestamp), Some(signature)) = (
+        header("x-ingest-source"),
+        header("x-ingest-timestamp"),
+        header("x-ingest-signature"),
+    ) else {
+        return reject(StatusCode::UNAUTHORIZED, "Unauthorized", "Missing x-ingest-source, x-ingest-timestamp, or x-ingest-signature header");
+    };
+
+    if !allowed_sources.contains(&source.as_str()) {
+        return reject(StatusCode::FORBIDDEN, "Forbidden", &format!("Source '{}' may not use this endpoint", source));
+    }
+    let Some(secret) = source_secret(&source) else {
+        return reject(StatusCode::SERVICE_UNAVAILABLE, "Ingest source disabled", &format!("No secret configured for source '{}' in INGEST_SECRETS", source));
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let skew = max_skew_secs();
+    let Ok(signed_at) = timestamp.parse::<i64>() else {
+        return reject(StatusCode::UNAUTHORIZED, "Unauthorized", "x-ingest-timestamp must be unix seconds");
+    };
+    if (now - signed_at).abs() > skew {
+        return reject(StatusCode::UNAUTHORIZED, "Unauthorized", &format!("Timestamp is more than {}s from server time", skew));
+    }
+
+    // Buffer the body to verify it, then hand it on unchanged
+    let (parts, body) = request.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_INGEST_BODY_BYTES).await else {
+        return reject(StatusCode::PAYLOAD_TOO_LARGE, "Body too large", "Ingest body exceeds 2 MiB");
+    };
+
+    let Ok(expected) = hex::decode(&signature) else {
+        return reject(StatusCode::UNAUTHORIZED, "Unauthorized", "x-ingest-signature must be hex");
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return reject(StatusCode::INTERNAL_SERVER_ERROR, "Internal error", "Invalid ingest secret");
+    };
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(&bytes);
+    if mac.verify_slice(&expected).is_err() {
+        return reject(StatusCode::UNAUTHORIZED, "Unauthorized", "Invalid signature");
+    }
+
+    // Replay protection: a signature is only valid once while its timestamp is within the window
+    match ledger.consume(&signature, signed_at + skew).await {
+        Ok(true) => {}
+        Ok(false) => return reject(StatusCode::CONFLICT, "Replayed request", "This signature has already been used"),
+        Err(e) => {
+            eprintln!("❌ [Ingest] Failed to record signature from {}: {}", source, e);
+            return reject(StatusCode::SERVICE_UNAVAILABLE, "Replay check unavailable", "Could not record the request signature; try again");
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Bytes;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::Router;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    const SECRET: &str = "bulk-secret";
+    const BODY: &str = r#"{"trades":[]}"#;
+
+    /// One process's view of the ledger SignatureChecks keeps in ClickHouse
+    #[derive(Default)]
+    struct MemoryLedger(Mutex<HashSet<String>>);
+
+    impl SignatureLedger for MemoryLedger {
+        fn consume<'a>(&'a self, signature: &'a str, _forget_at: i64) -> BoxFuture<'a, anyhow::Result<bool>> {
+            let fresh = self.0.lock().unwrap().insert(signature.to_lowercase());
+            Box::pin(async move { Ok(fresh) })
+        }
+    }
+
+    async fn verify_bulk(State(ledger): State<Arc<MemoryLedger>>, request: Request, next: Next) -> Response {
+        verify_ingest_signature(&["bulk"], ledger.as_ref(), request, next).await
+    }
+
+    /// An endpoint accepting the `bulk` source that echoes the body it was handed
+    async fn serve() -> String {
+        set_auth(IngestAuth {
+            secrets: HashMap::from([("bulk".to_string(), SECRET.to_string()), ("other".to_string(), "other-secret".to_string())]),
+            max_skew_secs: DEFAULT_MAX_SKEW_SECS,
+        });
+        let app = Router::new()
+            .route("/ingest", post(|body: Bytes| async move { body }))
+            .route_layer(axum::middleware::from_fn_with_state(Arc::new(MemoryLedger::default()), verify_bulk));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/ingest", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{}.{}", timestamp, body).as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    async fn post_signed(url: &str, source: &str, timestamp: i64, signature: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(url)
+            .header("x-ingest-source", source)
+            .header("x-ingest-timestamp", timestamp.to_string())
+            .header("x-ingest-signature", signature)
+            .body(BODY)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn signed_request_reaches_the_handler_with_its_body_unchanged() {
+        let url = serve().await;
+        let now = chrono::Utc::now().timestamp();
+        let response = post_signed(&url, "bulk", now, &sign(SECRET, now, BODY)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), BODY);
+    }
+
+    #[tokio::test]
+    async fn bad_signature_is_rejected() {
+        let url = serve().await;
+        let now = chrono::Utc::now().timestamp();
+        let response = post_signed(&url, "bulk", now, &sign("wrong-secret", now, BODY)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = post_signed(&url, "bulk", now, "not hex").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn missing_header_is_rejected() {
+        let url = serve().await;
+        let now = chrono::Utc::now().timestamp();
+        let response = reqwest::Client::new()
+            .post(&url)
+            .header("x-ingest-source", "bulk")
+            .header("x-ingest-timestamp", now.to_string())
+            .body(BODY)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn timestamps_outside_the_skew_are_rejected() {
+        let url = serve().await;
+        let now = chrono::Utc::now().timestamp();
+        for signed_at in [now - DEFAULT_MAX_SKEW_SECS - 10, now + DEFAULT_MAX_SKEW_SECS + 10] {
+            let response = post_signed(&url, "bulk", signed_at, &sign(SECRET, signed_at, BODY)).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn source_not_allowed_on_the_endpoint_is_forbidden() {
+        let url = serve().await;
+        let now = chrono::Utc::now().timestamp();
+        let response = post_signed(&url, "other", now, &sign("other-secret", now, BODY)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn replayed_signature_is_a_conflict() {
+        let url = serve().await;
+        let now = chrono::Utc::now().timestamp();
+        let signature = sign(SECRET, now, BODY);
+        assert_eq!(post_signed(&url, "bulk", now, &signature).await.status(), StatusCode::OK);
+        assert_eq!(post_signed(&url, "bulk", now, &signature).await.status(), StatusCode::CONFLICT);
+        // Case doesn't make a hex signature new
+        assert_eq!(post_signed(&url, "bulk", now, &signature.to_uppercase()).await.status(), StatusCode::CONFLICT);
+    }
+}
@@ -2,6 +2,14 @@
 
 pub mod cors;
 pub mod auth;
+pub mod admin;
+pub mod ingest;
+pub mod features;
+pub mod rate_limit;
 
 pub use cors::create_cors_layer;
+pub use admin::require_admin;
+pub use ingest::verify_ingest_signature;
+pub use features::require_feature;
+pub use rate_limit::{RateLimitLayer, RateLimiter};
 
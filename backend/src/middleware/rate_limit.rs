@@ -0,0 +1,377 @@
+// Rate limiting middleware module
+//
+// Token buckets per client for the endpoints that polling hits hardest (/api/trades, /api/ohlcv,
+// /auth/nonce and /auth/verify), so one client can't exhaust ClickHouse for everyone. Requests carrying a valid
+// session token are limited per wallet pubkey and also counted against their IP, at the wallet limit, so
+// one address can't multiply its allowance by rotating tokens; everything else per IP. Limits are requests per
+// minute, per endpoint group: RATE_LIMIT_TRADES, RATE_LIMIT_OHLCV, RATE_LIMIT_NONCE and RATE_LIMIT_VERIFY take
+// `ip=120,wallet=600` (0 = unlimited). Behind reverse proxies, RATE_LIMIT_TRUST_FORWARDED keys on the
+// X-Forwarded-For address appended by the outermost trusted proxy instead of the peer address: `true`
+// for one proxy, or the number of proxies in front. Entries left of it are client-supplied and ignored.
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{HeaderMap, HeaderValue, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+use crate::utils::jwt;
+
+/// Requests per minute allowed to one IP and to one signed-in wallet; 0 = unlimited
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Limits {
+    ip: u32,
+    wallet: u32,
+}
+
+impl Limits {
+    /// `ip=120,wallet=600`; a missing key keeps its default
+    pub(crate) fn parse(raw: &str, defaults: Limits) -> Result<Self, String> {
+        let mut limits = defaults;
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, value) = entry.split_once('=').ok_or_else(|| format!("expected key=value, got '{}'", entry))?;
+            let value: u32 = value.trim().parse().map_err(|_| format!("{} must be a whole number, got '{}'", key, value))?;
+            match key.trim() {
+                "ip" => limits.ip = value,
+                "wallet" => limits.wallet = value,
+                other => return Err(format!("unknown limit '{}'; expected ip or wallet", other)),
+            }
+        }
+        Ok(limits)
+    }
+}
+
+/// Endpoint groups: name, env var, path prefixes and default limits
+pub(crate) const GROUPS: &[(&str, &str, &[&str], Limits)] = &[
+    ("trades", "RATE_LIMIT_TRADES", &["/api/trades"], Limits { ip: 120, wallet: 600 }),
+    ("ohlcv", "RATE_LIMIT_OHLCV", &["/api/ohlcv"], Limits { ip: 120, wallet: 600 }),
+    ("nonce", "RATE_LIMIT_NONCE", &["/auth/nonce"], Limits { ip: 20, wallet: 20 }),
+    ("verify", "RATE_LIMIT_VERIFY", &["/auth/verify"], Limits { ip: 20, wallet: 20 }),
+];
+
+/// Per-group limits and the trusted proxy count, read with the rest of the Config
+#[derive(Debug, Clone)]
+pub struct RateLimits {
+    /// One entry per GROUPS group, in the same order
+    pub(crate) groups: Vec<Limits>,
+    /// RATE_LIMIT_TRUST_FORWARDED; 0 keys on the peer address
+    pub trusted_proxies: usize,
+}
+
+/// How often buckets that have refilled completely are forgotten
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Group {
+    name: &'static str,
+    paths: &'static [&'static str],
+    limits: Limits,
+}
+
+impl Group {
+    fn matches(&self, path: &str) -> bool {
+        self.paths
+            .iter()
+            .any(|prefix| path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/')))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    Wallet(String),
+    Ip(IpAddr),
+    /// No peer address (served without connect info); all such requests share a bucket
+    Unknown,
+}
+
+/// A full bucket holds a minute's worth of requests and refills continuously
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+enum Decision {
+    Unlimited,
+    Allowed { limit: u32, remaining: u32 },
+    Limited { group: &'static str, limit: u32, retry_after_secs: u64 },
+}
+
+pub struct RateLimiter {
+    groups: Vec<Group>,
+    /// Reverse proxies in front that append to X-Forwarded-For; 0 keys on the peer address
+    trusted_proxies: usize,
+    buckets: Mutex<HashMap<(&'static str, Client), Bucket>>,
+    last_prune: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: &RateLimits) -> Self {
+        let groups = GROUPS
+            .iter()
+            .zip(&settings.groups)
+            .map(|((name, _, paths, _), limits)| {
+                println!("🚦 [RateLimit] {}: {}/min per IP, {}/min per wallet (0 = unlimited)", name, limits.ip, limits.wallet);
+                Group { name, paths, limits: *limits }
+            })
+            .collect();
+        let trusted_proxies = settings.trusted_proxies;
+
+        Self {
+            groups,
+            trusted_proxies,
+            buckets: Mutex::new(HashMap::new()),
+            last_prune: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// The buckets a request is charged to, with their limits: the signed-in wallet and its IP when
+    /// it carries a valid session token (the IP at the higher of the two limits), otherwise its IP
+    fn charged_buckets(&self, request: &Request<Body>, limits: Limits) -> Vec<(Client, u32)> {
+        let ip = self.client_ip(request);
+        let wallet = request
+            .headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .and_then(|token| jwt::verify_token(token).ok())
+            .filter(|claims| claims.scope.is_none());
+        match wallet {
+            Some(claims) => vec![(Client::Wallet(claims.sub), limits.wallet), (ip, limits.ip.max(limits.wallet))],
+            None => vec![(ip, limits.ip)],
+        }
+    }
+
+    /// The request's IP address
+    fn client_ip(&self, request: &Request<Body>) -> Client {
+        // Each proxy appends the address it was reached from, so the client is the entry the outermost
+        // trusted proxy added, counting from the right; anything further left the client could have sent
+        let forwarded = (self.trusted_proxies > 0)
+            .then(|| request.headers().get("x-forwarded-for"))
+            .flatten()
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').nth(self.trusted_proxies - 1))
+            .and_then(|ip| ip.trim().parse().ok());
+        let peer = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip());
+        forwarded.or(peer).map_or(Client::Unknown, Client::Ip)
+    }
+
+    fn check(&self, request: &Request<Body>) -> Decision {
+        let Some(group) = self.groups.iter().find(|g| g.matches(request.uri().path())) else {
+            return Decision::Unlimited;
+        };
+        let charged: Vec<(Client, u32)> =
+            self.charged_buckets(request, group.limits).into_iter().filter(|(_, limit)| *limit > 0).collect();
+        if charged.is_empty() {
+            return Decision::Unlimited;
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        self.prune(&mut buckets, now);
+
+        // Refill every bucket first; the request goes through only if all of them have a token
+        let mut tightest: Option<(u32, f64)> = None;
+        for (client, limit) in &charged {
+            let capacity = *limit as f64;
+            let bucket = buckets.entry((group.name, client.clone())).or_insert(Bucket { tokens: capacity, updated: now });
+            bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * capacity / 60.0).min(capacity);
+            bucket.updated = now;
+            if bucket.tokens < 1.0 {
+                let retry_after_secs = ((1.0 - bucket.tokens) * 60.0 / capacity).ceil() as u64;
+                return Decision::Limited { group: group.name, limit: *limit, retry_after_secs: retry_after_secs.max(1) };
+            }
+            if tightest.is_none_or(|(_, tokens)| bucket.tokens < tokens) {
+                tightest = Some((*limit, bucket.tokens));
+            }
+        }
+        for (client, _) in charged {
+            if let Some(bucket) = buckets.get_mut(&(group.name, client)) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        let (limit, tokens) = tightest.unwrap_or_default();
+        Decision::Allowed { limit, remaining: (tokens - 1.0) as u32 }
+    }
+
+    /// Forget buckets that have refilled completely; they're no different from a new one
+    fn prune(&self, buckets: &mut HashMap<(&'static str, Client), Bucket>, now: Instant) {
+        let mut last_prune = self.last_prune.lock().unwrap_or_else(|e| e.into_inner());
+        if now.duration_since(*last_prune) < PRUNE_INTERVAL {
+            return;
+        }
+        *last_prune = now;
+        // A bucket refills in a minute at most, so one untouched that long is full
+        buckets.retain(|_, bucket| now.duration_since(bucket.updated) < Duration::from_secs(60));
+    }
+}
+
+fn set_limit_headers(headers: &mut HeaderMap, limit: u32, remaining: u32) {
+    headers.insert("x-ratelimit-limit", HeaderValue::from(limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+}
+
+fn limited_response(group: &str, limit: u32, retry_after_secs: u64) -> Response {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(json!({
+            "error": "Rate limited",
+            "message": format!(
+                "Too many {} requests (limit {} per minute); retry in {}s",
+                group, limit, retry_after_secs
+            ),
+        })),
+    )
+        .into_response();
+    set_limit_headers(response.headers_mut(), limit, 0);
+    response.headers_mut().insert("retry-after", HeaderValue::from(retry_after_secs));
+    response
+}
+
+/// Rate limits the configured endpoint groups; other requests pass straight through
+/// Usage: `.layer(RateLimitLayer::new(Arc::new(RateLimiter::new(&config.rate_limits))))`
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit { inner, limiter: self.limiter.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        match self.limiter.check(&request) {
+            Decision::Unlimited => Box::pin(self.inner.call(request)),
+            Decision::Allowed { limit, remaining } => {
+                let response = self.inner.call(request);
+                Box::pin(async move {
+                    let mut response = response.await?;
+                    set_limit_headers(response.headers_mut(), limit, remaining);
+                    Ok(response)
+                })
+            }
+            Decision::Limited { group, limit, retry_after_secs } => {
+                Box::pin(async move { Ok(limited_response(group, limit, retry_after_secs)) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(&RateLimits { groups: GROUPS.iter().map(|(.., limits)| *limits).collect(), trusted_proxies: 0 })
+    }
+
+    fn request(path: &str, ip: [u8; 4]) -> Request<Body> {
+        let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from((ip, 40000))));
+        request
+    }
+
+    #[test]
+    fn verify_attempts_are_limited_per_ip() {
+        let limiter = limiter();
+        for _ in 0..20 {
+            assert!(matches!(limiter.check(&request("/auth/verify", [10, 0, 0, 1])), Decision::Allowed { .. }));
+        }
+        assert!(matches!(
+            limiter.check(&request("/auth/verify", [10, 0, 0, 1])),
+            Decision::Limited { group: "verify", .. }
+        ));
+        // Another IP has its own bucket
+        assert!(matches!(limiter.check(&request("/auth/verify", [10, 0, 0, 2])), Decision::Allowed { .. }));
+    }
+
+    fn signed_in(path: &str, ip: [u8; 4], wallet: &str) -> Request<Body> {
+        let (token, _) = jwt::generate_token(wallet, chrono::Utc::now() + chrono::Duration::hours(1), chrono::Utc::now()).unwrap();
+        let mut request = request(path, ip);
+        request.headers_mut().insert("authorization", format!("Bearer {}", token).parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn anonymous_requests_are_charged_to_their_ip() {
+        let limiter = limiter();
+        let limits = Limits { ip: 120, wallet: 600 };
+        assert_eq!(limiter.charged_buckets(&request("/api/trades", [10, 0, 0, 1]), limits), [(Client::Ip([10, 0, 0, 1].into()), 120)]);
+        let unknown = Request::builder().uri("/api/trades").body(Body::empty()).unwrap();
+        assert_eq!(limiter.charged_buckets(&unknown, limits), [(Client::Unknown, 120)]);
+    }
+
+    #[test]
+    fn signed_in_requests_are_charged_to_the_wallet_and_the_ip() {
+        let limiter = limiter();
+        let limits = Limits { ip: 120, wallet: 600 };
+        assert_eq!(
+            limiter.charged_buckets(&signed_in("/api/trades", [10, 0, 0, 1], "wallet1"), limits),
+            [(Client::Wallet("wallet1".to_string()), 600), (Client::Ip([10, 0, 0, 1].into()), 600)]
+        );
+        // A token that doesn't verify counts as anonymous
+        let mut forged = request("/api/trades", [10, 0, 0, 1]);
+        forged.headers_mut().insert("authorization", "Bearer not-a-token".parse().unwrap());
+        assert_eq!(limiter.charged_buckets(&forged, limits), [(Client::Ip([10, 0, 0, 1].into()), 120)]);
+    }
+
+    #[test]
+    fn rotating_wallets_from_one_ip_share_its_bucket() {
+        let limiter = limiter();
+        // The nonce group allows 20 per wallet, so 20 fresh wallets exhaust the IP
+        for n in 0..20 {
+            let request = signed_in("/auth/nonce", [10, 0, 0, 3], &format!("wallet{}", n));
+            assert!(matches!(limiter.check(&request), Decision::Allowed { .. }));
+        }
+        assert!(matches!(
+            limiter.check(&signed_in("/auth/nonce", [10, 0, 0, 3], "wallet20")),
+            Decision::Limited { group: "nonce", .. }
+        ));
+        assert!(matches!(limiter.check(&signed_in("/auth/nonce", [10, 0, 0, 4], "wallet20")), Decision::Allowed { .. }));
+    }
+
+    #[test]
+    fn forwarded_address_is_taken_from_the_outermost_trusted_proxy() {
+        let limiter = RateLimiter::new(&RateLimits { groups: GROUPS.iter().map(|(.., limits)| *limits).collect(), trusted_proxies: 1 });
+        let mut forwarded = request("/api/trades", [10, 0, 0, 1]);
+        forwarded.headers_mut().insert("x-forwarded-for", "1.2.3.4, 5.6.7.8".parse().unwrap());
+        assert_eq!(limiter.client_ip(&forwarded), Client::Ip([5, 6, 7, 8].into()));
+    }
+}
@@ -15,5 +15,11 @@ pub struct VerifyRequest {
 pub struct VerifyResponse {
     pub token: String,
     pub expires_at: String,
+    /// Seconds until the token expires
+    pub expires_in: i64,
+    /// Full lifetime of the token in seconds, as configured
+    pub lifetime: i64,
+    /// Last moment /auth/refresh will issue a new token for this sign-in
+    pub refresh_until: String,
 }
 
@@ -2,4 +2,5 @@
 
 pub mod trade;
 pub mod auth;
+pub mod schema;
 
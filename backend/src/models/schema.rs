@@ -0,0 +1,96 @@
+// Wire schema versioning module
+//
+// Every trade/price payload sent over REST or WebSocket carries a `schema_version` field.
+//
+// Deprecation policy: when the Trade shape changes incompatibly, CURRENT_SCHEMA_VERSION is
+// bumped and the previous version is added to DEPRECATED_SCHEMA_VERSIONS with a sunset date.
+// Until the sunset the server dual-emits: clients that pin the old version (`schema_version`
+// query param on REST requests or on the /ws/trades URL) keep receiving the old shape, produced
+// by `downgrade`, and are warned via `Deprecation`/`Sunset` headers (REST) or a one-off
+// `schema_deprecated` message (WS). After the sunset the version is dropped from
+// SUPPORTED_SCHEMA_VERSIONS and requests pinning it are rejected.
+//
+// Purely additive changes (new optional fields) do not bump the version.
+
+use serde_json::{json, Value};
+
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// Versions the server can still emit
+pub const SUPPORTED_SCHEMA_VERSIONS: &[u32] = &[1];
+
+/// Deprecated versions and their sunset dates (RFC 3339)
+pub const DEPRECATED_SCHEMA_VERSIONS: &[(u32, &str)] = &[];
+
+/// Resolve the schema version requested by a client (defaults to the current version)
+pub fn negotiate(requested: Option<&str>) -> Result<u32, String> {
+    let version = match requested {
+        None => return Ok(CURRENT_SCHEMA_VERSION),
+        Some(raw) => raw
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid schema_version '{}'", raw))?,
+    };
+
+    if SUPPORTED_SCHEMA_VERSIONS.contains(&version) {
+        Ok(version)
+    } else {
+        Err(format!(
+            "Unsupported schema_version {} (supported: {:?})",
+            version, SUPPORTED_SCHEMA_VERSIONS
+        ))
+    }
+}
+
+/// Sunset date for a deprecated version, if any
+pub fn sunset_for(version: u32) -> Option<&'static str> {
+    DEPRECATED_SCHEMA_VERSIONS
+        .iter()
+        .find(|(v, _)| *v == version)
+        .map(|(_, sunset)| *sunset)
+}
+
+/// Stamp a payload with the given schema version
+pub fn with_schema_version(mut value: Value, version: u32) -> Value {
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("schema_version".to_string(), json!(version));
+    }
+    value
+}
+
+/// Convert a current-version payload into the shape of an older supported version
+/// Each incompatible change adds a step here, applied from newest to oldest
+pub fn downgrade(value: Value, version: u32) -> Value {
+    if version >= CURRENT_SCHEMA_VERSION {
+        return value;
+    }
+
+    // No older versions exist yet; future steps go here, e.g.
+    // if version < 2 { value = v2_to_v1(value); }
+    with_schema_version(value, version)
+}
+
+/// WS notice sent once to connections that pinned a deprecated version
+pub fn deprecation_notice(version: u32) -> Option<Value> {
+    sunset_for(version).map(|sunset| {
+        json!({
+            "type": "schema_deprecated",
+            "schema_version": version,
+            "current_schema_version": CURRENT_SCHEMA_VERSION,
+            "sunset": sunset,
+        })
+    })
+}
+
+/// REST response headers advertising the served version (and its sunset when deprecated)
+pub fn response_headers(version: u32) -> axum::http::HeaderMap {
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert("x-schema-version", version.into());
+    if let Some(sunset) = sunset_for(version) {
+        headers.insert("deprecation", axum::http::HeaderValue::from_static("true"));
+        if let Ok(value) = axum::http::HeaderValue::from_str(sunset) {
+            headers.insert("sunset", value);
+        }
+    }
+    headers
+}
@@ -17,5 +17,32 @@ pub struct Trade {
     pub total_value: f64,        // price * amount
     pub dex_program: String,     // Jupiter v6, Jupiter v4, Raydium, Orca, Meteora, Phoenix
     pub slot: u64,               // Block slot number
+    #[serde(default)]
+    pub sequence: u64,           // Monotonic per-pair ingest sequence: (slot << 16) | position within the slot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trader: Option<String>,  // Fee payer / first signer of the swap transaction
+    #[serde(default)]
+    pub inverted: bool,          // Stored in the opposite orientation and flipped to the requested pair
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spread_bps: Option<f64>, // Deviation from the concurrent oracle mid, (price / mid - 1) * 10_000
+}
+
+impl Trade {
+    /// Flip the trade to the opposite orientation (e.g. USDC/SOL -> SOL/USDC)
+    /// Price is inverted, base and quote amounts swap, and the side flips with them
+    pub fn invert(&mut self) {
+        std::mem::swap(&mut self.base_symbol, &mut self.quote_symbol);
+        std::mem::swap(&mut self.base_mint, &mut self.quote_mint);
+        std::mem::swap(&mut self.amount, &mut self.total_value);
+        self.price = if self.price > 0.0 { 1.0 / self.price } else { 0.0 };
+        // The mid inverts with the price, so the ratio does too
+        self.spread_bps = self.spread_bps.map(|bps| (10_000.0 / (10_000.0 + bps) - 1.0) * 10_000.0);
+        self.side = match self.side.as_str() {
+            "buy" => "sell".to_string(),
+            "sell" => "buy".to_string(),
+            other => other.to_string(),
+        };
+        self.inverted = !self.inverted;
+    }
 }
 
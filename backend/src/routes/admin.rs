@@ -0,0 +1,415 @@
+// Admin routes module
+
+use axum::{routing::{get, post}, Router, Json, extract::{Path, Query, State}};
+use serde::Deserialize;
+use serde_json::json;
+use crate::services::dex_programs::ProgramConfig;
+use crate::services::load_test::LoadTestConfig;
+use crate::services::pair_mapping::TokenConfig;
+use crate::state::AppState;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+struct SetVenueRequest {
+    enabled: bool,
+}
+
+/// `enabled: null` clears the override, back to the configured state
+#[derive(Debug, Deserialize)]
+struct SetFeatureRequest {
+    enabled: Option<bool>,
+    reason: Option<String>,
+}
+
+/// Token to track at runtime, with the pairs to offer for it
+#[derive(Debug, Deserialize)]
+struct AddTokenRequest {
+    symbol: String,
+    mint: String,
+    decimals: u8,
+    #[serde(default)]
+    pairs: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClearQuarantineRequest {
+    pair: String,
+}
+
+/// List monitored DEX programs and whether ingestion is enabled for each
+async fn list_venues(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!(state.dex_programs.list().await))
+}
+
+/// Start monitoring a DEX program; subscribes on the live QuickNode WebSocket if this shard owns it
+async fn add_venue(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ProgramConfig>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.dex_programs.add(payload).await {
+        Ok(added) => {
+            if let Err(e) = state.clickhouse.store_admin_event("venue_added", &added.program_id, &json!(added)).await {
+                eprintln!("⚠️  Failed to record venue added event: {}", e);
+            }
+            Ok(Json(json!(added)))
+        }
+        Err(message) => Err(axum::response::Json(json!({
+            "error": "Invalid venue",
+            "message": message
+        }))),
+    }
+}
+
+/// Stop monitoring a DEX program (by program ID or venue name) and drop it from the list
+async fn remove_venue(
+    State(state): State<Arc<AppState>>,
+    Path(program): Path<String>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.dex_programs.remove(&program).await {
+        Some(removed) => {
+            if let Err(e) = state.clickhouse.store_admin_event("venue_removed", &removed.program_id, &json!(removed)).await {
+                eprintln!("⚠️  Failed to record venue removed event: {}", e);
+            }
+            Ok(Json(json!(removed)))
+        }
+        None => Err(axum::response::Json(json!({
+            "error": "Unknown venue",
+            "message": format!("No DEX program matches '{}'", program)
+        }))),
+    }
+}
+
+/// Enable or disable ingestion for a DEX program (by program ID or venue name)
+async fn set_venue(
+    State(state): State<Arc<AppState>>,
+    Path(program): Path<String>,
+    Json(payload): Json<SetVenueRequest>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.dex_programs.set_enabled(&program, payload.enabled).await {
+        Some(updated) => Ok(Json(json!(updated))),
+        None => Err(axum::response::Json(json!({
+            "error": "Unknown venue",
+            "message": format!("No DEX program matches '{}'", program)
+        }))),
+    }
+}
+
+/// Every feature flag: configured state, admin override and the effective state
+async fn list_features(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!(state.features.list().await))
+}
+
+/// Override a feature flag on this instance (e.g. pause exports during an incident)
+async fn set_feature(
+    State(state): State<Arc<AppState>>,
+    Path(feature): Path<String>,
+    Json(payload): Json<SetFeatureRequest>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.features.set_override(&feature, payload.enabled, payload.reason).await {
+        Some(status) => {
+            if let Err(e) = state.clickhouse.store_admin_event("feature_flag_set", &feature, &json!(status)).await {
+                eprintln!("⚠️  Failed to record feature flag event: {}", e);
+            }
+            Ok(Json(json!(status)))
+        }
+        None => Err(axum::response::Json(json!({
+            "error": "Unknown feature",
+            "message": format!("No feature named '{}'", feature)
+        }))),
+    }
+}
+
+/// Start tracking a token (and offering pairs with it) on this instance without a restart
+async fn add_token(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<AddTokenRequest>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    let token = TokenConfig {
+        symbol: payload.symbol.trim().to_ascii_uppercase(),
+        mint: payload.mint.trim().to_string(),
+        decimals: payload.decimals,
+    };
+    let symbol = token.symbol.clone();
+    match state.tokens.add_token(token, payload.pairs.clone()).await {
+        Ok(info) => {
+            let details = json!({ "token": info, "pairs": payload.pairs });
+            if let Err(e) = state.clickhouse.store_admin_event("token_added", &symbol, &details).await {
+                eprintln!("⚠️  Failed to record token added event: {}", e);
+            }
+            Ok(Json(details))
+        }
+        Err(message) => Err(axum::response::Json(json!({
+            "error": "Invalid token",
+            "message": message
+        }))),
+    }
+}
+
+/// Accrued platform fees per partner and fee mint
+async fn list_fees(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.clickhouse.get_platform_fee_totals().await {
+        Ok(totals) => Ok(Json(json!(totals))),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch platform fees: {}", e);
+            Err(axum::response::Json(json!({
+                "error": "Failed to fetch platform fees",
+                "message": format!("{}", e)
+            })))
+        }
+    }
+}
+
+/// List pairs currently quarantined by the circuit breaker
+async fn list_quarantined(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!(state.circuit_breaker.list().await))
+}
+
+/// Manually clear a pair's quarantine so its trades are broadcast again
+async fn clear_quarantine(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ClearQuarantineRequest>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.circuit_breaker.clear(&payload.pair).await {
+        Some(cleared) => {
+            if let Err(e) = state.clickhouse.store_admin_event("pair_quarantine_cleared", &payload.pair, &json!(cleared)).await {
+                eprintln!("⚠️  Failed to record quarantine clear event: {}", e);
+            }
+            Ok(Json(json!(cleared)))
+        }
+        None => Err(axum::response::Json(json!({
+            "error": "Not quarantined",
+            "message": format!("Pair '{}' is not quarantined", payload.pair)
+        }))),
+    }
+}
+
+/// Most recent admin events (quarantines, clears, ...)
+async fn list_events(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.clickhouse.get_admin_events(100).await {
+        Ok(events) => Ok(Json(json!(events.into_iter().map(|event| json!({
+            "created_at": event.created_at.unix_timestamp(),
+            "event_type": event.event_type,
+            "subject": event.subject,
+            "details": serde_json::from_str::<serde_json::Value>(&event.details).unwrap_or_default(),
+        })).collect::<Vec<_>>()))),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch admin events: {}", e);
+            Err(axum::response::Json(json!({
+                "error": "Failed to fetch admin events",
+                "message": format!("{}", e)
+            })))
+        }
+    }
+}
+
+/// Latest ClickHouse part counts and the last maintenance optimize pass
+async fn get_maintenance(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!(state.maintenance.report().await))
+}
+
+/// Latest claim on each periodic job's lease: which replica ran it last and until when it's held
+async fn list_leases(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.job_leases.list().await {
+        Ok(leases) => {
+            let now = time::OffsetDateTime::now_utc();
+            Ok(Json(json!({
+                "instance_id": state.job_leases.instance_id(),
+                "leases": leases.into_iter().map(|lease| json!({
+                    "name": lease.name,
+                    "holder": lease.holder,
+                    "acquired_at": lease.acquired_at.unix_timestamp(),
+                    "expires_at": lease.expires_at.unix_timestamp(),
+                    "active": lease.expires_at > now,
+                })).collect::<Vec<_>>(),
+            })))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to fetch job leases: {}", e);
+            Err(axum::response::Json(json!({
+                "error": "Failed to fetch job leases",
+                "message": format!("{}", e)
+            })))
+        }
+    }
+}
+
+/// Trades rejected or flagged by amount sanity checks, per reason, since startup
+async fn get_sanity(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!(state.trade_sanity.counts().await))
+}
+
+/// Sign-in activity from the sessions table over the last `days` UTC days (default 30, max 90):
+/// daily active/returning wallets, the same for the whole window, and session durations
+async fn get_sessions(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    let days = params
+        .get("days")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(30)
+        .clamp(1, 90);
+    let rate = |returning: u64, active: u64| (active > 0).then(|| returning as f64 / active as f64);
+
+    let result = tokio::try_join!(
+        state.clickhouse.get_session_days(days),
+        state.clickhouse.get_session_window(days),
+        state.clickhouse.get_session_durations(days),
+    );
+    match result {
+        Ok((daily, window, durations)) => Ok(Json(json!({
+            "days": days,
+            "daily": daily.into_iter().map(|day| json!({
+                "day": day.day,
+                "sessions": day.sessions,
+                "active_wallets": day.active_wallets,
+                "new_wallets": day.active_wallets - day.returning_wallets,
+                "returning_wallets": day.returning_wallets,
+                "returning_rate": rate(day.returning_wallets, day.active_wallets),
+            })).collect::<Vec<_>>(),
+            "window": {
+                "sessions": window.sessions,
+                "active_wallets": window.active_wallets,
+                "new_wallets": window.active_wallets - window.returning_wallets,
+                "returning_wallets": window.returning_wallets,
+                "returning_rate": rate(window.returning_wallets, window.active_wallets),
+            },
+            "duration": (durations.sessions > 0).then(|| json!({
+                "sessions": durations.sessions,
+                "mean_secs": durations.mean_secs,
+                "p50_secs": durations.p50_secs,
+                "p90_secs": durations.p90_secs,
+                "max_secs": durations.max_secs,
+            })),
+        }))),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch session analytics: {}", e);
+            Err(axum::response::Json(json!({
+                "error": "Failed to fetch session analytics",
+                "message": format!("{}", e)
+            })))
+        }
+    }
+}
+
+/// Which markets users actually watch: live subscriber counts plus sampled history (default 24h, max 30 days)
+async fn get_ws_usage(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    let hours = params
+        .get("hours")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(24)
+        .clamp(1, 24 * 30);
+    let live: Vec<serde_json::Value> = state.ws_manager.subscription_counts().await
+        .into_iter()
+        .map(|((channel, pair), subscribers)| json!({
+            "channel": channel,
+            "pair": pair,
+            "subscribers": subscribers,
+        }))
+        .collect();
+    
+    match state.clickhouse.get_ws_usage(hours).await {
+        Ok(history) => Ok(Json(json!({
+            "hours": hours,
+            "live": live,
+            "history": history,
+        }))),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch WebSocket usage: {}", e);
+            Err(axum::response::Json(json!({
+                "error": "Failed to fetch WebSocket usage",
+                "message": format!("{}", e)
+            })))
+        }
+    }
+}
+
+/// Start injecting synthetic trades into the trade stream (one run at a time)
+async fn start_load_test(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<LoadTestConfig>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    match state.load_test.clone().start(payload).await {
+        Ok(report) => {
+            if let Err(e) = state.clickhouse.store_admin_event("load_test_started", &report.config.pair.to_string(), &json!(report.config)).await {
+                eprintln!("⚠️  Failed to record load test event: {}", e);
+            }
+            Ok(Json(json!(report)))
+        }
+        Err(message) => Err(axum::response::Json(json!({
+            "error": "Load test not started",
+            "message": message
+        }))),
+    }
+}
+
+/// The running load test, or the last one, with its latency percentiles so far
+async fn get_load_test(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!({ "run": state.load_test.report().await }))
+}
+
+/// Stop the running load test early; trades already sent are still waited for
+async fn stop_load_test(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!({ "stopped": state.load_test.stop().await }))
+}
+
+/// Estimated memory held by each per-pair cache and the signature dedup set, with the budget
+/// limits and how many pairs were evicted as cold or over budget
+async fn get_memory(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(state.memory_budget.report())
+}
+
+/// Effective alert and digest message template of every delivery channel
+async fn get_notification_templates(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(state.notification_templates.list())
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/venues", get(list_venues).post(add_venue))
+        .route("/venues/{program}", post(set_venue).delete(remove_venue))
+        .route("/fees", get(list_fees))
+        .route("/quarantine", get(list_quarantined))
+        .route("/quarantine/clear", post(clear_quarantine))
+        .route("/events", get(list_events))
+        .route("/maintenance", get(get_maintenance))
+        .route("/leases", get(list_leases))
+        .route("/sanity", get(get_sanity))
+        .route("/ws-usage", get(get_ws_usage))
+        .route("/sessions", get(get_sessions))
+        .route("/features", get(list_features))
+        .route("/features/{feature}", post(set_feature))
+        .route("/tokens", post(add_token))
+        .route("/load-test", get(get_load_test).post(start_load_test))
+        .route("/load-test/stop", post(stop_load_test))
+        .route("/memory", get(get_memory))
+        .route("/notification-templates", get(get_notification_templates))
+}
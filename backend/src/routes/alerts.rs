@@ -0,0 +1,65 @@
+// Market alert routes module
+
+use axum::{routing::get, Router, Json, extract::State, response::Response};
+use serde::Deserialize;
+use serde_json::json;
+use crate::routes::trades::query_failed;
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::pair_mapping::Pair;
+use crate::state::AppState;
+use std::sync::Arc;
+
+/// Alert kinds that can be filtered on
+const ALERT_KINDS: &[&str] = &["volume_spike"];
+const MAX_ALERTS: usize = 500;
+
+/// Query parameters of /api/alerts
+#[derive(Debug, Deserialize)]
+struct AlertsQuery {
+    pair: Option<Pair>,
+    kind: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+impl Validate for AlertsQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if let Some(kind) = &self.kind
+            && !ALERT_KINDS.contains(&kind.as_str())
+        {
+            errors.push(FieldError::new("kind", format!("kind must be one of {}", ALERT_KINDS.join(", "))));
+        }
+        if !(1..=MAX_ALERTS).contains(&self.limit) {
+            errors.push(FieldError::new("limit", format!("limit must be between 1 and {}", MAX_ALERTS)));
+        }
+        errors
+    }
+}
+
+/// Recent market alerts, newest first; each is the event that was sent on the `alerts` WS channel
+async fn get_alerts(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<AlertsQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let pair = params.pair.map(|p| p.to_string());
+    let rows = state.clickhouse
+        .get_market_alerts(pair.as_deref(), params.kind.as_deref(), params.limit)
+        .await
+        .map_err(|e| query_failed("Failed to query market alerts", &e))?;
+
+    let alerts: Vec<serde_json::Value> = rows
+        .iter()
+        .filter_map(|row| serde_json::from_str(&row.details).ok())
+        .collect();
+    Ok(Json(json!({ "alerts": alerts })))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/alerts", get(get_alerts))
+}
@@ -0,0 +1,228 @@
+// Analytics routes module
+
+use axum::{routing::get, Router, Json, extract::State, http::StatusCode, response::{IntoResponse, Response}};
+use futures_util::future::try_join_all;
+use serde::Deserialize;
+use serde_json::json;
+use crate::routes::trades::{default_pair, query_failed};
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::clickhouse::Candle;
+use crate::services::pair_mapping::{parse_pair, Pair};
+use crate::services::query_limits::interval_seconds;
+use crate::state::AppState;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Longest window accepted by /api/analytics/dex-volume
+const MAX_DEX_VOLUME_HOURS: u64 = 24 * 7;
+/// Longest window accepted by /api/analytics/correlation
+const MAX_CORRELATION_HOURS: u64 = 24 * 30;
+/// Fewest shared returns a correlation is computed from; fewer gives `null`
+const MIN_CORRELATION_RETURNS: usize = 3;
+
+/// Query parameters of /api/analytics/dex-volume
+#[derive(Debug, Deserialize)]
+struct DexVolumeQuery {
+    #[serde(default = "default_pair")]
+    pair: Pair,
+    #[serde(default = "default_window")]
+    window: String,
+}
+
+fn default_window() -> String {
+    "24h".to_string()
+}
+
+/// Hours in a window like `6h` or `7d`
+fn window_hours(raw: &str) -> Option<u64> {
+    let raw = raw.trim().to_ascii_lowercase();
+    let (count, unit_hours) = match raw.strip_suffix('h') {
+        Some(count) => (count, 1),
+        None => (raw.strip_suffix('d')?, 24),
+    };
+    count.parse::<u64>().ok()?.checked_mul(unit_hours)
+}
+
+impl Validate for DexVolumeQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.pair.mints().is_none() {
+            errors.push(FieldError::new("pair", format!("unsupported pair {}", self.pair)));
+        }
+        if !window_hours(&self.window).is_some_and(|hours| (1..=MAX_DEX_VOLUME_HOURS).contains(&hours)) {
+            errors.push(FieldError::new("window", format!("window must be 1h to {}h, e.g. 24h or 7d", MAX_DEX_VOLUME_HOURS)));
+        }
+        errors
+    }
+}
+
+/// Volume, trade count and average trade size per DEX for a pair, with each DEX's share of the
+/// pair's quote volume
+async fn get_dex_volume(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<DexVolumeQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let hours = window_hours(&params.window).unwrap_or_default();
+    let rows = state.clickhouse.get_dex_volume(&params.pair.base, &params.pair.quote, hours).await
+        .map_err(|e| query_failed("Failed to query DEX volume", &e))?;
+
+    let total_trades: u64 = rows.iter().map(|row| row.trades).sum();
+    let total_base_volume: f64 = rows.iter().map(|row| row.base_volume).sum();
+    let total_quote_volume: f64 = rows.iter().map(|row| row.quote_volume).sum();
+    let dexes: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            let share_pct = (total_quote_volume > 0.0).then(|| row.quote_volume / total_quote_volume * 100.0);
+            let mut entry = json!(row);
+            entry["share_pct"] = json!(share_pct);
+            entry
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "pair": params.pair,
+        "window": params.window.trim().to_ascii_lowercase(),
+        "hours": hours,
+        "dexes": dexes,
+        "total": {
+            "trades": total_trades,
+            "base_volume": total_base_volume,
+            "quote_volume": total_quote_volume,
+        },
+    })))
+}
+
+/// Query parameters of /api/analytics/correlation
+#[derive(Debug, Deserialize)]
+struct CorrelationQuery {
+    /// Comma-separated pairs, e.g. `SOL/USDC,BONK/SOL`
+    pairs: String,
+    #[serde(default = "default_correlation_window")]
+    window: String,
+    #[serde(default = "default_correlation_interval")]
+    interval: String,
+}
+
+fn default_correlation_window() -> String {
+    "7d".to_string()
+}
+
+fn default_correlation_interval() -> String {
+    "1h".to_string()
+}
+
+impl CorrelationQuery {
+    fn pair_list(&self) -> Vec<&str> {
+        self.pairs.split(',').map(str::trim).filter(|p| !p.is_empty()).collect()
+    }
+}
+
+impl Validate for CorrelationQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        let pairs = self.pair_list();
+        if pairs.len() < 2 {
+            errors.push(FieldError::new("pairs", "at least two pairs are needed, e.g. SOL/USDC,BONK/SOL"));
+        }
+        for pair in pairs {
+            if parse_pair(pair).is_none_or(|p| p.mints().is_none()) {
+                errors.push(FieldError::new("pairs", format!("unsupported pair {}", pair)));
+            }
+        }
+        if !window_hours(&self.window).is_some_and(|hours| (1..=MAX_CORRELATION_HOURS).contains(&hours)) {
+            errors.push(FieldError::new("window", format!("window must be 1h to {}h, e.g. 24h or 7d", MAX_CORRELATION_HOURS)));
+        }
+        if interval_seconds(&self.interval).is_none() {
+            errors.push(FieldError::new("interval", "interval must be one of 1m, 5m, 15m, 1h, 4h, 1d"));
+        }
+        errors
+    }
+}
+
+/// Log returns between consecutive candles, keyed by the later candle's time
+/// A gap (an interval without trades) breaks the series, so returns never span more than one interval
+fn candle_returns(candles: &[Candle], interval_secs: u64) -> HashMap<u64, f64> {
+    candles
+        .windows(2)
+        .filter(|pair| pair[1].time - pair[0].time == interval_secs && pair[0].close > 0.0 && pair[1].close > 0.0)
+        .map(|pair| (pair[1].time, (pair[1].close / pair[0].close).ln()))
+        .collect()
+}
+
+/// Pearson correlation of two return series over the times both have, with the number of shared returns
+fn correlation(a: &HashMap<u64, f64>, b: &HashMap<u64, f64>) -> (Option<f64>, usize) {
+    let shared: Vec<(f64, f64)> = a.iter().filter_map(|(time, x)| Some((*x, *b.get(time)?))).collect();
+    let n = shared.len();
+    if n < MIN_CORRELATION_RETURNS {
+        return (None, n);
+    }
+    let mean_a = shared.iter().map(|(x, _)| x).sum::<f64>() / n as f64;
+    let mean_b = shared.iter().map(|(_, y)| y).sum::<f64>() / n as f64;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in &shared {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    // A flat series has no defined correlation
+    let denominator = (var_a * var_b).sqrt();
+    ((denominator > 0.0).then(|| (cov / denominator).clamp(-1.0, 1.0)), n)
+}
+
+/// Correlation matrix of candle close-to-close log returns for several pairs over a window
+async fn get_correlation(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<CorrelationQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let symbols: Vec<Pair> = params.pair_list().into_iter().filter_map(parse_pair).collect();
+    // Canonical symbols key the response, so `sol-usdc` comes back as `SOL/USDC`
+    let pairs: Vec<String> = symbols.iter().map(ToString::to_string).collect();
+    let hours = window_hours(&params.window).unwrap_or_default();
+    let interval_secs = interval_seconds(&params.interval).unwrap_or(3_600);
+
+    let to = chrono::Utc::now().timestamp().max(0) as u64;
+    let from = to.saturating_sub(hours * 3_600) / interval_secs * interval_secs;
+    state.query_limits.check_backtest(pairs.len(), &params.interval, from, to).map_err(|message| (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+        "error": "Query too expensive",
+        "message": message
+    }))).into_response())?;
+
+    let per_pair = try_join_all(
+        symbols.iter().map(|pair| state.clickhouse.get_candles(&pair.base, &pair.quote, &params.interval, from, None))
+    )
+    .await
+    .map_err(|e| query_failed("Failed to query correlation candles", &e))?;
+    let returns: Vec<HashMap<u64, f64>> = per_pair.iter().map(|candles| candle_returns(candles, interval_secs)).collect();
+
+    let mut matrix = vec![vec![None; pairs.len()]; pairs.len()];
+    let mut observations = vec![vec![0; pairs.len()]; pairs.len()];
+    for i in 0..pairs.len() {
+        for j in i..pairs.len() {
+            let (value, n) = if i == j {
+                let n = returns[i].len();
+                ((n >= MIN_CORRELATION_RETURNS).then_some(1.0), n)
+            } else {
+                correlation(&returns[i], &returns[j])
+            };
+            matrix[i][j] = value;
+            matrix[j][i] = value;
+            observations[i][j] = n;
+            observations[j][i] = n;
+        }
+    }
+
+    Ok(Json(json!({
+        "pairs": pairs,
+        "window": params.window.trim().to_ascii_lowercase(),
+        "hours": hours,
+        "interval": params.interval,
+        "matrix": matrix,
+        "observations": observations,
+    })))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/analytics/dex-volume", get(get_dex_volume))
+        .route("/analytics/correlation", get(get_correlation))
+}
@@ -1,34 +1,77 @@
 // Auth routes module
 
-use axum::{routing::{get, post}, Router, Json, extract::State};
+use axum::{routing::{get, post}, Router, Json, extract::{Query, State}};
+use serde::Deserialize;
 use serde_json::json;
 use rand::{distributions::Alphanumeric, Rng};
 use ed25519_dalek::{VerifyingKey, Signature};
 use bs58;
+use crate::middleware::auth::AuthUser;
 use crate::models::auth::{VerifyRequest, VerifyResponse};
-use crate::utils::jwt;
+use crate::utils::{challenge, jwt};
 use crate::state::AppState;
 
 async fn health() -> Json<serde_json::Value> {
     Json(json!({ "status": "ok" }))
 }
 
-async fn get_nonce() -> Json<serde_json::Value> {
-    // Generate a random 32-character alphanumeric nonce
-    let nonce: String = rand::thread_rng()
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NonceQuery {
+    /// `raw` (default) or `offchain`
+    challenge_format: Option<String>,
+}
+
+/// Issue a nonce to sign; it is a signed resource token, so verify can check it was issued here
+/// and hasn't outlived the nonce TTL without keeping state
+/// The challenge format is recorded in the nonce, so verify checks the signature the same way
+async fn get_nonce(
+    State(state): State<std::sync::Arc<AppState>>,
+    Query(query): Query<NonceQuery>,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    let format = query.challenge_format.as_deref().unwrap_or("raw");
+    if !challenge::CHALLENGE_FORMATS.contains(&format) {
+        return Err(axum::response::Json(json!({
+            "error": "Invalid challenge format",
+            "message": format!("challengeFormat must be one of {:?}", challenge::CHALLENGE_FORMATS)
+        })));
+    }
+    
+    // Random 32-character alphanumeric part, so every nonce is unique
+    let random: String = rand::thread_rng()
         .sample_iter(&Alphanumeric)
         .take(32)
         .map(char::from)
         .collect();
     
-    Json(json!({ "nonce": nonce }))
+    let (nonce, expires_at) = jwt::generate_resource_token(&format!("nonce:{}:{}", format, random), state.auth.nonce_ttl)
+        .map_err(|e| {
+            axum::response::Json(json!({
+                "error": "Nonce generation failed",
+                "message": format!("Failed to generate nonce: {}", e)
+            }))
+        })?;
+    
+    let mut response = json!({
+        "nonce": nonce,
+        "challengeFormat": format,
+        "challenge": challenge::challenge_text(&nonce),
+        "expiresAt": expires_at,
+        "expiresIn": state.auth.nonce_ttl.num_seconds()
+    });
+    if format == "offchain" {
+        response["applicationDomain"] = json!(bs58::encode(challenge::application_domain()).into_string());
+    }
+    Ok(Json(response))
 }
 
-async fn verify_signature(
-    State(state): State<std::sync::Arc<AppState>>,
-    Json(payload): Json<VerifyRequest>,
-) -> Result<Json<VerifyResponse>, axum::response::Json<serde_json::Value>> {
-    // Step 1: Decode public key from base58
+/// Steps 3-5 of verify: decode the key and signature and check the signature over the challenge
+fn check_signature(
+    payload: &VerifyRequest,
+    challenge_format: &str,
+    offchain_raw_fallback: bool,
+) -> Result<(), axum::response::Json<serde_json::Value>> {
+    // Step 3: Decode public key and signature from base58
     let public_key_bytes = bs58::decode(&payload.public_key)
         .into_vec()
         .map_err(|e| {
@@ -37,8 +80,6 @@ async fn verify_signature(
                 "message": format!("Failed to decode public key: {}", e)
             }))
         })?;
-    
-    // Step 2: Decode signature from base58
     let signature_bytes = bs58::decode(&payload.signature)
         .into_vec()
         .map_err(|e| {
@@ -48,13 +89,10 @@ async fn verify_signature(
             }))
         })?;
     
-    // Step 3: Create the message that was signed
-    let message = format!("Sign this message to authenticate with Trade: {}", payload.nonce);
-    let message_bytes = message.as_bytes();
-    
-    // Step 4: Verify the signature
+    // Step 4: Create the message that was signed, and the key and signature to check it with
+    let message = challenge::challenge_text(&payload.nonce);
     let verifying_key = VerifyingKey::from_bytes(
-        public_key_bytes[..32].try_into().map_err(|_| {
+        public_key_bytes.as_slice().try_into().map_err(|_| {
             axum::response::Json(json!({
                 "error": "Invalid public key",
                 "message": "Public key must be 32 bytes"
@@ -66,28 +104,80 @@ async fn verify_signature(
             "message": format!("Failed to create verifying key: {}", e)
         }))
     })?;
-    
-    // Convert signature bytes to fixed-size array
-    let signature_array: [u8; 64] = signature_bytes[..64].try_into().map_err(|_| {
+    let signature_array: [u8; 64] = signature_bytes.as_slice().try_into().map_err(|_| {
         axum::response::Json(json!({
             "error": "Invalid signature",
             "message": "Signature must be 64 bytes"
         }))
     })?;
-    
     let signature = Signature::from_bytes(&signature_array);
     
-    // Step 5: Verify signature
-    verifying_key.verify_strict(message_bytes, &signature)
-        .map_err(|e| {
-            axum::response::Json(json!({
-                "error": "Signature verification failed",
-                "message": format!("Signature is invalid: {}", e)
-            }))
-        })?;
+    // Step 5: Verify signature, over the off-chain message (or the raw text if falling back) or the raw text
+    let signed_messages = challenge::signed_messages(
+        challenge_format,
+        &message,
+        verifying_key.as_bytes(),
+        offchain_raw_fallback,
+    );
+    if !signed_messages.iter().any(|bytes| verifying_key.verify_strict(bytes, &signature).is_ok()) {
+        return Err(axum::response::Json(json!({
+            "error": "Signature verification failed",
+            "message": format!("Signature is invalid for the {} challenge", challenge_format)
+        })));
+    }
+    Ok(())
+}
+
+fn nonce_reused() -> axum::response::Json<serde_json::Value> {
+    axum::response::Json(json!({
+        "error": "Nonce already used",
+        "message": "This nonce was already used to sign in; request a new one from /auth/nonce"
+    }))
+}
+
+async fn verify_signature(
+    State(state): State<std::sync::Arc<AppState>>,
+    Json(payload): Json<VerifyRequest>,
+) -> Result<Json<VerifyResponse>, axum::response::Json<serde_json::Value>> {
+    // Step 1: Check the nonce was issued by /auth/nonce and hasn't expired
+    let nonce = jwt::verify_token(&payload.nonce)
+        .ok()
+        .filter(|claims| claims.scope.as_deref() == Some("resource"))
+        .and_then(|claims| {
+            let (format, _) = claims.sub.strip_prefix("nonce:")?.split_once(':')?;
+            let format = challenge::CHALLENGE_FORMATS.iter().find(|f| **f == format).copied()?;
+            Some((format, claims.exp))
+        });
+    let Some((challenge_format, nonce_expires_at)) = nonce else {
+        return Err(axum::response::Json(json!({
+            "error": "Invalid nonce",
+            "message": format!(
+                "Nonce is invalid or older than {}s; request a new one from /auth/nonce",
+                state.auth.nonce_ttl.num_seconds()
+            )
+        })));
+    };
+    
+    // Steps 2-4 (repeated attempts are held off by the per-IP rate limit on this route)
+    check_signature(&payload, challenge_format, state.auth.offchain_raw_fallback)?;
+    
+    // Step 5: Consume the nonce on every replica; a concurrent request with the same one may have won
+    match state.signature_checks.consume(&payload.nonce, nonce_expires_at).await {
+        Ok(true) => {}
+        Ok(false) => return Err(nonce_reused()),
+        Err(e) => {
+            // Without the record a replay can't be ruled out, so refuse rather than sign in
+            eprintln!("❌ Failed to consume nonce: {:#}", e);
+            return Err(axum::response::Json(json!({
+                "error": "Sign-in unavailable",
+                "message": "The nonce could not be recorded; try again shortly"
+            })));
+        }
+    }
     
     // Step 6: Generate JWT token
-    let (token, expires_at) = jwt::generate_token(&payload.public_key)
+    let signed_in_at = chrono::Utc::now();
+    let (token, expires_at) = jwt::generate_token(&payload.public_key, signed_in_at + state.auth.token_ttl, signed_in_at)
         .map_err(|e| {
             axum::response::Json(json!({
                 "error": "Token generation failed",
@@ -116,13 +206,88 @@ async fn verify_signature(
     Ok(Json(VerifyResponse {
         token,
         expires_at,
+        expires_in: (expires_at_dt - chrono::Utc::now()).num_seconds().max(0),
+        lifetime: state.auth.token_ttl.num_seconds(),
+        refresh_until: (signed_in_at + state.auth.refresh_ttl).to_rfc3339(),
+    }))
+}
+
+/// Exchange a session token for a new one before it expires; the old token is revoked
+/// Sessions can be refreshed until `refresh_ttl` after the wallet signed in, then need a new signature
+async fn refresh_session(
+    State(state): State<std::sync::Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<Json<VerifyResponse>, axum::response::Json<serde_json::Value>> {
+    let now = chrono::Utc::now();
+    let signed_in_at = auth.claims.signed_in_at();
+    let refresh_until = signed_in_at + state.auth.refresh_ttl;
+    if refresh_until <= now {
+        return Err(axum::response::Json(json!({
+            "error": "Refresh window expired",
+            "message": "This session can no longer be refreshed; sign in again"
+        })));
+    }
+    
+    // The new token never outlives the refresh window
+    let expires_at_dt = (now + state.auth.token_ttl).min(refresh_until);
+    let (token, expires_at) = jwt::generate_token(&auth.wallet, expires_at_dt, signed_in_at)
+        .map_err(|e| {
+            axum::response::Json(json!({
+                "error": "Token generation failed",
+                "message": format!("Failed to generate token: {}", e)
+            }))
+        })?;
+    
+    // Unlike sign-in, fail without a session row: the middleware would reject the new token
+    state.clickhouse.store_session(&auth.wallet, &token, expires_at_dt).await
+        .map_err(|e| {
+            eprintln!("❌ Failed to store refreshed session in ClickHouse: {}", e);
+            axum::response::Json(json!({
+                "error": "Session storage failed",
+                "message": "Failed to store the refreshed session; the current token is still valid"
+            }))
+        })?;
+    
+    state.revocations.revoke(&auth.token, auth.claims.exp).await;
+    if let Err(e) = state.clickhouse.revoke_session(&auth.wallet, &auth.token).await {
+        eprintln!("⚠️  Failed to revoke replaced session for {}: {}", auth.wallet, e);
+    }
+    println!("🔄 Refreshed session for user: {}", auth.wallet);
+    
+    Ok(Json(VerifyResponse {
+        token,
+        expires_at,
+        expires_in: (expires_at_dt - now).num_seconds().max(0),
+        lifetime: state.auth.token_ttl.num_seconds(),
+        refresh_until: refresh_until.to_rfc3339(),
     }))
 }
 
+/// Sign out: revoke the token here and record its session as revoked, so no replica accepts it again
+async fn logout(
+    State(state): State<std::sync::Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    state.revocations.revoke(&auth.token, auth.claims.exp).await;
+    state.clickhouse.revoke_session(&auth.wallet, &auth.token).await
+        .map_err(|e| {
+            eprintln!("❌ Failed to revoke session for {}: {}", auth.wallet, e);
+            axum::response::Json(json!({
+                "error": "Logout failed",
+                "message": "The token is revoked on this server, but not yet on the others; try again"
+            }))
+        })?;
+    println!("👋 Logged out user: {}", auth.wallet);
+    
+    Ok(Json(json!({ "status": "logged_out" })))
+}
+
 pub fn routes() -> Router<std::sync::Arc<crate::state::AppState>> {
     Router::new()
         .route("/health", get(health))
         .route("/nonce", get(get_nonce))
         .route("/verify", post(verify_signature))
+        .route("/refresh", post(refresh_session))
+        .route("/logout", post(logout))
 }
 
@@ -0,0 +1,179 @@
+// Backtesting data routes module
+
+use axum::{routing::get, Router, Json, extract::{Query, State}, http::StatusCode, response::{IntoResponse, Response}};
+use futures_util::future::try_join_all;
+use serde_json::json;
+use crate::routes::trades::query_failed;
+use crate::services::clickhouse::Candle;
+use crate::services::pair_mapping::parse_pair;
+use crate::services::query_limits::interval_seconds;
+use crate::state::AppState;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How intervals without trades are represented in the matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MissingData {
+    /// `null` cell
+    Null,
+    /// Flat candle at the previous close with zero volume (`null` before the pair's first trade)
+    Previous,
+    /// Timestamp dropped unless every pair has a candle
+    Drop,
+}
+
+impl MissingData {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "null" => Some(Self::Null),
+            "previous" => Some(Self::Previous),
+            "drop" => Some(Self::Drop),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Previous => "previous",
+            Self::Drop => "drop",
+        }
+    }
+}
+
+fn bad_request(error: &str, message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({
+        "error": error,
+        "message": message
+    }))).into_response()
+}
+
+fn candle_json(candle: &Candle) -> serde_json::Value {
+    json!({
+        "open": candle.open,
+        "high": candle.high,
+        "low": candle.low,
+        "close": candle.close,
+        "volume": candle.volume,
+    })
+}
+
+/// Time-aligned candles for several pairs: one shared `timestamps` axis and, per pair,
+/// a column of candles aligned to it (`fill` picks the missing-data policy)
+async fn get_backtest_candles(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let requested: Vec<&str> = params
+        .get("pairs")
+        .map(|raw| raw.split(',').map(str::trim).filter(|p| !p.is_empty()).collect())
+        .unwrap_or_default();
+    let mut symbols = Vec::with_capacity(requested.len());
+    for pair in requested {
+        let Some(parsed) = parse_pair(pair) else {
+            return Err(bad_request("Invalid pair format", format!("'{}' must be in format BASE/QUOTE", pair)));
+        };
+        symbols.push(parsed);
+    }
+    // Canonical symbols key the response, so `sol-usdc` comes back as `SOL/USDC`
+    let pairs: Vec<String> = symbols.iter().map(ToString::to_string).collect();
+
+    let interval = params.get("interval").cloned().unwrap_or_else(|| "1h".to_string());
+    let fill = match params.get("fill") {
+        Some(raw) => MissingData::parse(raw)
+            .ok_or_else(|| bad_request("Invalid fill", "fill must be one of null, previous, drop".to_string()))?,
+        None => MissingData::Null,
+    };
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    let parse_time = |key: &'static str| params.get(key).map(|s| s.parse::<u64>()).transpose().map_err(|_| key);
+    let invalid_time = |key: &str| bad_request("Invalid time", format!("{} must be a unix timestamp in seconds", key));
+    let to = parse_time("to").map_err(invalid_time)?.unwrap_or(now);
+    let Some(from) = parse_time("from").map_err(invalid_time)? else {
+        return Err(bad_request("Missing from", "from (unix seconds) is required".to_string()));
+    };
+
+    let count = state.query_limits.check_backtest(pairs.len(), &interval, from, to).map_err(|message| (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+        "error": "Query too expensive",
+        "message": message
+    }))).into_response())?;
+
+    // Common axis: every interval start from the one containing `from` to the one containing `to`
+    let interval_secs = interval_seconds(&interval).unwrap_or(60);
+    let start = from / interval_secs * interval_secs;
+    let axis: Vec<u64> = (0..count).map(|i| start + i * interval_secs).collect();
+    let end = start + count * interval_secs;
+
+    let per_pair = try_join_all(
+        symbols.iter().map(|pair| state.clickhouse.get_candles(&pair.base, &pair.quote, &interval, start, Some(end)))
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("❌ Backtest candle query error for {:?}: {}", pairs, e);
+        query_failed("Failed to query backtest candles", &e)
+    })?;
+
+    // Align each pair's candles to the axis
+    let mut columns: Vec<Vec<Option<Candle>>> = per_pair
+        .into_iter()
+        .map(|candles| {
+            let by_time: HashMap<u64, Candle> = candles.into_iter().map(|c| (c.time, c)).collect();
+            let mut previous: Option<Candle> = None;
+            axis.iter()
+                .map(|time| match by_time.get(time) {
+                    Some(candle) => {
+                        previous = Some(candle.clone());
+                        Some(candle.clone())
+                    }
+                    None if fill == MissingData::Previous => previous.as_ref().map(|p| Candle {
+                        time: *time,
+                        open: p.close,
+                        high: p.close,
+                        low: p.close,
+                        close: p.close,
+                        volume: 0.0,
+                    }),
+                    None => None,
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut timestamps = axis.clone();
+    if fill == MissingData::Drop {
+        let keep: Vec<bool> = (0..axis.len())
+            .map(|i| columns.iter().all(|column| column[i].is_some()))
+            .collect();
+        timestamps = axis.iter().zip(&keep).filter(|(_, k)| **k).map(|(t, _)| *t).collect();
+        for column in columns.iter_mut() {
+            let mut keep = keep.iter();
+            column.retain(|_| *keep.next().unwrap_or(&false));
+        }
+    }
+
+    let candles: serde_json::Map<String, serde_json::Value> = pairs
+        .iter()
+        .zip(&columns)
+        .map(|(pair, column)| {
+            let cells: Vec<serde_json::Value> = column
+                .iter()
+                .map(|cell| cell.as_ref().map(candle_json).unwrap_or(serde_json::Value::Null))
+                .collect();
+            (pair.clone(), json!(cells))
+        })
+        .collect();
+
+    Ok(Json(json!({
+        "interval": interval,
+        "from": from,
+        "to": to,
+        "fill": fill.as_str(),
+        "pairs": pairs,
+        "timestamps": timestamps,
+        "candles": candles,
+    })))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/backtest/candles", get(get_backtest_candles))
+}
@@ -0,0 +1,102 @@
+// Frontend bootstrap route module
+
+use axum::{routing::get, Router, Json, extract::State, http::HeaderMap, response::{IntoResponse, Response}};
+use serde::Deserialize;
+use serde_json::json;
+use crate::models::schema;
+use crate::services::clickhouse::TradeFilter;
+use crate::routes::trades::{default_pair, query_failed, Interval};
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::pair_mapping::{pair_to_mints, Pair};
+use crate::state::AppState;
+use crate::websocket::manager::MAX_SUBSCRIPTIONS_PER_CONNECTION;
+use crate::websocket::messages::CHANNELS;
+use std::sync::Arc;
+
+const BOOTSTRAP_TRADE_LIMIT: usize = 100;
+
+/// Query parameters of /api/bootstrap
+#[derive(Debug, Deserialize)]
+struct BootstrapQuery {
+    #[serde(default = "default_pair")]
+    pair: Pair,
+    #[serde(default)]
+    interval: Interval,
+    schema_version: Option<String>,
+}
+
+/// Pair and interval are checked as they're deserialized; whether the pair is supported, by the handler
+impl Validate for BootstrapQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        Vec::new()
+    }
+}
+
+/// Everything the frontend needs on startup in one response:
+/// pair metadata, recent trades, candles, 24h stats, and WS connection parameters
+async fn get_bootstrap(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<BootstrapQuery>,
+) -> Result<(HeaderMap, Json<serde_json::Value>), Response> {
+    let schema_version = schema::negotiate(params.schema_version.as_deref())
+        .map_err(|message| axum::response::Json(json!({
+            "error": "Unsupported schema version",
+            "message": message
+        })).into_response())?;
+    let pair = params.pair.to_string();
+    let Pair { base: base_symbol, quote: quote_symbol } = params.pair;
+    let interval = params.interval.as_str();
+    let (base_mint, quote_mint) = pair_to_mints(&pair).ok_or_else(|| axum::response::Json(json!({
+        "error": "Unknown pair",
+        "message": format!("Pair {} is not supported", pair)
+    })).into_response())?;
+
+    // Run the three ClickHouse queries concurrently
+    let filter = TradeFilter::default();
+    let (trades, candles, stats) = tokio::try_join!(
+        state.clickhouse.get_trades(&base_symbol, &quote_symbol, BOOTSTRAP_TRADE_LIMIT, &filter),
+        state.clickhouse.get_ohlcv(&base_symbol, &quote_symbol, interval, state.query_limits.max_candles, None, None),
+        state.aggregator.stats(&base_symbol, &quote_symbol),
+    )
+    .map_err(|e| {
+        eprintln!("❌ Bootstrap query error for {}: {}", pair, e);
+        query_failed("Failed to load bootstrap data", &e)
+    })?;
+
+    let trades: Vec<serde_json::Value> = trades
+        .iter()
+        .map(|trade| schema::downgrade(
+            schema::with_schema_version(json!(trade), schema::CURRENT_SCHEMA_VERSION),
+            schema_version,
+        ))
+        .collect();
+
+    Ok((schema::response_headers(schema_version), Json(json!({
+        "schema_version": schema_version,
+        "pair": {
+            "symbol": format!("{}/{}", base_symbol, quote_symbol),
+            "base_symbol": base_symbol,
+            "quote_symbol": quote_symbol,
+            "base_mint": base_mint,
+            "quote_mint": quote_mint,
+        },
+        "trades": trades,
+        "candles": {
+            "interval": interval,
+            "data": candles,
+        },
+        "stats": stats,
+        "websocket": {
+            "path": "/ws/trades",
+            "schema_version": schema_version,
+            "channels": CHANNELS,
+            "max_subscriptions": MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            "signing_key": state.message_signing.public_key(),
+        },
+    }))))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/bootstrap", get(get_bootstrap))
+}
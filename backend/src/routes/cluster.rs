@@ -0,0 +1,22 @@
+// Cluster routes module
+
+use axum::{routing::get, Router, Json, extract::State, response::Response};
+use crate::routes::trades::query_failed;
+use crate::state::AppState;
+use std::sync::Arc;
+
+/// Replicas sharing this deployment's ClickHouse: roles, version, health and the jobs each holds
+async fn get_cluster(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, Response> {
+    state.cluster
+        .members()
+        .await
+        .map(Json)
+        .map_err(|e| query_failed("Failed to list cluster instances", &e))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/cluster", get(get_cluster))
+}
@@ -0,0 +1,71 @@
+// Export download routes module
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde_json::json;
+use crate::state::AppState;
+use crate::utils::jwt;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+
+/// Download a finished export using the signed URL from the `export_ready` WS event
+async fn download_export(
+    State(state): State<Arc<AppState>>,
+    Path(export_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let authorized = params
+        .get("token")
+        .and_then(|token| jwt::verify_token(token).ok())
+        .map(|claims| claims.sub == format!("export:{}", export_id))
+        .unwrap_or(false);
+    if !authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Invalid download token",
+                "message": "The download URL is invalid or has expired"
+            })),
+        )
+            .into_response();
+    }
+
+    let file = match state.exports.export_path(&export_id) {
+        Some(path) => tokio::fs::File::open(path).await.ok(),
+        None => None,
+    };
+    let file = match file {
+        Some(file) => file,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({
+                    "error": "Export not found",
+                    "message": format!("No export file for {}", export_id)
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"trades-{}.csv\"", export_id)),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response()
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/exports/{export_id}", get(download_export))
+}
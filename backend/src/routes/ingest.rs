@@ -0,0 +1,122 @@
+// Bulk trade ingestion routes module
+
+use axum::{routing::post, Router, Json, extract::{Request, State}, http::StatusCode, middleware::Next, response::Response};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use crate::middleware::verify_ingest_signature;
+use crate::models::trade::Trade;
+use crate::services::pair_mapping::symbol_to_mint;
+use crate::services::sharding::SHARD_INGEST_SOURCE;
+use crate::state::AppState;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::mpsc::error::TrySendError;
+
+/// Most trades accepted in one request
+const MAX_INGEST_BATCH: usize = 1_000;
+/// How far in the future a trade timestamp may be (clock skew allowance)
+const MAX_FUTURE_SKEW_SECS: i64 = 60;
+
+/// Semantic checks on top of the Trade schema; returns the rejection reason
+fn validate(trade: &mut Trade) -> Result<(), String> {
+    if trade.id.is_empty() {
+        return Err("id is required".to_string());
+    }
+    if !trade.price.is_finite() || trade.price <= 0.0 {
+        return Err("price must be a positive number".to_string());
+    }
+    if !trade.amount.is_finite() || trade.amount <= 0.0 {
+        return Err("amount must be a positive number".to_string());
+    }
+    if trade.side != "buy" && trade.side != "sell" {
+        return Err("side must be 'buy' or 'sell'".to_string());
+    }
+    if trade.timestamp.timestamp() > Utc::now().timestamp() + MAX_FUTURE_SKEW_SECS {
+        return Err("timestamp is in the future".to_string());
+    }
+    for (symbol, mint) in [(&trade.base_symbol, &trade.base_mint), (&trade.quote_symbol, &trade.quote_mint)] {
+        match symbol_to_mint(symbol) {
+            Some(expected) if *mint == expected => {}
+            Some(_) => return Err(format!("mint does not match {}", symbol)),
+            None => return Err(format!("unsupported token {}", symbol)),
+        }
+    }
+    // Block times are whole seconds; a stored trade is keyed on (timestamp, id), so the same
+    // transaction ingested here and seen on the stream must carry the same timestamp
+    trade.timestamp = DateTime::from_timestamp(trade.timestamp.timestamp(), 0).unwrap_or(trade.timestamp);
+    // Derived fields are recomputed rather than trusted
+    trade.total_value = trade.price * trade.amount;
+    trade.inverted = false;
+    Ok(())
+}
+
+/// Accept a batch of externally parsed trades and feed them into the ingestion pipeline
+/// (sanity checks, circuit breaker, storage, broadcast), skipping IDs already seen
+async fn ingest_trades(
+    State(state): State<Arc<AppState>>,
+    Json(trades): Json<Vec<Trade>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if trades.len() > MAX_INGEST_BATCH {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, Json(json!({
+            "error": "Batch too large",
+            "message": format!("At most {} trades per request", MAX_INGEST_BATCH)
+        }))));
+    }
+
+    let ids: Vec<String> = trades.iter().map(|t| t.id.clone()).collect();
+    let stored: HashSet<String> = state.clickhouse.existing_trade_ids(&ids).await
+        .map_err(|e| {
+            eprintln!("❌ [Ingest] Failed to check existing trades: {}", e);
+            (StatusCode::SERVICE_UNAVAILABLE, Json(json!({
+                "error": "Failed to check existing trades",
+                "message": format!("{}", e)
+            })))
+        })?
+        .into_iter()
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut accepted = 0;
+    let mut duplicates = 0;
+    let mut rejected = Vec::new();
+    // A degraded replica's queue has no consumer until the RPC answers
+    let ingesting = state.stream_health.is_ingesting().await;
+    for (index, mut trade) in trades.into_iter().enumerate() {
+        if stored.contains(&trade.id) || !seen.insert(trade.id.clone()) {
+            duplicates += 1;
+            continue;
+        }
+        if let Err(reason) = validate(&mut trade) {
+            rejected.push(json!({ "index": index, "id": trade.id, "reason": reason }));
+            continue;
+        }
+        let id = trade.id.clone();
+        if !ingesting {
+            rejected.push(json!({ "index": index, "id": id, "reason": "trade stream not running" }));
+            continue;
+        }
+        match state.ingest_tx.try_send(trade) {
+            Ok(()) => accepted += 1,
+            Err(TrySendError::Full(_)) => rejected.push(json!({ "index": index, "id": id, "reason": "ingest queue full" })),
+            Err(TrySendError::Closed(_)) => rejected.push(json!({ "index": index, "id": id, "reason": "trade stream not running" })),
+        }
+    }
+
+    println!("📥 [Ingest] Accepted {} trade(s), {} duplicate(s), {} rejected", accepted, duplicates, rejected.len());
+    Ok(Json(json!({
+        "accepted": accepted,
+        "duplicates": duplicates,
+        "rejected": rejected,
+    })))
+}
+
+/// Trades may be posted by the `bulk` source and by sharded ingest workers
+async fn verify_bulk_source(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    verify_ingest_signature(&["bulk", SHARD_INGEST_SOURCE], state.signature_checks.as_ref(), request, next).await
+}
+
+pub fn routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/ingest/trades", post(ingest_trades))
+        .route_layer(axum::middleware::from_fn_with_state(state, verify_bulk_source))
+}
@@ -2,4 +2,24 @@
 
 pub mod auth;
 pub mod trades;
+pub mod admin;
+pub mod bootstrap;
+pub mod exports;
+pub mod wallet;
+pub mod transactions;
+pub mod swap;
+pub mod tokens;
+pub mod slots;
+pub mod ingest;
+pub mod backtest;
+pub mod snapshots;
+pub mod orderbook;
+pub mod alerts;
+pub mod whales;
+pub mod stream;
+pub mod venues;
+pub mod validation;
+pub mod analytics;
+pub mod cluster;
+pub mod version;
 
@@ -0,0 +1,52 @@
+// Order book routes module
+
+use axum::{routing::get, Router, Json, extract::State, http::StatusCode, response::{IntoResponse, Response}};
+use serde::Deserialize;
+use serde_json::json;
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::orderbook::MAX_ORDERBOOK_DEPTH;
+use crate::services::pair_mapping::Pair;
+use crate::state::AppState;
+use std::sync::Arc;
+
+/// Query parameters of /api/orderbook
+#[derive(Debug, Deserialize)]
+struct OrderBookQuery {
+    pair: Pair,
+    #[serde(default = "default_depth")]
+    depth: usize,
+}
+
+fn default_depth() -> usize {
+    20
+}
+
+impl Validate for OrderBookQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if !(1..=MAX_ORDERBOOK_DEPTH).contains(&self.depth) {
+            errors.push(FieldError::new("depth", format!("depth must be between 1 and {}", MAX_ORDERBOOK_DEPTH)));
+        }
+        errors
+    }
+}
+
+/// Top levels of a pair's live order book; `sequence` lines it up with `orderbook` WS updates
+async fn get_orderbook(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<OrderBookQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let pair = params.pair.to_string();
+    match state.orderbooks.snapshot(&pair, params.depth).await {
+        Some(book) => Ok(Json(json!(book))),
+        None => Err((StatusCode::NOT_FOUND, Json(json!({
+            "error": "No order book",
+            "message": format!("No live order book for {}; available: {}", pair, state.orderbooks.pairs().await.join(", "))
+        }))).into_response()),
+    }
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/orderbook", get(get_orderbook))
+}
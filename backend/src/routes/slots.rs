@@ -0,0 +1,65 @@
+// Slot/time conversion routes module
+
+use axum::{routing::get, Router, Json, extract::{Path, Query, State}, http::StatusCode};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use crate::state::AppState;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn slot_json(slot: u64, block_time: Option<i64>) -> serde_json::Value {
+    json!({
+        "slot": slot,
+        "block_time": block_time,
+        "timestamp": block_time.and_then(|t| DateTime::<Utc>::from_timestamp(t, 0)),
+    })
+}
+
+fn upstream_error(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    eprintln!("❌ Slot lookup failed: {}", e);
+    (StatusCode::BAD_GATEWAY, Json(json!({
+        "error": "Failed to resolve slot",
+        "message": format!("{}", e)
+    })))
+}
+
+/// Resolve a slot to its block time (unix seconds and RFC 3339)
+async fn get_slot(
+    State(state): State<Arc<AppState>>,
+    Path(slot): Path<u64>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    match state.slot_clock.block_time(slot).await.map_err(upstream_error)? {
+        Some(block_time) => Ok(Json(slot_json(slot, Some(block_time)))),
+        None => Err((StatusCode::NOT_FOUND, Json(json!({
+            "error": "Block time unavailable",
+            "message": format!("Slot {} was skipped or is not available", slot)
+        })))),
+    }
+}
+
+/// Resolve a time (`?time=` unix seconds or RFC 3339) to the closest slot
+async fn find_slot(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let timestamp = params
+        .get("time")
+        .and_then(|t| {
+            t.parse::<i64>()
+                .ok()
+                .or_else(|| DateTime::parse_from_rfc3339(t).ok().map(|dt| dt.timestamp()))
+        })
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid time",
+            "message": "time must be unix seconds or an RFC 3339 timestamp"
+        }))))?;
+
+    let (slot, block_time) = state.slot_clock.slot_at(timestamp).await.map_err(upstream_error)?;
+    Ok(Json(slot_json(slot, Some(block_time))))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/slots", get(find_slot))
+        .route("/slots/{slot}", get(get_slot))
+}
@@ -0,0 +1,199 @@
+// Snapshot routes module - immutable, content-addressed datasets that research pipelines can pin
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use crate::routes::trades::query_failed;
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::clickhouse::CandleSnapshotRow;
+use crate::services::pair_mapping::Pair;
+use crate::services::query_limits::{interval_seconds, OHLCV_INTERVALS};
+use crate::state::AppState;
+
+/// Query parameters of /api/snapshots/candles; times are unix seconds
+#[derive(Debug, Deserialize)]
+struct CandleSnapshotQuery {
+    pair: Pair,
+    interval: String,
+    /// Exclusive end, rounded down to the interval so every candle in the snapshot is closed
+    #[serde(rename = "upTo")]
+    up_to: u64,
+    /// Inclusive start; defaults to as many candles before `upTo` as MAX_OHLCV_CANDLES allows
+    from: Option<u64>,
+}
+
+impl Validate for CandleSnapshotQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if interval_seconds(&self.interval).is_none() {
+            let supported: Vec<&str> = OHLCV_INTERVALS.iter().map(|(name, _)| *name).collect();
+            errors.push(FieldError::new("interval", format!("interval must be one of {}", supported.join(", "))));
+        }
+        if self.up_to > chrono::Utc::now().timestamp().max(0) as u64 {
+            errors.push(FieldError::new("upTo", "upTo must not be in the future"));
+        }
+        if let Some(from) = self.from
+            && from >= self.up_to
+        {
+            errors.push(FieldError::new("from", "from must be before upTo"));
+        }
+        errors
+    }
+}
+
+/// Snapshot body; its bytes never change once the snapshot exists
+fn snapshot_json(row: &CandleSnapshotRow) -> serde_json::Value {
+    json!({
+        "id": row.id,
+        "pair": row.pair,
+        "interval": row.interval,
+        "from": row.from_time,
+        "upTo": row.up_to,
+        "createdAt": row.created_at.unix_timestamp(),
+        "candleCount": row.candle_count,
+        "candles": serde_json::from_str::<serde_json::Value>(&row.candles).unwrap_or_default(),
+    })
+}
+
+/// Snapshots are immutable, so clients and proxies may cache them forever under their id
+fn snapshot_response(row: &CandleSnapshotRow, cache: &'static str) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("public, max-age=31536000, immutable"));
+    if let Ok(etag) = HeaderValue::from_str(&format!("\"{}\"", row.id)) {
+        headers.insert(header::ETAG, etag);
+    }
+    headers.insert("x-snapshot-cache", HeaderValue::from_static(cache));
+    (headers, Json(snapshot_json(row))).into_response()
+}
+
+/// The range a snapshot request covers, aligned to the interval: (from, up_to)
+/// Too wide (or empty) a range is rejected with 422
+fn snapshot_range(params: &CandleSnapshotQuery, max_candles: u64) -> Result<(u64, u64), Box<Response>> {
+    let interval_secs = interval_seconds(&params.interval).unwrap_or(60);
+    let up_to = params.up_to / interval_secs * interval_secs;
+    let from = match params.from {
+        Some(from) => from / interval_secs * interval_secs,
+        None => up_to.saturating_sub(max_candles * interval_secs),
+    };
+    let candles = up_to.saturating_sub(from) / interval_secs;
+    if candles == 0 || candles > max_candles {
+        return Err(Box::new((StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+            "error": "Query too expensive",
+            "message": format!(
+                "Range covers {} {} candles; a snapshot holds between 1 and {}",
+                candles, params.interval, max_candles
+            )
+        }))).into_response()));
+    }
+    Ok((from, up_to))
+}
+
+/// The snapshot already taken of a pair, interval and range; 404 until one is created with POST
+async fn get_candle_snapshot(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<CandleSnapshotQuery>,
+) -> Result<Response, Response> {
+    let (from, up_to) = snapshot_range(&params, state.query_limits.max_candles).map_err(|response| *response)?;
+    let pair = params.pair.to_string();
+    match state.clickhouse.find_candle_snapshot(&pair, &params.interval, from, up_to).await {
+        Ok(Some(existing)) => Ok(snapshot_response(&existing, "hit")),
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json(json!({
+            "error": "Snapshot not found",
+            "message": format!("No snapshot of {} {} [{}, {}) yet; POST to take one", pair, params.interval, from, up_to)
+        }))).into_response()),
+        Err(e) => Err(query_failed("Failed to query candle snapshots", &e)),
+    }
+}
+
+/// Pin the candles of a pair and interval over [from, upTo)
+/// The first request for a range takes the snapshot; later ones get that same snapshot back, even if
+/// trades arriving late (or being quarantined) have since changed the live candles
+async fn create_candle_snapshot(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<CandleSnapshotQuery>,
+) -> Result<Response, Response> {
+    let (from, up_to) = snapshot_range(&params, state.query_limits.max_candles).map_err(|response| *response)?;
+    let pair = params.pair.to_string();
+
+    if let Some(existing) = state.clickhouse.find_candle_snapshot(&pair, &params.interval, from, up_to).await
+        .map_err(|e| query_failed("Failed to query candle snapshots", &e))?
+    {
+        return Ok(snapshot_response(&existing, "hit"));
+    }
+
+    let rows = state.clickhouse
+        .get_candles(&params.pair.base, &params.pair.quote, &params.interval, from, Some(up_to))
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Candle snapshot query error for {}: {}", pair, e);
+            query_failed("Failed to query candles", &e)
+        })?;
+    let candles = serde_json::to_string(&rows).unwrap_or_else(|_| "[]".to_string());
+
+    // Hash the dataset's definition together with its candles, so equal candles over different ranges differ
+    let mut hasher = Sha256::new();
+    hasher.update(json!({
+        "pair": pair,
+        "interval": params.interval,
+        "from": from,
+        "upTo": up_to,
+    }).to_string().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(candles.as_bytes());
+    let snapshot = CandleSnapshotRow {
+        id: hex::encode(hasher.finalize()),
+        pair: pair.clone(),
+        interval: params.interval.clone(),
+        from_time: from,
+        up_to,
+        created_at: OffsetDateTime::now_utc(),
+        candle_count: rows.len() as u64,
+        candles,
+    };
+    state.clickhouse.store_candle_snapshot(&snapshot).await.map_err(|e| {
+        eprintln!("❌ Failed to store candle snapshot for {}: {}", pair, e);
+        query_failed("Failed to store candle snapshot", &e)
+    })?;
+    println!("📌 Candle snapshot {} taken: {} {} [{}, {}), {} candles", &snapshot.id[..12], pair, params.interval, from, up_to, snapshot.candle_count);
+
+    // Read back in case a concurrent request stored its snapshot first
+    let served = state.clickhouse.find_candle_snapshot(&pair, &params.interval, from, up_to).await
+        .map_err(|e| query_failed("Failed to query candle snapshots", &e))?
+        .unwrap_or(snapshot);
+    Ok(snapshot_response(&served, "miss"))
+}
+
+/// Fetch a pinned candle snapshot by id
+async fn get_snapshot_by_id(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Response, Response> {
+    let not_found = || (StatusCode::NOT_FOUND, Json(json!({
+        "error": "Snapshot not found",
+        "message": format!("No snapshot with id {}", id)
+    }))).into_response();
+    if id.len() != 64 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(not_found());
+    }
+
+    match state.clickhouse.get_candle_snapshot(&id.to_ascii_lowercase()).await {
+        Ok(Some(row)) => Ok(snapshot_response(&row, "hit")),
+        Ok(None) => Err(not_found()),
+        Err(e) => Err(query_failed("Failed to query candle snapshot", &e)),
+    }
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/snapshots/candles", get(get_candle_snapshot).post(create_candle_snapshot))
+        .route("/snapshots/{id}", get(get_snapshot_by_id))
+}
@@ -0,0 +1,231 @@
+// Server-Sent Events routes module - a trade stream for clients that can't hold a WebSocket open
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use crate::models::schema::{self, CURRENT_SCHEMA_VERSION};
+use crate::models::trade::Trade;
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::pair_mapping::{supported_pair, Pair};
+use crate::state::AppState;
+use crate::websocket::manager::{ConnectionReceivers, StreamGuard};
+
+/// Comment sent on an idle stream so proxies don't time it out
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// Reconnection delay suggested to EventSource clients
+const RETRY_AFTER: Duration = Duration::from_secs(3);
+
+/// Query parameters of /api/stream/trades
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    pair: Pair,
+    /// Data plan key, for clients that can't set X-API-Key
+    api_key: Option<String>,
+    /// Resume after this event id, for clients that can't set Last-Event-ID
+    last_event_id: Option<u64>,
+}
+
+impl Validate for StreamQuery {
+    /// Well-formed pairs the service doesn't trade are refused by the handler with 400, like /api/bootstrap
+    fn validate(&self) -> Vec<FieldError> {
+        Vec::new()
+    }
+}
+
+/// A trade as broadcast on the `trades` WS channel; its per-pair sequence is the event id
+fn trade_event(payload: &str, sequence: u64) -> Event {
+    Event::default().event("trade").id(sequence.to_string()).data(payload)
+}
+
+/// Trades may have been missed (replay window exceeded, or the client lagged); refetch /api/trades
+fn resync_event(reason: &str) -> Event {
+    Event::default().event("resync").data(json!({ "reason": reason }).to_string())
+}
+
+/// What a reconnecting client is sent from the recent trades cache (newest first, as it returns them)
+struct Replay<'a> {
+    /// The cache is full and all of it is newer than the client, so trades in between may be missing
+    window_exceeded: bool,
+    /// Trades after `last`, oldest first, up to `visible_until` (the data plan's delay)
+    trades: Vec<&'a Trade>,
+}
+
+fn replay_after<'a>(cached: &'a [Trade], capacity: usize, last: u64, visible_until: chrono::DateTime<Utc>) -> Replay<'a> {
+    Replay {
+        window_exceeded: cached.len() == capacity && cached.iter().all(|t| t.sequence > last),
+        trades: cached.iter().rev().filter(|t| t.sequence > last && t.timestamp <= visible_until).collect(),
+    }
+}
+
+struct LiveStream {
+    receivers: ConnectionReceivers,
+    _guard: StreamGuard,
+    pair: String,
+    /// Sequence of the last trade sent; anything at or before it was already replayed
+    last_sequence: u64,
+}
+
+/// `trade` events for one pair as they are broadcast, resuming after Last-Event-ID
+/// Replayed trades come from the recent trades cache; a gap it can't cover is sent as `resync`
+async fn stream_trades(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ValidQuery(params): ValidQuery<StreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Response> {
+    // Same data plans as the WebSocket, so a delayed plan can't read real time here
+    let api_key = headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .or(params.api_key.as_deref());
+    let plan = state.data_plans.for_api_key(api_key).map_err(|message| {
+        (StatusCode::UNAUTHORIZED, Json(json!({
+            "error": "Invalid API key",
+            "message": message
+        }))).into_response()
+    })?;
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .or(params.last_event_id);
+
+    // Subscribe before reading the replay, so nothing broadcast in between is missed
+    let pair = supported_pair(&params.pair.to_string()).ok_or_else(|| {
+        (StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Unknown pair",
+            "message": format!("Pair {} is not supported", params.pair)
+        }))).into_response()
+    })?;
+    let (receivers, guard) = state.ws_manager.add_stream(plan.delay).await;
+    let mut replay = vec![Event::default().retry(RETRY_AFTER).comment("connected")];
+    let mut last_sequence = 0;
+
+    if let Some(last) = last_event_id {
+        last_sequence = last;
+        let capacity = state.recent_trades.capacity;
+        let cached = match state.recent_trades.recent(&params.pair.base, &params.pair.quote, capacity).await {
+            Ok(Some(trades)) if capacity > 0 => Some(trades),
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!("⚠️  [SSE] Failed to read recent {} trades for replay: {}", pair, e);
+                None
+            }
+        };
+        match cached {
+            Some(trades) => {
+                let replayed = replay_after(&trades, capacity, last, Utc::now() - plan.delay);
+                if replayed.window_exceeded {
+                    replay.push(resync_event("replay_window_exceeded"));
+                }
+                for trade in replayed.trades {
+                    let payload = schema::with_schema_version(json!(trade), CURRENT_SCHEMA_VERSION);
+                    replay.push(trade_event(&payload.to_string(), trade.sequence));
+                    last_sequence = trade.sequence;
+                }
+            }
+            None => replay.push(resync_event("replay_unavailable")),
+        }
+    }
+
+    let live = LiveStream { receivers, _guard: guard, pair, last_sequence };
+    let live = stream::unfold(live, |mut live| async move {
+        loop {
+            match live.receivers.trades.recv().await {
+                Ok(outbound) => {
+                    if outbound.channel != "trades" || outbound.pair.as_deref() != Some(live.pair.as_str()) {
+                        continue;
+                    }
+                    let sequence = outbound.sequence.unwrap_or(0);
+                    if sequence <= live.last_sequence {
+                        continue;
+                    }
+                    live.last_sequence = sequence;
+                    let event = trade_event(&outbound.payload, sequence);
+                    return Some((Ok(event), live));
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("⚠️  [SSE] {} stream lagged, {} broadcasts dropped", live.pair, skipped);
+                    return Some((Ok(resync_event("lagged")), live));
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    let events = stream::iter(replay.into_iter().map(Ok)).chain(live);
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL).text("keep-alive")))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/stream/trades", get(stream_trades))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn trade(sequence: u64, secs: i64) -> Trade {
+        Trade {
+            id: format!("sig{}", sequence),
+            timestamp: Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            base_symbol: "SOL".to_string(),
+            quote_symbol: "USDC".to_string(),
+            base_mint: String::new(),
+            quote_mint: String::new(),
+            price: 100.0,
+            amount: 1.0,
+            side: "buy".to_string(),
+            total_value: 100.0,
+            dex_program: "Raydium".to_string(),
+            slot: sequence >> 16,
+            sequence,
+            trader: None,
+            inverted: false,
+            spread_bps: None,
+        }
+    }
+
+    fn sequences(replay: &Replay) -> Vec<u64> {
+        replay.trades.iter().map(|t| t.sequence).collect()
+    }
+
+    #[test]
+    fn replays_trades_after_last_event_id_oldest_first() {
+        // Newest first, as the cache returns them
+        let cached = [trade(40, 4), trade(30, 3), trade(20, 2), trade(10, 1)];
+        let replay = replay_after(&cached, 10, 20, Utc::now());
+        assert!(!replay.window_exceeded);
+        assert_eq!(sequences(&replay), [30, 40]);
+    }
+
+    #[test]
+    fn full_cache_newer_than_last_event_id_asks_for_resync() {
+        let cached = [trade(40, 4), trade(30, 3)];
+        let replay = replay_after(&cached, 2, 20, Utc::now());
+        assert!(replay.window_exceeded);
+        assert_eq!(sequences(&replay), [30, 40]);
+        // The same trades with room to spare in the cache are everything there is
+        assert!(!replay_after(&cached, 3, 20, Utc::now()).window_exceeded);
+    }
+
+    #[test]
+    fn delayed_plans_replay_only_visible_trades() {
+        let cached = [trade(40, 4), trade(30, 3), trade(20, 2)];
+        let visible_until = Utc.timestamp_opt(1_700_000_003, 0).unwrap();
+        assert_eq!(sequences(&replay_after(&cached, 10, 10, visible_until)), [20, 30]);
+    }
+}
@@ -0,0 +1,96 @@
+// Swap routes module
+
+use axum::{routing::{get, post}, Router, Json, extract::{Query, State}, http::{HeaderMap, StatusCode}};
+use serde::Deserialize;
+use serde_json::json;
+use crate::middleware::auth::AuthUser;
+use crate::services::swap::{SwapError, SwapRequest};
+use crate::state::AppState;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuoteParams {
+    /// Mint address or supported symbol
+    input_mint: String,
+    output_mint: String,
+    /// Input amount in base units
+    amount: u64,
+    slippage_bps: Option<u16>,
+}
+
+/// Preview a Jupiter swap quote: route legs, price impact and effective price
+async fn get_quote(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<QuoteParams>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if params.amount == 0 {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid amount",
+            "message": "amount must be greater than zero"
+        }))));
+    }
+
+    let api_key = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+    let request = SwapRequest {
+        input: params.input_mint,
+        output: params.output_mint,
+        amount: params.amount,
+        slippage_bps: params.slippage_bps,
+    };
+
+    match state.swaps.quote(api_key, request).await {
+        Ok(quote) => Ok(Json(json!(quote))),
+        Err(SwapError::Rejected(reason)) => Err((StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+            "error": "Quote rejected by slippage policy",
+            "message": reason
+        })))),
+        Err(SwapError::Upstream(e)) => {
+            eprintln!("❌ Swap quote failed: {}", e);
+            Err((StatusCode::BAD_GATEWAY, Json(json!({
+                "error": "Failed to fetch quote",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+/// Build an unsigned Jupiter swap transaction for the authenticated wallet
+async fn build_swap(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    headers: HeaderMap,
+    Json(payload): Json<SwapRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if payload.amount == 0 {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid amount",
+            "message": "amount must be greater than zero"
+        }))));
+    }
+
+    // Partner API key selects a per-partner platform fee, if configured
+    let api_key = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+
+    match state.swaps.build(&auth.wallet, api_key, payload).await {
+        Ok(build) => Ok(Json(json!(build))),
+        Err(SwapError::Rejected(reason)) => Err((StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+            "error": "Swap rejected by slippage policy",
+            "message": reason
+        })))),
+        Err(SwapError::Upstream(e)) => {
+            eprintln!("❌ Swap build failed: {}", e);
+            Err((StatusCode::BAD_GATEWAY, Json(json!({
+                "error": "Failed to build swap",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/quote", get(get_quote))
+        .route("/swap", post(build_swap))
+}
@@ -0,0 +1,198 @@
+// Token and pair registry routes module
+
+use axum::{routing::get, Router, Json, extract::{Path, Query, State}, http::StatusCode, response::Response};
+use serde::Deserialize;
+use serde_json::json;
+use crate::routes::trades::{invalid_as_of, parse_as_of, query_failed};
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::markets::{market_entry, token_moves, TokenMove};
+use crate::services::pair_mapping::{mint_to_symbol, parse_pair, supported_pairs, symbol_to_mint, Pair};
+use crate::state::AppState;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// List supported tokens with on-chain metadata and `risk` signals
+async fn list_tokens(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!(state.tokens.list_tokens().await))
+}
+
+/// List supported pairs; each side carries its token's `risk`, plus a combined pair-level `risk`,
+/// a 24h `market` summary and the live data `quality` score
+/// `sort=last_seen` / `sort=first_seen` orders by most recent activity / newest listing
+/// `asOf` re-creates the listing at that time: activity up to it, 24h window ending at it,
+/// and pairs without trades by then left out
+async fn list_pairs(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let as_of = parse_as_of(params.get("asOf").map(String::as_str)).map_err(invalid_as_of)?;
+    let mut pairs = state.tokens.list_pairs().await;
+    let summaries = match as_of {
+        Some(as_of) => {
+            let activity = state.clickhouse.get_pair_activity(Some(as_of)).await
+                .map_err(|e| query_failed("Failed to query pair activity", &e))?;
+            for pair in pairs.iter_mut() {
+                let Some(Pair { base, quote }) = parse_pair(&pair.symbol) else { continue };
+                // Trades are stored in whichever direction the swap went
+                let seen: Vec<_> = activity
+                    .iter()
+                    .filter(|(b, q, _, _)| (*b == base && *q == quote) || (*b == quote && *q == base))
+                    .collect();
+                pair.first_seen = seen.iter().map(|(_, _, first, _)| *first).min();
+                pair.last_seen = seen.iter().map(|(_, _, _, last)| *last).max();
+            }
+            pairs.retain(|pair| pair.first_seen.is_some());
+            state.clickhouse.get_market_summaries(Some(as_of)).await
+                .map_err(|e| query_failed("Failed to query market summaries", &e))?
+        }
+        // The live listing still works without ClickHouse, just without market data
+        None => state.aggregator.market_summaries().await.unwrap_or_else(|e| {
+            eprintln!("⚠️  Failed to load market summaries for /api/pairs: {}", e);
+            Vec::new()
+        }),
+    };
+    match params.get("sort").map(|s| s.as_str()) {
+        Some("last_seen") => pairs.sort_by_key(|p| std::cmp::Reverse(p.last_seen)),
+        Some("first_seen") => pairs.sort_by_key(|p| std::cmp::Reverse(p.first_seen)),
+        _ => {}
+    }
+
+    // Quality describes the live feed, so it isn't re-created for `asOf` listings
+    let quality = match as_of {
+        Some(_) => HashMap::new(),
+        None => state.data_quality.scores().await,
+    };
+
+    let pairs: Vec<serde_json::Value> = pairs
+        .into_iter()
+        .map(|pair| {
+            let levels = [&pair.base, &pair.quote].map(|t| t.risk.as_ref().map(|r| r.level.as_str()));
+            let level = if levels.contains(&Some("high")) {
+                Some("high")
+            } else if levels.contains(&Some("medium")) {
+                Some("medium")
+            } else if levels.iter().all(|l| l.is_some()) {
+                Some("low")
+            } else {
+                None // Not checked yet
+            };
+            let mut value = json!(pair);
+            value["risk"] = json!({ "level": level });
+            if let Some(Pair { base, quote }) = parse_pair(&pair.symbol) {
+                value["market"] = market_entry(&pair.symbol, &base, &quote, &summaries);
+            }
+            value["quality"] = json!(quality.get(&pair.symbol));
+            value
+        })
+        .collect();
+    Ok(Json(json!(pairs)))
+}
+
+/// Holder count and top-holder concentration for a mint (cached for 10 minutes)
+async fn get_holders(
+    State(state): State<Arc<AppState>>,
+    Path(mint): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let valid_mint = bs58::decode(&mint).into_vec().map(|bytes| bytes.len() == 32).unwrap_or(false);
+    if !valid_mint {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid mint",
+            "message": format!("'{}' is not a valid mint address", mint)
+        }))));
+    }
+
+    match state.holders.get(&mint).await {
+        Ok(stats) => Ok(Json(json!(stats))),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch holder stats for {}: {}", mint, e);
+            Err((StatusCode::BAD_GATEWAY, Json(json!({
+                "error": "Failed to fetch holder stats",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+/// Most entries per /api/movers leaderboard
+const MAX_MOVERS: usize = 50;
+
+/// Query parameters of /api/movers
+#[derive(Debug, Deserialize)]
+struct MoversQuery {
+    #[serde(default = "default_movers_window")]
+    window: String,
+    #[serde(default = "default_movers_limit")]
+    limit: usize,
+}
+
+fn default_movers_window() -> String {
+    "24h".to_string()
+}
+
+fn default_movers_limit() -> usize {
+    10
+}
+
+impl Validate for MoversQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if !["1h", "24h"].contains(&self.window.as_str()) {
+            errors.push(FieldError::new("window", "window must be 1h or 24h"));
+        }
+        if !(1..=MAX_MOVERS).contains(&self.limit) {
+            errors.push(FieldError::new("limit", format!("limit must be between 1 and {}", MAX_MOVERS)));
+        }
+        errors
+    }
+}
+
+/// Top gainers, top losers and volume leaders across tracked tokens over the last hour or day
+async fn get_movers(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<MoversQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    // The 24h window is kept in memory; the hour is read from ClickHouse
+    let summaries = match params.window.as_str() {
+        "1h" => state.clickhouse.get_window_summaries(None, 1).await,
+        _ => state.aggregator.market_summaries().await,
+    }
+    .map_err(|e| query_failed("Failed to query market summaries", &e))?;
+
+    // Quote volumes are converted to USD so pairs quoted in different tokens rank together
+    let mut quote_mints: Vec<String> = supported_pairs()
+        .iter()
+        .filter_map(|pair| symbol_to_mint(&parse_pair(pair)?.quote))
+        .collect();
+    quote_mints.sort();
+    quote_mints.dedup();
+    let usd_prices: HashMap<String, f64> = state.portfolio
+        .usd_prices(&quote_mints)
+        .await
+        .into_iter()
+        .filter_map(|(mint, price)| Some((mint_to_symbol(&mint)?, price)))
+        .collect();
+
+    let moves = token_moves(&summaries, &usd_prices);
+    let ranked = |filter: fn(&TokenMove) -> Option<f64>, descending: bool| {
+        let mut ranked: Vec<(f64, &TokenMove)> = moves.iter().filter_map(|m| Some((filter(m)?, m))).collect();
+        ranked.sort_by(|(a, _), (b, _)| if descending { b.total_cmp(a) } else { a.total_cmp(b) });
+        ranked.into_iter().take(params.limit).map(|(_, m)| m).collect::<Vec<_>>()
+    };
+
+    Ok(Json(json!({
+        "window": params.window,
+        "gainers": ranked(|m| m.change_pct.filter(|c| *c > 0.0), true),
+        "losers": ranked(|m| m.change_pct.filter(|c| *c < 0.0), false),
+        "volume_leaders": ranked(|m| m.volume_usd.filter(|v| *v > 0.0), true),
+    })))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/tokens", get(list_tokens))
+        .route("/tokens/{mint}/holders", get(get_holders))
+        .route("/pairs", get(list_pairs))
+        .route("/movers", get(get_movers))
+}
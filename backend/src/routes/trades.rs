@@ -1,83 +1,598 @@
 // Trades routes module
 
-use axum::{routing::get, Router, Json, extract::State};
+use axum::{routing::get, Router, Json, extract::State, http::{header, HeaderMap, StatusCode}, response::{IntoResponse, Response}};
+use serde::Deserialize;
 use serde_json::json;
+use crate::models::schema;
+use crate::models::trade::Trade;
+use crate::routes::validation::{validation_failed, FieldError, ValidQuery, Validate};
+use crate::services::clickhouse::{QueryShed, TradeCursor, TradeFilter, TradePosition};
+use crate::services::pair_mapping::{parse_pair, quote_group, Pair, QUOTE_GROUPS, supported_tokens};
 use crate::state::AppState;
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
 
-/// Get recent trades filtered by pair (from ClickHouse)
+const NDJSON: &str = "application/x-ndjson";
+/// Longest an ND-JSON stream may run; a client reading slower than this is cut off
+const NDJSON_STREAM_DEADLINE: std::time::Duration = std::time::Duration::from_secs(120);
+
+pub(crate) fn default_pair() -> Pair {
+    parse_pair("SOL/USDC").expect("default pair is valid")
+}
+
+/// Query parameters of /api/trades
+#[derive(Debug, Deserialize)]
+struct TradesQuery {
+    #[serde(default = "default_pair")]
+    pair: Pair,
+    #[serde(default = "default_trades_limit")]
+    limit: usize,
+    #[serde(rename = "fromSlot")]
+    from_slot: Option<u64>,
+    #[serde(rename = "toSlot")]
+    to_slot: Option<u64>,
+    /// Cursor paging: trades strictly older / newer than a cursor (see `resolve_cursor`)
+    before: Option<String>,
+    after: Option<String>,
+    page_size: Option<usize>,
+    schema_version: Option<String>,
+}
+
+impl TradesQuery {
+    /// Cursor-paged requests get `{ trades, next_cursor }` instead of a bare array
+    fn paged(&self) -> bool {
+        self.page_size.is_some() || self.before.is_some() || self.after.is_some()
+    }
+}
+
+fn default_trades_limit() -> usize {
+    100
+}
+
+impl Validate for TradesQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        // Upper bound depends on streaming and is enforced by the query guardrails
+        if self.limit == 0 {
+            errors.push(FieldError::new("limit", "limit must be at least 1"));
+        }
+        if let (Some(from), Some(to)) = (self.from_slot, self.to_slot)
+            && from > to
+        {
+            errors.push(FieldError::new("fromSlot", "fromSlot must not be greater than toSlot"));
+        }
+        if (self.before.is_some() || self.after.is_some()) && (self.from_slot.is_some() || self.to_slot.is_some()) {
+            errors.push(FieldError::new("before", "before/after cursors can't be combined with fromSlot/toSlot"));
+        }
+        if self.page_size == Some(0) {
+            errors.push(FieldError::new("page_size", "page_size must be at least 1"));
+        }
+        errors
+    }
+}
+
+/// Candle intervals accepted by /api/ohlcv and /api/bootstrap (see `query_limits::OHLCV_INTERVALS`)
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub(crate) enum Interval {
+    #[default]
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "4h")]
+    FourHours,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl Interval {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::OneMinute => "1m",
+            Self::FiveMinutes => "5m",
+            Self::FifteenMinutes => "15m",
+            Self::OneHour => "1h",
+            Self::FourHours => "4h",
+            Self::OneDay => "1d",
+        }
+    }
+}
+
+/// Query parameters of /api/ohlcv
+#[derive(Debug, Deserialize)]
+struct OhlcvQuery {
+    #[serde(default = "default_pair")]
+    pair: Pair,
+    #[serde(default)]
+    interval: Interval,
+    /// Unix seconds: candles from the one containing `from`, built from trades before `to`
+    from: Option<u64>,
+    to: Option<u64>,
+    schema_version: Option<String>,
+}
+
+impl Validate for OhlcvQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if let Some(from) = self.from
+            && from >= self.to.unwrap_or_else(|| chrono::Utc::now().timestamp().max(0) as u64)
+        {
+            errors.push(FieldError::new("from", "from must be less than to (now when to is omitted)"));
+        }
+        errors
+    }
+}
+
+/// Query parameters of /api/ohlcv/combined
+#[derive(Debug, Deserialize)]
+struct CombinedOhlcvQuery {
+    base: String,
+    #[serde(rename = "quoteGroup")]
+    quote_group: String,
+    #[serde(default)]
+    interval: Interval,
+}
+
+impl Validate for CombinedOhlcvQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if !supported_tokens().contains(&self.base.to_uppercase()) {
+            errors.push(FieldError::new("base", format!("Unsupported token '{}'", self.base)));
+        }
+        match quote_group(&self.quote_group) {
+            Some(quotes) if quotes.contains(&self.base.to_uppercase().as_str()) => {
+                errors.push(FieldError::new("base", format!("{} is itself in the {} quote group", self.base, self.quote_group)));
+            }
+            Some(_) => {}
+            None => {
+                let groups: Vec<&str> = QUOTE_GROUPS.iter().map(|(name, _)| *name).collect();
+                errors.push(FieldError::new("quoteGroup", format!("quoteGroup must be one of {}", groups.join(", "))));
+            }
+        }
+        errors
+    }
+}
+
+/// Query parameters of /api/stats
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    #[serde(default = "default_pair")]
+    pair: Pair,
+    #[serde(rename = "asOf")]
+    as_of: Option<String>,
+}
+
+impl Validate for StatsQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        match parse_as_of(self.as_of.as_deref()) {
+            Ok(_) => Vec::new(),
+            Err(message) => vec![FieldError::new("asOf", message)],
+        }
+    }
+}
+
+/// Query parameters of /api/midprice/history; times are unix seconds
+#[derive(Debug, Deserialize)]
+struct MidpriceHistoryQuery {
+    #[serde(default = "default_pair")]
+    pair: Pair,
+    from: Option<i64>,
+    to: Option<i64>,
+    #[serde(default = "default_midprice_limit")]
+    limit: usize,
+}
+
+fn default_midprice_limit() -> usize {
+    1000
+}
+
+impl Validate for MidpriceHistoryQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if !(1..=MAX_MIDPRICE_POINTS).contains(&self.limit) {
+            errors.push(FieldError::new("limit", format!("limit must be between 1 and {}", MAX_MIDPRICE_POINTS)));
+        }
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from > to
+        {
+            errors.push(FieldError::new("from", "from must not be greater than to"));
+        }
+        errors
+    }
+}
+
+/// Query parameters of /api/execution-quality
+#[derive(Debug, Deserialize)]
+struct ExecutionQualityQuery {
+    pair: Option<Pair>,
+    #[serde(default = "default_execution_quality_hours")]
+    hours: u64,
+}
+
+fn default_execution_quality_hours() -> u64 {
+    24
+}
+
+impl Validate for ExecutionQualityQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if !(1..=MAX_EXECUTION_QUALITY_HOURS).contains(&self.hours) {
+            errors.push(FieldError::new("hours", format!("hours must be between 1 and {}", MAX_EXECUTION_QUALITY_HOURS)));
+        }
+        errors
+    }
+}
+
+/// Map a failed ClickHouse read to a response: 503 + Retry-After when the read was shed, 500 otherwise
+pub(crate) fn query_failed(error: &str, e: &anyhow::Error) -> Response {
+    if let Some(shed) = e.downcast_ref::<QueryShed>() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, shed.retry_after_secs.to_string())],
+            Json(json!({
+                "error": error,
+                "message": format!("{}", shed)
+            })),
+        ).into_response();
+    }
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+        "error": error,
+        "message": format!("{}", e)
+    }))).into_response()
+}
+
+/// Parse the optional `asOf` time-travel parameter (unix seconds or RFC 3339); must not be in the future
+pub(crate) fn parse_as_of(raw: Option<&str>) -> Result<Option<DateTime<Utc>>, &'static str> {
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+    let as_of = raw
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .or_else(|| DateTime::parse_from_rfc3339(raw).ok().map(|dt| dt.with_timezone(&Utc)));
+    match as_of {
+        Some(as_of) if as_of <= Utc::now() => Ok(Some(as_of)),
+        Some(_) => Err("asOf must not be in the future"),
+        None => Err("asOf must be unix seconds or an RFC 3339 timestamp"),
+    }
+}
+
+pub(crate) fn invalid_as_of(message: &str) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({
+        "error": "Invalid asOf",
+        "message": message
+    }))).into_response()
+}
+
+/// Whether the client asked for an ND-JSON stream
+fn wants_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.split(',').any(|t| t.trim().starts_with(NDJSON)))
+}
+
+/// Stream trades as ND-JSON, one line per row as the cursor yields it
+/// A cursor error mid-stream, or passing NDJSON_STREAM_DEADLINE, aborts the body, so clients see a
+/// truncated response rather than a partial success
+fn ndjson_response(cursor: TradeCursor, schema_version: u32) -> Response {
+    let deadline = tokio::time::Instant::now() + NDJSON_STREAM_DEADLINE;
+    let lines = futures_util::stream::unfold(Some(cursor), move |cursor| async move {
+        let mut cursor = cursor?;
+        let Ok(next) = tokio::time::timeout_at(deadline, cursor.next()).await else {
+            eprintln!("❌ ND-JSON trade stream cut off after {:?}", NDJSON_STREAM_DEADLINE);
+            return Some((Err(std::io::Error::other("stream deadline exceeded")), None));
+        };
+        match next {
+            Ok(Some(trade)) => {
+                let value = schema::downgrade(
+                    schema::with_schema_version(json!(trade), schema::CURRENT_SCHEMA_VERSION),
+                    schema_version,
+                );
+                let mut line = value.to_string();
+                line.push('\n');
+                Some((Ok(axum::body::Bytes::from(line)), Some(cursor)))
+            }
+            Ok(None) => None,
+            Err(e) => {
+                eprintln!("❌ ND-JSON trade stream failed: {}", e);
+                Some((Err(std::io::Error::other(e.to_string())), None))
+            }
+        }
+    });
+
+    let mut headers = schema::response_headers(schema_version);
+    headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static(NDJSON));
+    (headers, axum::body::Body::from_stream(lines)).into_response()
+}
+
+/// Resolve a `before`/`after` cursor: a `next_cursor` value, unix milliseconds, an RFC 3339 time,
+/// or the signature of a stored trade of the pair
+async fn resolve_cursor(state: &AppState, pair: &Pair, field: &'static str, raw: &str) -> Result<TradePosition, Response> {
+    if let Some(position) = TradePosition::from_cursor(raw) {
+        return Ok(position);
+    }
+    let timestamp_ms = raw
+        .parse::<i64>()
+        .ok()
+        .or_else(|| DateTime::parse_from_rfc3339(raw).ok().map(|dt| dt.timestamp_millis()));
+    if let Some(timestamp_ms) = timestamp_ms {
+        return Ok(TradePosition { timestamp_ms, trade: None });
+    }
+    match state.clickhouse.trade_position(raw, &pair.base, &pair.quote).await {
+        Ok(Some(position)) => Ok(position),
+        Ok(None) => Err(validation_failed(vec![FieldError::new(field, format!("No {} trade with signature {}", pair, raw))])),
+        Err(e) => Err(query_failed("Failed to resolve trade cursor", &e)),
+    }
+}
+
+/// The newest `limit` trades from the recent trade cache, when it can answer exactly (no slot range
+/// or cursor, not deeper than it holds); cache errors fall back to ClickHouse
+async fn cached_trades(state: &AppState, pair: &Pair, limit: usize, filter: &TradeFilter) -> Option<Vec<Trade>> {
+    if filter.from_slot.is_some() || filter.to_slot.is_some() || filter.before.is_some() || filter.after.is_some() {
+        return None;
+    }
+    match state.recent_trades.recent(&pair.base, &pair.quote, limit).await {
+        Ok(trades) => trades,
+        Err(e) => {
+            eprintln!("⚠️  [RecentTrades] Cache read for {} failed, querying ClickHouse: {}", pair, e);
+            None
+        }
+    }
+}
+
+/// Get recent trades filtered by pair (from the recent trade cache, or ClickHouse)
+/// `before`/`after`/`page_size` page through history with a `next_cursor`, without overlapping rows
+/// `Accept: application/x-ndjson` streams rows from the cursor instead (higher limit, no buffering)
 async fn get_trades(
     State(state): State<std::sync::Arc<AppState>>,
-    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
+    request_headers: HeaderMap,
+    ValidQuery(params): ValidQuery<TradesQuery>,
+) -> Result<Response, Response> {
     println!("📥 GET /api/trades - Request received");
     println!("   Query params: {:?}", params);
     
-    let pair = params.get("pair").cloned().unwrap_or_else(|| "SOL/USDC".to_string());
-    let limit = params
-        .get("limit")
-        .and_then(|s| s.parse::<usize>().ok())
-        .unwrap_or(100);
+    let schema_version = schema::negotiate(params.schema_version.as_deref())
+        .map_err(|message| (StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Unsupported schema version",
+            "message": message
+        }))).into_response())?;
     
-    println!("   Parsed pair: {}, limit: {}", pair, limit);
+    let limit = params.limit;
+    
+    println!("   Parsed pair: {}, limit: {}", params.pair, limit);
 
-    // Parse pair
-    let parts: Vec<&str> = pair.split('/').collect();
-    if parts.len() != 2 {
-        return Err(axum::response::Json(json!({
-            "error": "Invalid pair format",
-            "message": "Pair must be in format BASE/QUOTE"
-        })));
+    // Optional inclusive slot range
+    let mut filter = TradeFilter {
+        from_slot: params.from_slot,
+        to_slot: params.to_slot,
+        ..Default::default()
+    };
+    if let Some(raw) = &params.before {
+        filter.before = Some(resolve_cursor(&state, &params.pair, "before", raw).await?);
+    }
+    if let Some(raw) = &params.after {
+        filter.after = Some(resolve_cursor(&state, &params.pair, "after", raw).await?);
     }
 
-    let base_symbol = parts[0];
-    let quote_symbol = parts[1];
+    let (base_symbol, quote_symbol) = (params.pair.base.as_str(), params.pair.quote.as_str());
     
-    println!("   Querying ClickHouse for {}/{} (limit: {})", base_symbol, quote_symbol, limit);
-
-    // Query ClickHouse for trades
-    match state.clickhouse.get_trades(base_symbol, quote_symbol, limit).await {
-        Ok(trades) => {
-            println!("✅ Successfully fetched {} trades from ClickHouse", trades.len());
-            Ok(Json(json!(trades)))
-        },
-        Err(e) => {
-            eprintln!("❌ ClickHouse query error for {}/{}: {}", base_symbol, quote_symbol, e);
-            Err(axum::response::Json(json!({
-                "error": "Failed to query trades",
-                "message": format!("{}", e)
-            })))
-        }
+    let streaming = wants_ndjson(&request_headers);
+    // Pages are sized by page_size alone; `limit` still bounds streams
+    let paged = params.paged() && !streaming;
+    let page_size = params.page_size.unwrap_or(default_trades_limit());
+    let checked = if paged {
+        state.query_limits.check_page(page_size, &filter)
+    } else {
+        state.query_limits.check_trades(limit, &filter, streaming)
+    };
+    checked.map_err(|message| (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+        "error": "Query too expensive",
+        "message": message
+    }))).into_response())?;
+    
+    println!("   Querying trades for {}/{} (limit: {})", base_symbol, quote_symbol, limit);
+    
+    if streaming {
+        let cursor = state.clickhouse.stream_trades(base_symbol, quote_symbol, limit, &filter).await.map_err(|e| {
+            eprintln!("❌ ClickHouse stream error for {}/{}: {}", base_symbol, quote_symbol, e);
+            query_failed("Failed to query trades", &e)
+        })?;
+        return Ok(ndjson_response(cursor, schema_version));
     }
+
+    // Identical requests within the response cache TTL share one body (and one ClickHouse query)
+    let key = format!(
+        "trades:{}:{}:{:?}:{:?}:{:?}:{:?}:{:?}:{}",
+        params.pair, limit, params.from_slot, params.to_slot, params.before, params.after, params.page_size, schema_version,
+    );
+    let body = state.response_cache.get_or_load(key, || async {
+        if paged {
+            // One extra row tells whether another page follows
+            let mut trades = match cached_trades(&state, &params.pair, page_size + 1, &filter).await {
+                Some(trades) => trades,
+                None => state.clickhouse.get_trades(base_symbol, quote_symbol, page_size + 1, &filter).await.map_err(|e| {
+                    eprintln!("❌ ClickHouse query error for {}/{}: {}", base_symbol, quote_symbol, e);
+                    query_failed("Failed to query trades", &e)
+                })?,
+            };
+            let next_cursor = (trades.len() > page_size).then(|| {
+                trades.truncate(page_size);
+                trades.last().map(|trade| TradePosition::of(trade).to_cursor())
+            }).flatten();
+            let payload: Vec<serde_json::Value> = trades
+                .iter()
+                .map(|trade| schema::downgrade(
+                    schema::with_schema_version(json!(trade), schema::CURRENT_SCHEMA_VERSION),
+                    schema_version,
+                ))
+                .collect();
+            return Ok(json!({
+                "trades": payload,
+                "next_cursor": next_cursor,
+            }));
+        }
+
+        // Serve from the recent trade cache when it holds enough, else query ClickHouse
+        let trades = match cached_trades(&state, &params.pair, limit, &filter).await {
+            Some(trades) => {
+                println!("✅ Served {} trades from the recent trade cache", trades.len());
+                trades
+            }
+            None => {
+                let trades = state.clickhouse.get_trades(base_symbol, quote_symbol, limit, &filter).await.map_err(|e| {
+                    eprintln!("❌ ClickHouse query error for {}/{}: {}", base_symbol, quote_symbol, e);
+                    query_failed("Failed to query trades", &e)
+                })?;
+                println!("✅ Successfully fetched {} trades from ClickHouse", trades.len());
+                trades
+            }
+        };
+        let payload: Vec<serde_json::Value> = trades
+            .iter()
+            .map(|trade| schema::downgrade(
+                schema::with_schema_version(json!(trade), schema::CURRENT_SCHEMA_VERSION),
+                schema_version,
+            ))
+            .collect();
+        Ok(json!(payload))
+    }).await?;
+    Ok((schema::response_headers(schema_version), Json(body)).into_response())
 }
 
 /// Get OHLCV data for a pair and interval (from ClickHouse)
 async fn get_ohlcv(
     State(state): State<std::sync::Arc<AppState>>,
-    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, axum::response::Json<serde_json::Value>> {
-    let pair = params.get("pair").cloned().unwrap_or_else(|| "SOL/USDC".to_string());
-    let interval = params.get("interval").cloned().unwrap_or_else(|| "1m".to_string());
+    ValidQuery(params): ValidQuery<OhlcvQuery>,
+) -> Result<(HeaderMap, Json<serde_json::Value>), Response> {
+    let schema_version = schema::negotiate(params.schema_version.as_deref())
+        .map_err(|message| (StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Unsupported schema version",
+            "message": message
+        }))).into_response())?;
+    let (base_symbol, quote_symbol) = (params.pair.base.as_str(), params.pair.quote.as_str());
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    state.query_limits.check_ohlcv_range(params.interval.as_str(), params.from, params.to, now)
+        .map_err(|message| (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+            "error": "Query too expensive",
+            "message": message
+        }))).into_response())?;
+
+    // Query ClickHouse for OHLCV data (bounded to the requested range, or the most recent candles)
+    let key = format!("ohlcv:{}:{}:{:?}:{:?}", params.pair, params.interval.as_str(), params.from, params.to);
+    let body = state.response_cache.get_or_load(key, || async {
+        state.clickhouse.get_ohlcv(base_symbol, quote_symbol, params.interval.as_str(), state.query_limits.max_candles, params.from, params.to).await
+            .map(|ohlcv_data| json!(ohlcv_data))
+            .map_err(|e| query_failed("Failed to query OHLCV data", &e))
+    }).await?;
+    Ok((schema::response_headers(schema_version), Json(body)))
+}
+
+/// Get 24h stats for a pair; `asOf` computes the window ending at that time instead of now
+async fn get_stats(
+    State(state): State<std::sync::Arc<AppState>>,
+    ValidQuery(params): ValidQuery<StatsQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let pair = params.pair;
+    let (base_symbol, quote_symbol) = (pair.base.as_str(), pair.quote.as_str());
+    let as_of = parse_as_of(params.as_of.as_deref()).map_err(invalid_as_of)?;
+    
+    // The live window comes from the in-memory aggregates; `asOf` windows are computed by ClickHouse
+    let key = format!("stats:{}:{:?}", pair, as_of.map(|t| t.timestamp_millis()));
+    let stats = state.response_cache.get_or_load(key, || async {
+        let stats = match as_of {
+            Some(as_of) => state.clickhouse.get_24h_stats(base_symbol, quote_symbol, Some(as_of)).await,
+            None => state.aggregator.stats(base_symbol, quote_symbol).await,
+        };
+        let mut stats = stats.map_err(|e| query_failed("Failed to query 24h stats", &e))?;
+        stats["pair"] = json!(pair);
+        stats["asOf"] = json!(as_of.unwrap_or_else(Utc::now).to_rfc3339());
+        Ok(stats)
+    }).await?;
+    Ok(Json(stats))
+}
+
+/// Most ticks returned by one /api/midprice/history request (~7h of 5s ticks)
+const MAX_MIDPRICE_POINTS: usize = 5000;
+
+/// Get persisted Jupiter mid-price ticks for a pair, to chart against traded prices
+async fn get_midprice_history(
+    State(state): State<std::sync::Arc<AppState>>,
+    ValidQuery(params): ValidQuery<MidpriceHistoryQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let pair = params.pair;
+    let (base_symbol, quote_symbol) = (pair.base.as_str(), pair.quote.as_str());
+    
+    // Window in unix seconds, defaulting to the last hour
+    let to = params.to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = params.from.unwrap_or(to - 3600);
+    if from > to {
+        return Err(validation_failed(vec![FieldError::new("from", "from must not be greater than to")]));
+    }
+    
+    match state.clickhouse.get_price_history(base_symbol, quote_symbol, from, to, params.limit).await {
+        Ok(ticks) => {
+            let points: Vec<serde_json::Value> = ticks
+                .iter()
+                .map(|tick| json!({
+                    "timestamp": (tick.created_at.unix_timestamp_nanos() / 1_000_000) as i64,
+                    "price": tick.price,
+                }))
+                .collect();
+            Ok(Json(json!({
+                "pair": pair,
+                "from": from,
+                "to": to,
+                "points": points,
+            })))
+        }
+        Err(e) => Err(query_failed("Failed to query mid-price history", &e)),
+    }
+}
+
+/// Longest lookback accepted by /api/execution-quality
+const MAX_EXECUTION_QUALITY_HOURS: u64 = 24 * 7;
 
-    // Parse pair
-    let parts: Vec<&str> = pair.split('/').collect();
-    if parts.len() != 2 {
-        return Err(axum::response::Json(json!({
-            "error": "Invalid pair format",
-            "message": "Pair must be in format BASE/QUOTE"
-        })));
+/// Get spread-to-oracle execution statistics per DEX, optionally for one pair
+async fn get_execution_quality(
+    State(state): State<std::sync::Arc<AppState>>,
+    ValidQuery(params): ValidQuery<ExecutionQualityQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let pair = params.pair;
+    let hours = params.hours;
+    
+    match state.clickhouse.get_execution_quality(pair.as_ref().map(|p| (p.base.as_str(), p.quote.as_str())), hours).await {
+        Ok(rows) => Ok(Json(json!({
+            "pair": pair,
+            "hours": hours,
+            "dexes": rows,
+        }))),
+        Err(e) => Err(query_failed("Failed to query execution quality", &e)),
     }
+}
 
-    let base_symbol = parts[0];
-    let quote_symbol = parts[1];
+/// One candle series for a base token across a group of quote tokens (e.g. SOL against USDC and USDT),
+/// since its liquidity is split between those pairs
+async fn get_combined_ohlcv(
+    State(state): State<std::sync::Arc<AppState>>,
+    ValidQuery(params): ValidQuery<CombinedOhlcvQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let base_symbol = params.base.to_uppercase();
+    let quotes = quote_group(&params.quote_group).unwrap_or_default();
 
-    // Query ClickHouse for OHLCV data
-    match state.clickhouse.get_ohlcv(base_symbol, quote_symbol, &interval).await {
-        Ok(ohlcv_data) => Ok(Json(json!(ohlcv_data))),
-        Err(e) => Err(axum::response::Json(json!({
-            "error": "Failed to query OHLCV data",
-            "message": format!("{}", e)
-        })))
+    match state.clickhouse.get_combined_ohlcv(&base_symbol, quotes, params.interval.as_str(), state.query_limits.max_candles).await {
+        Ok(candles) => Ok(Json(json!({
+            "base": base_symbol,
+            "quoteGroup": params.quote_group.to_lowercase(),
+            "quotes": quotes,
+            "interval": params.interval.as_str(),
+            "candles": candles,
+        }))),
+        Err(e) => Err(query_failed("Failed to query combined OHLCV data", &e))
     }
 }
 
@@ -85,4 +600,142 @@ pub fn routes() -> Router<std::sync::Arc<AppState>> {
     Router::new()
         .route("/trades", get(get_trades))
         .route("/ohlcv", get(get_ohlcv))
+        .route("/ohlcv/combined", get(get_combined_ohlcv))
+        .route("/stats", get(get_stats))
+        .route("/midprice/history", get(get_midprice_history))
+        .route("/execution-quality", get(get_execution_quality))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::FromRequestParts;
+    use axum::http::Request;
+    use serde::de::DeserializeOwned;
+    use serde_json::Value;
+
+    /// Extract `T` from `/?{query}` as a handler would; a rejection must be a 422, whose body is returned
+    async fn extract<T: DeserializeOwned + Validate>(query: &str) -> Result<T, Value> {
+        let (mut parts, _) = Request::builder().uri(format!("/?{}", query)).body(()).unwrap().into_parts();
+        match ValidQuery::<T>::from_request_parts(&mut parts, &()).await {
+            Ok(ValidQuery(value)) => Ok(value),
+            Err(response) => {
+                assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                Err(serde_json::from_slice(&body).unwrap())
+            }
+        }
+    }
+
+    /// The rejected fields of a 422 body, checking its shape on the way
+    fn rejected(body: Value) -> Vec<String> {
+        assert_eq!(body["error"], "Invalid request");
+        let fields = body["fields"].as_array().expect("fields array");
+        let message: Vec<String> = fields
+            .iter()
+            .map(|f| format!("{}: {}", f["field"].as_str().unwrap(), f["message"].as_str().unwrap()))
+            .collect();
+        assert_eq!(body["message"], message.join("; "));
+        fields.iter().map(|f| f["field"].as_str().unwrap().to_string()).collect()
+    }
+
+    async fn rejected_fields<T: DeserializeOwned + Validate + std::fmt::Debug>(query: &str) -> Vec<String> {
+        rejected(extract::<T>(query).await.expect_err(query))
+    }
+
+    #[tokio::test]
+    async fn trades_query_defaults_and_canonicalizes_the_pair() {
+        let query = extract::<TradesQuery>("").await.unwrap();
+        assert_eq!(query.pair.to_string(), "SOL/USDC");
+        assert_eq!(query.limit, 100);
+        assert!(!query.paged());
+
+        let query = extract::<TradesQuery>("pair=wsol-usdc&limit=5&page_size=2&before=1700000000000").await.unwrap();
+        assert_eq!(query.pair.to_string(), "SOL/USDC");
+        assert_eq!(query.limit, 5);
+        assert!(query.paged());
+    }
+
+    #[tokio::test]
+    async fn pairs_that_are_not_base_quote_are_rejected() {
+        for pair in ["SOL", "SOL/USDC/USDT", "SOL/", "S%20L/USDC", "SOL/ABCDEFGHIJKLMNOPQ"] {
+            assert_eq!(rejected_fields::<TradesQuery>(&format!("pair={}", pair)).await, ["pair"], "{}", pair);
+        }
+        let body = extract::<TradesQuery>("pair=SOL").await.unwrap_err();
+        assert!(body["fields"][0]["message"].as_str().unwrap().contains("must be BASE/QUOTE"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn trade_limits_must_be_positive_numbers() {
+        assert_eq!(rejected_fields::<TradesQuery>("limit=0").await, ["limit"]);
+        assert_eq!(rejected_fields::<TradesQuery>("limit=-1").await, ["limit"]);
+        assert_eq!(rejected_fields::<TradesQuery>("limit=ten").await, ["limit"]);
+        assert_eq!(rejected_fields::<TradesQuery>("page_size=0").await, ["page_size"]);
+    }
+
+    #[tokio::test]
+    async fn slot_ranges_and_cursors_are_checked_together() {
+        assert_eq!(rejected_fields::<TradesQuery>("fromSlot=10&toSlot=9").await, ["fromSlot"]);
+        assert!(extract::<TradesQuery>("fromSlot=10&toSlot=10").await.is_ok());
+        assert_eq!(rejected_fields::<TradesQuery>("before=abc&fromSlot=1").await, ["before"]);
+        assert_eq!(rejected_fields::<TradesQuery>("after=abc&toSlot=1").await, ["before"]);
+        // Every failing field is reported
+        assert_eq!(rejected_fields::<TradesQuery>("limit=0&page_size=0&after=x&fromSlot=1").await, ["limit", "before", "page_size"]);
+    }
+
+    #[tokio::test]
+    async fn intervals_are_limited_to_the_candle_sizes() {
+        for interval in ["1m", "5m", "15m", "1h", "4h", "1d"] {
+            let query = extract::<OhlcvQuery>(&format!("interval={}", interval)).await.unwrap();
+            assert_eq!(query.interval.as_str(), interval);
+        }
+        assert_eq!(extract::<OhlcvQuery>("").await.unwrap().interval.as_str(), "1m");
+        for interval in ["2m", "1H", "", "1w"] {
+            assert_eq!(rejected_fields::<OhlcvQuery>(&format!("interval={}", interval)).await, ["interval"], "{}", interval);
+        }
+        assert_eq!(rejected_fields::<CombinedOhlcvQuery>("base=SOL&quoteGroup=stables&interval=2m").await, ["interval"]);
+    }
+
+    #[tokio::test]
+    async fn ohlcv_ranges_must_be_increasing() {
+        assert!(extract::<OhlcvQuery>("from=100&to=200").await.is_ok());
+        assert_eq!(rejected_fields::<OhlcvQuery>("from=200&to=200").await, ["from"]);
+        // Without `to`, `from` must be in the past
+        assert_eq!(rejected_fields::<OhlcvQuery>("from=99999999999").await, ["from"]);
+    }
+
+    #[tokio::test]
+    async fn combined_ohlcv_needs_a_supported_base_outside_a_known_quote_group() {
+        assert!(extract::<CombinedOhlcvQuery>("base=SOL&quoteGroup=stables").await.is_ok());
+        assert_eq!(rejected_fields::<CombinedOhlcvQuery>("base=NOPE&quoteGroup=stables").await, ["base"]);
+        assert_eq!(rejected_fields::<CombinedOhlcvQuery>("base=USDC&quoteGroup=stables").await, ["base"]);
+        assert_eq!(rejected_fields::<CombinedOhlcvQuery>("base=SOL&quoteGroup=majors").await, ["quoteGroup"]);
+        // A missing required parameter doesn't name a field
+        assert_eq!(rejected_fields::<CombinedOhlcvQuery>("quoteGroup=stables").await, ["query"]);
+    }
+
+    #[tokio::test]
+    async fn stats_as_of_must_be_a_time() {
+        assert!(extract::<StatsQuery>("asOf=2024-01-01T00:00:00Z").await.is_ok());
+        assert_eq!(rejected_fields::<StatsQuery>("asOf=yesterday").await, ["asOf"]);
+    }
+
+    #[tokio::test]
+    async fn midprice_history_limits_and_ranges_are_bounded() {
+        assert_eq!(extract::<MidpriceHistoryQuery>("").await.unwrap().limit, 1000);
+        assert!(extract::<MidpriceHistoryQuery>(&format!("limit={}", MAX_MIDPRICE_POINTS)).await.is_ok());
+        assert_eq!(rejected_fields::<MidpriceHistoryQuery>("limit=0").await, ["limit"]);
+        assert_eq!(rejected_fields::<MidpriceHistoryQuery>(&format!("limit={}", MAX_MIDPRICE_POINTS + 1)).await, ["limit"]);
+        assert_eq!(rejected_fields::<MidpriceHistoryQuery>("from=20&to=10").await, ["from"]);
+    }
+
+    #[tokio::test]
+    async fn execution_quality_hours_are_bounded() {
+        let query = extract::<ExecutionQualityQuery>("").await.unwrap();
+        assert_eq!((query.pair.is_none(), query.hours), (true, 24));
+        assert!(extract::<ExecutionQualityQuery>(&format!("hours={}", MAX_EXECUTION_QUALITY_HOURS)).await.is_ok());
+        assert_eq!(rejected_fields::<ExecutionQualityQuery>("hours=0").await, ["hours"]);
+        assert_eq!(rejected_fields::<ExecutionQualityQuery>(&format!("hours={}", MAX_EXECUTION_QUALITY_HOURS + 1)).await, ["hours"]);
+        assert_eq!(rejected_fields::<ExecutionQualityQuery>("pair=nope").await, ["pair"]);
+    }
 }
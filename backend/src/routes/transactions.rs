@@ -0,0 +1,80 @@
+// Transaction routes module
+
+use axum::{routing::post, Router, Json, extract::State, http::StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use crate::middleware::auth::AuthUser;
+use crate::services::submission::MAX_TRANSACTION_BASE64_LEN;
+use crate::state::AppState;
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub struct SubmitRequest {
+    /// Signed transaction, base64-encoded
+    pub transaction: String,
+}
+
+#[derive(Deserialize)]
+pub struct SimulateRequest {
+    /// Transaction to dry-run, base64-encoded (signatures are not verified)
+    pub transaction: String,
+}
+
+/// Simulate a transaction before the user signs it: balance changes, compute units, failure reason
+async fn simulate(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(payload): Json<SimulateRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if payload.transaction.is_empty() || payload.transaction.len() > MAX_TRANSACTION_BASE64_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid transaction",
+            "message": "transaction must be a base64-encoded transaction"
+        }))));
+    }
+
+    match state.simulations.simulate(&auth.wallet, &payload.transaction).await {
+        Ok(result) => Ok(Json(json!(result))),
+        Err(e) => {
+            eprintln!("❌ Transaction simulation failed: {}", e);
+            Err((StatusCode::BAD_GATEWAY, Json(json!({
+                "error": "Simulation failed",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+/// Relay a user-signed transaction; confirmation updates arrive as `tx_status` WS messages
+async fn submit(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(payload): Json<SubmitRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if payload.transaction.is_empty() || payload.transaction.len() > MAX_TRANSACTION_BASE64_LEN {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid transaction",
+            "message": "transaction must be a base64-encoded signed transaction"
+        }))));
+    }
+
+    match state.submissions.submit(auth.wallet, &payload.transaction).await {
+        Ok(signature) => Ok(Json(json!({
+            "signature": signature,
+            "status": "submitted"
+        }))),
+        Err(e) => {
+            eprintln!("❌ Transaction submission failed: {}", e);
+            Err((StatusCode::BAD_GATEWAY, Json(json!({
+                "error": "Submission failed",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/simulate", post(simulate))
+        .route("/submit", post(submit))
+}
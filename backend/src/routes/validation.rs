@@ -0,0 +1,70 @@
+// Request validation module - typed query DTOs checked before a handler runs
+
+use axum::{Json, extract::{FromRequestParts, Query, rejection::QueryRejection}, http::{StatusCode, request::Parts}, response::{IntoResponse, Response}};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+
+/// One rejected query parameter
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+/// Checks a deserialized query DTO; every failing field is reported, not just the first
+pub trait Validate {
+    fn validate(&self) -> Vec<FieldError>;
+}
+
+/// `Query<T>` that also runs `T::validate`; malformed or invalid parameters are rejected with
+/// `422 {"error", "message", "fields": [{"field", "message"}]}`
+pub struct ValidQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| validation_failed(vec![deserialize_error(&rejection)]))?;
+        let errors = value.validate();
+        if !errors.is_empty() {
+            return Err(validation_failed(errors));
+        }
+        Ok(Self(value))
+    }
+}
+
+/// Turn a serde rejection ("Failed to deserialize query string: limit: invalid digit ...")
+/// into a field error, falling back to `query` when no field is named
+fn deserialize_error(rejection: &QueryRejection) -> FieldError {
+    let text = rejection.body_text();
+    let detail = text.split_once(": ").map(|(_, rest)| rest).unwrap_or(&text);
+    match detail.split_once(": ") {
+        Some((field, message)) if !field.is_empty() && !field.contains(' ') && field != "." => FieldError::new(field, message),
+        _ => FieldError::new("query", detail),
+    }
+}
+
+pub fn validation_failed(errors: Vec<FieldError>) -> Response {
+    let message = errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ");
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+        "error": "Invalid request",
+        "message": message,
+        "fields": errors
+    }))).into_response()
+}
@@ -0,0 +1,32 @@
+// Venue routes module
+
+use axum::{routing::get, Router, Json, extract::State};
+use serde_json::json;
+use crate::state::AppState;
+use std::sync::Arc;
+
+/// Per-venue latency of this service's data: block time to ingest and confirmed to finalized,
+/// over each venue's most recent trades
+async fn get_venue_latency(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    Json(json!({ "venues": state.venue_latency.report().await }))
+}
+
+/// QuickNode stream health: connection, last message, reconnect backoff, and the subscription
+/// state of each DEX program
+async fn get_stream_health(
+    State(state): State<Arc<AppState>>,
+) -> Json<serde_json::Value> {
+    let programs = state.dex_programs.list().await;
+    let mut report = state.stream_health.report(&programs).await;
+    // API-only processes never connect, so this explains a stream that stays down
+    report["startup_mode"] = state.config.startup_mode.as_str().into();
+    Json(report)
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/venues/latency", get(get_venue_latency))
+        .route("/health/stream", get(get_stream_health))
+}
@@ -0,0 +1,19 @@
+// Version routes module
+
+use axum::{routing::get, Router, Json, extract::State};
+use crate::state::AppState;
+use crate::utils::build_info;
+use std::sync::Arc;
+
+/// Build info plus the runtime feature flags, so bug reports name the exact build and clients can
+/// gate on what this deployment offers
+async fn get_version(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let mut info = build_info::report();
+    info["features"] = serde_json::json!(state.features.list().await);
+    Json(info)
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/version", get(get_version))
+}
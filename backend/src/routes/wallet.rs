@@ -0,0 +1,326 @@
+// Wallet routes module
+
+use axum::{routing::{delete, get, put}, Router, Json, extract::{Path, Query, State}, http::StatusCode, response::{IntoResponse, Response}};
+use serde::Deserialize;
+use serde_json::json;
+use crate::middleware::auth::AuthUser;
+use crate::routes::trades::query_failed;
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::pair_mapping::{symbol_to_mint, mint_to_symbol};
+use crate::services::wallet_performance;
+use crate::state::AppState;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn is_wallet_address(pubkey: &str) -> bool {
+    bs58::decode(pubkey).into_vec().map(|bytes| bytes.len() == 32).unwrap_or(false)
+}
+
+/// Get SPL balances (with USD valuation) for the authenticated wallet
+async fn get_balances(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(pubkey): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if auth.wallet != pubkey {
+        return Err((StatusCode::FORBIDDEN, Json(json!({
+            "error": "Forbidden",
+            "message": "Balances can only be read for the authenticated wallet"
+        }))));
+    }
+
+    let refresh = params.get("refresh").map(|v| v == "true" || v == "1").unwrap_or(false);
+    match state.portfolio.get_balances(&pubkey, refresh).await {
+        Ok(balances) => Ok(Json(json!(balances))),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch balances for {}: {}", pubkey, e);
+            Err((StatusCode::BAD_GATEWAY, Json(json!({
+                "error": "Failed to fetch balances",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+/// Get the most recent daily digest for the authenticated wallet
+async fn get_latest_digest(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(pubkey): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if auth.wallet != pubkey {
+        return Err((StatusCode::FORBIDDEN, Json(json!({
+            "error": "Forbidden",
+            "message": "Digests can only be read for the authenticated wallet"
+        }))));
+    }
+
+    match state.clickhouse.latest_digest(&pubkey, None).await {
+        Ok(Some(digest)) => Ok(Json(serde_json::from_str(&digest.body).unwrap_or_default())),
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json(json!({
+            "error": "Not found",
+            "message": "No digest has been generated for this wallet yet"
+        })))),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch digest for {}: {}", pubkey, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+                "error": "Failed to fetch digest",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+/// Most trades returned by one /api/wallet/{pubkey}/trades request
+const MAX_WALLET_TRADES: usize = 1000;
+
+/// Query parameters of /api/wallet/{pubkey}/trades
+#[derive(Debug, Deserialize)]
+struct WalletTradesQuery {
+    #[serde(default = "default_wallet_trades_limit")]
+    limit: usize,
+}
+
+fn default_wallet_trades_limit() -> usize {
+    100
+}
+
+impl Validate for WalletTradesQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        if (1..=MAX_WALLET_TRADES).contains(&self.limit) {
+            Vec::new()
+        } else {
+            vec![FieldError::new("limit", format!("limit must be between 1 and {}", MAX_WALLET_TRADES))]
+        }
+    }
+}
+
+/// Get a wallet's most recent swaps across all pairs (public on-chain data, no auth)
+/// Served from the trades_by_trader owner index rather than a scan of the trades table
+async fn get_wallet_trades(
+    State(state): State<Arc<AppState>>,
+    Path(pubkey): Path<String>,
+    ValidQuery(params): ValidQuery<WalletTradesQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    if !is_wallet_address(&pubkey) {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid wallet",
+            "message": format!("'{}' is not a valid wallet address", pubkey)
+        }))).into_response());
+    }
+
+    match state.clickhouse.get_wallet_trades(&pubkey, params.limit).await {
+        Ok(trades) => Ok(Json(json!(trades))),
+        Err(e) => {
+            eprintln!("❌ Failed to fetch trades for {}: {}", pubkey, e);
+            Err(query_failed("Failed to query wallet trades", &e))
+        }
+    }
+}
+
+/// Most fills replayed by one /api/wallet/{pubkey}/performance request
+const MAX_PERFORMANCE_TRADES: usize = 10_000;
+
+/// Query parameters of /api/wallet/{pubkey}/performance
+#[derive(Debug, Deserialize)]
+struct PerformanceQuery {
+    #[serde(default = "default_performance_limit")]
+    limit: usize,
+}
+
+fn default_performance_limit() -> usize {
+    1_000
+}
+
+impl Validate for PerformanceQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        if (1..=MAX_PERFORMANCE_TRADES).contains(&self.limit) {
+            Vec::new()
+        } else {
+            vec![FieldError::new("limit", format!("limit must be between 1 and {}", MAX_PERFORMANCE_TRADES))]
+        }
+    }
+}
+
+/// Realized PnL, equity curve, max drawdown, win rate and average trade size reconstructed from a
+/// wallet's most recent fills (public on-chain data, no auth)
+async fn get_wallet_performance(
+    State(state): State<Arc<AppState>>,
+    Path(pubkey): Path<String>,
+    ValidQuery(params): ValidQuery<PerformanceQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let valid_wallet = bs58::decode(&pubkey).into_vec().map(|bytes| bytes.len() == 32).unwrap_or(false);
+    if !valid_wallet {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid wallet",
+            "message": format!("'{}' is not a valid wallet address", pubkey)
+        }))).into_response());
+    }
+
+    let trades = state.clickhouse.get_wallet_trades(&pubkey, params.limit).await.map_err(|e| {
+        eprintln!("❌ Failed to fetch trades for {}: {}", pubkey, e);
+        query_failed("Failed to query wallet trades", &e)
+    })?;
+
+    // PnL is realized in each pair's quote token and converted at its current USD price
+    // (both symbols are priced since fills are stored in whichever orientation the swap went)
+    let mut mints: Vec<String> = trades
+        .iter()
+        .flat_map(|t| [&t.base_symbol, &t.quote_symbol])
+        .filter_map(|symbol| symbol_to_mint(symbol))
+        .collect();
+    mints.sort();
+    mints.dedup();
+    let usd_prices: HashMap<String, f64> = state.portfolio
+        .usd_prices(&mints)
+        .await
+        .into_iter()
+        .filter_map(|(mint, price)| Some((mint_to_symbol(&mint)?, price)))
+        .collect();
+
+    let mut performance = json!(wallet_performance::compute(&pubkey, trades, &usd_prices));
+    performance["history_limit"] = json!(params.limit);
+    Ok(Json(performance))
+}
+
+/// Erase the authenticated wallet's stored data: sessions (which signs it out everywhere), settings,
+/// digests and swap quotes are deleted; its fills and platform fee records stay but lose the wallet
+async fn delete_wallet_data(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(pubkey): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if auth.wallet != pubkey {
+        return Err((StatusCode::FORBIDDEN, Json(json!({
+            "error": "Forbidden",
+            "message": "Data can only be deleted for the authenticated wallet"
+        }))));
+    }
+
+    match state.clickhouse.delete_wallet_data(&pubkey).await {
+        Ok(mut report) => {
+            state.portfolio.invalidate(&pubkey).await;
+            state.follows.forget(&pubkey).await;
+            println!("🗑️  Deleted stored data for wallet {}…", &pubkey[..8.min(pubkey.len())]);
+            if let Some(obj) = report.as_object_mut() {
+                obj.insert("wallet".to_string(), json!(pubkey));
+            }
+            Ok(Json(report))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to delete wallet data: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+                "error": "Failed to delete wallet data",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+/// List the wallets the authenticated wallet follows
+async fn get_follows(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(pubkey): Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if auth.wallet != pubkey {
+        return Err((StatusCode::FORBIDDEN, Json(json!({
+            "error": "Forbidden",
+            "message": "Follows can only be read for the authenticated wallet"
+        }))));
+    }
+    Ok(Json(json!({
+        "wallet": pubkey,
+        "follows": state.follows.following(&pubkey).await,
+        "max_follows": state.follows.max_per_wallet,
+    })))
+}
+
+/// Body of PUT /api/wallet/{pubkey}/follows/{wallet}
+#[derive(Debug, Deserialize)]
+struct FollowBody {
+    /// Also deliver the followed wallet's trades to the notification webhook
+    #[serde(default)]
+    notify: bool,
+}
+
+/// Follow a wallet: its parsed trades are pushed to the follower's connections as `followed_wallet_trade`
+/// Following an already followed wallet updates `notify`
+async fn put_follow(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((pubkey, followed)): Path<(String, String)>,
+    Json(body): Json<FollowBody>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if auth.wallet != pubkey {
+        return Err((StatusCode::FORBIDDEN, Json(json!({
+            "error": "Forbidden",
+            "message": "Follows can only be changed for the authenticated wallet"
+        }))));
+    }
+    if !is_wallet_address(&followed) || followed == pubkey {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({
+            "error": "Invalid wallet",
+            "message": format!("'{}' is not a wallet that can be followed", followed)
+        }))));
+    }
+
+    let following = state.follows.following(&pubkey).await;
+    if following.len() >= state.follows.max_per_wallet && !following.iter().any(|f| f.wallet == followed) {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, Json(json!({
+            "error": "Too many follows",
+            "message": format!("A wallet can follow at most {} wallets", state.follows.max_per_wallet)
+        }))));
+    }
+
+    match state.follows.follow(&pubkey, &followed, body.notify).await {
+        Ok(follow) => Ok(Json(json!(follow))),
+        Err(e) => {
+            eprintln!("❌ Failed to store follow of {} by {}: {}", followed, pubkey, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+                "error": "Failed to follow wallet",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+/// Stop following a wallet
+async fn delete_follow(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((pubkey, followed)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    if auth.wallet != pubkey {
+        return Err((StatusCode::FORBIDDEN, Json(json!({
+            "error": "Forbidden",
+            "message": "Follows can only be changed for the authenticated wallet"
+        }))));
+    }
+    match state.follows.unfollow(&pubkey, &followed).await {
+        Ok(true) => Ok(Json(json!({ "wallet": followed, "unfollowed": true }))),
+        Ok(false) => Err((StatusCode::NOT_FOUND, Json(json!({
+            "error": "Not found",
+            "message": format!("{} is not followed", followed)
+        })))),
+        Err(e) => {
+            eprintln!("❌ Failed to store unfollow of {} by {}: {}", followed, pubkey, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+                "error": "Failed to unfollow wallet",
+                "message": format!("{}", e)
+            }))))
+        }
+    }
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/wallet/{pubkey}/balances", get(get_balances))
+        .route("/wallet/{pubkey}/trades", get(get_wallet_trades))
+        .route("/wallet/{pubkey}/performance", get(get_wallet_performance))
+        .route("/wallet/{pubkey}/digest", get(get_latest_digest))
+        .route("/wallet/{pubkey}/data", delete(delete_wallet_data))
+        .route("/wallet/{pubkey}/follows", get(get_follows))
+        .route("/wallet/{pubkey}/follows/{wallet}", put(put_follow).delete(delete_follow))
+}
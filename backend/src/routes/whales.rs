@@ -0,0 +1,80 @@
+// Whale trade routes module
+
+use axum::{routing::get, Router, Json, extract::State, response::Response};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use crate::routes::trades::query_failed;
+use crate::routes::validation::{FieldError, ValidQuery, Validate};
+use crate::services::clickhouse::time_to_chrono;
+use crate::services::pair_mapping::Pair;
+use crate::state::AppState;
+use std::sync::Arc;
+
+const MAX_WHALES: usize = 500;
+
+/// Query parameters of /api/whales
+#[derive(Debug, Deserialize)]
+struct WhalesQuery {
+    pair: Option<Pair>,
+    /// Only trades worth at least this much (USD); below WHALE_ALERT_MIN_USD nothing more is stored
+    min_usd: Option<f64>,
+    /// Paging: trades strictly older than this RFC 3339 timestamp (the last `timestamp` returned)
+    before: Option<DateTime<Utc>>,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+impl Validate for WhalesQuery {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if let Some(min_usd) = self.min_usd
+            && !(min_usd.is_finite() && min_usd >= 0.0)
+        {
+            errors.push(FieldError::new("min_usd", "min_usd must be a non-negative number"));
+        }
+        if !(1..=MAX_WHALES).contains(&self.limit) {
+            errors.push(FieldError::new("limit", format!("limit must be between 1 and {}", MAX_WHALES)));
+        }
+        errors
+    }
+}
+
+/// Whale trades, newest first, as stored when their `whale_alert` was sent on the `whales` WS channel
+async fn get_whales(
+    State(state): State<Arc<AppState>>,
+    ValidQuery(params): ValidQuery<WhalesQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let pair = params.pair.as_ref().map(|p| (p.base.as_str(), p.quote.as_str()));
+    let rows = state.clickhouse
+        .get_whale_trades(pair, params.min_usd, params.before, params.limit)
+        .await
+        .map_err(|e| query_failed("Failed to query whale trades", &e))?;
+
+    let whales: Vec<serde_json::Value> = rows
+        .into_iter()
+        .map(|row| json!({
+            "id": row.id,
+            "timestamp": time_to_chrono(row.timestamp).to_rfc3339(),
+            "pair": format!("{}/{}", row.base_symbol, row.quote_symbol),
+            "side": row.side,
+            "price": row.price,
+            "amount": row.amount,
+            "total_value": row.total_value,
+            "usd_value": row.usd_value,
+            "dex_program": row.dex_program,
+            "slot": row.slot,
+            "trader": Some(row.trader).filter(|t| !t.is_empty()),
+        }))
+        .collect();
+    Ok(Json(json!({ "whales": whales })))
+}
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/whales", get(get_whales))
+}
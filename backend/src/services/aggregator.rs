@@ -0,0 +1,318 @@
+// Incremental 24h stats per pair, fed by the trade stream and seeded from ClickHouse on first use
+
+use crate::models::trade::Trade;
+use crate::services::clickhouse::{ClickHouseService, MarketSummaryRow, PairStatsRow};
+use crate::services::memory_budget::{Footprint, MemoryBudget, PairLru};
+use crate::services::pair_mapping::{parse_pair, Pair, supported_pairs};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use futures_util::future::join_all;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Length of the rolling window, matching the 24h ClickHouse queries
+const WINDOW_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// Width of one bucket of the window
+const BUCKET_MILLIS: i64 = 60 * 1000;
+
+/// Running totals of the trades of one minute
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct MinuteBucket {
+    /// Start of the minute, in minutes since the epoch
+    minute: i64,
+    first_price: f64,
+    last_price: f64,
+    high: f64,
+    low: f64,
+    volume: f64,
+    trades: u64,
+}
+
+/// One stored (base, quote) direction's last 24h as per-minute running totals, oldest minute first
+/// Its size is bounded by the number of minutes, however many trades a hot pair has; the window's
+/// start moves a minute at a time, so it covers up to a minute more than 24h
+#[derive(Default)]
+struct RollingWindow {
+    buckets: VecDeque<MinuteBucket>,
+}
+
+impl RollingWindow {
+    /// Add a trade to its minute; within a minute, first and last follow arrival order
+    fn push(&mut self, timestamp_ms: i64, price: f64, amount: f64) {
+        let minute = timestamp_ms.div_euclid(BUCKET_MILLIS);
+        let position = self.buckets.partition_point(|bucket| bucket.minute < minute);
+        match self.buckets.get_mut(position) {
+            Some(bucket) if bucket.minute == minute => {
+                bucket.last_price = price;
+                bucket.high = bucket.high.max(price);
+                bucket.low = bucket.low.min(price);
+                bucket.volume += price * amount;
+                bucket.trades += 1;
+            }
+            _ => self.buckets.insert(position, MinuteBucket {
+                minute,
+                first_price: price,
+                last_price: price,
+                high: price,
+                low: price,
+                volume: price * amount,
+                trades: 1,
+            }),
+        }
+    }
+
+    /// Drop the minutes that end at or before `cutoff_ms`
+    fn evict(&mut self, cutoff_ms: i64) {
+        while self.buckets.front().is_some_and(|bucket| (bucket.minute + 1) * BUCKET_MILLIS <= cutoff_ms) {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn trades(&self) -> u64 {
+        self.buckets.iter().map(|bucket| bucket.trades).sum()
+    }
+
+    fn stats(&self) -> PairStatsRow {
+        let (Some(first), Some(last)) = (self.buckets.front(), self.buckets.back()) else {
+            return PairStatsRow::default();
+        };
+        PairStatsRow {
+            last_price: last.last_price,
+            first_price: first.first_price,
+            high_24h: self.buckets.iter().map(|bucket| bucket.high).fold(f64::MIN, f64::max),
+            low_24h: self.buckets.iter().map(|bucket| bucket.low).fold(f64::MAX, f64::min),
+            volume_24h: self.buckets.iter().map(|bucket| bucket.volume).sum::<f64>().max(0.0),
+            trades_24h: self.trades(),
+        }
+    }
+}
+
+enum PairState {
+    /// Seed query in flight; live trades (with their ids, to drop ones the seed already read) wait here
+    Seeding(Vec<(String, i64, f64, f64)>),
+    Warm(RollingWindow),
+    /// The window outgrew the per-pair memory budget (only with a budget below a full day of
+    /// minutes); stats come from ClickHouse until the direction is evicted as cold or invalidated
+    OverBudget,
+}
+
+impl PairState {
+    /// Window stats as of now; None while seeding or over budget
+    fn current_stats(&mut self) -> Option<PairStatsRow> {
+        match self {
+            PairState::Warm(window) => {
+                window.evict(Utc::now().timestamp_millis() - WINDOW_MILLIS);
+                Some(window.stats())
+            }
+            PairState::Seeding(_) | PairState::OverBudget => None,
+        }
+    }
+}
+
+impl Footprint for PairState {
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + match self {
+                PairState::Seeding(pending) => pending
+                    .iter()
+                    .map(|(id, ..)| std::mem::size_of::<(String, i64, f64, f64)>() + id.capacity())
+                    .sum(),
+                PairState::Warm(window) => window.buckets.capacity() * std::mem::size_of::<MinuteBucket>(),
+                PairState::OverBudget => 0,
+            }
+    }
+
+    fn shed(&mut self) {
+        *self = PairState::OverBudget;
+    }
+}
+
+type PairKey = (String, String);
+
+pub struct TradeAggregator {
+    clickhouse: Arc<ClickHouseService>,
+    pairs: Arc<RwLock<PairLru<PairKey, PairState>>>,
+}
+
+impl TradeAggregator {
+    pub fn new(clickhouse: Arc<ClickHouseService>, memory_budget: Arc<MemoryBudget>) -> Self {
+        Self {
+            clickhouse,
+            pairs: Arc::new(RwLock::new(PairLru::new("aggregator", memory_budget))),
+        }
+    }
+
+    /// Seed both directions of every supported pair, so reads right after a restart don't wait on ClickHouse
+    pub async fn warm_up(self: Arc<Self>) {
+        let directions: Vec<PairKey> = supported_pairs()
+            .iter()
+            .filter_map(|pair| parse_pair(pair))
+            .flat_map(|Pair { base, quote }| [(base.clone(), quote.clone()), (quote, base)])
+            .collect();
+
+        let results = join_all(directions.iter().map(|(base, quote)| self.window_stats(base, quote))).await;
+        let mut warmed = 0;
+        for ((base, quote), result) in directions.iter().zip(results) {
+            match result {
+                Ok(_) => warmed += 1,
+                Err(e) => eprintln!("⚠️  [Aggregator] Failed to warm up {}/{}: {}", base, quote, e),
+            }
+        }
+        println!("✅ [Aggregator] Warmed up {} of {} pair directions", warmed, directions.len());
+    }
+
+    /// Add a stored (unquarantined) trade to its direction's window
+    /// Directions nobody has asked about yet are skipped; their seed reads the trade from ClickHouse
+    pub async fn record(&self, trade: &Trade) {
+        let key = (trade.base_symbol.clone(), trade.quote_symbol.clone());
+        let timestamp_ms = trade.timestamp.timestamp_millis();
+        self.pairs.write().await.update(&key, |state| match state {
+            PairState::Warm(window) => {
+                window.push(timestamp_ms, trade.price, trade.amount);
+                window.evict(Utc::now().timestamp_millis() - WINDOW_MILLIS);
+            }
+            PairState::Seeding(pending) => {
+                pending.push((trade.id.clone(), timestamp_ms, trade.price, trade.amount));
+            }
+            PairState::OverBudget => {}
+        });
+    }
+
+    /// Forget a direction's window (e.g. after trades were flagged quarantined); the next read reseeds it
+    pub async fn invalidate(&self, base_symbol: &str, quote_symbol: &str) {
+        self.pairs.write().await.remove(&(base_symbol.to_string(), quote_symbol.to_string()));
+    }
+
+    /// 24h stats for a stored direction in the /api/stats shape
+    /// Served from memory; ClickHouse is only read to seed a cold direction (or while another read seeds it)
+    pub async fn stats(&self, base_symbol: &str, quote_symbol: &str) -> Result<serde_json::Value> {
+        match self.window_stats(base_symbol, quote_symbol).await? {
+            Some(stats) => Ok(stats.to_json()),
+            None => self.clickhouse.get_24h_stats(base_symbol, quote_symbol, None).await,
+        }
+    }
+
+    /// 24h summaries of both directions of every supported pair, like `get_market_summaries(None)`
+    pub async fn market_summaries(&self) -> Result<Vec<MarketSummaryRow>> {
+        let mut summaries = Vec::new();
+        for pair in supported_pairs() {
+            let Some(Pair { base, quote }) = parse_pair(&pair) else { continue };
+            for (base_symbol, quote_symbol) in [(&base, &quote), (&quote, &base)] {
+                let Some(stats) = self.window_stats(base_symbol, quote_symbol).await? else {
+                    // Still seeding elsewhere or over budget: answer this round from ClickHouse
+                    return self.clickhouse.get_market_summaries(None).await;
+                };
+                // ClickHouse only returns directions that traded
+                if stats.trades_24h > 0 {
+                    summaries.push(MarketSummaryRow {
+                        base_symbol: base_symbol.clone(),
+                        quote_symbol: quote_symbol.clone(),
+                        last_price: stats.last_price,
+                        first_price: stats.first_price,
+                        volume: stats.volume_24h,
+                    });
+                }
+            }
+        }
+        Ok(summaries)
+    }
+
+    /// Current window stats, seeding the direction first if it's cold
+    /// None while another caller's seed is in flight, or when the direction is over its memory budget
+    async fn window_stats(&self, base_symbol: &str, quote_symbol: &str) -> Result<Option<PairStatsRow>> {
+        let key = (base_symbol.to_string(), quote_symbol.to_string());
+        {
+            let mut pairs = self.pairs.write().await;
+            match pairs.update(&key, PairState::current_stats) {
+                Some(stats) => return Ok(stats),
+                None => pairs.insert(key.clone(), PairState::Seeding(Vec::new())),
+            }
+        }
+
+        // Seeded in a task so a dropped request can't leave the direction stuck in Seeding
+        let seed = tokio::spawn(Self::seed(self.clickhouse.clone(), self.pairs.clone(), key));
+        seed.await.map_err(|e| anyhow!("Aggregate seed task failed: {}", e))?
+    }
+
+    async fn seed(
+        clickhouse: Arc<ClickHouseService>,
+        pairs: Arc<RwLock<PairLru<PairKey, PairState>>>,
+        key: PairKey,
+    ) -> Result<Option<PairStatsRow>> {
+        let rows = clickhouse.get_window_trades(&key.0, &key.1).await;
+
+        let mut pairs = pairs.write().await;
+        // Invalidated while the query ran: leave it cold
+        let Some(PairState::Seeding(pending)) = pairs.remove(&key) else {
+            return Ok(None);
+        };
+        let rows = rows?;
+
+        let seeded: HashSet<&str> = rows.iter().map(|row| row.id.as_str()).collect();
+        let mut window = RollingWindow::default();
+        for row in &rows {
+            let timestamp_ms = (row.timestamp.unix_timestamp_nanos() / 1_000_000) as i64;
+            window.push(timestamp_ms, row.price, row.amount);
+        }
+        for (id, timestamp_ms, price, amount) in pending {
+            if !seeded.contains(id.as_str()) {
+                window.push(timestamp_ms, price, amount);
+            }
+        }
+        window.evict(Utc::now().timestamp_millis() - WINDOW_MILLIS);
+        println!("📊 [Aggregator] Seeded {}/{} with {} trades", key.0, key.1, window.trades());
+
+        // A window already over budget is shed on insert; these stats are still good for this read
+        let stats = window.stats();
+        pairs.insert(key, PairState::Warm(window));
+        Ok(Some(stats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = BUCKET_MILLIS;
+
+    #[test]
+    fn trades_fold_into_minute_totals() {
+        let mut window = RollingWindow::default();
+        window.push(10 * MINUTE + 1_000, 100.0, 1.0);
+        window.push(10 * MINUTE + 2_000, 120.0, 2.0);
+        window.push(11 * MINUTE, 90.0, 1.0);
+        // A late trade lands in its own minute, not at the end
+        window.push(10 * MINUTE + 500, 80.0, 1.0);
+
+        assert_eq!(window.buckets.len(), 2);
+        let stats = window.stats();
+        assert_eq!((stats.first_price, stats.last_price), (100.0, 90.0));
+        assert_eq!((stats.high_24h, stats.low_24h), (120.0, 80.0));
+        assert_eq!((stats.volume_24h, stats.trades_24h), (100.0 + 240.0 + 90.0 + 80.0, 4));
+    }
+
+    #[test]
+    fn a_busy_pair_holds_one_bucket_per_minute() {
+        let mut window = RollingWindow::default();
+        for n in 0..100_000 {
+            window.push((n % 60) * MINUTE, 100.0, 1.0);
+        }
+        assert_eq!(window.buckets.len(), 60);
+        assert_eq!(window.stats().trades_24h, 100_000);
+    }
+
+    #[test]
+    fn minutes_leave_once_they_end_before_the_cutoff() {
+        let mut window = RollingWindow::default();
+        window.push(0, 100.0, 1.0);
+        window.push(MINUTE + 30_000, 110.0, 1.0);
+        window.evict(MINUTE - 1);
+        assert_eq!(window.buckets.len(), 2);
+        window.evict(MINUTE);
+        assert_eq!(window.stats().first_price, 110.0);
+        window.evict(2 * MINUTE);
+        assert_eq!(window.stats().trades_24h, 0);
+    }
+}
@@ -0,0 +1,33 @@
+// Auth lifetimes - how long session tokens, refresh tokens and sign-in nonces stay valid,
+// read with the rest of the Config, which holds them to these bounds
+
+use chrono::Duration;
+
+/// (env var, default, min, max) in seconds
+pub const TOKEN_TTL: (&str, i64, i64, i64) = ("JWT_EXPIRY_SECS", 86_400, 300, 7 * 86_400);
+pub const REFRESH_TTL: (&str, i64, i64, i64) = ("REFRESH_TOKEN_TTL_SECS", 30 * 86_400, 3_600, 90 * 86_400);
+pub const NONCE_TTL: (&str, i64, i64, i64) = ("NONCE_TTL_SECS", 300, 30, 3_600);
+
+#[derive(Debug, Clone, Copy)]
+pub struct AuthConfig {
+    /// Lifetime of session JWTs issued by /auth/verify
+    pub token_ttl: Duration,
+    /// How long after signing in a session can be kept alive with /auth/refresh; never shorter than `token_ttl`
+    pub refresh_ttl: Duration,
+    /// How long a nonce from /auth/nonce can be signed and verified
+    pub nonce_ttl: Duration,
+    /// Whether `offchain` challenges also accept a signature over the raw text
+    pub offchain_raw_fallback: bool,
+}
+
+impl AuthConfig {
+    /// Log the lifetimes once at startup
+    pub fn log(&self) {
+        println!(
+            "🔑 [Auth] Session tokens last {}s, refresh tokens {}s, nonces {}s",
+            self.token_ttl.num_seconds(),
+            self.refresh_ttl.num_seconds(),
+            self.nonce_ttl.num_seconds()
+        );
+    }
+}
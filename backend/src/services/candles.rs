@@ -0,0 +1,179 @@
+// Live candles - builds in-progress 1m/5m/1h candles from the trade stream and pushes
+// `candle_update` messages on the `candles` WS channel
+
+use crate::models::schema::CURRENT_SCHEMA_VERSION;
+use crate::models::trade::Trade;
+use crate::services::memory_budget::{Footprint, MemoryBudget, PairLru};
+use crate::websocket::messages::OutboundMessage;
+use crate::websocket::ConnectionManager;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration};
+
+/// Intervals built live, with their length in seconds
+const CANDLE_INTERVALS: &[(&str, i64)] = &[("1m", 60), ("5m", 300), ("1h", 3_600)];
+/// How often changed candles are pushed; a busy candle sends at most one update per tick
+const CANDLE_PUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// In-progress candle, built the way /api/ohlcv aggregates stored trades
+#[derive(Debug, Clone)]
+struct LiveCandle {
+    time: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    /// Quote volume, sum of amount * price
+    volume: f64,
+    trades: u64,
+    /// Sequences of the trades that set open and close
+    open_sequence: u64,
+    close_sequence: u64,
+    /// Changed since the last push
+    dirty: bool,
+}
+
+impl LiveCandle {
+    fn new(time: i64, trade: &Trade) -> Self {
+        Self {
+            time,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: trade.amount * trade.price,
+            trades: 1,
+            open_sequence: trade.sequence,
+            close_sequence: trade.sequence,
+            dirty: true,
+        }
+    }
+
+    fn add(&mut self, trade: &Trade) {
+        // Open and close follow ingest sequence, not arrival order
+        if trade.sequence < self.open_sequence {
+            self.open = trade.price;
+            self.open_sequence = trade.sequence;
+        }
+        if trade.sequence >= self.close_sequence {
+            self.close = trade.price;
+            self.close_sequence = trade.sequence;
+        }
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.volume += trade.amount * trade.price;
+        self.trades += 1;
+        self.dirty = true;
+    }
+
+    fn update(&self, pair: &str, interval: &str, closed: bool) -> serde_json::Value {
+        json!({
+            "type": "candle_update",
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "pair": pair,
+            "interval": interval,
+            "time": self.time,
+            "open": self.open,
+            "high": self.high,
+            "low": self.low,
+            "close": self.close,
+            "volume": self.volume,
+            "trades": self.trades,
+            "closed": closed,
+        })
+    }
+}
+
+/// A pair's candles in progress, by interval
+#[derive(Default)]
+struct PairCandles(HashMap<&'static str, LiveCandle>);
+
+impl Footprint for PairCandles {
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>() + self.0.capacity() * std::mem::size_of::<(&str, LiveCandle)>()
+    }
+}
+
+struct CandleBook {
+    /// Pair -> candles in progress
+    open: PairLru<String, PairCandles>,
+    /// Candles that ended since the last push, sent once more as final
+    closed: Vec<(String, &'static str, LiveCandle)>,
+}
+
+pub struct CandleAggregator {
+    ws_manager: Arc<ConnectionManager>,
+    book: Mutex<CandleBook>,
+}
+
+impl CandleAggregator {
+    pub fn new(ws_manager: Arc<ConnectionManager>, memory_budget: Arc<MemoryBudget>) -> Self {
+        Self {
+            ws_manager,
+            book: Mutex::new(CandleBook {
+                open: PairLru::new("candles", memory_budget),
+                closed: Vec::new(),
+            }),
+        }
+    }
+
+    /// Add a broadcast trade to its pair's candles (in the trade's stored orientation, like /api/ohlcv)
+    /// Trades for a candle that already closed are left to /api/ohlcv
+    pub async fn record(&self, trade: &Trade) {
+        let pair = format!("{}/{}", trade.base_symbol, trade.quote_symbol);
+        let timestamp = trade.timestamp.timestamp();
+        let mut book = self.book.lock().await;
+        let CandleBook { open, closed } = &mut *book;
+
+        open.upsert(pair.clone(), PairCandles::default, |PairCandles(candles)| {
+            for (interval, secs) in CANDLE_INTERVALS {
+                let time = timestamp - timestamp.rem_euclid(*secs);
+                match candles.get_mut(interval) {
+                    Some(candle) if candle.time == time => candle.add(trade),
+                    Some(candle) if candle.time > time => {}
+                    Some(candle) => {
+                        let ended = std::mem::replace(candle, LiveCandle::new(time, trade));
+                        closed.push((pair.clone(), *interval, ended));
+                    }
+                    None => {
+                        candles.insert(*interval, LiveCandle::new(time, trade));
+                    }
+                }
+            }
+        });
+    }
+
+    /// Push closed and changed candles every second
+    pub async fn start(self: Arc<Self>) {
+        let mut ticker = interval(CANDLE_PUSH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let updates: Vec<(String, serde_json::Value)> = {
+                let mut book = self.book.lock().await;
+                let mut updates: Vec<(String, serde_json::Value)> = book
+                    .closed
+                    .drain(..)
+                    .map(|(pair, interval, candle)| {
+                        let update = candle.update(&pair, interval, true);
+                        (pair, update)
+                    })
+                    .collect();
+                for (pair, PairCandles(candles)) in book.open.iter_mut() {
+                    for (interval, candle) in candles.iter_mut().filter(|(_, candle)| candle.dirty) {
+                        candle.dirty = false;
+                        updates.push((pair.clone(), candle.update(pair, interval, false)));
+                    }
+                }
+                updates
+            };
+            if updates.is_empty() || self.ws_manager.connection_count().await == 0 {
+                continue;
+            }
+            for (pair, update) in updates {
+                self.ws_manager.broadcast(OutboundMessage::new("candles", Some(pair), update.to_string())).await;
+            }
+        }
+    }
+}
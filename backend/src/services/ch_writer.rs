@@ -0,0 +1,137 @@
+// ClickHouse trade writer - buffers trades from the pipeline and inserts them in batches
+
+use crate::models::trade::Trade;
+use crate::services::clickhouse::ClickHouseService;
+use crate::utils::shutdown::{self, DrainToken};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+
+/// Wait before the first retry; doubled for each one after, up to MAX_RETRY_BACKOFF
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Trade waiting for the next batch insert
+struct PendingTrade {
+    trade: Trade,
+    quarantined: bool,
+}
+
+/// Handle to the background batching writer
+/// Trades queue in a bounded channel; when ClickHouse falls behind (or is down, while a batch is
+/// retried) the channel fills and `write` waits, slowing the pipeline instead of buffering without
+/// limit or dropping trades. On shutdown the queued trades are inserted before the process exits
+#[derive(Clone)]
+pub struct TradeWriter {
+    tx: mpsc::Sender<PendingTrade>,
+}
+
+impl TradeWriter {
+    /// Spawn the writer
+    /// `max_rows` and `max_age` (CLICKHOUSE_BATCH_ROWS and CLICKHOUSE_BATCH_MS) bound a batch's size and age
+    pub fn start(clickhouse: Arc<ClickHouseService>, max_rows: usize, max_age: Duration) -> Self {
+        println!("🗃️  [TradeWriter] Batching trade inserts: up to {} rows or {:?}", max_rows, max_age);
+
+        // Room for a few batches while one is being inserted (or retried)
+        let (tx, rx) = mpsc::channel(max_rows * 4);
+        tokio::spawn(run(rx, clickhouse, max_rows, max_age, shutdown::register()));
+        Self { tx }
+    }
+
+    /// Queue a trade for insertion; waits while the queue is full
+    pub async fn write(&self, trade: Trade) {
+        self.enqueue(trade, false).await;
+    }
+
+    /// Queue a trade flagged as quarantined (kept for forensics, excluded from reads)
+    pub async fn write_quarantined(&self, trade: Trade) {
+        self.enqueue(trade, true).await;
+    }
+
+    async fn enqueue(&self, trade: Trade, quarantined: bool) {
+        if self.tx.send(PendingTrade { trade, quarantined }).await.is_err() {
+            eprintln!("❌ [TradeWriter] Writer stopped; trade not stored");
+        }
+    }
+}
+
+async fn run(
+    mut rx: mpsc::Receiver<PendingTrade>,
+    clickhouse: Arc<ClickHouseService>,
+    max_rows: usize,
+    max_age: Duration,
+    mut drain: DrainToken,
+) {
+    let mut batch: Vec<PendingTrade> = Vec::with_capacity(max_rows);
+    // The age limit counts from the first trade in the batch
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let due = async move {
+            match deadline {
+                Some(at) => sleep_until(at).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            received = rx.recv() => match received {
+                Some(pending) => {
+                    deadline.get_or_insert_with(|| Instant::now() + max_age);
+                    batch.push(pending);
+                    if batch.len() < max_rows {
+                        continue;
+                    }
+                }
+                None => {
+                    // Every sender is gone; store what's left and stop
+                    flush(&clickhouse, &batch).await;
+                    return;
+                }
+            },
+            _ = drain.requested() => {
+                // Take what is already queued, then store it all; later writes are refused
+                rx.close();
+                while let Some(pending) = rx.recv().await {
+                    batch.push(pending);
+                }
+                println!("🗃️  [TradeWriter] Shutting down; inserting {} queued trades", batch.len());
+                for chunk in batch.chunks(max_rows.max(1)) {
+                    flush(&clickhouse, chunk).await;
+                }
+                return;
+            }
+            _ = due => {}
+        }
+
+        flush(&clickhouse, &batch).await;
+        batch.clear();
+        deadline = None;
+    }
+}
+
+/// Insert the batch, retrying with a capped backoff until it is stored
+/// The pipeline waits on the full queue meanwhile, so an outage slows ingestion rather than losing trades
+async fn flush(clickhouse: &ClickHouseService, batch: &[PendingTrade]) {
+    if batch.is_empty() {
+        return;
+    }
+    let rows: Vec<(&Trade, bool)> = batch.iter().map(|p| (&p.trade, p.quarantined)).collect();
+
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 1u32;
+    loop {
+        match clickhouse.store_trades(&rows).await {
+            Ok(()) => {
+                println!("✅ [TradeWriter] Inserted {} trades", rows.len());
+                return;
+            }
+            Err(e) => {
+                eprintln!("⚠️  [TradeWriter] Insert of {} trades failed (attempt {}): {}; retrying in {:?}",
+                    rows.len(), attempt, e, backoff);
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+                attempt += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// Per-pair circuit breaker - quarantines pairs whose parsed prices diverge from the oracle
+
+use crate::services::memory_budget::{Footprint, MemoryBudget, PairLru};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Number of recent trades per pair considered when deciding to trip
+const WINDOW_SIZE: usize = 20;
+/// Deviating trades within the window that trip the breaker
+const TRIP_COUNT: usize = 5;
+
+/// Why and when a pair was quarantined
+#[derive(Debug, Clone, Serialize)]
+pub struct Quarantine {
+    pub pair: String,
+    pub since: DateTime<Utc>,
+    pub reason: String,
+    pub last_price: f64,
+    pub oracle_price: f64,
+    pub deviation_pct: f64,
+    /// Trades stored (flagged) while quarantined
+    pub suppressed_trades: u64,
+}
+
+/// Outcome of checking a trade against the breaker
+pub enum Verdict {
+    /// Broadcast and store normally
+    Pass,
+    /// Pair is quarantined: store flagged, don't broadcast
+    Quarantined,
+    /// This trade tripped the breaker; the listed stored trades should be flagged too
+    Tripped { quarantine: Quarantine, deviating_trade_ids: Vec<String> },
+}
+
+#[derive(Default)]
+struct PairWindow {
+    /// (trade id, deviated) for the most recent trades
+    recent: VecDeque<(String, bool)>,
+}
+
+impl Footprint for PairWindow {
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.recent.capacity() * std::mem::size_of::<(String, bool)>()
+            + self.recent.iter().map(|(id, _)| id.capacity()).sum::<usize>()
+    }
+}
+
+pub struct CircuitBreaker {
+    /// Deviation from the oracle (percent) that counts a trade as suspect
+    max_deviation_pct: f64,
+    /// Recent trades per pair, evicted when cold (quarantines never are)
+    windows: RwLock<PairLru<String, PairWindow>>,
+    quarantined: RwLock<HashMap<String, Quarantine>>,
+}
+
+impl CircuitBreaker {
+    /// `max_deviation_pct` (CIRCUIT_BREAKER_DEVIATION_PCT, default 25%) sets the suspect threshold
+    pub fn new(memory_budget: Arc<MemoryBudget>, max_deviation_pct: f64) -> Self {
+        Self {
+            max_deviation_pct,
+            windows: RwLock::new(PairLru::new("circuit_breaker", memory_budget)),
+            quarantined: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Record a trade's price against the oracle and decide whether it may be broadcast
+    pub async fn check(&self, pair: &str, trade_id: &str, price: f64, oracle_price: Option<f64>) -> Verdict {
+        if let Some(quarantine) = self.quarantined.write().await.get_mut(pair) {
+            quarantine.suppressed_trades += 1;
+            return Verdict::Quarantined;
+        }
+
+        // Without an oracle price there's nothing to compare against
+        let oracle_price = match oracle_price {
+            Some(p) if p > 0.0 => p,
+            _ => return Verdict::Pass,
+        };
+        let deviation_pct = ((price - oracle_price) / oracle_price * 100.0).abs();
+        let deviated = !deviation_pct.is_finite() || deviation_pct > self.max_deviation_pct;
+
+        let deviating_trade_ids = self.windows.write().await.upsert(pair.to_string(), PairWindow::default, |window| {
+            window.recent.push_back((trade_id.to_string(), deviated));
+            if window.recent.len() > WINDOW_SIZE {
+                window.recent.pop_front();
+            }
+
+            let deviating_trade_ids: Vec<String> = window
+                .recent
+                .iter()
+                .filter(|(_, deviated)| *deviated)
+                .map(|(id, _)| id.clone())
+                .collect();
+            if deviating_trade_ids.len() >= TRIP_COUNT {
+                window.recent.clear();
+            }
+            deviating_trade_ids
+        });
+        if deviating_trade_ids.len() < TRIP_COUNT {
+            return Verdict::Pass;
+        }
+
+        let quarantine = Quarantine {
+            pair: pair.to_string(),
+            since: Utc::now(),
+            reason: format!(
+                "{} of the last {} trades deviated more than {:.1}% from the oracle",
+                deviating_trade_ids.len(), WINDOW_SIZE, self.max_deviation_pct
+            ),
+            last_price: price,
+            oracle_price,
+            deviation_pct,
+            suppressed_trades: 1,
+        };
+        self.quarantined.write().await.insert(pair.to_string(), quarantine.clone());
+        eprintln!("🚨 [CircuitBreaker] Quarantined {}: {}", pair, quarantine.reason);
+
+        Verdict::Tripped { quarantine, deviating_trade_ids }
+    }
+
+    /// Currently quarantined pairs
+    pub async fn list(&self) -> Vec<Quarantine> {
+        self.quarantined.read().await.values().cloned().collect()
+    }
+
+    /// Manually clear a quarantine; returns it if the pair was quarantined
+    pub async fn clear(&self, pair: &str) -> Option<Quarantine> {
+        let cleared = self.quarantined.write().await.remove(pair);
+        if cleared.is_some() {
+            println!("✅ [CircuitBreaker] Quarantine cleared for {}", pair);
+        }
+        cleared
+    }
+}
@@ -1,7 +1,10 @@
 // ClickHouse database service module
 // Uses official clickhouse crate for ClickHouse Cloud
 
+use crate::config::ClickHouseConfig;
 use crate::models::trade::Trade;
+use crate::services::query_limits::interval_seconds;
+use crate::utils::chaos::{self, Fault, FaultPoint};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use clickhouse::Client;
@@ -9,22 +12,78 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::sync::Arc;
 use time::OffsetDateTime;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Duration;
 
 #[derive(Clone)]
 pub struct ClickHouseService {
     client: Arc<Client>,
+    // Heavy analytical reads (exports, aggregates) go here; same as `client` without a replica
+    analytics_client: Arc<Client>,
+    // Caps concurrent reads so read stampedes can't starve inserts
+    read_limiter: Arc<Semaphore>,
+    // ND-JSON streams hold their slot while the client reads, so they get their own smaller pool
+    stream_limiter: Arc<Semaphore>,
+    read_queue_timeout: Duration,
 }
 
+/// Returned (inside anyhow::Error) when a read waited too long for a query slot
+#[derive(Debug)]
+pub struct QueryShed {
+    pub retry_after_secs: u64,
+}
+
+impl std::fmt::Display for QueryShed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ClickHouse is saturated; retry in {}s", self.retry_after_secs)
+    }
+}
+
+impl std::error::Error for QueryShed {}
+
 #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
 struct TradeRow {
     id: String,
-    #[serde(with = "clickhouse::serde::time::datetime")]
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    timestamp: OffsetDateTime,
+    base_symbol: String,
+    quote_symbol: String,
+    price: f64,
+    amount: f64,
+    side: String,
+    slot: u64,
+    sequence: u64,
+    dex_program: String,
+    spread_bps: Option<f64>,
+    trader: String,
+}
+
+/// Trade row as inserted; `quarantined` rows are kept for forensics but excluded from reads
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+struct TradeInsertRow {
+    id: String,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
     timestamp: OffsetDateTime,
     base_symbol: String,
     quote_symbol: String,
     price: f64,
     amount: f64,
     side: String,
+    slot: u64,
+    sequence: u64,
+    dex_program: String,
+    spread_bps: Option<f64>,
+    trader: String,
+    quarantined: u8,
+}
+
+/// A session ended by logout or refresh before it expired
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+struct RevokedSessionRow {
+    user_pubkey: String,
+    token: String,
+    #[serde(with = "clickhouse::serde::time::datetime")]
+    revoked_at: OffsetDateTime,
 }
 
 #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
@@ -37,42 +96,553 @@ struct SessionRow {
     expires_at: OffsetDateTime,
 }
 
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+struct PreferenceRow {
+    user_pubkey: String,
+    key: String,
+    value: String,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    updated_at: OffsetDateTime,
+}
+
+/// Audit record of a swap quote served by /api/swap (built or rejected)
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct SwapQuoteRow {
+    pub quote_id: String,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub created_at: OffsetDateTime,
+    pub user_pubkey: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub min_out_amount: u64,
+    pub slippage_bps: u16,
+    pub price_impact_pct: f64,
+    pub route: String,
+    pub status: String,
+    pub reason: String,
+}
+
+/// Platform fee taken on a built swap, attributed to the partner (API key) that routed it
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct PlatformFeeRow {
+    pub quote_id: String,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub created_at: OffsetDateTime,
+    pub partner: String,
+    pub user_pubkey: String,
+    pub fee_bps: u16,
+    pub fee_amount: u64,
+    pub fee_mint: String,
+    pub fee_account: String,
+}
+
+/// Accrued fee totals for one partner and fee mint
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct PlatformFeeTotal {
+    pub partner: String,
+    pub fee_mint: String,
+    pub swaps: u64,
+    pub fee_amount: u64,
+}
+
+/// One wallet's daily digest (JSON body) as delivered
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct DigestRow {
+    pub user_pubkey: String,
+    #[serde(with = "clickhouse::serde::time::date")]
+    pub digest_date: time::Date,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub created_at: OffsetDateTime,
+    pub portfolio_usd: f64,
+    pub body: String,
+}
+
+/// A wallet following another for copy trading; the newest row per pair of wallets wins
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct FollowRow {
+    pub follower: String,
+    pub followed: String,
+    /// Also deliver the followed wallet's trades to FOLLOW_WEBHOOK_URL
+    pub notify: u8,
+    /// 0 once unfollowed
+    pub active: u8,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// Trade worth at least WHALE_ALERT_MIN_USD when it was broadcast as a `whale_alert`
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct WhaleTradeRow {
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub timestamp: OffsetDateTime,
+    pub id: String,
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub side: String,
+    pub price: f64,
+    pub amount: f64,
+    pub total_value: f64,
+    /// total_value priced in USD when the trade was seen
+    pub usd_value: f64,
+    pub dex_program: String,
+    pub slot: u64,
+    /// '' when unknown
+    pub trader: String,
+}
+
+/// Operational event raised for admins (e.g. a pair tripping the circuit breaker)
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct AdminEventRow {
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub created_at: OffsetDateTime,
+    pub event_type: String,
+    pub subject: String,
+    pub details: String,
+}
+
+/// Quoted (Jupiter) mid price for a pair at one 5-second tick
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct PriceTickRow {
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub created_at: OffsetDateTime,
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub price: f64,
+}
+
+/// Spread-to-oracle statistics for one DEX
+/// `avg_cost_bps` is side-adjusted: positive when buys fill above the mid or sells below it
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct ExecutionQualityRow {
+    pub dex_program: String,
+    pub trades: u64,
+    pub avg_cost_bps: f64,
+    pub avg_abs_spread_bps: f64,
+    pub p50_abs_spread_bps: f64,
+    pub p95_abs_spread_bps: f64,
+}
+
+/// Volume of one DEX for a pair; volumes and averages are in the pair's base and quote tokens
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct DexVolumeRow {
+    pub dex_program: String,
+    pub trades: u64,
+    pub base_volume: f64,
+    pub quote_volume: f64,
+    pub avg_trade_size: f64,
+    pub avg_trade_value: f64,
+}
+
+/// Market alert (e.g. a volume spike); `details` is the event as broadcast on the `alerts` WS channel
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct MarketAlertRow {
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub created_at: OffsetDateTime,
+    pub kind: String,
+    pub pair: String,
+    pub details: String,
+}
+
+/// Immutable candle dataset; `id` is the SHA-256 of its canonical JSON (pair, interval, range and candles)
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct CandleSnapshotRow {
+    pub id: String,
+    pub pair: String,
+    pub interval: String,
+    /// Range covered, unix seconds: `from_time` inclusive, `up_to` exclusive
+    pub from_time: u64,
+    pub up_to: u64,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub created_at: OffsetDateTime,
+    pub candle_count: u64,
+    /// Candles as a JSON array, exactly as hashed
+    pub candles: String,
+}
+
+/// Where a candle snapshot sits in `candle_snapshots`' sort key
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+struct CandleSnapshotIdRow {
+    id: String,
+    pair: String,
+    interval: String,
+    up_to: u64,
+    from_time: u64,
+}
+
+/// Subscriber count for one (channel, pair) at a usage sample
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct WsUsageSampleRow {
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub sampled_at: OffsetDateTime,
+    pub channel: String,
+    pub pair: String,
+    pub subscribers: u32,
+}
+
+/// Aggregated WebSocket usage for one (channel, pair) over a lookback
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct WsUsageSummaryRow {
+    pub channel: String,
+    pub pair: String,
+    pub subscriber_samples: u64,
+    pub peak_subscribers: u32,
+    pub avg_subscribers: f64,
+}
+
+/// Wallet activity of one UTC day, from the sessions created that day
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct SessionDayRow {
+    /// YYYY-MM-DD
+    pub day: String,
+    pub sessions: u64,
+    pub active_wallets: u64,
+    /// Active wallets that had a session on an earlier day
+    pub returning_wallets: u64,
+}
+
+/// Duration of the sessions that have ended, in seconds
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct SessionDurationRow {
+    pub sessions: u64,
+    pub mean_secs: f64,
+    pub p50_secs: f64,
+    pub p90_secs: f64,
+    pub max_secs: i64,
+}
+
+/// One OHLCV candle; `time` is the interval start in unix seconds
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct Candle {
+    pub time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// One candle merged across several quote tokens; `vwap` is the amount-weighted average price
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct CombinedCandle {
+    pub time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub vwap: f64,
+    pub trades: u64,
+}
+
+/// Active MergeTree part counts for one table, from system.parts
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct PartsHealthRow {
+    pub table: String,
+    pub parts: u64,
+    pub rows: u64,
+    pub bytes_on_disk: u64,
+}
+
+/// 24h market summary for one stored (base, quote) direction
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct MarketSummaryRow {
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub last_price: f64,
+    pub first_price: f64,
+    pub volume: f64,
+}
+
+/// Data-quality inputs of one pair (both directions, keyed `A/B` in symbol order)
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct PairQualityRow {
+    pub pair: String,
+    pub trades: u64,
+    /// Median |spread_bps| of the trades that had an oracle price
+    pub median_deviation_bps: Option<f64>,
+    pub gaps: u64,
+}
+
+/// 24h stats for one stored (base, quote) direction; all zero when it had no trades
+#[derive(Debug, Clone, Default, Serialize, Deserialize, clickhouse::Row)]
+pub struct PairStatsRow {
+    pub last_price: f64,
+    pub first_price: f64,
+    pub high_24h: f64,
+    pub low_24h: f64,
+    pub volume_24h: f64,
+    pub trades_24h: u64,
+}
+
+impl PairStatsRow {
+    /// Response shape of /api/stats
+    pub fn to_json(&self) -> serde_json::Value {
+        let change_24h = self.last_price - self.first_price;
+        let change_percent_24h = if self.first_price > 0.0 {
+            (change_24h / self.first_price) * 100.0
+        } else {
+            0.0
+        };
+        
+        json!({
+            "currentPrice": self.last_price,
+            "high24h": self.high_24h,
+            "low24h": self.low_24h,
+            "volume24h": self.volume_24h,
+            "change24h": change_24h,
+            "changePercent24h": change_percent_24h,
+            "trades24h": self.trades_24h,
+        })
+    }
+}
+
+/// Trade in a pair's rolling 24h window, as read to seed the in-memory aggregates
+#[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+pub struct WindowTradeRow {
+    pub id: String,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub timestamp: OffsetDateTime,
+    pub price: f64,
+    pub amount: f64,
+}
+
+/// Latest claim on a named lease
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct LeaseRow {
+    pub name: String,
+    pub holder: String,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub acquired_at: OffsetDateTime,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub expires_at: OffsetDateTime,
+}
+
+/// A sign-in's claim on a nonce; the newest claim per nonce wins a race
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+struct NonceClaimRow {
+    nonce_hash: String,
+    claim_id: String,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    claimed_at: OffsetDateTime,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    expires_at: OffsetDateTime,
+}
+
+/// A replica's latest heartbeat
+#[derive(Debug, Clone, Serialize, Deserialize, clickhouse::Row)]
+pub struct InstanceRow {
+    pub instance_id: String,
+    pub version: String,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+    pub last_seen: OffsetDateTime,
+    /// INGEST_SHARD as "index/count"
+    pub ingest_shard: String,
+    /// Enabled DEX programs this replica subscribes to
+    pub ingesting_programs: u32,
+    /// Parsed trades go to a hub instead of this replica's pipeline
+    pub forwards_to_hub: u8,
+    pub stream_connected: u8,
+    pub ws_connections: u32,
+}
+
+/// ClickHouse INTERVAL for a supported candle interval (1m when unknown)
+fn interval_sql(interval: &str) -> &'static str {
+    match interval {
+        "1m" => "1 MINUTE",
+        "5m" => "5 MINUTE",
+        "15m" => "15 MINUTE",
+        "1h" => "1 HOUR",
+        "4h" => "4 HOUR",
+        "1d" => "1 DAY",
+        _ => "1 MINUTE",
+    }
+}
+
+/// How long a lease claim waits before reading back which claim won
+const LEASE_SETTLE_DELAY: Duration = Duration::from_secs(1);
+
+/// Tables of trades deduplicated by ReplacingMergeTree, with their sort keys
+/// Merges collapse duplicates only eventually (maybe never), so reads of these tables that count or sum
+/// rows set `final`, which applies FINAL to them and is ignored while a table awaits its migration
+const REPLACING_TABLES: &[(&str, &str)] = &[
+    ("trades", "timestamp, id"),
+    ("trades_by_trader", "trader, timestamp, id"),
+];
+
+/// Keeps trades_by_trader up to date on each insert into trades
+const TRADES_BY_TRADER_MV_SQL: &str = "CREATE MATERIALIZED VIEW IF NOT EXISTS trades_by_trader_mv TO trades_by_trader AS
+    SELECT trader, id, timestamp, base_symbol, quote_symbol, price, amount, side,
+        slot, sequence, dex_program, spread_bps, quarantined
+    FROM trades
+    WHERE trader != ''";
+
 // Helper functions to convert between chrono::DateTime<Utc> and time::OffsetDateTime
-fn chrono_to_time(dt: DateTime<Utc>) -> OffsetDateTime {
-    let unix_timestamp = dt.timestamp();
-    OffsetDateTime::from_unix_timestamp(unix_timestamp)
+// Conversions keep millisecond precision (DateTime64(3) columns); DateTime columns truncate to seconds
+pub(crate) fn chrono_to_time(dt: DateTime<Utc>) -> OffsetDateTime {
+    let unix_millis = dt.timestamp_millis() as i128;
+    OffsetDateTime::from_unix_timestamp_nanos(unix_millis * 1_000_000)
         .unwrap_or_else(|_| OffsetDateTime::now_utc())
 }
 
-fn time_to_chrono(dt: OffsetDateTime) -> DateTime<Utc> {
-    let unix_timestamp = dt.unix_timestamp();
-    DateTime::from_timestamp(unix_timestamp, 0)
+pub(crate) fn time_to_chrono(dt: OffsetDateTime) -> DateTime<Utc> {
+    let unix_millis = (dt.unix_timestamp_nanos() / 1_000_000) as i64;
+    DateTime::from_timestamp_millis(unix_millis)
         .unwrap_or_else(|| Utc::now())
 }
 
-impl ClickHouseService {
-    pub async fn new() -> Result<Self> {
-        // Get ClickHouse connection details from environment
-        let clickhouse_url = std::env::var("CLICKHOUSE_URL")
-            .unwrap_or_else(|_| "http://localhost:8123".to_string());
-        
-        let clickhouse_username = std::env::var("CLICKHOUSE_USERNAME")
-            .unwrap_or_else(|_| "default".to_string());
-        
-        let clickhouse_password = std::env::var("CLICKHOUSE_PASSWORD")
-            .unwrap_or_else(|_| "".to_string());
-        
-        // Create ClickHouse client using official crate
-        // URL should include protocol and port: https://instance.clickhouse.cloud:8443
-        let client = Client::default()
-            .with_url(&clickhouse_url)
-            .with_user(&clickhouse_username)
-            .with_password(&clickhouse_password)
-            .with_database("default");
-        
-        let service = Self {
-            client: Arc::new(client),
+/// End of a trailing window in unix millis: `as_of` for time-travel queries, otherwise now
+fn window_end_millis(as_of: Option<DateTime<Utc>>) -> i64 {
+    as_of.unwrap_or_else(Utc::now).timestamp_millis()
+}
+
+// Convert a stored row back into a Trade
+// Note: base_mint and quote_mint are not stored in ClickHouse and are left empty; total_value is
+// recomputed from price and amount
+fn trade_from_row(row: TradeRow) -> Trade {
+    Trade {
+        total_value: row.price * row.amount, // Calculate from stored price and amount
+        id: row.id,
+        timestamp: time_to_chrono(row.timestamp),
+        base_symbol: row.base_symbol,
+        quote_symbol: row.quote_symbol,
+        base_mint: String::new(), // Not stored in ClickHouse per assignment
+        quote_mint: String::new(), // Not stored in ClickHouse per assignment
+        price: row.price,
+        amount: row.amount,
+        side: row.side,
+        dex_program: row.dex_program,
+        slot: row.slot,
+        sequence: row.sequence,
+        trader: (!row.trader.is_empty()).then_some(row.trader),
+        inverted: false,
+        spread_bps: row.spread_bps,
+    }
+}
+
+/// Optional filters for trade queries
+#[derive(Debug, Default, Clone)]
+pub struct TradeFilter {
+    /// Inclusive slot range
+    pub from_slot: Option<u64>,
+    pub to_slot: Option<u64>,
+    /// Exclusive cursor bounds; with only `after` set, trades come oldest first
+    pub before: Option<TradePosition>,
+    pub after: Option<TradePosition>,
+}
+
+/// Position in a pair's (timestamp, sequence, id) trade order, for cursor paging
+/// A bare timestamp bounds by time alone; a trade's full position resumes exactly after it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradePosition {
+    pub timestamp_ms: i64,
+    pub trade: Option<(u64, String)>,
+}
+
+impl TradePosition {
+    pub fn of(trade: &Trade) -> Self {
+        Self {
+            timestamp_ms: trade.timestamp.timestamp_millis(),
+            trade: Some((trade.sequence, trade.id.clone())),
+        }
+    }
+
+    /// Opaque `next_cursor` form: `{timestamp_ms}_{sequence}_{id}`
+    pub fn to_cursor(&self) -> String {
+        match &self.trade {
+            Some((sequence, id)) => format!("{}_{}_{}", self.timestamp_ms, sequence, id),
+            None => self.timestamp_ms.to_string(),
+        }
+    }
+
+    /// Parse a cursor returned as `next_cursor`; None for anything else
+    pub fn from_cursor(cursor: &str) -> Option<Self> {
+        let mut parts = cursor.splitn(3, '_');
+        let (timestamp_ms, sequence, id) = (parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, parts.next()?);
+        (!id.is_empty()).then(|| Self { timestamp_ms, trade: Some((sequence, id.to_string())) })
+    }
+
+    /// SQL condition placing rows strictly before (`<`) or after (`>`) this position, with its binds
+    fn condition(&self, op: &str) -> String {
+        match self.trade {
+            Some(_) => format!(" AND (timestamp, sequence, id) {} (fromUnixTimestamp64Milli(toInt64(?)), ?, ?)", op),
+            None => format!(" AND timestamp {} fromUnixTimestamp64Milli(toInt64(?))", op),
+        }
+    }
+
+    fn bind(&self, query: clickhouse::query::Query) -> clickhouse::query::Query {
+        let query = query.bind(self.timestamp_ms);
+        match &self.trade {
+            Some((sequence, id)) => query.bind(*sequence).bind(id.as_str()),
+            None => query,
+        }
+    }
+}
+
+/// Streaming cursor over stored trades
+pub struct TradeCursor {
+    cursor: clickhouse::query::RowCursor<TradeRow>,
+    // Requested (base, quote); rows stored the other way round are inverted to it
+    orientation: Option<(String, String)>,
+    // Read slot held until the cursor is dropped
+    _permit: OwnedSemaphorePermit,
+}
+
+impl TradeCursor {
+    /// Next trade, or None when the result set is exhausted
+    pub async fn next(&mut self) -> Result<Option<Trade>> {
+        let Some(mut trade) = self.cursor.next().await?.map(trade_from_row) else {
+            return Ok(None);
         };
+        if let Some((base_symbol, quote_symbol)) = &self.orientation
+            && trade.base_symbol == *quote_symbol
+            && trade.quote_symbol == *base_symbol
+        {
+            trade.invert();
+        }
+        Ok(Some(trade))
+    }
+}
+
+/// Streaming cursor over aggregated candles
+pub struct CandleCursor {
+    cursor: clickhouse::query::RowCursor<Candle>,
+    // Read slot held until the cursor is dropped
+    _permit: OwnedSemaphorePermit,
+}
+
+impl CandleCursor {
+    /// Next candle, or None when the result set is exhausted
+    pub async fn next(&mut self) -> Result<Option<Candle>> {
+        self.cursor.next().await.context("Failed to query OHLCV from ClickHouse")
+    }
+}
+
+impl ClickHouseService {
+    /// Client for the primary from the connection settings
+    /// URL should include protocol and port: https://instance.clickhouse.cloud:8443
+    fn primary_client(config: &ClickHouseConfig) -> Client {
+        Client::default()
+            .with_url(&config.url)
+            .with_user(&config.username)
+            .with_password(&config.password)
+            .with_database("default")
+    }
+    
+    /// Run `SELECT 1` against the primary without creating tables (startup preflight)
+    pub async fn check_connection(config: &ClickHouseConfig) -> Result<()> {
+        Self::primary_client(config)
+            .query("SELECT 1")
+            .fetch_one::<u8>()
+            .await?;
+        Ok(())
+    }
+    
+    pub async fn new(config: &ClickHouseConfig) -> Result<Self> {
+        let service = Self::from_config(config);
         
         // Test connection
         match service.test_connection().await {
@@ -81,8 +651,8 @@ impl ClickHouseService {
             }
             Err(e) => {
                 eprintln!("⚠️  ClickHouse connection test failed: {}", e);
-                eprintln!("   URL: {}", clickhouse_url);
-                eprintln!("   Username: {}", clickhouse_username);
+                eprintln!("   URL: {}", config.url);
+                eprintln!("   Username: {}", config.username);
                 eprintln!("   This may cause insert failures. Please check your CLICKHOUSE_URL, CLICKHOUSE_USERNAME, and CLICKHOUSE_PASSWORD environment variables.");
             }
         }
@@ -93,6 +663,57 @@ impl ClickHouseService {
         Ok(service)
     }
     
+    /// The clients and read pools, without connecting
+    fn from_config(config: &ClickHouseConfig) -> Self {
+        let client = Arc::new(Self::primary_client(config));
+        
+        // Optional read replica for analytics (CLICKHOUSE_READ_URL)
+        let analytics_client = match &config.read_replica {
+            Some(replica) => {
+                println!("📚 ClickHouse analytics reads routed to {}", replica.url);
+                Arc::new(Client::default()
+                    .with_url(&replica.url)
+                    .with_user(&replica.username)
+                    .with_password(&replica.password)
+                    .with_database("default"))
+            }
+            None => client.clone(),
+        };
+        Self {
+            client,
+            analytics_client,
+            read_limiter: Arc::new(Semaphore::new(config.max_concurrent_reads)),
+            stream_limiter: Arc::new(Semaphore::new(config.max_concurrent_streams)),
+            read_queue_timeout: config.read_queue_timeout,
+        }
+    }
+    
+    /// Wait for a read slot, shedding the query with `QueryShed` if none frees up in time
+    async fn read_permit(&self) -> Result<OwnedSemaphorePermit> {
+        self.permit(&self.read_limiter, "query").await
+    }
+
+    /// Wait for a slot in the ND-JSON stream pool, kept apart so slow clients can't hold read slots
+    async fn stream_permit(&self) -> Result<OwnedSemaphorePermit> {
+        self.permit(&self.stream_limiter, "stream").await
+    }
+
+    async fn permit(&self, limiter: &Arc<Semaphore>, kind: &str) -> Result<OwnedSemaphorePermit> {
+        match tokio::time::timeout(self.read_queue_timeout, limiter.clone().acquire_owned()).await {
+            Ok(permit) => Ok(permit?),
+            Err(_) => {
+                eprintln!("⚠️  [ClickHouse] Read shed after waiting {:?} for a {} slot", self.read_queue_timeout, kind);
+                Err(QueryShed { retry_after_secs: self.read_queue_timeout.as_secs().max(1) }.into())
+            }
+        }
+    }
+    
+    /// Client for long scans and aggregations that tolerate replica lag
+    /// Writes and latency-sensitive reads that must see the latest inserts use `self.client`
+    fn analytics(&self) -> &Client {
+        &self.analytics_client
+    }
+    
     /// Test ClickHouse connection
     async fn test_connection(&self) -> Result<()> {
         self.client
@@ -108,14 +729,14 @@ impl ClickHouseService {
         // Create trades table - matching assignment schema
         let trades_sql = "CREATE TABLE IF NOT EXISTS trades (
             id String,
-            timestamp DateTime,
+            timestamp DateTime64(3),
             base_symbol String,
             quote_symbol String,
             price Float64,
             amount Float64,
             side String
-        ) ENGINE = MergeTree()
-        ORDER BY timestamp";
+        ) ENGINE = ReplacingMergeTree()
+        ORDER BY (timestamp, id)";
         
         self.client
             .query(trades_sql)
@@ -125,6 +746,91 @@ impl ClickHouseService {
         
         println!("✅ ClickHouse trades table initialized");
         
+        // Trades stored while their pair is quarantined by the circuit breaker are flagged
+        // and excluded from reads (added separately so existing tables pick it up)
+        self.client
+            .query("ALTER TABLE trades ADD COLUMN IF NOT EXISTS quarantined UInt8 DEFAULT 0")
+            .execute()
+            .await
+            .context("Failed to add quarantined column to trades table")?;
+        
+        // Slot of the transaction, for slot-range queries (0 for rows stored before it was persisted)
+        self.client
+            .query("ALTER TABLE trades ADD COLUMN IF NOT EXISTS slot UInt64 DEFAULT 0")
+            .execute()
+            .await
+            .context("Failed to add slot column to trades table")?;
+        
+        // Per-pair ingest sequence, totally ordering trades that share a timestamp
+        self.client
+            .query("ALTER TABLE trades ADD COLUMN IF NOT EXISTS sequence UInt64 DEFAULT 0")
+            .execute()
+            .await
+            .context("Failed to add sequence column to trades table")?;
+        
+        // Execution quality: the DEX that filled the trade and its deviation from the oracle mid
+        // (NULL when no oracle price was available)
+        self.client
+            .query("ALTER TABLE trades ADD COLUMN IF NOT EXISTS dex_program LowCardinality(String) DEFAULT ''")
+            .execute()
+            .await
+            .context("Failed to add dex_program column to trades table")?;
+        
+        self.client
+            .query("ALTER TABLE trades ADD COLUMN IF NOT EXISTS spread_bps Nullable(Float64)")
+            .execute()
+            .await
+            .context("Failed to add spread_bps column to trades table")?;
+        
+        // Fee payer of the swap ('' when unknown)
+        self.client
+            .query("ALTER TABLE trades ADD COLUMN IF NOT EXISTS trader String DEFAULT ''")
+            .execute()
+            .await
+            .context("Failed to add trader column to trades table")?;
+        
+        // Notional in the quote token (USD for stablecoin-quoted pairs), computed by ClickHouse on insert
+        self.client
+            .query("ALTER TABLE trades ADD COLUMN IF NOT EXISTS usd_value Float64 MATERIALIZED price * amount")
+            .execute()
+            .await
+            .context("Failed to add usd_value column to trades table")?;
+        
+        self.init_trade_skip_indexes("trades").await?;
+        
+        // Owner index: the same trades sorted by trader, so wallet lookups read one key range
+        // instead of scanning every pair. Kept up to date by a materialized view on each insert
+        let trades_by_trader_sql = "CREATE TABLE IF NOT EXISTS trades_by_trader (
+            trader String,
+            id String,
+            timestamp DateTime64(3),
+            base_symbol String,
+            quote_symbol String,
+            price Float64,
+            amount Float64,
+            side String,
+            slot UInt64,
+            sequence UInt64,
+            dex_program LowCardinality(String),
+            spread_bps Nullable(Float64),
+            quarantined UInt8
+        ) ENGINE = ReplacingMergeTree()
+        ORDER BY (trader, timestamp, id)";
+        
+        self.client
+            .query(trades_by_trader_sql)
+            .execute()
+            .await
+            .context("Failed to create trades_by_trader table")?;
+        
+        self.client
+            .query(TRADES_BY_TRADER_MV_SQL)
+            .execute()
+            .await
+            .context("Failed to create trades_by_trader_mv materialized view")?;
+        
+        println!("✅ ClickHouse trades_by_trader index initialized");
+        
         // Create sessions table for user sessions
         // Using DateTime('UTC') to ensure timezone consistency
         let sessions_sql = "CREATE TABLE IF NOT EXISTS sessions (
@@ -141,73 +847,1330 @@ impl ClickHouseService {
             .await
             .context("Failed to create sessions table")?;
         
-        println!("✅ ClickHouse sessions table initialized");
+        // Sessions ended early; an insert per logout instead of a DELETE mutation on sessions
+        self.client
+            .query("CREATE TABLE IF NOT EXISTS revoked_sessions (
+                user_pubkey String,
+                token String,
+                revoked_at DateTime('UTC')
+            ) ENGINE = MergeTree()
+            ORDER BY (user_pubkey, token)")
+            .execute()
+            .await
+            .context("Failed to create revoked_sessions table")?;
+        
+        println!("✅ ClickHouse sessions table initialized");
+        
+        // Create user preferences table (latest value per key wins on merge)
+        let preferences_sql = "CREATE TABLE IF NOT EXISTS user_preferences (
+            user_pubkey String,
+            key String,
+            value String,
+            updated_at DateTime64(3, 'UTC')
+        ) ENGINE = ReplacingMergeTree(updated_at)
+        ORDER BY (user_pubkey, key)";
+        
+        self.client
+            .query(preferences_sql)
+            .execute()
+            .await
+            .context("Failed to create user_preferences table")?;
+        
+        println!("✅ ClickHouse user_preferences table initialized");
+        
+        // Create swap quote audit log (every quote served by /api/swap, for dispute resolution)
+        let swap_quotes_sql = "CREATE TABLE IF NOT EXISTS swap_quotes (
+            quote_id String,
+            created_at DateTime64(3, 'UTC'),
+            user_pubkey String,
+            input_mint String,
+            output_mint String,
+            in_amount UInt64,
+            out_amount UInt64,
+            min_out_amount UInt64,
+            slippage_bps UInt16,
+            price_impact_pct Float64,
+            route String,
+            status LowCardinality(String),
+            reason String
+        ) ENGINE = MergeTree()
+        ORDER BY (user_pubkey, created_at)";
+        
+        self.client
+            .query(swap_quotes_sql)
+            .execute()
+            .await
+            .context("Failed to create swap_quotes table")?;
+        
+        println!("✅ ClickHouse swap_quotes table initialized");
+        
+        // Create platform fee ledger (fees accrued per partner/API key on built swaps)
+        let platform_fees_sql = "CREATE TABLE IF NOT EXISTS platform_fees (
+            quote_id String,
+            created_at DateTime64(3, 'UTC'),
+            partner LowCardinality(String),
+            user_pubkey String,
+            fee_bps UInt16,
+            fee_amount UInt64,
+            fee_mint String,
+            fee_account String
+        ) ENGINE = MergeTree()
+        ORDER BY (partner, created_at)";
+        
+        self.client
+            .query(platform_fees_sql)
+            .execute()
+            .await
+            .context("Failed to create platform_fees table")?;
+        
+        println!("✅ ClickHouse platform_fees table initialized");
+        
+        // Create daily digest table (one row per wallet per day, re-runs replace)
+        let digests_sql = "CREATE TABLE IF NOT EXISTS user_digests (
+            user_pubkey String,
+            digest_date Date,
+            created_at DateTime64(3, 'UTC'),
+            portfolio_usd Float64,
+            body String
+        ) ENGINE = ReplacingMergeTree(created_at)
+        ORDER BY (user_pubkey, digest_date)";
+        
+        self.client
+            .query(digests_sql)
+            .execute()
+            .await
+            .context("Failed to create user_digests table")?;
+        
+        println!("✅ ClickHouse user_digests table initialized");
+        
+        // Create copy-trading follows (latest row per follower/followed wins; unfollows are rows with active = 0)
+        let follows_sql = "CREATE TABLE IF NOT EXISTS wallet_follows (
+            follower String,
+            followed String,
+            notify UInt8,
+            active UInt8,
+            updated_at DateTime64(3, 'UTC')
+        ) ENGINE = ReplacingMergeTree(updated_at)
+        ORDER BY (follower, followed)";
+        
+        self.client
+            .query(follows_sql)
+            .execute()
+            .await
+            .context("Failed to create wallet_follows table")?;
+        
+        println!("✅ ClickHouse wallet_follows table initialized");
+        
+        // Create whale trade history (trades at or above WHALE_ALERT_MIN_USD, for /api/whales)
+        let whale_trades_sql = "CREATE TABLE IF NOT EXISTS whale_trades (
+            timestamp DateTime64(3, 'UTC'),
+            id String,
+            base_symbol LowCardinality(String),
+            quote_symbol LowCardinality(String),
+            side LowCardinality(String),
+            price Float64,
+            amount Float64,
+            total_value Float64,
+            usd_value Float64,
+            dex_program LowCardinality(String),
+            slot UInt64,
+            trader String
+        ) ENGINE = ReplacingMergeTree()
+        ORDER BY (timestamp, id)";
+        
+        self.client
+            .query(whale_trades_sql)
+            .execute()
+            .await
+            .context("Failed to create whale_trades table")?;
+        
+        println!("✅ ClickHouse whale_trades table initialized");
+        
+        // Create admin event log
+        let admin_events_sql = "CREATE TABLE IF NOT EXISTS admin_events (
+            created_at DateTime64(3, 'UTC'),
+            event_type LowCardinality(String),
+            subject String,
+            details String
+        ) ENGINE = MergeTree()
+        ORDER BY created_at";
+        
+        self.client
+            .query(admin_events_sql)
+            .execute()
+            .await
+            .context("Failed to create admin_events table")?;
+        
+        println!("✅ ClickHouse admin_events table initialized");
+        
+        // Create mid-price history (Jupiter price ticks broadcast on the prices channel)
+        let prices_sql = "CREATE TABLE IF NOT EXISTS prices (
+            created_at DateTime64(3, 'UTC'),
+            base_symbol LowCardinality(String),
+            quote_symbol LowCardinality(String),
+            price Float64
+        ) ENGINE = MergeTree()
+        ORDER BY (base_symbol, quote_symbol, created_at)";
+        
+        self.client
+            .query(prices_sql)
+            .execute()
+            .await
+            .context("Failed to create prices table")?;
+        
+        println!("✅ ClickHouse prices table initialized");
+        
+        // Create WebSocket usage samples (subscribers per channel and pair, sampled every minute)
+        let ws_usage_sql = "CREATE TABLE IF NOT EXISTS ws_usage_samples (
+            sampled_at DateTime64(3, 'UTC'),
+            channel LowCardinality(String),
+            pair LowCardinality(String),
+            subscribers UInt32
+        ) ENGINE = MergeTree()
+        ORDER BY (pair, channel, sampled_at)
+        TTL toDateTime(sampled_at) + INTERVAL 90 DAY";
+        
+        self.client
+            .query(ws_usage_sql)
+            .execute()
+            .await
+            .context("Failed to create ws_usage_samples table")?;
+        
+        println!("✅ ClickHouse ws_usage_samples table initialized");
+        
+        // Leases coordinating replicas (which instance runs each periodic job); the newest row per name wins
+        let leases_sql = "CREATE TABLE IF NOT EXISTS leases (
+            name String,
+            holder String,
+            acquired_at DateTime64(3, 'UTC'),
+            expires_at DateTime64(3, 'UTC')
+        ) ENGINE = ReplacingMergeTree(acquired_at)
+        ORDER BY name
+        TTL toDateTime(expires_at) + INTERVAL 1 DAY";
+        
+        self.client
+            .query(leases_sql)
+            .execute()
+            .await
+            .context("Failed to create leases table")?;
+        
+        println!("✅ ClickHouse leases table initialized");
+        
+        // Nonces used to sign in, shared so a nonce consumed on one replica can't be replayed on another
+        let consumed_nonces_sql = "CREATE TABLE IF NOT EXISTS consumed_nonces (
+            nonce_hash String,
+            claim_id String,
+            claimed_at DateTime64(3, 'UTC'),
+            expires_at DateTime64(3, 'UTC')
+        ) ENGINE = ReplacingMergeTree(claimed_at)
+        ORDER BY nonce_hash
+        TTL toDateTime(expires_at) + INTERVAL 1 DAY";
+        
+        self.client
+            .query(consumed_nonces_sql)
+            .execute()
+            .await
+            .context("Failed to create consumed_nonces table")?;
+        
+        println!("✅ ClickHouse consumed_nonces table initialized");
+        
+        // Replica heartbeats for /api/cluster; the newest row per instance wins
+        let instances_sql = "CREATE TABLE IF NOT EXISTS instances (
+            instance_id String,
+            version String,
+            started_at DateTime64(3, 'UTC'),
+            last_seen DateTime64(3, 'UTC'),
+            ingest_shard String,
+            ingesting_programs UInt32,
+            forwards_to_hub UInt8,
+            stream_connected UInt8,
+            ws_connections UInt32
+        ) ENGINE = ReplacingMergeTree(last_seen)
+        ORDER BY instance_id
+        TTL toDateTime(last_seen) + INTERVAL 1 DAY";
+        
+        self.client
+            .query(instances_sql)
+            .execute()
+            .await
+            .context("Failed to create instances table")?;
+        
+        println!("✅ ClickHouse instances table initialized");
+        
+        // Create candle snapshots (pinned datasets; the first snapshot of a pair/interval/range is the one served)
+        let candle_snapshots_sql = "CREATE TABLE IF NOT EXISTS candle_snapshots (
+            id String,
+            pair LowCardinality(String),
+            interval LowCardinality(String),
+            from_time UInt64,
+            up_to UInt64,
+            created_at DateTime64(3, 'UTC'),
+            candle_count UInt64,
+            candles String CODEC(ZSTD(3))
+        ) ENGINE = MergeTree()
+        ORDER BY (pair, interval, up_to, from_time)";
+        
+        self.client
+            .query(candle_snapshots_sql)
+            .execute()
+            .await
+            .context("Failed to create candle_snapshots table")?;
+        
+        // Snapshot ids to their sort key, so a lookup by id reads one granule of each table
+        let candle_snapshot_ids_sql = "CREATE TABLE IF NOT EXISTS candle_snapshot_ids (
+            id String,
+            pair LowCardinality(String),
+            interval LowCardinality(String),
+            up_to UInt64,
+            from_time UInt64
+        ) ENGINE = ReplacingMergeTree()
+        ORDER BY id";
+        
+        self.client
+            .query(candle_snapshot_ids_sql)
+            .execute()
+            .await
+            .context("Failed to create candle_snapshot_ids table")?;
+        
+        // Index snapshots stored before the id table existed (or whose id row was never written)
+        self.client
+            .query("INSERT INTO candle_snapshot_ids
+                    SELECT id, pair, interval, up_to, from_time FROM candle_snapshots
+                    WHERE id NOT IN (SELECT id FROM candle_snapshot_ids)")
+            .execute()
+            .await
+            .context("Failed to index candle snapshot ids")?;
+        
+        println!("✅ ClickHouse candle_snapshots table initialized");
+        
+        // Create market alerts (volume spikes), kept for a week
+        let market_alerts_sql = "CREATE TABLE IF NOT EXISTS market_alerts (
+            created_at DateTime64(3, 'UTC'),
+            kind LowCardinality(String),
+            pair LowCardinality(String),
+            details String
+        ) ENGINE = MergeTree()
+        ORDER BY (pair, created_at)
+        TTL toDateTime(created_at) + INTERVAL 7 DAY";
+        
+        self.client
+            .query(market_alerts_sql)
+            .execute()
+            .await
+            .context("Failed to create market_alerts table")?;
+        
+        println!("✅ ClickHouse market_alerts table initialized");
+        
+        Ok(())
+    }
+    
+    /// Data-skipping indexes for the venue, side and notional filters and signature lookups, which aren't in the sort key
+    /// Only parts written (or merged) after an index is added carry it, so older data picks it up gradually
+    async fn init_trade_skip_indexes(&self, table: &str) -> Result<()> {
+        const TRADE_SKIP_INDEXES: &[(&str, &str)] = &[
+            ("idx_dex_program", "dex_program TYPE set(64) GRANULARITY 4"),
+            ("idx_side", "side TYPE set(2) GRANULARITY 4"),
+            ("idx_usd_value", "usd_value TYPE minmax GRANULARITY 1"),
+            ("idx_id", "id TYPE bloom_filter(0.01) GRANULARITY 1"),
+        ];
+        
+        for (name, definition) in TRADE_SKIP_INDEXES {
+            self.client
+                .query(&format!("ALTER TABLE {} ADD INDEX IF NOT EXISTS {} {}", table, name, definition))
+                .execute()
+                .await
+                .with_context(|| format!("Failed to add {} index to {} table", name, table))?;
+        }
+        
+        println!("✅ ClickHouse {} skip indexes initialized", table);
+        
+        Ok(())
+    }
+    
+    /// Whether trades was created with second-precision timestamps and still needs migrate_trades_to_millis
+    pub async fn trades_timestamp_in_seconds(&self) -> Result<bool> {
+        let timestamp_type = self.client
+            .query("SELECT type FROM system.columns
+                    WHERE database = currentDatabase() AND table = 'trades' AND name = 'timestamp'")
+            .fetch_one::<String>()
+            .await
+            .context("Failed to read trades.timestamp type")?;
+        Ok(timestamp_type == "DateTime")
+    }
+    
+    /// Move a trades table created with second-precision timestamps to DateTime64(3)
+    /// `timestamp` is the sort key, which can't be retyped in place, so the rows are copied into a
+    /// table with the live engine, sort key, columns and skip indexes, which is then swapped in like
+    /// migrate_to_replacing's copy (rows written meanwhile are carried over). Run by one replica,
+    /// under a job lease, before any replica on this version ingests
+    pub async fn migrate_trades_to_millis(&self) -> Result<()> {
+        println!("🔄 Migrating trades.timestamp from DateTime to DateTime64(3)...");
+        
+        let copied_parts = self.active_parts("trades").await?;
+        for statement in [
+            "DROP TABLE IF EXISTS trades_ms",
+            // Same columns, in the same order, as trades after init_tables
+            "CREATE TABLE trades_ms (
+                id String,
+                timestamp DateTime64(3),
+                base_symbol String,
+                quote_symbol String,
+                price Float64,
+                amount Float64,
+                side String,
+                quarantined UInt8 DEFAULT 0,
+                slot UInt64 DEFAULT 0,
+                sequence UInt64 DEFAULT 0,
+                dex_program LowCardinality(String) DEFAULT '',
+                spread_bps Nullable(Float64),
+                trader String DEFAULT '',
+                usd_value Float64 MATERIALIZED price * amount
+            ) ENGINE = ReplacingMergeTree()
+            ORDER BY (timestamp, id)",
+        ] {
+            self.run_migration_step("trades", statement).await?;
+        }
+        self.init_trade_skip_indexes("trades_ms").await?;
+        self.run_migration_step("trades", "INSERT INTO trades_ms SELECT * FROM trades").await?;
+        self.swap_in_copy("trades", "trades_ms", &copied_parts).await?;
+        
+        println!("✅ trades.timestamp migrated to DateTime64(3)");
+        Ok(())
+    }
+    
+    /// Tables of trades still on a plain MergeTree, so duplicates aren't collapsed on merge
+    /// Shared (ClickHouse Cloud) and Replicated variants of ReplacingMergeTree count as migrated
+    pub async fn pending_replacing_migrations(&self) -> Result<Vec<&'static str>> {
+        let mut pending = Vec::new();
+        for (table, _) in REPLACING_TABLES {
+            let replacing = self.client
+                .query("SELECT engine LIKE '%ReplacingMergeTree' FROM system.tables WHERE database = currentDatabase() AND name = ?")
+                .bind(table)
+                .fetch_one::<bool>()
+                .await
+                .with_context(|| format!("Failed to read {} engine", table))?;
+            if !replacing {
+                pending.push(*table);
+            }
+        }
+        Ok(pending)
+    }
+    
+    /// Names of the active parts of `table`
+    async fn active_parts(&self, table: &str) -> Result<Vec<String>> {
+        self.client
+            .query("SELECT name FROM system.parts WHERE database = currentDatabase() AND table = ? AND active")
+            .bind(table)
+            .fetch_all::<String>()
+            .await
+            .with_context(|| format!("Failed to list {} parts", table))
+    }
+    
+    /// Move `table` (see REPLACING_TABLES) from a plain MergeTree to ReplacingMergeTree
+    /// A trade's id is its transaction signature and a re-delivered trade has the same block time, so
+    /// duplicates that get past the stream's SignatureDedup are collapsed on merge. The engine can't
+    /// be changed in place, so the rows are copied (and deduplicated) into a new table that is then
+    /// swapped in. Ingestion keeps running: rows written to the old table during the copy, and trades
+    /// inserted while the trades_by_trader view is recreated, are copied over afterwards (re-copied
+    /// rows are duplicates, which the new engine collapses). Run by one replica, under a job lease
+    pub async fn migrate_to_replacing(&self, table: &str) -> Result<()> {
+        let order_by = REPLACING_TABLES
+            .iter()
+            .find(|(name, _)| *name == table)
+            .map(|(_, order_by)| *order_by)
+            .with_context(|| format!("{} is not a trades table", table))?;
+        println!("🔄 Migrating {} to ReplacingMergeTree...", table);
+        
+        let copied_parts = self.active_parts(table).await?;
+        for statement in [
+            format!("DROP TABLE IF EXISTS {}_dedup", table),
+            format!("CREATE TABLE {0}_dedup AS {0} ENGINE = ReplacingMergeTree() ORDER BY ({1})", table, order_by),
+            format!("INSERT INTO {0}_dedup SELECT * FROM {0}", table),
+            format!("OPTIMIZE TABLE {}_dedup FINAL", table),
+        ] {
+            self.run_migration_step(table, &statement).await?;
+        }
+        
+        self.swap_in_copy(table, &format!("{}_dedup", table), &copied_parts).await?;
+        
+        println!("✅ {} migrated to ReplacingMergeTree", table);
+        Ok(())
+    }
+    
+    /// Swap `copy` in for `table` (one of the trades tables), recreating the trades_by_trader view
+    /// around the swap, then copy over what the swap missed: trades inserted while the view was down,
+    /// and rows written to the old table outside `copied_parts` (the parts `copy` was filled from)
+    async fn swap_in_copy(&self, table: &str, copy: &str, copied_parts: &[String]) -> Result<()> {
+        // The view's source is the new trades table once swapped in, so snapshot that one
+        let view_source = if table == "trades" { copy } else { "trades" };
+        let viewed_parts = self.active_parts(view_source).await?;
+        for statement in [
+            "DROP VIEW IF EXISTS trades_by_trader_mv".to_string(),
+            format!("EXCHANGE TABLES {} AND {}", table, copy),
+            TRADES_BY_TRADER_MV_SQL.to_string(),
+        ] {
+            self.run_migration_step(table, &statement).await?;
+        }
+        
+        // Trades inserted while the view was down
+        self.client
+            .query("INSERT INTO trades_by_trader
+                    SELECT trader, id, timestamp, base_symbol, quote_symbol, price, amount, side,
+                        slot, sequence, dex_program, spread_bps, quarantined
+                    FROM trades
+                    WHERE trader != '' AND NOT has(?, _part)")
+            .bind(&viewed_parts)
+            .execute()
+            .await
+            .with_context(|| format!("{} migration failed to index trades written during the swap", table))?;
+        // Rows written to the old table (now `copy`) after the copy started
+        self.client
+            .query(&format!("INSERT INTO {} SELECT * FROM {} WHERE NOT has(?, _part)", table, copy))
+            .bind(copied_parts)
+            .execute()
+            .await
+            .with_context(|| format!("{} migration failed to copy rows written during the copy", table))?;
+        self.run_migration_step(table, &format!("DROP TABLE {}", copy)).await
+    }
+    
+    async fn run_migration_step(&self, table: &str, statement: &str) -> Result<()> {
+        self.client
+            .query(statement)
+            .execute()
+            .await
+            .with_context(|| format!("{} migration failed at: {}", table, statement.lines().next().unwrap_or_default()))
+    }
+    
+    /// Insert a batch of trades in one INSERT (see TradeWriter, which buffers the stream's trades)
+    /// Rows flagged quarantined are kept for forensics but excluded from reads
+    pub async fn store_trades(&self, trades: &[(&Trade, bool)]) -> Result<()> {
+        if trades.is_empty() {
+            return Ok(());
+        }
+        // A dropped insert is acknowledged but never lands
+        match chaos::inject(FaultPoint::ClickHouseInsert).await {
+            Ok(()) => {}
+            Err(Fault::Drop) => return Ok(()),
+            Err(fault) => return Err(anyhow::anyhow!(fault)).context("Failed to insert trades"),
+        }
+        
+        let mut insert = self.client.insert("trades")?;
+        for (trade, quarantined) in trades {
+            // Convert chrono::DateTime<Utc> to time::OffsetDateTime
+            let trade_row = TradeInsertRow {
+                id: trade.id.clone(),
+                timestamp: chrono_to_time(trade.timestamp),
+                base_symbol: trade.base_symbol.clone(),
+                quote_symbol: trade.quote_symbol.clone(),
+                price: trade.price,
+                amount: trade.amount,
+                side: trade.side.clone(),
+                slot: trade.slot,
+                sequence: trade.sequence,
+                dex_program: trade.dex_program.clone(),
+                spread_bps: trade.spread_bps,
+                trader: trade.trader.clone().unwrap_or_default(),
+                quarantined: u8::from(*quarantined),
+            };
+            insert.write(&trade_row).await?;
+        }
+        insert.end().await?;
+        
+        Ok(())
+    }
+    
+    /// Retroactively flag already-stored trades as quarantined
+    pub async fn flag_trades_quarantined(&self, trade_ids: &[String]) -> Result<()> {
+        if trade_ids.is_empty() {
+            return Ok(());
+        }
+        
+        // The owner index is a copy, so the flag is applied to both tables
+        for table in ["trades", "trades_by_trader"] {
+            self.client
+                .query(&format!("ALTER TABLE {} UPDATE quarantined = 1 WHERE has(?, id)", table))
+                .bind(trade_ids)
+                .execute()
+                .await
+                .with_context(|| format!("Failed to flag quarantined trades in {}", table))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Which of the given trade IDs are already stored
+    pub async fn existing_trade_ids(&self, trade_ids: &[String]) -> Result<Vec<String>> {
+        if trade_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let _permit = self.read_permit().await?;
+        self.client
+            .query("SELECT DISTINCT id FROM trades WHERE has(?, id)")
+            .bind(trade_ids)
+            .fetch_all::<String>()
+            .await
+            .context("Failed to look up existing trade IDs")
+    }
+    
+    /// Append an admin event
+    pub async fn store_admin_event(&self, event_type: &str, subject: &str, details: &serde_json::Value) -> Result<()> {
+        let row = AdminEventRow {
+            created_at: OffsetDateTime::now_utc(),
+            event_type: event_type.to_string(),
+            subject: subject.to_string(),
+            details: details.to_string(),
+        };
+        
+        let mut inserter = self.client
+            .inserter("admin_events")?
+            .with_max_rows(1);
+        
+        inserter.write(&row)?;
+        inserter.end().await?;
+        
+        Ok(())
+    }
+    
+    /// Append a market alert
+    pub async fn store_market_alert(&self, kind: &str, pair: &str, details: &serde_json::Value) -> Result<()> {
+        let row = MarketAlertRow {
+            created_at: OffsetDateTime::now_utc(),
+            kind: kind.to_string(),
+            pair: pair.to_string(),
+            details: details.to_string(),
+        };
+        
+        let mut inserter = self.client
+            .inserter("market_alerts")?
+            .with_max_rows(1);
+        
+        inserter.write(&row)?;
+        inserter.end().await?;
+        
+        Ok(())
+    }
+    
+    /// Most recent market alerts, newest first, optionally for one pair and/or kind
+    pub async fn get_market_alerts(&self, pair: Option<&str>, kind: Option<&str>, limit: usize) -> Result<Vec<MarketAlertRow>> {
+        let _permit = self.read_permit().await?;
+        let mut sql = "SELECT ?fields FROM market_alerts WHERE 1 = 1".to_string();
+        if pair.is_some() {
+            sql.push_str(" AND pair = ?");
+        }
+        if kind.is_some() {
+            sql.push_str(" AND kind = ?");
+        }
+        sql.push_str(" ORDER BY created_at DESC LIMIT ?");
+        
+        let mut query = self.client.query(&sql);
+        if let Some(pair) = pair {
+            query = query.bind(pair);
+        }
+        if let Some(kind) = kind {
+            query = query.bind(kind);
+        }
+        query
+            .bind(limit as u64)
+            .fetch_all::<MarketAlertRow>()
+            .await
+            .context("Failed to query market alerts from ClickHouse")
+    }
+    
+    /// Current holder of a lease and when it expires, or None if it was never taken
+    pub async fn current_lease(&self, name: &str) -> Result<Option<LeaseRow>> {
+        let rows = self.client
+            .query("SELECT name,
+                        argMax(holder, (acquired_at, holder)) AS holder,
+                        max(acquired_at) AS acquired_at,
+                        argMax(expires_at, (acquired_at, holder)) AS expires_at
+                    FROM leases
+                    WHERE name = ?
+                    GROUP BY name")
+            .bind(name)
+            .fetch_all::<LeaseRow>()
+            .await
+            .with_context(|| format!("Failed to read lease {}", name))?;
+        
+        Ok(rows.into_iter().next())
+    }
+    
+    /// Latest claim on every lease
+    pub async fn list_leases(&self) -> Result<Vec<LeaseRow>> {
+        self.client
+            .query("SELECT name,
+                        argMax(holder, (acquired_at, holder)) AS holder,
+                        max(acquired_at) AS acquired_at,
+                        argMax(expires_at, (acquired_at, holder)) AS expires_at
+                    FROM leases
+                    GROUP BY name
+                    ORDER BY name")
+            .fetch_all::<LeaseRow>()
+            .await
+            .context("Failed to list leases")
+    }
+    
+    /// Take or renew a lease for `ttl`; true if `holder` holds it afterwards
+    /// A lease held by someone else is only taken once it has expired. Claims racing for a free lease are
+    /// settled by reading back after a short delay: the newest row wins, so every racer sees the same holder
+    pub async fn acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let now = OffsetDateTime::now_utc();
+        if let Some(current) = self.current_lease(name).await?
+            && current.holder != holder
+            && current.expires_at > now
+        {
+            return Ok(false);
+        }
+        
+        let row = LeaseRow {
+            name: name.to_string(),
+            holder: holder.to_string(),
+            acquired_at: now,
+            expires_at: now + ttl,
+        };
+        let mut inserter = self.client
+            .inserter("leases")?
+            .with_max_rows(1);
+        inserter.write(&row)?;
+        inserter.end().await.with_context(|| format!("Failed to claim lease {}", name))?;
+        
+        tokio::time::sleep(LEASE_SETTLE_DELAY).await;
+        Ok(self.current_lease(name).await?.is_some_and(|current| current.holder == holder))
+    }
+    
+    /// Winning claim on a nonce, if any sign-in has claimed it
+    async fn nonce_claim(&self, nonce_hash: &str) -> Result<Option<String>> {
+        let claims = self.client
+            .query("SELECT argMax(claim_id, (claimed_at, claim_id)) FROM consumed_nonces WHERE nonce_hash = ? GROUP BY nonce_hash")
+            .bind(nonce_hash)
+            .fetch_all::<String>()
+            .await
+            .context("Failed to read nonce claims")?;
+        Ok(claims.into_iter().next())
+    }
+    
+    /// Consume a nonce for one sign-in; false if it was already used, on this replica or another
+    /// Racing claims are settled like leases: after a short delay the newest claim wins for every racer
+    pub async fn claim_nonce(&self, nonce_hash: &str, expires_at: OffsetDateTime) -> Result<bool> {
+        if self.nonce_claim(nonce_hash).await?.is_some() {
+            return Ok(false);
+        }
+        
+        let claim_id = uuid::Uuid::new_v4().to_string();
+        let row = NonceClaimRow {
+            nonce_hash: nonce_hash.to_string(),
+            claim_id: claim_id.clone(),
+            claimed_at: OffsetDateTime::now_utc(),
+            expires_at,
+        };
+        let mut inserter = self.client
+            .inserter("consumed_nonces")?
+            .with_max_rows(1);
+        inserter.write(&row)?;
+        inserter.end().await.context("Failed to claim nonce")?;
+        
+        tokio::time::sleep(LEASE_SETTLE_DELAY).await;
+        Ok(self.nonce_claim(nonce_hash).await?.as_deref() == Some(claim_id.as_str()))
+    }
+    
+    /// Record a replica's heartbeat
+    pub async fn record_heartbeat(&self, row: &InstanceRow) -> Result<()> {
+        let mut inserter = self.client
+            .inserter("instances")?
+            .with_max_rows(1);
+        inserter.write(row)?;
+        inserter.end().await.context("Failed to record instance heartbeat")?;
+        Ok(())
+    }
+    
+    /// Latest heartbeat of every replica seen since `since`
+    pub async fn list_instances(&self, since: OffsetDateTime) -> Result<Vec<InstanceRow>> {
+        self.client
+            .query("SELECT ?fields FROM instances FINAL WHERE last_seen >= fromUnixTimestamp64Milli(toInt64(?)) ORDER BY instance_id")
+            .bind((since.unix_timestamp_nanos() / 1_000_000) as i64)
+            .fetch_all::<InstanceRow>()
+            .await
+            .context("Failed to list instances")
+    }
+    
+    /// Active part, row, and byte counts per table in the current database
+    pub async fn get_parts_health(&self) -> Result<Vec<PartsHealthRow>> {
+        self.client
+            .query("SELECT table, count() AS parts, sum(rows) AS rows, sum(bytes_on_disk) AS bytes_on_disk
+                    FROM system.parts
+                    WHERE active AND database = currentDatabase()
+                    GROUP BY table
+                    ORDER BY parts DESC")
+            .fetch_all::<PartsHealthRow>()
+            .await
+            .context("Failed to query part counts from ClickHouse")
+    }
+    
+    /// Trigger an unscheduled merge of a table's parts
+    /// `table` is interpolated into the statement, so callers must pass a known table name
+    pub async fn optimize_table(&self, table: &str) -> Result<()> {
+        self.client
+            .query(&format!("OPTIMIZE TABLE {}", table))
+            .execute()
+            .await
+            .with_context(|| format!("Failed to optimize table {}", table))
+    }
+    
+    /// Most recent admin events, newest first
+    pub async fn get_admin_events(&self, limit: usize) -> Result<Vec<AdminEventRow>> {
+        let _permit = self.read_permit().await?;
+        let rows = self.client
+            .query("SELECT ?fields FROM admin_events ORDER BY created_at DESC LIMIT ?")
+            .bind(limit as u64)
+            .fetch_all::<AdminEventRow>()
+            .await
+            .context("Failed to query admin events from ClickHouse")?;
+        
+        Ok(rows)
+    }
+    
+    /// Store a user session in ClickHouse
+    /// Uses the inserter pattern for type-safe insertion
+    pub async fn store_session(
+        &self,
+        user_pubkey: &str,
+        token: &str,
+        expires_at: chrono::DateTime<Utc>,
+    ) -> Result<()> {
+        let created_at = Utc::now();
+        
+        println!("📝 Attempting to store session in ClickHouse: user={}, created_at={}, expires_at={}", 
+            user_pubkey, created_at, expires_at);
+        
+        // Create SessionRow for insertion - convert chrono::DateTime<Utc> to time::OffsetDateTime
+        let session_row = SessionRow {
+            user_pubkey: user_pubkey.to_string(),
+            token: token.to_string(),
+            created_at: chrono_to_time(created_at),
+            expires_at: chrono_to_time(expires_at),
+        };
+        
+        // Use inserter pattern (type-safe, recommended by ClickHouse Rust client docs)
+        let mut inserter = self.client
+            .inserter("sessions")?
+            .with_max_rows(1);
+        
+        inserter.write(&session_row)?; // write() is not async, remove .await
+        inserter.end().await?;
+        
+        println!("✅ Session stored in ClickHouse for user: {}", user_pubkey);
+        
+        Ok(())
+    }
+    
+    /// End one session (logout, or the token a refresh replaced) with a revocation row; the insert
+    /// is acknowledged once written, so validate_session refuses the token from then on
+    pub async fn revoke_session(&self, user_pubkey: &str, token: &str) -> Result<()> {
+        let mut insert = self.client.insert("revoked_sessions")?;
+        insert.write(&RevokedSessionRow {
+            user_pubkey: user_pubkey.to_string(),
+            token: token.to_string(),
+            revoked_at: OffsetDateTime::now_utc(),
+        }).await?;
+        insert.end().await.context("Failed to revoke session")?;
+        
+        Ok(())
+    }
+    
+    /// Store a user preference (JSON-encoded value) keyed by wallet and setting name
+    pub async fn store_preference(&self, user_pubkey: &str, key: &str, value: &str) -> Result<()> {
+        let preference_row = PreferenceRow {
+            user_pubkey: user_pubkey.to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+            updated_at: OffsetDateTime::now_utc(),
+        };
+        
+        let mut inserter = self.client
+            .inserter("user_preferences")?
+            .with_max_rows(1);
+        
+        inserter.write(&preference_row)?;
+        inserter.end().await?;
+        
+        Ok(())
+    }
+    
+    /// Append a swap quote to the audit log
+    pub async fn store_swap_quote(&self, row: &SwapQuoteRow) -> Result<()> {
+        let mut inserter = self.client
+            .inserter("swap_quotes")?
+            .with_max_rows(1);
+        
+        inserter.write(row)?;
+        inserter.end().await?;
+        
+        Ok(())
+    }
+    
+    /// Record a platform fee accrued on a built swap
+    pub async fn store_platform_fee(&self, row: &PlatformFeeRow) -> Result<()> {
+        let mut inserter = self.client
+            .inserter("platform_fees")?
+            .with_max_rows(1);
+        
+        inserter.write(row)?;
+        inserter.end().await?;
+        
+        Ok(())
+    }
+    
+    /// Record a quoted mid-price tick
+    pub async fn store_price_tick(&self, row: &PriceTickRow) -> Result<()> {
+        let mut inserter = self.client
+            .inserter("prices")?
+            .with_max_rows(1);
+        
+        inserter.write(row)?;
+        inserter.end().await?;
+        
+        Ok(())
+    }
+    
+    /// Mid-price ticks for a pair within [from, to] (unix seconds), oldest first
+    pub async fn get_price_history(
+        &self,
+        base_symbol: &str,
+        quote_symbol: &str,
+        from: i64,
+        to: i64,
+        limit: usize,
+    ) -> Result<Vec<PriceTickRow>> {
+        let _permit = self.read_permit().await?;
+        let rows = self.analytics()
+            .query("SELECT ?fields FROM prices
+                    WHERE base_symbol = ? AND quote_symbol = ?
+                    AND created_at >= toDateTime64(?, 3, 'UTC') AND created_at <= toDateTime64(?, 3, 'UTC')
+                    ORDER BY created_at
+                    LIMIT ?")
+            .bind(base_symbol)
+            .bind(quote_symbol)
+            .bind(from)
+            .bind(to)
+            .bind(limit as u64)
+            .fetch_all::<PriceTickRow>()
+            .await
+            .context("Failed to query price history from ClickHouse")?;
+        
+        Ok(rows)
+    }
+    
+    /// Store a candle snapshot, and its id for lookups by id
+    pub async fn store_candle_snapshot(&self, row: &CandleSnapshotRow) -> Result<()> {
+        let mut inserter = self.client
+            .inserter("candle_snapshots")?
+            .with_max_rows(1);
+        
+        inserter.write(row)?;
+        inserter.end().await?;
+        
+        let mut ids = self.client
+            .inserter("candle_snapshot_ids")?
+            .with_max_rows(1);
+        
+        ids.write(&CandleSnapshotIdRow {
+            id: row.id.clone(),
+            pair: row.pair.clone(),
+            interval: row.interval.clone(),
+            up_to: row.up_to,
+            from_time: row.from_time,
+        })?;
+        ids.end().await?;
+        
+        Ok(())
+    }
+    
+    /// The snapshot served for a pair, interval and range: the earliest one taken
+    /// Racing first requests may each store one; picking the earliest makes them all agree afterwards
+    pub async fn find_candle_snapshot(&self, pair: &str, interval: &str, from_time: u64, up_to: u64) -> Result<Option<CandleSnapshotRow>> {
+        let _permit = self.read_permit().await?;
+        let rows = self.client
+            .query("SELECT ?fields FROM candle_snapshots
+                    WHERE pair = ? AND interval = ? AND from_time = ? AND up_to = ?
+                    ORDER BY created_at ASC, id ASC
+                    LIMIT 1")
+            .bind(pair)
+            .bind(interval)
+            .bind(from_time)
+            .bind(up_to)
+            .fetch_all::<CandleSnapshotRow>()
+            .await
+            .context("Failed to query candle snapshots from ClickHouse")?;
+        
+        Ok(rows.into_iter().next())
+    }
+    
+    /// A candle snapshot by its content hash, found through its sort key in `candle_snapshot_ids`
+    pub async fn get_candle_snapshot(&self, id: &str) -> Result<Option<CandleSnapshotRow>> {
+        let _permit = self.read_permit().await?;
+        let rows = self.client
+            .query("SELECT ?fields FROM candle_snapshots
+                    WHERE (pair, interval, up_to, from_time) IN
+                        (SELECT pair, interval, up_to, from_time FROM candle_snapshot_ids WHERE id = ?)
+                    AND id = ?
+                    LIMIT 1")
+            .bind(id)
+            .bind(id)
+            .fetch_all::<CandleSnapshotRow>()
+            .await
+            .context("Failed to query candle snapshot from ClickHouse")?;
+        
+        Ok(rows.into_iter().next())
+    }
+    
+    /// Record one usage sample (a row per watched channel and pair)
+    pub async fn store_ws_usage_samples(&self, rows: &[WsUsageSampleRow]) -> Result<()> {
+        let mut inserter = self.client
+            .inserter("ws_usage_samples")?
+            .with_max_rows(rows.len() as u64);
+        
+        for row in rows {
+            inserter.write(row)?;
+        }
+        inserter.end().await?;
+        
+        Ok(())
+    }
+    
+    /// WebSocket usage per (channel, pair) over the last `hours`, most watched first
+    /// `subscriber_samples` sums subscribers across samples (subscriber-minutes at one sample per minute)
+    pub async fn get_ws_usage(&self, hours: u64) -> Result<Vec<WsUsageSummaryRow>> {
+        let _permit = self.read_permit().await?;
+        self.analytics()
+            .query("SELECT channel, pair,
+                        sum(subscribers) AS subscriber_samples,
+                        max(subscribers) AS peak_subscribers,
+                        avg(subscribers) AS avg_subscribers
+                    FROM ws_usage_samples
+                    WHERE sampled_at >= now() - toIntervalHour(?)
+                    GROUP BY channel, pair
+                    ORDER BY subscriber_samples DESC")
+            .bind(hours)
+            .fetch_all::<WsUsageSummaryRow>()
+            .await
+            .context("Failed to query WebSocket usage from ClickHouse")
+    }
+    
+    /// Daily active and returning wallets over the last `days` days, oldest first
+    /// (whole UTC days, today included); a wallet is returning when its first session was on an earlier day
+    pub async fn get_session_days(&self, days: u64) -> Result<Vec<SessionDayRow>> {
+        let _permit = self.read_permit().await?;
+        self.analytics()
+            .query("SELECT toString(toDate(s.created_at)) AS day,
+                        count() AS sessions,
+                        uniqExact(s.user_pubkey) AS active_wallets,
+                        uniqExactIf(s.user_pubkey, f.first_day < toDate(s.created_at)) AS returning_wallets
+                    FROM sessions AS s
+                    INNER JOIN (
+                        SELECT user_pubkey, min(toDate(created_at)) AS first_day FROM sessions GROUP BY user_pubkey
+                    ) AS f ON s.user_pubkey = f.user_pubkey
+                    WHERE s.created_at >= toStartOfDay(now()) - toIntervalDay(?)
+                    GROUP BY day
+                    ORDER BY day")
+            .bind(days.saturating_sub(1))
+            .fetch_all::<SessionDayRow>()
+            .await
+            .context("Failed to query session days from ClickHouse")
+    }
+    
+    /// Active and returning wallets over the whole of the last `days` days (see `get_session_days`);
+    /// here returning means the wallet had a session before the window
+    pub async fn get_session_window(&self, days: u64) -> Result<SessionDayRow> {
+        let _permit = self.read_permit().await?;
+        self.analytics()
+            .query("SELECT '' AS day,
+                        count() AS sessions,
+                        uniqExact(s.user_pubkey) AS active_wallets,
+                        uniqExactIf(s.user_pubkey, f.first_day < toDate(toStartOfDay(now()) - toIntervalDay(?))) AS returning_wallets
+                    FROM sessions AS s
+                    INNER JOIN (
+                        SELECT user_pubkey, min(toDate(created_at)) AS first_day FROM sessions GROUP BY user_pubkey
+                    ) AS f ON s.user_pubkey = f.user_pubkey
+                    WHERE s.created_at >= toStartOfDay(now()) - toIntervalDay(?)")
+            .bind(days.saturating_sub(1))
+            .bind(days.saturating_sub(1))
+            .fetch_one::<SessionDayRow>()
+            .await
+            .context("Failed to query session window from ClickHouse")
+    }
+    
+    /// Duration of sessions created in the last `days` days that have ended: a session ends when it
+    /// expires, is revoked, or the wallet signs in again, whichever comes first; sessions still open are left out
+    pub async fn get_session_durations(&self, days: u64) -> Result<SessionDurationRow> {
+        let _permit = self.read_permit().await?;
+        self.analytics()
+            .query("SELECT count() AS sessions,
+                        avg(duration) AS mean_secs,
+                        quantile(0.5)(duration) AS p50_secs,
+                        quantile(0.9)(duration) AS p90_secs,
+                        max(duration) AS max_secs
+                    FROM (
+                        SELECT created_at,
+                            dateDiff('second', created_at, least(expires_at, ifNull(next_login, expires_at), ifNull(revoked_at, expires_at))) AS duration
+                        FROM (
+                            SELECT s.created_at AS created_at, s.expires_at AS expires_at, r.revoked_at AS revoked_at,
+                                leadInFrame(toNullable(s.created_at)) OVER (
+                                    PARTITION BY s.user_pubkey ORDER BY s.created_at ASC
+                                    ROWS BETWEEN CURRENT ROW AND 1 FOLLOWING
+                                ) AS next_login
+                            FROM sessions AS s
+                            LEFT JOIN (
+                                SELECT user_pubkey, token, min(revoked_at) AS revoked_at FROM revoked_sessions GROUP BY user_pubkey, token
+                            ) AS r ON s.user_pubkey = r.user_pubkey AND s.token = r.token
+                        )
+                        WHERE created_at >= toStartOfDay(now()) - toIntervalDay(?)
+                        AND (next_login IS NOT NULL OR revoked_at IS NOT NULL OR expires_at <= now())
+                    )")
+            .with_option("join_use_nulls", "1")
+            .bind(days.saturating_sub(1))
+            .fetch_one::<SessionDurationRow>()
+            .await
+            .context("Failed to query session durations from ClickHouse")
+    }
+    
+    /// Accrued platform fees per partner and fee mint
+    pub async fn get_platform_fee_totals(&self) -> Result<Vec<PlatformFeeTotal>> {
+        let _permit = self.read_permit().await?;
+        let rows = self.analytics()
+            .query("SELECT partner, fee_mint, count() AS swaps, sum(fee_amount) AS fee_amount
+                    FROM platform_fees
+                    GROUP BY partner, fee_mint
+                    ORDER BY partner, fee_mint")
+            .fetch_all::<PlatformFeeTotal>()
+            .await
+            .context("Failed to query platform fees from ClickHouse")?;
+        
+        Ok(rows)
+    }
+    
+    /// Get all preferences for a wallet as (key, JSON-encoded value) pairs
+    pub async fn get_preferences(&self, user_pubkey: &str) -> Result<Vec<(String, String)>> {
+        let _permit = self.read_permit().await?;
+        #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+        struct PreferenceValue {
+            key: String,
+            value: String,
+        }
+        
+        let rows = self.client
+            .query("SELECT key, value
+                    FROM user_preferences FINAL
+                    WHERE user_pubkey = ?
+                    ORDER BY key")
+            .bind(user_pubkey)
+            .fetch_all::<PreferenceValue>()
+            .await
+            .context("Failed to query user preferences from ClickHouse")?;
+        
+        Ok(rows.into_iter().map(|row| (row.key, row.value)).collect())
+    }
+    
+    /// Get every wallet's value for one preference key as (wallet, JSON-encoded value) pairs
+    pub async fn get_preference_values(&self, key: &str) -> Result<Vec<(String, String)>> {
+        let _permit = self.read_permit().await?;
+        #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+        struct WalletValue {
+            user_pubkey: String,
+            value: String,
+        }
+        
+        let rows = self.client
+            .query("SELECT user_pubkey, value
+                    FROM user_preferences FINAL
+                    WHERE key = ?")
+            .bind(key)
+            .fetch_all::<WalletValue>()
+            .await
+            .context("Failed to query preference values from ClickHouse")?;
+        
+        Ok(rows.into_iter().map(|row| (row.user_pubkey, row.value)).collect())
+    }
+    
+    /// Store a wallet's daily digest
+    pub async fn store_digest(&self, row: &DigestRow) -> Result<()> {
+        let mut inserter = self.client
+            .inserter("user_digests")?
+            .with_max_rows(1);
+        
+        inserter.write(row)?;
+        inserter.end().await?;
+        
+        Ok(())
+    }
+    
+    /// Store a follow (or, with `active` 0, an unfollow)
+    pub async fn store_follow(&self, row: &FollowRow) -> Result<()> {
+        let mut inserter = self.client
+            .inserter("wallet_follows")?
+            .with_max_rows(1);
+        
+        inserter.write(row)?;
+        inserter.end().await?;
         
         Ok(())
     }
     
-    /// Store a trade in ClickHouse
-    /// Uses the inserter pattern for type-safe insertion (recommended by ClickHouse Rust client docs)
-    pub async fn store_trade(&self, trade: &Trade) -> Result<()> {
-        // Create TradeRow for insertion - convert chrono::DateTime<Utc> to time::OffsetDateTime
-        // Only store fields required by assignment schema
-        let trade_row = TradeRow {
-            id: trade.id.clone(),
-            timestamp: chrono_to_time(trade.timestamp),
-            base_symbol: trade.base_symbol.clone(),
-            quote_symbol: trade.quote_symbol.clone(),
-            price: trade.price,
-            amount: trade.amount,
-            side: trade.side.clone(),
-        };
-        
-        println!("📝 Attempting to insert trade: {} {} {} @ ${:.6}", trade.side, trade.amount, trade.base_symbol, trade.price);
-        
-        // Use inserter pattern (type-safe, recommended by ClickHouse Rust client docs)
+    /// Every active follow, across all followers
+    pub async fn get_follows(&self) -> Result<Vec<FollowRow>> {
+        let _permit = self.read_permit().await?;
+        self.client
+            .query("SELECT ?fields FROM wallet_follows FINAL WHERE active = 1")
+            .fetch_all::<FollowRow>()
+            .await
+            .context("Failed to query wallet follows from ClickHouse")
+    }
+    
+    /// Store a trade that crossed the whale threshold
+    pub async fn store_whale_trade(&self, row: &WhaleTradeRow) -> Result<()> {
         let mut inserter = self.client
-            .inserter("trades")?
+            .inserter("whale_trades")?
             .with_max_rows(1);
         
-        inserter.write(&trade_row)?; // write() is not async, remove .await
+        inserter.write(row)?;
         inserter.end().await?;
         
-        println!("✅ Successfully inserted trade: {} {} {} @ ${:.6}", trade.side, trade.amount, trade.base_symbol, trade.price);
-        
         Ok(())
     }
     
-    /// Store a user session in ClickHouse
-    /// Uses the inserter pattern for type-safe insertion
-    pub async fn store_session(
+    /// Whale trades newest first, optionally for one pair (both stored directions), at least
+    /// `min_usd`, and older than `before`
+    pub async fn get_whale_trades(
         &self,
-        user_pubkey: &str,
-        token: &str,
-        expires_at: chrono::DateTime<Utc>,
-    ) -> Result<()> {
-        let created_at = Utc::now();
-        
-        println!("📝 Attempting to store session in ClickHouse: user={}, created_at={}, expires_at={}", 
-            user_pubkey, created_at, expires_at);
+        pair: Option<(&str, &str)>,
+        min_usd: Option<f64>,
+        before: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> Result<Vec<WhaleTradeRow>> {
+        let _permit = self.read_permit().await?;
+        let mut sql = "SELECT ?fields FROM whale_trades FINAL WHERE 1 = 1".to_string();
+        if pair.is_some() {
+            sql.push_str(" AND ((base_symbol = ? AND quote_symbol = ?) OR (base_symbol = ? AND quote_symbol = ?))");
+        }
+        if min_usd.is_some() {
+            sql.push_str(" AND usd_value >= ?");
+        }
+        if before.is_some() {
+            sql.push_str(" AND timestamp < fromUnixTimestamp64Milli(toInt64(?))");
+        }
+        sql.push_str(" ORDER BY timestamp DESC, id DESC LIMIT ?");
         
-        // Create SessionRow for insertion - convert chrono::DateTime<Utc> to time::OffsetDateTime
-        let session_row = SessionRow {
-            user_pubkey: user_pubkey.to_string(),
-            token: token.to_string(),
-            created_at: chrono_to_time(created_at),
-            expires_at: chrono_to_time(expires_at),
-        };
+        let mut query = self.client.query(&sql);
+        if let Some((base_symbol, quote_symbol)) = pair {
+            query = query.bind(base_symbol).bind(quote_symbol).bind(quote_symbol).bind(base_symbol);
+        }
+        if let Some(min_usd) = min_usd {
+            query = query.bind(min_usd);
+        }
+        if let Some(before) = before {
+            query = query.bind(before.timestamp_millis());
+        }
+        query
+            .bind(limit as u64)
+            .fetch_all::<WhaleTradeRow>()
+            .await
+            .context("Failed to query whale trades from ClickHouse")
+    }
+    
+    /// Most recent digest for a wallet, optionally only those dated before `before`
+    pub async fn latest_digest(&self, user_pubkey: &str, before: Option<time::Date>) -> Result<Option<DigestRow>> {
+        let _permit = self.read_permit().await?;
+        let mut sql = "SELECT ?fields FROM user_digests FINAL WHERE user_pubkey = ?".to_string();
+        if before.is_some() {
+            sql.push_str(" AND digest_date < toDate(?)");
+        }
+        sql.push_str(" ORDER BY digest_date DESC LIMIT 1");
         
-        // Use inserter pattern (type-safe, recommended by ClickHouse Rust client docs)
-        let mut inserter = self.client
-            .inserter("sessions")?
-            .with_max_rows(1);
+        let mut query = self.client.query(&sql).bind(user_pubkey);
+        if let Some(date) = before {
+            query = query.bind(date.to_string());
+        }
         
-        inserter.write(&session_row)?; // write() is not async, remove .await
-        inserter.end().await?;
+        let rows = query
+            .fetch_all::<DigestRow>()
+            .await
+            .context("Failed to query digests from ClickHouse")?;
         
-        println!("✅ Session stored in ClickHouse for user: {}", user_pubkey);
+        Ok(rows.into_iter().next())
+    }
+    
+    /// Trade query for a pair (both stored directions) with optional slot range, newest first
+    /// Columns are selected in the exact order of TradeRow
+    fn trades_query(&self, base_symbol: &str, quote_symbol: &str, limit: usize, filter: &TradeFilter) -> clickhouse::query::Query {
+        // Filter by pair in both directions (SOL/USDC or USDC/SOL)
+        let mut sql = "SELECT id, timestamp, base_symbol, quote_symbol, price, amount, side, slot, sequence, dex_program, spread_bps, trader
+                    FROM trades
+                    WHERE ((base_symbol = ? AND quote_symbol = ?) OR (base_symbol = ? AND quote_symbol = ?))
+                    AND quarantined = 0".to_string();
+        if filter.from_slot.is_some() {
+            sql.push_str(" AND slot >= ?");
+        }
+        if filter.to_slot.is_some() {
+            sql.push_str(" AND slot <= ?");
+        }
+        if let Some(before) = &filter.before {
+            sql.push_str(&before.condition("<"));
+        }
+        if let Some(after) = &filter.after {
+            sql.push_str(&after.condition(">"));
+        }
+        // Slot-filtered queries page through blocks in order; paging forward from an `after` cursor
+        // reads oldest first so each page continues where the last one ended
+        if filter.from_slot.is_some() || filter.to_slot.is_some() {
+            sql.push_str(" ORDER BY slot DESC, sequence DESC LIMIT ?");
+        } else if filter.after.is_some() && filter.before.is_none() {
+            sql.push_str(" ORDER BY timestamp ASC, sequence ASC, id ASC LIMIT ?");
+        } else {
+            sql.push_str(" ORDER BY timestamp DESC, sequence DESC, id DESC LIMIT ?");
+        }
         
-        Ok(())
+        let mut query = self.client
+            .query(&sql)
+            .with_option("final", "1")
+            .bind(base_symbol)
+            .bind(quote_symbol)
+            .bind(quote_symbol)  // Reverse direction
+            .bind(base_symbol);   // Reverse direction
+        if let Some(from_slot) = filter.from_slot {
+            query = query.bind(from_slot);
+        }
+        if let Some(to_slot) = filter.to_slot {
+            query = query.bind(to_slot);
+        }
+        if let Some(before) = &filter.before {
+            query = before.bind(query);
+        }
+        if let Some(after) = &filter.after {
+            query = after.bind(query);
+        }
+        query.bind(limit as u64)
+    }
+    
+    /// Cursor position of a stored trade of a pair (either orientation), for `before`/`after` cursors
+    /// given as a transaction signature; `idx_id` lets the lookup skip granules without it
+    pub async fn trade_position(&self, id: &str, base_symbol: &str, quote_symbol: &str) -> Result<Option<TradePosition>> {
+        let _permit = self.read_permit().await?;
+        let row = self.analytics()
+            .query("SELECT toInt64(toUnixTimestamp64Milli(timestamp)), sequence FROM trades
+                    WHERE id = ?
+                    AND ((base_symbol = ? AND quote_symbol = ?) OR (base_symbol = ? AND quote_symbol = ?))
+                    LIMIT 1")
+            .bind(id)
+            .bind(base_symbol)
+            .bind(quote_symbol)
+            .bind(quote_symbol)
+            .bind(base_symbol)
+            .fetch_optional::<(i64, u64)>()
+            .await
+            .context("Failed to look up trade position")?;
+        Ok(row.map(|(timestamp_ms, sequence)| TradePosition {
+            timestamp_ms,
+            trade: Some((sequence, id.to_string())),
+        }))
     }
     
     /// Get recent trades filtered by pair
@@ -216,195 +2179,527 @@ impl ClickHouseService {
         base_symbol: &str,
         quote_symbol: &str,
         limit: usize,
+        filter: &TradeFilter,
     ) -> Result<Vec<Trade>> {
+        // Rows are converted as the cursor yields them (time::OffsetDateTime -> chrono::DateTime<Utc>,
+        // reverse-direction rows flipped), so only the resulting trades are held in memory
+        let permit = self.read_permit().await?;
+        let mut cursor = self.open_trades_cursor(permit, base_symbol, quote_symbol, limit, filter)?;
+        let mut trades = Vec::with_capacity(limit.min(1024));
+        loop {
+            match cursor.next().await {
+                Ok(Some(trade)) => trades.push(trade),
+                Ok(None) => break,
+                Err(e) => {
+                    let error_msg = format!("{}", e);
+                    eprintln!("❌ ClickHouse query error: {}", error_msg);
+                    eprintln!("   Query: SELECT ... FROM trades WHERE (base_symbol = '{}' AND quote_symbol = '{}') OR (base_symbol = '{}' AND quote_symbol = '{}') LIMIT {}", base_symbol, quote_symbol, quote_symbol, base_symbol, limit);
+                    return Err(e).with_context(|| format!("Failed to query trades from ClickHouse for pair {}/{}: {}", base_symbol, quote_symbol, error_msg));
+                }
+            }
+        }
+        println!("✅ ClickHouse query successful, fetched {} rows", trades.len());
         
-        // Query - DateTime<Utc> is handled automatically by serde with time feature
-        // Must select columns in the exact order of TradeRow struct
-        // Filter by pair in both directions (SOL/USDC or USDC/SOL)
-        let query_result = self.client
-            .query("SELECT id, timestamp, base_symbol, quote_symbol, price, amount, side
+        Ok(trades)
+    }
+    
+    /// Same rows as `get_trades`, streamed from a row cursor instead of buffered
+    /// The cursor holds a slot of the stream pool (CLICKHOUSE_MAX_CONCURRENT_STREAMS) until dropped
+    pub async fn stream_trades(
+        &self,
+        base_symbol: &str,
+        quote_symbol: &str,
+        limit: usize,
+        filter: &TradeFilter,
+    ) -> Result<TradeCursor> {
+        let permit = self.stream_permit().await?;
+        self.open_trades_cursor(permit, base_symbol, quote_symbol, limit, filter)
+    }
+
+    fn open_trades_cursor(
+        &self,
+        permit: OwnedSemaphorePermit,
+        base_symbol: &str,
+        quote_symbol: &str,
+        limit: usize,
+        filter: &TradeFilter,
+    ) -> Result<TradeCursor> {
+        let cursor = self.trades_query(base_symbol, quote_symbol, limit, filter)
+            .fetch::<TradeRow>()
+            .context("Failed to open trades cursor")?;
+        
+        Ok(TradeCursor {
+            cursor,
+            orientation: Some((base_symbol.to_string(), quote_symbol.to_string())),
+            _permit: permit,
+        })
+    }
+    
+    /// Spread-to-oracle statistics per DEX over the last `hours`, optionally for one pair (either orientation)
+    pub async fn get_execution_quality(&self, pair: Option<(&str, &str)>, hours: u64) -> Result<Vec<ExecutionQualityRow>> {
+        let _permit = self.read_permit().await?;
+        let mut sql = "SELECT dex_program,
+                    count() AS trades,
+                    avg(if(side = 'sell', -assumeNotNull(spread_bps), assumeNotNull(spread_bps))) AS avg_cost_bps,
+                    avg(abs(assumeNotNull(spread_bps))) AS avg_abs_spread_bps,
+                    quantile(0.5)(abs(assumeNotNull(spread_bps))) AS p50_abs_spread_bps,
+                    quantile(0.95)(abs(assumeNotNull(spread_bps))) AS p95_abs_spread_bps
+                FROM trades
+                WHERE spread_bps IS NOT NULL AND dex_program != '' AND quarantined = 0
+                AND timestamp >= now() - toIntervalHour(?)".to_string();
+        if pair.is_some() {
+            sql.push_str(" AND ((base_symbol = ? AND quote_symbol = ?) OR (base_symbol = ? AND quote_symbol = ?))");
+        }
+        sql.push_str(" GROUP BY dex_program ORDER BY trades DESC");
+        
+        let mut query = self.analytics().query(&sql).with_option("final", "1").bind(hours);
+        if let Some((base_symbol, quote_symbol)) = pair {
+            query = query
+                .bind(base_symbol)
+                .bind(quote_symbol)
+                .bind(quote_symbol)
+                .bind(base_symbol);
+        }
+        query
+            .fetch_all::<ExecutionQualityRow>()
+            .await
+            .context("Failed to query execution quality from ClickHouse")
+    }
+    
+    /// Volume, trade count and average trade size per DEX for a pair (either orientation) over the last `hours`
+    /// Trades stored in the other orientation are converted, so volumes are always in `base_symbol`/`quote_symbol`
+    pub async fn get_dex_volume(&self, base_symbol: &str, quote_symbol: &str, hours: u64) -> Result<Vec<DexVolumeRow>> {
+        let _permit = self.read_permit().await?;
+        self.analytics()
+            .query("SELECT dex_program,
+                        count() AS trades,
+                        sum(if(base_symbol = ?, amount, amount * price)) AS base_volume,
+                        sum(if(base_symbol = ?, amount * price, amount)) AS quote_volume,
+                        base_volume / trades AS avg_trade_size,
+                        quote_volume / trades AS avg_trade_value
+                    FROM trades
+                    WHERE dex_program != '' AND quarantined = 0
+                    AND timestamp >= now() - toIntervalHour(?)
+                    AND ((base_symbol = ? AND quote_symbol = ?) OR (base_symbol = ? AND quote_symbol = ?))
+                    GROUP BY dex_program
+                    ORDER BY quote_volume DESC")
+            .with_option("final", "1")
+            .bind(base_symbol)
+            .bind(base_symbol)
+            .bind(hours)
+            .bind(base_symbol)
+            .bind(quote_symbol)
+            .bind(quote_symbol)
+            .bind(base_symbol)
+            .fetch_all::<DexVolumeRow>()
+            .await
+            .context("Failed to query DEX volume from ClickHouse")
+    }
+    
+    /// Last price, first price, and quote volume over the 24h ending at `as_of` (default now)
+    /// for every stored pair direction
+    pub async fn get_market_summaries(&self, as_of: Option<DateTime<Utc>>) -> Result<Vec<MarketSummaryRow>> {
+        self.get_window_summaries(as_of, 24).await
+    }
+    
+    /// Like `get_market_summaries`, over the `hours` ending at `as_of`
+    pub async fn get_window_summaries(&self, as_of: Option<DateTime<Utc>>, hours: u64) -> Result<Vec<MarketSummaryRow>> {
+        let _permit = self.read_permit().await?;
+        let end = window_end_millis(as_of);
+        self.analytics()
+            .query("SELECT base_symbol, quote_symbol,
+                        argMax(price, (sequence, timestamp)) AS last_price,
+                        argMin(price, (sequence, timestamp)) AS first_price,
+                        sum(amount * price) AS volume
+                    FROM trades
+                    WHERE timestamp > fromUnixTimestamp64Milli(toInt64(?)) - toIntervalHour(?)
+                    AND timestamp <= fromUnixTimestamp64Milli(toInt64(?))
+                    AND quarantined = 0
+                    GROUP BY base_symbol, quote_symbol")
+            .with_option("final", "1")
+            .bind(end)
+            .bind(hours)
+            .bind(end)
+            .fetch_all::<MarketSummaryRow>()
+            .await
+            .context("Failed to query market summaries from ClickHouse")
+    }
+    
+    /// First and last trade time per stored (base, quote) pair
+    pub async fn get_pair_activity(&self, as_of: Option<DateTime<Utc>>) -> Result<Vec<(String, String, DateTime<Utc>, DateTime<Utc>)>> {
+        let _permit = self.read_permit().await?;
+        #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+        struct PairActivityRow {
+            base_symbol: String,
+            quote_symbol: String,
+            #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+            first_seen: OffsetDateTime,
+            #[serde(with = "clickhouse::serde::time::datetime64::millis")]
+            last_seen: OffsetDateTime,
+        }
+        
+        let rows = self.analytics()
+            .query("SELECT base_symbol, quote_symbol, min(timestamp) AS first_seen, max(timestamp) AS last_seen
+                    FROM trades
+                    WHERE quarantined = 0 AND timestamp <= fromUnixTimestamp64Milli(toInt64(?))
+                    GROUP BY base_symbol, quote_symbol")
+            .bind(window_end_millis(as_of))
+            .fetch_all::<PairActivityRow>()
+            .await
+            .context("Failed to query pair activity from ClickHouse")?;
+        
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.base_symbol, row.quote_symbol, time_to_chrono(row.first_seen), time_to_chrono(row.last_seen)))
+            .collect())
+    }
+    
+    /// Median trade size per token symbol over the last day, counting both sides of each trade
+    /// (base side in base tokens, quote side as amount * price)
+    pub async fn get_median_trade_sizes(&self) -> Result<Vec<(String, f64)>> {
+        let _permit = self.read_permit().await?;
+        #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+        struct MedianSizeRow {
+            symbol: String,
+            median_size: f64,
+        }
+        
+        let rows = self.analytics()
+            .query("SELECT symbol, quantile(0.5)(size) AS median_size
+                    FROM (
+                        SELECT base_symbol AS symbol, amount AS size FROM trades
+                        WHERE quarantined = 0 AND timestamp >= now() - INTERVAL 1 DAY
+                        UNION ALL
+                        SELECT quote_symbol AS symbol, amount * price AS size FROM trades
+                        WHERE quarantined = 0 AND timestamp >= now() - INTERVAL 1 DAY
+                    )
+                    WHERE size > 0
+                    GROUP BY symbol")
+            .with_option("final", "1")
+            .fetch_all::<MedianSizeRow>()
+            .await
+            .context("Failed to query median trade sizes from ClickHouse")?;
+        
+        Ok(rows.into_iter().map(|row| (row.symbol, row.median_size)).collect())
+    }
+    
+    /// Accepted trades, median oracle deviation and trade gaps per pair (either direction) over the
+    /// last `window_secs`; a gap is a stretch longer than `gap_secs` without trades, counting the
+    /// stretches before the first and after the last trade of the window
+    pub async fn get_pair_quality_inputs(&self, window_secs: u64, gap_secs: u64) -> Result<Vec<PairQualityRow>> {
+        let _permit = self.read_permit().await?;
+        let rows = self.analytics()
+            .query("SELECT
+                        concat(least(base_symbol, quote_symbol), '/', greatest(base_symbol, quote_symbol)) AS pair,
+                        count() AS trades,
+                        quantile(0.5)(abs(spread_bps)) AS median_deviation_bps,
+                        toUInt64(arrayCount(d -> d > ?, arrayDifference(arrayConcat(
+                            [toInt64(toUnixTimestamp(now())) - ?],
+                            arraySort(groupArray(toInt64(toUnixTimestamp(timestamp)))),
+                            [toInt64(toUnixTimestamp(now()))]
+                        )))) AS gaps
+                    FROM trades
+                    WHERE quarantined = 0 AND timestamp > now() - INTERVAL ? SECOND
+                    GROUP BY pair")
+            .with_option("final", "1")
+            .bind(gap_secs as i64)
+            .bind(window_secs as i64)
+            .bind(window_secs)
+            .fetch_all::<PairQualityRow>()
+            .await
+            .context("Failed to query pair quality inputs from ClickHouse")?;
+        Ok(rows)
+    }
+    
+    /// Highest ingest sequence per stored (base, quote) pair over the last day, quarantined rows included
+    pub async fn get_last_sequences(&self) -> Result<Vec<(String, String, u64)>> {
+        let _permit = self.read_permit().await?;
+        #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
+        struct LastSequenceRow {
+            base_symbol: String,
+            quote_symbol: String,
+            sequence: u64,
+        }
+        
+        let rows = self.client
+            .query("SELECT base_symbol, quote_symbol, max(sequence) AS sequence
+                    FROM trades
+                    WHERE timestamp > fromUnixTimestamp64Milli(toInt64(?)) - INTERVAL 24 HOUR
+                    GROUP BY base_symbol, quote_symbol")
+            .bind(window_end_millis(None))
+            .fetch_all::<LastSequenceRow>()
+            .await
+            .context("Failed to query last sequences from ClickHouse")?;
+        
+        Ok(rows.into_iter().map(|row| (row.base_symbol, row.quote_symbol, row.sequence)).collect())
+    }
+    
+    /// Count stored trades for a pair (both directions)
+    pub async fn count_trades(&self, base_symbol: &str, quote_symbol: &str) -> Result<u64> {
+        let _permit = self.read_permit().await?;
+        self.analytics()
+            .query("SELECT count()
+                    FROM trades
+                    WHERE ((base_symbol = ? AND quote_symbol = ?) OR (base_symbol = ? AND quote_symbol = ?))
+                    AND quarantined = 0")
+            .with_option("final", "1")
+            .bind(base_symbol)
+            .bind(quote_symbol)
+            .bind(quote_symbol)
+            .bind(base_symbol)
+            .fetch_one::<u64>()
+            .await
+            .context("Failed to count trades in ClickHouse")
+    }
+    
+    /// Open a row cursor over a pair's trades (newest first) for streaming consumers like exports
+    pub async fn trades_cursor(&self, base_symbol: &str, quote_symbol: &str, limit: usize) -> Result<TradeCursor> {
+        let permit = self.read_permit().await?;
+        let cursor = self.analytics()
+            .query("SELECT id, timestamp, base_symbol, quote_symbol, price, amount, side, slot, sequence, dex_program, spread_bps, trader
                     FROM trades
-                    WHERE (base_symbol = ? AND quote_symbol = ?) OR (base_symbol = ? AND quote_symbol = ?)
-                    ORDER BY timestamp DESC
+                    WHERE ((base_symbol = ? AND quote_symbol = ?) OR (base_symbol = ? AND quote_symbol = ?))
+                    AND quarantined = 0
+                    ORDER BY timestamp DESC, sequence DESC
                     LIMIT ?")
+            .with_option("final", "1")
             .bind(base_symbol)
             .bind(quote_symbol)
-            .bind(quote_symbol)  // Reverse direction
-            .bind(base_symbol)    // Reverse direction
+            .bind(quote_symbol)
+            .bind(base_symbol)
             .bind(limit as u64)
-            .fetch_all::<TradeRow>()
-            .await;
-        
-        let cursor = match query_result {
-            Ok(cursor) => {
-                println!("✅ ClickHouse query successful, fetched {} rows", cursor.len());
-                cursor
-            }
-            Err(e) => {
-                let error_msg = format!("{}", e);
-                eprintln!("❌ ClickHouse query error: {}", error_msg);
-                eprintln!("   Query: SELECT ... FROM trades WHERE (base_symbol = '{}' AND quote_symbol = '{}') OR (base_symbol = '{}' AND quote_symbol = '{}') LIMIT {}", base_symbol, quote_symbol, quote_symbol, base_symbol, limit);
-                return Err(e).with_context(|| format!("Failed to query trades from ClickHouse for pair {}/{}: {}", base_symbol, quote_symbol, error_msg));
-            }
-        };
+            .fetch::<TradeRow>()
+            .context("Failed to open trades cursor")?;
         
-        // Convert to Trade structs - convert time::OffsetDateTime back to chrono::DateTime<Utc>
-        // Note: Extra fields (base_mint, quote_mint, total_value, dex_program, slot) are not stored in ClickHouse
-        // They will be set to default values when reading from ClickHouse
-        let trades: Vec<Trade> = cursor
-            .iter()
-            .map(|row| Trade {
-                id: row.id.clone(),
-                timestamp: time_to_chrono(row.timestamp),
-                base_symbol: row.base_symbol.clone(),
-                quote_symbol: row.quote_symbol.clone(),
-                base_mint: String::new(), // Not stored in ClickHouse per assignment
-                quote_mint: String::new(), // Not stored in ClickHouse per assignment
-                price: row.price,
-                amount: row.amount,
-                side: row.side.clone(),
-                total_value: row.price * row.amount, // Calculate from stored price and amount
-                dex_program: String::new(), // Not stored in ClickHouse per assignment
-                slot: 0, // Not stored in ClickHouse per assignment
-            })
-            .collect();
+        Ok(TradeCursor { cursor, orientation: None, _permit: permit })
+    }
+    
+    /// A wallet's most recent trades (as stored, newest first), read from the trades_by_trader owner index
+    pub async fn get_wallet_trades(&self, trader: &str, limit: usize) -> Result<Vec<Trade>> {
+        let permit = self.read_permit().await?;
+        let cursor = self.analytics()
+            .query("SELECT id, timestamp, base_symbol, quote_symbol, price, amount, side, slot, sequence, dex_program, spread_bps, trader
+                    FROM trades_by_trader
+                    WHERE trader = ? AND quarantined = 0
+                    ORDER BY timestamp DESC, sequence DESC
+                    LIMIT ?")
+            .with_option("final", "1")
+            .bind(trader)
+            .bind(limit as u64)
+            .fetch::<TradeRow>()
+            .context("Failed to open wallet trades cursor")?;
         
+        let mut cursor = TradeCursor { cursor, orientation: None, _permit: permit };
+        let mut trades = Vec::with_capacity(limit.min(1024));
+        while let Some(trade) = cursor.next().await.context("Failed to query wallet trades from ClickHouse")? {
+            trades.push(trade);
+        }
         Ok(trades)
     }
     
     /// Get OHLCV data aggregated from ClickHouse
+    /// Candles from the one containing `from` up to trades before `to` (unix seconds); without `from`,
+    /// the most recent `max_candles` candles before `to` (default now)
     pub async fn get_ohlcv(
         &self,
         base_symbol: &str,
         quote_symbol: &str,
         interval: &str,
+        max_candles: u64,
+        from: Option<u64>,
+        to: Option<u64>,
     ) -> Result<Vec<serde_json::Value>> {
-        // Define row struct for OHLCV aggregation results
-        #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
-        struct OHLCVRow {
-            time: u64,
-            open: f64,
-            high: f64,
-            low: f64,
-            close: f64,
-            volume: f64,
-        }
-        
-        // Convert interval to ClickHouse format
-        let interval_sql = match interval {
-            "1m" => "1 MINUTE",
-            "5m" => "5 MINUTE",
-            "15m" => "15 MINUTE",
-            "1h" => "1 HOUR",
-            "4h" => "4 HOUR",
-            "1d" => "1 DAY",
-            _ => "1 MINUTE",
-        };
+        // Only aggregate the window the most recent `max_candles` candles can cover
+        let interval_secs = interval_seconds(interval).unwrap_or(60);
+        let window_start = from
+            .unwrap_or_else(|| {
+                to.unwrap_or(Utc::now().timestamp() as u64)
+                    .saturating_sub(max_candles.saturating_sub(1) * interval_secs)
+            })
+            / interval_secs
+            * interval_secs;
+        
+        // Convert to JSON format as candles arrive
+        let mut cursor = self.candles_cursor(base_symbol, quote_symbol, interval, window_start, to).await?;
+        let mut ohlcv_data = Vec::new();
+        while let Some(row) = cursor.next().await? {
+            ohlcv_data.push(json!({
+                "time": row.time,
+                "open": row.open,
+                "high": row.high,
+                "low": row.low,
+                "close": row.close,
+                "volume": row.volume,
+            }));
+        }
+        
+        Ok(ohlcv_data)
+    }
+    
+    /// Candles for a pair from `from` (inclusive) to `to` (exclusive, open-ended when None), unix seconds, oldest first
+    /// Intervals without trades have no candle
+    pub async fn get_candles(
+        &self,
+        base_symbol: &str,
+        quote_symbol: &str,
+        interval: &str,
+        from: u64,
+        to: Option<u64>,
+    ) -> Result<Vec<Candle>> {
+        let mut cursor = self.candles_cursor(base_symbol, quote_symbol, interval, from, to).await?;
+        let mut candles = Vec::new();
+        while let Some(candle) = cursor.next().await? {
+            candles.push(candle);
+        }
+        Ok(candles)
+    }
+    
+    /// Open a row cursor over a pair's candles (see `get_candles`)
+    pub async fn candles_cursor(
+        &self,
+        base_symbol: &str,
+        quote_symbol: &str,
+        interval: &str,
+        from: u64,
+        to: Option<u64>,
+    ) -> Result<CandleCursor> {
+        let permit = self.read_permit().await?;
+        let interval_sql = interval_sql(interval);
         
         // Query with OHLC aggregation
-        let cursor = self.client
+        // Open/close are picked by ingest sequence ((slot << 16) | position in slot) so fills in the
+        // same block resolve deterministically; timestamp only breaks ties for pre-sequence rows (sequence = 0)
+        let query = self.client
             .query(&format!(
                 "SELECT
-                    toUnixTimestamp(toStartOfInterval(timestamp, INTERVAL {})) as time,
-                    argMin(price, timestamp) as open,
+                    toUInt64(toUnixTimestamp(toStartOfInterval(timestamp, INTERVAL {}))) as time,
+                    argMin(price, (sequence, timestamp)) as open,
                     max(price) as high,
                     min(price) as low,
-                    argMax(price, timestamp) as close,
+                    argMax(price, (sequence, timestamp)) as close,
                     sum(amount * price) as volume
                 FROM trades
-                WHERE base_symbol = ? AND quote_symbol = ?
+                WHERE base_symbol = ? AND quote_symbol = ? AND quarantined = 0
+                AND timestamp >= toDateTime(?){}
                 GROUP BY time
                 ORDER BY time ASC",
-                interval_sql
+                interval_sql,
+                if to.is_some() { " AND timestamp < toDateTime(?)" } else { "" }
             ))
+            .with_option("final", "1")
             .bind(base_symbol)
             .bind(quote_symbol)
-            .fetch_all::<OHLCVRow>()
-            .await
+            .bind(from);
+        let query = match to {
+            Some(to) => query.bind(to),
+            None => query,
+        };
+        let cursor = query
+            .fetch::<Candle>()
             .context("Failed to query OHLCV from ClickHouse")?;
         
-        // Convert to JSON format
-        let ohlcv_data: Vec<serde_json::Value> = cursor
-            .iter()
-            .map(|row| {
-                json!({
-                    "time": row.time,
-                    "open": row.open,
-                    "high": row.high,
-                    "low": row.low,
-                    "close": row.close,
-                    "volume": row.volume,
-                })
-            })
-            .collect();
+        Ok(CandleCursor { cursor, _permit: permit })
+    }
+    
+    /// Most recent candles of `base_symbol` against any of `quote_symbols`, merging their fills into one series
+    /// (see `get_ohlcv` for the window); quote tokens are assumed to trade at par, e.g. USDC and USDT
+    pub async fn get_combined_ohlcv(
+        &self,
+        base_symbol: &str,
+        quote_symbols: &[&str],
+        interval: &str,
+        max_candles: u64,
+    ) -> Result<Vec<CombinedCandle>> {
+        let _permit = self.read_permit().await?;
+        let interval_secs = interval_seconds(interval).unwrap_or(60);
+        let window_start = (Utc::now().timestamp() as u64)
+            .saturating_sub(max_candles.saturating_sub(1) * interval_secs)
+            / interval_secs
+            * interval_secs;
         
-        Ok(ohlcv_data)
+        // Sequences are slot-based, so open/close order fills across the quote pairs too
+        self.analytics()
+            .query(&format!(
+                "SELECT
+                    toUInt64(toUnixTimestamp(toStartOfInterval(timestamp, INTERVAL {}))) as time,
+                    argMin(price, (sequence, timestamp)) as open,
+                    max(price) as high,
+                    min(price) as low,
+                    argMax(price, (sequence, timestamp)) as close,
+                    sum(amount * price) as volume,
+                    sum(amount * price) / sum(amount) as vwap,
+                    count() as trades
+                FROM trades
+                WHERE base_symbol = ? AND has(?, quote_symbol) AND quarantined = 0
+                AND timestamp >= toDateTime(?)
+                GROUP BY time
+                HAVING sum(amount) > 0
+                ORDER BY time ASC",
+                interval_sql(interval)
+            ))
+            .with_option("final", "1")
+            .bind(base_symbol)
+            .bind(quote_symbols)
+            .bind(window_start)
+            .fetch_all::<CombinedCandle>()
+            .await
+            .context("Failed to query combined OHLCV from ClickHouse")
     }
     
-    /// Get 24h stats for a pair
+    /// Get 24h stats for a pair, over the 24h ending at `as_of` (default now)
     pub async fn get_24h_stats(
         &self,
         base_symbol: &str,
         quote_symbol: &str,
+        as_of: Option<DateTime<Utc>>,
     ) -> Result<serde_json::Value> {
-        // Define row struct for 24h stats
-        #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
-        struct StatsRow {
-            current_price: f64,
-            low_24h: f64,
-            high_24h: f64,
-            volume_24h: f64,
-            first_price: f64,
-            last_price: f64,
-        }
-        
-        let cursor = self.client
+        let _permit = self.read_permit().await?;
+        let end = window_end_millis(as_of);
+        let rows = self.client
             .query("SELECT
-                argMax(price, timestamp) as current_price,
-                min(price) as low_24h,
+                argMax(price, (sequence, timestamp)) as last_price,
+                argMin(price, (sequence, timestamp)) as first_price,
                 max(price) as high_24h,
+                min(price) as low_24h,
                 sum(amount * price) as volume_24h,
-                argMin(price, timestamp) as first_price,
-                argMax(price, timestamp) as last_price
+                count() as trades_24h
             FROM trades
-            WHERE base_symbol = ? AND quote_symbol = ?
-            AND timestamp >= now() - INTERVAL 24 HOUR")
+            WHERE base_symbol = ? AND quote_symbol = ? AND quarantined = 0
+            AND timestamp > fromUnixTimestamp64Milli(toInt64(?)) - INTERVAL 24 HOUR
+            AND timestamp <= fromUnixTimestamp64Milli(toInt64(?))")
+            .with_option("final", "1")
             .bind(base_symbol)
             .bind(quote_symbol)
-            .fetch_all::<StatsRow>()
+            .bind(end)
+            .bind(end)
+            .fetch_all::<PairStatsRow>()
             .await
             .context("Failed to query 24h stats from ClickHouse")?;
         
-        if let Some(row) = cursor.first() {
-            let change_24h = row.last_price - row.first_price;
-            let change_percent_24h = if row.first_price > 0.0 {
-                (change_24h / row.first_price) * 100.0
-            } else {
-                0.0
-            };
-            
-            return Ok(json!({
-                "currentPrice": row.current_price,
-                "high24h": row.high_24h,
-                "low24h": row.low_24h,
-                "volume24h": row.volume_24h,
-                "change24h": change_24h,
-                "changePercent24h": change_percent_24h,
-            }));
-        }
-        
-        Ok(json!({
-            "currentPrice": 0.0,
-            "high24h": 0.0,
-            "low24h": 0.0,
-            "volume24h": 0.0,
-            "change24h": 0.0,
-            "changePercent24h": 0.0,
-        }))
+        Ok(rows.first().map(PairStatsRow::to_json).unwrap_or_else(|| PairStatsRow::default().to_json()))
+    }
+    
+    /// Unquarantined trades of one stored direction from the last 24h, oldest first
+    pub async fn get_window_trades(&self, base_symbol: &str, quote_symbol: &str) -> Result<Vec<WindowTradeRow>> {
+        let _permit = self.read_permit().await?;
+        self.client
+            .query("SELECT id, timestamp, price, amount
+                    FROM trades
+                    WHERE base_symbol = ? AND quote_symbol = ? AND quarantined = 0
+                    AND timestamp > fromUnixTimestamp64Milli(toInt64(?)) - INTERVAL 24 HOUR
+                    ORDER BY timestamp, sequence")
+            .with_option("final", "1")
+            .bind(base_symbol)
+            .bind(quote_symbol)
+            .bind(window_end_millis(None))
+            .fetch_all::<WindowTradeRow>()
+            .await
+            .context("Failed to query window trades from ClickHouse")
     }
     
     /// Check if a session is valid
     pub async fn validate_session(&self, user_pubkey: &str, token: &str) -> Result<bool> {
+        let _permit = self.read_permit().await?;
         #[derive(Debug, Serialize, Deserialize, clickhouse::Row)]
         struct SessionCheck {
             count: u8,
@@ -414,9 +2709,11 @@ impl ClickHouseService {
             .query("SELECT 1 as count
                     FROM sessions
                     WHERE user_pubkey = ? AND token = ? AND expires_at > now()
+                    AND token NOT IN (SELECT token FROM revoked_sessions WHERE user_pubkey = ?)
                     LIMIT 1")
             .bind(user_pubkey)
             .bind(token)
+            .bind(user_pubkey)
             .fetch_all::<SessionCheck>()
             .await
             .context("Failed to validate session in ClickHouse")?;
@@ -424,14 +2721,145 @@ impl ClickHouseService {
         Ok(!cursor.is_empty())
     }
     
-    /// Delete expired sessions
-    pub async fn cleanup_expired_sessions(&self) -> Result<()> {
+    /// Erase what's stored about a wallet (privacy requests)
+    /// Personal rows go; rows kept for market data or accounting lose their attribution instead.
+    /// Small per-wallet tables are rewritten before returning; the trade history mutation finishes in the background
+    pub async fn delete_wallet_data(&self, wallet: &str) -> Result<serde_json::Value> {
+        const DELETED: &[(&str, &str)] = &[
+            ("sessions", "user_pubkey"),
+            ("revoked_sessions", "user_pubkey"),
+            ("user_preferences", "user_pubkey"),
+            ("user_digests", "user_pubkey"),
+            ("wallet_follows", "follower"),
+            ("wallet_follows", "followed"),
+            // The wallet is part of these tables' sort keys, so their rows can't be anonymized in place
+            ("swap_quotes", "user_pubkey"),
+            ("trades_by_trader", "trader"),
+        ];
+        const ANONYMIZED: &[(&str, &str, bool)] = &[
+            ("platform_fees", "user_pubkey", true),
+            ("whale_trades", "trader", true),
+            ("trades", "trader", false),
+        ];
+        
+        for (table, column) in DELETED {
+            self.client
+                .query(&format!("ALTER TABLE {} DELETE WHERE {} = ?", table, column))
+                .bind(wallet)
+                .with_option("mutations_sync", "1")
+                .execute()
+                .await
+                .with_context(|| format!("Failed to delete wallet data from {}", table))?;
+        }
+        for (table, column, sync) in ANONYMIZED {
+            let query = self.client
+                .query(&format!("ALTER TABLE {} UPDATE {} = '' WHERE {} = ?", table, column, column))
+                .bind(wallet);
+            let query = if *sync { query.with_option("mutations_sync", "1") } else { query };
+            query
+                .execute()
+                .await
+                .with_context(|| format!("Failed to anonymize wallet data in {}", table))?;
+        }
+        
+        let mut deleted: Vec<&str> = DELETED.iter().map(|(table, _)| *table).collect();
+        deleted.dedup();
+        Ok(json!({
+            "deleted": deleted,
+            "anonymized": ANONYMIZED.iter().map(|(table, column, _)| format!("{}.{}", table, column)).collect::<Vec<_>>(),
+            "pending": ANONYMIZED.iter().filter(|(_, _, sync)| !sync).map(|(table, _, _)| *table).collect::<Vec<_>>(),
+        }))
+    }
+    
+    /// Delete sessions that expired more than `retention_days` ago (expired ones are kept for session
+    /// analytics), then the revocations of sessions no longer kept
+    pub async fn cleanup_expired_sessions(&self, retention_days: u64) -> Result<()> {
         self.client
-            .query("ALTER TABLE sessions DELETE WHERE expires_at < now()")
+            .query("ALTER TABLE sessions DELETE WHERE expires_at < now() - toIntervalDay(?)")
+            .bind(retention_days)
+            .with_option("mutations_sync", "1")
             .execute()
             .await
             .context("Failed to cleanup expired sessions")?;
+        self.client
+            .query("ALTER TABLE revoked_sessions DELETE WHERE (user_pubkey, token) NOT IN (SELECT user_pubkey, token FROM sessions)")
+            .execute()
+            .await
+            .context("Failed to cleanup revoked sessions")?;
         
         Ok(())
     }
 }
+
+#[cfg(all(test, feature = "chaos"))]
+mod tests {
+    use super::*;
+    use crate::utils::chaos::{configure, FaultRates};
+    use axum::Router;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// An HTTP server counting the requests sent to it; stands in for ClickHouse
+    async fn counting_server() -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        let app = Router::new().fallback(move || async move {
+            counted.fetch_add(1, Ordering::SeqCst);
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
+
+    fn service(url: String) -> ClickHouseService {
+        ClickHouseService::from_config(&ClickHouseConfig {
+            url,
+            username: "default".to_string(),
+            password: String::new(),
+            read_replica: None,
+            max_concurrent_reads: 1,
+            max_concurrent_streams: 1,
+            read_queue_timeout: Duration::from_secs(1),
+            batch_rows: 1,
+            batch_interval: Duration::from_secs(1),
+        })
+    }
+
+    fn trade() -> Trade {
+        serde_json::from_value(serde_json::json!({
+            "id": "signature",
+            "timestamp": "2026-01-01T00:00:00Z",
+            "base_symbol": "SOL",
+            "quote_symbol": "USDC",
+            "base_mint": "So11111111111111111111111111111111111111112",
+            "quote_mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            "price": 150.0,
+            "amount": 1.0,
+            "total_value": 150.0,
+            "side": "buy",
+            "slot": 1,
+            "dex_program": "Raydium",
+        }))
+        .unwrap()
+    }
+
+    // One test drives the ClickHouseInsert point, so its settings can't leak into another
+    #[tokio::test]
+    async fn insert_faults_are_injected_before_the_insert() {
+        let (url, requests) = counting_server().await;
+        let clickhouse = service(url);
+        let trade = trade();
+
+        configure(FaultPoint::ClickHouseInsert, Some(FaultRates::parse("error=1").unwrap()));
+        let error = format!("{:#}", clickhouse.store_trades(&[(&trade, false)]).await.unwrap_err());
+        assert!(error.contains("injected error"), "{}", error);
+        // A dropped insert is acknowledged without reaching ClickHouse
+        configure(FaultPoint::ClickHouseInsert, Some(FaultRates::parse("drop=1").unwrap()));
+        clickhouse.store_trades(&[(&trade, false)]).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        configure(FaultPoint::ClickHouseInsert, None);
+        let _ = clickhouse.store_trades(&[(&trade, false)]).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
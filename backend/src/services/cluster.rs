@@ -0,0 +1,149 @@
+// Cluster membership - each replica heartbeats its identity and state into ClickHouse, the store
+// replicas already share for job leases, so /api/cluster can show which node is doing what
+//
+// Roles are derived rather than configured: every replica serves the API, replicas subscribed to
+// at least one enabled DEX program are ingestors, and replicas holding an active job lease run jobs.
+
+use crate::services::clickhouse::{ClickHouseService, InstanceRow};
+use crate::services::dex_programs::DexProgramRegistry;
+use crate::services::job_leases::JobLeases;
+use crate::services::stream_health::StreamHealth;
+use crate::utils::build_info;
+use crate::websocket::ConnectionManager;
+use anyhow::Result;
+use serde_json::json;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::time::{interval, Duration};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// A replica that missed this many heartbeats is reported stale
+const MISSED_HEARTBEATS_STALE: u32 = 3;
+/// Replicas silent for longer than this are left out of the listing
+const LISTED_FOR: Duration = Duration::from_secs(3600);
+
+pub struct ClusterMembership {
+    clickhouse: Arc<ClickHouseService>,
+    job_leases: Arc<JobLeases>,
+    dex_programs: Arc<DexProgramRegistry>,
+    stream_health: Arc<StreamHealth>,
+    ws_manager: Arc<ConnectionManager>,
+    /// INGEST_FORWARD_URL is set (the config requires the shard ingest secret with it)
+    forwards_to_hub: bool,
+    started_at: OffsetDateTime,
+}
+
+impl ClusterMembership {
+    pub fn new(
+        clickhouse: Arc<ClickHouseService>,
+        job_leases: Arc<JobLeases>,
+        dex_programs: Arc<DexProgramRegistry>,
+        stream_health: Arc<StreamHealth>,
+        ws_manager: Arc<ConnectionManager>,
+        forwards_to_hub: bool,
+    ) -> Self {
+        Self {
+            clickhouse,
+            job_leases,
+            dex_programs,
+            stream_health,
+            ws_manager,
+            forwards_to_hub,
+            started_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    /// Heartbeat every HEARTBEAT_INTERVAL
+    pub async fn start(self: Arc<Self>) {
+        let mut ticker = interval(HEARTBEAT_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.heartbeat().await {
+                eprintln!("⚠️  [Cluster] Heartbeat failed: {}", e);
+            }
+        }
+    }
+
+    async fn heartbeat(&self) -> Result<()> {
+        let shard = self.dex_programs.shard();
+        // API-only and still-degraded replicas subscribe to nothing, whatever their registry holds
+        let ingesting_programs = if self.stream_health.is_ingesting().await {
+            self.dex_programs.enabled_program_ids().await.len() as u32
+        } else {
+            0
+        };
+
+        self.clickhouse
+            .record_heartbeat(&InstanceRow {
+                instance_id: self.job_leases.instance_id().to_string(),
+                version: build_info::VERSION.to_string(),
+                started_at: self.started_at,
+                last_seen: OffsetDateTime::now_utc(),
+                ingest_shard: format!("{}/{}", shard.index, shard.count),
+                ingesting_programs,
+                forwards_to_hub: self.forwards_to_hub as u8,
+                stream_connected: self.stream_health.is_connected().await as u8,
+                ws_connections: self.ws_manager.connection_count().await as u32,
+            })
+            .await
+    }
+
+    /// Replicas heard from within LISTED_FOR, with their roles, jobs held and health
+    /// health: `stale` after missed heartbeats, `degraded` for an ingestor whose QuickNode stream is
+    /// down, otherwise `healthy`
+    pub async fn members(&self) -> Result<serde_json::Value> {
+        let now = OffsetDateTime::now_utc();
+        let instances = self.clickhouse.list_instances(now - LISTED_FOR).await?;
+        let leases = self.job_leases.list().await?;
+        let stale_after = (HEARTBEAT_INTERVAL * MISSED_HEARTBEATS_STALE).as_secs_f64();
+
+        let members: Vec<serde_json::Value> = instances
+            .into_iter()
+            .map(|instance| {
+                let jobs: Vec<&str> = leases
+                    .iter()
+                    .filter(|lease| lease.holder == instance.instance_id && lease.expires_at > now)
+                    .map(|lease| lease.name.strip_prefix("job:").unwrap_or(&lease.name))
+                    .collect();
+                let ingestor = instance.ingesting_programs > 0;
+                let mut roles = vec!["api"];
+                if ingestor {
+                    roles.push("ingestor");
+                }
+                if !jobs.is_empty() {
+                    roles.push("jobs");
+                }
+                let since_heartbeat = (now - instance.last_seen).as_seconds_f64();
+                let health = if since_heartbeat > stale_after {
+                    "stale"
+                } else if ingestor && instance.stream_connected == 0 {
+                    "degraded"
+                } else {
+                    "healthy"
+                };
+
+                json!({
+                    "instance_id": instance.instance_id,
+                    "roles": roles,
+                    "version": instance.version,
+                    "health": health,
+                    "started_at": instance.started_at.unix_timestamp(),
+                    "last_seen": instance.last_seen.unix_timestamp(),
+                    "seconds_since_heartbeat": since_heartbeat,
+                    "ingest_shard": instance.ingest_shard,
+                    "ingesting_programs": instance.ingesting_programs,
+                    "forwards_to_hub": instance.forwards_to_hub != 0,
+                    "stream_connected": instance.stream_connected != 0,
+                    "ws_connections": instance.ws_connections,
+                    "jobs": jobs,
+                })
+            })
+            .collect();
+
+        Ok(json!({
+            "instance_id": self.job_leases.instance_id(),
+            "heartbeat_interval_secs": HEARTBEAT_INTERVAL.as_secs(),
+            "instances": members,
+        }))
+    }
+}
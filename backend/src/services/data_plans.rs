@@ -0,0 +1,93 @@
+// Data plans - API key tiers and how far behind real time each tier's WebSocket stream runs
+
+use std::collections::HashMap;
+use tokio::time::Duration;
+
+/// Plan a connection streams under; a zero delay is the real-time feed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataPlan {
+    pub name: String,
+    pub delay: Duration,
+}
+
+impl DataPlan {
+    fn realtime() -> Self {
+        Self { name: "realtime".to_string(), delay: Duration::ZERO }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DataPlans {
+    delays: HashMap<String, Duration>,
+    /// API key -> plan name
+    by_api_key: HashMap<String, String>,
+    /// Plan for connections without an API key; None streams them in real time
+    default_plan: Option<String>,
+}
+
+impl DataPlans {
+    /// `delays` (DATA_PLAN_DELAYS=plan:seconds,...) defines the plans (e.g. free:15,pro:0); `api_key_plans`
+    /// (API_KEY_PLANS=api_key:plan,...) assigns keys to them; `default_plan` (DEFAULT_DATA_PLAN) applies to
+    /// keyless connections. An error names the variable at fault and the entry
+    pub fn parse(delays: &str, api_key_plans: &str, default_plan: Option<&str>) -> Result<Self, (&'static str, String)> {
+        fn entries(key: &'static str, raw: &str) -> Result<Vec<(String, String)>, (&'static str, String)> {
+            raw.split(',')
+                .filter(|e| !e.trim().is_empty())
+                .map(|entry| match entry.trim().split_once(':') {
+                    Some((name, value)) if !name.is_empty() && !value.is_empty() => Ok((name.to_string(), value.to_string())),
+                    _ => Err((key, format!("'{}' is malformed", entry.trim()))),
+                })
+                .collect()
+        }
+
+        let mut plan_delays = HashMap::new();
+        for (plan, seconds) in entries("DATA_PLAN_DELAYS", delays)? {
+            let seconds = seconds
+                .parse::<u64>()
+                .map_err(|_| ("DATA_PLAN_DELAYS", format!("plan {} has invalid delay '{}'", plan, seconds)))?;
+            plan_delays.insert(plan, Duration::from_secs(seconds));
+        }
+
+        let mut by_api_key = HashMap::new();
+        for (api_key, plan) in entries("API_KEY_PLANS", api_key_plans)? {
+            if !plan_delays.contains_key(&plan) {
+                return Err(("API_KEY_PLANS", format!("plan {} is not in DATA_PLAN_DELAYS", plan)));
+            }
+            by_api_key.insert(api_key, plan);
+        }
+
+        let default_plan = default_plan.map(str::to_string);
+        if let Some(plan) = &default_plan
+            && !plan_delays.contains_key(plan)
+        {
+            return Err(("DEFAULT_DATA_PLAN", format!("plan {} is not in DATA_PLAN_DELAYS", plan)));
+        }
+
+        Ok(Self { delays: plan_delays, by_api_key, default_plan })
+    }
+
+    /// Log the configured plans once at startup
+    pub fn log(&self) {
+        println!(
+            "⏱️  [DataPlans] {} plan(s), {} API key(s), keyless connections on {}",
+            self.delays.len(),
+            self.by_api_key.len(),
+            self.default_plan.as_deref().unwrap_or("realtime")
+        );
+    }
+
+    /// Plan for a connection's API key; an unrecognized key is an error rather than a silent downgrade
+    pub fn for_api_key(&self, api_key: Option<&str>) -> Result<DataPlan, String> {
+        let name = match api_key {
+            Some(key) => self.by_api_key.get(key).ok_or_else(|| "Unknown API key".to_string())?,
+            None => match &self.default_plan {
+                Some(plan) => plan,
+                None => return Ok(DataPlan::realtime()),
+            },
+        };
+        Ok(DataPlan {
+            name: name.clone(),
+            delay: self.delays.get(name).copied().unwrap_or(Duration::ZERO),
+        })
+    }
+}
@@ -0,0 +1,134 @@
+// Data quality - per-pair score of how trustworthy the current market data is, from the share of
+// parsed trades rejected as bad data, deviation from the oracle price, and gaps in the trade stream
+
+use crate::services::clickhouse::ClickHouseService;
+use crate::services::pair_mapping::{parse_pair, supported_pairs, Pair};
+use crate::services::trade_sanity::{pair_key, TradeSanity};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{interval, Duration};
+
+/// How often scores are recomputed
+const QUALITY_INTERVAL: Duration = Duration::from_secs(60);
+/// Trailing window the inputs are measured over
+const QUALITY_WINDOW_SECS: u64 = 3_600;
+/// A stretch without trades longer than this counts as a gap
+const QUALITY_GAP_SECS: u64 = 300;
+
+/// Penalties (points off 100) and their caps
+const REJECTION_PENALTY_PER_PCT: f64 = 2.0;
+const MAX_REJECTION_PENALTY: f64 = 40.0;
+const DEVIATION_PENALTY_PER_BPS: f64 = 0.2;
+const MAX_DEVIATION_PENALTY: f64 = 30.0;
+const GAP_PENALTY: f64 = 5.0;
+const MAX_GAP_PENALTY: f64 = 30.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairQuality {
+    /// 0-100; None when the pair had neither trades nor rejections in the window
+    pub score: Option<f64>,
+    /// good (80+), fair (50+), poor, or no_data
+    pub level: &'static str,
+    pub window_secs: u64,
+    pub trades: u64,
+    pub rejected: u64,
+    pub rejection_rate: Option<f64>,
+    pub median_oracle_deviation_bps: Option<f64>,
+    pub gaps: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PairQuality {
+    fn compute(trades: u64, rejected: u64, median_deviation_bps: Option<f64>, gaps: u64) -> Self {
+        let parsed = trades + rejected;
+        let rejection_rate = (parsed > 0).then(|| rejected as f64 / parsed as f64);
+        let score = rejection_rate.map(|rate| {
+            let penalty = (rate * 100.0 * REJECTION_PENALTY_PER_PCT).min(MAX_REJECTION_PENALTY)
+                + median_deviation_bps.map_or(0.0, |bps| (bps * DEVIATION_PENALTY_PER_BPS).min(MAX_DEVIATION_PENALTY))
+                + (gaps as f64 * GAP_PENALTY).min(MAX_GAP_PENALTY);
+            (100.0 - penalty).max(0.0)
+        });
+        let level = match score {
+            None => "no_data",
+            Some(score) if score >= 80.0 => "good",
+            Some(score) if score >= 50.0 => "fair",
+            Some(_) => "poor",
+        };
+        Self {
+            score,
+            level,
+            window_secs: QUALITY_WINDOW_SECS,
+            trades,
+            rejected,
+            rejection_rate,
+            median_oracle_deviation_bps: median_deviation_bps,
+            gaps,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+pub struct DataQuality {
+    clickhouse: Arc<ClickHouseService>,
+    sanity: Arc<TradeSanity>,
+    /// Latest score per listed pair
+    scores: RwLock<HashMap<String, PairQuality>>,
+}
+
+impl DataQuality {
+    pub fn new(clickhouse: Arc<ClickHouseService>, sanity: Arc<TradeSanity>) -> Self {
+        Self {
+            clickhouse,
+            sanity,
+            scores: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Recompute every pair's score once a minute
+    /// Rejections are counted by this replica's ingest, so scores are per replica like the sanity counters
+    pub async fn start(self: Arc<Self>) {
+        let mut ticker = interval(QUALITY_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.refresh().await {
+                eprintln!("⚠️  [DataQuality] Failed to refresh pair quality: {}", e);
+            }
+        }
+    }
+
+    async fn refresh(&self) -> anyhow::Result<()> {
+        let rows = self.clickhouse.get_pair_quality_inputs(QUALITY_WINDOW_SECS, QUALITY_GAP_SECS).await?;
+        let since = Utc::now() - chrono::Duration::seconds(QUALITY_WINDOW_SECS as i64);
+        let rejections = self.sanity.pair_rejections_since(since).await;
+
+        let scores: HashMap<String, PairQuality> = supported_pairs()
+            .iter()
+            .filter_map(|pair| {
+                let Pair { base, quote } = parse_pair(pair)?;
+                let key = pair_key(&base, &quote);
+                let rejected = rejections.get(&key).copied().unwrap_or(0);
+                let quality = match rows.iter().find(|row| row.pair == key) {
+                    Some(row) => PairQuality::compute(row.trades, rejected, row.median_deviation_bps, row.gaps),
+                    // Not a single accepted trade: the whole window is one gap
+                    None => PairQuality::compute(0, rejected, None, 1),
+                };
+                Some((pair.clone(), quality))
+            })
+            .collect();
+
+        let poor: Vec<&String> = scores.iter().filter(|(_, q)| q.level == "poor").map(|(pair, _)| pair).collect();
+        if !poor.is_empty() {
+            println!("📉 [DataQuality] Poor data quality on {:?}", poor);
+        }
+        *self.scores.write().await = scores;
+        Ok(())
+    }
+
+    /// Latest score per listed pair; empty until the first refresh
+    pub async fn scores(&self) -> HashMap<String, PairQuality> {
+        self.scores.read().await.clone()
+    }
+}
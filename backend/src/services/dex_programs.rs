@@ -0,0 +1,271 @@
+// DEX program registry - tracks which venues are ingested (built-in list, DEX_PROGRAMS_FILE or
+// DEX_PROGRAMS, admin additions) and lets them be added, removed and toggled at runtime
+
+use crate::services::sharding::IngestShard;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::{broadcast, RwLock};
+
+/// Monitored when neither DEX_PROGRAMS_FILE nor DEX_PROGRAMS is set
+const DEFAULT_PROGRAMS: &[(&str, &str)] = &[
+    ("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", "Jupiter v6"),
+    ("JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB", "Jupiter v4"),
+    ("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8", "Raydium"),
+    ("9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP", "Orca"),
+    ("9H6tua7jkLhdm3w8BvgpTn5LZNU7g4ZynDmCiNN3q6Rp", "Meteora"),
+    ("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLRJi5i4Z2j3Yc", "Phoenix"),
+];
+
+/// One entry of DEX_PROGRAMS_FILE
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProgramConfig {
+    pub program_id: String,
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// DEX_PROGRAMS_FILE contents
+#[derive(Debug, Deserialize)]
+struct ProgramList {
+    programs: Vec<ProgramConfig>,
+}
+
+/// Check a program ID is a base58 32-byte key
+fn validate_program_id(program_id: &str) -> Result<(), String> {
+    match bs58::decode(program_id).into_vec() {
+        Ok(bytes) if bytes.len() == 32 => Ok(()),
+        _ => Err(format!("'{}' is not a base58 program ID", program_id)),
+    }
+}
+
+/// Check a configured list: valid IDs, non-empty names, no duplicate IDs or names
+fn validate_programs(programs: &[ProgramConfig]) -> Result<(), String> {
+    for (i, program) in programs.iter().enumerate() {
+        validate_program_id(&program.program_id)?;
+        if program.name.trim().is_empty() {
+            return Err(format!("program {} has an empty name", program.program_id));
+        }
+        let earlier = &programs[..i];
+        if earlier.iter().any(|p| p.program_id == program.program_id) {
+            return Err(format!("program {} is listed twice", program.program_id));
+        }
+        if earlier.iter().any(|p| p.name.eq_ignore_ascii_case(&program.name)) {
+            return Err(format!("venue name '{}' is used twice", program.name));
+        }
+    }
+    Ok(())
+}
+
+/// The configured program list: DEX_PROGRAMS_FILE (`file`, JSON `{ "programs": [{ program_id, name, enabled }] }`),
+/// else DEX_PROGRAMS (`inline`, `program_id:name,...`), else the built-in list
+pub fn configured_programs(file: Option<&str>, inline: Option<&str>) -> Result<Vec<ProgramConfig>> {
+    let (programs, source) = if let Some(path) = file {
+        let raw = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+        let list: ProgramList = serde_json::from_str(&raw).with_context(|| format!("Invalid program list in {}", path))?;
+        (list.programs, path.to_string())
+    } else if let Some(raw) = inline {
+        let programs = raw
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let (program_id, name) = entry
+                    .trim()
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("DEX_PROGRAMS entry '{}' is not program_id:name", entry.trim()))?;
+                Ok(ProgramConfig {
+                    program_id: program_id.trim().to_string(),
+                    name: name.trim().to_string(),
+                    enabled: true,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        (programs, "DEX_PROGRAMS".to_string())
+    } else {
+        let programs = DEFAULT_PROGRAMS
+            .iter()
+            .map(|(program_id, name)| ProgramConfig {
+                program_id: program_id.to_string(),
+                name: name.to_string(),
+                enabled: true,
+            })
+            .collect();
+        return Ok(programs);
+    };
+
+    validate_programs(&programs).map_err(|e| anyhow!("Invalid program list in {}: {}", source, e))?;
+    println!("✅ [DexPrograms] Loaded {} programs from {}", programs.len(), source);
+    Ok(programs)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DexProgram {
+    pub program_id: String,
+    pub name: String,
+    pub enabled: bool,
+    /// Ingest shard that subscribes to this program
+    pub shard: usize,
+    /// Whether this process is that shard
+    pub ingested_here: bool,
+}
+
+/// Commands sent to the live QuickNode subscription when a venue is toggled
+#[derive(Debug, Clone)]
+pub enum ProgramCommand {
+    Subscribe(String),
+    Unsubscribe(String),
+}
+
+pub struct DexProgramRegistry {
+    shard: IngestShard,
+    programs: RwLock<Vec<DexProgram>>,
+    /// Position the next added program takes; positions aren't reused, so removals don't move
+    /// other programs between shards
+    next_position: AtomicUsize,
+    command_tx: broadcast::Sender<ProgramCommand>,
+}
+
+impl DexProgramRegistry {
+    /// Programs are assigned to shards by their position in the configured list
+    pub fn new(shard: IngestShard, configured: Vec<ProgramConfig>) -> Self {
+        let programs: Vec<DexProgram> = configured
+            .into_iter()
+            .enumerate()
+            .map(|(position, program)| DexProgram {
+                program_id: program.program_id,
+                name: program.name,
+                enabled: program.enabled,
+                shard: shard.shard_of(position),
+                ingested_here: shard.owns(position),
+            })
+            .collect();
+
+        let (command_tx, _) = broadcast::channel(32);
+
+        Self {
+            shard,
+            next_position: AtomicUsize::new(programs.len()),
+            programs: RwLock::new(programs),
+            command_tx,
+        }
+    }
+
+    /// This process's ingest shard
+    pub fn shard(&self) -> IngestShard {
+        self.shard
+    }
+
+    /// List all known programs with their current state
+    pub async fn list(&self) -> Vec<DexProgram> {
+        self.programs.read().await.clone()
+    }
+
+    /// Program IDs this shard should currently be subscribed to
+    pub async fn enabled_program_ids(&self) -> Vec<String> {
+        self.programs
+            .read()
+            .await
+            .iter()
+            .filter(|p| p.enabled && p.ingested_here)
+            .map(|p| p.program_id.clone())
+            .collect()
+    }
+
+    /// Enable or disable a program, looked up by program ID or venue name (case-insensitive)
+    /// Returns the updated program, or None if it isn't known
+    pub async fn set_enabled(&self, program: &str, enabled: bool) -> Option<DexProgram> {
+        let mut programs = self.programs.write().await;
+        let entry = programs.iter_mut().find(|p| {
+            p.program_id == program || p.name.eq_ignore_ascii_case(program)
+        })?;
+
+        if entry.enabled != enabled {
+            entry.enabled = enabled;
+            // Another shard's program only changes state here; toggle it on that shard's /admin/venues
+            if entry.ingested_here {
+                let command = if enabled {
+                    ProgramCommand::Subscribe(entry.program_id.clone())
+                } else {
+                    ProgramCommand::Unsubscribe(entry.program_id.clone())
+                };
+                // No receivers just means the subscription isn't connected yet;
+                // it will pick up the new state from enabled_program_ids() on connect
+                let _ = self.command_tx.send(command);
+            }
+            println!("🔀 [DexPrograms] {} ({}) {}", entry.name, entry.program_id,
+                if enabled { "enabled" } else { "disabled" });
+        }
+
+        Some(entry.clone())
+    }
+
+    /// Start monitoring a program at runtime; it takes the next shard position, so in a sharded
+    /// deployment add programs in the same order on every process
+    pub async fn add(&self, program: ProgramConfig) -> Result<DexProgram, String> {
+        validate_program_id(&program.program_id)?;
+        let name = program.name.trim().to_string();
+        if name.is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+
+        let mut programs = self.programs.write().await;
+        if programs.iter().any(|p| p.program_id == program.program_id) {
+            return Err(format!("program {} is already monitored", program.program_id));
+        }
+        if programs.iter().any(|p| p.name.eq_ignore_ascii_case(&name)) {
+            return Err(format!("venue name '{}' is already used", name));
+        }
+
+        let position = self.next_position.fetch_add(1, Ordering::Relaxed);
+        let entry = DexProgram {
+            program_id: program.program_id,
+            name,
+            enabled: program.enabled,
+            shard: self.shard.shard_of(position),
+            ingested_here: self.shard.owns(position),
+        };
+        if entry.enabled && entry.ingested_here {
+            let _ = self.command_tx.send(ProgramCommand::Subscribe(entry.program_id.clone()));
+        }
+        println!("➕ [DexPrograms] Added {} ({}) on shard {}", entry.name, entry.program_id, entry.shard);
+        programs.push(entry.clone());
+        Ok(entry)
+    }
+
+    /// Stop monitoring a program (by program ID or venue name) and forget it
+    /// Returns the removed program, or None if it isn't known
+    pub async fn remove(&self, program: &str) -> Option<DexProgram> {
+        let mut programs = self.programs.write().await;
+        let index = programs.iter().position(|p| {
+            p.program_id == program || p.name.eq_ignore_ascii_case(program)
+        })?;
+        let entry = programs.remove(index);
+
+        if entry.enabled && entry.ingested_here {
+            let _ = self.command_tx.send(ProgramCommand::Unsubscribe(entry.program_id.clone()));
+        }
+        println!("➖ [DexPrograms] Removed {} ({})", entry.name, entry.program_id);
+        Some(entry)
+    }
+
+    /// Venue name of the first known program mentioned in a transaction's logs
+    pub async fn venue_in_logs(&self, logs: &[String]) -> Option<String> {
+        let logs = logs.join(" ");
+        self.programs
+            .read()
+            .await
+            .iter()
+            .find(|p| logs.contains(&p.program_id))
+            .map(|p| p.name.clone())
+    }
+
+    /// Receive subscribe/unsubscribe commands for the live subscription
+    pub fn subscribe_commands(&self) -> broadcast::Receiver<ProgramCommand> {
+        self.command_tx.subscribe()
+    }
+}
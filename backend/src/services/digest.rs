@@ -0,0 +1,173 @@
+// Daily digest job - per-wallet portfolio change and watched-pair summaries
+
+use crate::services::clickhouse::{ClickHouseService, DigestRow};
+use crate::services::job_leases::JobLeases;
+use crate::services::notification_templates::NotificationTemplates;
+use crate::services::pair_mapping::{parse_pair, Pair};
+use crate::services::portfolio::PortfolioService;
+use crate::websocket::ConnectionManager;
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, Timelike, Utc};
+use serde_json::json;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// Preference key holding a wallet's watched pairs (JSON array, e.g. ["SOL/USDC"])
+pub const WATCHLIST_PREFERENCE_KEY: &str = "watchlist";
+/// Cap on pairs summarized per digest
+const MAX_DIGEST_PAIRS: usize = 20;
+
+pub struct DigestService {
+    clickhouse: Arc<ClickHouseService>,
+    portfolio: Arc<PortfolioService>,
+    ws_manager: Arc<ConnectionManager>,
+    /// Each day's run is claimed by one replica
+    job_leases: Arc<JobLeases>,
+    /// Text of the digest message, per delivery channel
+    templates: Arc<NotificationTemplates>,
+    /// Hour of day (UTC) the digest runs; DIGEST_HOUR_UTC, default 0
+    hour_utc: u32,
+    /// Optional webhook each digest is POSTed to; DIGEST_WEBHOOK_URL
+    webhook_url: Option<String>,
+}
+
+impl DigestService {
+    pub fn new(
+        clickhouse: Arc<ClickHouseService>,
+        portfolio: Arc<PortfolioService>,
+        ws_manager: Arc<ConnectionManager>,
+        job_leases: Arc<JobLeases>,
+        templates: Arc<NotificationTemplates>,
+        hour_utc: u32,
+        webhook_url: Option<String>,
+    ) -> Self {
+        Self {
+            clickhouse,
+            portfolio,
+            ws_manager,
+            job_leases,
+            templates,
+            hour_utc,
+            webhook_url,
+        }
+    }
+
+    /// Run the digest once a day at `hour_utc`
+    pub async fn start(self: Arc<Self>) {
+        println!("📰 [Digest] Daily digest scheduled at {:02}:00 UTC", self.hour_utc);
+        loop {
+            let now = Utc::now();
+            let mut next = now
+                .date_naive()
+                .and_hms_opt(self.hour_utc, 0, 0)
+                .unwrap_or_default()
+                .and_utc();
+            if next <= now {
+                next += ChronoDuration::days(1);
+            }
+            let wait = (next - now).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            if self.job_leases.claim("digest", std::time::Duration::from_secs(20 * 3600)).await {
+                match self.run_once().await {
+                    Ok(count) => println!("📰 [Digest] Delivered {} digest(s)", count),
+                    Err(e) => eprintln!("❌ [Digest] Digest run failed: {}", e),
+                }
+            }
+            // Guard against waking again within the same hour
+            if Utc::now().hour() == self.hour_utc {
+                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            }
+        }
+    }
+
+    /// Build and deliver a digest for every wallet with a watchlist; returns how many were delivered
+    pub async fn run_once(&self) -> Result<usize> {
+        let watchlists = self.clickhouse.get_preference_values(WATCHLIST_PREFERENCE_KEY).await?;
+        let mut delivered = 0;
+
+        for (wallet, value) in watchlists {
+            let pairs: Vec<String> = serde_json::from_str(&value).unwrap_or_default();
+            match self.build_digest(&wallet, &pairs).await {
+                Ok(row) => {
+                    if let Err(e) = self.clickhouse.store_digest(&row).await {
+                        eprintln!("⚠️  [Digest] Failed to store digest for {}: {}", wallet, e);
+                    }
+                    self.deliver(&wallet, &row.body).await;
+                    delivered += 1;
+                }
+                Err(e) => eprintln!("⚠️  [Digest] Failed to build digest for {}: {}", wallet, e),
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    async fn build_digest(&self, wallet: &str, pairs: &[String]) -> Result<DigestRow> {
+        let now = OffsetDateTime::now_utc();
+        let today = now.date();
+
+        // Portfolio change since the previous digest
+        let portfolio_usd = self.portfolio.get_balances(wallet, true).await?.total_usd;
+        let previous = self.clickhouse.latest_digest(wallet, Some(today)).await?;
+        let portfolio_change = previous.as_ref().map(|p| {
+            let change = portfolio_usd - p.portfolio_usd;
+            json!({
+                "previous_usd": p.portfolio_usd,
+                "change_usd": change,
+                "change_percent": if p.portfolio_usd > 0.0 { change / p.portfolio_usd * 100.0 } else { 0.0 },
+                "since": p.digest_date.to_string(),
+            })
+        });
+
+        let mut summaries = Vec::new();
+        for pair in pairs.iter().take(MAX_DIGEST_PAIRS) {
+            if let Some(Pair { base, quote }) = parse_pair(pair) {
+                let stats = self.clickhouse.get_24h_stats(&base, &quote, None).await?;
+                summaries.push(json!({ "pair": pair, "stats": stats }));
+            }
+        }
+
+        let body = json!({
+            "type": "digest",
+            "wallet": wallet,
+            "date": today.to_string(),
+            "portfolio": {
+                "total_usd": portfolio_usd,
+                "change": portfolio_change,
+            },
+            "watched_pairs": summaries,
+        });
+        // Stored and pushed over WS with the WS wording; the webhook gets its own in `deliver`
+        let body = self.templates.with_message("digest", "ws", &body);
+
+        Ok(DigestRow {
+            user_pubkey: wallet.to_string(),
+            digest_date: today,
+            created_at: now,
+            portfolio_usd,
+            body: body.to_string(),
+        })
+    }
+
+    /// Push to the wallet's open WS connections and the webhook, if configured
+    async fn deliver(&self, wallet: &str, body: &str) {
+        self.ws_manager.send_to_wallet(wallet, body, None).await;
+
+        if let Some(url) = &self.webhook_url {
+            let body = match serde_json::from_str(body) {
+                Ok(event) => self.templates.with_message("digest", "webhook", &event).to_string(),
+                Err(_) => body.to_string(),
+            };
+            let result = reqwest::Client::new()
+                .post(url)
+                .header("content-type", "application/json")
+                .body(body)
+                .send()
+                .await;
+            if let Err(e) = result {
+                eprintln!("⚠️  [Digest] Webhook delivery failed for {}: {}", wallet, e);
+            }
+        }
+    }
+}
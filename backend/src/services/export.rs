@@ -0,0 +1,166 @@
+// Trade export service - builds CSV files in the background and reports progress over WS
+
+use crate::services::clickhouse::ClickHouseService;
+use crate::services::sampling::TradeSample;
+use crate::utils::jwt;
+use crate::websocket::manager::ConnectionId;
+use crate::websocket::ConnectionManager;
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// Upper bound on rows in a single export
+pub const MAX_EXPORT_ROWS: usize = 1_000_000;
+/// Emit a progress event every N rows
+const PROGRESS_EVERY_ROWS: u64 = 5_000;
+/// How long the signed download URL (and the file) stays valid
+const EXPORT_TTL_MINUTES: i64 = 60;
+
+pub struct ExportService {
+    clickhouse: Arc<ClickHouseService>,
+    ws_manager: Arc<ConnectionManager>,
+    export_dir: PathBuf,
+    // Connections with an export in flight (one at a time per connection)
+    active: Mutex<HashSet<ConnectionId>>,
+}
+
+impl ExportService {
+    pub fn new(clickhouse: Arc<ClickHouseService>, ws_manager: Arc<ConnectionManager>, export_dir: PathBuf) -> Self {
+        Self {
+            clickhouse,
+            ws_manager,
+            export_dir,
+            active: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Path of a finished export file, if the ID is well-formed
+    pub fn export_path(&self, export_id: &str) -> Option<PathBuf> {
+        let id = Uuid::parse_str(export_id).ok()?;
+        Some(self.export_dir.join(format!("{}.csv", id)))
+    }
+
+    /// Start a CSV export for a connection; returns the export ID, or None if one is already running
+    /// With a sample, only the sampled trades among the `limit` most recent are written
+    pub async fn start_export(
+        self: &Arc<Self>,
+        connection_id: ConnectionId,
+        base_symbol: String,
+        quote_symbol: String,
+        limit: usize,
+        sample: Option<TradeSample>,
+    ) -> Option<String> {
+        if !self.active.lock().await.insert(connection_id) {
+            return None;
+        }
+
+        let export_id = Uuid::new_v4().to_string();
+        let service = self.clone();
+        let id = export_id.clone();
+        tokio::spawn(async move {
+            let event = match service.run_export(connection_id, &id, &base_symbol, &quote_symbol, limit, sample).await {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("❌ [Export] Export {} failed: {}", id, e);
+                    json!({
+                        "type": "export_failed",
+                        "export_id": id,
+                        "message": format!("{}", e),
+                    })
+                }
+            };
+            service.ws_manager.send_to_connection(connection_id, &event.to_string()).await;
+            service.active.lock().await.remove(&connection_id);
+        });
+
+        Some(export_id)
+    }
+
+    /// Stream trades into a CSV file, emitting progress, and return the `export_ready` event
+    async fn run_export(
+        &self,
+        connection_id: ConnectionId,
+        export_id: &str,
+        base_symbol: &str,
+        quote_symbol: &str,
+        limit: usize,
+        sample: Option<TradeSample>,
+    ) -> Result<serde_json::Value> {
+        tokio::fs::create_dir_all(&self.export_dir)
+            .await
+            .context("Failed to create export directory")?;
+        let path = self.export_path(export_id).context("Invalid export ID")?;
+
+        let total = self.clickhouse.count_trades(base_symbol, quote_symbol).await?.min(limit as u64);
+        println!("📦 [Export] Starting export {} for {}/{} ({} rows)", export_id, base_symbol, quote_symbol, total);
+
+        let file = tokio::fs::File::create(&path).await.context("Failed to create export file")?;
+        let mut writer = tokio::io::BufWriter::new(file);
+        writer.write_all(b"id,timestamp,base_symbol,quote_symbol,side,price,amount,total_value\n").await?;
+
+        let mut cursor = self.clickhouse.trades_cursor(base_symbol, quote_symbol, limit).await?;
+        // `total` and progress count scanned trades; `rows` counts the ones written
+        let mut scanned: u64 = 0;
+        let mut rows: u64 = 0;
+        while let Some(trade) = cursor.next().await? {
+            scanned += 1;
+            if scanned.is_multiple_of(PROGRESS_EVERY_ROWS) {
+                let progress = json!({
+                    "type": "export_progress",
+                    "export_id": export_id,
+                    "rows": rows,
+                    "scanned": scanned,
+                    "total": total,
+                    "percent": if total > 0 { (scanned as f64 / total as f64 * 100.0).min(100.0) } else { 100.0 },
+                });
+                self.ws_manager.send_to_connection(connection_id, &progress.to_string()).await;
+            }
+            if sample.is_some_and(|sample| !sample.includes(&trade.id)) {
+                continue;
+            }
+            let line = format!(
+                "{},{},{},{},{},{},{},{}\n",
+                trade.id,
+                trade.timestamp.to_rfc3339(),
+                trade.base_symbol,
+                trade.quote_symbol,
+                trade.side,
+                trade.price,
+                trade.amount,
+                trade.total_value,
+            );
+            writer.write_all(line.as_bytes()).await?;
+            rows += 1;
+        }
+        writer.flush().await?;
+
+        let (token, expires_at) = jwt::generate_resource_token(
+            &format!("export:{}", export_id),
+            chrono::Duration::minutes(EXPORT_TTL_MINUTES),
+        )?;
+
+        // Remove the file once the download URL has expired
+        let cleanup_path = path.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(EXPORT_TTL_MINUTES as u64 * 60)).await;
+            let _ = tokio::fs::remove_file(cleanup_path).await;
+        });
+
+        println!("✅ [Export] Export {} ready ({} rows)", export_id, rows);
+
+        Ok(json!({
+            "type": "export_ready",
+            "export_id": export_id,
+            "rows": rows,
+            "sample": sample.map(|s| s.to_string()),
+            "format": "csv",
+            "url": format!("/api/exports/{}?token={}", export_id, token),
+            "expires_at": expires_at,
+        }))
+    }
+}
@@ -0,0 +1,114 @@
+// Feature flags - switch expensive or experimental endpoints off at runtime (config default + admin override)
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::RwLock;
+
+/// Features that can be switched off
+pub const FEATURES: &[&str] = &["exports", "backtest", "snapshots"];
+
+/// Admin override of a feature's configured state; lives until cleared or the process restarts
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureOverride {
+    pub enabled: bool,
+    pub reason: Option<String>,
+    /// Unix seconds
+    pub set_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureStatus {
+    pub feature: String,
+    pub enabled: bool,
+    /// State from DISABLED_FEATURES, before any override
+    pub configured: bool,
+    #[serde(rename = "override")]
+    pub admin_override: Option<FeatureOverride>,
+}
+
+/// Why a feature is off
+#[derive(Debug, Clone)]
+pub enum FeatureOff {
+    /// Disabled in this deployment's config
+    NotOffered,
+    /// Paused by an admin, with their reason if given
+    Paused(Option<String>),
+}
+
+pub struct FeatureFlags {
+    disabled_by_config: HashSet<String>,
+    overrides: RwLock<HashMap<String, FeatureOverride>>,
+}
+
+impl FeatureFlags {
+    /// `disabled` (DISABLED_FEATURES=exports,backtest,...) switches features off for this deployment
+    pub fn new(disabled: &[String]) -> Self {
+        let disabled_by_config: HashSet<String> = disabled.iter().cloned().collect();
+        if !disabled_by_config.is_empty() {
+            println!("🚩 [FeatureFlags] Disabled by config: {:?}", disabled_by_config);
+        }
+
+        Self {
+            disabled_by_config,
+            overrides: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Ok if the feature is on; an admin override beats the config
+    pub async fn check(&self, feature: &str) -> Result<(), FeatureOff> {
+        match self.overrides.read().await.get(feature) {
+            Some(o) if o.enabled => Ok(()),
+            Some(o) => Err(FeatureOff::Paused(o.reason.clone())),
+            None if self.disabled_by_config.contains(feature) => Err(FeatureOff::NotOffered),
+            None => Ok(()),
+        }
+    }
+
+    /// Override a feature's state, or clear the override with None; None if the feature is unknown
+    pub async fn set_override(&self, feature: &str, enabled: Option<bool>, reason: Option<String>) -> Option<FeatureStatus> {
+        if !FEATURES.contains(&feature) {
+            return None;
+        }
+        {
+            let mut overrides = self.overrides.write().await;
+            match enabled {
+                Some(enabled) => {
+                    overrides.insert(feature.to_string(), FeatureOverride {
+                        enabled,
+                        reason,
+                        set_at: chrono::Utc::now().timestamp(),
+                    });
+                }
+                None => {
+                    overrides.remove(feature);
+                }
+            }
+        }
+        println!("🚩 [FeatureFlags] {} override {}", feature,
+            enabled.map_or("cleared", |on| if on { "enabled" } else { "disabled" }));
+        self.status(feature).await
+    }
+
+    /// Every feature's effective state
+    pub async fn list(&self) -> Vec<FeatureStatus> {
+        let mut statuses = Vec::with_capacity(FEATURES.len());
+        for feature in FEATURES {
+            if let Some(status) = self.status(feature).await {
+                statuses.push(status);
+            }
+        }
+        statuses
+    }
+
+    async fn status(&self, feature: &str) -> Option<FeatureStatus> {
+        if !FEATURES.contains(&feature) {
+            return None;
+        }
+        Some(FeatureStatus {
+            feature: feature.to_string(),
+            enabled: self.check(feature).await.is_ok(),
+            configured: !self.disabled_by_config.contains(feature),
+            admin_override: self.overrides.read().await.get(feature).cloned(),
+        })
+    }
+}
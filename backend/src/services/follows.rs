@@ -0,0 +1,318 @@
+// Copy-trading follows - authenticated wallets following other wallets' fills
+//
+// When the trade stream attributes a parsed swap to a followed wallet (its fee payer), each
+// follower's authenticated connections receive a `followed_wallet_trade` event, and follows with
+// `notify` set are also POSTed to FOLLOW_WEBHOOK_URL, one at a time from a bounded queue. Follows
+// are stored in wallet_follows; every replica keeps an index of them in memory, updated on its own
+// writes and reloaded every minute to pick up the others'.
+
+use crate::models::schema::CURRENT_SCHEMA_VERSION;
+use crate::models::trade::Trade;
+use crate::services::clickhouse::{time_to_chrono, ClickHouseService, FollowRow};
+use crate::services::notification_templates::NotificationTemplates;
+use crate::websocket::ConnectionManager;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::{mpsc, RwLock};
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+pub const DEFAULT_MAX_FOLLOWS: usize = 50;
+/// Webhook notifications waiting to be POSTed; more are dropped
+const WEBHOOK_QUEUE_CAPACITY: usize = 1_000;
+/// A webhook request taking longer is abandoned, so one slow delivery can't stall the queue
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A wallet followed by a follower
+#[derive(Debug, Clone, Serialize)]
+pub struct Follow {
+    pub wallet: String,
+    pub notify: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// followed -> follower -> follow
+type FollowIndex = HashMap<String, HashMap<String, Follow>>;
+
+/// A follower and the event POSTed for them
+type Notification = (String, Value);
+
+/// FOLLOW_WEBHOOK_URL notifications, POSTed one at a time by a single worker
+/// The queue is bounded: while the webhook is slow or down it fills, and further notifications are
+/// dropped (and counted) instead of piling up as tasks or holding up the trade pipeline
+struct WebhookQueue {
+    tx: mpsc::Sender<Notification>,
+    dropped: AtomicU64,
+}
+
+impl WebhookQueue {
+    fn new(capacity: usize) -> (Self, mpsc::Receiver<Notification>) {
+        let (tx, rx) = mpsc::channel(capacity);
+        (Self { tx, dropped: AtomicU64::new(0) }, rx)
+    }
+
+    /// Spawn the worker POSTing to `url`
+    fn start(url: String) -> Self {
+        let (queue, rx) = Self::new(WEBHOOK_QUEUE_CAPACITY);
+        tokio::spawn(deliver(rx, reqwest::Client::new(), url));
+        queue
+    }
+
+    /// Queue a notification; false when the queue is full and it was dropped
+    fn push(&self, follower: String, body: Value) -> bool {
+        if self.tx.try_send((follower, body)).is_ok() {
+            return true;
+        }
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        // Logged at 1, 2, 4, 8... so a long outage doesn't flood the log
+        if dropped.is_power_of_two() {
+            eprintln!("⚠️  [Follows] Webhook queue full; {} notification(s) dropped so far", dropped);
+        }
+        false
+    }
+}
+
+/// Remove the follows made by `wallet` and the follows of it
+fn forget_wallet(index: &mut FollowIndex, wallet: &str) {
+    index.remove(wallet);
+    index.retain(|_, followers| {
+        followers.remove(wallet);
+        !followers.is_empty()
+    });
+}
+
+async fn deliver(mut rx: mpsc::Receiver<Notification>, http: reqwest::Client, url: String) {
+    while let Some((follower, body)) = rx.recv().await {
+        let sent = http.post(&url).timeout(WEBHOOK_TIMEOUT).json(&body).send().await;
+        if let Err(e) = sent.and_then(reqwest::Response::error_for_status) {
+            eprintln!("⚠️  [Follows] Webhook delivery failed for {}: {}", follower, e);
+        }
+    }
+}
+
+pub struct WalletFollows {
+    clickhouse: Arc<ClickHouseService>,
+    ws_manager: Arc<ConnectionManager>,
+    templates: Arc<NotificationTemplates>,
+    index: RwLock<FollowIndex>,
+    /// Followed trades of follows with `notify` set, for FOLLOW_WEBHOOK_URL when set
+    webhooks: Option<WebhookQueue>,
+    /// Wallets one follower may follow; MAX_FOLLOWS_PER_WALLET, default 50
+    pub max_per_wallet: usize,
+}
+
+impl WalletFollows {
+    pub fn new(
+        clickhouse: Arc<ClickHouseService>,
+        ws_manager: Arc<ConnectionManager>,
+        templates: Arc<NotificationTemplates>,
+        max_per_wallet: usize,
+        webhook_url: Option<String>,
+    ) -> Self {
+        Self {
+            clickhouse,
+            ws_manager,
+            templates,
+            index: RwLock::new(HashMap::new()),
+            webhooks: webhook_url.map(WebhookQueue::start),
+            max_per_wallet,
+        }
+    }
+
+    /// Load the follows, then reload them every minute
+    pub async fn start(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+        let mut loaded = false;
+        loop {
+            interval.tick().await;
+            match self.clickhouse.get_follows().await {
+                Ok(rows) => {
+                    let mut index = FollowIndex::new();
+                    for row in rows {
+                        let follow = Follow { wallet: row.followed.clone(), notify: row.notify != 0, updated_at: time_to_chrono(row.updated_at) };
+                        index.entry(row.followed).or_default().insert(row.follower, follow);
+                    }
+                    if !loaded {
+                        println!("👥 [Follows] Loaded {} follow(s) of {} wallet(s)", index.values().map(HashMap::len).sum::<usize>(), index.len());
+                        loaded = true;
+                    }
+                    *self.index.write().await = index;
+                }
+                Err(e) => eprintln!("⚠️  [Follows] Failed to load follows: {}", e),
+            }
+        }
+    }
+
+    /// Wallets `follower` follows, most recently changed first
+    pub async fn following(&self, follower: &str) -> Vec<Follow> {
+        let mut follows: Vec<Follow> = self
+            .index
+            .read()
+            .await
+            .values()
+            .filter_map(|followers| followers.get(follower).cloned())
+            .collect();
+        follows.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then_with(|| a.wallet.cmp(&b.wallet)));
+        follows
+    }
+
+    /// Follow `followed` (or change `notify` of an existing follow)
+    pub async fn follow(&self, follower: &str, followed: &str, notify: bool) -> Result<Follow> {
+        let now = OffsetDateTime::now_utc();
+        self.clickhouse.store_follow(&FollowRow {
+            follower: follower.to_string(),
+            followed: followed.to_string(),
+            notify: notify as u8,
+            active: 1,
+            updated_at: now,
+        }).await?;
+        let follow = Follow { wallet: followed.to_string(), notify, updated_at: time_to_chrono(now) };
+        self.index.write().await.entry(followed.to_string()).or_default().insert(follower.to_string(), follow.clone());
+        Ok(follow)
+    }
+
+    /// Stop following `followed`; false if `follower` didn't follow it
+    pub async fn unfollow(&self, follower: &str, followed: &str) -> Result<bool> {
+        let following = self.index.read().await.get(followed).is_some_and(|followers| followers.contains_key(follower));
+        if !following {
+            return Ok(false);
+        }
+        self.clickhouse.store_follow(&FollowRow {
+            follower: follower.to_string(),
+            followed: followed.to_string(),
+            notify: 0,
+            active: 0,
+            updated_at: OffsetDateTime::now_utc(),
+        }).await?;
+        let mut index = self.index.write().await;
+        if let Some(followers) = index.get_mut(followed) {
+            followers.remove(follower);
+            if followers.is_empty() {
+                index.remove(followed);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Drop a wallet's follows, and its followers, from the index after its stored data was erased
+    pub async fn forget(&self, wallet: &str) {
+        forget_wallet(&mut *self.index.write().await, wallet);
+    }
+
+    /// Push a stored trade to the followers of its trader
+    /// `trade_payload` is the trade as broadcast on the `trades` channel
+    pub async fn on_trade(&self, trade: &Trade, trade_payload: &Value) {
+        let Some(trader) = &trade.trader else { return };
+        let followers: Vec<(String, bool)> = match self.index.read().await.get(trader) {
+            Some(followers) => followers.iter().map(|(follower, follow)| (follower.clone(), follow.notify)).collect(),
+            None => return,
+        };
+
+        let event = json!({
+            "type": "followed_wallet_trade",
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "wallet": trader,
+            "trade": trade_payload,
+        });
+        let ws_event = self.templates.with_message("followed_wallet_trade", "ws", &event).to_string();
+        let mut delivered = 0;
+        for (follower, _) in &followers {
+            delivered += self.ws_manager.send_to_wallet(follower, &ws_event, None).await;
+        }
+        if delivered > 0 {
+            println!("👥 [Follows] Pushed {}'s trade to {} connection(s)", trader, delivered);
+        }
+
+        let Some(webhooks) = &self.webhooks else { return };
+        let webhook_event = self.templates.with_message("followed_wallet_trade", "webhook", &event);
+        for (follower, _) in followers.into_iter().filter(|(_, notify)| *notify) {
+            let mut body = webhook_event.clone();
+            body["follower"] = json!(follower);
+            webhooks.push(follower, body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::Mutex;
+
+    /// Records the followers POSTed to it, and the most requests it was ever handling at once
+    #[derive(Default)]
+    struct Receiver {
+        followers: Mutex<Vec<String>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    async fn receive(receiver: Arc<Receiver>) -> String {
+        let app = Router::new().route(
+            "/",
+            post(move |Json(body): Json<Value>| async move {
+                let in_flight = receiver.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                receiver.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                receiver.followers.lock().await.push(body["follower"].as_str().unwrap().to_string());
+                receiver.in_flight.fetch_sub(1, Ordering::SeqCst);
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn notifications_are_posted_one_at_a_time_in_order() {
+        let receiver = Arc::new(Receiver::default());
+        let queue = WebhookQueue::start(receive(receiver.clone()).await);
+        let followers: Vec<String> = (0..10).map(|n| format!("follower{}", n)).collect();
+        for follower in &followers {
+            assert!(queue.push(follower.clone(), json!({ "follower": follower })));
+        }
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while receiver.followers.lock().await.len() < followers.len() {
+            assert!(tokio::time::Instant::now() < deadline, "notifications were not delivered");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(*receiver.followers.lock().await, followers);
+        assert_eq!(receiver.max_in_flight.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn forgetting_a_wallet_drops_its_follows_and_its_followers() {
+        let follow = |wallet: &str| Follow { wallet: wallet.to_string(), notify: false, updated_at: Utc::now() };
+        let mut index: FollowIndex = HashMap::from([
+            ("erased".to_string(), HashMap::from([("alice".to_string(), follow("erased")), ("bob".to_string(), follow("erased"))])),
+            ("carol".to_string(), HashMap::from([("erased".to_string(), follow("carol")), ("alice".to_string(), follow("carol"))])),
+            ("dave".to_string(), HashMap::from([("erased".to_string(), follow("dave"))])),
+        ]);
+
+        forget_wallet(&mut index, "erased");
+
+        assert_eq!(index.len(), 1);
+        assert_eq!(index["carol"].keys().collect::<Vec<_>>(), ["alice"]);
+    }
+
+    #[test]
+    fn notifications_are_dropped_once_the_queue_is_full() {
+        // No worker: nothing leaves the queue
+        let (queue, _rx) = WebhookQueue::new(2);
+        assert!(queue.push("a".to_string(), json!({})));
+        assert!(queue.push("b".to_string(), json!({})));
+        assert!(!queue.push("c".to_string(), json!({})));
+        assert!(!queue.push("d".to_string(), json!({})));
+        assert_eq!(queue.dropped.load(Ordering::Relaxed), 2);
+    }
+}
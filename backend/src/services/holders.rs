@@ -0,0 +1,120 @@
+// Token holder stats service - holder count and top-holder concentration, cached per mint
+
+use crate::services::solana::{SolanaService, TOKEN_PROGRAM_ID};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use tokio::time::{Duration, Instant};
+
+/// Holder stats change slowly and the holder count is an expensive scan
+const HOLDER_STATS_TTL: Duration = Duration::from_secs(600);
+/// Upper bound on cached mints
+const MAX_CACHED_MINTS: usize = 1_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TopHolder {
+    pub token_account: String,
+    /// Wallet owning the token account, when it could be resolved
+    pub owner: Option<String>,
+    pub amount: f64,
+    pub percent_of_supply: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HolderStats {
+    pub mint: String,
+    pub supply: f64,
+    /// Token accounts with a non-zero balance; None if the RPC refused the scan
+    pub holder_count: Option<u64>,
+    pub top1_percent: f64,
+    pub top10_percent: f64,
+    pub top20_percent: f64,
+    pub top_holders: Vec<TopHolder>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+pub struct HolderStatsService {
+    solana: SolanaService,
+    cache: RwLock<HashMap<String, (Instant, HolderStats)>>,
+}
+
+impl HolderStatsService {
+    pub fn new(solana: SolanaService) -> Self {
+        Self {
+            solana,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Get holder stats for a mint, served from cache when fresh
+    pub async fn get(&self, mint: &str) -> Result<HolderStats> {
+        if let Some((fetched, stats)) = self.cache.read().await.get(mint)
+            && fetched.elapsed() < HOLDER_STATS_TTL
+        {
+            return Ok(stats.clone());
+        }
+
+        let stats = self.fetch(mint).await?;
+        let mut cache = self.cache.write().await;
+        cache.retain(|_, (fetched, _)| fetched.elapsed() < HOLDER_STATS_TTL);
+        if cache.len() < MAX_CACHED_MINTS {
+            cache.insert(mint.to_string(), (Instant::now(), stats.clone()));
+        }
+        Ok(stats)
+    }
+
+    async fn fetch(&self, mint: &str) -> Result<HolderStats> {
+        let mint_account = self
+            .solana
+            .get_account_info_parsed(mint)
+            .await?
+            .filter(|account| account["data"]["parsed"]["type"].as_str() == Some("mint"))
+            .ok_or_else(|| anyhow::anyhow!("{} is not a token mint", mint))?;
+        let program_id = mint_account["owner"].as_str().unwrap_or(TOKEN_PROGRAM_ID).to_string();
+
+        let supply = self.solana.get_token_supply(mint).await?;
+        let largest = self.solana.get_token_largest_accounts(mint).await?;
+
+        // Resolve token accounts to owning wallets
+        let addresses: Vec<String> = largest.iter().map(|(address, _)| address.clone()).collect();
+        let owners = self.solana.get_multiple_accounts_parsed(&addresses).await.unwrap_or_default();
+
+        let percent = |amount: f64| if supply > 0.0 { amount / supply * 100.0 } else { 0.0 };
+        let top_holders: Vec<TopHolder> = largest
+            .iter()
+            .enumerate()
+            .map(|(i, (address, amount))| TopHolder {
+                token_account: address.clone(),
+                owner: owners
+                    .get(i)
+                    .and_then(|a| a.as_ref())
+                    .and_then(|a| a["data"]["parsed"]["info"]["owner"].as_str())
+                    .map(|s| s.to_string()),
+                amount: *amount,
+                percent_of_supply: percent(*amount),
+            })
+            .collect();
+        let top_share = |n: usize| top_holders.iter().take(n).map(|h| h.percent_of_supply).sum::<f64>();
+
+        let holder_count = match self.solana.count_token_holders(mint, &program_id).await {
+            Ok(count) => Some(count),
+            Err(e) => {
+                eprintln!("⚠️  [Holders] Holder count unavailable for {}: {}", mint, e);
+                None
+            }
+        };
+
+        Ok(HolderStats {
+            mint: mint.to_string(),
+            supply,
+            holder_count,
+            top1_percent: top_share(1),
+            top10_percent: top_share(10),
+            top20_percent: top_share(20),
+            top_holders,
+            fetched_at: Utc::now(),
+        })
+    }
+}
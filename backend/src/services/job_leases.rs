@@ -0,0 +1,46 @@
+// Job leases - periodic jobs claim a ClickHouse lease per run so exactly one replica runs each
+
+use crate::services::clickhouse::{ClickHouseService, LeaseRow};
+use anyhow::Result;
+use std::sync::Arc;
+use tokio::time::Duration;
+
+pub struct JobLeases {
+    clickhouse: Arc<ClickHouseService>,
+    /// This replica's name in lease rows and heartbeats; INSTANCE_ID, else HOSTNAME, else a random id
+    instance_id: String,
+}
+
+impl JobLeases {
+    pub fn new(clickhouse: Arc<ClickHouseService>, instance_id: String) -> Self {
+        println!("🔒 [JobLeases] Running as instance {}", instance_id);
+
+        Self { clickhouse, instance_id }
+    }
+
+    /// Claim `job` for `ttl`; false if another replica holds it (or the claim can't be confirmed)
+    /// `ttl` should cover one run and stay below the job's period, so the next run is up for grabs again
+    pub async fn claim(&self, job: &str, ttl: Duration) -> bool {
+        match self.clickhouse.acquire_lease(&format!("job:{}", job), &self.instance_id, ttl).await {
+            Ok(true) => true,
+            Ok(false) => {
+                println!("🔒 [JobLeases] Skipping {}: another instance holds it", job);
+                false
+            }
+            Err(e) => {
+                // Skipping one run is safer than two replicas running it
+                eprintln!("⚠️  [JobLeases] Failed to claim {}: {}", job, e);
+                false
+            }
+        }
+    }
+
+    /// Latest claim on every job lease
+    pub async fn list(&self) -> Result<Vec<LeaseRow>> {
+        self.clickhouse.list_leases().await
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+}
@@ -6,6 +6,11 @@ use anyhow::Result;
 use serde::Deserialize;
 use std::collections::HashMap;
 
+/// Price API V3, unless JUPITER_PRICE_API_URL is set
+pub const DEFAULT_PRICE_API_URL: &str = "https://lite-api.jup.ag/price/v3";
+/// Swap API V6, unless JUPITER_SWAP_API_URL is set
+pub const DEFAULT_SWAP_API_URL: &str = "https://quote-api.jup.ag/v6";
+
 #[derive(Clone)]
 pub struct JupiterService {
     price_api_url: String,
@@ -48,6 +53,9 @@ pub struct QuoteResponse {
     pub price_impact_pct: String,
     #[serde(rename = "routePlan")]
     pub route_plan: Vec<RoutePlan>,
+    /// Untouched response body, echoed back to /swap when building the transaction
+    #[serde(skip)]
+    pub raw: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
@@ -84,14 +92,11 @@ pub struct SwapInfo {
 }
 
 impl JupiterService {
-    pub fn new() -> Result<Self> {
-        // Hardcoded Jupiter API URLs (not from .env)
-        // Price API V3: https://lite-api.jup.ag/price/v3
-        // Swap API V6: https://quote-api.jup.ag/v6
-        Ok(Self {
-            price_api_url: "https://lite-api.jup.ag/price/v3".to_string(),
-            swap_api_url: "https://quote-api.jup.ag/v6".to_string(),
-        })
+    pub fn new(price_api_url: &str, swap_api_url: &str) -> Self {
+        Self {
+            price_api_url: price_api_url.trim_end_matches('/').to_string(),
+            swap_api_url: swap_api_url.trim_end_matches('/').to_string(),
+        }
     }
 
     /// Get price for a token pair (Jupiter Price API V3)
@@ -167,38 +172,99 @@ impl JupiterService {
         }
     }
 
+    /// Get USD prices for a set of mints (Jupiter Price API V3, batched 50 ids per call)
+    /// Mints without price data are omitted from the result
+    pub async fn get_usd_prices(&self, mints: &[String]) -> Result<HashMap<String, f64>> {
+        let client = reqwest::Client::new();
+        let mut prices = HashMap::new();
+        
+        for chunk in mints.chunks(50) {
+            let url = format!("{}?ids={}", self.price_api_url, chunk.join(","));
+            let response: HashMap<String, Option<PriceDataV3>> = client
+                .get(&url)
+                .send()
+                .await?
+                .json()
+                .await?;
+            
+            for (mint, data) in response {
+                if let Some(data) = data {
+                    prices.insert(mint, data.usd_price);
+                }
+            }
+        }
+        
+        Ok(prices)
+    }
+
     /// Get quote for a swap (Jupiter Swap API V6)
-    /// Uses: https://quote-api.jup.ag/v6/quote?inputMint={}&outputMint={}&amount={}&slippageBps={}
+    /// Uses: https://quote-api.jup.ag/v6/quote?inputMint={}&outputMint={}&amount={}&slippageBps={}[&platformFeeBps={}]
     pub async fn get_quote(
         &self,
         input_mint: &str,
         output_mint: &str,
         amount: u64,
         slippage_bps: u16,
+        platform_fee_bps: Option<u16>,
     ) -> Result<QuoteResponse> {
         let client = reqwest::Client::new();
-        let url = format!(
+        let mut url = format!(
             "{}/quote?inputMint={}&outputMint={}&amount={}&slippageBps={}",
             self.swap_api_url, input_mint, output_mint, amount, slippage_bps
         );
+        if let Some(fee_bps) = platform_fee_bps {
+            url.push_str(&format!("&platformFeeBps={}", fee_bps));
+        }
         
-        let response: QuoteResponse = client
+        let raw: serde_json::Value = client
             .get(&url)
             .send()
             .await?
             .json()
             .await?;
+        
+        if let Some(error) = raw.get("error") {
+            return Err(anyhow::anyhow!("Jupiter quote failed: {}", error));
+        }
+        
+        let mut quote: QuoteResponse = serde_json::from_value(raw.clone())?;
+        quote.raw = raw;
 
-        Ok(response)
+        Ok(quote)
     }
 
-    /// Get price for SOL/USDC pair
-    pub async fn get_sol_usdc_price(&self) -> Result<f64> {
-        // SOL mint: So11111111111111111111111111111111111111112
-        // USDC mint: EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v
-        let sol_mint = "So11111111111111111111111111111111111111112";
-        let usdc_mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    /// Build an unsigned swap transaction for a quote (Jupiter Swap API V6)
+    /// Uses: POST https://quote-api.jup.ag/v6/swap; returns the base64 transaction
+    /// `fee_account` receives the platform fee when the quote was requested with platformFeeBps
+    pub async fn build_swap_transaction(
+        &self,
+        quote: &QuoteResponse,
+        user_pubkey: &str,
+        fee_account: Option<&str>,
+    ) -> Result<String> {
+        let client = reqwest::Client::new();
+        let mut payload = serde_json::json!({
+            "quoteResponse": quote.raw,
+            "userPublicKey": user_pubkey,
+            "wrapAndUnwrapSol": true,
+            "dynamicComputeUnitLimit": true
+        });
+        if let Some(fee_account) = fee_account {
+            payload["feeAccount"] = serde_json::json!(fee_account);
+        }
+        
+        let response: serde_json::Value = client
+            .post(format!("{}/swap", self.swap_api_url))
+            .json(&payload)
+            .send()
+            .await?
+            .json()
+            .await?;
         
-        self.get_price(sol_mint, usdc_mint).await
+        response
+            .get("swapTransaction")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow::anyhow!("Jupiter swap build failed: {}", response))
     }
 }
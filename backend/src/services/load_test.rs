@@ -0,0 +1,266 @@
+// Load test generator - injects synthetic trades into the trade stream at a fixed rate for a bounded
+// time and reports their end-to-end latency, to check capacity before onboarding high-volume pairs
+//
+// Synthetic trades go through the stream like ingested ones (oracle lookup, ClickHouse writer,
+// broadcast) but are stored flagged quarantined, so reads never see them, skip the per-pair
+// stats, sequencing and price checks, and are broadcast on the opt-in `loadtest` channel only.
+
+use crate::models::trade::Trade;
+use crate::services::pair_mapping::Pair;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
+
+/// Venue and id prefix of synthetic trades
+pub const LOAD_TEST_VENUE: &str = "LoadTest";
+const ID_PREFIX: &str = "loadtest-";
+/// Accepted rates (trades per second) and durations (seconds)
+pub const MAX_RATE: u32 = 5_000;
+pub const MAX_DURATION_SECS: u64 = 300;
+/// How often the generator sends the trades that are due
+const TICK: Duration = Duration::from_millis(10);
+/// How long trades still in the pipeline are waited for after the last one is sent
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether a trade came from the load test generator
+pub fn is_synthetic(trade: &Trade) -> bool {
+    trade.dex_program == LOAD_TEST_VENUE && trade.id.starts_with(ID_PREFIX)
+}
+
+/// POST /admin/load-test body
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoadTestConfig {
+    /// Trades per second
+    pub rate: u32,
+    pub duration_secs: u64,
+    pub pair: Pair,
+    /// Price of the synthetic trades, in the quote token
+    #[serde(default = "default_price")]
+    pub price: f64,
+}
+
+fn default_price() -> f64 {
+    100.0
+}
+
+impl LoadTestConfig {
+    fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_RATE).contains(&self.rate) {
+            return Err(format!("rate must be between 1 and {} trades per second", MAX_RATE));
+        }
+        if !(1..=MAX_DURATION_SECS).contains(&self.duration_secs) {
+            return Err(format!("duration_secs must be between 1 and {}", MAX_DURATION_SECS));
+        }
+        if self.pair.mints().is_none() {
+            return Err(format!("unsupported pair {}", self.pair));
+        }
+        if !self.price.is_finite() || self.price <= 0.0 {
+            return Err("price must be a positive number".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Latency percentiles of the completed trades, in milliseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyPercentiles {
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadTestReport {
+    pub run_id: u64,
+    pub status: &'static str,
+    pub config: LoadTestConfig,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub sent: u64,
+    /// Trades that made it through the pipeline to the broadcast
+    pub completed: u64,
+    /// Trades still unaccounted for when the run finished
+    pub lost: u64,
+    /// Rate actually sent; below the requested one when the ingest queue pushed back
+    pub achieved_rate: f64,
+    pub latency: Option<LatencyPercentiles>,
+}
+
+struct Run {
+    report: LoadTestReport,
+    /// Synthetic trade id -> when it was handed to the pipeline
+    in_flight: HashMap<String, Instant>,
+    latencies_us: Vec<u64>,
+    stop_tx: watch::Sender<bool>,
+}
+
+fn percentiles(samples: &mut [u64]) -> Option<LatencyPercentiles> {
+    samples.sort_unstable();
+    let last = samples.len().checked_sub(1)?;
+    let at = |p: f64| samples[((last as f64) * p).round() as usize] as f64 / 1000.0;
+    Some(LatencyPercentiles {
+        p50_ms: at(0.5),
+        p90_ms: at(0.9),
+        p99_ms: at(0.99),
+        max_ms: samples[last] as f64 / 1000.0,
+    })
+}
+
+pub struct LoadTestService {
+    ingest_tx: mpsc::Sender<Trade>,
+    /// The current run, or the last one once it's finished
+    run: Mutex<Option<Run>>,
+    next_run_id: Mutex<u64>,
+}
+
+impl LoadTestService {
+    pub fn new(ingest_tx: mpsc::Sender<Trade>) -> Self {
+        Self {
+            ingest_tx,
+            run: Mutex::new(None),
+            next_run_id: Mutex::new(1),
+        }
+    }
+
+    /// Start a run; only one runs at a time
+    pub async fn start(self: Arc<Self>, config: LoadTestConfig) -> Result<LoadTestReport, String> {
+        config.validate()?;
+        let mut run = self.run.lock().await;
+        if run.as_ref().is_some_and(|r| r.report.finished_at.is_none()) {
+            return Err("A load test is already running".to_string());
+        }
+
+        let run_id = {
+            let mut next = self.next_run_id.lock().await;
+            let id = *next;
+            *next += 1;
+            id
+        };
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let report = LoadTestReport {
+            run_id,
+            status: "running",
+            config: config.clone(),
+            started_at: Utc::now(),
+            finished_at: None,
+            sent: 0,
+            completed: 0,
+            lost: 0,
+            achieved_rate: 0.0,
+            latency: None,
+        };
+        *run = Some(Run { report: report.clone(), in_flight: HashMap::new(), latencies_us: Vec::new(), stop_tx });
+        drop(run);
+
+        println!("🏋️ [LoadTest] Run {}: {} trades/s of {} for {}s", run_id, config.rate, config.pair, config.duration_secs);
+        tokio::spawn(self.generate(run_id, config, stop_rx));
+        Ok(report)
+    }
+
+    /// Stop the running test early; it still waits for trades in flight
+    pub async fn stop(&self) -> bool {
+        match self.run.lock().await.as_ref() {
+            Some(run) if run.report.finished_at.is_none() => run.stop_tx.send(true).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Record a synthetic trade reaching the end of the pipeline
+    pub async fn complete(&self, trade_id: &str) {
+        let mut run = self.run.lock().await;
+        let Some(run) = run.as_mut() else { return };
+        if let Some(sent_at) = run.in_flight.remove(trade_id) {
+            run.latencies_us.push(sent_at.elapsed().as_micros() as u64);
+            run.report.completed += 1;
+        }
+    }
+
+    /// The current or last run, with latencies so far
+    pub async fn report(&self) -> Option<LoadTestReport> {
+        let mut run = self.run.lock().await;
+        let run = run.as_mut()?;
+        run.report.latency = percentiles(&mut run.latencies_us);
+        Some(run.report.clone())
+    }
+
+    fn synthetic_trade(run_id: u64, seq: u64, config: &LoadTestConfig, base_mint: &str, quote_mint: &str) -> Trade {
+        let amount = 1.0 + (seq % 100) as f64 / 100.0;
+        Trade {
+            id: format!("{}{}-{}", ID_PREFIX, run_id, seq),
+            timestamp: Utc::now(),
+            base_symbol: config.pair.base.clone(),
+            quote_symbol: config.pair.quote.clone(),
+            base_mint: base_mint.to_string(),
+            quote_mint: quote_mint.to_string(),
+            price: config.price,
+            amount,
+            side: if seq.is_multiple_of(2) { "buy" } else { "sell" }.to_string(),
+            total_value: config.price * amount,
+            dex_program: LOAD_TEST_VENUE.to_string(),
+            slot: 0,
+            sequence: 0,
+            trader: None,
+            inverted: false,
+            spread_bps: None,
+        }
+    }
+
+    async fn generate(self: Arc<Self>, run_id: u64, config: LoadTestConfig, mut stop_rx: watch::Receiver<bool>) {
+        let (base_mint, quote_mint) = config.pair.mints().unwrap_or_default();
+        let started = Instant::now();
+        let duration = Duration::from_secs(config.duration_secs);
+        let mut ticker = interval(TICK);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut sent: u64 = 0;
+
+        'generate: while started.elapsed() < duration && !*stop_rx.borrow() {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = stop_rx.changed() => break,
+            }
+            let due = (started.elapsed().min(duration).as_secs_f64() * config.rate as f64) as u64;
+            while sent < due {
+                let trade = Self::synthetic_trade(run_id, sent, &config, &base_mint, &quote_mint);
+                if let Some(run) = self.run.lock().await.as_mut() {
+                    run.in_flight.insert(trade.id.clone(), Instant::now());
+                }
+                // Waits while the ingest queue is full, which shows up as a lower achieved rate
+                if self.ingest_tx.send(trade).await.is_err() {
+                    break 'generate;
+                }
+                sent += 1;
+            }
+            if let Some(run) = self.run.lock().await.as_mut() {
+                run.report.sent = sent;
+            }
+        }
+        let elapsed = started.elapsed().as_secs_f64();
+
+        // Give trades still in the pipeline a chance to come out
+        let drain_deadline = Instant::now() + DRAIN_TIMEOUT;
+        while Instant::now() < drain_deadline {
+            if self.run.lock().await.as_ref().is_none_or(|run| run.in_flight.is_empty()) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        if let Some(run) = self.run.lock().await.as_mut() {
+            run.report.sent = sent;
+            run.report.lost = run.in_flight.len() as u64;
+            run.in_flight.clear();
+            run.report.achieved_rate = if elapsed > 0.0 { sent as f64 / elapsed } else { 0.0 };
+            run.report.status = if *stop_rx.borrow() { "stopped" } else { "finished" };
+            run.report.finished_at = Some(Utc::now());
+            run.report.latency = percentiles(&mut run.latencies_us);
+            println!(
+                "🏋️ [LoadTest] Run {} {}: {} sent ({:.0}/s), {} completed, {} lost, latency {:?}",
+                run_id, run.report.status, sent, run.report.achieved_rate, run.report.completed, run.report.lost, run.report.latency
+            );
+        }
+    }
+}
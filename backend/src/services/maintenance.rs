@@ -0,0 +1,223 @@
+// ClickHouse maintenance job - samples part counts and runs OPTIMIZE during a low-traffic window
+
+use crate::services::clickhouse::{ClickHouseService, PartsHealthRow};
+use crate::services::job_leases::JobLeases;
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+
+/// Tables the job may optimize (names are interpolated into OPTIMIZE statements)
+const MAINTAINED_TABLES: &[&str] = &[
+    "trades",
+    "trades_by_trader",
+    "sessions",
+    "user_preferences",
+    "swap_quotes",
+    "platform_fees",
+    "user_digests",
+    "admin_events",
+    "prices",
+    "ws_usage_samples",
+    "leases",
+    "candle_snapshots",
+    "market_alerts",
+];
+/// How often part counts are sampled
+const CHECK_INTERVAL: Duration = Duration::from_secs(900);
+/// Lease on the one-off ReplacingMergeTree migration, long enough to copy a large trades table
+const ENGINE_MIGRATION_LEASE: Duration = Duration::from_secs(6 * 3600);
+/// How often a replica waiting on another's timestamp migration checks whether it has finished
+const TIMESTAMP_MIGRATION_POLL: Duration = Duration::from_secs(30);
+
+/// Move a second-precision trades table to DateTime64(3) before ingesting, once across replicas
+/// The replica that claims the `replacing_migration` lease (both migrations rebuild trades, so they
+/// share it) runs it; the others wait until it's done, since their inserts don't fit the old column
+pub async fn migrate_timestamps(clickhouse: &ClickHouseService, job_leases: &JobLeases) {
+    loop {
+        match clickhouse.trades_timestamp_in_seconds().await {
+            Ok(false) => return,
+            Ok(true) => {
+                if job_leases.claim("replacing_migration", ENGINE_MIGRATION_LEASE).await {
+                    match clickhouse.migrate_trades_to_millis().await {
+                        Ok(()) => {
+                            if let Err(e) = clickhouse.store_admin_event("tables_migrated", "clickhouse", &json!({ "tables": ["trades"], "timestamp": "DateTime64(3)" })).await {
+                                eprintln!("⚠️  [Maintenance] Failed to record migration event: {}", e);
+                            }
+                        }
+                        // Retried by whichever replica claims the lease once it expires
+                        Err(e) => eprintln!("❌ [Maintenance] {:#}", e),
+                    }
+                    return;
+                }
+                println!("⏳ [Maintenance] Waiting for another instance to migrate trades.timestamp");
+            }
+            Err(e) => eprintln!("❌ [Maintenance] Timestamp check failed: {}", e),
+        }
+        tokio::time::sleep(TIMESTAMP_MIGRATION_POLL).await;
+    }
+}
+
+/// When the optimize pass may run, read with the rest of the Config
+#[derive(Debug, Clone, Copy)]
+pub struct MaintenanceWindow {
+    /// First hour (UTC) of the window; MAINTENANCE_WINDOW_START_HOUR, default 3
+    pub start_hour: u32,
+    /// Length of the window in hours; MAINTENANCE_WINDOW_HOURS, default 2
+    pub hours: u32,
+    /// Tables with more active parts than this are optimized; MAINTENANCE_PARTS_THRESHOLD, default 50
+    pub parts_threshold: u64,
+}
+
+/// Latest part-count sample and the last optimize pass
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaintenanceReport {
+    pub checked_at: Option<DateTime<Utc>>,
+    pub tables: Vec<PartsHealthRow>,
+    pub last_optimized_at: Option<DateTime<Utc>>,
+    pub last_optimized_tables: Vec<String>,
+    /// Replica this report was sampled on (optimize passes run on whichever replica claims them)
+    pub instance_id: String,
+}
+
+pub struct MaintenanceService {
+    clickhouse: Arc<ClickHouseService>,
+    /// Every replica samples part counts; one claims each day's optimize pass
+    job_leases: Arc<JobLeases>,
+    window_start_hour: u32,
+    window_hours: u32,
+    parts_threshold: u64,
+    report: RwLock<MaintenanceReport>,
+}
+
+impl MaintenanceService {
+    pub fn new(clickhouse: Arc<ClickHouseService>, job_leases: Arc<JobLeases>, window: MaintenanceWindow) -> Self {
+        Self {
+            clickhouse,
+            job_leases,
+            window_start_hour: window.start_hour,
+            window_hours: window.hours,
+            parts_threshold: window.parts_threshold,
+            report: RwLock::new(MaintenanceReport::default()),
+        }
+    }
+
+    /// Sample part counts every 15 minutes and optimize once per day inside the window
+    /// Trades tables still on a plain MergeTree are migrated first, by whichever replica claims it
+    pub async fn start(self: Arc<Self>) {
+        self.migrate_engines().await;
+        println!(
+            "🧹 [Maintenance] Window {:02}:00 UTC for {}h, optimizing tables above {} parts",
+            self.window_start_hour, self.window_hours, self.parts_threshold
+        );
+        let mut last_run: Option<NaiveDate> = None;
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let tables = match self.clickhouse.get_parts_health().await {
+                Ok(tables) => tables,
+                Err(e) => {
+                    eprintln!("❌ [Maintenance] Parts check failed: {}", e);
+                    continue;
+                }
+            };
+            for table in tables.iter().filter(|t| t.parts > self.parts_threshold) {
+                println!("⚠️  [Maintenance] {} has {} active parts", table.table, table.parts);
+            }
+            {
+                let mut report = self.report.write().await;
+                report.checked_at = Some(Utc::now());
+                report.tables = tables.clone();
+            }
+
+            let now = Utc::now();
+            if !self.in_window(now.hour()) || last_run == Some(now.date_naive()) {
+                continue;
+            }
+            last_run = Some(now.date_naive());
+            // Held past the window so no other replica optimizes again today
+            if !self.job_leases.claim("maintenance_optimize", Duration::from_secs(20 * 3600)).await {
+                continue;
+            }
+            self.optimize(&tables).await;
+        }
+    }
+
+    /// Move trades tables created before deduplication to ReplacingMergeTree, once across replicas
+    /// Skipped while trades still has second-precision timestamps: that migration rebuilds trades (and the
+    /// trades_by_trader view) too, and the lease doesn't keep its own holder from running both at once
+    async fn migrate_engines(&self) {
+        match self.clickhouse.trades_timestamp_in_seconds().await {
+            Ok(false) => {}
+            Ok(true) => {
+                println!("⏳ [Maintenance] Engine migration deferred until trades.timestamp is migrated");
+                return;
+            }
+            Err(e) => {
+                eprintln!("❌ [Maintenance] Timestamp check failed: {}", e);
+                return;
+            }
+        }
+        let pending = match self.clickhouse.pending_replacing_migrations().await {
+            Ok(pending) => pending,
+            Err(e) => {
+                eprintln!("❌ [Maintenance] Engine check failed: {}", e);
+                return;
+            }
+        };
+        if pending.is_empty() || !self.job_leases.claim("replacing_migration", ENGINE_MIGRATION_LEASE).await {
+            return;
+        }
+        for table in &pending {
+            if let Err(e) = self.clickhouse.migrate_to_replacing(table).await {
+                eprintln!("❌ [Maintenance] {:#}", e);
+                return;
+            }
+        }
+        if let Err(e) = self.clickhouse.store_admin_event("tables_migrated", "clickhouse", &json!({ "tables": pending, "engine": "ReplacingMergeTree" })).await {
+            eprintln!("⚠️  [Maintenance] Failed to record migration event: {}", e);
+        }
+    }
+
+    /// Whether an hour of day (UTC) falls inside the maintenance window
+    fn in_window(&self, hour: u32) -> bool {
+        (hour + 24 - self.window_start_hour) % 24 < self.window_hours
+    }
+
+    /// OPTIMIZE every maintained table over the parts threshold
+    async fn optimize(&self, tables: &[PartsHealthRow]) {
+        let mut optimized = Vec::new();
+        for table in tables {
+            if table.parts <= self.parts_threshold || !MAINTAINED_TABLES.contains(&table.table.as_str()) {
+                continue;
+            }
+            match self.clickhouse.optimize_table(&table.table).await {
+                Ok(()) => {
+                    println!("🧹 [Maintenance] Optimized {} ({} parts)", table.table, table.parts);
+                    optimized.push(table.table.clone());
+                }
+                Err(e) => eprintln!("❌ [Maintenance] {}", e),
+            }
+        }
+
+        if !optimized.is_empty()
+            && let Err(e) = self.clickhouse.store_admin_event("tables_optimized", "clickhouse", &json!({ "tables": optimized })).await
+        {
+            eprintln!("⚠️  [Maintenance] Failed to record optimize event: {}", e);
+        }
+        let mut report = self.report.write().await;
+        report.last_optimized_at = Some(Utc::now());
+        report.last_optimized_tables = optimized;
+    }
+
+    /// Latest part counts and optimize pass
+    pub async fn report(&self) -> MaintenanceReport {
+        MaintenanceReport {
+            instance_id: self.job_leases.instance_id().to_string(),
+            ..self.report.read().await.clone()
+        }
+    }
+}
@@ -0,0 +1,134 @@
+// Market overview service - periodic compact snapshot of every tracked pair for the `markets` WS channel,
+// and the per-token moves behind the /api/movers leaderboards
+
+use crate::models::schema::CURRENT_SCHEMA_VERSION;
+use crate::services::aggregator::TradeAggregator;
+use crate::services::clickhouse::MarketSummaryRow;
+use crate::services::pair_mapping::{parse_pair, Pair, supported_pairs};
+use crate::websocket::messages::OutboundMessage;
+use crate::websocket::ConnectionManager;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+
+const MARKETS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Broadcast the market overview every few seconds while anyone is connected
+pub async fn start(aggregator: Arc<TradeAggregator>, ws_manager: Arc<ConnectionManager>) {
+    let mut ticker = interval(MARKETS_INTERVAL);
+    loop {
+        ticker.tick().await;
+        if ws_manager.connection_count().await == 0 {
+            continue;
+        }
+
+        let summaries = match aggregator.market_summaries().await {
+            Ok(summaries) => summaries,
+            Err(e) => {
+                eprintln!("⚠️  [Markets] Failed to load market summaries: {}", e);
+                continue;
+            }
+        };
+
+        let markets: Vec<serde_json::Value> = supported_pairs()
+            .iter()
+            .filter_map(|pair| {
+                let Pair { base, quote } = parse_pair(pair)?;
+                Some(market_entry(pair, &base, &quote, &summaries))
+            })
+            .collect();
+
+        let payload = json!({
+            "type": "markets",
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "timestamp": Utc::now().to_rfc3339(),
+            "markets": markets,
+        });
+        ws_manager.broadcast(OutboundMessage::new("markets", None, payload.to_string())).await;
+    }
+}
+
+/// Last price, first price and quote volume of a pair; trades stored in the reverse direction are inverted
+fn pair_summary(base: &str, quote: &str, summaries: &[MarketSummaryRow]) -> Option<(f64, f64, f64)> {
+    let direct = summaries.iter().find(|s| s.base_symbol == base && s.quote_symbol == quote);
+    let reverse = summaries.iter().find(|s| s.base_symbol == quote && s.quote_symbol == base);
+
+    match (direct, reverse) {
+        (Some(s), _) => Some((s.last_price, s.first_price, s.volume)),
+        (None, Some(s)) if s.last_price > 0.0 && s.first_price > 0.0 => {
+            // Reverse volume is denominated in our base token
+            let price = 1.0 / s.last_price;
+            Some((price, 1.0 / s.first_price, s.volume * price))
+        }
+        _ => None,
+    }
+}
+
+/// Compact entry for a pair; trades stored in the reverse direction are inverted
+pub fn market_entry(pair: &str, base: &str, quote: &str, summaries: &[MarketSummaryRow]) -> serde_json::Value {
+    let Some((price, first_price, volume)) = pair_summary(base, quote, summaries) else {
+        return json!({ "pair": pair, "price": null, "change_24h_pct": null, "volume_24h": 0.0 });
+    };
+
+    let change_pct = if first_price > 0.0 { (price - first_price) / first_price * 100.0 } else { 0.0 };
+    json!({
+        "pair": pair,
+        "price": price,
+        "change_24h_pct": change_pct,
+        "volume_24h": volume,
+    })
+}
+
+/// A tracked token's move over a window, for the /api/movers leaderboards
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenMove {
+    pub token: String,
+    /// Pair the price and change are read from: the token's busiest pair as base; None when it's
+    /// only ever the quote token
+    pub pair: Option<String>,
+    pub price: Option<f64>,
+    pub change_pct: Option<f64>,
+    /// Traded volume across every pair the token is in, in USD; None when a quote token has no USD price
+    pub volume_usd: Option<f64>,
+}
+
+/// Moves of every tracked token that traded in the summaries' window
+/// `usd_prices` is keyed by token symbol and converts each pair's quote volume to USD
+pub fn token_moves(summaries: &[MarketSummaryRow], usd_prices: &HashMap<String, f64>) -> Vec<TokenMove> {
+    let mut moves: HashMap<String, TokenMove> = HashMap::new();
+    // Volume of the pair each token's price is currently read from, to keep the busiest
+    let mut reference_volume: HashMap<String, f64> = HashMap::new();
+
+    for pair in supported_pairs() {
+        let Some(Pair { base, quote }) = parse_pair(&pair) else { continue };
+        let Some((price, first_price, volume)) = pair_summary(&base, &quote, summaries) else { continue };
+        let volume_usd = usd_prices.get(&quote).map(|usd| volume * usd);
+
+        for token in [&base, &quote] {
+            let entry = moves.entry(token.clone()).or_insert_with(|| TokenMove {
+                token: token.clone(),
+                pair: None,
+                price: None,
+                change_pct: None,
+                volume_usd: Some(0.0),
+            });
+            entry.volume_usd = entry.volume_usd.zip(volume_usd).map(|(total, pair_usd)| total + pair_usd);
+        }
+
+        // Rank by USD volume where known so pairs quoted in different tokens compare fairly
+        let rank = volume_usd.unwrap_or(volume);
+        if reference_volume.get(&base).is_none_or(|busiest| rank > *busiest) {
+            reference_volume.insert(base.clone(), rank);
+            if let Some(entry) = moves.get_mut(&base) {
+                entry.pair = Some(pair.clone());
+                entry.price = Some(price);
+                entry.change_pct = (first_price > 0.0).then(|| (price - first_price) / first_price * 100.0);
+            }
+        }
+    }
+
+    moves.into_values().collect()
+}
@@ -0,0 +1,376 @@
+// Memory budget - per-pair accounting and eviction for the in-memory state the trade stream keeps
+// (24h windows, live candles, spike baselines, circuit breaker windows, sanity rejections) and the
+// signature dedup set, so pairs picked up from the token list can't grow resident memory unbounded
+//
+// Each cache holds its pairs in a `PairLru`. A pair that outgrows PAIR_MEMORY_BUDGET_BYTES is shed
+// (or dropped), and once a cache holds PAIR_CACHE_MAX_PAIRS pairs the least recently used one is
+// evicted to make room. Sizes are estimates of the entries' inline and heap bytes, not allocator
+// measurements. Usage per cache is reported on GET /admin/memory.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PAIR_BUDGET_BYTES: usize = 16 * 1024 * 1024;
+pub const DEFAULT_MAX_PAIRS: usize = 256;
+pub const DEFAULT_DEDUP_CAPACITY: usize = 10_000;
+pub const DEFAULT_DEDUP_TTL_SECS: u64 = 600;
+
+/// Limits of the in-memory caches, read with the rest of the Config
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryLimits {
+    /// PAIR_MEMORY_BUDGET_BYTES, default 16 MiB
+    pub per_pair_bytes: usize,
+    /// PAIR_CACHE_MAX_PAIRS, default 256
+    pub max_pairs: usize,
+    /// SIGNATURE_DEDUP_CAPACITY, default 10000
+    pub dedup_capacity: usize,
+    /// SIGNATURE_DEDUP_TTL_SECS, default 600
+    pub dedup_ttl: Duration,
+}
+
+/// Approximate memory held by a cache entry
+pub trait Footprint {
+    /// Inline plus heap bytes
+    fn footprint(&self) -> usize;
+
+    /// Called when the entry outgrows the per-pair budget; entries still over it afterwards are dropped
+    fn shed(&mut self) {}
+}
+
+/// What one cache holds and has evicted since startup
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CacheUsage {
+    /// Pairs (or signatures, for the dedup set)
+    pub entries: usize,
+    pub bytes: usize,
+    /// Least recently used pairs evicted to stay within PAIR_CACHE_MAX_PAIRS
+    pub evicted_cold: u64,
+    /// Pairs shed or dropped for outgrowing PAIR_MEMORY_BUDGET_BYTES
+    pub over_budget: u64,
+}
+
+pub struct MemoryBudget {
+    /// Bytes one pair may hold in a single cache
+    pub per_pair_bytes: usize,
+    /// Pairs a single cache holds before evicting the least recently used
+    pub max_pairs: usize,
+    /// Signatures remembered for deduplicating the log stream
+    pub dedup_capacity: usize,
+    /// How long after it was first seen a signature is forgotten
+    pub dedup_ttl: Duration,
+    usage: Mutex<BTreeMap<&'static str, CacheUsage>>,
+}
+
+impl MemoryBudget {
+    pub fn new(limits: MemoryLimits) -> Self {
+        Self {
+            per_pair_bytes: limits.per_pair_bytes,
+            max_pairs: limits.max_pairs,
+            dedup_capacity: limits.dedup_capacity,
+            dedup_ttl: limits.dedup_ttl,
+            usage: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn publish(&self, cache: &'static str, usage: CacheUsage) {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).insert(cache, usage);
+    }
+
+    /// Limits, and usage per cache with the total
+    pub fn report(&self) -> serde_json::Value {
+        let usage = self.usage.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        serde_json::json!({
+            "per_pair_bytes": self.per_pair_bytes,
+            "max_pairs": self.max_pairs,
+            "dedup_capacity": self.dedup_capacity,
+            "dedup_ttl_secs": self.dedup_ttl.as_secs(),
+            "total_bytes": usage.values().map(|u| u.bytes).sum::<usize>(),
+            "caches": usage,
+        })
+    }
+}
+
+struct Slot<V> {
+    value: V,
+    last_used: u64,
+    bytes: usize,
+}
+
+/// Per-pair entries of one cache, kept within the pair budget and evicted least recently used first
+pub struct PairLru<K, V> {
+    cache: &'static str,
+    budget: Arc<MemoryBudget>,
+    entries: HashMap<K, Slot<V>>,
+    clock: u64,
+    usage: CacheUsage,
+}
+
+impl<K: Eq + Hash + Clone + Debug, V: Footprint> PairLru<K, V> {
+    pub fn new(cache: &'static str, budget: Arc<MemoryBudget>) -> Self {
+        let lru = Self {
+            cache,
+            budget,
+            entries: HashMap::new(),
+            clock: 0,
+            usage: CacheUsage::default(),
+        };
+        lru.budget.publish(cache, lru.usage);
+        lru
+    }
+
+    /// Update a held pair's entry, counting it as used; None when the pair isn't held
+    pub fn update<R>(&mut self, key: &K, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        self.clock += 1;
+        let slot = self.entries.get_mut(key)?;
+        slot.last_used = self.clock;
+        let result = f(&mut slot.value);
+        self.resize(key);
+        Some(result)
+    }
+
+    /// Like `update`, inserting `default()` first when the pair isn't held
+    pub fn upsert<R>(&mut self, key: K, default: impl FnOnce() -> V, f: impl FnOnce(&mut V) -> R) -> R {
+        if !self.entries.contains_key(&key) {
+            self.insert(key.clone(), default());
+        }
+        self.update(&key, f).expect("entry was just inserted")
+    }
+
+    /// Hold a pair's entry, replacing any it had; evicts the least recently used pair when full
+    pub fn insert(&mut self, key: K, value: V) {
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.budget.max_pairs {
+            self.evict_coldest();
+        }
+        self.clock += 1;
+        let previous = self.entries.insert(key.clone(), Slot { value, last_used: self.clock, bytes: 0 });
+        if let Some(previous) = previous {
+            self.usage.bytes -= previous.bytes;
+        }
+        self.resize(&key);
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let slot = self.entries.remove(key)?;
+        self.usage.bytes -= slot.bytes;
+        self.usage.entries = self.entries.len();
+        self.budget.publish(self.cache, self.usage);
+        Some(slot.value)
+    }
+
+    /// Held pairs, without counting as use
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(key, slot)| (key, &slot.value))
+    }
+
+    /// Held pairs for in-place changes that don't grow them (e.g. clearing flags); not counted as use
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.entries.iter_mut().map(|(key, slot)| (key, &mut slot.value))
+    }
+
+    /// Re-measure a pair after a change, shedding or dropping it when it's over budget
+    fn resize(&mut self, key: &K) {
+        let per_pair_bytes = self.budget.per_pair_bytes;
+        let Some(slot) = self.entries.get_mut(key) else { return };
+        let mut bytes = std::mem::size_of::<K>() + slot.value.footprint();
+        if bytes > per_pair_bytes {
+            self.usage.over_budget += 1;
+            slot.value.shed();
+            bytes = std::mem::size_of::<K>() + slot.value.footprint();
+            eprintln!(
+                "⚠️  [Memory] {} {:?} outgrew its {} byte budget ({} bytes after shedding)",
+                self.cache, key, per_pair_bytes, bytes
+            );
+        }
+        self.usage.bytes = self.usage.bytes - slot.bytes + bytes;
+        slot.bytes = bytes;
+        if bytes > per_pair_bytes {
+            self.remove(key);
+            return;
+        }
+        self.usage.entries = self.entries.len();
+        self.budget.publish(self.cache, self.usage);
+    }
+
+    fn evict_coldest(&mut self) {
+        let Some(coldest) = self.entries.iter().min_by_key(|(_, slot)| slot.last_used).map(|(key, _)| key.clone())
+        else {
+            return;
+        };
+        self.usage.evicted_cold += 1;
+        self.remove(&coldest);
+        println!("🧹 [Memory] Evicted cold pair {:?} from {}", coldest, self.cache);
+    }
+}
+
+/// Recently seen signatures, oldest first out: a signature is forgotten `dedup_ttl` after it was
+/// first seen, or earlier once `dedup_capacity` newer ones are held. Repeat sightings don't extend
+/// it, so each signature is held once however often the stream repeats it
+pub struct SignatureDedup {
+    budget: Arc<MemoryBudget>,
+    /// When each signature was first seen
+    seen: HashMap<String, Instant>,
+    /// The same signatures, oldest first
+    order: VecDeque<(String, Instant)>,
+    bytes: usize,
+}
+
+impl SignatureDedup {
+    const CACHE: &'static str = "signature_dedup";
+
+    pub fn new(budget: Arc<MemoryBudget>) -> Self {
+        let dedup = Self { budget, seen: HashMap::new(), order: VecDeque::new(), bytes: 0 };
+        dedup.publish();
+        dedup
+    }
+
+    /// Held in the map and in each of its sightings
+    fn entry_bytes(signature: &str) -> usize {
+        std::mem::size_of::<String>() + signature.len() + std::mem::size_of::<Instant>()
+    }
+
+    /// Remember a signature; false when it was seen within the TTL
+    pub fn insert(&mut self, signature: &str) -> bool {
+        self.insert_at(signature, Instant::now())
+    }
+
+    fn insert_at(&mut self, signature: &str, now: Instant) -> bool {
+        self.forget_while(|dedup, seen_at| now.duration_since(seen_at) >= dedup.budget.dedup_ttl);
+        if self.seen.contains_key(signature) {
+            return false;
+        }
+        self.forget_while(|dedup, _| dedup.seen.len() >= dedup.budget.dedup_capacity);
+        self.seen.insert(signature.to_string(), now);
+        self.order.push_back((signature.to_string(), now));
+        self.bytes += 2 * Self::entry_bytes(signature);
+        self.publish();
+        true
+    }
+
+    /// Forget the oldest signatures while `condition` holds for the oldest
+    fn forget_while(&mut self, condition: impl Fn(&Self, Instant) -> bool) {
+        while let Some((_, seen_at)) = self.order.front() {
+            if !condition(self, *seen_at) {
+                break;
+            }
+            let Some((signature, _)) = self.order.pop_front() else { break };
+            self.seen.remove(&signature);
+            self.bytes -= 2 * Self::entry_bytes(&signature);
+        }
+    }
+
+    fn publish(&self) {
+        self.budget.publish(Self::CACHE, CacheUsage { entries: self.seen.len(), bytes: self.bytes, ..Default::default() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dedup(capacity: usize, ttl_secs: u64) -> SignatureDedup {
+        SignatureDedup::new(Arc::new(MemoryBudget::new(MemoryLimits {
+            per_pair_bytes: DEFAULT_PAIR_BUDGET_BYTES,
+            max_pairs: DEFAULT_MAX_PAIRS,
+            dedup_capacity: capacity,
+            dedup_ttl: Duration::from_secs(ttl_secs),
+        })))
+    }
+
+    fn budget(per_pair_bytes: usize, max_pairs: usize) -> Arc<MemoryBudget> {
+        Arc::new(MemoryBudget::new(MemoryLimits {
+            per_pair_bytes,
+            max_pairs,
+            dedup_capacity: DEFAULT_DEDUP_CAPACITY,
+            dedup_ttl: Duration::from_secs(DEFAULT_DEDUP_TTL_SECS),
+        }))
+    }
+
+    /// A cache entry of a given size; shedding leaves a marker
+    #[derive(Debug, PartialEq)]
+    struct Sized(usize);
+
+    impl Footprint for Sized {
+        fn footprint(&self) -> usize {
+            self.0
+        }
+
+        fn shed(&mut self) {
+            self.0 = 0;
+        }
+    }
+
+    #[test]
+    fn least_recently_used_pair_is_evicted_when_full() {
+        let mut lru = PairLru::new("test", budget(1_000, 2));
+        lru.insert("a", Sized(10));
+        lru.insert("b", Sized(10));
+        lru.update(&"a", |_| ());
+        lru.insert("c", Sized(10));
+        let mut held: Vec<&str> = lru.iter().map(|(key, _)| *key).collect();
+        held.sort();
+        assert_eq!(held, ["a", "c"]);
+        assert_eq!((lru.usage.entries, lru.usage.evicted_cold), (2, 1));
+    }
+
+    #[test]
+    fn pair_over_budget_is_shed_and_counted() {
+        let mut lru = PairLru::new("test", budget(100, 10));
+        lru.insert("a", Sized(10));
+        lru.update(&"a", |entry| entry.0 = 500);
+        assert_eq!(lru.iter().next(), Some((&"a", &Sized(0))));
+        assert_eq!(lru.usage.over_budget, 1);
+        assert_eq!(lru.usage.bytes, std::mem::size_of::<&str>());
+    }
+
+    #[test]
+    fn usage_follows_inserts_replacements_and_removals() {
+        let mut lru = PairLru::new("test", budget(1_000, 10));
+        let key = std::mem::size_of::<&str>();
+        lru.insert("a", Sized(10));
+        lru.insert("b", Sized(20));
+        lru.insert("a", Sized(30));
+        assert_eq!(lru.usage.bytes, 50 + 2 * key);
+        assert_eq!(lru.remove(&"b"), Some(Sized(20)));
+        assert_eq!((lru.usage.entries, lru.usage.bytes), (1, 30 + key));
+        assert_eq!(lru.update(&"b", |_| ()), None);
+    }
+
+    #[test]
+    fn signatures_are_forgotten_after_the_ttl() {
+        let mut dedup = dedup(100, 600);
+        let start = Instant::now();
+        assert!(dedup.insert_at("a", start));
+        assert!(!dedup.insert_at("a", start + Duration::from_secs(599)));
+        assert!(dedup.insert_at("a", start + Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn oldest_signatures_are_evicted_at_capacity() {
+        let mut dedup = dedup(2, 600);
+        let start = Instant::now();
+        for signature in ["a", "b", "c"] {
+            assert!(dedup.insert_at(signature, start));
+        }
+        assert_eq!(dedup.seen.len(), 2);
+        assert!(!dedup.insert_at("c", start));
+        assert!(dedup.insert_at("a", start));
+    }
+
+    #[test]
+    fn repeat_sightings_hold_one_entry_and_keep_the_first_time() {
+        let mut dedup = dedup(100, 600);
+        let start = Instant::now();
+        dedup.insert_at("a", start);
+        let bytes = dedup.bytes;
+        for secs in 1..50 {
+            assert!(!dedup.insert_at("a", start + Duration::from_secs(secs)));
+        }
+        assert_eq!((dedup.order.len(), dedup.bytes), (1, bytes));
+        assert!(dedup.insert_at("a", start + Duration::from_secs(600)));
+        assert_eq!(dedup.order.len(), 1);
+    }
+}
@@ -0,0 +1,183 @@
+// Message signing - optional signatures over WebSocket broadcasts, so systems relaying the feed
+// can prove the data came from this server: HMAC-SHA256 with a per-API-key secret, or the server's
+// Ed25519 key, which anyone can verify against the published public key
+
+use ed25519_dalek::{Signer, SigningKey};
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use std::collections::HashMap;
+
+/// How one connection's broadcasts are signed
+#[derive(Clone)]
+pub enum ConnectionSigner {
+    Hmac(Vec<u8>),
+    Ed25519(SigningKey),
+}
+
+impl ConnectionSigner {
+    /// Wrap a message in a signed envelope; the payload is carried as the exact string that was signed
+    pub fn sign(&self, payload: &str) -> String {
+        match self {
+            ConnectionSigner::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+                mac.update(payload.as_bytes());
+                json!({
+                    "type": "signed",
+                    "alg": "hmac-sha256",
+                    "payload": payload,
+                    "signature": hex::encode(mac.finalize().into_bytes()),
+                })
+            }
+            ConnectionSigner::Ed25519(key) => json!({
+                "type": "signed",
+                "alg": "ed25519",
+                "key": bs58::encode(key.verifying_key().as_bytes()).into_string(),
+                "payload": payload,
+                "signature": bs58::encode(key.sign(payload.as_bytes()).to_bytes()).into_string(),
+            }),
+        }
+        .to_string()
+    }
+}
+
+#[derive(Default)]
+pub struct MessageSigning {
+    server_key: Option<SigningKey>,
+    /// API key -> HMAC secret
+    hmac_secrets: HashMap<String, String>,
+}
+
+impl MessageSigning {
+    /// `server_key` signs `ed25519` envelopes (WS_SIGNING_KEY); `hmac_secrets` maps API keys to their
+    /// HMAC secrets (WS_HMAC_SECRETS); both are read with the rest of the Config
+    pub fn new(server_key: Option<SigningKey>, hmac_secrets: HashMap<String, String>) -> Self {
+        let signing = Self { server_key, hmac_secrets };
+        println!(
+            "🔏 [Signing] Ed25519 {}, HMAC secrets for {} API key(s)",
+            signing.public_key().map(|key| format!("key {}", key)).unwrap_or_else(|| "disabled".to_string()),
+            signing.hmac_secrets.len()
+        );
+        signing
+    }
+
+    /// WS_SIGNING_KEY: the server's base58 Ed25519 secret key (32-byte seed or 64-byte keypair)
+    /// A keypair's public half must be the key its seed derives, or a different key would be published
+    pub fn parse_server_key(raw: &str) -> Result<SigningKey, String> {
+        let bytes = bs58::decode(raw.trim()).into_vec().unwrap_or_default();
+        if let Ok(seed) = <[u8; 32]>::try_from(bytes.as_slice()) {
+            return Ok(SigningKey::from_bytes(&seed));
+        }
+        let keypair = <[u8; 64]>::try_from(bytes.as_slice()).map_err(|_| "not a base58 32- or 64-byte key".to_string())?;
+        SigningKey::from_keypair_bytes(&keypair)
+            .map_err(|_| "a 64-byte keypair whose public key doesn't match its secret key".to_string())
+    }
+
+    /// WS_HMAC_SECRETS=api_key:secret,...; an error names the first malformed entry
+    pub fn parse_hmac_secrets(raw: &str) -> Result<HashMap<String, String>, String> {
+        raw.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once(':') {
+                Some((api_key, secret)) if !api_key.is_empty() && !secret.is_empty() => Ok((api_key.to_string(), secret.to_string())),
+                _ => Err(format!("'{}' is not api_key:secret", entry)),
+            })
+            .collect()
+    }
+
+    /// Base58 public key that verifies `ed25519` envelopes, if the server has a signing key
+    pub fn public_key(&self) -> Option<String> {
+        self.server_key
+            .as_ref()
+            .map(|key| bs58::encode(key.verifying_key().as_bytes()).into_string())
+    }
+
+    /// Signer for a connection that asked for `?sign=hmac` or `?sign=ed25519`
+    pub fn signer(&self, scheme: &str, api_key: Option<&str>) -> Result<ConnectionSigner, String> {
+        match scheme {
+            "hmac" => api_key
+                .and_then(|key| self.hmac_secrets.get(key))
+                .map(|secret| ConnectionSigner::Hmac(secret.as_bytes().to_vec()))
+                .ok_or_else(|| "HMAC signing requires an API key with a signing secret".to_string()),
+            "ed25519" => self
+                .server_key
+                .clone()
+                .map(ConnectionSigner::Ed25519)
+                .ok_or_else(|| "Ed25519 signing is not configured on this server".to_string()),
+            other => Err(format!("Unknown signing scheme '{}'; expected hmac or ed25519", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+    use serde_json::Value;
+
+    const SEED: [u8; 32] = [7; 32];
+    const PAYLOAD: &str = r#"{"type":"trade","pair":"SOL/USDC","price":150.25}"#;
+
+    fn keypair_bytes(seed: [u8; 32]) -> Vec<u8> {
+        SigningKey::from_bytes(&seed).to_keypair_bytes().to_vec()
+    }
+
+    fn signing() -> MessageSigning {
+        MessageSigning::new(Some(SigningKey::from_bytes(&SEED)), HashMap::from([("key-1".to_string(), "hmac-secret".to_string())]))
+    }
+
+    #[test]
+    fn server_key_parses_from_a_seed_or_its_keypair() {
+        let from_seed = MessageSigning::parse_server_key(&bs58::encode(SEED).into_string()).unwrap();
+        let from_keypair = MessageSigning::parse_server_key(&bs58::encode(keypair_bytes(SEED)).into_string()).unwrap();
+        assert_eq!(from_seed.to_bytes(), from_keypair.to_bytes());
+    }
+
+    #[test]
+    fn keypair_with_a_mismatched_public_key_is_rejected() {
+        let mut mismatched = keypair_bytes(SEED);
+        mismatched[32..].copy_from_slice(&keypair_bytes([8; 32])[32..]);
+        assert!(MessageSigning::parse_server_key(&bs58::encode(mismatched).into_string()).is_err());
+    }
+
+    #[test]
+    fn keys_of_other_lengths_or_not_base58_are_rejected() {
+        assert!(MessageSigning::parse_server_key(&bs58::encode([7u8; 48]).into_string()).is_err());
+        assert!(MessageSigning::parse_server_key("not base58: 0OIl").is_err());
+    }
+
+    #[test]
+    fn hmac_envelope_verifies_with_the_api_keys_secret() {
+        let envelope: Value = serde_json::from_str(&signing().signer("hmac", Some("key-1")).unwrap().sign(PAYLOAD)).unwrap();
+        assert_eq!(envelope["alg"], "hmac-sha256");
+        assert_eq!(envelope["payload"], PAYLOAD);
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"hmac-secret").unwrap();
+        mac.update(envelope["payload"].as_str().unwrap().as_bytes());
+        assert!(mac.verify_slice(&hex::decode(envelope["signature"].as_str().unwrap()).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn ed25519_envelope_verifies_with_the_published_key() {
+        let signing = signing();
+        let envelope: Value = serde_json::from_str(&signing.signer("ed25519", None).unwrap().sign(PAYLOAD)).unwrap();
+        assert_eq!(envelope["alg"], "ed25519");
+        assert_eq!(envelope["key"].as_str(), signing.public_key().as_deref());
+
+        let key_bytes: [u8; 32] = bs58::decode(signing.public_key().unwrap()).into_vec().unwrap().try_into().unwrap();
+        let signature_bytes: [u8; 64] = bs58::decode(envelope["signature"].as_str().unwrap()).into_vec().unwrap().try_into().unwrap();
+        let key = VerifyingKey::from_bytes(&key_bytes).unwrap();
+        let signature = Signature::from_bytes(&signature_bytes);
+        assert!(key.verify(envelope["payload"].as_str().unwrap().as_bytes(), &signature).is_ok());
+        assert!(key.verify(b"tampered", &signature).is_err());
+    }
+
+    #[test]
+    fn unavailable_schemes_are_refused() {
+        let signing = signing();
+        assert!(signing.signer("hmac", None).is_err());
+        assert!(signing.signer("hmac", Some("key-without-secret")).is_err());
+        assert!(MessageSigning::default().signer("ed25519", None).is_err());
+        assert!(signing.signer("rsa", None).is_err());
+    }
+}
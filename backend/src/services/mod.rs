@@ -3,9 +3,53 @@
 pub mod solana;
 pub mod jupiter;
 pub mod clickhouse;
+pub mod ch_writer;
 pub mod trade_stream;
 pub mod quicknode_ws;
+pub mod swap_decoder;
 pub mod pair_mapping;
+pub mod dex_programs;
+pub mod export;
+pub mod portfolio;
+pub mod submission;
+pub mod simulation;
+pub mod swap;
+pub mod digest;
+pub mod circuit_breaker;
+pub mod token_registry;
+pub mod holders;
+pub mod markets;
+pub mod slot_clock;
+pub mod query_limits;
+pub mod auth_config;
+pub mod revocations;
+pub mod signature_checks;
+pub mod maintenance;
+pub mod trade_sanity;
+pub mod data_quality;
+pub mod ws_usage;
+pub mod aggregator;
+pub mod sharding;
+pub mod job_leases;
+pub mod sampling;
+pub mod data_plans;
+pub mod message_signing;
+pub mod feature_flags;
+pub mod orderbook;
+pub mod spikes;
+pub mod candles;
+pub mod venue_latency;
+pub mod stream_health;
+pub mod load_test;
+pub mod memory_budget;
+pub mod notification_templates;
+pub mod cluster;
+pub mod wallet_performance;
+pub mod recent_trades;
+pub mod response_cache;
+pub mod follows;
+pub mod whales;
+pub mod preflight;
 
 pub use solana::SolanaService;
 pub use jupiter::JupiterService;
@@ -13,4 +57,29 @@ pub use clickhouse::ClickHouseService;
 pub use trade_stream::TradeStreamService;
 pub use quicknode_ws::QuickNodeWebSocket;
 pub use pair_mapping::{pair_to_mints, parse_pair, symbol_to_mint};
+pub use dex_programs::DexProgramRegistry;
+pub use export::ExportService;
+pub use portfolio::PortfolioService;
+pub use submission::SubmissionService;
+pub use simulation::SimulationService;
+pub use swap::SwapService;
+pub use digest::DigestService;
+pub use circuit_breaker::CircuitBreaker;
+pub use token_registry::TokenRegistry;
+pub use holders::HolderStatsService;
+pub use slot_clock::SlotClockService;
+pub use maintenance::MaintenanceService;
+pub use trade_sanity::TradeSanity;
+pub use data_quality::DataQuality;
+pub use aggregator::TradeAggregator;
+pub use job_leases::JobLeases;
+pub use feature_flags::FeatureFlags;
+pub use orderbook::OrderBookService;
+pub use venue_latency::VenueLatency;
+pub use stream_health::StreamHealth;
+pub use memory_budget::MemoryBudget;
+pub use notification_templates::NotificationTemplates;
+pub use cluster::ClusterMembership;
+pub use recent_trades::RecentTrades;
+pub use follows::WalletFollows;
 
@@ -0,0 +1,140 @@
+// Notification templates - the text of alert and digest messages, per notification kind and
+// delivery channel, so deployments can word (and brand) them without code changes
+//
+// Every notification carries its rendered text in a `message` field next to the structured event.
+// NOTIFICATION_TEMPLATES_FILE (JSON) overrides the built-in templates:
+//   { "volume_spike": { "default": "...", "ws": "..." }, "digest": { "webhook": "..." } }
+// A channel without its own template uses the kind's `default`, then the built-in one. The template
+// syntax is described in utils::template; the data is the event as sent.
+
+use crate::utils::template::Template;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Notification kinds, with the channels they are delivered on and their built-in template
+const KINDS: &[(&str, &[&str], &str)] = &[
+    (
+        "volume_spike",
+        &["ws"],
+        "Volume spike on {{pair}}: {{trades}} trades and {{volume | fixed:2}} volume in the minute from {{minute}} \
+         (baseline {{baseline.mean_trades | fixed:1}} trades, {{baseline.mean_volume | fixed:2}} volume per minute)",
+    ),
+    (
+        "whale_alert",
+        &["ws"],
+        "Whale {{trade.side}} on {{pair}}: {{trade.amount | fixed:4}} {{trade.base_symbol}} at {{trade.price | fixed:6}} {{trade.quote_symbol}} \
+         (${{usd_value | fixed:0}}) on {{trade.dex_program}}",
+    ),
+    (
+        "followed_wallet_trade",
+        &["ws", "webhook"],
+        "{{wallet}} {{trade.side}} {{trade.amount | fixed:4}} {{trade.base_symbol}} at {{trade.price | fixed:6}} {{trade.quote_symbol}} \
+         on {{trade.dex_program}}",
+    ),
+    (
+        "digest",
+        &["ws", "webhook"],
+        "Daily digest for {{date}}: portfolio ${{portfolio.total_usd | fixed:2}}\
+         {{#if portfolio.change}} ({{portfolio.change.change_percent | fixed:2}}% since {{portfolio.change.since}}){{/if}}.\
+         {{#each watched_pairs}} {{pair}} {{stats.currentPrice}} ({{stats.changePercent24h | fixed:2}}% 24h).{{/each}}",
+    ),
+];
+
+/// Key of a kind's template for channels without their own
+const DEFAULT_CHANNEL: &str = "default";
+
+pub struct NotificationTemplates {
+    /// (kind, channel or "default") -> template
+    templates: HashMap<(String, String), Template>,
+}
+
+impl NotificationTemplates {
+    /// Built-in templates, overridden by the NOTIFICATION_TEMPLATES_FILE at `path` when set
+    /// Unknown kinds or channels and templates that don't parse are skipped with a warning
+    pub fn load(path: Option<&Path>) -> Self {
+        let mut templates: HashMap<(String, String), Template> = KINDS
+            .iter()
+            .map(|(kind, _, source)| {
+                let template = Template::parse(source).expect("built-in notification template must parse");
+                ((kind.to_string(), DEFAULT_CHANNEL.to_string()), template)
+            })
+            .collect();
+
+        let Some(path) = path else {
+            return Self { templates };
+        };
+        let configured: HashMap<String, HashMap<String, String>> = match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string()))
+        {
+            Ok(configured) => configured,
+            Err(e) => {
+                eprintln!("⚠️  [Templates] Ignoring {}: {}", path.display(), e);
+                return Self { templates };
+            }
+        };
+
+        let mut loaded = 0;
+        for (kind, channels) in configured {
+            let Some((_, kind_channels, _)) = KINDS.iter().find(|(k, ..)| *k == kind) else {
+                eprintln!("⚠️  [Templates] Ignoring unknown notification kind '{}'", kind);
+                continue;
+            };
+            for (channel, source) in channels {
+                if channel != DEFAULT_CHANNEL && !kind_channels.contains(&channel.as_str()) {
+                    eprintln!("⚠️  [Templates] Ignoring {} template for channel '{}', which it isn't sent on", kind, channel);
+                    continue;
+                }
+                match Template::parse(&source) {
+                    Ok(template) => {
+                        templates.insert((kind.clone(), channel), template);
+                        loaded += 1;
+                    }
+                    Err(e) => eprintln!("⚠️  [Templates] Ignoring {} template for {}: {}", kind, channel, e),
+                }
+            }
+        }
+        println!("📝 [Templates] Loaded {} notification template(s) from {}", loaded, path.display());
+        Self { templates }
+    }
+
+    fn template(&self, kind: &str, channel: &str) -> Option<&Template> {
+        self.templates
+            .get(&(kind.to_string(), channel.to_string()))
+            .or_else(|| self.templates.get(&(kind.to_string(), DEFAULT_CHANNEL.to_string())))
+    }
+
+    /// Text of a `kind` notification for `channel`, rendered from the event
+    pub fn render(&self, kind: &str, channel: &str, event: &Value) -> String {
+        self.template(kind, channel).map(|t| t.render(event)).unwrap_or_default()
+    }
+
+    /// The event with its rendered text added as `message`
+    pub fn with_message(&self, kind: &str, channel: &str, event: &Value) -> Value {
+        let message = self.render(kind, channel, event);
+        let mut event = event.clone();
+        if let Some(object) = event.as_object_mut() {
+            object.insert("message".to_string(), Value::String(message));
+        }
+        event
+    }
+
+    /// Effective template of every kind, per channel it's sent on
+    pub fn list(&self) -> Value {
+        let kinds: serde_json::Map<String, Value> = KINDS
+            .iter()
+            .map(|(kind, channels, _)| {
+                let channels: serde_json::Map<String, Value> = channels
+                    .iter()
+                    .map(|channel| {
+                        let template = self.template(kind, channel).map(|t| t.to_string());
+                        (channel.to_string(), json!(template))
+                    })
+                    .collect();
+                (kind.to_string(), Value::Object(channels))
+            })
+            .collect();
+        Value::Object(kinds)
+    }
+}
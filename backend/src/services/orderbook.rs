@@ -0,0 +1,490 @@
+// Order book service - live Phoenix order books from accountSubscribe on the market accounts
+// Each account update is decoded into price levels; changed levels are broadcast on the `orderbook` WS channel
+
+use crate::services::pair_mapping::mint_to_symbol;
+use crate::services::solana::SolanaService;
+use crate::websocket::messages::OutboundMessage;
+use crate::websocket::ConnectionManager;
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+
+/// Phoenix SOL/USDC, used when PHOENIX_MARKETS is unset
+pub const DEFAULT_PHOENIX_MARKETS: &[&str] = &["4DoNfFBfF7UokCC2FQzriy7yHK6DY6NVdYpuekQ5pRgg"];
+/// Deepest book a single /api/orderbook request may return
+pub const MAX_ORDERBOOK_DEPTH: usize = 100;
+
+/// Price levels of one market: price in ticks -> resting size in base lots
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Levels {
+    bids: BTreeMap<u64, u64>,
+    asks: BTreeMap<u64, u64>,
+}
+
+/// Scales from a market header that turn ticks and lots into prices and base amounts
+#[derive(Debug, Clone, Copy)]
+struct MarketScale {
+    /// Quote per base unit for one tick
+    price_per_tick: f64,
+    /// Base tokens per base lot
+    base_per_lot: f64,
+}
+
+impl MarketScale {
+    fn level(&self, ticks: u64, lots: u64) -> [f64; 2] {
+        [ticks as f64 * self.price_per_tick, lots as f64 * self.base_per_lot]
+    }
+}
+
+/// A market's book as last decoded
+struct Book {
+    market: String,
+    pair: String,
+    slot: u64,
+    /// Phoenix market sequence number; every update carries the one it produced
+    sequence: u64,
+    scale: MarketScale,
+    levels: Levels,
+}
+
+/// One side of a book snapshot: `[price, size]` from the best price outwards
+#[derive(Debug, Serialize)]
+pub struct OrderBookSnapshot {
+    pub pair: String,
+    pub venue: &'static str,
+    pub market: String,
+    pub slot: u64,
+    pub sequence: u64,
+    pub bids: Vec<[f64; 2]>,
+    pub asks: Vec<[f64; 2]>,
+}
+
+pub struct OrderBookService {
+    solana: SolanaService,
+    ws_manager: Arc<ConnectionManager>,
+    markets: Vec<String>,
+    /// Market address -> book
+    books: RwLock<HashMap<String, Book>>,
+}
+
+impl OrderBookService {
+    /// `markets` are the Phoenix market accounts to follow (PHOENIX_MARKETS)
+    pub fn new(ws_manager: Arc<ConnectionManager>, solana: SolanaService, markets: Vec<String>) -> Self {
+        Self {
+            solana,
+            ws_manager,
+            markets,
+            books: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Follow the configured markets, reconnecting when the subscription drops
+    pub async fn start(self: Arc<Self>) {
+        if self.markets.is_empty() {
+            return;
+        }
+        println!("📚 [OrderBook] Following {} Phoenix market(s)", self.markets.len());
+        loop {
+            if let Err(e) = self.subscribe().await {
+                eprintln!("❌ [OrderBook] Subscription error: {}", e);
+            } else {
+                eprintln!("⚠️  [OrderBook] Subscription closed, reconnecting...");
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+
+    /// Top `depth` levels per side of the book for a pair, if one is followed
+    pub async fn snapshot(&self, pair: &str, depth: usize) -> Option<OrderBookSnapshot> {
+        let books = self.books.read().await;
+        let book = books.values().find(|book| book.pair == pair)?;
+        Some(OrderBookSnapshot {
+            pair: book.pair.clone(),
+            venue: "Phoenix",
+            market: book.market.clone(),
+            slot: book.slot,
+            sequence: book.sequence,
+            bids: book.levels.bids.iter().rev().take(depth).map(|(t, l)| book.scale.level(*t, *l)).collect(),
+            asks: book.levels.asks.iter().take(depth).map(|(t, l)| book.scale.level(*t, *l)).collect(),
+        })
+    }
+
+    /// Pairs with a live book
+    pub async fn pairs(&self) -> Vec<String> {
+        self.books.read().await.values().map(|book| book.pair.clone()).collect()
+    }
+
+    async fn subscribe(&self) -> Result<()> {
+        let ws_url = self.solana.rpc_url().replace("https://", "wss://").replace("http://", "ws://");
+        let (ws_stream, _) = connect_async(url::Url::parse(&ws_url).context("Invalid QuickNode WebSocket URL")?)
+            .await
+            .context("Failed to connect to QuickNode WebSocket")?;
+        let (mut write, mut read) = ws_stream.split();
+
+        // Subscribe first, then load snapshots, so no update between the two is missed
+        let mut pending: HashMap<u64, String> = HashMap::new();
+        for (i, market) in self.markets.iter().enumerate() {
+            let request_id = i as u64 + 1;
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": request_id,
+                "method": "accountSubscribe",
+                "params": [market, { "encoding": "base64", "commitment": "confirmed" }],
+            });
+            write.send(WsMessage::Text(request.to_string())).await?;
+            pending.insert(request_id, market.clone());
+        }
+        for market in &self.markets {
+            match self.solana.get_account_data(market).await {
+                Ok(Some((slot, data))) => self.apply(market, slot, &data).await,
+                Ok(None) => eprintln!("⚠️  [OrderBook] Market account {} not found", market),
+                Err(e) => eprintln!("⚠️  [OrderBook] Failed to load market {}: {}", market, e),
+            }
+        }
+
+        let mut subscriptions: HashMap<u64, String> = HashMap::new();
+        while let Some(msg) = read.next().await {
+            let text = match msg? {
+                WsMessage::Text(text) => text,
+                WsMessage::Close(_) => break,
+                _ => continue,
+            };
+            let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) else { continue };
+
+            // Subscription confirmation: request id -> subscription id
+            if let (Some(request_id), Some(subscription_id)) = (value["id"].as_u64(), value["result"].as_u64()) {
+                if let Some(market) = pending.remove(&request_id) {
+                    subscriptions.insert(subscription_id, market);
+                }
+                continue;
+            }
+            if value["method"] != "accountNotification" {
+                continue;
+            }
+            let params = &value["params"];
+            let Some(market) = params["subscription"].as_u64().and_then(|id| subscriptions.get(&id)) else { continue };
+            let slot = params["result"]["context"]["slot"].as_u64().unwrap_or(0);
+            let Some(encoded) = params["result"]["value"]["data"][0].as_str() else { continue };
+            match base64::engine::general_purpose::STANDARD.decode(encoded) {
+                Ok(data) => self.apply(market, slot, &data).await,
+                Err(e) => eprintln!("⚠️  [OrderBook] Invalid account data for {}: {}", market, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode a market account, store the book and broadcast the levels that changed
+    async fn apply(&self, market: &str, slot: u64, data: &[u8]) {
+        let decoded = match phoenix::decode(data, slot, chrono::Utc::now().timestamp().max(0) as u64) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                eprintln!("⚠️  [OrderBook] Failed to decode market {}: {}", market, e);
+                return;
+            }
+        };
+        let Some(pair) = decoded.pair() else {
+            eprintln!("⚠️  [OrderBook] Market {} trades unsupported mints", market);
+            return;
+        };
+
+        let mut books = self.books.write().await;
+        // Notifications can arrive out of order with the startup snapshot
+        if let Some(book) = books.get(market)
+            && book.sequence > decoded.sequence
+        {
+            return;
+        }
+        let previous = books.get(market).map(|book| book.levels.clone()).unwrap_or_default();
+        let bids = changed_levels(&previous.bids, &decoded.levels.bids, decoded.scale);
+        let asks = changed_levels(&previous.asks, &decoded.levels.asks, decoded.scale);
+        books.insert(market.to_string(), Book {
+            market: market.to_string(),
+            pair: pair.clone(),
+            slot,
+            sequence: decoded.sequence,
+            scale: decoded.scale,
+            levels: decoded.levels,
+        });
+        drop(books);
+
+        if bids.is_empty() && asks.is_empty() {
+            return;
+        }
+        let update = json!({
+            "type": "orderbook",
+            "pair": pair,
+            "market": market,
+            "slot": slot,
+            "sequence": decoded.sequence,
+            "bids": bids,
+            "asks": asks,
+        });
+        self.ws_manager.broadcast(OutboundMessage::new("orderbook", Some(pair), update.to_string())).await;
+    }
+}
+
+/// Levels whose size differs between two decodes; size 0 means the level is gone
+fn changed_levels(before: &BTreeMap<u64, u64>, after: &BTreeMap<u64, u64>, scale: MarketScale) -> Vec<[f64; 2]> {
+    let removed = before.keys().filter(|ticks| !after.contains_key(ticks)).map(|ticks| scale.level(*ticks, 0));
+    let updated = after
+        .iter()
+        .filter(|(ticks, lots)| before.get(ticks) != Some(lots))
+        .map(|(ticks, lots)| scale.level(*ticks, *lots));
+    removed.chain(updated).collect()
+}
+
+/// Phoenix market account layout (MarketHeader followed by a FIFOMarket of sokoban red-black trees)
+mod phoenix {
+    use super::{anyhow, mint_to_symbol, Levels, MarketScale, Result};
+    use std::collections::BTreeMap;
+
+    const HEADER_LEN: usize = 576;
+    /// FIFOMarket: 256 bytes of padding, then six u64 fields before the bid tree
+    const BIDS_OFFSET: usize = HEADER_LEN + 256 + 6 * 8;
+    /// Tree header: root u32, 12 bytes of padding, then the allocator's size u64, bump index and free list head
+    const TREE_HEADER_LEN: usize = 32;
+    /// Node: four u32 registers (left, right, parent, color), FIFOOrderId (16 bytes), FIFORestingOrder (32 bytes)
+    const NODE_LEN: usize = 64;
+    const SENTINEL: u32 = 0;
+
+    pub struct DecodedMarket {
+        pub sequence: u64,
+        pub scale: MarketScale,
+        pub levels: Levels,
+        base_mint: String,
+        quote_mint: String,
+    }
+
+    impl DecodedMarket {
+        /// `BASE/QUOTE` of the market's mints, if both are supported tokens
+        pub fn pair(&self) -> Option<String> {
+            Some(format!("{}/{}", mint_to_symbol(&self.base_mint)?, mint_to_symbol(&self.quote_mint)?))
+        }
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> Result<u32> {
+        data.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap_or_default()))
+            .ok_or_else(|| anyhow!("Account data too short at offset {}", offset))
+    }
+
+    fn u64_at(data: &[u8], offset: usize) -> Result<u64> {
+        data.get(offset..offset + 8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap_or_default()))
+            .ok_or_else(|| anyhow!("Account data too short at offset {}", offset))
+    }
+
+    fn pubkey_at(data: &[u8], offset: usize) -> Result<String> {
+        data.get(offset..offset + 32)
+            .map(|b| bs58::encode(b).into_string())
+            .ok_or_else(|| anyhow!("Account data too short at offset {}", offset))
+    }
+
+    /// Decode a market account; orders expired at `slot` / `now` (unix seconds) are left out
+    pub fn decode(data: &[u8], slot: u64, now: u64) -> Result<DecodedMarket> {
+        let bids_size = u64_at(data, 16)? as usize;
+        let asks_size = u64_at(data, 24)? as usize;
+        let base_decimals = u32_at(data, 40)?;
+        let base_mint = pubkey_at(data, 48)?;
+        let base_lot_size = u64_at(data, 112)?;
+        let quote_decimals = u32_at(data, 120)?;
+        let quote_mint = pubkey_at(data, 128)?;
+        let tick_size_in_quote_atoms_per_base_unit = u64_at(data, 200)?;
+        let sequence = u64_at(data, 272)?;
+        let raw_base_units_per_base_unit = u32_at(data, 312)?.max(1);
+
+        let scale = MarketScale {
+            price_per_tick: tick_size_in_quote_atoms_per_base_unit as f64
+                / 10f64.powi(quote_decimals as i32)
+                / raw_base_units_per_base_unit as f64,
+            base_per_lot: base_lot_size as f64 / 10f64.powi(base_decimals as i32),
+        };
+        let asks_offset = BIDS_OFFSET + TREE_HEADER_LEN + bids_size * NODE_LEN;
+        let levels = Levels {
+            bids: tree_levels(data, BIDS_OFFSET, bids_size, slot, now)?,
+            asks: tree_levels(data, asks_offset, asks_size, slot, now)?,
+        };
+        Ok(DecodedMarket { sequence, scale, levels, base_mint, quote_mint })
+    }
+
+    /// Sum resting base lots per price tick over the tree at `offset`
+    /// Walks from the root rather than over the node array, which also holds freed nodes
+    fn tree_levels(data: &[u8], offset: usize, capacity: usize, slot: u64, now: u64) -> Result<BTreeMap<u64, u64>> {
+        let mut levels = BTreeMap::new();
+        let mut stack = vec![u32_at(data, offset)?];
+        let mut visited = 0;
+        while let Some(address) = stack.pop() {
+            if address == SENTINEL {
+                continue;
+            }
+            // Node addresses are 1-based; a corrupt tree shouldn't loop forever
+            visited += 1;
+            if address as usize > capacity || visited > capacity {
+                return Err(anyhow!("Malformed order tree"));
+            }
+            let node = offset + TREE_HEADER_LEN + (address as usize - 1) * NODE_LEN;
+            stack.push(u32_at(data, node)?);
+            stack.push(u32_at(data, node + 4)?);
+
+            let price_in_ticks = u64_at(data, node + 16)?;
+            let num_base_lots = u64_at(data, node + 40)?;
+            let last_valid_slot = u64_at(data, node + 48)?;
+            let last_valid_unix_timestamp = u64_at(data, node + 56)?;
+            let expired = (last_valid_slot != 0 && last_valid_slot < slot)
+                || (last_valid_unix_timestamp != 0 && last_valid_unix_timestamp < now);
+            if !expired && num_base_lots > 0 {
+                *levels.entry(price_in_ticks).or_insert(0) += num_base_lots;
+            }
+        }
+        Ok(levels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const HEADER_LEN: usize = 576;
+    const BIDS_OFFSET: usize = HEADER_LEN + 256 + 6 * 8;
+    const TREE_HEADER_LEN: usize = 32;
+    const NODE_LEN: usize = 64;
+    const SLOT: u64 = 1_000;
+    const NOW: u64 = 1_700_000_000;
+
+    /// One tree node; addresses are 1-based and 0 is the sentinel
+    struct Node {
+        left: u32,
+        right: u32,
+        price_in_ticks: u64,
+        num_base_lots: u64,
+        last_valid_slot: u64,
+        last_valid_unix_timestamp: u64,
+    }
+
+    fn node(left: u32, right: u32, price_in_ticks: u64, num_base_lots: u64) -> Node {
+        Node { left, right, price_in_ticks, num_base_lots, last_valid_slot: 0, last_valid_unix_timestamp: 0 }
+    }
+
+    /// A SOL/USDC market account: 0.001 SOL lots, 0.001 USDC ticks, and `capacity` nodes per side
+    struct Market {
+        data: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl Market {
+        fn new(capacity: usize) -> Self {
+            let mut market = Self { data: vec![0; BIDS_OFFSET + 2 * (TREE_HEADER_LEN + capacity * NODE_LEN)], capacity };
+            market.put(16, &(capacity as u64).to_le_bytes());
+            market.put(24, &(capacity as u64).to_le_bytes());
+            market.put(40, &9u32.to_le_bytes());
+            market.put(48, &bs58::decode(SOL).into_vec().unwrap());
+            market.put(112, &1_000_000u64.to_le_bytes());
+            market.put(120, &6u32.to_le_bytes());
+            market.put(128, &bs58::decode(USDC).into_vec().unwrap());
+            market.put(200, &1_000u64.to_le_bytes());
+            market.put(272, &42u64.to_le_bytes());
+            market.put(312, &1u32.to_le_bytes());
+            market
+        }
+
+        fn put(&mut self, offset: usize, bytes: &[u8]) {
+            self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        fn tree_offset(&self, bids: bool) -> usize {
+            if bids { BIDS_OFFSET } else { BIDS_OFFSET + TREE_HEADER_LEN + self.capacity * NODE_LEN }
+        }
+
+        fn tree(&mut self, bids: bool, root: u32, nodes: &[Node]) {
+            let offset = self.tree_offset(bids);
+            self.put(offset, &root.to_le_bytes());
+            for (index, node) in nodes.iter().enumerate() {
+                let at = offset + TREE_HEADER_LEN + index * NODE_LEN;
+                self.put(at, &node.left.to_le_bytes());
+                self.put(at + 4, &node.right.to_le_bytes());
+                self.put(at + 16, &node.price_in_ticks.to_le_bytes());
+                self.put(at + 40, &node.num_base_lots.to_le_bytes());
+                self.put(at + 48, &node.last_valid_slot.to_le_bytes());
+                self.put(at + 56, &node.last_valid_unix_timestamp.to_le_bytes());
+            }
+        }
+
+        fn decode(&self) -> Result<phoenix::DecodedMarket> {
+            phoenix::decode(&self.data, SLOT, NOW)
+        }
+    }
+
+    fn book() -> Market {
+        let mut market = Market::new(4);
+        // Two orders at 150.000, one at 149.990; node 4 is a freed node outside the tree
+        market.tree(true, 1, &[node(2, 3, 150_000, 5), node(0, 0, 149_990, 3), node(0, 0, 150_000, 2), node(0, 0, 1, 99)]);
+        market.tree(false, 1, &[node(0, 2, 150_010, 4), node(0, 0, 150_020, 10)]);
+        market
+    }
+
+    #[test]
+    fn bid_and_ask_levels_decode_to_prices_and_sizes() {
+        let decoded = book().decode().unwrap();
+        assert_eq!(decoded.sequence, 42);
+        assert_eq!(decoded.pair().as_deref(), Some("SOL/USDC"));
+        assert_eq!(decoded.levels.bids, BTreeMap::from([(149_990, 3), (150_000, 7)]));
+        assert_eq!(decoded.levels.asks, BTreeMap::from([(150_010, 4), (150_020, 10)]));
+
+        let [price, size] = decoded.scale.level(150_010, 4);
+        assert!((price - 150.01).abs() < 1e-9, "price {}", price);
+        assert!((size - 0.004).abs() < 1e-12, "size {}", size);
+    }
+
+    #[test]
+    fn expired_and_empty_orders_are_skipped() {
+        let mut market = Market::new(4);
+        let mut expired_by_slot = node(0, 0, 150_020, 6);
+        expired_by_slot.last_valid_slot = SLOT - 1;
+        let mut expired_by_time = node(0, 0, 150_030, 7);
+        expired_by_time.last_valid_unix_timestamp = NOW - 1;
+        let mut still_valid = node(0, 0, 150_040, 8);
+        still_valid.last_valid_slot = SLOT;
+        still_valid.last_valid_unix_timestamp = NOW;
+        // 1 -> (2, 3), 3 -> (4, 0): every node is reachable
+        market.tree(false, 1, &[node(2, 3, 150_010, 0), expired_by_slot, node(4, 0, 150_050, 1), still_valid]);
+        market.tree(true, 1, &[node(0, 0, 149_000, 0)]);
+
+        let decoded = market.decode().unwrap();
+        assert!(decoded.levels.bids.is_empty());
+        assert_eq!(decoded.levels.asks, BTreeMap::from([(150_040, 8), (150_050, 1)]));
+
+        market.tree(false, 1, &[node(2, 0, 150_060, 2), expired_by_time]);
+        assert_eq!(market.decode().unwrap().levels.asks, BTreeMap::from([(150_060, 2)]));
+    }
+
+    #[test]
+    fn truncated_accounts_are_errors() {
+        let market = book();
+        // Where the last ask node in the tree ends
+        let last_ask_node_end = market.tree_offset(false) + TREE_HEADER_LEN + 2 * NODE_LEN;
+        for len in [0, 100, HEADER_LEN, BIDS_OFFSET + 10, last_ask_node_end - 1] {
+            assert!(phoenix::decode(&market.data[..len], SLOT, NOW).is_err(), "{} bytes decoded", len);
+        }
+    }
+
+    #[test]
+    fn trees_pointing_outside_their_nodes_are_errors() {
+        let mut market = book();
+        market.tree(true, 5, &[]);
+        assert!(market.decode().is_err());
+
+        // A node that is its own child
+        let mut market = book();
+        market.tree(false, 1, &[node(1, 0, 150_010, 4)]);
+        assert!(market.decode().is_err());
+    }
+}
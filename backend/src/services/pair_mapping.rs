@@ -1,36 +1,212 @@
 // Pair symbol to mint address mapping utility
 
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{LazyLock, RwLock, RwLockReadGuard};
+
+/// A token the platform tracks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenConfig {
+    pub symbol: String,
+    pub mint: String,
+    pub decimals: u8,
+}
+
+/// Tracked tokens and the pairs offered in the UI
+/// Loaded by TokenRegistry (TOKEN_REGISTRY_FILE, admin additions); the built-in list is used until then
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenList {
+    pub tokens: Vec<TokenConfig>,
+    pub pairs: Vec<String>,
+}
+
+impl Default for TokenList {
+    fn default() -> Self {
+        let token = |symbol: &str, mint: &str, decimals: u8| TokenConfig {
+            symbol: symbol.to_string(),
+            mint: mint.to_string(),
+            decimals,
+        };
+        Self {
+            tokens: vec![
+                token("SOL", "So11111111111111111111111111111111111111112", 9),
+                token("USDC", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", 6),
+                token("USDT", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", 6),
+                token("BONK", "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263", 5),
+                token("JUP", "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN", 6),
+                token("WIF", "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm", 6),
+                token("RAY", "4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R", 6),
+            ],
+            pairs: ["SOL/USDC", "SOL/USDT", "BONK/SOL", "JUP/SOL", "WIF/SOL", "RAY/SOL"]
+                .iter()
+                .map(|pair| pair.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl TokenList {
+    /// Check symbols and mints are well-formed and unique, and every pair's tokens are listed
+    pub fn validate(&self) -> Result<(), String> {
+        let mut symbols = HashSet::new();
+        let mut mints = HashSet::new();
+        for token in &self.tokens {
+            if canonical_symbol(&token.symbol).as_deref() != Some(token.symbol.as_str()) {
+                return Err(format!("symbol '{}' must be 1-{} uppercase letters or digits", token.symbol, MAX_SYMBOL_LEN));
+            }
+            if bs58::decode(&token.mint).into_vec().map_or(true, |bytes| bytes.len() != 32) {
+                return Err(format!("mint of {} is not a base58 address", token.symbol));
+            }
+            if !symbols.insert(token.symbol.as_str()) {
+                return Err(format!("symbol {} is listed twice", token.symbol));
+            }
+            if !mints.insert(token.mint.as_str()) {
+                return Err(format!("mint {} is listed twice", token.mint));
+            }
+        }
+        for pair in &self.pairs {
+            let parsed = parse_pair(pair).ok_or_else(|| format!("pair '{}' must be BASE/QUOTE", pair))?;
+            if parsed.to_string() != *pair {
+                return Err(format!("pair '{}' must be written as {}", pair, parsed));
+            }
+            if parsed.base == parsed.quote {
+                return Err(format!("pair {} has the same token on both sides", pair));
+            }
+            for symbol in [&parsed.base, &parsed.quote] {
+                if !symbols.contains(symbol.as_str()) {
+                    return Err(format!("pair {} uses unlisted token {}", pair, symbol));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+static TOKEN_LIST: LazyLock<RwLock<TokenList>> = LazyLock::new(|| RwLock::new(TokenList::default()));
+
+fn token_list() -> RwLockReadGuard<'static, TokenList> {
+    TOKEN_LIST.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Replace the tracked tokens and pairs (already validated)
+pub fn set_token_list(list: TokenList) {
+    *TOKEN_LIST.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = list;
+}
+
+/// The tracked tokens and pairs
+pub fn current_token_list() -> TokenList {
+    token_list().clone()
+}
+
+/// Symbols of every token the platform tracks
+pub fn supported_tokens() -> Vec<String> {
+    token_list().tokens.iter().map(|t| t.symbol.clone()).collect()
+}
+
+/// Pairs offered in the UI
+pub fn supported_pairs() -> Vec<String> {
+    token_list().pairs.clone()
+}
+
+/// Quote tokens treated as one market by /api/ohlcv/combined (liquidity is split across them)
+pub const QUOTE_GROUPS: &[(&str, &[&str])] = &[("stables", &["USDC", "USDT"])];
+
+/// Quote symbols of a named quote group
+pub fn quote_group(name: &str) -> Option<&'static [&'static str]> {
+    QUOTE_GROUPS.iter().find(|(group, _)| group.eq_ignore_ascii_case(name)).map(|(_, quotes)| *quotes)
+}
 
 /// Map symbol to mint address
-pub fn symbol_to_mint(symbol: &str) -> Option<&str> {
-    match symbol {
-        "SOL" => Some("So11111111111111111111111111111111111111112"),
-        "USDC" => Some("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
-        "USDT" => Some("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
-        "BONK" => Some("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263"),
-        "JUP" => Some("JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN"),
-        "WIF" => Some("EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm"),
-        "RAY" => Some("4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R"),
-        _ => None,
+pub fn symbol_to_mint(symbol: &str) -> Option<String> {
+    token_list().tokens.iter().find(|t| t.symbol == symbol).map(|t| t.mint.clone())
+}
+
+/// Map mint address to symbol
+pub fn mint_to_symbol(mint: &str) -> Option<String> {
+    token_list().tokens.iter().find(|t| t.mint == mint).map(|t| t.symbol.clone())
+}
+
+/// Decimals of a supported token's mint
+pub fn mint_decimals(mint: &str) -> Option<u8> {
+    token_list().tokens.iter().find(|t| t.mint == mint).map(|t| t.decimals)
+}
+
+/// Longest symbol accepted on either side of a pair
+pub const MAX_SYMBOL_LEN: usize = 16;
+
+/// Alternative spellings of supported symbols
+const SYMBOL_ALIASES: &[(&str, &str)] = &[("WSOL", "SOL")];
+
+/// A trading pair in canonical form: uppercased symbols with aliases resolved
+/// Serializes as (and deserializes from) the `BASE/QUOTE` string
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pair {
+    pub base: String,
+    pub quote: String,
+}
+
+impl Pair {
+    /// Mint addresses of both sides, if both tokens are supported
+    pub fn mints(&self) -> Option<(String, String)> {
+        Some((symbol_to_mint(&self.base)?, symbol_to_mint(&self.quote)?))
     }
 }
 
-/// Parse pair string (e.g., "SOL/USDC") into base and quote symbols
-pub fn parse_pair(pair: &str) -> Option<(String, String)> {
-    let parts: Vec<&str> = pair.split('/').collect();
-    if parts.len() == 2 {
-        Some((parts[0].to_string(), parts[1].to_string()))
-    } else {
-        None
+/// Canonical `BASE/QUOTE` form of a client-supplied pair, if both tokens are supported
+pub fn supported_pair(raw: &str) -> Option<String> {
+    parse_pair(raw).filter(|pair| pair.mints().is_some()).map(|pair| pair.to_string())
+}
+
+impl fmt::Display for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.base, self.quote)
     }
 }
 
+impl Serialize for Pair {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pair {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        parse_pair(&raw).ok_or_else(|| serde::de::Error::custom(format!(
+            "'{}' must be BASE/QUOTE with 1-{} letters or digits per symbol", raw, MAX_SYMBOL_LEN
+        )))
+    }
+}
+
+/// Canonical form of a symbol: trimmed, uppercased, aliases resolved
+/// None unless it is 1-16 ASCII letters or digits
+pub fn canonical_symbol(symbol: &str) -> Option<String> {
+    let symbol = symbol.trim().to_ascii_uppercase();
+    if !(1..=MAX_SYMBOL_LEN).contains(&symbol.len()) || !symbol.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(match SYMBOL_ALIASES.iter().find(|(alias, _)| *alias == symbol) {
+        Some((_, canonical)) => canonical.to_string(),
+        None => symbol,
+    })
+}
+
+/// Parse a pair string into its canonical form
+/// Accepts `/`, `-`, or `_` between the symbols, so "sol-usdc" and " WSOL_USDC" both give SOL/USDC
+pub fn parse_pair(pair: &str) -> Option<Pair> {
+    let mut parts = pair.trim().split(['/', '-', '_']);
+    let (Some(base), Some(quote), None) = (parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    Some(Pair {
+        base: canonical_symbol(base)?,
+        quote: canonical_symbol(quote)?,
+    })
+}
+
 /// Get mint addresses for a pair
 pub fn pair_to_mints(pair: &str) -> Option<(String, String)> {
-    let (base_symbol, quote_symbol) = parse_pair(pair)?;
-    let base_mint = symbol_to_mint(&base_symbol)?.to_string();
-    let quote_mint = symbol_to_mint(&quote_symbol)?.to_string();
-    Some((base_mint, quote_mint))
+    parse_pair(pair)?.mints()
 }
 
@@ -0,0 +1,168 @@
+// Portfolio service - wallet SPL balances with USD valuation, cached per wallet
+
+use crate::services::jupiter::JupiterService;
+use crate::services::pair_mapping::mint_to_symbol;
+use crate::services::solana::SolanaService;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use tokio::time::{Duration, Instant};
+
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+/// Cached balances are served for this long unless a refresh is requested
+const BALANCE_TTL: Duration = Duration::from_secs(60);
+/// USD prices are shared across wallets for this long
+const PRICE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenBalance {
+    pub mint: String,
+    pub symbol: Option<String>,
+    pub amount: f64,
+    pub decimals: u8,
+    pub native: bool,
+    pub usd_price: Option<f64>,
+    pub usd_value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletBalances {
+    pub wallet: String,
+    pub tokens: Vec<TokenBalance>,
+    pub total_usd: f64,
+    pub fetched_at: DateTime<Utc>,
+}
+
+pub struct PortfolioService {
+    solana: SolanaService,
+    jupiter: JupiterService,
+    balances: RwLock<HashMap<String, (Instant, WalletBalances)>>,
+    prices: RwLock<HashMap<String, (Instant, f64)>>,
+}
+
+impl PortfolioService {
+    pub fn new(solana: SolanaService, jupiter: JupiterService) -> Result<Self> {
+        Ok(Self {
+            solana,
+            jupiter,
+            balances: RwLock::new(HashMap::new()),
+            prices: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Get a wallet's balances, served from cache unless stale or `refresh` is set
+    pub async fn get_balances(&self, wallet: &str, refresh: bool) -> Result<WalletBalances> {
+        if !refresh
+            && let Some((fetched, balances)) = self.balances.read().await.get(wallet)
+            && fetched.elapsed() < BALANCE_TTL
+        {
+            return Ok(balances.clone());
+        }
+
+        let balances = self.fetch_balances(wallet).await?;
+        self.balances
+            .write()
+            .await
+            .insert(wallet.to_string(), (Instant::now(), balances.clone()));
+        Ok(balances)
+    }
+
+    /// Drop a wallet's cached balances (e.g. after one of its fills was observed)
+    pub async fn invalidate(&self, wallet: &str) {
+        self.balances.write().await.remove(wallet);
+    }
+
+    async fn fetch_balances(&self, wallet: &str) -> Result<WalletBalances> {
+        let lamports = self.solana.get_balance(wallet).await?;
+        let accounts = self.solana.get_token_accounts_by_owner(wallet).await?;
+
+        // Aggregate multiple token accounts of the same mint
+        let mut holdings: HashMap<String, (f64, u8)> = HashMap::new();
+        for account in accounts.into_iter().filter(|a| a.amount > 0.0) {
+            let entry = holdings.entry(account.mint).or_insert((0.0, account.decimals));
+            entry.0 += account.amount;
+        }
+
+        let mut tokens = vec![TokenBalance {
+            mint: SOL_MINT.to_string(),
+            symbol: Some("SOL".to_string()),
+            amount: lamports as f64 / LAMPORTS_PER_SOL,
+            decimals: 9,
+            native: true,
+            usd_price: None,
+            usd_value: None,
+        }];
+        tokens.extend(holdings.into_iter().map(|(mint, (amount, decimals))| TokenBalance {
+            symbol: mint_to_symbol(&mint),
+            mint,
+            amount,
+            decimals,
+            native: false,
+            usd_price: None,
+            usd_value: None,
+        }));
+
+        let mints: Vec<String> = tokens.iter().map(|t| t.mint.clone()).collect();
+        let prices = self.usd_prices(&mints).await;
+
+        let mut total_usd = 0.0;
+        for token in tokens.iter_mut() {
+            if let Some(price) = prices.get(&token.mint) {
+                let value = token.amount * price;
+                token.usd_price = Some(*price);
+                token.usd_value = Some(value);
+                total_usd += value;
+            }
+        }
+        tokens.sort_by(|a, b| {
+            b.usd_value
+                .unwrap_or(0.0)
+                .partial_cmp(&a.usd_value.unwrap_or(0.0))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(WalletBalances {
+            wallet: wallet.to_string(),
+            tokens,
+            total_usd,
+            fetched_at: Utc::now(),
+        })
+    }
+
+    /// USD prices from the shared price cache, fetching missing/stale mints from Jupiter
+    /// Price failures only leave tokens unvalued, they don't fail the request
+    pub async fn usd_prices(&self, mints: &[String]) -> HashMap<String, f64> {
+        let mut result = HashMap::new();
+        let mut missing = Vec::new();
+        {
+            let cache = self.prices.read().await;
+            for mint in mints {
+                match cache.get(mint) {
+                    Some((fetched, price)) if fetched.elapsed() < PRICE_TTL => {
+                        result.insert(mint.clone(), *price);
+                    }
+                    _ => missing.push(mint.clone()),
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            match self.jupiter.get_usd_prices(&missing).await {
+                Ok(fetched) => {
+                    let mut cache = self.prices.write().await;
+                    let now = Instant::now();
+                    for (mint, price) in fetched {
+                        cache.insert(mint.clone(), (now, price));
+                        result.insert(mint, price);
+                    }
+                }
+                Err(e) => eprintln!("⚠️  [Portfolio] Failed to fetch USD prices: {}", e),
+            }
+        }
+
+        result
+    }
+}
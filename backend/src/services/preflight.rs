@@ -0,0 +1,254 @@
+// Startup preflight - validates configuration before the listener is bound
+//
+// Settings are loaded into a `Config` first; every invalid one is reported, each with the variable
+// to fix. The remaining checks then all run (none stops at the first problem) and the results are
+// printed as one report. Blocking problems exit the process before any service starts. With
+// STARTUP_MODE=degraded an unreachable RPC starts the API alone (no trade stream, order books or
+// latency polling) instead, and ingestion starts once `wait_for_rpc` sees the RPC answer;
+// STARTUP_MODE=api-only never ingests and never probes it.
+
+use crate::config::{ClickHouseConfig, Config};
+use crate::services::clickhouse::ClickHouseService;
+use crate::services::solana::SolanaService;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+const RPC_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const CLICKHOUSE_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often a degraded process probes the RPC again before starting ingestion
+const RPC_REPROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What this process runs; STARTUP_MODE, default full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupMode {
+    /// API plus ingestion; any failed check is blocking
+    Full,
+    /// Full when the RPC is reachable, otherwise API only until it is
+    Degraded,
+    /// API only: no trade stream, order books or latency polling
+    ApiOnly,
+}
+
+impl StartupMode {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "full" => Some(Self::Full),
+            "degraded" => Some(Self::Degraded),
+            "api-only" => Some(Self::ApiOnly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Degraded => "degraded",
+            Self::ApiOnly => "api-only",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Ok,
+    /// Works, but should be fixed
+    Warning,
+    /// Ingestion is switched off; the API still starts
+    Degraded,
+    /// Startup is refused
+    Fatal,
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub severity: Severity,
+    pub detail: String,
+    /// What to change, for anything not Ok
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, severity: Severity::Ok, detail: detail.into(), fix: None }
+    }
+
+    fn problem(name: &'static str, severity: Severity, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, severity, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+pub struct PreflightReport {
+    pub checks: Vec<Check>,
+    /// Mode requested by STARTUP_MODE; None when the settings are invalid
+    requested: Option<StartupMode>,
+    /// The loaded settings; None when any setting is invalid
+    config: Option<Config>,
+    /// Bound by the port check and handed to the server, so nothing can take the port in between
+    listener: Option<TcpListener>,
+}
+
+impl PreflightReport {
+    pub fn is_fatal(&self) -> bool {
+        self.checks.iter().any(|c| c.severity == Severity::Fatal)
+    }
+
+    /// The bound listener and the settings to start with, unless a check was blocking
+    pub fn into_start(self) -> Option<(TcpListener, Config)> {
+        if self.is_fatal() {
+            return None;
+        }
+        self.listener.zip(self.config)
+    }
+
+    pub fn print(&self) {
+        match self.requested {
+            Some(mode) => println!("🛫 [Preflight] Startup checks (STARTUP_MODE={})", mode.as_str()),
+            None => println!("🛫 [Preflight] Startup checks"),
+        }
+        for check in &self.checks {
+            let icon = match check.severity {
+                Severity::Ok => "✅",
+                Severity::Warning => "⚠️ ",
+                Severity::Degraded => "🟡",
+                Severity::Fatal => "❌",
+            };
+            println!("   {} {:<12} {}", icon, check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                println!("      {:<12} → {}", "", fix);
+            }
+        }
+
+        let count = |severity| self.checks.iter().filter(|c| c.severity == severity).count();
+        if self.is_fatal() {
+            eprintln!("❌ [Preflight] {} blocking problem(s); fix them and restart", count(Severity::Fatal));
+        } else if self.config.as_ref().is_some_and(|config| config.startup_mode == StartupMode::ApiOnly) {
+            println!("🟡 [Preflight] Starting in API-only mode: trade stream, order books and latency polling are off");
+        } else if count(Severity::Degraded) > 0 {
+            println!("🟡 [Preflight] Starting degraded: trade stream, order books and latency polling start once the RPC answers");
+        } else {
+            println!("✅ [Preflight] Ready ({} warning(s))", count(Severity::Warning));
+        }
+    }
+}
+
+/// Load the settings and run every check, binding the listener on PORT
+pub async fn run() -> PreflightReport {
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(errors) => {
+            let mut checks: Vec<Check> = errors
+                .into_iter()
+                .map(|e| Check::problem("config", Severity::Fatal, format!("{}: {}", e.var, e.problem), format!("Set {} to {}", e.var, e.expected)))
+                .collect();
+            checks.push(Check::ok("connections", "Not checked until the settings are valid"));
+            return PreflightReport { checks, requested: None, config: None, listener: None };
+        }
+    };
+    let requested = config.startup_mode;
+    let mut checks = vec![Check::ok("config", "Settings are valid")];
+
+    checks.push(check_rpc(&config.rpc_url, requested).await);
+    checks.push(check_clickhouse(&config.clickhouse).await);
+    if config.uses_default_jwt_secret() {
+        checks.push(Check::problem("jwt", Severity::Warning, "JWT_SECRET is not set; signing with the built-in development secret", "Set JWT_SECRET to a random string of at least 32 bytes (e.g. `openssl rand -hex 32`)"));
+    }
+    if config.jwt_previous_secret.is_some() {
+        checks.push(Check::ok("jwt", "Rotating: tokens signed with JWT_PREVIOUS_SECRET are still accepted; unset it once they have expired"));
+    }
+    checks.extend(check_templates(config.notification_templates_file.as_deref()));
+    let (port, listener) = check_port(SocketAddr::from(([0, 0, 0, 0], config.port))).await;
+    checks.push(port);
+
+    PreflightReport { checks, requested: Some(requested), config: Some(config), listener }
+}
+
+/// QUICKNODE_RPC_URL answers getSlot
+async fn check_rpc(rpc_url: &str, mode: StartupMode) -> Check {
+    let solana = SolanaService::new(rpc_url);
+    if mode == StartupMode::ApiOnly {
+        return Check::ok("rpc", "Not probed (STARTUP_MODE=api-only)");
+    }
+
+    let failure = match probe_rpc(&solana).await {
+        Ok(slot) => return Check::ok("rpc", format!("Reachable, finalized slot {}", slot)),
+        Err(failure) => failure,
+    };
+    match mode {
+        StartupMode::Degraded => Check::problem("rpc", Severity::Degraded, failure, "Check QUICKNODE_RPC_URL and the endpoint's status; starting without ingestion until then"),
+        _ => Check::problem("rpc", Severity::Fatal, failure, "Check QUICKNODE_RPC_URL and the endpoint's status, or set STARTUP_MODE=degraded to serve the API without ingestion"),
+    }
+}
+
+/// The finalized slot, or why getSlot failed
+async fn probe_rpc(solana: &SolanaService) -> Result<u64, String> {
+    match tokio::time::timeout(RPC_PROBE_TIMEOUT, solana.get_slot()).await {
+        Ok(Ok(slot)) => Ok(slot),
+        Ok(Err(e)) => Err(format!("getSlot failed: {}", e)),
+        Err(_) => Err(format!("getSlot timed out after {:?}", RPC_PROBE_TIMEOUT)),
+    }
+}
+
+/// Returns once QUICKNODE_RPC_URL answers getSlot, probing every RPC_REPROBE_INTERVAL; at once
+/// when it already does
+pub async fn wait_for_rpc(solana: &SolanaService) {
+    let mut failures = 0u32;
+    loop {
+        match probe_rpc(solana).await {
+            Ok(slot) => {
+                if failures > 0 {
+                    println!("✅ [Preflight] RPC answered (finalized slot {}) after {} failed probe(s); starting ingestion", slot, failures);
+                }
+                return;
+            }
+            Err(failure) => {
+                failures += 1;
+                eprintln!("🟡 [Preflight] RPC still unreachable ({}); probing again in {:?}", failure, RPC_REPROBE_INTERVAL);
+            }
+        }
+        tokio::time::sleep(RPC_REPROBE_INTERVAL).await;
+    }
+}
+
+/// The primary ClickHouse accepts the configured credentials
+async fn check_clickhouse(config: &ClickHouseConfig) -> Check {
+    let (url, username) = (&config.url, &config.username);
+    let error = match tokio::time::timeout(CLICKHOUSE_PROBE_TIMEOUT, ClickHouseService::check_connection(config)).await {
+        Ok(Ok(())) => return Check::ok("clickhouse", format!("Connected to {} as {}", url, username)),
+        Ok(Err(e)) => format!("{:#}", e),
+        Err(_) => format!("No response within {:?}", CLICKHOUSE_PROBE_TIMEOUT),
+    };
+    // Code 516: AUTHENTICATION_FAILED
+    if error.contains("Code: 516") || error.contains("AUTHENTICATION_FAILED") || error.contains("Authentication failed") {
+        Check::problem("clickhouse", Severity::Fatal, format!("{} rejected the credentials for {}", url, username), "Check CLICKHOUSE_USERNAME and CLICKHOUSE_PASSWORD")
+    } else {
+        Check::problem("clickhouse", Severity::Fatal, format!("Cannot reach {}: {}", url, error), "Check CLICKHOUSE_URL (protocol and port, e.g. https://instance.clickhouse.cloud:8443) and that the server is up")
+    }
+}
+
+/// NOTIFICATION_TEMPLATES_FILE parses; the services would otherwise only ignore it after the listener is up
+/// Unreadable templates fall back to the built-in ones, so this only warns
+fn check_templates(path: Option<&Path>) -> Option<Check> {
+    let path = path?;
+    let parsed = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|raw| serde_json::from_str::<HashMap<String, HashMap<String, String>>>(&raw).map_err(|e| e.to_string()));
+    Some(match parsed {
+        Ok(_) => Check::ok("templates", format!("Overrides read from {}", path.display())),
+        Err(e) => Check::problem("templates", Severity::Warning, format!("{}: {}; using the built-in templates", path.display(), e), "Fix or unset NOTIFICATION_TEMPLATES_FILE"),
+    })
+}
+
+/// The listen address is free
+async fn check_port(addr: SocketAddr) -> (Check, Option<TcpListener>) {
+    match TcpListener::bind(addr).await {
+        Ok(listener) => (Check::ok("port", format!("Listening on {}", addr)), Some(listener)),
+        Err(e) => (
+            Check::problem("port", Severity::Fatal, format!("Cannot bind {}: {}", addr, e), format!("Stop the process using port {}", addr.port())),
+            None,
+        ),
+    }
+}
@@ -0,0 +1,154 @@
+// Query guardrails - per-request bounds checked before a query reaches ClickHouse,
+// which is shared with trade ingestion
+
+use crate::services::clickhouse::TradeFilter;
+
+/// Candle intervals accepted by /api/ohlcv, in seconds
+pub const OHLCV_INTERVALS: &[(&str, u64)] = &[
+    ("1m", 60),
+    ("5m", 300),
+    ("15m", 900),
+    ("1h", 3_600),
+    ("4h", 14_400),
+    ("1d", 86_400),
+];
+
+/// Length of a candle interval in seconds, if supported
+pub fn interval_seconds(interval: &str) -> Option<u64> {
+    OHLCV_INTERVALS.iter().find(|(name, _)| *name == interval).map(|(_, secs)| *secs)
+}
+
+/// Read with the rest of the Config: MAX_TRADES_LIMIT (default 1000), MAX_TRADES_STREAM_LIMIT (default 100000),
+/// MAX_TRADES_PAGE_SIZE (default 500), MAX_SLOT_RANGE (default 216000, ~1 day), MAX_OHLCV_CANDLES (default 5000),
+/// MAX_BACKTEST_PAIRS (default 10)
+#[derive(Debug, Clone, Copy)]
+pub struct QueryLimits {
+    /// Largest `limit` accepted by /api/trades
+    pub max_trades_limit: usize,
+    /// Largest `limit` accepted by /api/trades when streamed as ND-JSON (rows aren't buffered)
+    pub max_stream_limit: usize,
+    /// Largest `page_size` accepted by cursor-paged /api/trades requests
+    pub max_page_size: usize,
+    /// Widest `fromSlot`..`toSlot` range accepted by /api/trades
+    pub max_slot_range: u64,
+    /// Most candles a single /api/ohlcv request may return (per pair for /api/backtest/candles)
+    pub max_candles: u64,
+    /// Most pairs a single /api/backtest/candles request may align
+    pub max_backtest_pairs: usize,
+}
+
+impl QueryLimits {
+    /// Validate a /api/trades request; the error is guidance for the caller
+    pub fn check_trades(&self, limit: usize, filter: &TradeFilter, streaming: bool) -> Result<(), String> {
+        let max_limit = if streaming { self.max_stream_limit } else { self.max_trades_limit };
+        if limit == 0 || limit > max_limit {
+            return Err(if streaming {
+                format!("limit must be between 1 and {} for ND-JSON streams", max_limit)
+            } else {
+                format!(
+                    "limit must be between 1 and {}; page through older trades with page_size and before, or stream larger results with Accept: application/x-ndjson",
+                    max_limit
+                )
+            });
+        }
+        self.check_slot_range(filter)
+    }
+
+    /// Validate a cursor-paged /api/trades request
+    pub fn check_page(&self, page_size: usize, filter: &TradeFilter) -> Result<(), String> {
+        if page_size == 0 || page_size > self.max_page_size {
+            return Err(format!("page_size must be between 1 and {}", self.max_page_size));
+        }
+        self.check_slot_range(filter)
+    }
+
+    fn check_slot_range(&self, filter: &TradeFilter) -> Result<(), String> {
+        if let (Some(from), Some(to)) = (filter.from_slot, filter.to_slot)
+            && to - from > self.max_slot_range
+        {
+            return Err(format!(
+                "Slot range spans {} slots; narrow it to at most {} slots",
+                to - from,
+                self.max_slot_range
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validate a /api/ohlcv interval, returning its length in seconds
+    pub fn check_ohlcv(&self, interval: &str) -> Result<u64, String> {
+        interval_seconds(interval).ok_or_else(|| {
+            let supported: Vec<&str> = OHLCV_INTERVALS.iter().map(|(name, _)| *name).collect();
+            format!("Unsupported interval '{}'; use one of {}", interval, supported.join(", "))
+        })
+    }
+
+    /// Validate the `from`/`to` range of an /api/ohlcv request (unix seconds, `to` defaulting to now)
+    /// Without `from` the most recent candles are served, which is always within bounds
+    pub fn check_ohlcv_range(&self, interval: &str, from: Option<u64>, to: Option<u64>, now: u64) -> Result<(), String> {
+        let interval_secs = self.check_ohlcv(interval)?;
+        let Some(from) = from else { return Ok(()) };
+        let to = to.unwrap_or(now);
+        if from >= to {
+            return Err("from must be less than to (now when to is omitted)".to_string());
+        }
+        let candles = to.saturating_sub(1) / interval_secs - from / interval_secs + 1;
+        if candles > self.max_candles {
+            return Err(format!(
+                "Range covers {} {} candles; narrow from/to to at most {} candles or use a wider interval",
+                candles, interval, self.max_candles
+            ));
+        }
+        Ok(())
+    }
+
+    /// Validate a /api/backtest/candles request, returning the number of aligned timestamps
+    pub fn check_backtest(&self, pairs: usize, interval: &str, from: u64, to: u64) -> Result<u64, String> {
+        let interval_secs = self.check_ohlcv(interval)?;
+        if pairs == 0 || pairs > self.max_backtest_pairs {
+            return Err(format!("Request between 1 and {} pairs", self.max_backtest_pairs));
+        }
+        if from > to {
+            return Err("from must not be greater than to".to_string());
+        }
+        let candles = to / interval_secs - from / interval_secs + 1;
+        if candles > self.max_candles {
+            return Err(format!(
+                "Range covers {} {} candles per pair; narrow it to at most {}",
+                candles, interval, self.max_candles
+            ));
+        }
+        Ok(candles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: QueryLimits = QueryLimits {
+        max_trades_limit: 1000,
+        max_stream_limit: 100_000,
+        max_page_size: 500,
+        max_slot_range: 216_000,
+        max_candles: 5000,
+        max_backtest_pairs: 10,
+    };
+
+    #[test]
+    fn ohlcv_range_from_the_future_is_rejected() {
+        let now = 1_700_000_000;
+        assert!(LIMITS.check_ohlcv_range("1m", Some(now + 60), None, now).is_err());
+        assert!(LIMITS.check_ohlcv_range("1m", Some(now), None, now).is_err());
+        assert!(LIMITS.check_ohlcv_range("1m", Some(now), Some(now - 1), now).is_err());
+    }
+
+    #[test]
+    fn ohlcv_range_is_bounded_by_candle_count() {
+        let now = 1_700_000_040;
+        assert_eq!(LIMITS.check_ohlcv_range("1m", None, None, now), Ok(()));
+        assert_eq!(LIMITS.check_ohlcv_range("1m", Some(now - 5000 * 60), None, now), Ok(()));
+        assert!(LIMITS.check_ohlcv_range("1m", Some(now - 5001 * 60), None, now).is_err());
+        assert!(LIMITS.check_ohlcv_range("2m", Some(now - 60), None, now).is_err());
+    }
+}
@@ -3,11 +3,17 @@
 
 use crate::models::trade::Trade;
 use crate::services::solana::SolanaService;
+use crate::services::dex_programs::{DexProgramRegistry, ProgramCommand};
+use crate::services::pair_mapping::{mint_decimals, mint_to_symbol, supported_pairs};
+use crate::services::swap_decoder::{decode_swap, CompiledInstruction, InnerInstructions, SwapContext, SwapLegs};
+use crate::services::trade_sanity::{orders_apart, SanityFailure, TradeSanity};
+use crate::services::stream_health::StreamHealth;
+use crate::services::memory_budget::{MemoryBudget, SignatureDedup};
 use anyhow::{Context, Result};
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
 use futures_util::{SinkExt, StreamExt};
@@ -15,8 +21,12 @@ use url::Url;
 
 #[derive(Clone)]
 pub struct QuickNodeWebSocket {
-    rpc_url: String,
     solana_service: Arc<SolanaService>,
+    dex_programs: Arc<DexProgramRegistry>,
+    sanity: Arc<TradeSanity>,
+    health: Arc<StreamHealth>,
+    /// Kept across reconnects, so notifications re-delivered after one are still recognized
+    seen_signatures: Arc<Mutex<SignatureDedup>>,
 }
 
 // JSON-RPC notification wrapper
@@ -81,10 +91,17 @@ struct TransactionMessage {
     #[serde(default)]
     pub account_keys: Vec<serde_json::Value>, // Can be strings or objects
     #[serde(default)]
-    pub instructions: Vec<serde_json::Value>,
+    pub instructions: Vec<CompiledInstruction>,
     #[serde(rename = "recentBlockhash")]
     #[serde(default)]
     pub recent_blockhash: Option<String>,
+    pub header: Option<MessageHeader>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageHeader {
+    #[serde(rename = "numRequiredSignatures")]
+    pub num_required_signatures: usize,
 }
 
 #[derive(Debug, Deserialize)]
@@ -101,6 +118,19 @@ struct TransactionMeta {
     pub log_messages: Option<Vec<String>>,
     pub err: Option<serde_json::Value>,
     pub fee: Option<u64>,
+    #[serde(rename = "innerInstructions")]
+    pub inner_instructions: Option<Vec<InnerInstructions>>,
+    /// Address lookup table accounts of v0 transactions, indexed after the static keys
+    #[serde(rename = "loadedAddresses")]
+    pub loaded_addresses: Option<LoadedAddresses>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoadedAddresses {
+    #[serde(default)]
+    pub writable: Vec<String>,
+    #[serde(default)]
+    pub readonly: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -116,6 +146,9 @@ struct TokenBalance {
 struct TokenAmount {
     #[serde(rename = "uiAmount")]
     pub ui_amount: Option<f64>,
+    /// Raw amount in base units
+    pub amount: Option<String>,
+    pub decimals: Option<u8>,
 }
 
 #[derive(Debug, Serialize)]
@@ -127,14 +160,20 @@ struct SubscribeRequest {
 }
 
 impl QuickNodeWebSocket {
-    pub fn new(solana_service: Arc<SolanaService>) -> Result<Self> {
-        let rpc_url = std::env::var("QUICKNODE_RPC_URL")
-            .context("QUICKNODE_RPC_URL must be set")?;
-        
-        Ok(Self {
-            rpc_url,
+    pub fn new(
+        solana_service: Arc<SolanaService>,
+        dex_programs: Arc<DexProgramRegistry>,
+        sanity: Arc<TradeSanity>,
+        health: Arc<StreamHealth>,
+        memory_budget: Arc<MemoryBudget>,
+    ) -> Self {
+        Self {
             solana_service,
-        })
+            dex_programs,
+            sanity,
+            health,
+            seen_signatures: Arc::new(Mutex::new(SignatureDedup::new(memory_budget))),
+        }
     }
 
     /// Start WebSocket subscription to DEX program logs
@@ -144,7 +183,7 @@ impl QuickNodeWebSocket {
         trade_tx: mpsc::Sender<Trade>,
     ) -> Result<()> {
         // Convert HTTP URL to WebSocket URL
-        let ws_url = self.rpc_url
+        let ws_url = self.solana_service.rpc_url()
             .replace("https://", "wss://")
             .replace("http://", "ws://");
         
@@ -154,49 +193,100 @@ impl QuickNodeWebSocket {
         let (ws_stream, _) = connect_async(url)
             .await
             .context("Failed to connect to QuickNode WebSocket")?;
+        self.health.connected().await;
         
         let (mut write, mut read) = ws_stream.split();
         
-        let dex_programs = vec![
-            "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", // Jupiter v6
-            "JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB", // Jupiter v4
-            "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8", // Raydium
-            "9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP", // Orca
-            "9H6tua7jkLhdm3w8BvgpTn5LZNU7g4ZynDmCiNN3q6Rp", // Meteora
-            "PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLRJi5i4Z2j3Yc", // Phoenix
-        ];
-        
-        // Subscribe to logs for each DEX program
-        for (idx, program_id) in dex_programs.iter().enumerate() {
-            let subscribe_req = SubscribeRequest {
-                jsonrpc: "2.0".to_string(),
-                id: idx as u64 + 1,
-                method: "logsSubscribe".to_string(),
-                params: vec![
-                    json!({
-                        "mentions": [program_id]
-                    }),
-                    json!({
-                        "commitment": "confirmed"
-                    }),
-                ],
-            };
-            
-            let msg = serde_json::to_string(&subscribe_req)?;
+        // Listen for venue toggles before subscribing so none are missed
+        let mut program_commands = self.dex_programs.subscribe_commands();
+        
+        // Request id -> program id for pending logsSubscribe calls
+        let mut pending_subscriptions: HashMap<u64, String> = HashMap::new();
+        // Program id -> subscription id, and the reverse for routing notifications
+        let mut program_subscriptions: HashMap<String, u64> = HashMap::new();
+        let mut subscription_programs: HashMap<u64, String> = HashMap::new();
+        let mut next_request_id: u64 = 1;
+        
+        // Subscribe to logs for each enabled DEX program
+        for program_id in self.dex_programs.enabled_program_ids().await {
+            let msg = Self::logs_subscribe_message(next_request_id, &program_id)?;
+            self.health.subscription_requested(&program_id).await;
+            pending_subscriptions.insert(next_request_id, program_id);
+            next_request_id += 1;
             write.send(WsMessage::Text(msg)).await?;
         }
         
         // Process incoming messages
         let solana_clone = self.solana_service.clone();
-        let mut seen_signatures = std::collections::HashSet::new();
-        
-        while let Some(msg) = read.next().await {
+        let sanity = self.sanity.clone();
+        let dex_programs = self.dex_programs.clone();
+        
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                command = program_commands.recv() => {
+                    match command {
+                        Ok(ProgramCommand::Subscribe(program_id)) => {
+                            let already_subscribed = program_subscriptions.contains_key(&program_id)
+                                || pending_subscriptions.values().any(|p| p == &program_id);
+                            if !already_subscribed {
+                                let msg = Self::logs_subscribe_message(next_request_id, &program_id)?;
+                                self.health.subscription_requested(&program_id).await;
+                                pending_subscriptions.insert(next_request_id, program_id.clone());
+                                next_request_id += 1;
+                                write.send(WsMessage::Text(msg)).await?;
+                                println!("📡 [QuickNode] Subscribing to {}", program_id);
+                            }
+                        }
+                        Ok(ProgramCommand::Unsubscribe(program_id)) => {
+                            pending_subscriptions.retain(|_, p| p != &program_id);
+                            self.health.subscription_dropped(&program_id).await;
+                            if let Some(subscription_id) = program_subscriptions.remove(&program_id) {
+                                subscription_programs.remove(&subscription_id);
+                                let msg = Self::logs_unsubscribe_message(next_request_id, subscription_id)?;
+                                next_request_id += 1;
+                                write.send(WsMessage::Text(msg)).await?;
+                                println!("📡 [QuickNode] Unsubscribed from {}", program_id);
+                            }
+                        }
+                        Err(e) => {
+                            // Missed toggles - reconnect so subscriptions are rebuilt from the registry
+                            eprintln!("⚠️  [QuickNode] Program command channel error: {}, reconnecting", e);
+                            break;
+                        }
+                    }
+                    continue;
+                }
+            };
+            
             match msg {
                 Ok(WsMessage::Text(text)) => {
+                    self.health.message_received().await;
                     // Try to parse as JSON-RPC notification
                     if let Ok(jsonrpc_notif) = serde_json::from_str::<JsonRpcNotification>(&text) {
                         // Handle subscription confirmation responses
-                        if jsonrpc_notif.id.is_some() && jsonrpc_notif.result.is_some() && jsonrpc_notif.method.is_empty() {
+                        if jsonrpc_notif.method.is_empty() {
+                            if let (Some(request_id), Some(subscription_id)) = (
+                                jsonrpc_notif.id,
+                                jsonrpc_notif.result.as_ref().and_then(|r| r.as_u64()),
+                            ) {
+                                match pending_subscriptions.remove(&request_id) {
+                                    Some(program_id) => {
+                                        self.health.subscription_confirmed(&program_id, subscription_id).await;
+                                        program_subscriptions.insert(program_id.clone(), subscription_id);
+                                        subscription_programs.insert(subscription_id, program_id);
+                                    }
+                                    None => {
+                                        // Program was disabled before the subscription was confirmed
+                                        let msg = Self::logs_unsubscribe_message(next_request_id, subscription_id)?;
+                                        next_request_id += 1;
+                                        write.send(WsMessage::Text(msg)).await?;
+                                    }
+                                }
+                            }
                             continue;
                         }
                         
@@ -207,17 +297,16 @@ impl QuickNodeWebSocket {
                                 None => continue,
                             };
                             
-                            let signature = log_notif.result.value.signature.clone();
-                            
-                            // Deduplicate by signature
-                            if seen_signatures.contains(&signature) {
+                            // Drop notifications still in flight for disabled programs
+                            if !subscription_programs.contains_key(&log_notif.subscription) {
                                 continue;
                             }
-                            seen_signatures.insert(signature.clone());
                             
-                            // Keep only last 1000 signatures to prevent memory leak
-                            if seen_signatures.len() > 1000 {
-                                seen_signatures.clear();
+                            let signature = log_notif.result.value.signature.clone();
+                            
+                            // Deduplicate by signature (see SignatureDedup for when one is forgotten)
+                            if !self.seen_signatures.lock().unwrap_or_else(|e| e.into_inner()).insert(&signature) {
+                                continue;
                             }
                             
                             // Skip failed transactions (ONLY rejection criteria)
@@ -236,23 +325,33 @@ impl QuickNodeWebSocket {
                                 let signature_clone = signature.clone();
                                 let slot_clone = log_notif.result.context.slot;
                                 let trade_tx_clone = trade_tx.clone();
+                                let sanity = sanity.clone();
+                                let dex_programs = dex_programs.clone();
                                 
                                 tokio::spawn(async move {
                                     match solana_clone.get_transaction(&signature_clone).await {
-                                        Ok(Some(tx_json)) => {
-                                            // Parse transaction data
-                                            if let Ok(tx_data) = serde_json::from_value::<TransactionData>(tx_json) {
-                                                // Construct trade from both logsSubscribe and getTransaction data
-                                                if let Some(trade) = Self::construct_trade(
-                                                    &signature_clone,
-                                                    &slot_clone,
-                                                    &tx_data,
-                                                ) {
-                                                    if let Err(_) = trade_tx_clone.send(trade).await {
-                                                        // Channel closed, ignore
+                                        Ok(Some(mut tx_json)) => {
+                                            // Every replica must store the same timestamp, so a missing block time
+                                            // is looked up by slot rather than taken from the local clock
+                                            if tx_json.get("blockTime").is_none_or(|t| t.is_null()) {
+                                                match solana_clone.get_block_time(slot_clone).await {
+                                                    Ok(Some(block_time)) => tx_json["blockTime"] = serde_json::json!(block_time),
+                                                    _ => {
+                                                        eprintln!("⚠️  No block time for {} (slot {}); skipping", signature_clone, slot_clone);
+                                                        return;
                                                     }
                                                 }
                                             }
+                                            if let Some(trade) = Self::trade_from_transaction(
+                                                &signature_clone,
+                                                slot_clone,
+                                                tx_json,
+                                                &dex_programs,
+                                                &sanity,
+                                            ).await {
+                                                // Channel closed, ignore
+                                                let _ = trade_tx_clone.send(trade).await;
+                                            }
                                         }
                                         Ok(None) => {}
                                         Err(_) => {}
@@ -276,6 +375,58 @@ impl QuickNodeWebSocket {
         Ok(())
     }
     
+    /// Parse a getTransaction result into a trade, as the live stream does for each logged signature
+    /// Trades whose balances or legs point at a decimals bug are recorded with the sanity checks and dropped
+    pub(crate) async fn trade_from_transaction(
+        signature: &str,
+        slot: u64,
+        tx_json: serde_json::Value,
+        dex_programs: &DexProgramRegistry,
+        sanity: &TradeSanity,
+    ) -> Option<Trade> {
+        let tx_data = serde_json::from_value::<TransactionData>(tx_json).ok()?;
+        let logs = tx_data.meta.as_ref().and_then(|m| m.log_messages.as_deref()).unwrap_or_default();
+        let logged_venue = dex_programs.venue_in_logs(logs).await;
+        // Construct trade from both logsSubscribe and getTransaction data
+        let trade = Self::construct_trade(signature, &slot, &tx_data, logged_venue.as_deref())?;
+        if let Some(failure) = Self::check_token_amounts(&tx_data, &trade) {
+            sanity.record(failure, &trade).await;
+            return None;
+        }
+        Some(trade)
+    }
+    
+    /// Build a logsSubscribe request for a DEX program
+    fn logs_subscribe_message(request_id: u64, program_id: &str) -> Result<String> {
+        let subscribe_req = SubscribeRequest {
+            jsonrpc: "2.0".to_string(),
+            id: request_id,
+            method: "logsSubscribe".to_string(),
+            params: vec![
+                json!({
+                    "mentions": [program_id]
+                }),
+                json!({
+                    "commitment": "confirmed"
+                }),
+            ],
+        };
+        
+        Ok(serde_json::to_string(&subscribe_req)?)
+    }
+    
+    /// Build a logsUnsubscribe request for an active subscription
+    fn logs_unsubscribe_message(request_id: u64, subscription_id: u64) -> Result<String> {
+        let unsubscribe_req = SubscribeRequest {
+            jsonrpc: "2.0".to_string(),
+            id: request_id,
+            method: "logsUnsubscribe".to_string(),
+            params: vec![json!(subscription_id)],
+        };
+        
+        Ok(serde_json::to_string(&unsubscribe_req)?)
+    }
+    
     /// Check if transaction logs indicate a swap
     fn is_swap_transaction(logs: &[String]) -> bool {
         // Look for swap-related log messages
@@ -299,6 +450,7 @@ impl QuickNodeWebSocket {
         signature: &str,
         slot: &u64,
         tx_data: &TransactionData,
+        logged_venue: Option<&str>,
     ) -> Option<Trade> {
         // Commented out: Check if meta exists (use default if None)
         // let meta = tx_data.meta.as_ref()?;
@@ -322,6 +474,115 @@ impl QuickNodeWebSocket {
         //     return None;
         // }
         
+        // Prefer the actual swap legs from the swap instructions; balance deltas misattribute multi-hop
+        // routes and LP deposits, so they're only the fallback for venues that aren't decoded
+        let decoded = Self::decode_swap_legs(tx_data, meta);
+        let (base_mint, quote_mint, base_amount, quote_amount, side) = match &decoded {
+            Some(legs) => Self::orient_swap_legs(legs)?,
+            None => Self::balance_delta_legs(meta),
+        };
+        
+        // Dust legs are dropped by the trade stream, against per-token thresholds from the token registry
+        
+        // Filter: Only process trades involving allowed tokens (the token registry's list)
+        // Both base and quote mints must be in the allowed list
+        let (Some(final_base_symbol), Some(final_quote_symbol)) = (mint_to_symbol(&base_mint), mint_to_symbol(&quote_mint)) else {
+            return None; // Reject trades with unknown tokens
+        };
+        
+        // Calculate price (handle division by zero)
+        let final_price = if base_amount > 0.0 {
+            quote_amount / base_amount
+        } else {
+            0.0
+        };
+        
+        // Total value is the quote-side transfer total (price * amount before any price fallback)
+        let total_value = quote_amount;
+        
+        // Identify DEX program: the decoded swap's venue, else the first registered program in the logs
+        let dex_program = decoded.as_ref().map(|legs| legs.venue).or(logged_venue).unwrap_or("Unknown");
+        
+        // The fee payer (first account key) is the wallet that submitted the swap
+        let trader = tx_data.transaction.message.account_keys
+            .first()
+            .and_then(|key| key.as_str().or_else(|| key.get("pubkey").and_then(|p| p.as_str())))
+            .map(|key| key.to_string());
+        
+        // Get timestamp (the stream fills in a missing one from getBlockTime before parsing)
+        let block_time = tx_data.block_time?;
+        
+        Some(Trade {
+            id: signature.to_string(),
+            // Block times are whole seconds; order within a second comes only from the sequence assigned at
+            // ingest, which every open/close query uses. Arrival milliseconds would differ per replica and
+            // break the (timestamp, id) dedup key, so none are added
+            timestamp: chrono::DateTime::from_timestamp(block_time, 0)?,
+            base_symbol: final_base_symbol,
+            quote_symbol: final_quote_symbol,
+            base_mint: base_mint.clone(),
+            quote_mint: quote_mint.clone(),
+            price: final_price,
+            amount: base_amount,
+            side: side.to_string(),
+            total_value,
+            dex_program: dex_program.to_string(),
+            slot: *slot,
+            sequence: 0, // Assigned by the trade stream
+            trader,
+            inverted: false,
+            spread_bps: None, // Set by the trade stream against the oracle price
+        })
+    }
+    
+    /// Account keys in index order: static keys, then loaded writable and readonly addresses
+    fn account_keys(tx_data: &TransactionData, meta: &TransactionMeta) -> Vec<String> {
+        let static_keys = tx_data.transaction.message.account_keys.iter().filter_map(|key| {
+            key.as_str().or_else(|| key.get("pubkey").and_then(|p| p.as_str())).map(|key| key.to_string())
+        });
+        let loaded = meta.loaded_addresses.iter().flat_map(|loaded| loaded.writable.iter().chain(&loaded.readonly).cloned());
+        static_keys.chain(loaded).collect()
+    }
+    
+    /// Decode the swap from Jupiter v6 swap events or Raydium/Orca swap instructions
+    fn decode_swap_legs(tx_data: &TransactionData, meta: &TransactionMeta) -> Option<SwapLegs> {
+        let account_keys = Self::account_keys(tx_data, meta);
+        let token_accounts: HashMap<usize, (String, u8)> = meta.pre_token_balances.iter().flatten()
+            .chain(meta.post_token_balances.iter().flatten())
+            .filter_map(|balance| {
+                let decimals = balance.ui_token_amount.as_ref()?.decimals?;
+                Some((balance.account_index as usize, (balance.mint.clone(), decimals)))
+            })
+            .collect();
+        let ctx = SwapContext {
+            account_keys: &account_keys,
+            instructions: &tx_data.transaction.message.instructions,
+            inner_instructions: meta.inner_instructions.as_deref().unwrap_or_default(),
+            token_accounts: &token_accounts,
+            num_signers: tx_data.transaction.message.header.as_ref().map_or(1, |h| h.num_required_signatures),
+        };
+        decode_swap(&ctx, |mint| {
+            token_accounts.values().find(|(m, _)| m == mint).map(|(_, decimals)| *decimals).or_else(|| mint_decimals(mint))
+        })
+    }
+    
+    /// Base/quote mints and amounts plus side of decoded legs, in the orientation of the listed pair
+    /// (None if either token isn't tracked); unlisted orientations treat the received token as the base
+    fn orient_swap_legs(legs: &SwapLegs) -> Option<(String, String, f64, f64, &'static str)> {
+        let input_symbol = mint_to_symbol(&legs.input_mint)?;
+        let output_symbol = mint_to_symbol(&legs.output_mint)?;
+        let sold_base = supported_pairs().contains(&format!("{}/{}", input_symbol, output_symbol))
+            && !supported_pairs().contains(&format!("{}/{}", output_symbol, input_symbol));
+        Some(if sold_base {
+            (legs.input_mint.clone(), legs.output_mint.clone(), legs.input_amount, legs.output_amount, "sell")
+        } else {
+            (legs.output_mint.clone(), legs.input_mint.clone(), legs.output_amount, legs.input_amount, "buy")
+        })
+    }
+    
+    /// Base/quote mints and amounts plus side inferred from token balance deltas: the two mints
+    /// with the largest changes, the larger being the base
+    fn balance_delta_legs(meta: &TransactionMeta) -> (String, String, f64, f64, &'static str) {
         // Use empty vec if None to continue processing
         let empty_pre: Vec<TokenBalance> = vec![];
         let empty_post: Vec<TokenBalance> = vec![];
@@ -389,107 +650,167 @@ impl QuickNodeWebSocket {
         let post_quote = post_map.get(quote_mint.as_str()).copied().unwrap_or(0.0);
         let quote_amount = (post_quote - pre_quote).abs();
         
-        // Commented out: Check if amounts are too small
-        // if base_amount < 0.000001 || quote_amount < 0.0001 {
-        //     return None;
-        // }
-        
-        // Filter: Only process trades involving allowed tokens
-        // Both base and quote mints must be in the allowed list
-        if !Self::is_allowed_mint(&base_mint) || !Self::is_allowed_mint(&quote_mint) {
-            return None; // Reject trades with unknown tokens
-        }
-        
-        // Map mints to symbols
-        let base_symbol = Self::mint_to_symbol(&base_mint);
-        let quote_symbol = Self::mint_to_symbol(&quote_mint);
-        
-        // Use symbols (should never be UNKNOWN now due to filtering above)
-        let final_base_symbol = base_symbol;
-        let final_quote_symbol = quote_symbol;
-        
-        // Calculate price (handle division by zero)
-        let final_price = if base_amount > 0.0 {
-            quote_amount / base_amount
-        } else {
-            0.0
-        };
-        
         // Determine side based on base delta
         let base_delta = post_base - pre_base;
         let side = if base_delta > 0.0 { "buy" } else { "sell" };
         
-        // Calculate total value
-        let total_value = final_price * base_amount;
-        
-        // Identify DEX program
-        let dex_program = meta.log_messages.as_ref()
-            .map(|logs| {
-                let logs_str = logs.join(" ");
-                if logs_str.contains("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4") {
-                    "Jupiter v6"
-                } else if logs_str.contains("JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB") {
-                    "Jupiter v4"
-                } else if logs_str.contains("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8") {
-                    "Raydium"
-                } else if logs_str.contains("9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP") {
-                    "Orca"
-                } else if logs_str.contains("9H6tua7jkLhdm3w8BvgpTn5LZNU7g4ZynDmCiNN3q6Rp") {
-                    "Meteora"
-                } else if logs_str.contains("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLRJi5i4Z2j3Yc") {
-                    "Phoenix"
-                } else {
-                    "Unknown"
-                }
-            })
-            .unwrap_or("Unknown");
-        
-        // Get timestamp
-        let block_time = tx_data.block_time.unwrap_or(Utc::now().timestamp());
-        
-        Some(Trade {
-            id: signature.to_string(),
-            timestamp: chrono::DateTime::from_timestamp(block_time, 0)
-                .unwrap_or_else(|| Utc::now()),
-            base_symbol: final_base_symbol,
-            quote_symbol: final_quote_symbol,
-            base_mint: base_mint.clone(),
-            quote_mint: quote_mint.clone(),
-            price: final_price,
-            amount: base_amount,
-            side: side.to_string(),
-            total_value,
-            dex_program: dex_program.to_string(),
-            slot: *slot,
-        })
+        (base_mint, quote_mint, base_amount, quote_amount, side)
     }
     
-    /// Map mint address to symbol
-    fn mint_to_symbol(mint: &str) -> String {
-        match mint {
-            "So11111111111111111111111111111111111111112" => "SOL".to_string(),
-            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v" => "USDC".to_string(),
-            "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB" => "USDT".to_string(),
-            "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263" => "BONK".to_string(),
-            "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN" => "JUP".to_string(),
-            "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm" => "WIF".to_string(),
-            "4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R" => "RAY".to_string(),
-            _ => "UNKNOWN".to_string(),
+    /// Check the trade's token balances against mint decimals and their raw amounts, and its legs against
+    /// the amounts those balances moved
+    fn check_token_amounts(tx_data: &TransactionData, trade: &Trade) -> Option<SanityFailure> {
+        let meta = tx_data.meta.as_ref()?;
+        let balances = meta.pre_token_balances.iter().flatten()
+            .chain(meta.post_token_balances.iter().flatten())
+            .filter(|balance| balance.mint == trade.base_mint || balance.mint == trade.quote_mint);
+        
+        for balance in balances {
+            let Some(token_amount) = balance.ui_token_amount.as_ref() else {
+                continue;
+            };
+            if let (Some(reported), Some(expected)) = (token_amount.decimals, mint_decimals(&balance.mint))
+                && reported != expected
+            {
+                return Some(SanityFailure::DecimalsMismatch);
+            }
+            if let (Some(ui_amount), Some(raw), Some(decimals)) = (
+                token_amount.ui_amount,
+                token_amount.amount.as_ref().and_then(|a| a.parse::<f64>().ok()),
+                token_amount.decimals,
+            ) && orders_apart(ui_amount, raw / 10f64.powi(decimals as i32))
+            {
+                return Some(SanityFailure::UiAmountMismatch);
+            }
+        }
+        
+        // The parsed legs vs what the token balances say moved, for the same mints
+        let moved = Self::transfer_totals(meta);
+        let moved_of = |mint: &str| moved.get(mint).copied().unwrap_or(0.0);
+        if orders_apart(trade.amount, moved_of(&trade.base_mint))
+            || orders_apart(trade.amount * trade.price, moved_of(&trade.quote_mint))
+        {
+            return Some(SanityFailure::NotionalMismatch);
         }
+        None
     }
     
-    /// Check if mint is in the allowed list
-    fn is_allowed_mint(mint: &str) -> bool {
-        matches!(
-            mint,
-            "So11111111111111111111111111111111111111112" | // SOL
-            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v" | // USDC
-            "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB" | // USDT
-            "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263" | // BONK
-            "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN" | // JUP
-            "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm" | // WIF
-            "4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R"   // RAY
-        )
+    /// UI amount of each mint the transaction moved, from per-account balance deltas: the larger of what
+    /// its accounts gained and what they lost (a wrapped SOL account opened and closed in the swap has no
+    /// balances, leaving only the pool's side)
+    fn transfer_totals(meta: &TransactionMeta) -> HashMap<String, f64> {
+        let ui = |balance: &TokenBalance| -> Option<f64> {
+            let token_amount = balance.ui_token_amount.as_ref()?;
+            Some(token_amount.amount.as_ref()?.parse::<f64>().ok()? / 10f64.powi(token_amount.decimals? as i32))
+        };
+        let mut deltas: HashMap<(u8, &str), f64> = HashMap::new();
+        for balance in meta.pre_token_balances.iter().flatten() {
+            *deltas.entry((balance.account_index, balance.mint.as_str())).or_insert(0.0) -= ui(balance).unwrap_or(0.0);
+        }
+        for balance in meta.post_token_balances.iter().flatten() {
+            *deltas.entry((balance.account_index, balance.mint.as_str())).or_insert(0.0) += ui(balance).unwrap_or(0.0);
+        }
+        
+        let mut gained: HashMap<String, f64> = HashMap::new();
+        let mut lost: HashMap<String, f64> = HashMap::new();
+        for ((_, mint), delta) in deltas {
+            if delta > 0.0 {
+                *gained.entry(mint.to_string()).or_insert(0.0) += delta;
+            } else if delta < 0.0 {
+                *lost.entry(mint.to_string()).or_insert(0.0) -= delta;
+            }
+        }
+        for (mint, amount) in lost {
+            let total = gained.entry(mint).or_insert(0.0);
+            *total = total.max(amount);
+        }
+        gained
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn balance(account_index: u8, mint: &str, raw: u64, decimals: u8) -> serde_json::Value {
+        json!({
+            "accountIndex": account_index,
+            "mint": mint,
+            "uiTokenAmount": {
+                "uiAmount": raw as f64 / 10f64.powi(decimals as i32),
+                "amount": raw.to_string(),
+                "decimals": decimals,
+            }
+        })
+    }
+
+    /// The trader (accounts 1, 2) sells 2 SOL for 300 USDC to a pool (accounts 3, 4)
+    fn sell_two_sol() -> TransactionData {
+        serde_json::from_value(json!({
+            "slot": 1,
+            "blockTime": 1_700_000_000,
+            "transaction": { "message": { "accountKeys": [] }, "signatures": [] },
+            "meta": {
+                "preTokenBalances": [
+                    balance(1, SOL, 5_000_000_000, 9),
+                    balance(2, USDC, 0, 6),
+                    balance(3, SOL, 100_000_000_000, 9),
+                    balance(4, USDC, 20_000_000_000, 6),
+                ],
+                "postTokenBalances": [
+                    balance(1, SOL, 3_000_000_000, 9),
+                    balance(2, USDC, 300_000_000, 6),
+                    balance(3, SOL, 102_000_000_000, 9),
+                    balance(4, USDC, 19_700_000_000, 6),
+                ],
+            }
+        }))
+        .unwrap()
+    }
+
+    fn trade(amount: f64, price: f64) -> Trade {
+        Trade {
+            id: "sig".to_string(),
+            timestamp: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            base_symbol: "SOL".to_string(),
+            quote_symbol: "USDC".to_string(),
+            base_mint: SOL.to_string(),
+            quote_mint: USDC.to_string(),
+            price,
+            amount,
+            side: "sell".to_string(),
+            total_value: amount * price,
+            dex_program: "Raydium".to_string(),
+            slot: 1,
+            sequence: 0,
+            trader: None,
+            inverted: false,
+            spread_bps: None,
+        }
+    }
+
+    #[test]
+    fn transfer_totals_count_each_mint_once() {
+        let totals = QuickNodeWebSocket::transfer_totals(sell_two_sol().meta.as_ref().unwrap());
+        assert_eq!(totals[SOL], 2.0);
+        assert_eq!(totals[USDC], 300.0);
+    }
+
+    #[test]
+    fn legs_matching_the_balances_pass() {
+        assert_eq!(QuickNodeWebSocket::check_token_amounts(&sell_two_sol(), &trade(2.0, 150.0)), None);
+    }
+
+    #[test]
+    fn legs_off_by_powers_of_ten_are_notional_mismatches() {
+        let tx = sell_two_sol();
+        // Base amount read with the quote's decimals
+        assert_eq!(QuickNodeWebSocket::check_token_amounts(&tx, &trade(2000.0, 0.15)), Some(SanityFailure::NotionalMismatch));
+        // Base amount right, quote amount (and so the price) a thousand times too large
+        assert_eq!(QuickNodeWebSocket::check_token_amounts(&tx, &trade(2.0, 150_000.0)), Some(SanityFailure::NotionalMismatch));
+    }
+}
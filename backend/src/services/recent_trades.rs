@@ -0,0 +1,321 @@
+// Recent trade cache - the last few hundred trades of each pair, fed by the trade stream and seeded
+// from ClickHouse on first use, so first-page /api/trades loads don't query ClickHouse
+//
+// Trades are held per pair (both stored directions merged) in the orientation of the pair's symbols
+// in sorted order, newest last, exactly as ClickHouse returns them: stored-shape fields only, and
+// `inverted` set on trades flipped from the other direction. Requests for the other orientation
+// flip them back. Requests the cache can't answer exactly (deeper than it holds, cursors, slot
+// ranges, streams) go to ClickHouse.
+//
+// Only a replica that stores the trades it sees is fed every trade: one forwarding to a hub,
+// serving API only, or waiting out a failed preflight would serve a seed that never advances. Until
+// its trade stream starts storing, every read goes to ClickHouse.
+
+use crate::models::trade::Trade;
+use crate::services::clickhouse::{ClickHouseService, TradeFilter};
+use crate::services::memory_budget::{Footprint, MemoryBudget, PairLru};
+use crate::services::pair_mapping::{parse_pair, Pair, supported_pairs};
+use anyhow::{anyhow, Result};
+use futures_util::future::join_all;
+use std::cmp::Ordering;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub const DEFAULT_TRADES_PER_PAIR: usize = 500;
+
+/// Order of trades within a pair, as `ORDER BY timestamp, sequence, id` reads them
+fn trade_order(a: &Trade, b: &Trade) -> Ordering {
+    a.timestamp.cmp(&b.timestamp).then(a.sequence.cmp(&b.sequence)).then_with(|| a.id.cmp(&b.id))
+}
+
+/// The fields a trade read back from ClickHouse carries (mints and the transfer total aren't stored)
+fn as_stored(trade: &Trade) -> Trade {
+    Trade {
+        base_mint: String::new(),
+        quote_mint: String::new(),
+        total_value: trade.price * trade.amount,
+        ..trade.clone()
+    }
+}
+
+/// Newest trades of a pair, oldest first, at most `capacity`
+#[derive(Default)]
+struct PairTrades {
+    trades: VecDeque<Trade>,
+}
+
+impl PairTrades {
+    /// Insert in order; a late trade older than everything held in a full buffer isn't recent
+    fn insert(&mut self, trade: Trade, capacity: usize) {
+        let position = self.trades.iter().rposition(|held| trade_order(held, &trade) == Ordering::Less).map_or(0, |i| i + 1);
+        if position == 0 && self.trades.len() >= capacity {
+            return;
+        }
+        self.trades.insert(position, trade);
+        while self.trades.len() > capacity {
+            self.trades.pop_front();
+        }
+    }
+
+    /// The `limit` newest, newest first, flipped to the other orientation if asked
+    fn newest(&self, limit: usize, flip: bool) -> Vec<Trade> {
+        self.trades
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .map(|mut trade| {
+                if flip {
+                    trade.invert();
+                }
+                trade
+            })
+            .collect()
+    }
+
+    /// The seed query's rows (newest first) merged with the trades that arrived while it ran
+    fn seeded(rows: Vec<Trade>, pending: Vec<Trade>, capacity: usize) -> Self {
+        let seeded: HashSet<String> = rows.iter().map(|trade| trade.id.clone()).collect();
+        let mut trades = PairTrades::default();
+        for trade in rows.into_iter().rev() {
+            trades.insert(trade, capacity);
+        }
+        for trade in pending {
+            if !seeded.contains(&trade.id) {
+                trades.insert(trade, capacity);
+            }
+        }
+        trades
+    }
+}
+
+enum PairState {
+    /// Seed query in flight; live trades wait here
+    Seeding(Vec<Trade>),
+    /// `complete` once the pair had fewer trades than the cache holds, so every trade is here
+    Warm { trades: PairTrades, complete: bool },
+    /// Outgrew the per-pair memory budget; reads go to ClickHouse until it's evicted or invalidated
+    OverBudget,
+}
+
+fn trade_footprint(trade: &Trade) -> usize {
+    std::mem::size_of::<Trade>()
+        + [&trade.id, &trade.base_symbol, &trade.quote_symbol, &trade.base_mint, &trade.quote_mint, &trade.side, &trade.dex_program]
+            .iter()
+            .map(|s| s.capacity())
+            .sum::<usize>()
+        + trade.trader.as_ref().map_or(0, String::capacity)
+}
+
+impl Footprint for PairState {
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + match self {
+                PairState::Seeding(pending) => pending.iter().map(trade_footprint).sum(),
+                PairState::Warm { trades, .. } => {
+                    trades.trades.iter().map(trade_footprint).sum::<usize>()
+                        + (trades.trades.capacity() - trades.trades.len()) * std::mem::size_of::<Trade>()
+                }
+                PairState::OverBudget => 0,
+            }
+    }
+
+    fn shed(&mut self) {
+        *self = PairState::OverBudget;
+    }
+}
+
+/// The pair's symbols in a fixed order, so both orientations share one entry
+type PairKey = (String, String);
+
+fn pair_key(a: &str, b: &str) -> PairKey {
+    if a <= b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) }
+}
+
+/// A trade as its pair entry holds it: stored-shape fields, in the key's orientation
+fn keyed(trade: &Trade, key: &PairKey) -> Trade {
+    let mut stored = as_stored(trade);
+    if stored.base_symbol != key.0 {
+        stored.invert();
+    }
+    stored
+}
+
+pub struct RecentTrades {
+    clickhouse: Arc<ClickHouseService>,
+    pairs: Arc<RwLock<PairLru<PairKey, PairState>>>,
+    /// Trades held per pair
+    pub capacity: usize,
+    /// Set once this replica's trade stream stores every trade it sees; until then nothing is served
+    fed: AtomicBool,
+}
+
+impl RecentTrades {
+    /// `capacity` (RECENT_TRADES_PER_PAIR, default 500) trades are held per pair
+    pub fn new(clickhouse: Arc<ClickHouseService>, memory_budget: Arc<MemoryBudget>, capacity: usize) -> Self {
+        Self {
+            clickhouse,
+            pairs: Arc::new(RwLock::new(PairLru::new("recent_trades", memory_budget))),
+            capacity,
+            fed: AtomicBool::new(false),
+        }
+    }
+
+    /// Called by the trade stream once it stores the trades it sees; starts serving, seeding
+    /// every supported pair so first page loads after a restart don't wait on ClickHouse
+    pub async fn start_feeding(self: Arc<Self>) {
+        if self.capacity == 0 || self.fed.swap(true, AtomicOrdering::SeqCst) {
+            return;
+        }
+        let pairs: Vec<Pair> = supported_pairs().iter().filter_map(|pair| parse_pair(pair)).collect();
+        let results = join_all(pairs.iter().map(|pair| self.recent(&pair.base, &pair.quote, 1))).await;
+        let warmed = results.iter().filter(|r| r.as_ref().is_ok_and(Option::is_some)).count();
+        for (pair, result) in pairs.iter().zip(&results) {
+            if let Err(e) = result {
+                eprintln!("⚠️  [RecentTrades] Failed to warm up {}: {}", pair, e);
+            }
+        }
+        println!("✅ [RecentTrades] Warmed up {} of {} pairs ({} trades each)", warmed, pairs.len(), self.capacity);
+    }
+
+    /// Add a stored (unquarantined) trade to its pair
+    /// Pairs nobody has asked about yet are skipped; their seed reads the trade from ClickHouse
+    pub async fn record(&self, trade: &Trade) {
+        if !self.fed.load(AtomicOrdering::SeqCst) {
+            return;
+        }
+        let key = pair_key(&trade.base_symbol, &trade.quote_symbol);
+        let stored = keyed(trade, &key);
+        let capacity = self.capacity;
+        self.pairs.write().await.update(&key, |state| match state {
+            PairState::Warm { trades, .. } => trades.insert(stored, capacity),
+            PairState::Seeding(pending) => pending.push(stored),
+            PairState::OverBudget => {}
+        });
+    }
+
+    /// Forget a pair (e.g. after trades were flagged quarantined); the next read reseeds it
+    pub async fn invalidate(&self, base_symbol: &str, quote_symbol: &str) {
+        self.pairs.write().await.remove(&pair_key(base_symbol, quote_symbol));
+    }
+
+    /// The `limit` newest trades of a pair in the requested orientation, newest first, as
+    /// `get_trades` would return them; seeds a cold pair first
+    /// None when the cache can't answer: not fed here, deeper than it holds, seeding elsewhere, or over budget
+    pub async fn recent(&self, base_symbol: &str, quote_symbol: &str, limit: usize) -> Result<Option<Vec<Trade>>> {
+        if limit > self.capacity || !self.fed.load(AtomicOrdering::SeqCst) {
+            return Ok(None);
+        }
+        let key = pair_key(base_symbol, quote_symbol);
+        let flip = base_symbol != key.0;
+        let read = |state: &mut PairState| match state {
+            PairState::Warm { trades, complete } if *complete || trades.trades.len() >= limit => {
+                Some(trades.newest(limit, flip))
+            }
+            PairState::Warm { .. } | PairState::Seeding(_) | PairState::OverBudget => None,
+        };
+        {
+            let mut pairs = self.pairs.write().await;
+            match pairs.update(&key, read) {
+                Some(trades) => return Ok(trades),
+                None => pairs.insert(key.clone(), PairState::Seeding(Vec::new())),
+            }
+        }
+
+        // Seeded in a task so a dropped request can't leave the pair stuck in Seeding
+        let seed = tokio::spawn(Self::seed(self.clickhouse.clone(), self.pairs.clone(), key.clone(), self.capacity));
+        seed.await.map_err(|e| anyhow!("Recent trades seed task failed: {}", e))??;
+        Ok(self.pairs.write().await.update(&key, read).flatten())
+    }
+
+    async fn seed(
+        clickhouse: Arc<ClickHouseService>,
+        pairs: Arc<RwLock<PairLru<PairKey, PairState>>>,
+        key: PairKey,
+        capacity: usize,
+    ) -> Result<()> {
+        let rows = clickhouse.get_trades(&key.0, &key.1, capacity, &TradeFilter::default()).await;
+
+        let mut pairs = pairs.write().await;
+        // Invalidated while the query ran: leave it cold
+        let Some(PairState::Seeding(pending)) = pairs.remove(&key) else {
+            return Ok(());
+        };
+        let rows = rows?;
+
+        let complete = rows.len() < capacity;
+        let trades = PairTrades::seeded(rows, pending, capacity);
+        println!("📜 [RecentTrades] Seeded {}/{} with {} trades", key.0, key.1, trades.trades.len());
+        pairs.insert(key, PairState::Warm { trades, complete });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn trade(id: &str, secs: i64, sequence: u64) -> Trade {
+        Trade {
+            id: id.to_string(),
+            timestamp: Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            base_symbol: "SOL".to_string(),
+            quote_symbol: "USDC".to_string(),
+            base_mint: String::new(),
+            quote_mint: String::new(),
+            price: 100.0,
+            amount: 2.0,
+            side: "buy".to_string(),
+            total_value: 200.0,
+            dex_program: "Raydium".to_string(),
+            slot: 1,
+            sequence,
+            trader: None,
+            inverted: false,
+            spread_bps: None,
+        }
+    }
+
+    fn ids(trades: &[Trade]) -> Vec<&str> {
+        trades.iter().map(|trade| trade.id.as_str()).collect()
+    }
+
+    #[test]
+    fn seed_merges_pending_trades_in_order_without_duplicates() {
+        // The seed query returns newest first; "c" was also recorded while it ran
+        let rows = vec![trade("c", 2, 1), trade("b", 1, 1), trade("a", 0, 1)];
+        let pending = vec![trade("e", 3, 2), trade("c", 2, 1), trade("d", 3, 1)];
+        let trades = PairTrades::seeded(rows, pending, 10);
+        assert_eq!(ids(&trades.newest(10, false)), ["e", "d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn full_buffer_keeps_the_newest() {
+        let rows = vec![trade("c", 2, 0), trade("b", 1, 0)];
+        let mut trades = PairTrades::seeded(rows, Vec::new(), 2);
+        trades.insert(trade("a", 0, 0), 2);
+        assert_eq!(ids(&trades.newest(2, false)), ["c", "b"]);
+        trades.insert(trade("d", 3, 0), 2);
+        assert_eq!(ids(&trades.newest(2, false)), ["d", "c"]);
+    }
+
+    #[test]
+    fn trades_are_held_in_key_orientation_and_flipped_on_read() {
+        let key = pair_key("USDC", "SOL");
+        assert_eq!(key, ("SOL".to_string(), "USDC".to_string()));
+
+        let mut reversed = trade("a", 0, 0);
+        reversed.invert();
+        let held = keyed(&reversed, &key);
+        assert_eq!((held.base_symbol.as_str(), held.price, held.side.as_str()), ("SOL", 100.0, "buy"));
+
+        let mut trades = PairTrades::default();
+        trades.insert(held, 10);
+        let flipped = &trades.newest(1, true)[0];
+        assert_eq!((flipped.base_symbol.as_str(), flipped.price, flipped.side.as_str()), ("USDC", 0.01, "sell"));
+        assert!(flipped.inverted);
+    }
+}
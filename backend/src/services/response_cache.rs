@@ -0,0 +1,68 @@
+// Response cache - /api/trades, /api/ohlcv and /api/stats bodies kept for a few seconds per request,
+// so a burst of identical frontend refreshes becomes one ClickHouse query
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell};
+
+/// Most distinct requests held at once; keys come from query strings, so they are bounded
+const MAX_ENTRIES: usize = 2_048;
+
+struct Entry {
+    created: Instant,
+    /// Shared by every request for the key, so concurrent misses wait on one load
+    body: Arc<OnceCell<Value>>,
+}
+
+pub struct ResponseCache {
+    /// How long a body is served; zero disables the cache
+    pub ttl: Duration,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl ResponseCache {
+    /// `ttl` is RESPONSE_CACHE_TTL_MS from the Config; zero disables the cache
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The cached body for `key`, or the one `load` produces; failed loads aren't cached,
+    /// so the next request for the key retries
+    pub async fn get_or_load<E, F, Fut>(&self, key: String, load: F) -> Result<Value, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, E>>,
+    {
+        if self.ttl.is_zero() {
+            return load().await;
+        }
+        let body = {
+            let mut entries = self.entries.lock().await;
+            let now = Instant::now();
+            match entries.get(&key) {
+                Some(entry) if now.duration_since(entry.created) < self.ttl => Some(entry.body.clone()),
+                _ => {
+                    if entries.len() >= MAX_ENTRIES {
+                        entries.retain(|_, entry| now.duration_since(entry.created) < self.ttl);
+                    }
+                    // Still full of live entries: answer uncached rather than evict them
+                    (entries.len() < MAX_ENTRIES).then(|| {
+                        let body = Arc::new(OnceCell::new());
+                        entries.insert(key, Entry { created: now, body: body.clone() });
+                        body
+                    })
+                }
+            }
+        };
+        match body {
+            Some(body) => body.get_or_try_init(load).await.cloned(),
+            None => load().await,
+        }
+    }
+}
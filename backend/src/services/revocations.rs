@@ -0,0 +1,36 @@
+// Token revocations - session tokens given up by /auth/logout or replaced by /auth/refresh
+// Refused here at once and until they expire; other replicas refuse them through the revoked_sessions table, which validate_session checks
+
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+pub struct TokenRevocations {
+    /// sha256 of the token -> its expiry (unix seconds), after which it can be forgotten
+    revoked: Mutex<HashMap<String, usize>>,
+}
+
+fn token_digest(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+impl TokenRevocations {
+    pub fn new() -> Self {
+        Self {
+            revoked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Refuse a token from now on; tokens that have expired anyway are pruned
+    pub async fn revoke(&self, token: &str, expires_at: usize) {
+        let now = Utc::now().timestamp() as usize;
+        let mut revoked = self.revoked.lock().await;
+        revoked.retain(|_, exp| *exp > now);
+        revoked.insert(token_digest(token), expires_at);
+    }
+
+    pub async fn is_revoked(&self, token: &str) -> bool {
+        self.revoked.lock().await.contains_key(&token_digest(token))
+    }
+}
@@ -0,0 +1,41 @@
+// Deterministic trade sampling - keeps 1 in N trades, chosen by a hash of the trade's signature
+
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Largest N accepted in a "1/N" sample rate
+pub const MAX_SAMPLE_DENOMINATOR: u64 = 1_000_000;
+
+/// Keeps a trade when the first 8 bytes of SHA-256(trade id), read big-endian, are 0 mod N
+/// The id is the transaction signature, so every consumer sampling at the same rate sees the same trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TradeSample {
+    denominator: u64,
+}
+
+impl TradeSample {
+    /// Parse a "1/N" rate, N between 1 and MAX_SAMPLE_DENOMINATOR
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let denominator = raw
+            .trim()
+            .strip_prefix("1/")
+            .and_then(|n| n.parse::<u64>().ok())
+            .filter(|n| (1..=MAX_SAMPLE_DENOMINATOR).contains(n))
+            .ok_or_else(|| format!("sample must be 1/N with N between 1 and {}, got '{}'", MAX_SAMPLE_DENOMINATOR, raw))?;
+        Ok(Self { denominator })
+    }
+
+    /// Whether the trade with this id is in the sample
+    pub fn includes(&self, trade_id: &str) -> bool {
+        let digest = Sha256::digest(trade_id.as_bytes());
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(prefix) % self.denominator == 0
+    }
+}
+
+impl fmt::Display for TradeSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "1/{}", self.denominator)
+    }
+}
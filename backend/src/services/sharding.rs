@@ -0,0 +1,145 @@
+// Ingestion sharding - splits the DEX programs across processes and forwards a worker's trades to the hub
+
+use crate::middleware::ingest::source_secret;
+use crate::models::trade::Trade;
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Duration};
+
+/// Ingest source forwarded batches are signed as (its secret comes from INGEST_SECRETS)
+pub const SHARD_INGEST_SOURCE: &str = "shard";
+/// Most trades per forwarded batch (the ingest endpoint's limit)
+const MAX_FORWARD_BATCH: usize = 1_000;
+/// How long a partial batch waits for more trades before it's sent
+const FORWARD_FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+/// Attempts per batch before it's dropped
+const FORWARD_ATTEMPTS: u32 = 3;
+
+/// This process's share of the DEX programs: those whose registry position % `count` == `index`
+/// The default is a single shard owning every program
+#[derive(Debug, Clone, Copy)]
+pub struct IngestShard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Default for IngestShard {
+    fn default() -> Self {
+        Self { index: 0, count: 1 }
+    }
+}
+
+impl IngestShard {
+    /// INGEST_SHARD as "index/count" (e.g. "0/3"); None unless index < count
+    pub fn parse(raw: &str) -> Option<Self> {
+        let (index, count) = raw.trim().split_once('/')?;
+        let (index, count) = (index.trim().parse().ok()?, count.trim().parse().ok()?);
+        (index < count).then_some(Self { index, count })
+    }
+
+    /// Shard responsible for the program at `position` in the registry
+    pub fn shard_of(&self, position: usize) -> usize {
+        position % self.count
+    }
+
+    pub fn owns(&self, position: usize) -> bool {
+        self.shard_of(position) == self.index
+    }
+}
+
+/// Sends a worker's parsed trades to the hub's POST /api/ingest/trades, where they join the hub's pipeline
+/// (sequencing, checks, storage, broadcast) alongside every other shard's
+pub struct ShardForwarder {
+    client: reqwest::Client,
+    url: String,
+    secret: String,
+}
+
+impl ShardForwarder {
+    /// `forward_url` (INGEST_FORWARD_URL) is the hub's base URL; None means this process runs the pipeline itself
+    pub fn new(forward_url: Option<&str>) -> Option<Self> {
+        let base = forward_url?;
+        let Some(secret) = source_secret(SHARD_INGEST_SOURCE) else {
+            eprintln!("⚠️  [Sharding] INGEST_FORWARD_URL is set but INGEST_SECRETS has no '{}' secret; not forwarding", SHARD_INGEST_SOURCE);
+            return None;
+        };
+        Some(Self {
+            client: reqwest::Client::new(),
+            url: format!("{}/api/ingest/trades", base.trim_end_matches('/')),
+            secret,
+        })
+    }
+
+    /// Batch trades from `trade_rx` and forward them until the channel closes
+    pub async fn run(self, mut trade_rx: mpsc::Receiver<Trade>) {
+        println!("📨 [Sharding] Forwarding parsed trades to {}", self.url);
+        let mut batch = Vec::new();
+        loop {
+            match timeout(FORWARD_FLUSH_INTERVAL, trade_rx.recv()).await {
+                Ok(Some(trade)) => {
+                    batch.push(trade);
+                    if batch.len() < MAX_FORWARD_BATCH {
+                        continue;
+                    }
+                }
+                Ok(None) => break,
+                Err(_) if batch.is_empty() => continue,
+                Err(_) => {}
+            }
+            self.send(std::mem::take(&mut batch)).await;
+        }
+        if !batch.is_empty() {
+            self.send(batch).await;
+        }
+    }
+
+    async fn send(&self, batch: Vec<Trade>) {
+        let body = match serde_json::to_vec(&batch) {
+            Ok(body) => body,
+            Err(e) => {
+                eprintln!("❌ [Sharding] Failed to serialize {} trade(s): {}", batch.len(), e);
+                return;
+            }
+        };
+        for attempt in 1..=FORWARD_ATTEMPTS {
+            match self.post(&body).await {
+                Ok(result) => {
+                    println!("📨 [Sharding] Forwarded {} trade(s): {} accepted, {} duplicate(s), {} rejected",
+                        batch.len(), result["accepted"], result["duplicates"],
+                        result["rejected"].as_array().map_or(0, |r| r.len()));
+                    return;
+                }
+                Err(e) => eprintln!("⚠️  [Sharding] Forward attempt {}/{} failed: {}", attempt, FORWARD_ATTEMPTS, e),
+            }
+            tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+        }
+        eprintln!("❌ [Sharding] Dropped {} trade(s) after {} attempts", batch.len(), FORWARD_ATTEMPTS);
+    }
+
+    /// POST one signed batch; returns the hub's accepted/duplicates/rejected summary
+    async fn post(&self, body: &[u8]) -> Result<serde_json::Value> {
+        // Signed fresh per attempt: the hub accepts each signature only once
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).context("Invalid shard ingest secret")?;
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        let response = self.client
+            .post(&self.url)
+            .header("content-type", "application/json")
+            .header("x-ingest-source", SHARD_INGEST_SOURCE)
+            .header("x-ingest-timestamp", &timestamp)
+            .header("x-ingest-signature", signature)
+            .body(body.to_vec())
+            .send()
+            .await
+            .context("Failed to reach the ingest hub")?
+            .error_for_status()
+            .context("Ingest hub rejected the batch")?;
+        response.json().await.context("Failed to parse ingest hub response")
+    }
+}
@@ -0,0 +1,57 @@
+// Replay protection - each nonce from /auth/nonce signs in once, and each signed ingest request is
+// accepted once, on any replica
+//
+// A nonce is a stateless signed token, so on its own it could be replayed with its signature until
+// it expires. A successful sign-in consumes its nonce in ClickHouse, where every replica sees it,
+// and reuse is refused. Repeated bad signatures are held off by the per-IP rate limit on
+// /auth/verify (RATE_LIMIT_VERIFY) rather than remembered, since a changed byte would miss any memo.
+// Ingest request signatures are consumed the same way, in the same table, while their signed
+// timestamp is within the allowed skew.
+
+use crate::middleware::ingest::SignatureLedger;
+use crate::services::clickhouse::ClickHouseService;
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+pub struct SignatureChecks {
+    clickhouse: Arc<ClickHouseService>,
+}
+
+/// Nonces are stored hashed; they are short-lived bearer material
+fn nonce_digest(nonce: &str) -> String {
+    hex::encode(Sha256::digest(nonce.as_bytes()))
+}
+
+/// Ingest signatures are hashed under their own prefix, so they can't collide with a nonce
+fn ingest_signature_digest(signature: &str) -> String {
+    nonce_digest(&format!("ingest:{}", signature.to_lowercase()))
+}
+
+impl SignatureChecks {
+    pub fn new(clickhouse: Arc<ClickHouseService>) -> Self {
+        Self { clickhouse }
+    }
+
+    /// Consume a nonce for a successful sign-in until it expires (unix seconds)
+    /// False if it was already used, here or on another replica, or lost a race for it
+    pub async fn consume(&self, nonce: &str, expires_at: usize) -> Result<bool> {
+        let expires_at = OffsetDateTime::from_unix_timestamp(expires_at as i64).unwrap_or_else(|_| OffsetDateTime::now_utc());
+        self.clickhouse.claim_nonce(&nonce_digest(nonce), expires_at).await
+    }
+
+    /// Consume an ingest request signature until `forget_at` (unix seconds)
+    /// False if it was already accepted, here or on another replica, or lost a race for it
+    pub async fn consume_ingest_signature(&self, signature: &str, forget_at: i64) -> Result<bool> {
+        let forget_at = OffsetDateTime::from_unix_timestamp(forget_at).unwrap_or_else(|_| OffsetDateTime::now_utc());
+        self.clickhouse.claim_nonce(&ingest_signature_digest(signature), forget_at).await
+    }
+}
+
+impl SignatureLedger for SignatureChecks {
+    fn consume<'a>(&'a self, signature: &'a str, forget_at: i64) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(self.consume_ingest_signature(signature, forget_at))
+    }
+}
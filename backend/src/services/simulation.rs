@@ -0,0 +1,142 @@
+// Simulation service - dry-runs unsigned/signed transactions before the user signs
+
+use crate::services::pair_mapping::mint_to_symbol;
+use crate::services::solana::SolanaService;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+#[derive(Debug, Serialize)]
+pub struct BalanceChange {
+    pub mint: String,
+    pub symbol: Option<String>,
+    pub account: String,
+    pub before: f64,
+    pub after: f64,
+    pub change: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulationResult {
+    pub success: bool,
+    pub units_consumed: Option<u64>,
+    pub balance_changes: Vec<BalanceChange>,
+    pub error: Option<Value>,
+    /// Human-readable explanation of `error`
+    pub reason: Option<String>,
+    pub logs: Vec<String>,
+}
+
+pub struct SimulationService {
+    solana: SolanaService,
+}
+
+impl SimulationService {
+    pub fn new(solana: SolanaService) -> Self {
+        Self { solana }
+    }
+
+    /// Simulate a transaction and diff the wallet's SOL and existing token account balances
+    /// Token accounts created by the transaction itself are not reported
+    pub async fn simulate(&self, wallet: &str, transaction: &str) -> Result<SimulationResult> {
+        let lamports = self.solana.get_balance(wallet).await?;
+        let token_accounts = self.solana.get_token_accounts_by_owner(wallet).await?;
+
+        let mut addresses = vec![wallet.to_string()];
+        addresses.extend(token_accounts.iter().map(|a| a.address.clone()));
+
+        let value = self.solana.simulate_transaction(transaction, &addresses).await?;
+        let logs: Vec<String> = value["logs"]
+            .as_array()
+            .map(|logs| logs.iter().filter_map(|l| l.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default();
+        let error = value.get("err").filter(|e| !e.is_null()).cloned();
+        let post_accounts = value["accounts"].as_array().cloned().unwrap_or_default();
+
+        let mut balance_changes = Vec::new();
+        if error.is_none() {
+            // Native SOL (includes fees)
+            if let Some(post) = post_accounts.first() {
+                let after = post.get("lamports").and_then(|v| v.as_u64()).unwrap_or(0);
+                if after != lamports {
+                    let before = lamports as f64 / LAMPORTS_PER_SOL;
+                    let after = after as f64 / LAMPORTS_PER_SOL;
+                    balance_changes.push(BalanceChange {
+                        mint: SOL_MINT.to_string(),
+                        symbol: Some("SOL".to_string()),
+                        account: wallet.to_string(),
+                        before,
+                        after,
+                        change: after - before,
+                    });
+                }
+            }
+
+            // Token accounts; a null post-state means the account was closed
+            for (account, post) in token_accounts.iter().zip(post_accounts.iter().skip(1)) {
+                let after = post["data"]["parsed"]["info"]["tokenAmount"]["uiAmount"]
+                    .as_f64()
+                    .unwrap_or(0.0);
+                if (after - account.amount).abs() > f64::EPSILON {
+                    balance_changes.push(BalanceChange {
+                        mint: account.mint.clone(),
+                        symbol: mint_to_symbol(&account.mint),
+                        account: account.address.clone(),
+                        before: account.amount,
+                        after,
+                        change: after - account.amount,
+                    });
+                }
+            }
+        }
+
+        Ok(SimulationResult {
+            success: error.is_none(),
+            units_consumed: value.get("unitsConsumed").and_then(|v| v.as_u64()),
+            balance_changes,
+            reason: error.as_ref().map(|e| explain_failure(e, &logs)),
+            error,
+            logs,
+        })
+    }
+}
+
+/// Turn a TransactionError plus program logs into a message a user can act on
+pub fn explain_failure(error: &Value, logs: &[String]) -> String {
+    let log_contains = |needle: &str| logs.iter().any(|l| l.to_lowercase().contains(needle));
+
+    if let Some(name) = error.as_str() {
+        return match name {
+            "InsufficientFundsForFee" => "Not enough SOL to pay the transaction fee".to_string(),
+            "AccountNotFound" => "The fee payer account does not exist (fund it with SOL first)".to_string(),
+            "BlockhashNotFound" => "The transaction's blockhash has expired; rebuild and try again".to_string(),
+            "AlreadyProcessed" => "This transaction has already been processed".to_string(),
+            other => format!("Transaction rejected: {}", other),
+        };
+    }
+
+    if log_contains("slippage") || log_contains("0x1771") {
+        return "Price moved beyond your slippage tolerance; refresh the quote or raise slippage".to_string();
+    }
+    if log_contains("insufficient funds") || log_contains("insufficient lamports") {
+        return "Insufficient balance for this swap".to_string();
+    }
+    if log_contains("exceeded cus meter") || log_contains("computational budget exceeded") {
+        return "The transaction ran out of compute units".to_string();
+    }
+
+    if let Some(instruction_error) = error.get("InstructionError").and_then(|e| e.as_array()) {
+        let index = instruction_error.first().and_then(|v| v.as_u64()).unwrap_or(0);
+        let detail = match instruction_error.get(1) {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.get("Custom").map(|c| format!("custom program error {}", c)).unwrap_or_else(|| other.to_string()),
+            None => "unknown error".to_string(),
+        };
+        return format!("Instruction {} failed: {}", index, detail);
+    }
+
+    format!("Transaction failed: {}", error)
+}
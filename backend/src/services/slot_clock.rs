@@ -0,0 +1,90 @@
+// Slot clock service - converts between slots and block times, caching getBlockTime lookups
+
+use crate::services::solana::SolanaService;
+use anyhow::Result;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// Upper bound on cached slot → block time entries
+const MAX_CACHED_SLOTS: usize = 50_000;
+/// Nominal slot duration used to estimate slots from timestamps
+const SLOT_DURATION_MS: i64 = 400;
+/// Refinement rounds when searching for the slot at a timestamp
+const MAX_REFINEMENTS: usize = 8;
+/// Neighbouring slots tried when a probe lands on a skipped slot
+const MAX_SKIPPED_PROBES: u64 = 8;
+
+pub struct SlotClockService {
+    solana: SolanaService,
+    // Block times of finalized slots never change, so entries don't expire
+    cache: RwLock<HashMap<u64, Option<i64>>>,
+}
+
+impl SlotClockService {
+    pub fn new(solana: SolanaService) -> Self {
+        Self {
+            solana,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Block time (unix seconds) of a slot; None if the slot was skipped or is unavailable
+    pub async fn block_time(&self, slot: u64) -> Result<Option<i64>> {
+        if let Some(block_time) = self.cache.read().await.get(&slot) {
+            return Ok(*block_time);
+        }
+
+        let block_time = self.solana.get_block_time(slot).await?;
+        let mut cache = self.cache.write().await;
+        if cache.len() >= MAX_CACHED_SLOTS {
+            cache.clear();
+        }
+        cache.insert(slot, block_time);
+        Ok(block_time)
+    }
+
+    /// Closest slot at or after `slot` that produced a block, with its block time
+    async fn next_block(&self, slot: u64) -> Result<Option<(u64, i64)>> {
+        for candidate in slot..slot + MAX_SKIPPED_PROBES {
+            if let Some(block_time) = self.block_time(candidate).await? {
+                return Ok(Some((candidate, block_time)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Approximate slot produced at `timestamp` (unix seconds), with its block time
+    /// Block times have one-second resolution, so the result is the first block found
+    /// whose time matches the target, starting from an estimate at 400ms per slot
+    pub async fn slot_at(&self, timestamp: i64) -> Result<(u64, i64)> {
+        let latest = self.solana.get_slot().await?;
+        let (latest, latest_time) = self
+            .next_block(latest.saturating_sub(MAX_SKIPPED_PROBES))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No recent block time available"))?;
+        if timestamp >= latest_time {
+            return Ok((latest, latest_time));
+        }
+
+        let mut best = (latest, latest_time);
+        let mut estimate = latest.saturating_sub(((latest_time - timestamp) * 1000 / SLOT_DURATION_MS) as u64);
+        for _ in 0..MAX_REFINEMENTS {
+            let Some((slot, block_time)) = self.next_block(estimate).await? else {
+                break;
+            };
+            if (block_time - timestamp).abs() < (best.1 - timestamp).abs() {
+                best = (slot, block_time);
+            }
+            if block_time == timestamp {
+                break;
+            }
+            let offset = (timestamp - block_time) * 1000 / SLOT_DURATION_MS;
+            let next = (slot as i64 + offset).clamp(0, latest as i64) as u64;
+            if next == estimate {
+                break;
+            }
+            estimate = next;
+        }
+        Ok(best)
+    }
+}
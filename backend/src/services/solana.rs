@@ -1,8 +1,8 @@
 // Solana service module - QuickNode RPC integration
 
+use crate::utils::chaos::{self, Fault, FaultPoint};
 use anyhow::Result;
 use serde::Deserialize;
-use std::env;
 
 #[derive(Debug, Clone)]
 pub struct SolanaService {
@@ -21,17 +21,277 @@ pub struct SignatureInfo {
     pub block_time: Option<i64>,
 }
 
+#[derive(Debug, Clone)]
+pub struct TokenAccountBalance {
+    pub address: String,
+    pub mint: String,
+    pub amount: f64,
+    pub decimals: u8,
+}
+
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
 impl SolanaService {
-    pub fn new() -> Result<Self> {
-        let rpc_url = env::var("QUICKNODE_RPC_URL")
-            .expect("QUICKNODE_RPC_URL must be set in environment variables");
-        
-        Ok(Self { rpc_url })
+    /// `rpc_url` is QUICKNODE_RPC_URL
+    pub fn new(rpc_url: &str) -> Self {
+        Self { rpc_url: rpc_url.to_string() }
+    }
+    
+    /// The endpoint's URL (its WebSocket subscriptions use the same host)
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_url
     }
 
 
+    /// Send a JSON-RPC request and return its `result` field
+    async fn rpc_call(&self, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+        match chaos::inject(FaultPoint::Rpc).await {
+            Ok(()) => {}
+            Err(Fault::Drop) => return Err(anyhow::anyhow!("HTTP request failed: {} timed out ({})", method, Fault::Drop)),
+            Err(fault) => return Err(anyhow::anyhow!("RPC {} failed: {}", method, fault)),
+        }
+        let client = reqwest::Client::new();
+        let payload = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params
+        });
+        
+        let response: serde_json::Value = client
+            .post(&self.rpc_url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("HTTP request failed: {}", e))?
+            .json()
+            .await?;
+        
+        if let Some(error) = response.get("error") {
+            return Err(anyhow::anyhow!("RPC {} failed: {}", method, error));
+        }
+        
+        response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("RPC {} returned no result", method))
+    }
+    
+    /// Get native SOL balance (lamports) for an account
+    pub async fn get_balance(&self, owner: &str) -> Result<u64> {
+        let result = self.rpc_call("getBalance", serde_json::json!([owner])).await?;
+        result
+            .get("value")
+            .and_then(|v| v.as_u64())
+            .ok_or_else(|| anyhow::anyhow!("Unexpected getBalance response"))
+    }
+    
+    /// Get SPL token balances held by an owner across the Token and Token-2022 programs
+    pub async fn get_token_accounts_by_owner(&self, owner: &str) -> Result<Vec<TokenAccountBalance>> {
+        let mut balances = Vec::new();
+        
+        for program_id in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+            let result = self.rpc_call(
+                "getTokenAccountsByOwner",
+                serde_json::json!([
+                    owner,
+                    { "programId": program_id },
+                    { "encoding": "jsonParsed" }
+                ]),
+            ).await?;
+            
+            let accounts = result.get("value").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            for account in accounts {
+                let info = &account["account"]["data"]["parsed"]["info"];
+                let mint = match info.get("mint").and_then(|v| v.as_str()) {
+                    Some(mint) => mint.to_string(),
+                    None => continue,
+                };
+                let token_amount = &info["tokenAmount"];
+                balances.push(TokenAccountBalance {
+                    address: account.get("pubkey").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                    mint,
+                    amount: token_amount.get("uiAmount").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                    decimals: token_amount.get("decimals").and_then(|v| v.as_u64()).unwrap_or(0) as u8,
+                });
+            }
+        }
+        
+        Ok(balances)
+    }
+    
+    /// Get an account's jsonParsed data (None if the account doesn't exist)
+    pub async fn get_account_info_parsed(&self, address: &str) -> Result<Option<serde_json::Value>> {
+        let result = self.rpc_call(
+            "getAccountInfo",
+            serde_json::json!([address, { "encoding": "jsonParsed" }]),
+        ).await?;
+        Ok(result.get("value").filter(|v| !v.is_null()).cloned())
+    }
+    
+    /// Get an account's raw data and the slot it was read at (None if the account doesn't exist)
+    pub async fn get_account_data(&self, address: &str) -> Result<Option<(u64, Vec<u8>)>> {
+        use base64::Engine;
+        let result = self.rpc_call(
+            "getAccountInfo",
+            serde_json::json!([address, { "encoding": "base64", "commitment": "confirmed" }]),
+        ).await?;
+        let slot = result["context"]["slot"].as_u64().unwrap_or(0);
+        let Some(data) = result["value"]["data"][0].as_str() else {
+            return Ok(None);
+        };
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| anyhow::anyhow!("Invalid base64 account data: {}", e))?;
+        Ok(Some((slot, bytes)))
+    }
+    
+    /// Get several accounts' jsonParsed data in one call (None for missing accounts)
+    pub async fn get_multiple_accounts_parsed(&self, addresses: &[String]) -> Result<Vec<Option<serde_json::Value>>> {
+        let result = self.rpc_call(
+            "getMultipleAccounts",
+            serde_json::json!([addresses, { "encoding": "jsonParsed" }]),
+        ).await?;
+        Ok(result
+            .get("value")
+            .and_then(|v| v.as_array())
+            .map(|accounts| accounts.iter().map(|a| Some(a.clone()).filter(|a| !a.is_null())).collect())
+            .unwrap_or_default())
+    }
+    
+    /// Get a mint's circulating supply (UI amount)
+    pub async fn get_token_supply(&self, mint: &str) -> Result<f64> {
+        let result = self.rpc_call("getTokenSupply", serde_json::json!([mint])).await?;
+        result["value"]["uiAmount"]
+            .as_f64()
+            .ok_or_else(|| anyhow::anyhow!("Unexpected getTokenSupply response"))
+    }
+    
+    /// Get the 20 largest token accounts of a mint as (token account, UI amount)
+    pub async fn get_token_largest_accounts(&self, mint: &str) -> Result<Vec<(String, f64)>> {
+        let result = self.rpc_call("getTokenLargestAccounts", serde_json::json!([mint])).await?;
+        Ok(result
+            .get("value")
+            .and_then(|v| v.as_array())
+            .map(|accounts| {
+                accounts
+                    .iter()
+                    .filter_map(|a| Some((a["address"].as_str()?.to_string(), a["uiAmount"].as_f64().unwrap_or(0.0))))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+    
+    /// Count token accounts of a mint holding a non-zero balance
+    /// Only the 8-byte amount of each account is fetched, but this is still a full program scan
+    pub async fn count_token_holders(&self, mint: &str, program_id: &str) -> Result<u64> {
+        let mut filters = vec![serde_json::json!({ "memcmp": { "offset": 0, "bytes": mint } })];
+        if program_id == TOKEN_PROGRAM_ID {
+            filters.push(serde_json::json!({ "dataSize": 165 }));
+        }
+        
+        let result = self.rpc_call(
+            "getProgramAccounts",
+            serde_json::json!([
+                program_id,
+                {
+                    "encoding": "base58",
+                    "dataSlice": { "offset": 64, "length": 8 },
+                    "filters": filters
+                }
+            ]),
+        ).await?;
+        
+        // A zero u64 amount encodes to eight '1's in base58
+        Ok(result
+            .as_array()
+            .map(|accounts| {
+                accounts
+                    .iter()
+                    .filter(|a| a["account"]["data"][0].as_str().is_some_and(|data| data != "11111111"))
+                    .count() as u64
+            })
+            .unwrap_or(0))
+    }
+    
+    /// Forward a signed, base64-encoded transaction to the cluster; returns its signature
+    pub async fn send_transaction(&self, transaction: &str) -> Result<String> {
+        let result = self.rpc_call(
+            "sendTransaction",
+            serde_json::json!([
+                transaction,
+                { "encoding": "base64", "preflightCommitment": "processed" }
+            ]),
+        ).await?;
+        result
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow::anyhow!("Unexpected sendTransaction response"))
+    }
+    
+    /// Get the latest finalized slot
+    pub async fn get_slot(&self) -> Result<u64> {
+        let result = self.rpc_call("getSlot", serde_json::json!([{ "commitment": "finalized" }])).await?;
+        result
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("Unexpected getSlot response"))
+    }
+    
+    /// Get the estimated production time (unix seconds) of a slot
+    /// Returns None for skipped slots and slots the node has no block for
+    pub async fn get_block_time(&self, slot: u64) -> Result<Option<i64>> {
+        match self.rpc_call("getBlockTime", serde_json::json!([slot])).await {
+            Ok(result) => Ok(result.as_i64()),
+            // -32004 block not available, -32007 slot skipped, -32009 missing in long-term storage
+            Err(e) if ["-32004", "-32007", "-32009"].iter().any(|code| e.to_string().contains(code)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Get the status of a signature (None if the cluster hasn't seen it yet)
+    pub async fn get_signature_status(&self, signature: &str) -> Result<Option<serde_json::Value>> {
+        let result = self.rpc_call(
+            "getSignatureStatuses",
+            serde_json::json!([[signature], { "searchTransactionHistory": false }]),
+        ).await?;
+        Ok(result
+            .get("value")
+            .and_then(|v| v.get(0))
+            .filter(|status| !status.is_null())
+            .cloned())
+    }
+    
+    /// Simulate a base64-encoded transaction against the latest blockhash, returning
+    /// post-simulation state for `addresses` (jsonParsed) alongside logs and compute units
+    pub async fn simulate_transaction(&self, transaction: &str, addresses: &[String]) -> Result<serde_json::Value> {
+        let result = self.rpc_call(
+            "simulateTransaction",
+            serde_json::json!([
+                transaction,
+                {
+                    "encoding": "base64",
+                    "sigVerify": false,
+                    "replaceRecentBlockhash": true,
+                    "commitment": "processed",
+                    "accounts": { "addresses": addresses, "encoding": "jsonParsed" }
+                }
+            ]),
+        ).await?;
+        result
+            .get("value")
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unexpected simulateTransaction response"))
+    }
+    
     /// Get transaction details by signature
     pub async fn get_transaction(&self, signature: &str) -> Result<Option<serde_json::Value>> {
+        // A dropped request looks like a transaction the node hasn't seen
+        match chaos::inject(FaultPoint::Rpc).await {
+            Ok(()) => {}
+            Err(Fault::Drop) => return Ok(None),
+            Err(fault) => return Err(anyhow::anyhow!("RPC getTransaction failed: {}", fault)),
+        }
         let client = reqwest::Client::new();
         let payload = serde_json::json!({
             "jsonrpc": "2.0",
@@ -82,3 +342,61 @@ impl SolanaService {
 
 }
 
+
+#[cfg(all(test, feature = "chaos"))]
+mod tests {
+    use super::*;
+    use crate::utils::chaos::{configure, FaultRates};
+    use axum::{routing::post, Json, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// A JSON-RPC endpoint answering every request with slot 42; returns its URL and request count
+    async fn rpc_stub() -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        let app = Router::new().route(
+            "/",
+            post(move || async move {
+                counted.fetch_add(1, Ordering::SeqCst);
+                Json(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": 42 }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, requests)
+    }
+
+    fn rates(raw: &str) -> Option<FaultRates> {
+        Some(FaultRates::parse(raw).unwrap())
+    }
+
+    // One test drives the Rpc point, so its settings can't leak into another
+    #[tokio::test]
+    async fn rpc_faults_are_injected_before_the_request() {
+        let (url, requests) = rpc_stub().await;
+        let solana = SolanaService::new(&url);
+
+        configure(FaultPoint::Rpc, rates("error=1"));
+        let error = solana.get_slot().await.unwrap_err().to_string();
+        assert!(error.contains("injected error"), "{}", error);
+
+        configure(FaultPoint::Rpc, rates("drop=1"));
+        let error = solana.get_slot().await.unwrap_err().to_string();
+        assert!(error.contains("timed out"), "{}", error);
+        // Dropped, getTransaction looks like a transaction the node hasn't seen
+        assert!(solana.get_transaction("signature").await.unwrap().is_none());
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        configure(FaultPoint::Rpc, rates("delay=1,delay_ms=50"));
+        let started = Instant::now();
+        assert_eq!(solana.get_slot().await.unwrap(), 42);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        configure(FaultPoint::Rpc, None);
+        assert_eq!(solana.get_slot().await.unwrap(), 42);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,187 @@
+// Volume spike detection - flags minutes whose volume or trade count is far above a pair's recent baseline
+
+use crate::models::schema::CURRENT_SCHEMA_VERSION;
+use crate::models::trade::Trade;
+use crate::services::clickhouse::ClickHouseService;
+use crate::services::memory_budget::{Footprint, MemoryBudget, PairLru};
+use crate::services::notification_templates::NotificationTemplates;
+use crate::websocket::messages::OutboundMessage;
+use crate::websocket::ConnectionManager;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Volume and trade count of one minute
+#[derive(Debug, Clone, Copy, Default)]
+struct MinuteActivity {
+    volume: f64,
+    trades: u64,
+}
+
+/// A pair's open minute and the closed minutes before it (quiet minutes count as zero)
+#[derive(Debug, Default)]
+struct PairActivity {
+    minute: i64,
+    current: MinuteActivity,
+    history: VecDeque<MinuteActivity>,
+    /// The open minute was already reported
+    flagged: bool,
+}
+
+impl PairActivity {
+    /// Close the open minute and any quiet minutes up to `minute`
+    fn roll(&mut self, minute: i64, baseline_minutes: usize) {
+        self.history.push_back(self.current);
+        let quiet = (minute - self.minute - 1).clamp(0, baseline_minutes as i64) as usize;
+        self.history.extend(std::iter::repeat_n(MinuteActivity::default(), quiet));
+        while self.history.len() > baseline_minutes {
+            self.history.pop_front();
+        }
+        self.minute = minute;
+        self.current = MinuteActivity::default();
+        self.flagged = false;
+    }
+}
+
+impl Footprint for PairActivity {
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>() + self.history.capacity() * std::mem::size_of::<MinuteActivity>()
+    }
+}
+
+/// Mean and standard deviation of the baseline minutes
+fn baseline(samples: impl Iterator<Item = f64> + Clone) -> (f64, f64) {
+    let n = samples.clone().count().max(1) as f64;
+    let mean = samples.clone().sum::<f64>() / n;
+    let variance = samples.map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    (mean, variance.sqrt())
+}
+
+/// z-score of `value`; None when the baseline has no spread at all (e.g. no volume in any minute)
+fn z_score(value: f64, mean: f64, std: f64) -> Option<f64> {
+    (std > 0.0).then(|| (value - mean) / std)
+}
+
+/// Spike thresholds, read with the rest of the Config
+#[derive(Debug, Clone, Copy)]
+pub struct SpikeSettings {
+    /// SPIKE_Z_THRESHOLD, default 4
+    pub threshold: f64,
+    /// SPIKE_BASELINE_MINUTES, default 60 (at least 2)
+    pub baseline_minutes: usize,
+    /// SPIKE_MIN_BASELINE_MINUTES, default 15 (2 up to the baseline)
+    pub min_baseline_minutes: usize,
+    /// SPIKE_MIN_TRADES, default 5
+    pub min_trades: u64,
+}
+
+pub struct SpikeDetector {
+    ws_manager: Arc<ConnectionManager>,
+    clickhouse: Arc<ClickHouseService>,
+    templates: Arc<NotificationTemplates>,
+    /// z-score at which a minute is flagged
+    threshold: f64,
+    /// Closed minutes in the rolling baseline
+    baseline_minutes: usize,
+    /// Closed minutes needed before a pair is checked at all (avoids flagging right after startup)
+    min_baseline_minutes: usize,
+    /// Trades needed in a minute before it can be flagged
+    min_trades: u64,
+    pairs: Mutex<PairLru<String, PairActivity>>,
+}
+
+impl SpikeDetector {
+    pub fn new(
+        ws_manager: Arc<ConnectionManager>,
+        clickhouse: Arc<ClickHouseService>,
+        memory_budget: Arc<MemoryBudget>,
+        templates: Arc<NotificationTemplates>,
+        settings: SpikeSettings,
+    ) -> Self {
+        Self {
+            ws_manager,
+            clickhouse,
+            templates,
+            threshold: settings.threshold,
+            baseline_minutes: settings.baseline_minutes,
+            min_baseline_minutes: settings.min_baseline_minutes,
+            min_trades: settings.min_trades,
+            pairs: Mutex::new(PairLru::new("spikes", memory_budget)),
+        }
+    }
+
+    /// Count a broadcast trade; the first time its minute crosses the threshold, a `volume_spike`
+    /// is sent on the `alerts` channel and stored in market_alerts
+    pub async fn record(&self, trade: &Trade) {
+        let pair = format!("{}/{}", trade.base_symbol, trade.quote_symbol);
+        let minute = trade.timestamp.timestamp().div_euclid(60);
+
+        let spike = self.pairs.lock().await.upsert(
+            pair.clone(),
+            || PairActivity { minute, ..Default::default() },
+            |activity| self.observe(activity, &pair, minute, trade),
+        );
+        let Some(spike) = spike else { return };
+        let spike = self.templates.with_message("volume_spike", "ws", &spike);
+
+        println!("📈 [Spikes] Volume spike on {}: {} trades, volume {} this minute", pair, spike["trades"], spike["volume"]);
+        self.ws_manager.broadcast(OutboundMessage::new("alerts", Some(pair.clone()), spike.to_string())).await;
+
+        let clickhouse = self.clickhouse.clone();
+        tokio::spawn(async move {
+            if let Err(e) = clickhouse.store_market_alert("volume_spike", &pair, &spike).await {
+                eprintln!("⚠️  [Spikes] Failed to store volume spike for {}: {}", pair, e);
+            }
+        });
+    }
+
+    /// Count a trade into its pair's open minute; the `volume_spike` alert when this trade made it cross the threshold
+    fn observe(&self, activity: &mut PairActivity, pair: &str, minute: i64, trade: &Trade) -> Option<serde_json::Value> {
+        // Late trades for an already closed minute don't change the baseline
+        if minute < activity.minute {
+            return None;
+        }
+        if minute > activity.minute {
+            activity.roll(minute, self.baseline_minutes);
+        }
+        activity.current.volume += trade.amount;
+        activity.current.trades += 1;
+
+        if activity.flagged
+            || activity.history.len() < self.min_baseline_minutes
+            || activity.current.trades < self.min_trades
+        {
+            return None;
+        }
+
+        let (mean_volume, std_volume) = baseline(activity.history.iter().map(|m| m.volume));
+        let (mean_trades, std_trades) = baseline(activity.history.iter().map(|m| m.trades as f64));
+        // Floors keep a very steady baseline from turning every wobble into a spike;
+        // trade counts are whole numbers, so a spread under one trade isn't meaningful
+        let volume_z = z_score(activity.current.volume, mean_volume, std_volume.max(mean_volume * 0.1));
+        let trades_z = z_score(activity.current.trades as f64, mean_trades, std_trades.max(1.0));
+        if ![volume_z, trades_z].into_iter().flatten().any(|z| z >= self.threshold) {
+            return None;
+        }
+        activity.flagged = true;
+
+        Some(json!({
+            "type": "volume_spike",
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "pair": pair,
+            "minute": DateTime::<Utc>::from_timestamp(minute * 60, 0).map(|t| t.to_rfc3339()),
+            "volume": activity.current.volume,
+            "trades": activity.current.trades,
+            "volume_z": volume_z,
+            "trades_z": trades_z,
+            "baseline": {
+                "minutes": activity.history.len(),
+                "mean_volume": mean_volume,
+                "mean_trades": mean_trades,
+            },
+            "detected_at": Utc::now().to_rfc3339(),
+        }))
+    }
+}
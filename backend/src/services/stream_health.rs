@@ -0,0 +1,203 @@
+// Stream health - connection, reconnect backoff and per-program subscription state of the
+// QuickNode log stream, for /api/health/stream
+
+use crate::services::dex_programs::DexProgram;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// First reconnect delay; doubles per consecutive failure up to MAX_BACKOFF
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A connection that stayed up this long resets the backoff
+const STABLE_CONNECTION: Duration = Duration::from_secs(30);
+
+/// Where a program's logsSubscribe stands on the current (or last) connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    /// Requested, waiting for the subscription id
+    Pending,
+    Subscribed,
+    /// Was subscribed when the connection dropped; re-established after the reconnect
+    Lost,
+    /// Unsubscribed because the venue was disabled or removed
+    Unsubscribed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgramSubscription {
+    pub status: SubscriptionStatus,
+    pub subscription_id: Option<u64>,
+    /// When the current subscription was confirmed
+    pub subscribed_at: Option<DateTime<Utc>>,
+    /// Times the subscription was confirmed again after a reconnect
+    pub reestablished: u64,
+}
+
+#[derive(Debug, Default)]
+struct HealthState {
+    /// The trade stream was started; a degraded process starts it once the RPC answers
+    ingesting: bool,
+    connected: bool,
+    connected_since: Option<Instant>,
+    connected_at: Option<DateTime<Utc>>,
+    disconnected_at: Option<DateTime<Utc>>,
+    last_message_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    /// Failed or short-lived connections since the last stable one
+    consecutive_failures: u32,
+    total_reconnects: u64,
+    current_backoff: Option<Duration>,
+    next_attempt_at: Option<DateTime<Utc>>,
+    subscriptions: HashMap<String, ProgramSubscription>,
+}
+
+pub struct StreamHealth {
+    state: Mutex<HealthState>,
+}
+
+/// Exponential backoff with equal jitter: half the capped delay, plus a random part of the other half
+fn backoff_delay(failures: u32) -> Duration {
+    let exponential = BASE_BACKOFF.saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)));
+    let capped = exponential.min(MAX_BACKOFF);
+    let half = capped / 2;
+    half + Duration::from_millis(rand::thread_rng().gen_range(0..=half.as_millis() as u64))
+}
+
+impl StreamHealth {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(HealthState::default()),
+        }
+    }
+
+    /// Ingestion started; before this, a stream that is down was never meant to be up
+    pub async fn ingestion_started(&self) {
+        self.state.lock().await.ingesting = true;
+    }
+
+    pub async fn is_ingesting(&self) -> bool {
+        self.state.lock().await.ingesting
+    }
+
+    /// The WebSocket connected; subscriptions are about to be requested again
+    pub async fn connected(&self) {
+        let mut state = self.state.lock().await;
+        state.connected = true;
+        state.connected_since = Some(Instant::now());
+        state.connected_at = Some(Utc::now());
+        state.current_backoff = None;
+        state.next_attempt_at = None;
+    }
+
+    /// The connection closed or failed; returns how long to wait before reconnecting
+    /// Subscriptions it held are marked lost until they are confirmed again
+    pub async fn disconnected(&self, error: Option<String>) -> Duration {
+        let mut state = self.state.lock().await;
+        let stable = state
+            .connected_since
+            .is_some_and(|since| state.connected && since.elapsed() >= STABLE_CONNECTION);
+        state.consecutive_failures = if stable { 1 } else { state.consecutive_failures + 1 };
+        state.total_reconnects += 1;
+        state.connected = false;
+        state.connected_since = None;
+        state.disconnected_at = Some(Utc::now());
+        if error.is_some() {
+            state.last_error = error;
+        }
+        for subscription in state.subscriptions.values_mut() {
+            if matches!(subscription.status, SubscriptionStatus::Subscribed | SubscriptionStatus::Pending) {
+                subscription.status = SubscriptionStatus::Lost;
+                subscription.subscription_id = None;
+            }
+        }
+
+        let delay = backoff_delay(state.consecutive_failures);
+        state.current_backoff = Some(delay);
+        state.next_attempt_at = chrono::Duration::from_std(delay).ok().map(|d| Utc::now() + d);
+        delay
+    }
+
+    pub async fn is_connected(&self) -> bool {
+        self.state.lock().await.connected
+    }
+
+    pub async fn message_received(&self) {
+        self.state.lock().await.last_message_at = Some(Utc::now());
+    }
+
+    pub async fn subscription_requested(&self, program_id: &str) {
+        let mut state = self.state.lock().await;
+        let subscription = state
+            .subscriptions
+            .entry(program_id.to_string())
+            .or_insert(ProgramSubscription {
+                status: SubscriptionStatus::Pending,
+                subscription_id: None,
+                subscribed_at: None,
+                reestablished: 0,
+            });
+        // Lost subscriptions keep that status until confirmed, so re-establishment shows
+        if subscription.status != SubscriptionStatus::Lost {
+            subscription.status = SubscriptionStatus::Pending;
+        }
+    }
+
+    pub async fn subscription_confirmed(&self, program_id: &str, subscription_id: u64) {
+        let mut state = self.state.lock().await;
+        if let Some(subscription) = state.subscriptions.get_mut(program_id) {
+            if subscription.status == SubscriptionStatus::Lost {
+                subscription.reestablished += 1;
+            }
+            subscription.status = SubscriptionStatus::Subscribed;
+            subscription.subscription_id = Some(subscription_id);
+            subscription.subscribed_at = Some(Utc::now());
+        }
+    }
+
+    pub async fn subscription_dropped(&self, program_id: &str) {
+        let mut state = self.state.lock().await;
+        if let Some(subscription) = state.subscriptions.get_mut(program_id) {
+            subscription.status = SubscriptionStatus::Unsubscribed;
+            subscription.subscription_id = None;
+        }
+    }
+
+    /// Connection state and backoff, with the subscription of each of `programs` (null when this
+    /// process never subscribed to it)
+    pub async fn report(&self, programs: &[DexProgram]) -> serde_json::Value {
+        let state = self.state.lock().await;
+        let programs: Vec<serde_json::Value> = programs
+            .iter()
+            .map(|program| {
+                serde_json::json!({
+                    "program_id": program.program_id,
+                    "name": program.name,
+                    "enabled": program.enabled,
+                    "ingested_here": program.ingested_here,
+                    "subscription": state.subscriptions.get(&program.program_id),
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "ingesting": state.ingesting,
+            "connected": state.connected,
+            "connected_at": state.connected_at,
+            "disconnected_at": state.disconnected_at,
+            "last_message_at": state.last_message_at,
+            "seconds_since_last_message": state
+                .last_message_at
+                .map(|at| (Utc::now() - at).num_milliseconds() as f64 / 1000.0),
+            "last_error": state.last_error,
+            "consecutive_failures": state.consecutive_failures,
+            "total_reconnects": state.total_reconnects,
+            "current_backoff_ms": state.current_backoff.map(|d| d.as_millis() as u64),
+            "next_attempt_at": state.next_attempt_at,
+            "programs": programs,
+        })
+    }
+}
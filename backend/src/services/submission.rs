@@ -0,0 +1,114 @@
+// Transaction submission service - relays user-signed transactions and tracks confirmation
+
+use crate::services::solana::SolanaService;
+use crate::websocket::ConnectionManager;
+use anyhow::Result;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+
+/// Base64 length of the largest possible transaction (1232-byte packet)
+pub const MAX_TRANSACTION_BASE64_LEN: usize = 1644;
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+/// A blockhash is valid for ~60-90s, so a signature not finalized by then never will be
+const TRACK_TIMEOUT: Duration = Duration::from_secs(90);
+
+pub struct SubmissionService {
+    solana: SolanaService,
+    ws_manager: Arc<ConnectionManager>,
+}
+
+impl SubmissionService {
+    pub fn new(solana: SolanaService, ws_manager: Arc<ConnectionManager>) -> Self {
+        Self { solana, ws_manager }
+    }
+
+    /// Send a signed transaction and track it in the background, pushing
+    /// `tx_status` events to the wallet's authenticated WS connections
+    pub async fn submit(self: &Arc<Self>, wallet: String, transaction: &str) -> Result<String> {
+        let signature = self.solana.send_transaction(transaction).await?;
+        println!("📨 [Submit] Relayed transaction {} for {}", signature, wallet);
+
+        let service = self.clone();
+        let sig = signature.clone();
+        tokio::spawn(async move {
+            service.track(&wallet, &sig).await;
+        });
+
+        Ok(signature)
+    }
+
+    /// Poll the signature status until finalized, failed, or expired
+    async fn track(&self, wallet: &str, signature: &str) {
+        let started = Instant::now();
+        let mut last_status: Option<String> = None;
+
+        while started.elapsed() < TRACK_TIMEOUT {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let status = match self.solana.get_signature_status(signature).await {
+                Ok(Some(status)) => status,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("⚠️  [Submit] Status poll failed for {}: {}", signature, e);
+                    continue;
+                }
+            };
+            let slot = status.get("slot").and_then(|v| v.as_u64());
+
+            if let Some(err) = status.get("err").filter(|e| !e.is_null()) {
+                let logs = self.failure_logs(signature).await;
+                self.push(wallet, json!({
+                    "type": "tx_status",
+                    "signature": signature,
+                    "status": "failed",
+                    "slot": slot,
+                    "error": err,
+                    "logs": logs,
+                })).await;
+                return;
+            }
+
+            let confirmation = status
+                .get("confirmationStatus")
+                .and_then(|v| v.as_str())
+                .unwrap_or("processed")
+                .to_string();
+            if last_status.as_deref() != Some(confirmation.as_str()) {
+                self.push(wallet, json!({
+                    "type": "tx_status",
+                    "signature": signature,
+                    "status": confirmation,
+                    "slot": slot,
+                })).await;
+                if confirmation == "finalized" {
+                    return;
+                }
+                last_status = Some(confirmation);
+            }
+        }
+
+        self.push(wallet, json!({
+            "type": "tx_status",
+            "signature": signature,
+            "status": "expired",
+            "last_status": last_status,
+        })).await;
+    }
+
+    /// Program logs of a failed transaction, if the RPC has them yet
+    async fn failure_logs(&self, signature: &str) -> Vec<String> {
+        match self.solana.get_transaction(signature).await {
+            Ok(Some(tx)) => tx["meta"]["logMessages"]
+                .as_array()
+                .map(|logs| logs.iter().filter_map(|l| l.as_str().map(|s| s.to_string())).collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    async fn push(&self, wallet: &str, event: serde_json::Value) {
+        println!("📡 [Submit] {} -> {}", event["signature"], event["status"]);
+        self.ws_manager.send_to_wallet(wallet, &event.to_string(), None).await;
+    }
+}
@@ -0,0 +1,464 @@
+// Swap builder service - Jupiter quotes turned into unsigned transactions, with slippage policy
+
+use crate::services::clickhouse::{ClickHouseService, PlatformFeeRow, SwapQuoteRow};
+use crate::services::jupiter::{JupiterService, QuoteResponse};
+use crate::services::pair_mapping::{mint_decimals, symbol_to_mint};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+const DEFAULT_SLIPPAGE_BPS: u16 = 50;
+
+/// Server-side limits applied to every quote before a transaction is built; MAX_SLIPPAGE_BPS (default 300),
+/// MAX_PRICE_IMPACT_PCT (default 5) and WARN_PRICE_IMPACT_PCT (default 1), read with the rest of the Config
+#[derive(Debug, Clone, Serialize)]
+pub struct SlippagePolicy {
+    /// Requests asking for more slippage than this are rejected
+    pub max_slippage_bps: u16,
+    /// Quotes with a larger price impact (percent) are rejected
+    pub max_price_impact_pct: f64,
+    /// Quotes above this price impact (percent) are built but carry a warning
+    pub warn_price_impact_pct: f64,
+}
+
+/// Platform fee charged on routed volume and the token account that collects it
+#[derive(Debug, Clone)]
+pub struct FeeConfig {
+    /// Name the fee is attributed to in the `platform_fees` ledger
+    pub partner: String,
+    pub fee_bps: u16,
+    pub fee_account: String,
+}
+
+/// Deployment-wide platform fee plus per-API-key partner overrides
+#[derive(Debug, Clone, Default)]
+pub struct FeeSchedule {
+    default: Option<FeeConfig>,
+    by_api_key: HashMap<String, FeeConfig>,
+}
+
+impl FeeSchedule {
+    /// The deployment fee from `fee_bps` (PLATFORM_FEE_BPS) and `fee_account` (PLATFORM_FEE_ACCOUNT), plus
+    /// per-key overrides from `partner_fees` (PARTNER_FEES=name:api_key:fee_bps:fee_account,...)
+    /// An error names the first malformed PARTNER_FEES entry
+    pub fn new(fee_bps: u16, fee_account: Option<String>, partner_fees: &str) -> Result<Self, String> {
+        let default = fee_account.filter(|_| fee_bps > 0).map(|fee_account| FeeConfig {
+            partner: "default".to_string(),
+            fee_bps,
+            fee_account,
+        });
+
+        let mut by_api_key = HashMap::new();
+        for entry in partner_fees.split(',').filter(|e| !e.trim().is_empty()) {
+            let parts: Vec<&str> = entry.trim().split(':').collect();
+            match parts.as_slice() {
+                [partner, api_key, fee_bps, fee_account] if fee_bps.parse::<u16>().is_ok() => {
+                    by_api_key.insert(api_key.to_string(), FeeConfig {
+                        partner: partner.to_string(),
+                        fee_bps: fee_bps.parse().unwrap_or(0),
+                        fee_account: fee_account.to_string(),
+                    });
+                }
+                _ => return Err(format!("'{}' is not name:api_key:fee_bps:fee_account", entry.trim())),
+            }
+        }
+
+        Ok(Self { default, by_api_key })
+    }
+
+    /// Fee for a request: the partner's if the API key is known, else the deployment default
+    pub fn for_api_key(&self, api_key: Option<&str>) -> Option<&FeeConfig> {
+        api_key
+            .and_then(|key| self.by_api_key.get(key))
+            .or(self.default.as_ref())
+            .filter(|fee| fee.fee_bps > 0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwapRequest {
+    /// Mint address or supported symbol (e.g. "SOL")
+    pub input: String,
+    pub output: String,
+    /// Input amount in base units (lamports for SOL)
+    pub amount: u64,
+    pub slippage_bps: Option<u16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SwapBuild {
+    pub quote_id: String,
+    /// Unsigned transaction, base64-encoded, for the wallet to sign
+    pub swap_transaction: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub min_out_amount: u64,
+    pub slippage_bps: u16,
+    pub price_impact_pct: f64,
+    pub route: Vec<String>,
+    pub platform_fee: Option<PlatformFee>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformFee {
+    pub fee_bps: u16,
+    pub amount: u64,
+    pub mint: String,
+}
+
+/// One hop of a quote's route
+#[derive(Debug, Serialize)]
+pub struct QuoteLeg {
+    /// AMM name (e.g. "Raydium CLMM") and pool address
+    pub label: String,
+    pub amm_key: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub fee_amount: u64,
+    pub fee_mint: String,
+    /// Share of the input routed through this leg
+    pub percent: u8,
+}
+
+/// A quote previewed without building a transaction
+#[derive(Debug, Serialize)]
+pub struct SwapQuote {
+    pub input_mint: String,
+    pub output_mint: String,
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub min_out_amount: u64,
+    pub slippage_bps: u16,
+    pub price_impact_pct: f64,
+    /// Output tokens received per input token, in UI units; None when either mint's decimals are unknown
+    pub effective_price: Option<f64>,
+    pub route: Vec<String>,
+    pub legs: Vec<QuoteLeg>,
+    pub platform_fee: Option<PlatformFee>,
+    pub warnings: Vec<String>,
+}
+
+pub enum SwapError {
+    /// The request or quote violates the slippage policy
+    Rejected(String),
+    /// Jupiter or the RPC failed
+    Upstream(anyhow::Error),
+}
+
+/// A quote's price impact checked against the policy
+#[derive(Debug)]
+struct PriceImpact {
+    pct: f64,
+    /// Why the impact is over MAX_PRICE_IMPACT_PCT; building such a swap is rejected
+    over_limit: Option<String>,
+    /// Over WARN_PRICE_IMPACT_PCT
+    high: bool,
+}
+
+impl SlippagePolicy {
+    /// Read a quote's price impact (Jupiter reports it as a fraction) and check it against the limits
+    /// A value that doesn't parse is an error, since the limits can't be checked against it
+    fn check_price_impact(&self, raw: &str) -> Result<PriceImpact, String> {
+        let pct = raw
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|fraction| fraction.is_finite())
+            .map(|fraction| fraction * 100.0)
+            .ok_or_else(|| format!("Jupiter returned an unreadable price impact '{}'; the quote can't be checked", raw))?;
+        let over_limit = (pct > self.max_price_impact_pct)
+            .then(|| format!("Price impact {:.2}% exceeds the maximum of {:.2}%", pct, self.max_price_impact_pct));
+        Ok(PriceImpact { pct, over_limit, high: pct > self.warn_price_impact_pct })
+    }
+}
+
+/// Request parameters shared by every audit record of one quote
+struct QuoteContext<'a> {
+    quote_id: String,
+    wallet: &'a str,
+    input_mint: String,
+    output_mint: String,
+    amount: u64,
+    slippage_bps: u16,
+}
+
+pub struct SwapService {
+    jupiter: JupiterService,
+    clickhouse: Arc<ClickHouseService>,
+    policy: SlippagePolicy,
+    fees: FeeSchedule,
+}
+
+impl SwapService {
+    pub fn new(clickhouse: Arc<ClickHouseService>, jupiter: JupiterService, policy: SlippagePolicy, fees: FeeSchedule) -> Result<Self> {
+        println!("🛡️  [Swap] Slippage policy: {:?}", policy);
+        println!(
+            "💸 [Swap] Platform fee: {} bps, {} partner override(s)",
+            fees.default.as_ref().map(|f| f.fee_bps).unwrap_or(0),
+            fees.by_api_key.len()
+        );
+
+        Ok(Self {
+            jupiter,
+            clickhouse,
+            policy,
+            fees,
+        })
+    }
+
+    /// Quote, enforce the slippage policy, and build an unsigned swap transaction
+    /// `api_key` selects a partner fee override, if configured
+    pub async fn build(&self, wallet: &str, api_key: Option<&str>, request: SwapRequest) -> Result<SwapBuild, SwapError> {
+        let fee = self.fees.for_api_key(api_key);
+        let ctx = QuoteContext {
+            quote_id: Uuid::new_v4().to_string(),
+            wallet,
+            input_mint: resolve_mint(&request.input),
+            output_mint: resolve_mint(&request.output),
+            amount: request.amount,
+            slippage_bps: request.slippage_bps.unwrap_or(DEFAULT_SLIPPAGE_BPS),
+        };
+        let slippage_bps = ctx.slippage_bps;
+
+        if slippage_bps > self.policy.max_slippage_bps {
+            let reason = format!(
+                "Requested slippage {} bps exceeds the maximum of {} bps",
+                slippage_bps, self.policy.max_slippage_bps
+            );
+            self.log_quote(&ctx, None, "rejected", &reason).await;
+            return Err(SwapError::Rejected(reason));
+        }
+
+        let quote = self
+            .jupiter
+            .get_quote(&ctx.input_mint, &ctx.output_mint, ctx.amount, slippage_bps, fee.map(|f| f.fee_bps))
+            .await
+            .map_err(SwapError::Upstream)?;
+
+        let impact = match self.policy.check_price_impact(&quote.price_impact_pct) {
+            Ok(impact) => impact,
+            Err(reason) => {
+                self.log_quote(&ctx, Some(&quote), "rejected", &reason).await;
+                return Err(SwapError::Upstream(anyhow::anyhow!(reason)));
+            }
+        };
+        if let Some(reason) = impact.over_limit {
+            self.log_quote(&ctx, Some(&quote), "rejected", &reason).await;
+            return Err(SwapError::Rejected(reason));
+        }
+        let price_impact_pct = impact.pct;
+
+        let mut warnings = Vec::new();
+        if impact.high {
+            warnings.push(format!("High price impact: {:.2}%", price_impact_pct));
+        }
+
+        let swap_transaction = self
+            .jupiter
+            .build_swap_transaction(&quote, wallet, fee.map(|f| f.fee_account.as_str()))
+            .await
+            .map_err(SwapError::Upstream)?;
+
+        self.log_quote(&ctx, Some(&quote), "built", &warnings.join("; ")).await;
+
+        // Jupiter takes the platform fee from the output token on ExactIn swaps
+        let platform_fee = fee.map(|fee| PlatformFee {
+            fee_bps: fee.fee_bps,
+            amount: quote.platform_fee.as_ref().and_then(|f| f.amount.parse().ok()).unwrap_or(0),
+            mint: ctx.output_mint.clone(),
+        });
+        if let (Some(fee), Some(accrued)) = (fee, &platform_fee) {
+            self.record_fee(&ctx, fee, accrued).await;
+        }
+
+        Ok(SwapBuild {
+            quote_id: ctx.quote_id,
+            swap_transaction,
+            in_amount: quote.in_amount.parse().unwrap_or(request.amount),
+            out_amount: quote.out_amount.parse().unwrap_or(0),
+            min_out_amount: quote.other_amount_threshold.parse().unwrap_or(0),
+            slippage_bps,
+            price_impact_pct,
+            route: route_labels(&quote),
+            platform_fee,
+            input_mint: ctx.input_mint,
+            output_mint: ctx.output_mint,
+            warnings,
+        })
+    }
+
+    /// Quote a swap for preview; no transaction is built and nothing is logged
+    /// Requests over the slippage limit are rejected as on build, while quotes over the price impact
+    /// limit are returned with a warning that building them will be rejected
+    pub async fn quote(&self, api_key: Option<&str>, request: SwapRequest) -> Result<SwapQuote, SwapError> {
+        let fee = self.fees.for_api_key(api_key);
+        let input_mint = resolve_mint(&request.input);
+        let output_mint = resolve_mint(&request.output);
+        let slippage_bps = request.slippage_bps.unwrap_or(DEFAULT_SLIPPAGE_BPS);
+
+        if slippage_bps > self.policy.max_slippage_bps {
+            return Err(SwapError::Rejected(format!(
+                "Requested slippage {} bps exceeds the maximum of {} bps",
+                slippage_bps, self.policy.max_slippage_bps
+            )));
+        }
+
+        let quote = self
+            .jupiter
+            .get_quote(&input_mint, &output_mint, request.amount, slippage_bps, fee.map(|f| f.fee_bps))
+            .await
+            .map_err(SwapError::Upstream)?;
+
+        let impact = self
+            .policy
+            .check_price_impact(&quote.price_impact_pct)
+            .map_err(|reason| SwapError::Upstream(anyhow::anyhow!(reason)))?;
+        let price_impact_pct = impact.pct;
+        let mut warnings = Vec::new();
+        if let Some(reason) = impact.over_limit {
+            warnings.push(format!("{}; this swap will be rejected", reason));
+        } else if impact.high {
+            warnings.push(format!("High price impact: {:.2}%", price_impact_pct));
+        }
+
+        let in_amount = quote.in_amount.parse().unwrap_or(request.amount);
+        let out_amount = quote.out_amount.parse().unwrap_or(0);
+        let effective_price = match (mint_decimals(&input_mint), mint_decimals(&output_mint)) {
+            (Some(in_decimals), Some(out_decimals)) if in_amount > 0 => Some(
+                (out_amount as f64 / 10f64.powi(out_decimals as i32)) / (in_amount as f64 / 10f64.powi(in_decimals as i32)),
+            ),
+            _ => None,
+        };
+        let legs = quote
+            .route_plan
+            .iter()
+            .map(|step| QuoteLeg {
+                label: step.swap_info.label.clone(),
+                amm_key: step.swap_info.amm_key.clone(),
+                input_mint: step.swap_info.input_mint.clone(),
+                output_mint: step.swap_info.output_mint.clone(),
+                in_amount: step.swap_info.in_amount.parse().unwrap_or(0),
+                out_amount: step.swap_info.out_amount.parse().unwrap_or(0),
+                fee_amount: step.swap_info.fee_amount.parse().unwrap_or(0),
+                fee_mint: step.swap_info.fee_mint.clone(),
+                percent: step.percent,
+            })
+            .collect();
+
+        Ok(SwapQuote {
+            in_amount,
+            out_amount,
+            min_out_amount: quote.other_amount_threshold.parse().unwrap_or(0),
+            slippage_bps,
+            price_impact_pct,
+            effective_price,
+            route: route_labels(&quote),
+            legs,
+            platform_fee: fee.map(|fee| PlatformFee {
+                fee_bps: fee.fee_bps,
+                amount: quote.platform_fee.as_ref().and_then(|f| f.amount.parse().ok()).unwrap_or(0),
+                mint: output_mint.clone(),
+            }),
+            input_mint,
+            output_mint,
+            warnings,
+        })
+    }
+
+    /// Record every quote parameter (logs and ClickHouse) so disputes can be reconstructed
+    async fn log_quote(&self, ctx: &QuoteContext<'_>, quote: Option<&QuoteResponse>, status: &str, reason: &str) {
+        let row = SwapQuoteRow {
+            quote_id: ctx.quote_id.clone(),
+            created_at: OffsetDateTime::now_utc(),
+            user_pubkey: ctx.wallet.to_string(),
+            input_mint: ctx.input_mint.clone(),
+            output_mint: ctx.output_mint.clone(),
+            in_amount: quote.and_then(|q| q.in_amount.parse().ok()).unwrap_or(ctx.amount),
+            out_amount: quote.and_then(|q| q.out_amount.parse().ok()).unwrap_or(0),
+            min_out_amount: quote.and_then(|q| q.other_amount_threshold.parse().ok()).unwrap_or(0),
+            slippage_bps: ctx.slippage_bps,
+            price_impact_pct: quote.and_then(|q| q.price_impact_pct.parse::<f64>().ok()).unwrap_or(0.0) * 100.0,
+            route: quote.map(|q| route_labels(q).join(" > ")).unwrap_or_default(),
+            status: status.to_string(),
+            reason: reason.to_string(),
+        };
+
+        println!(
+            "🧾 [Swap] quote={} wallet={} {} -> {} in={} out={} min_out={} slippage={}bps impact={:.4}% route=[{}] status={} {}",
+            row.quote_id, row.user_pubkey, row.input_mint, row.output_mint, row.in_amount, row.out_amount,
+            row.min_out_amount, row.slippage_bps, row.price_impact_pct, row.route, row.status, row.reason
+        );
+
+        if let Err(e) = self.clickhouse.store_swap_quote(&row).await {
+            eprintln!("⚠️  [Swap] Failed to store quote {} in ClickHouse: {}", row.quote_id, e);
+        }
+    }
+
+    /// Add a built swap's platform fee to the partner's ledger
+    async fn record_fee(&self, ctx: &QuoteContext<'_>, fee: &FeeConfig, accrued: &PlatformFee) {
+        let row = PlatformFeeRow {
+            quote_id: ctx.quote_id.clone(),
+            created_at: OffsetDateTime::now_utc(),
+            partner: fee.partner.clone(),
+            user_pubkey: ctx.wallet.to_string(),
+            fee_bps: fee.fee_bps,
+            fee_amount: accrued.amount,
+            fee_mint: accrued.mint.clone(),
+            fee_account: fee.fee_account.clone(),
+        };
+
+        if let Err(e) = self.clickhouse.store_platform_fee(&row).await {
+            eprintln!("⚠️  [Swap] Failed to record platform fee for quote {}: {}", ctx.quote_id, e);
+        }
+    }
+}
+
+fn resolve_mint(input: &str) -> String {
+    symbol_to_mint(&input.to_uppercase())
+        .unwrap_or_else(|| input.to_string())
+}
+
+fn route_labels(quote: &QuoteResponse) -> Vec<String> {
+    quote.route_plan.iter().map(|step| step.swap_info.label.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> SlippagePolicy {
+        SlippagePolicy { max_slippage_bps: 300, max_price_impact_pct: 5.0, warn_price_impact_pct: 1.0 }
+    }
+
+    #[test]
+    fn price_impact_is_a_percentage() {
+        let impact = policy().check_price_impact("0.0123").unwrap();
+        assert_eq!(impact.pct, 1.23);
+        assert!(impact.high);
+        assert!(impact.over_limit.is_none());
+
+        let impact = policy().check_price_impact("0").unwrap();
+        assert!(!impact.high && impact.over_limit.is_none());
+    }
+
+    #[test]
+    fn price_impact_over_the_maximum_is_flagged() {
+        let impact = policy().check_price_impact("0.06").unwrap();
+        assert_eq!(impact.over_limit.as_deref(), Some("Price impact 6.00% exceeds the maximum of 5.00%"));
+    }
+
+    #[test]
+    fn unreadable_price_impact_is_rejected() {
+        for raw in ["", "n/a", "NaN", "inf"] {
+            assert!(policy().check_price_impact(raw).is_err(), "{:?} was accepted", raw);
+        }
+    }
+}
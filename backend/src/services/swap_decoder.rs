@@ -0,0 +1,452 @@
+// Swap instruction decoder - reads a transaction's actual swap legs from Jupiter v6 swap events
+// and the token transfers made by Raydium and Orca swap instructions
+
+use crate::services::solana::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const JUPITER_V6_PROGRAM_ID: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+const RAYDIUM_AMM_V4_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+const ORCA_TOKEN_SWAP_PROGRAM_ID: &str = "9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP";
+const ORCA_WHIRLPOOL_PROGRAM_ID: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+
+/// Prefix of Anchor's self-CPI event instructions (emit_cpi!)
+const ANCHOR_EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+/// Raydium AMM v4 SwapBaseIn / SwapBaseOut instruction tags
+const RAYDIUM_SWAP_TAGS: [u8; 2] = [9, 11];
+/// SPL token-swap Swap instruction tag
+const TOKEN_SWAP_SWAP_TAG: u8 = 1;
+/// SPL Token Transfer / TransferChecked instruction tags
+const TOKEN_TRANSFER_TAG: u8 = 3;
+const TOKEN_TRANSFER_CHECKED_TAG: u8 = 12;
+
+/// Instruction as returned by getTransaction with `json` encoding
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompiledInstruction {
+    #[serde(rename = "programIdIndex")]
+    pub program_id_index: usize,
+    #[serde(default)]
+    pub accounts: Vec<usize>,
+    /// Base58
+    #[serde(default)]
+    pub data: String,
+    /// 1 for top-level instructions; missing on transactions from before it was recorded
+    #[serde(rename = "stackHeight")]
+    pub stack_height: Option<u32>,
+}
+
+/// CPIs made by the top-level instruction at `index`
+#[derive(Debug, Clone, Deserialize)]
+pub struct InnerInstructions {
+    pub index: usize,
+    pub instructions: Vec<CompiledInstruction>,
+}
+
+/// What the decoder needs from a transaction
+pub struct SwapContext<'a> {
+    /// Static account keys followed by loaded writable then readonly addresses
+    pub account_keys: &'a [String],
+    pub instructions: &'a [CompiledInstruction],
+    pub inner_instructions: &'a [InnerInstructions],
+    /// Token account index -> (mint, decimals), from the transaction's token balances
+    pub token_accounts: &'a HashMap<usize, (String, u8)>,
+    /// Accounts before this index signed the transaction
+    pub num_signers: usize,
+}
+
+/// The swap as the trader saw it: what they gave up and what they received, in UI units
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapLegs {
+    pub venue: &'static str,
+    pub input_mint: String,
+    pub input_amount: f64,
+    pub output_mint: String,
+    pub output_amount: f64,
+}
+
+/// First 8 bytes of sha256("<namespace>:<name>"), as Anchor derives instruction and event discriminators
+fn anchor_discriminator(preimage: &str) -> [u8; 8] {
+    let hash = Sha256::digest(preimage.as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<String> {
+    Some(bs58::encode(data.get(offset..offset + 32)?).into_string())
+}
+
+/// Top-level instructions in execution order with their CPIs, each with its stack height
+fn execution_trace<'a>(ctx: &SwapContext<'a>) -> Vec<(u32, &'a CompiledInstruction)> {
+    let mut trace = Vec::new();
+    for (index, instruction) in ctx.instructions.iter().enumerate() {
+        trace.push((1, instruction));
+        for inner in ctx.inner_instructions.iter().filter(|inner| inner.index == index) {
+            // Without recorded stack heights, treat CPIs as direct children of the top-level instruction
+            trace.extend(inner.instructions.iter().map(|ix| (ix.stack_height.unwrap_or(2), ix)));
+        }
+    }
+    trace
+}
+
+/// A token transfer: (source, destination, authority, mint if the instruction names it, raw amount)
+fn token_transfer(ctx: &SwapContext, instruction: &CompiledInstruction) -> Option<(usize, usize, usize, Option<usize>, u64)> {
+    let program = ctx.account_keys.get(instruction.program_id_index)?;
+    if program != TOKEN_PROGRAM_ID && program != TOKEN_2022_PROGRAM_ID {
+        return None;
+    }
+    let data = bs58::decode(&instruction.data).into_vec().ok()?;
+    let accounts = &instruction.accounts;
+    match *data.first()? {
+        TOKEN_TRANSFER_TAG => Some((*accounts.first()?, *accounts.get(1)?, *accounts.get(2)?, None, read_u64(&data, 1)?)),
+        TOKEN_TRANSFER_CHECKED_TAG => Some((*accounts.first()?, *accounts.get(2)?, *accounts.get(3)?, Some(*accounts.get(1)?), read_u64(&data, 1)?)),
+        _ => None,
+    }
+}
+
+/// Net raw amount per mint moved by Jupiter v6 swap events (negative: given up), one event per hop
+fn jupiter_event_legs(ctx: &SwapContext, trace: &[(u32, &CompiledInstruction)]) -> HashMap<String, i128> {
+    let swap_event = anchor_discriminator("event:SwapEvent");
+    let mut net: HashMap<String, i128> = HashMap::new();
+    for (_, instruction) in trace {
+        if ctx.account_keys.get(instruction.program_id_index).map(String::as_str) != Some(JUPITER_V6_PROGRAM_ID) {
+            continue;
+        }
+        let Ok(data) = bs58::decode(&instruction.data).into_vec() else { continue };
+        if data.get(..8) != Some(&ANCHOR_EVENT_IX_TAG[..]) || data.get(8..16) != Some(&swap_event[..]) {
+            continue;
+        }
+        // SwapEvent { amm, input_mint, input_amount, output_mint, output_amount }
+        let event = &data[16..];
+        let (Some(input_mint), Some(input_amount), Some(output_mint), Some(output_amount)) =
+            (read_pubkey(event, 32), read_u64(event, 64), read_pubkey(event, 72), read_u64(event, 104))
+        else {
+            continue;
+        };
+        *net.entry(input_mint).or_insert(0) -= input_amount as i128;
+        *net.entry(output_mint).or_insert(0) += output_amount as i128;
+    }
+    net
+}
+
+/// Whether an instruction is a Raydium or Orca swap, and which venue
+fn amm_swap_venue(ctx: &SwapContext, instruction: &CompiledInstruction) -> Option<&'static str> {
+    let program = ctx.account_keys.get(instruction.program_id_index)?;
+    let data = bs58::decode(&instruction.data).into_vec().ok()?;
+    match program.as_str() {
+        RAYDIUM_AMM_V4_PROGRAM_ID if RAYDIUM_SWAP_TAGS.contains(data.first()?) => Some("Raydium"),
+        ORCA_TOKEN_SWAP_PROGRAM_ID if *data.first()? == TOKEN_SWAP_SWAP_TAG => Some("Orca"),
+        ORCA_WHIRLPOOL_PROGRAM_ID
+            if [anchor_discriminator("global:swap"), anchor_discriminator("global:swap_v2")]
+                .iter()
+                .any(|d| data.get(..8) == Some(&d[..])) =>
+        {
+            Some("Orca")
+        }
+        _ => None,
+    }
+}
+
+/// Net raw amount per mint moved by Raydium/Orca swap instructions, from the transfers each one makes:
+/// the transfer a signer authorizes is the trader's input, the other is their output
+fn amm_swap_legs(ctx: &SwapContext, trace: &[(u32, &CompiledInstruction)]) -> (Option<&'static str>, HashMap<String, i128>) {
+    let mut venue = None;
+    let mut net: HashMap<String, i128> = HashMap::new();
+    for (position, (height, instruction)) in trace.iter().enumerate() {
+        let Some(swap_venue) = amm_swap_venue(ctx, instruction) else { continue };
+        let transfers: Vec<_> = trace[position + 1..]
+            .iter()
+            .take_while(|(h, _)| h > height)
+            .filter(|(h, _)| *h == height + 1)
+            .filter_map(|(_, ix)| token_transfer(ctx, ix))
+            .collect();
+        let (inputs, outputs): (Vec<_>, Vec<_>) = transfers
+            .iter()
+            .partition(|(_, _, authority, _, _)| *authority < ctx.num_signers);
+        let ([input], [output]) = (inputs.as_slice(), outputs.as_slice()) else { continue };
+
+        let mint_of = |(source, destination, _, mint, _): &(usize, usize, usize, Option<usize>, u64)| {
+            mint.and_then(|m| ctx.account_keys.get(m).cloned())
+                .or_else(|| ctx.token_accounts.get(source).map(|(mint, _)| mint.clone()))
+                .or_else(|| ctx.token_accounts.get(destination).map(|(mint, _)| mint.clone()))
+        };
+        let (Some(input_mint), Some(output_mint)) = (mint_of(input), mint_of(output)) else { continue };
+        *net.entry(input_mint).or_insert(0) -= input.4 as i128;
+        *net.entry(output_mint).or_insert(0) += output.4 as i128;
+        venue.get_or_insert(swap_venue);
+    }
+    (venue, net)
+}
+
+/// Decode the trader's swap legs; None when no supported swap instruction is found, so the caller
+/// can fall back to balance deltas
+/// Multi-hop and split routes are netted per mint: intermediate tokens cancel out, leaving the
+/// mint given up (most negative) and the mint received (most positive)
+pub fn decode_swap(ctx: &SwapContext, decimals_of: impl Fn(&str) -> Option<u8>) -> Option<SwapLegs> {
+    let trace = execution_trace(ctx);
+    // Jupiter's events already describe every hop; the AMM instructions it calls would count them twice
+    let jupiter = jupiter_event_legs(ctx, &trace);
+    let (venue, net) = if jupiter.is_empty() {
+        let (venue, net) = amm_swap_legs(ctx, &trace);
+        (venue?, net)
+    } else {
+        ("Jupiter v6", jupiter)
+    };
+
+    let (input_mint, input_raw) = net.iter().min_by_key(|(_, amount)| **amount)?;
+    let (output_mint, output_raw) = net.iter().max_by_key(|(_, amount)| **amount)?;
+    if *input_raw >= 0 || *output_raw <= 0 || input_mint == output_mint {
+        return None;
+    }
+    let ui = |mint: &str, raw: i128| -> Option<f64> {
+        Some(raw.unsigned_abs() as f64 / 10f64.powi(decimals_of(mint)? as i32))
+    };
+
+    Some(SwapLegs {
+        venue,
+        input_mint: input_mint.clone(),
+        input_amount: ui(input_mint, *input_raw)?,
+        output_mint: output_mint.clone(),
+        output_amount: ui(output_mint, *output_raw)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const JUP: &str = "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN";
+    const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+    fn decimals_of(mint: &str) -> Option<u8> {
+        match mint {
+            SOL => Some(9),
+            USDC | JUP => Some(6),
+            _ => None,
+        }
+    }
+
+    /// A transaction's instructions as getTransaction returns them: top-level, then CPIs per top-level index
+    struct Tx {
+        account_keys: Vec<String>,
+        instructions: Vec<CompiledInstruction>,
+        inner_instructions: Vec<InnerInstructions>,
+        token_accounts: HashMap<usize, (String, u8)>,
+    }
+
+    impl Tx {
+        fn new(account_keys: &[&str]) -> Self {
+            Self {
+                account_keys: account_keys.iter().map(|key| key.to_string()).collect(),
+                instructions: Vec::new(),
+                inner_instructions: Vec::new(),
+                token_accounts: HashMap::new(),
+            }
+        }
+
+        fn decode(&self) -> Option<SwapLegs> {
+            decode_swap(
+                &SwapContext {
+                    account_keys: &self.account_keys,
+                    instructions: &self.instructions,
+                    inner_instructions: &self.inner_instructions,
+                    token_accounts: &self.token_accounts,
+                    num_signers: 1,
+                },
+                decimals_of,
+            )
+        }
+    }
+
+    fn ix(program_id_index: usize, accounts: &[usize], data: &[u8], stack_height: u32) -> CompiledInstruction {
+        CompiledInstruction {
+            program_id_index,
+            accounts: accounts.to_vec(),
+            data: bs58::encode(data).into_string(),
+            stack_height: Some(stack_height),
+        }
+    }
+
+    fn with_u64s(prefix: &[u8], values: &[u64]) -> Vec<u8> {
+        let mut data = prefix.to_vec();
+        for value in values {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data
+    }
+
+    fn transfer(amount: u64) -> Vec<u8> {
+        with_u64s(&[TOKEN_TRANSFER_TAG], &[amount])
+    }
+
+    fn transfer_checked(amount: u64, decimals: u8) -> Vec<u8> {
+        let mut data = with_u64s(&[TOKEN_TRANSFER_CHECKED_TAG], &[amount]);
+        data.push(decimals);
+        data
+    }
+
+    /// Jupiter v6's self-CPI SwapEvent { amm, input_mint, input_amount, output_mint, output_amount }
+    fn swap_event(input_mint: &str, input_amount: u64, output_mint: &str, output_amount: u64) -> Vec<u8> {
+        let mut data = ANCHOR_EVENT_IX_TAG.to_vec();
+        data.extend_from_slice(&anchor_discriminator("event:SwapEvent"));
+        data.extend_from_slice(&[7u8; 32]);
+        data.extend_from_slice(&bs58::decode(input_mint).into_vec().unwrap());
+        data.extend_from_slice(&input_amount.to_le_bytes());
+        data.extend_from_slice(&bs58::decode(output_mint).into_vec().unwrap());
+        data.extend_from_slice(&output_amount.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn jupiter_multi_hop_nets_out_the_intermediate_mint() {
+        // 1 SOL -> 150 USDC on Raydium, then 150 USDC -> 200 JUP on a Whirlpool, routed by Jupiter
+        let mut tx = Tx::new(&[
+            "TraderWa11et1111111111111111111111111111111",
+            "TraderSo1Ata1111111111111111111111111111111",
+            "TraderUsdcAta111111111111111111111111111111",
+            "TraderJupAta11111111111111111111111111111111",
+            JUPITER_V6_PROGRAM_ID,
+            RAYDIUM_AMM_V4_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+            ORCA_WHIRLPOOL_PROGRAM_ID,
+            "RaydiumSo1Vau1t1111111111111111111111111111",
+            "RaydiumUsdcVau1t111111111111111111111111111",
+            "RaydiumAuthority111111111111111111111111111",
+            "Whir1poo1UsdcVau1t11111111111111111111111111",
+            "Whir1poo1JupVau1t111111111111111111111111111",
+            "Whir1poo1111111111111111111111111111111111",
+        ]);
+        for (account, mint) in [(1, SOL), (2, USDC), (3, JUP), (8, SOL), (9, USDC), (11, USDC), (12, JUP)] {
+            tx.token_accounts.insert(account, (mint.to_string(), decimals_of(mint).unwrap()));
+        }
+        tx.instructions.push(ix(4, &[0, 1, 3, 6], &with_u64s(&anchor_discriminator("global:route"), &[1_000_000_000]), 1));
+        tx.inner_instructions.push(InnerInstructions {
+            index: 0,
+            instructions: vec![
+                ix(5, &[6, 10, 8, 9, 1, 2, 0], &with_u64s(&[9], &[1_000_000_000, 149_000_000]), 2),
+                ix(6, &[1, 8, 0], &transfer(1_000_000_000), 3),
+                ix(6, &[9, 2, 10], &transfer(150_000_000), 3),
+                ix(4, &[4], &swap_event(SOL, 1_000_000_000, USDC, 150_000_000), 2),
+                ix(7, &[6, 0, 13, 2, 11, 3, 12], &with_u64s(&anchor_discriminator("global:swap"), &[150_000_000, 0]), 2),
+                ix(6, &[2, 11, 0], &transfer(150_000_000), 3),
+                ix(6, &[12, 3, 13], &transfer(200_000_000), 3),
+                ix(4, &[4], &swap_event(USDC, 150_000_000, JUP, 200_000_000), 2),
+            ],
+        });
+
+        // The events describe both hops; the Raydium and Whirlpool swaps inside aren't counted again
+        assert_eq!(tx.decode(), Some(SwapLegs {
+            venue: "Jupiter v6",
+            input_mint: SOL.to_string(),
+            input_amount: 1.0,
+            output_mint: JUP.to_string(),
+            output_amount: 200.0,
+        }));
+    }
+
+    #[test]
+    fn raydium_swap_takes_the_signer_authorized_transfer_as_input() {
+        // SwapBaseIn of 150 USDC for 1 SOL, after a compute budget instruction
+        let mut tx = Tx::new(&[
+            "TraderWa11et1111111111111111111111111111111",
+            "TraderUsdcAta111111111111111111111111111111",
+            "TraderSo1Ata1111111111111111111111111111111",
+            "RaydiumUsdcVau1t111111111111111111111111111",
+            "RaydiumSo1Vau1t1111111111111111111111111111",
+            "RaydiumAuthority111111111111111111111111111",
+            RAYDIUM_AMM_V4_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+            COMPUTE_BUDGET_PROGRAM_ID,
+        ]);
+        for (account, mint) in [(1, USDC), (2, SOL), (3, USDC), (4, SOL)] {
+            tx.token_accounts.insert(account, (mint.to_string(), decimals_of(mint).unwrap()));
+        }
+        tx.instructions.push(ix(8, &[], &with_u64s(&[3], &[10_000]), 1));
+        tx.instructions.push(ix(6, &[7, 5, 3, 4, 1, 2, 0], &with_u64s(&[9], &[150_000_000, 990_000_000]), 1));
+        tx.inner_instructions.push(InnerInstructions {
+            index: 1,
+            instructions: vec![
+                ix(7, &[1, 3, 0], &transfer(150_000_000), 2),
+                ix(7, &[4, 2, 5], &transfer(1_000_000_000), 2),
+            ],
+        });
+
+        assert_eq!(tx.decode(), Some(SwapLegs {
+            venue: "Raydium",
+            input_mint: USDC.to_string(),
+            input_amount: 150.0,
+            output_mint: SOL.to_string(),
+            output_amount: 1.0,
+        }));
+    }
+
+    #[test]
+    fn whirlpool_swap_v2_reads_mints_from_transfer_checked() {
+        // swap_v2 of 2 SOL for 300 USDC; no token balances, so the mints come from the transfers
+        let mut tx = Tx::new(&[
+            "TraderWa11et1111111111111111111111111111111",
+            "TraderSo1Ata1111111111111111111111111111111",
+            "TraderUsdcAta111111111111111111111111111111",
+            "Whir1poo1So1Vau1t11111111111111111111111111",
+            "Whir1poo1UsdcVau1t11111111111111111111111111",
+            "Whir1poo1111111111111111111111111111111111",
+            SOL,
+            USDC,
+            ORCA_WHIRLPOOL_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+        ]);
+        tx.instructions.push(ix(
+            8,
+            &[9, 9, 0, 5, 6, 7, 1, 3, 2, 4],
+            &with_u64s(&anchor_discriminator("global:swap_v2"), &[2_000_000_000, 290_000_000]),
+            1,
+        ));
+        tx.inner_instructions.push(InnerInstructions {
+            index: 0,
+            instructions: vec![
+                ix(9, &[1, 6, 3, 0], &transfer_checked(2_000_000_000, 9), 2),
+                ix(9, &[4, 7, 2, 5], &transfer_checked(300_000_000, 6), 2),
+            ],
+        });
+
+        assert_eq!(tx.decode(), Some(SwapLegs {
+            venue: "Orca",
+            input_mint: SOL.to_string(),
+            input_amount: 2.0,
+            output_mint: USDC.to_string(),
+            output_amount: 300.0,
+        }));
+    }
+
+    #[test]
+    fn transactions_without_a_swap_instruction_decode_to_none() {
+        let mut tx = Tx::new(&[
+            "TraderWa11et1111111111111111111111111111111",
+            "TraderUsdcAta111111111111111111111111111111",
+            "FriendUsdcAta111111111111111111111111111111",
+            TOKEN_PROGRAM_ID,
+            RAYDIUM_AMM_V4_PROGRAM_ID,
+        ]);
+        for account in [1, 2] {
+            tx.token_accounts.insert(account, (USDC.to_string(), 6));
+        }
+        // A plain token transfer
+        tx.instructions.push(ix(3, &[1, 2, 0], &transfer(5_000_000), 1));
+        assert_eq!(tx.decode(), None);
+
+        // A Raydium instruction that isn't a swap (deposit), moving tokens the same way
+        tx.instructions.push(ix(4, &[3, 0, 1, 2], &with_u64s(&[3], &[5_000_000, 0, 0]), 1));
+        tx.inner_instructions.push(InnerInstructions {
+            index: 1,
+            instructions: vec![ix(3, &[1, 2, 0], &transfer(5_000_000), 2)],
+        });
+        assert_eq!(tx.decode(), None);
+    }
+}
@@ -0,0 +1,413 @@
+// Token registry - supported tokens (built-in list, TOKEN_REGISTRY_FILE, admin additions)
+// with on-chain metadata and risk signals
+
+use crate::models::trade::Trade;
+use crate::services::clickhouse::ClickHouseService;
+use crate::services::pair_mapping::{current_token_list, parse_pair, set_token_list, symbol_to_mint, Pair, TokenConfig, TokenList};
+use crate::services::solana::{SolanaService, TOKEN_2022_PROGRAM_ID};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::{interval, Duration};
+
+/// How often mint accounts are re-read for risk signals
+const RISK_REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+/// How often TOKEN_REGISTRY_FILE is checked for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// How often median trade sizes (and with them dust thresholds) are recomputed
+const MEDIAN_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+/// Dust floor in the token's smallest units, for tokens without a median yet
+const DUST_MIN_RAW_UNITS: f64 = 100.0;
+/// Share of the median trade size below which a leg is dust
+const DUST_MEDIAN_FRACTION: f64 = 0.001;
+
+/// Smallest amount of a token treated as a real trade leg: a thousandth of its median trade,
+/// but never under 100 of its smallest units
+fn dust_threshold(decimals: Option<u8>, median_trade_size: Option<f64>) -> f64 {
+    let floor = decimals.map_or(0.0, |d| DUST_MIN_RAW_UNITS / 10f64.powi(d as i32));
+    floor.max(median_trade_size.unwrap_or(0.0) * DUST_MEDIAN_FRACTION)
+}
+
+/// Risk signals read from a token's mint account
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenRisk {
+    /// Authority that can freeze holders' token accounts (honeypot vector)
+    pub freeze_authority: Option<String>,
+    /// Authority that can still mint new supply
+    pub mint_authority: Option<String>,
+    /// Token-2022 transfer fee, if the mint has one
+    pub transfer_fee_bps: Option<u16>,
+    pub token_2022: bool,
+    /// Short machine-readable flags: freeze_authority, mint_authority, transfer_fee
+    pub flags: Vec<String>,
+    /// low / medium / high
+    pub level: String,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl TokenRisk {
+    /// Build risk signals from a jsonParsed mint account
+    fn from_mint_account(account: &serde_json::Value) -> Self {
+        let info = &account["data"]["parsed"]["info"];
+        let freeze_authority = info["freezeAuthority"].as_str().map(|s| s.to_string());
+        let mint_authority = info["mintAuthority"].as_str().map(|s| s.to_string());
+        let token_2022 = account["owner"].as_str() == Some(TOKEN_2022_PROGRAM_ID);
+        let transfer_fee_bps = info["extensions"]
+            .as_array()
+            .and_then(|extensions| {
+                extensions
+                    .iter()
+                    .find(|e| e["extension"].as_str() == Some("transferFeeConfig"))
+            })
+            .and_then(|e| e["state"]["newerTransferFee"]["transferFeeBasisPoints"].as_u64())
+            .map(|bps| bps as u16);
+
+        let mut flags = Vec::new();
+        if freeze_authority.is_some() {
+            flags.push("freeze_authority".to_string());
+        }
+        if mint_authority.is_some() {
+            flags.push("mint_authority".to_string());
+        }
+        if transfer_fee_bps.is_some_and(|bps| bps > 0) {
+            flags.push("transfer_fee".to_string());
+        }
+
+        // Freezing or taxing transfers can trap holders; open minting dilutes them
+        let level = if freeze_authority.is_some() || transfer_fee_bps.is_some_and(|bps| bps > 0) {
+            "high"
+        } else if mint_authority.is_some() {
+            "medium"
+        } else {
+            "low"
+        };
+
+        Self {
+            freeze_authority,
+            mint_authority,
+            transfer_fee_bps,
+            token_2022,
+            flags,
+            level: level.to_string(),
+            checked_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenInfo {
+    pub symbol: String,
+    pub mint: String,
+    pub decimals: Option<u8>,
+    /// None until the mint account has been read
+    pub risk: Option<TokenRisk>,
+    /// Median size of the token's trades over the last day (either side); None until computed
+    pub median_trade_size: Option<f64>,
+    /// Trades with a leg of this token below it are dropped as dust
+    pub dust_threshold: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairInfo {
+    pub symbol: String,
+    pub base: TokenInfo,
+    pub quote: TokenInfo,
+    /// First and most recent trade seen for the pair (either direction)
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PairActivity {
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+}
+
+/// Read and validate a token list file
+pub(crate) fn read_token_file(path: &PathBuf) -> Result<(TokenList, Option<SystemTime>)> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let raw = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let list: TokenList = serde_json::from_str(&raw).with_context(|| format!("Invalid token list in {}", path.display()))?;
+    list.validate().map_err(|e| anyhow!("Invalid token list in {}: {}", path.display(), e))?;
+    Ok((list, modified))
+}
+
+/// Metadata entries for a token list, keeping what was already read for mints still listed
+fn token_infos(list: &TokenList, existing: &HashMap<String, TokenInfo>) -> HashMap<String, TokenInfo> {
+    list.tokens
+        .iter()
+        .map(|token| {
+            let info = match existing.get(&token.mint) {
+                Some(info) => TokenInfo { symbol: token.symbol.clone(), ..info.clone() },
+                None => TokenInfo {
+                    symbol: token.symbol.clone(),
+                    mint: token.mint.clone(),
+                    decimals: Some(token.decimals),
+                    risk: None,
+                    median_trade_size: None,
+                    dust_threshold: dust_threshold(Some(token.decimals), None),
+                },
+            };
+            (token.mint.clone(), info)
+        })
+        .collect()
+}
+
+/// Tokens and pairs of `base` plus the additions not already in it
+fn merge_token_lists(base: &TokenList, added: &TokenList) -> TokenList {
+    let mut merged = base.clone();
+    for token in &added.tokens {
+        if !merged.tokens.iter().any(|t| t.symbol == token.symbol) {
+            merged.tokens.push(token.clone());
+        }
+    }
+    for pair in &added.pairs {
+        if !merged.pairs.contains(pair) {
+            merged.pairs.push(pair.clone());
+        }
+    }
+    merged
+}
+
+pub struct TokenRegistry {
+    solana: SolanaService,
+    clickhouse: Arc<ClickHouseService>,
+    /// TOKEN_REGISTRY_FILE, reloaded when it changes; None uses the built-in list
+    config_path: Option<PathBuf>,
+    /// The configured list (file or built-in) and when the file was last modified
+    configured: Mutex<(TokenList, Option<SystemTime>)>,
+    /// Tokens and pairs added through /admin/tokens; kept across reloads until restart
+    added: Mutex<TokenList>,
+    /// Keyed by mint
+    tokens: RwLock<HashMap<String, TokenInfo>>,
+    /// Keyed by "BASE/QUOTE" as stored
+    activity: RwLock<HashMap<String, PairActivity>>,
+}
+
+impl TokenRegistry {
+    /// Loads `config_path` (TOKEN_REGISTRY_FILE, JSON `{ "tokens": [{ symbol, mint, decimals }], "pairs": ["BASE/QUOTE"] }`)
+    /// when set; a missing or invalid file is an error at startup
+    pub fn new(clickhouse: Arc<ClickHouseService>, solana: SolanaService, config_path: Option<PathBuf>) -> Result<Self> {
+        let configured = match &config_path {
+            Some(path) => {
+                let (list, modified) = read_token_file(path)?;
+                println!("✅ [TokenRegistry] Loaded {} tokens and {} pairs from {}", list.tokens.len(), list.pairs.len(), path.display());
+                (list, modified)
+            }
+            None => (TokenList::default(), None),
+        };
+        set_token_list(configured.0.clone());
+
+        let tokens = token_infos(&configured.0, &HashMap::new());
+
+        Ok(Self {
+            solana,
+            clickhouse,
+            config_path,
+            configured: Mutex::new(configured),
+            added: Mutex::new(TokenList { tokens: Vec::new(), pairs: Vec::new() }),
+            tokens: RwLock::new(tokens),
+            activity: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Load first/last trade times from stored trades, then keep refreshing risk signals and
+    /// reloading TOKEN_REGISTRY_FILE when it changes
+    pub async fn start(self: Arc<Self>) {
+        match self.clickhouse.get_pair_activity(None).await {
+            Ok(rows) => {
+                let mut activity = self.activity.write().await;
+                for (base, quote, first_seen, last_seen) in rows {
+                    let entry = activity
+                        .entry(format!("{}/{}", base, quote))
+                        .or_insert(PairActivity { first_seen, last_seen });
+                    entry.first_seen = entry.first_seen.min(first_seen);
+                    entry.last_seen = entry.last_seen.max(last_seen);
+                }
+                println!("✅ [TokenRegistry] Loaded activity for {} pairs", activity.len());
+            }
+            Err(e) => eprintln!("⚠️  [TokenRegistry] Failed to load pair activity: {}", e),
+        }
+        
+        // Periodically refresh on-chain metadata and risk signals for every token
+        let mut refresh = interval(RISK_REFRESH_INTERVAL);
+        let mut poll = interval(CONFIG_POLL_INTERVAL);
+        let mut medians = interval(MEDIAN_REFRESH_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = medians.tick() => self.refresh_medians().await,
+                _ = refresh.tick() => {
+                    let mints: Vec<String> = self.tokens.read().await.keys().cloned().collect();
+                    self.refresh(mints).await;
+                }
+                _ = poll.tick() => self.reload_if_changed().await,
+            }
+        }
+    }
+
+    /// Reload TOKEN_REGISTRY_FILE if it was modified; an invalid file keeps the current list
+    async fn reload_if_changed(&self) {
+        let Some(path) = &self.config_path else { return };
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified == self.configured.lock().await.1 {
+            return;
+        }
+        match read_token_file(path) {
+            Ok((list, modified)) => {
+                let added = self.added.lock().await.clone();
+                if let Err(e) = merge_token_lists(&list, &added).validate() {
+                    eprintln!("⚠️  [TokenRegistry] Not reloading {}: conflicts with added tokens: {}", path.display(), e);
+                    self.configured.lock().await.1 = modified;
+                    return;
+                }
+                println!("🔄 [TokenRegistry] Reloaded {} tokens and {} pairs from {}", list.tokens.len(), list.pairs.len(), path.display());
+                *self.configured.lock().await = (list, modified);
+                self.apply().await;
+            }
+            Err(e) => {
+                eprintln!("⚠️  [TokenRegistry] Keeping the current token list: {}", e);
+                // Don't re-read the same broken file every poll
+                self.configured.lock().await.1 = modified;
+            }
+        }
+    }
+
+    /// Add a token (and pairs using it) at runtime; kept until restart, also across file reloads
+    /// Err with the reason if it conflicts with a listed token or a pair is invalid
+    pub async fn add_token(&self, token: TokenConfig, pairs: Vec<String>) -> Result<TokenInfo, String> {
+        let pairs: Vec<String> = pairs
+            .iter()
+            .map(|pair| parse_pair(pair).map(|p| p.to_string()).ok_or_else(|| format!("pair '{}' must be BASE/QUOTE", pair)))
+            .collect::<Result<_, _>>()?;
+        {
+            let configured = self.configured.lock().await;
+            let mut added = self.added.lock().await;
+            let current = merge_token_lists(&configured.0, &added);
+            if let Some(listed) = current.tokens.iter().find(|t| t.symbol == token.symbol || t.mint == token.mint) {
+                return Err(format!("{} ({}) is already listed", listed.symbol, listed.mint));
+            }
+            let mut candidate = added.clone();
+            candidate.tokens.push(token.clone());
+            candidate.pairs.extend(pairs.iter().cloned());
+            merge_token_lists(&configured.0, &candidate).validate()?;
+            *added = candidate;
+        }
+        println!("➕ [TokenRegistry] Added {} ({}) with pairs {:?}", token.symbol, token.mint, pairs);
+        self.apply().await;
+        self.refresh(vec![token.mint.clone()]).await;
+        self.tokens.read().await.get(&token.mint).cloned().ok_or_else(|| format!("{} was not added", token.symbol))
+    }
+
+    /// Publish the configured list plus additions to pair mapping and sync token metadata
+    async fn apply(&self) {
+        let merged = merge_token_lists(&self.configured.lock().await.0, &self.added.lock().await.clone());
+        set_token_list(merged.clone());
+        let mut tokens = self.tokens.write().await;
+        let new_mints: Vec<String> = merged.tokens.iter().filter(|t| !tokens.contains_key(&t.mint)).map(|t| t.mint.clone()).collect();
+        *tokens = token_infos(&merged, &tokens);
+        drop(tokens);
+        if !new_mints.is_empty() {
+            self.refresh(new_mints).await;
+        }
+    }
+
+    async fn refresh(&self, mints: Vec<String>) {
+        let count = mints.len();
+        for mint in mints {
+            match self.solana.get_account_info_parsed(&mint).await {
+                Ok(Some(account)) => {
+                    let risk = TokenRisk::from_mint_account(&account);
+                    let decimals = account["data"]["parsed"]["info"]["decimals"].as_u64().map(|d| d as u8);
+                    if let Some(token) = self.tokens.write().await.get_mut(&mint) {
+                        if !risk.flags.is_empty() {
+                            println!("⚠️  [TokenRegistry] {} risk flags: {:?}", token.symbol, risk.flags);
+                        }
+                        if decimals.is_some() && decimals != token.decimals {
+                            eprintln!("⚠️  [TokenRegistry] {} mint has {:?} decimals, not the configured {:?}", token.symbol, decimals, token.decimals);
+                        }
+                        token.decimals = decimals;
+                        token.dust_threshold = dust_threshold(token.decimals, token.median_trade_size);
+                        token.risk = Some(risk);
+                    }
+                }
+                Ok(None) => eprintln!("⚠️  [TokenRegistry] Mint account not found: {}", mint),
+                Err(e) => eprintln!("⚠️  [TokenRegistry] Failed to read mint {}: {}", mint, e),
+            }
+        }
+        println!("✅ [TokenRegistry] Refreshed risk signals for {} tokens", count);
+    }
+
+    /// Recompute each token's median trade size and dust threshold from the last day of trades
+    async fn refresh_medians(&self) {
+        let medians: HashMap<String, f64> = match self.clickhouse.get_median_trade_sizes().await {
+            Ok(rows) => rows.into_iter().collect(),
+            Err(e) => {
+                eprintln!("⚠️  [TokenRegistry] Failed to load median trade sizes: {}", e);
+                return;
+            }
+        };
+        let mut tokens = self.tokens.write().await;
+        for token in tokens.values_mut() {
+            // Keep the last median through a quiet day
+            if let Some(median) = medians.get(&token.symbol) {
+                token.median_trade_size = Some(*median);
+            }
+            token.dust_threshold = dust_threshold(token.decimals, token.median_trade_size);
+        }
+        println!("✅ [TokenRegistry] Updated dust thresholds from {} median trade sizes", medians.len());
+    }
+
+    /// Whether either leg of a trade is below its token's dust threshold
+    pub async fn is_dust(&self, trade: &Trade) -> bool {
+        let tokens = self.tokens.read().await;
+        let below = |mint: &str, amount: f64| tokens.get(mint).is_some_and(|token| amount < token.dust_threshold);
+        below(&trade.base_mint, trade.amount) || below(&trade.quote_mint, trade.total_value)
+    }
+
+    /// All tracked tokens, sorted by symbol
+    pub async fn list_tokens(&self) -> Vec<TokenInfo> {
+        let mut tokens: Vec<TokenInfo> = self.tokens.read().await.values().cloned().collect();
+        tokens.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        tokens
+    }
+
+    /// Record an ingested trade's time against its pair
+    pub async fn record_trade(&self, base_symbol: &str, quote_symbol: &str, timestamp: DateTime<Utc>) {
+        let mut activity = self.activity.write().await;
+        let entry = activity
+            .entry(format!("{}/{}", base_symbol, quote_symbol))
+            .or_insert(PairActivity { first_seen: timestamp, last_seen: timestamp });
+        entry.first_seen = entry.first_seen.min(timestamp);
+        entry.last_seen = entry.last_seen.max(timestamp);
+    }
+
+    /// Supported pairs with both tokens' metadata and trade activity
+    pub async fn list_pairs(&self) -> Vec<PairInfo> {
+        let tokens = self.tokens.read().await;
+        let activity = self.activity.read().await;
+        current_token_list()
+            .pairs
+            .iter()
+            .filter_map(|pair| {
+                let Pair { base, quote } = parse_pair(pair)?;
+                // Trades are stored in whichever direction the swap went
+                let seen: Vec<PairActivity> = [format!("{}/{}", base, quote), format!("{}/{}", quote, base)]
+                    .iter()
+                    .filter_map(|key| activity.get(key).copied())
+                    .collect();
+                Some(PairInfo {
+                    symbol: pair.to_string(),
+                    base: tokens.get(&symbol_to_mint(&base)?)?.clone(),
+                    quote: tokens.get(&symbol_to_mint(&quote)?)?.clone(),
+                    first_seen: seen.iter().map(|a| a.first_seen).min(),
+                    last_seen: seen.iter().map(|a| a.last_seen).max(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,118 @@
+// Trade sanity checks - catches parsed amounts that are off by powers of ten (decimals bugs)
+
+use crate::models::trade::Trade;
+use crate::services::memory_budget::{Footprint, MemoryBudget, PairLru};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Most recent rejection times kept per pair
+const MAX_PAIR_REJECTIONS: usize = 10_000;
+
+/// Why a parsed trade failed a sanity check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SanityFailure {
+    /// A token balance reports different decimals than the mint has
+    DecimalsMismatch,
+    /// A token balance's UI amount disagrees with raw amount / 10^decimals
+    UiAmountMismatch,
+    /// A swap leg (base amount, or amount * price) disagrees with the transaction's transfer total of its mint
+    NotionalMismatch,
+    /// Price is off from the oracle by at least an order of magnitude
+    OracleMagnitude,
+    /// Base or quote amount is below its token's dust threshold
+    Dust,
+}
+
+impl SanityFailure {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SanityFailure::DecimalsMismatch => "decimals_mismatch",
+            SanityFailure::UiAmountMismatch => "ui_amount_mismatch",
+            SanityFailure::NotionalMismatch => "notional_mismatch",
+            SanityFailure::OracleMagnitude => "oracle_magnitude",
+            SanityFailure::Dust => "dust",
+        }
+    }
+
+    /// Whether the failure means the parsed data was wrong (dust is real, just too small to use)
+    pub fn is_bad_data(&self) -> bool {
+        !matches!(self, SanityFailure::Dust)
+    }
+}
+
+/// Order-independent key of a trade's pair (SOL/USDC and USDC/SOL share one)
+pub fn pair_key(base_symbol: &str, quote_symbol: &str) -> String {
+    if base_symbol <= quote_symbol {
+        format!("{}/{}", base_symbol, quote_symbol)
+    } else {
+        format!("{}/{}", quote_symbol, base_symbol)
+    }
+}
+
+/// Whether two positive quantities differ by a factor of ten or more
+pub fn orders_apart(a: f64, b: f64) -> bool {
+    a > 0.0 && b > 0.0 && (a / b).log10().abs() >= 1.0
+}
+
+/// Per-reason counters for trades rejected or flagged by sanity checks
+pub struct TradeSanity {
+    counts: RwLock<HashMap<SanityFailure, u64>>,
+    /// When bad-data failures happened, per pair (see `pair_key`)
+    pair_rejections: RwLock<PairLru<String, PairRejections>>,
+}
+
+/// A pair's most recent bad-data failure times
+#[derive(Default)]
+struct PairRejections(VecDeque<DateTime<Utc>>);
+
+impl Footprint for PairRejections {
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>() + self.0.capacity() * std::mem::size_of::<DateTime<Utc>>()
+    }
+}
+
+impl TradeSanity {
+    pub fn new(memory_budget: Arc<MemoryBudget>) -> Self {
+        Self {
+            counts: RwLock::new(HashMap::new()),
+            pair_rejections: RwLock::new(PairLru::new("trade_sanity", memory_budget)),
+        }
+    }
+
+    /// Count a failed check
+    pub async fn record(&self, failure: SanityFailure, trade: &Trade) {
+        eprintln!("⚠️  [Sanity] {} failed {}", &trade.id[..16.min(trade.id.len())], failure.as_str());
+        *self.counts.write().await.entry(failure).or_insert(0) += 1;
+        if failure.is_bad_data() {
+            let pair = pair_key(&trade.base_symbol, &trade.quote_symbol);
+            self.pair_rejections.write().await.upsert(pair, PairRejections::default, |PairRejections(times)| {
+                if times.len() == MAX_PAIR_REJECTIONS {
+                    times.pop_front();
+                }
+                times.push_back(Utc::now());
+            });
+        }
+    }
+
+    /// Bad-data failures per pair (see `pair_key`) since `since`
+    pub async fn pair_rejections_since(&self, since: DateTime<Utc>) -> HashMap<String, u64> {
+        self.pair_rejections
+            .read()
+            .await
+            .iter()
+            .map(|(pair, PairRejections(times))| (pair.clone(), times.iter().filter(|t| **t >= since).count() as u64))
+            .collect()
+    }
+
+    /// Failures per reason since startup
+    pub async fn counts(&self) -> HashMap<&'static str, u64> {
+        self.counts
+            .read()
+            .await
+            .iter()
+            .map(|(failure, count)| (failure.as_str(), *count))
+            .collect()
+    }
+}
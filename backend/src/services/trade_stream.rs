@@ -1,39 +1,138 @@
 // Trade stream processing service module
 
+use crate::config::Config;
+use crate::models::schema::{self, CURRENT_SCHEMA_VERSION};
 use crate::models::trade::Trade;
 use crate::services::jupiter::JupiterService;
 use crate::services::solana::SolanaService;
 use crate::services::quicknode_ws::QuickNodeWebSocket;
-use crate::services::clickhouse::ClickHouseService;
-use crate::services::pair_mapping::{pair_to_mints, parse_pair};
+use crate::services::clickhouse::{ClickHouseService, PriceTickRow};
+use crate::services::dex_programs::DexProgramRegistry;
+use crate::services::portfolio::PortfolioService;
+use crate::services::circuit_breaker::{CircuitBreaker, Verdict};
+use crate::services::token_registry::TokenRegistry;
+use crate::services::trade_sanity::{orders_apart, SanityFailure, TradeSanity};
+use crate::services::aggregator::TradeAggregator;
+use crate::services::recent_trades::RecentTrades;
+use crate::services::follows::WalletFollows;
+use crate::services::ch_writer::TradeWriter;
+use crate::services::spikes::SpikeDetector;
+use crate::services::whales::WhaleAlerts;
+use crate::services::candles::CandleAggregator;
+use crate::services::memory_budget::MemoryBudget;
+use crate::services::notification_templates::NotificationTemplates;
+use crate::services::job_leases::JobLeases;
+use crate::services::sharding::ShardForwarder;
+use crate::services::venue_latency::VenueLatency;
+use crate::services::stream_health::StreamHealth;
+use crate::services::load_test::{self, LoadTestService};
+use crate::services::pair_mapping::{pair_to_mints, parse_pair, Pair};
 use crate::websocket::ConnectionManager;
+use crate::websocket::messages::OutboundMessage;
 use anyhow::Result;
 use chrono::Utc;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, Duration, Instant};
+
+/// How long an oracle (Jupiter) price is reused when validating parsed trades
+const ORACLE_TTL: Duration = Duration::from_secs(10);
+/// Buffered trades from POST /api/ingest/trades awaiting the pipeline
+pub const INGEST_QUEUE_CAPACITY: usize = 10_000;
+/// How often expired sessions are deleted
+const SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+/// Low bits of a sequence that hold the trade's position within its slot
+const SEQUENCE_SLOT_SHIFT: u32 = 16;
+
+/// Assigns each pair (either direction) a monotonic sequence: (slot << 16) | position in slot
+/// getTransaction doesn't expose the transaction index, so position is arrival order within
+/// the slot; trades arriving for an older slot still get the next sequence so it never decreases
+#[derive(Default)]
+struct SequenceAssigner {
+    last: HashMap<(String, String), u64>,
+}
+
+impl SequenceAssigner {
+    fn key(base_symbol: &str, quote_symbol: &str) -> (String, String) {
+        if base_symbol <= quote_symbol {
+            (base_symbol.to_string(), quote_symbol.to_string())
+        } else {
+            (quote_symbol.to_string(), base_symbol.to_string())
+        }
+    }
+
+    /// Continue from sequences already stored, so a restart never hands out a lower one
+    fn resume(&mut self, stored: Vec<(String, String, u64)>) {
+        for (base_symbol, quote_symbol, sequence) in stored {
+            let last = self.last.entry(Self::key(&base_symbol, &quote_symbol)).or_insert(0);
+            *last = (*last).max(sequence);
+        }
+    }
+
+    fn next(&mut self, trade: &Trade) -> u64 {
+        let last = self.last.entry(Self::key(&trade.base_symbol, &trade.quote_symbol)).or_insert(0);
+        *last = (*last + 1).max(trade.slot << SEQUENCE_SLOT_SHIFT);
+        *last
+    }
+}
 
 pub struct TradeStreamService {
     solana: SolanaService,
     jupiter: JupiterService,
     clickhouse: Arc<ClickHouseService>,
     ws_manager: Arc<ConnectionManager>,
+    dex_programs: Arc<DexProgramRegistry>,
+    portfolio: Arc<PortfolioService>,
+    circuit_breaker: Arc<CircuitBreaker>,
+    tokens: Arc<TokenRegistry>,
+    sanity: Arc<TradeSanity>,
+    aggregator: Arc<TradeAggregator>,
+    latency: Arc<VenueLatency>,
+    stream_health: Arc<StreamHealth>,
+    load_test: Arc<LoadTestService>,
+    memory_budget: Arc<MemoryBudget>,
+    templates: Arc<NotificationTemplates>,
+    recent_trades: Arc<RecentTrades>,
+    follows: Arc<WalletFollows>,
+    config: Arc<Config>,
 }
 
 impl TradeStreamService {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         ws_manager: Arc<ConnectionManager>,
         clickhouse: Arc<ClickHouseService>,
+        dex_programs: Arc<DexProgramRegistry>,
+        portfolio: Arc<PortfolioService>,
+        circuit_breaker: Arc<CircuitBreaker>,
+        tokens: Arc<TokenRegistry>,
+        sanity: Arc<TradeSanity>,
+        aggregator: Arc<TradeAggregator>,
+        job_leases: Arc<JobLeases>,
+        latency: Arc<VenueLatency>,
+        stream_health: Arc<StreamHealth>,
+        load_test: Arc<LoadTestService>,
+        memory_budget: Arc<MemoryBudget>,
+        templates: Arc<NotificationTemplates>,
+        recent_trades: Arc<RecentTrades>,
+        follows: Arc<WalletFollows>,
+        solana: SolanaService,
+        jupiter: JupiterService,
+        config: Arc<Config>,
     ) -> Result<Self> {
-        let solana = SolanaService::new()?;
-        
-        // Cleanup expired sessions periodically
+        // Cleanup expired sessions periodically (sessions are shared, so one replica per hour)
+        // SESSION_RETENTION_DAYS keeps expired ones for session analytics
+        let retention_days = config.session_retention_days;
         let clickhouse_clone = clickhouse.clone();
         tokio::spawn(async move {
-            let mut cleanup_interval = interval(Duration::from_secs(3600)); // Every hour
+            let mut cleanup_interval = interval(SESSION_CLEANUP_INTERVAL);
             loop {
                 cleanup_interval.tick().await;
-                if let Err(e) = clickhouse_clone.cleanup_expired_sessions().await {
+                if !job_leases.claim("session_cleanup", SESSION_CLEANUP_INTERVAL - Duration::from_secs(60)).await {
+                    continue;
+                }
+                if let Err(e) = clickhouse_clone.cleanup_expired_sessions(retention_days).await {
                     eprintln!("⚠️  Failed to cleanup expired sessions: {}", e);
                 }
             }
@@ -41,49 +140,116 @@ impl TradeStreamService {
         
         Ok(Self {
             solana,
-            jupiter: JupiterService::new()?,
+            jupiter,
             clickhouse,
             ws_manager,
+            dex_programs,
+            portfolio,
+            circuit_breaker,
+            tokens,
+            sanity,
+            aggregator,
+            latency,
+            stream_health,
+            load_test,
+            memory_budget,
+            templates,
+            recent_trades,
+            follows,
+            config,
         })
     }
 
     /// Start the trade stream service
-    pub async fn start(&self) {
+    /// `ingest_rx` carries externally parsed trades, which go through the same pipeline
+    pub async fn start(&self, mut ingest_rx: mpsc::Receiver<Trade>) {
         println!("🚀 Starting trade stream service...");
         
         let solana_service = Arc::new(self.solana.clone());
         let ws_manager = self.ws_manager.clone();
         let jupiter = self.jupiter.clone();
         let clickhouse = self.clickhouse.clone();
+        let portfolio = self.portfolio.clone();
+        let circuit_breaker = self.circuit_breaker.clone();
+        let tokens = self.tokens.clone();
+        let sanity = self.sanity.clone();
+        let aggregator = self.aggregator.clone();
+        let recent_trades = self.recent_trades.clone();
+        let follows = self.follows.clone();
+        let latency = self.latency.clone();
+        let load_test = self.load_test.clone();
+        // Trades are inserted in batches rather than one INSERT each
+        let writer = TradeWriter::start(
+            clickhouse.clone(),
+            self.config.clickhouse.batch_rows,
+            self.config.clickhouse.batch_interval,
+        );
+        // Per-minute volume/trade-count spikes on the `alerts` channel
+        let spikes = SpikeDetector::new(ws_manager.clone(), clickhouse.clone(), self.memory_budget.clone(), self.templates.clone(), self.config.spikes);
+        // Trades worth at least WHALE_ALERT_MIN_USD on the `whales` channel
+        let whales = WhaleAlerts::new(ws_manager.clone(), clickhouse.clone(), portfolio.clone(), self.templates.clone(), self.config.whale_alert_min_usd);
+        // In-progress candles on the `candles` channel
+        let candles = Arc::new(CandleAggregator::new(ws_manager.clone(), self.memory_budget.clone()));
+        tokio::spawn(candles.clone().start());
         
         // Channel for QuickNode WebSocket trades
         let (trade_tx, mut trade_rx) = mpsc::channel::<Trade>(100);
         
         // Start QuickNode WebSocket subscription
-        let quicknode_ws = QuickNodeWebSocket::new(solana_service.clone())
-            .expect("Failed to create QuickNode WebSocket client");
+        let quicknode_ws = QuickNodeWebSocket::new(
+            solana_service.clone(),
+            self.dex_programs.clone(),
+            self.sanity.clone(),
+            self.stream_health.clone(),
+            self.memory_budget.clone(),
+        );
         
         let quicknode_ws_clone = quicknode_ws.clone();
-        let trade_tx_clone = trade_tx.clone();
+        // A sharded worker hands its parsed trades to the hub instead of running the pipeline on them
+        let trade_tx_clone = match ShardForwarder::new(self.config.ingest_forward_url.as_deref()) {
+            Some(forwarder) => {
+                let (forward_tx, forward_rx) = mpsc::channel::<Trade>(100);
+                tokio::spawn(forwarder.run(forward_rx));
+                forward_tx
+            }
+            None => {
+                // Every trade is stored here, so the recent trades cache stays current
+                tokio::spawn(self.recent_trades.clone().start_feeding());
+                trade_tx.clone()
+            }
+        };
+        
+        // Forward trades from external feeders into the pipeline
+        let ingest_tx = trade_tx.clone();
+        tokio::spawn(async move {
+            while let Some(trade) = ingest_rx.recv().await {
+                if ingest_tx.send(trade).await.is_err() {
+                    break;
+                }
+            }
+        });
         
         // Spawn QuickNode WebSocket subscription task
+        // Reconnects back off exponentially (with jitter) until a connection stays up
+        let stream_health = self.stream_health.clone();
         tokio::spawn(async move {
             loop {
-                match quicknode_ws_clone.start_subscription(trade_tx_clone.clone()).await {
-                    Ok(_) => {
-                        eprintln!("⚠️  QuickNode WebSocket closed, reconnecting...");
-                    }
+                let backoff = match quicknode_ws_clone.start_subscription(trade_tx_clone.clone()).await {
+                    Ok(_) => stream_health.disconnected(None).await,
                     Err(e) => {
-                        eprintln!("❌ QuickNode WebSocket error: {}", e);
+                        eprintln!("❌ QuickNode WebSocket error: {:#}", e);
+                        stream_health.disconnected(Some(format!("{:#}", e))).await
                     }
-                }
-                tokio::time::sleep(Duration::from_secs(5)).await;
+                };
+                eprintln!("⚠️  QuickNode WebSocket closed, reconnecting in {}ms...", backoff.as_millis());
+                tokio::time::sleep(backoff).await;
             }
         });
         
         // Spawn Jupiter price update task (every 5 seconds)
         let jupiter_clone = jupiter.clone();
         let ws_manager_price = ws_manager.clone();
+        let clickhouse_price = clickhouse.clone();
         tokio::spawn(async move {
             let mut price_interval = interval(Duration::from_secs(5));
             let mut tick_count = 0u64;
@@ -97,22 +263,39 @@ impl TradeStreamService {
                 
                 // Parse pair and get mint addresses
                 if let Some((base_mint, quote_mint)) = pair_to_mints(&selected_pair) {
-                    if let Some((base_symbol, quote_symbol)) = parse_pair(&selected_pair) {
+                    if let Some(Pair { base: base_symbol, quote: quote_symbol }) = parse_pair(&selected_pair) {
                         match jupiter_clone.get_price(&base_mint, &quote_mint).await {
                             Ok(price) => {
                                 println!("💰 Jupiter price fetched: {} {} @ ${:.6}", base_symbol, quote_symbol, price);
+                                let now = Utc::now();
+                                
+                                // Persist the tick for /api/midprice/history
+                                let tick = PriceTickRow {
+                                    created_at: time::OffsetDateTime::from_unix_timestamp_nanos(now.timestamp_millis() as i128 * 1_000_000)
+                                        .unwrap_or_else(|_| time::OffsetDateTime::now_utc()),
+                                    base_symbol: base_symbol.clone(),
+                                    quote_symbol: quote_symbol.clone(),
+                                    price,
+                                };
+                                if let Err(e) = clickhouse_price.store_price_tick(&tick).await {
+                                    eprintln!("⚠️  Failed to store price tick for {}: {}", selected_pair, e);
+                                }
+                                
                                 let price_trade = serde_json::json!({
-                                    "id": format!("price_{}", Utc::now().timestamp()),
-                                    "timestamp": Utc::now().to_rfc3339(),
+                                    "id": format!("price_{}", now.timestamp()),
+                                    "timestamp": now.to_rfc3339(),
                                     "base_symbol": base_symbol,
                                     "quote_symbol": quote_symbol,
                                     "price": price,
                                     "amount": 0.0,
-                                    "side": "price"
+                                    "side": "price",
+                                    "schema_version": CURRENT_SCHEMA_VERSION
                                 });
                                 
                                 if let Ok(price_json) = serde_json::to_string(&price_trade) {
-                                    let client_count = ws_manager_price.broadcast(price_json).await;
+                                    let client_count = ws_manager_price.broadcast(
+                                        OutboundMessage::new("prices", Some(selected_pair.clone()), price_json)
+                                    ).await;
                                     println!("📤 [PRICE-UPDATE] Broadcasting {} {} @ ${:.6} to {} clients", 
                                         base_symbol, quote_symbol, price, client_count);
                                 } else {
@@ -130,41 +313,136 @@ impl TradeStreamService {
             }
         });
         
+        // Oracle prices per pair, shared by price validation and the circuit breaker
+        let mut oracle_prices: HashMap<String, (Instant, f64)> = HashMap::new();
+        let mut sequences = SequenceAssigner::default();
+        match clickhouse.get_last_sequences().await {
+            Ok(stored) => sequences.resume(stored),
+            Err(e) => eprintln!("⚠️  Failed to load stored trade sequences: {}", e),
+        }
+        
         // Process trades from QuickNode WebSocket
         loop {
             tokio::select! {
                 // Receive trades from QuickNode WebSocket
                 Some(trade) = trade_rx.recv() => {
+                    let mut trade = trade;
+                    let synthetic = load_test::is_synthetic(&trade);
+                    if !synthetic {
+                        latency.record(&trade).await;
+                    }
+                    // Legs too small to be meaningful for their token (thresholds scale with decimals and typical size)
+                    if !synthetic && tokens.is_dust(&trade).await {
+                        sanity.record(SanityFailure::Dust, &trade).await;
+                        continue;
+                    }
+                    let pair = format!("{}/{}", trade.base_symbol, trade.quote_symbol);
+                    if !synthetic {
+                        trade.sequence = sequences.next(&trade);
+                    }
+                    
                     // Get current price from Jupiter for validation
-                    let current_price = match jupiter.get_sol_usdc_price().await {
-                        Ok(price) => price,
-                        Err(_) => 150.0, // Fallback
+                    let oracle_price = match oracle_prices.get(&pair) {
+                        Some((fetched, price)) if fetched.elapsed() < ORACLE_TTL => Some(*price),
+                        _ => match jupiter.get_price(&trade.base_mint, &trade.quote_mint).await {
+                            Ok(price) => {
+                                oracle_prices.insert(pair.clone(), (Instant::now(), price));
+                                Some(price)
+                            }
+                            Err(_) => None,
+                        },
                     };
                     
-                    let mut trade = trade;
-                    
                     // Validate price
                     if trade.price <= 0.0 || trade.price.is_infinite() || trade.price.is_nan() {
-                        trade.price = current_price;
+                        trade.price = oracle_price.unwrap_or(150.0); // Fallback
                     }
                     
-                    // Store trade in ClickHouse
-                    if let Err(e) = clickhouse.store_trade(&trade).await {
-                        eprintln!("❌ Failed to store trade in ClickHouse: {}", e);
-                        eprintln!("   Trade details: {} {} {} @ ${:.6} (ID: {})", 
-                            trade.side, trade.amount, trade.base_symbol, trade.price, 
-                            &trade.id[..16.min(trade.id.len())]);
-                    } else {
-                        println!("✅ Stored trade in ClickHouse: {} {} {} @ ${:.6}", 
-                            trade.side, trade.amount, trade.base_symbol, trade.price);
+                    trade.spread_bps = oracle_price
+                        .filter(|oracle| *oracle > 0.0)
+                        .map(|oracle| (trade.price / oracle - 1.0) * 10_000.0);
+                    
+                    // Load test trades: stored flagged (never read back) and broadcast on their own channel
+                    if synthetic {
+                        writer.write_quarantined(trade.clone()).await;
+                        let trade_payload = schema::with_schema_version(serde_json::json!(trade), CURRENT_SCHEMA_VERSION);
+                        ws_manager.broadcast(OutboundMessage::new(
+                            "loadtest",
+                            Some(pair.clone()),
+                            trade_payload.to_string(),
+                        )).await;
+                        load_test.complete(&trade.id).await;
+                        continue;
                     }
                     
+                    // Sanity: price magnitude vs the oracle, the one source the price isn't derived from
+                    // Failing trades are stored flagged (like quarantined ones) and not broadcast
+                    if oracle_price.is_some_and(|oracle| orders_apart(trade.price, oracle)) {
+                        sanity.record(SanityFailure::OracleMagnitude, &trade).await;
+                        writer.write_quarantined(trade).await;
+                        continue;
+                    }
+                    
+                    // Circuit breaker: quarantined pairs are stored flagged and not broadcast
+                    match circuit_breaker.check(&pair, &trade.id, trade.price, oracle_price).await {
+                        Verdict::Pass => {}
+                        verdict => {
+                            if let Verdict::Tripped { quarantine, deviating_trade_ids } = verdict {
+                                if let Err(e) = clickhouse.flag_trades_quarantined(&deviating_trade_ids).await {
+                                    eprintln!("⚠️  Failed to flag quarantined trades for {}: {}", pair, e);
+                                }
+                                // The flagged trades are in the in-memory window and recent trades; reseed them without
+                                aggregator.invalidate(&trade.base_symbol, &trade.quote_symbol).await;
+                                recent_trades.invalidate(&trade.base_symbol, &trade.quote_symbol).await;
+                                if let Err(e) = clickhouse.store_admin_event("pair_quarantined", &pair, &serde_json::json!(quarantine)).await {
+                                    eprintln!("⚠️  Failed to record quarantine event for {}: {}", pair, e);
+                                }
+                            }
+                            writer.write_quarantined(trade).await;
+                            continue;
+                        }
+                    }
+                    
+                    // Queue the trade for the next ClickHouse batch; the live stats count it now
+                    // (the writer reseeds the pair if the batch is eventually dropped)
+                    writer.write(trade.clone()).await;
+                    aggregator.record(&trade).await;
+                    recent_trades.record(&trade).await;
+                    tokens.record_trade(&trade.base_symbol, &trade.quote_symbol, trade.timestamp).await;
+                    spikes.record(&trade).await;
+                    candles.record(&trade).await;
+                    
                     // Broadcast via WebSocket
-                    if let Ok(trade_json) = serde_json::to_string(&trade) {
-                        let client_count = ws_manager.broadcast(trade_json.clone()).await;
+                    let trade_payload = schema::with_schema_version(serde_json::json!(trade), CURRENT_SCHEMA_VERSION);
+                    if let Ok(trade_json) = serde_json::to_string(&trade_payload) {
+                        let client_count = ws_manager.broadcast(OutboundMessage::new(
+                            "trades",
+                            Some(format!("{}/{}", trade.base_symbol, trade.quote_symbol)),
+                            trade_json,
+                        ).with_trade_id(&trade.id).with_sequence(trade.sequence)).await;
                         println!("send_trade: {} {:.6} SOL @ ${:.2} to {} clients", 
                             trade.side, trade.amount, trade.price, client_count);
                     }
+                    whales.record(&trade, &trade_payload).await;
+                    
+                    // Let the trader's own authenticated connections know their swap landed
+                    if let Some(trader) = &trade.trader {
+                        // Their balances changed, so the next read refetches
+                        portfolio.invalidate(trader).await;
+                        
+                        let my_fill = serde_json::json!({
+                            "type": "my_fill",
+                            "schema_version": CURRENT_SCHEMA_VERSION,
+                            "trade": trade_payload,
+                        });
+                        let delivered = ws_manager.send_to_wallet(trader, &my_fill.to_string(), None).await;
+                        if delivered > 0 {
+                            println!("🎯 [MY-FILL] Notified {} connection(s) of {}", delivered, trader);
+                        }
+                    }
+                    
+                    // Copy trading: wallets following the trader get it too
+                    follows.on_trade(&trade, &trade_payload).await;
                 }
             }
         }
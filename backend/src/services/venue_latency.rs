@@ -0,0 +1,166 @@
+// Venue latency - per-DEX blockTime→ingest latency and confirmed→finalized time of ingested trades
+
+use crate::models::trade::Trade;
+use crate::services::solana::SolanaService;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Duration, Instant};
+
+/// Most recent samples kept per venue and measure
+const MAX_SAMPLES: usize = 1_000;
+/// Trades waiting for their slot to finalize; the oldest are dropped past this
+const MAX_PENDING: usize = 10_000;
+/// How often the finalized slot is polled while trades are waiting
+const FINALITY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Percentiles of a venue's recent samples, in milliseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyDistribution {
+    pub samples: usize,
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<i64>,
+    pub p90_ms: Option<i64>,
+    pub p99_ms: Option<i64>,
+    pub max_ms: Option<i64>,
+}
+
+impl LatencyDistribution {
+    fn from_samples(samples: &VecDeque<i64>) -> Self {
+        let mut sorted: Vec<i64> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |p: f64| -> Option<i64> {
+            let last = sorted.len().checked_sub(1)?;
+            Some(sorted[((last as f64) * p).round() as usize])
+        };
+        Self {
+            samples: sorted.len(),
+            mean_ms: (!sorted.is_empty()).then(|| sorted.iter().sum::<i64>() as f64 / sorted.len() as f64),
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: sorted.last().copied(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VenueLatencyReport {
+    pub venue: String,
+    /// Block time to the trade entering this service's pipeline
+    pub ingest: LatencyDistribution,
+    /// Seen at `confirmed` to its slot being finalized
+    pub finalization: LatencyDistribution,
+    pub last_trade_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct VenueSamples {
+    ingest_ms: VecDeque<i64>,
+    finalization_ms: VecDeque<i64>,
+    last_trade_at: Option<DateTime<Utc>>,
+}
+
+fn push_sample(samples: &mut VecDeque<i64>, value: i64) {
+    if samples.len() == MAX_SAMPLES {
+        samples.pop_front();
+    }
+    samples.push_back(value);
+}
+
+/// Trade seen at `confirmed` whose slot hasn't finalized yet
+struct PendingFinality {
+    slot: u64,
+    venue: String,
+    seen_at: Instant,
+}
+
+pub struct VenueLatency {
+    solana: SolanaService,
+    venues: Mutex<HashMap<String, VenueSamples>>,
+    pending: Mutex<VecDeque<PendingFinality>>,
+}
+
+impl VenueLatency {
+    pub fn new(solana: SolanaService) -> Self {
+        Self {
+            solana,
+            venues: Mutex::new(HashMap::new()),
+            pending: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Sample a trade as it enters the pipeline
+    /// blockTime has one-second resolution, so ingest latencies are accurate to about a second
+    pub async fn record(&self, trade: &Trade) {
+        let now = Utc::now();
+        {
+            let mut venues = self.venues.lock().await;
+            let venue = venues.entry(trade.dex_program.clone()).or_default();
+            push_sample(&mut venue.ingest_ms, (now - trade.timestamp).num_milliseconds().max(0));
+            venue.last_trade_at = Some(now);
+        }
+
+        let mut pending = self.pending.lock().await;
+        if pending.len() == MAX_PENDING {
+            pending.pop_front();
+        }
+        pending.push_back(PendingFinality {
+            slot: trade.slot,
+            venue: trade.dex_program.clone(),
+            seen_at: Instant::now(),
+        });
+    }
+
+    /// Poll the finalized slot and time the trades waiting on it
+    /// Finalization times are accurate to about the poll interval
+    pub async fn start(self: Arc<Self>) {
+        let mut poll = interval(FINALITY_POLL_INTERVAL);
+        loop {
+            poll.tick().await;
+            if self.pending.lock().await.is_empty() {
+                continue;
+            }
+            let finalized_slot = match self.solana.get_slot().await {
+                Ok(slot) => slot,
+                Err(e) => {
+                    eprintln!("⚠️  [VenueLatency] Failed to read the finalized slot: {}", e);
+                    continue;
+                }
+            };
+
+            let finalized: Vec<PendingFinality> = {
+                let mut pending = self.pending.lock().await;
+                let (done, waiting): (Vec<_>, Vec<_>) = pending.drain(..).partition(|p| p.slot <= finalized_slot);
+                pending.extend(waiting);
+                done
+            };
+            if finalized.is_empty() {
+                continue;
+            }
+            let mut venues = self.venues.lock().await;
+            for trade in finalized {
+                let venue = venues.entry(trade.venue).or_default();
+                push_sample(&mut venue.finalization_ms, trade.seen_at.elapsed().as_millis() as i64);
+            }
+        }
+    }
+
+    /// Latency distributions of every venue that has produced a trade, freshest median first
+    pub async fn report(&self) -> Vec<VenueLatencyReport> {
+        let venues = self.venues.lock().await;
+        let mut reports: Vec<VenueLatencyReport> = venues
+            .iter()
+            .map(|(venue, samples)| VenueLatencyReport {
+                venue: venue.clone(),
+                ingest: LatencyDistribution::from_samples(&samples.ingest_ms),
+                finalization: LatencyDistribution::from_samples(&samples.finalization_ms),
+                last_trade_at: samples.last_trade_at,
+            })
+            .collect();
+        reports.sort_by_key(|r| r.ingest.p50_ms.unwrap_or(i64::MAX));
+        reports
+    }
+}
@@ -0,0 +1,177 @@
+// Wallet performance - realized PnL, equity curve, drawdown and trade statistics reconstructed from
+// a wallet's fills, for copy-trading research
+//
+// Fills are replayed oldest first with average-cost accounting per pair: buys add to the position at
+// their cost, and sells close against the average cost, realizing the difference. Sells beyond the
+// held position (bought before the analysed history, or elsewhere) realize nothing and are counted as
+// unmatched. PnL is in each pair's quote token, converted to USD at the quote's current price, so the
+// equity curve is cumulative realized USD PnL; open positions are not marked to market.
+
+use crate::models::trade::Trade;
+use crate::services::pair_mapping::{parse_pair, supported_pairs};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One point of the equity curve: cumulative realized PnL after a closing fill
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityPoint {
+    pub timestamp: DateTime<Utc>,
+    pub equity_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairPerformance {
+    pub pair: String,
+    pub trades: u64,
+    /// Sells that closed part of a position
+    pub closing_trades: u64,
+    /// In the quote token
+    pub realized_pnl: f64,
+    pub realized_pnl_usd: Option<f64>,
+    /// Base still held at the end of the history, and its average cost in the quote token
+    pub open_position: f64,
+    pub average_cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletPerformance {
+    pub wallet: String,
+    /// Fills analysed, and the range they cover
+    pub trades: u64,
+    pub first_trade: Option<DateTime<Utc>>,
+    pub last_trade: Option<DateTime<Utc>>,
+    pub closing_trades: u64,
+    pub unmatched_sells: u64,
+    pub realized_pnl_usd: f64,
+    /// Share of closing trades with a positive realized PnL
+    pub win_rate: Option<f64>,
+    pub average_trade_usd: Option<f64>,
+    /// Largest fall of the equity curve from a previous peak, in USD and as a share of that peak
+    /// (None when the peak wasn't positive)
+    pub max_drawdown_usd: f64,
+    pub max_drawdown_pct: Option<f64>,
+    pub equity_curve: Vec<EquityPoint>,
+    pub pairs: Vec<PairPerformance>,
+    /// Fills in pairs whose quote token has no USD price; left out of the USD figures
+    pub unpriced_trades: u64,
+}
+
+#[derive(Default)]
+struct Position {
+    trades: u64,
+    closing_trades: u64,
+    base: f64,
+    cost: f64,
+    realized: f64,
+}
+
+/// Orient a stored fill to the supported pair it belongs to (stored orientation follows the swap)
+fn orient(mut trade: Trade, pairs: &[String]) -> Trade {
+    let stored = format!("{}/{}", trade.base_symbol, trade.quote_symbol);
+    let reversed = format!("{}/{}", trade.quote_symbol, trade.base_symbol);
+    if !pairs.contains(&stored) && pairs.contains(&reversed) {
+        trade.invert();
+    }
+    trade
+}
+
+/// Replay a wallet's fills (any order) into performance metrics
+/// `usd_prices` is keyed by token symbol and prices each pair's quote token
+pub fn compute(wallet: &str, trades: Vec<Trade>, usd_prices: &HashMap<String, f64>) -> WalletPerformance {
+    let supported = supported_pairs();
+    let mut trades: Vec<Trade> = trades.into_iter().map(|t| orient(t, &supported)).collect();
+    trades.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.sequence.cmp(&b.sequence)));
+
+    let mut positions: HashMap<String, Position> = HashMap::new();
+    let mut equity = 0.0;
+    let mut peak = 0.0_f64;
+    let (mut max_drawdown_usd, mut max_drawdown_pct) = (0.0_f64, None::<f64>);
+    let mut equity_curve = Vec::new();
+    let (mut wins, mut closing_trades, mut unmatched_sells, mut unpriced_trades) = (0, 0, 0, 0);
+    let (mut traded_usd, mut priced_trades) = (0.0, 0u64);
+
+    for trade in &trades {
+        let pair = format!("{}/{}", trade.base_symbol, trade.quote_symbol);
+        let quote_usd = usd_prices.get(&trade.quote_symbol).copied();
+        let value = trade.price * trade.amount;
+        match quote_usd {
+            Some(usd) => {
+                traded_usd += value * usd;
+                priced_trades += 1;
+            }
+            None => unpriced_trades += 1,
+        }
+
+        let position = positions.entry(pair).or_default();
+        position.trades += 1;
+        match trade.side.as_str() {
+            "buy" => {
+                position.base += trade.amount;
+                position.cost += value;
+            }
+            "sell" => {
+                let closed = trade.amount.min(position.base);
+                if closed <= 0.0 {
+                    unmatched_sells += 1;
+                    continue;
+                }
+                let average_cost = position.cost / position.base;
+                let pnl = (trade.price - average_cost) * closed;
+                position.cost -= average_cost * closed;
+                position.base -= closed;
+                position.realized += pnl;
+                position.closing_trades += 1;
+                closing_trades += 1;
+                if pnl > 0.0 {
+                    wins += 1;
+                }
+
+                let Some(usd) = quote_usd else { continue };
+                equity += pnl * usd;
+                peak = peak.max(equity);
+                let drawdown = peak - equity;
+                if drawdown > max_drawdown_usd {
+                    max_drawdown_usd = drawdown;
+                    max_drawdown_pct = (peak > 0.0).then(|| drawdown / peak * 100.0);
+                }
+                equity_curve.push(EquityPoint { timestamp: trade.timestamp, equity_usd: equity });
+            }
+            _ => {}
+        }
+    }
+
+    let mut pairs: Vec<PairPerformance> = positions
+        .into_iter()
+        .map(|(pair, position)| {
+            let quote_usd = parse_pair(&pair).and_then(|p| usd_prices.get(&p.quote).copied());
+            PairPerformance {
+                trades: position.trades,
+                closing_trades: position.closing_trades,
+                realized_pnl: position.realized,
+                realized_pnl_usd: quote_usd.map(|usd| position.realized * usd),
+                open_position: position.base,
+                average_cost: (position.base > 0.0).then(|| position.cost / position.base),
+                pair,
+            }
+        })
+        .collect();
+    pairs.sort_by(|a, b| b.trades.cmp(&a.trades).then_with(|| a.pair.cmp(&b.pair)));
+
+    WalletPerformance {
+        wallet: wallet.to_string(),
+        trades: trades.len() as u64,
+        first_trade: trades.first().map(|t| t.timestamp),
+        last_trade: trades.last().map(|t| t.timestamp),
+        closing_trades,
+        unmatched_sells,
+        realized_pnl_usd: equity,
+        win_rate: (closing_trades > 0).then(|| wins as f64 / closing_trades as f64),
+        average_trade_usd: (priced_trades > 0).then(|| traded_usd / priced_trades as f64),
+        max_drawdown_usd,
+        max_drawdown_pct,
+        equity_curve,
+        pairs,
+        unpriced_trades,
+    }
+}
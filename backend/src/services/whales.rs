@@ -0,0 +1,100 @@
+// Whale alerts - broadcast trades worth at least WHALE_ALERT_MIN_USD
+//
+// A trade's value is its quote total priced in USD (Jupiter, through the portfolio price cache);
+// stablecoin quotes count at par when no price is available. Whale trades are sent as
+// `whale_alert` events on the global `whales` channel and stored in whale_trades for /api/whales.
+
+use crate::models::schema::CURRENT_SCHEMA_VERSION;
+use crate::models::trade::Trade;
+use crate::services::clickhouse::{chrono_to_time, ClickHouseService, WhaleTradeRow};
+use crate::services::notification_templates::NotificationTemplates;
+use crate::services::pair_mapping::quote_group;
+use crate::services::portfolio::PortfolioService;
+use crate::websocket::messages::OutboundMessage;
+use crate::websocket::ConnectionManager;
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+pub const DEFAULT_MIN_USD: f64 = 50_000.0;
+
+pub struct WhaleAlerts {
+    ws_manager: Arc<ConnectionManager>,
+    clickhouse: Arc<ClickHouseService>,
+    portfolio: Arc<PortfolioService>,
+    templates: Arc<NotificationTemplates>,
+    /// USD value at which a trade is a whale trade; WHALE_ALERT_MIN_USD, default 50000 (0 disables)
+    min_usd: f64,
+}
+
+impl WhaleAlerts {
+    pub fn new(
+        ws_manager: Arc<ConnectionManager>,
+        clickhouse: Arc<ClickHouseService>,
+        portfolio: Arc<PortfolioService>,
+        templates: Arc<NotificationTemplates>,
+        min_usd: f64,
+    ) -> Self {
+        Self { ws_manager, clickhouse, portfolio, templates, min_usd }
+    }
+
+    /// USD value of a trade's quote total; None when the quote token has no price
+    async fn usd_value(&self, trade: &Trade) -> Option<f64> {
+        let quote_mint = trade.quote_mint.clone();
+        let quote_usd = match self.portfolio.usd_prices(std::slice::from_ref(&quote_mint)).await.get(&quote_mint) {
+            Some(price) => *price,
+            None if quote_group("stables").is_some_and(|stables| stables.contains(&trade.quote_symbol.as_str())) => 1.0,
+            None => return None,
+        };
+        Some(trade.total_value * quote_usd)
+    }
+
+    /// Check a broadcast trade; whale trades are sent on the `whales` channel and stored
+    /// `trade_payload` is the trade as broadcast on the `trades` channel
+    pub async fn record(&self, trade: &Trade, trade_payload: &Value) {
+        if self.min_usd <= 0.0 {
+            return;
+        }
+        let Some(usd_value) = self.usd_value(trade).await else { return };
+        if usd_value < self.min_usd {
+            return;
+        }
+
+        let pair = format!("{}/{}", trade.base_symbol, trade.quote_symbol);
+        let event = json!({
+            "type": "whale_alert",
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "pair": pair,
+            "usd_value": usd_value,
+            "threshold_usd": self.min_usd,
+            "trade": trade_payload,
+            "detected_at": Utc::now().to_rfc3339(),
+        });
+        let event = self.templates.with_message("whale_alert", "ws", &event);
+
+        println!("🐋 [Whales] {} {} {:.4} {} (${:.0})", pair, trade.side, trade.amount, trade.base_symbol, usd_value);
+        self.ws_manager.broadcast(OutboundMessage::new("whales", None, event.to_string())).await;
+
+        let row = WhaleTradeRow {
+            timestamp: chrono_to_time(trade.timestamp),
+            id: trade.id.clone(),
+            base_symbol: trade.base_symbol.clone(),
+            quote_symbol: trade.quote_symbol.clone(),
+            side: trade.side.clone(),
+            price: trade.price,
+            amount: trade.amount,
+            total_value: trade.total_value,
+            usd_value,
+            dex_program: trade.dex_program.clone(),
+            slot: trade.slot,
+            trader: trade.trader.clone().unwrap_or_default(),
+        };
+        let clickhouse = self.clickhouse.clone();
+        tokio::spawn(async move {
+            if let Err(e) = clickhouse.store_whale_trade(&row).await {
+                eprintln!("⚠️  [Whales] Failed to store whale trade {}: {}", row.id, e);
+            }
+        });
+    }
+}
+
@@ -0,0 +1,36 @@
+// WebSocket usage sampler - records live subscriber counts per (channel, pair) to ClickHouse every minute
+
+use crate::services::clickhouse::{ClickHouseService, WsUsageSampleRow};
+use crate::websocket::ConnectionManager;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::time::{interval, Duration};
+
+/// How often subscriber counts are sampled; each sample counts as one subscriber-minute per subscriber
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Sample subscription counts while anyone is connected
+pub async fn start(clickhouse: Arc<ClickHouseService>, ws_manager: Arc<ConnectionManager>) {
+    let mut ticker = interval(SAMPLE_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let counts = ws_manager.subscription_counts().await;
+        if counts.is_empty() {
+            continue;
+        }
+
+        let sampled_at = OffsetDateTime::now_utc();
+        let rows: Vec<WsUsageSampleRow> = counts
+            .into_iter()
+            .map(|((channel, pair), subscribers)| WsUsageSampleRow {
+                sampled_at,
+                channel,
+                pair,
+                subscribers,
+            })
+            .collect();
+        if let Err(e) = clickhouse.store_ws_usage_samples(&rows).await {
+            eprintln!("⚠️  [WsUsage] Failed to store usage samples: {}", e);
+        }
+    }
+}
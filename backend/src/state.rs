@@ -1,10 +1,101 @@
 // Application state module
 
+use crate::config::Config;
 use crate::services::clickhouse::ClickHouseService;
+use crate::services::dex_programs::DexProgramRegistry;
+use crate::services::export::ExportService;
+use crate::services::portfolio::PortfolioService;
+use crate::services::submission::SubmissionService;
+use crate::services::simulation::SimulationService;
+use crate::services::swap::SwapService;
+use crate::services::circuit_breaker::CircuitBreaker;
+use crate::services::token_registry::TokenRegistry;
+use crate::services::holders::HolderStatsService;
+use crate::services::slot_clock::SlotClockService;
+use crate::services::query_limits::QueryLimits;
+use crate::services::auth_config::AuthConfig;
+use crate::services::revocations::TokenRevocations;
+use crate::services::signature_checks::SignatureChecks;
+use crate::services::maintenance::MaintenanceService;
+use crate::services::trade_sanity::TradeSanity;
+use crate::services::data_quality::DataQuality;
+use crate::services::aggregator::TradeAggregator;
+use crate::services::recent_trades::RecentTrades;
+use crate::services::response_cache::ResponseCache;
+use crate::services::job_leases::JobLeases;
+use crate::services::data_plans::DataPlans;
+use crate::services::message_signing::MessageSigning;
+use crate::services::feature_flags::FeatureFlags;
+use crate::services::orderbook::OrderBookService;
+use crate::services::venue_latency::VenueLatency;
+use crate::services::stream_health::StreamHealth;
+use crate::services::load_test::LoadTestService;
+use crate::services::memory_budget::MemoryBudget;
+use crate::services::notification_templates::NotificationTemplates;
+use crate::services::cluster::ClusterMembership;
+use crate::services::follows::WalletFollows;
+use crate::models::trade::Trade;
+use crate::websocket::ConnectionManager;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub clickhouse: Arc<ClickHouseService>,
+    pub dex_programs: Arc<DexProgramRegistry>,
+    pub ws_manager: Arc<ConnectionManager>,
+    pub exports: Arc<ExportService>,
+    pub portfolio: Arc<PortfolioService>,
+    pub submissions: Arc<SubmissionService>,
+    pub simulations: Arc<SimulationService>,
+    pub swaps: Arc<SwapService>,
+    pub circuit_breaker: Arc<CircuitBreaker>,
+    pub tokens: Arc<TokenRegistry>,
+    pub holders: Arc<HolderStatsService>,
+    pub slot_clock: Arc<SlotClockService>,
+    pub query_limits: QueryLimits,
+    /// Session token, refresh token and nonce lifetimes
+    pub auth: AuthConfig,
+    /// Session tokens revoked by logout or refresh
+    pub revocations: Arc<TokenRevocations>,
+    /// Sign-in nonces and ingest-signature digests already consumed, so neither can be replayed
+    pub signature_checks: Arc<SignatureChecks>,
+    pub maintenance: Arc<MaintenanceService>,
+    pub trade_sanity: Arc<TradeSanity>,
+    /// Per-pair data-quality scores shown in /api/pairs
+    pub data_quality: Arc<DataQuality>,
+    /// In-memory 24h stats per pair, kept current by the trade stream
+    pub aggregator: Arc<TradeAggregator>,
+    /// Newest trades per pair, serving first-page /api/trades loads
+    pub recent_trades: Arc<RecentTrades>,
+    /// /api/trades, /api/ohlcv and /api/stats bodies reused for a few seconds
+    pub response_cache: Arc<ResponseCache>,
+    pub job_leases: Arc<JobLeases>,
+    /// API key tiers and their WebSocket stream delays
+    pub data_plans: Arc<DataPlans>,
+    /// Keys for signing WebSocket broadcasts of connections that opt in
+    pub message_signing: Arc<MessageSigning>,
+    /// Runtime switches for expensive or experimental endpoints
+    pub features: Arc<FeatureFlags>,
+    /// Live Phoenix order books
+    pub orderbooks: Arc<OrderBookService>,
+    /// Per-venue ingest and finalization latency of the trade stream
+    pub venue_latency: Arc<VenueLatency>,
+    /// QuickNode connection, reconnect backoff and subscription state
+    pub stream_health: Arc<StreamHealth>,
+    /// Synthetic trade generator for /admin/load-test
+    pub load_test: Arc<LoadTestService>,
+    /// Per-pair memory limits and usage of the in-memory caches
+    pub memory_budget: Arc<MemoryBudget>,
+    /// Alert and digest message text per delivery channel
+    pub notification_templates: Arc<NotificationTemplates>,
+    /// Copy-trading follows between wallets
+    pub follows: Arc<WalletFollows>,
+    /// Heartbeats and membership of the replicas sharing ClickHouse
+    pub cluster: Arc<ClusterMembership>,
+    /// Feeds externally parsed trades into the trade stream pipeline
+    pub ingest_tx: mpsc::Sender<Trade>,
+    /// Settings read and validated at startup
+    pub config: Arc<Config>,
 }
 
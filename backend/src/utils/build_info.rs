@@ -0,0 +1,34 @@
+// Build info - what this binary was built from, embedded at compile time (see build.rs)
+
+use crate::models::schema::{CURRENT_SCHEMA_VERSION, SUPPORTED_SCHEMA_VERSIONS};
+use chrono::DateTime;
+use serde_json::{json, Value};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Commit the binary was built from, or "unknown"
+pub const GIT_SHA: &str = env!("BUILD_GIT_SHA");
+/// Unix seconds
+const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+
+/// Cargo features compiled in
+pub fn cargo_features() -> Vec<&'static str> {
+    [("integration", cfg!(feature = "integration")), ("chaos", cfg!(feature = "chaos"))]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Version, commit, build time, compiled-in features and wire schema versions
+pub fn report() -> Value {
+    let built_at = BUILD_TIMESTAMP.parse::<i64>().unwrap_or(0);
+    json!({
+        "version": VERSION,
+        "git_sha": GIT_SHA,
+        "built_at": built_at,
+        "built_at_iso": DateTime::from_timestamp(built_at, 0).map(|t| t.to_rfc3339()),
+        "cargo_features": cargo_features(),
+        "schema_version": CURRENT_SCHEMA_VERSION,
+        "supported_schema_versions": SUPPORTED_SCHEMA_VERSIONS,
+    })
+}
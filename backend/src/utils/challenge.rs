@@ -0,0 +1,143 @@
+// Auth challenge module - the message a wallet signs to sign in, as raw UTF-8 bytes or as a
+// Solana off-chain message (the format Ledger's Solana app will sign)
+
+use sha2::{Digest, Sha256};
+
+/// Challenge formats /auth/nonce can issue; `raw` is the default
+pub const CHALLENGE_FORMATS: &[&str] = &["raw", "offchain"];
+
+/// Signing domain that prefixes every off-chain message, so it can never be a valid transaction
+const OFFCHAIN_SIGNING_DOMAIN: &[u8] = b"\xffsolana offchain";
+/// Largest message body Ledger will sign in the ASCII and UTF-8 formats
+const OFFCHAIN_LEDGER_MAX_LEN: usize = 1_212;
+
+/// Text shown to the user and signed, for either format
+pub fn challenge_text(nonce: &str) -> String {
+    format!("Sign this message to authenticate with Trade: {}", nonce)
+}
+
+/// 32-byte application domain of this app's off-chain messages: sha256("Trade")
+pub fn application_domain() -> [u8; 32] {
+    Sha256::digest(b"Trade").into()
+}
+
+/// Serialize `text` as a version 0 off-chain message signed by `signer`:
+/// signing domain, version, application domain, format, signer count, signers, u16 LE length, body
+/// The format is 0 (restricted ASCII) or 1 (UTF-8) when Ledger can display the body, else 2
+pub fn offchain_message(text: &str, signer: &[u8; 32]) -> Option<Vec<u8>> {
+    let body = text.as_bytes();
+    let length = u16::try_from(body.len()).ok()?;
+    let format: u8 = if body.len() > OFFCHAIN_LEDGER_MAX_LEN {
+        2
+    } else if body.iter().all(|b| (0x20..=0x7e).contains(b)) {
+        0
+    } else {
+        1
+    };
+
+    let mut message = Vec::with_capacity(OFFCHAIN_SIGNING_DOMAIN.len() + 69 + body.len());
+    message.extend_from_slice(OFFCHAIN_SIGNING_DOMAIN);
+    message.push(0);
+    message.extend_from_slice(&application_domain());
+    message.push(format);
+    message.push(1);
+    message.extend_from_slice(signer);
+    message.extend_from_slice(&length.to_le_bytes());
+    message.extend_from_slice(body);
+    Some(message)
+}
+
+/// Byte strings a signature over the challenge may cover, in the order to try them
+/// An `offchain` challenge also accepts the raw text when `raw_fallback` is set, for wallets that
+/// can't sign off-chain messages
+pub fn signed_messages(format: &str, text: &str, signer: &[u8; 32], raw_fallback: bool) -> Vec<Vec<u8>> {
+    match format {
+        "offchain" => {
+            let mut messages: Vec<Vec<u8>> = offchain_message(text, signer).into_iter().collect();
+            if raw_fallback {
+                messages.push(text.as_bytes().to_vec());
+            }
+            messages
+        }
+        _ => vec![text.as_bytes().to_vec()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
+
+    const SIGNER: [u8; 32] = [1; 32];
+
+    #[test]
+    fn offchain_message_encodes_every_field() {
+        let expected = hex::decode(concat!(
+            "ff736f6c616e61206f6666636861696e",                                 // "\xffsolana offchain"
+            "00",                                                               // version 0
+            "9709653fc80ceb83ff874034ac132929f62898d352847637481cb96ac5cf2c76", // sha256("Trade")
+            "00",                                                               // restricted ASCII
+            "01",                                                               // one signer
+            "0101010101010101010101010101010101010101010101010101010101010101", // the signer
+            "0200",                                                             // u16 LE body length
+            "4869",                                                             // "Hi"
+        ))
+        .unwrap();
+        assert_eq!(offchain_message("Hi", &SIGNER).unwrap(), expected);
+        assert_eq!(application_domain().to_vec(), expected[17..49]);
+    }
+
+    #[test]
+    fn body_format_follows_what_ledger_can_display() {
+        // The format byte follows the 16-byte signing domain, version and application domain
+        let format_of = |text: &str| offchain_message(text, &SIGNER).unwrap()[49];
+        assert_eq!(format_of(&challenge_text("abc123")), 0);
+        assert_eq!(format_of("line\nbreak"), 1);
+        assert_eq!(format_of("préférences"), 1);
+        assert_eq!(format_of(&"a".repeat(OFFCHAIN_LEDGER_MAX_LEN)), 0);
+        assert_eq!(format_of(&"a".repeat(OFFCHAIN_LEDGER_MAX_LEN + 1)), 2);
+    }
+
+    #[test]
+    fn bodies_longer_than_a_u16_length_have_no_offchain_message() {
+        let longest = "a".repeat(u16::MAX as usize);
+        let message = offchain_message(&longest, &SIGNER).unwrap();
+        assert_eq!(message[83..85], [0xff, 0xff]);
+        assert!(offchain_message(&"a".repeat(u16::MAX as usize + 1), &SIGNER).is_none());
+        assert!(signed_messages("offchain", &"a".repeat(u16::MAX as usize + 1), &SIGNER, false).is_empty());
+    }
+
+    #[test]
+    fn signed_messages_follow_the_challenge_format() {
+        let text = challenge_text("abc123");
+        let offchain = offchain_message(&text, &SIGNER).unwrap();
+        assert_eq!(signed_messages("raw", &text, &SIGNER, false), vec![text.as_bytes().to_vec()]);
+        assert_eq!(signed_messages("raw", &text, &SIGNER, true), vec![text.as_bytes().to_vec()]);
+        assert_eq!(signed_messages("offchain", &text, &SIGNER, false), vec![offchain.clone()]);
+        // The raw text comes second, after the off-chain message
+        assert_eq!(signed_messages("offchain", &text, &SIGNER, true), vec![offchain, text.as_bytes().to_vec()]);
+    }
+
+    #[test]
+    fn a_wallet_signature_verifies_against_one_of_the_messages() {
+        let wallet = SigningKey::from_bytes(&[9; 32]);
+        let pubkey = wallet.verifying_key().to_bytes();
+        let text = challenge_text("abc123");
+        let verifies = |signature: &Signature, raw_fallback: bool| {
+            signed_messages("offchain", &text, &pubkey, raw_fallback)
+                .iter()
+                .any(|message| wallet.verifying_key().verify(message, signature).is_ok())
+        };
+
+        let offchain_signature = wallet.sign(&offchain_message(&text, &pubkey).unwrap());
+        assert!(verifies(&offchain_signature, false));
+
+        let raw_signature = wallet.sign(text.as_bytes());
+        assert!(!verifies(&raw_signature, false));
+        assert!(verifies(&raw_signature, true));
+
+        // Signed for another signer, the message differs
+        let other_signature = wallet.sign(&offchain_message(&text, &SIGNER).unwrap());
+        assert!(!verifies(&other_signature, true));
+    }
+}
@@ -0,0 +1,187 @@
+// Fault injection - drops, delays and errors at the service boundaries (Solana RPC calls, ClickHouse
+// trade inserts, WebSocket sends) so retries, the circuit breaker and reconnects can be exercised
+//
+// Only active in builds with the `chaos` feature; otherwise `inject` is a no-op. Rates come from
+// CHAOS_RPC, CHAOS_CLICKHOUSE_INSERT and CHAOS_WS_SEND, e.g. `drop=0.1,error=0.05,delay=0.2,delay_ms=500`,
+// or from `configure` in tests (each fault point's tests live next to its call site). Each point rolls its own RNG seeded from CHAOS_SEED (default 0), so a
+// given seed injects the same faults into the same sequence of calls.
+
+// Without the feature only `inject` and the types it returns are used
+#![cfg_attr(not(feature = "chaos"), allow(dead_code))]
+
+use std::fmt;
+
+/// Where a fault can be injected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultPoint {
+    /// Solana JSON-RPC requests
+    Rpc,
+    /// Batched trade inserts into ClickHouse
+    ClickHouseInsert,
+    /// Messages sent to WebSocket clients
+    WsSend,
+}
+
+/// What the call site should do instead of its normal work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Lose the work silently: the request gets no answer, the insert or message never lands
+    Drop,
+    /// Fail the way the dependency would
+    Error,
+}
+
+impl FaultPoint {
+    fn env_var(self) -> &'static str {
+        match self {
+            FaultPoint::Rpc => "CHAOS_RPC",
+            FaultPoint::ClickHouseInsert => "CHAOS_CLICKHOUSE_INSERT",
+            FaultPoint::WsSend => "CHAOS_WS_SEND",
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::Drop => f.write_str("injected drop"),
+            Fault::Error => f.write_str("injected error"),
+        }
+    }
+}
+
+/// Probabilities (0..=1) of each fault per call, and how long an injected delay lasts
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FaultRates {
+    pub drop: f64,
+    pub error: f64,
+    pub delay: f64,
+    pub delay_ms: u64,
+}
+
+/// Default length of an injected delay
+const DEFAULT_DELAY_MS: u64 = 100;
+
+impl FaultRates {
+    /// `drop=0.1,error=0.05,delay=0.2,delay_ms=500`; missing rates are 0
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut rates = FaultRates { delay_ms: DEFAULT_DELAY_MS, ..Default::default() };
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, value) = entry.split_once('=').ok_or_else(|| format!("expected key=value, got '{}'", entry))?;
+            if key.trim() == "delay_ms" {
+                rates.delay_ms = value.trim().parse().map_err(|_| format!("delay_ms '{}' is not a number", value))?;
+                continue;
+            }
+            let rate: f64 = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|r| (0.0..=1.0).contains(r))
+                .ok_or_else(|| format!("{} must be a rate between 0 and 1, got '{}'", key, value))?;
+            match key.trim() {
+                "drop" => rates.drop = rate,
+                "error" => rates.error = rate,
+                "delay" => rates.delay = rate,
+                other => return Err(format!("unknown fault '{}'; expected drop, error, delay or delay_ms", other)),
+            }
+        }
+        if rates.drop + rates.error > 1.0 {
+            return Err("drop and error rates add up to more than 1".to_string());
+        }
+        Ok(rates)
+    }
+}
+
+#[cfg(feature = "chaos")]
+mod active {
+    use super::{Fault, FaultPoint, FaultRates};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::HashMap;
+    use std::sync::{LazyLock, Mutex};
+    use tokio::time::Duration;
+
+    const POINTS: [FaultPoint; 3] = [FaultPoint::Rpc, FaultPoint::ClickHouseInsert, FaultPoint::WsSend];
+
+    struct PointState {
+        rates: FaultRates,
+        rng: StdRng,
+    }
+
+    fn seed() -> u64 {
+        std::env::var("CHAOS_SEED").ok().and_then(|v| v.parse().ok()).unwrap_or(0)
+    }
+
+    fn state(point: FaultPoint, rates: FaultRates) -> PointState {
+        let index = POINTS.iter().position(|p| *p == point).unwrap_or_default() as u64;
+        PointState { rates, rng: StdRng::seed_from_u64(seed().wrapping_add(index)) }
+    }
+
+    static POINT_STATES: LazyLock<Mutex<HashMap<FaultPoint, PointState>>> = LazyLock::new(|| {
+        let mut states = HashMap::new();
+        for point in POINTS {
+            let Ok(raw) = std::env::var(point.env_var()) else { continue };
+            match FaultRates::parse(&raw) {
+                Ok(rates) => {
+                    println!("💥 [Chaos] {:?}: {:?} (seed {})", point, rates, seed());
+                    states.insert(point, state(point, rates));
+                }
+                Err(e) => eprintln!("⚠️  [Chaos] Ignoring {}: {}", point.env_var(), e),
+            }
+        }
+        Mutex::new(states)
+    });
+
+    #[cfg(test)]
+    pub fn configure(point: FaultPoint, rates: Option<FaultRates>) {
+        let mut states = POINT_STATES.lock().unwrap_or_else(|e| e.into_inner());
+        match rates {
+            Some(rates) => states.insert(point, state(point, rates)),
+            None => states.remove(&point),
+        };
+    }
+
+    pub async fn inject(point: FaultPoint) -> Result<(), Fault> {
+        let (delay, fault) = {
+            let mut states = POINT_STATES.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(PointState { rates, rng }) = states.get_mut(&point) else { return Ok(()) };
+            let delay = (rng.r#gen::<f64>() < rates.delay).then(|| Duration::from_millis(rates.delay_ms));
+            let roll = rng.r#gen::<f64>();
+            let fault = if roll < rates.drop {
+                Some(Fault::Drop)
+            } else if roll < rates.drop + rates.error {
+                Some(Fault::Error)
+            } else {
+                None
+            };
+            (delay, fault)
+        };
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        match fault {
+            Some(fault) => {
+                eprintln!("💥 [Chaos] {} at {:?}", fault, point);
+                Err(fault)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Set (or with None, clear) a point's fault rates, restarting its RNG from the seed
+#[cfg(all(test, feature = "chaos"))]
+pub fn configure(point: FaultPoint, rates: Option<FaultRates>) {
+    active::configure(point, rates)
+}
+
+/// Roll for a fault before the call at `point`, after any injected delay; Ok to proceed as usual
+#[cfg(feature = "chaos")]
+pub async fn inject(point: FaultPoint) -> Result<(), Fault> {
+    active::inject(point).await
+}
+
+#[cfg(not(feature = "chaos"))]
+#[inline]
+pub async fn inject(_point: FaultPoint) -> Result<(), Fault> {
+    Ok(())
+}
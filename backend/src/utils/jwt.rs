@@ -1,35 +1,181 @@
 // JWT utility module
 
-use chrono::{Duration, Utc};
-use jsonwebtoken::{encode, EncodingKey, Header};
+use chrono::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 
-// JWT secret key (in production, use environment variable)
-const JWT_SECRET: &str = "your-secret-key-change-in-production";
+/// Used when JWT_SECRET is unset in debug builds; startup preflight warns about it
+pub const DEFAULT_JWT_SECRET: &str = "your-secret-key-change-in-production";
+
+/// Secrets for session and resource tokens, set from the config at startup
+struct Secrets {
+    /// Signs new tokens and verifies them (JWT_SECRET)
+    current: String,
+    /// Only verifies, so tokens signed before a rotation stay valid (JWT_PREVIOUS_SECRET)
+    previous: Option<String>,
+}
+
+static SECRETS: OnceLock<Secrets> = OnceLock::new();
+
+/// Set the signing secret and the optional previous one; only the first call takes effect
+pub fn set_secrets(current: &str, previous: Option<&str>) {
+    let _ = SECRETS.set(Secrets { current: current.to_string(), previous: previous.map(String::from) });
+}
+
+fn secret() -> &'static [u8] {
+    SECRETS.get().map(|s| s.current.as_str()).unwrap_or(DEFAULT_JWT_SECRET).as_bytes()
+}
+
+fn previous_secret() -> Option<&'static [u8]> {
+    SECRETS.get()?.previous.as_deref().map(str::as_bytes)
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // Subject (public key)
     pub exp: usize, // Expiration time
     pub iat: usize, // Issued at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // Set on resource tokens; None for session tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<usize>, // When the wallet signed in, carried over by refreshes (session tokens)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // Unique per session token, so a refresh never reissues the same token
 }
 
-pub fn generate_token(public_key: &str) -> Result<(String, String), anyhow::Error> {
+/// Generate a session token for a wallet that signed in at `signed_in_at`, valid until `expires_at`
+pub fn generate_token(
+    public_key: &str,
+    expires_at: DateTime<Utc>,
+    signed_in_at: DateTime<Utc>,
+) -> Result<(String, String), anyhow::Error> {
     let now = Utc::now();
-    let expires_at = now + Duration::hours(24); // 24 hour expiry
+    let jti: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
     
     let claims = Claims {
         sub: public_key.to_string(),
         exp: expires_at.timestamp() as usize,
         iat: now.timestamp() as usize,
+        scope: None,
+        auth_time: Some(signed_in_at.timestamp() as usize),
+        jti: Some(jti),
+    };
+    
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret()),
+    )?;
+    
+    Ok((token, expires_at.to_rfc3339()))
+}
+
+/// Generate a short-lived token granting access to a single resource (e.g. "export:<id>")
+/// Used to sign download URLs
+pub fn generate_resource_token(resource: &str, ttl: Duration) -> Result<(String, String), anyhow::Error> {
+    let now = Utc::now();
+    let expires_at = now + ttl;
+    
+    let claims = Claims {
+        sub: resource.to_string(),
+        exp: expires_at.timestamp() as usize,
+        iat: now.timestamp() as usize,
+        scope: Some("resource".to_string()),
+        auth_time: None,
+        jti: None,
     };
     
     let token = encode(
         &Header::default(),
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.as_ref()),
+        &EncodingKey::from_secret(secret()),
     )?;
     
     Ok((token, expires_at.to_rfc3339()))
 }
 
+/// Verify a token's signature and expiry, returning its claims
+/// A token whose signature doesn't match the current secret is checked against the previous one
+pub fn verify_token(token: &str) -> Result<Claims, anyhow::Error> {
+    verify_with_secrets(token, secret(), previous_secret())
+}
+
+/// verify_token against explicit secrets; only a signature mismatch falls back to `previous`
+fn verify_with_secrets(token: &str, current: &[u8], previous: Option<&[u8]>) -> Result<Claims, anyhow::Error> {
+    let data = match decode::<Claims>(token, &DecodingKey::from_secret(current), &Validation::default()) {
+        Err(e) if *e.kind() == ErrorKind::InvalidSignature && let Some(previous) = previous => {
+            decode::<Claims>(token, &DecodingKey::from_secret(previous), &Validation::default())?
+        }
+        result => result?,
+    };
+    
+    Ok(data.claims)
+}
+
+impl Claims {
+    /// When the wallet signed in; tokens from before `auth_time` was recorded use their issue time
+    pub fn signed_in_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.auth_time.unwrap_or(self.iat) as i64, 0).unwrap_or_else(Utc::now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURRENT: &[u8] = b"current-secret-current-secret-current";
+    const PREVIOUS: &[u8] = b"previous-secret-previous-secret-previous";
+
+    /// A session token for `wallet` signed with `secret`, expiring `expires_in` from now
+    fn signed_with(secret: &[u8], wallet: &str, expires_in: Duration) -> String {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: wallet.to_string(),
+            exp: (now + expires_in).timestamp() as usize,
+            iat: now.timestamp() as usize,
+            scope: None,
+            auth_time: Some(now.timestamp() as usize),
+            jti: Some("jti".to_string()),
+        };
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    fn error_kind(result: Result<Claims, anyhow::Error>) -> ErrorKind {
+        result.unwrap_err().downcast::<jsonwebtoken::errors::Error>().unwrap().into_kind()
+    }
+
+    #[test]
+    fn tokens_signed_with_either_secret_verify() {
+        let current = signed_with(CURRENT, "wallet-a", Duration::hours(1));
+        let previous = signed_with(PREVIOUS, "wallet-b", Duration::hours(1));
+        assert_eq!(verify_with_secrets(&current, CURRENT, Some(PREVIOUS)).unwrap().sub, "wallet-a");
+        assert_eq!(verify_with_secrets(&previous, CURRENT, Some(PREVIOUS)).unwrap().sub, "wallet-b");
+    }
+
+    #[test]
+    fn previous_secret_is_not_accepted_once_unset() {
+        let previous = signed_with(PREVIOUS, "wallet", Duration::hours(1));
+        assert_eq!(error_kind(verify_with_secrets(&previous, CURRENT, None)), ErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn token_matching_neither_secret_is_rejected() {
+        let forged = signed_with(b"some-other-secret-some-other-secret", "wallet", Duration::hours(1));
+        assert_eq!(error_kind(verify_with_secrets(&forged, CURRENT, Some(PREVIOUS))), ErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn expired_token_is_not_retried_against_the_previous_secret() {
+        // Past the default 60s leeway
+        let expired = signed_with(CURRENT, "wallet", Duration::minutes(-5));
+        // Retried against the previous secret, it would fail on the signature instead
+        assert_eq!(error_kind(verify_with_secrets(&expired, CURRENT, Some(PREVIOUS))), ErrorKind::ExpiredSignature);
+    }
+}
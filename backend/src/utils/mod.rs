@@ -1,4 +1,9 @@
 // Utilities module
 
 pub mod jwt;
+pub mod challenge;
+pub mod template;
+pub mod build_info;
+pub mod shutdown;
 
+pub mod chaos;
@@ -0,0 +1,78 @@
+// Graceful shutdown - SIGTERM or Ctrl-C stops the listener, then tasks holding buffered work
+// (the trade writer's pending batch) finish it before the process exits
+
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::{mpsc, watch};
+use tokio::time::Duration;
+
+struct Shutdown {
+    requested: watch::Sender<bool>,
+    /// Cloned into every drain token; taken when draining, so the receiver closes once the tokens are gone
+    finished_tx: Mutex<Option<mpsc::Sender<()>>>,
+    finished_rx: tokio::sync::Mutex<mpsc::Receiver<()>>,
+}
+
+static SHUTDOWN: LazyLock<Shutdown> = LazyLock::new(|| {
+    let (finished_tx, finished_rx) = mpsc::channel(1);
+    Shutdown {
+        requested: watch::channel(false).0,
+        finished_tx: Mutex::new(Some(finished_tx)),
+        finished_rx: tokio::sync::Mutex::new(finished_rx),
+    }
+});
+
+/// Held by a task with work to finish before exit; drop it once the work is done
+pub struct DrainToken {
+    requested: watch::Receiver<bool>,
+    _finished: Option<mpsc::Sender<()>>,
+}
+
+impl DrainToken {
+    /// Resolves once shutdown has been requested
+    pub async fn requested(&mut self) {
+        let _ = self.requested.wait_for(|requested| *requested).await;
+    }
+}
+
+/// Register a task that `drain` waits for
+pub fn register() -> DrainToken {
+    DrainToken {
+        requested: SHUTDOWN.requested.subscribe(),
+        _finished: SHUTDOWN.finished_tx.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+    }
+}
+
+/// Resolves on SIGTERM or Ctrl-C, and tells registered tasks to finish up
+pub async fn signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    println!("🛑 [Shutdown] Signal received; finishing buffered work");
+    SHUTDOWN.requested.send_replace(true);
+}
+
+/// Wait up to `timeout` for every registered task to drop its token
+pub async fn drain(timeout: Duration) {
+    SHUTDOWN.finished_tx.lock().unwrap_or_else(|e| e.into_inner()).take();
+    let mut finished = SHUTDOWN.finished_rx.lock().await;
+    match tokio::time::timeout(timeout, finished.recv()).await {
+        Ok(_) => println!("✅ [Shutdown] Buffered work finished"),
+        Err(_) => eprintln!("⚠️  [Shutdown] Buffered work still pending after {:?}; exiting anyway", timeout),
+    }
+}
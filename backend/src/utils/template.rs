@@ -0,0 +1,308 @@
+// Message templates - a small handlebars-style subset for rendering notification text from JSON
+//
+//   {{pair}}, {{baseline.minutes}}, {{items.0.name}}  value at a dotted path (null and missing render empty)
+//   {{volume | fixed:2}}, {{pair | upper}}            filters: fixed:N, upper, lower
+//   {{#if portfolio.change}}...{{else}}...{{/if}}     false, null, 0, "" and empty arrays are falsy
+//   {{#each watched_pairs}}{{pair}} {{/each}}         paths inside resolve against the item first,
+//                                                     then outwards; {{this}} and {{@index}} also work
+// Templates are parsed once, so mistakes surface when they are loaded rather than when rendered.
+
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Filter {
+    Fixed(usize),
+    Upper,
+    Lower,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Value { path: Vec<String>, filter: Option<Filter> },
+    If { path: Vec<String>, then: Vec<Node>, otherwise: Vec<Node> },
+    Each { path: Vec<String>, body: Vec<Node> },
+}
+
+/// A parsed template; `source` is kept for display
+#[derive(Debug, Clone)]
+pub struct Template {
+    source: String,
+    nodes: Vec<Node>,
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse_path(raw: &str) -> Result<Vec<String>, String> {
+    let raw = raw.trim();
+    if raw.is_empty() || raw.contains(char::is_whitespace) {
+        return Err(format!("invalid path '{}'", raw));
+    }
+    Ok(raw.split('.').map(str::to_string).collect())
+}
+
+fn parse_filter(raw: &str) -> Result<Filter, String> {
+    match raw.trim().split_once(':') {
+        Some(("fixed", digits)) => digits
+            .trim()
+            .parse()
+            .ok()
+            .filter(|d| *d <= 12)
+            .map(Filter::Fixed)
+            .ok_or_else(|| format!("fixed takes 0 to 12 decimals, got '{}'", digits)),
+        None if raw.trim() == "upper" => Ok(Filter::Upper),
+        None if raw.trim() == "lower" => Ok(Filter::Lower),
+        _ => Err(format!("unknown filter '{}'; expected fixed:N, upper or lower", raw.trim())),
+    }
+}
+
+/// A block being parsed: its kind and path, and the nodes collected so far
+struct OpenBlock {
+    kind: &'static str,
+    path: Vec<String>,
+    nodes: Vec<Node>,
+    /// Nodes before `{{else}}` once one was seen
+    then: Option<Vec<Node>>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut stack: Vec<OpenBlock> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            let current = stack.last_mut().map_or(&mut nodes, |block| &mut block.nodes);
+            if start > 0 {
+                current.push(Node::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find("}}").ok_or("unclosed '{{'")? + start;
+            let tag = rest[start + 2..end].trim();
+            rest = &rest[end + 2..];
+
+            if let Some(path) = tag.strip_prefix("#if ") {
+                stack.push(OpenBlock { kind: "if", path: parse_path(path)?, nodes: Vec::new(), then: None });
+            } else if let Some(path) = tag.strip_prefix("#each ") {
+                stack.push(OpenBlock { kind: "each", path: parse_path(path)?, nodes: Vec::new(), then: None });
+            } else if tag == "else" {
+                let block = stack.last_mut().filter(|b| b.kind == "if" && b.then.is_none()).ok_or("'{{else}}' outside an #if")?;
+                block.then = Some(std::mem::take(&mut block.nodes));
+            } else if let Some(kind) = tag.strip_prefix('/') {
+                let block = stack.pop().ok_or_else(|| format!("'{{{{/{}}}}}' without an opening block", kind))?;
+                if block.kind != kind {
+                    return Err(format!("'{{{{/{}}}}}' closes an #{}", kind, block.kind));
+                }
+                let node = match block.then {
+                    _ if block.kind == "each" => Node::Each { path: block.path, body: block.nodes },
+                    Some(then) => Node::If { path: block.path, then, otherwise: block.nodes },
+                    None => Node::If { path: block.path, then: block.nodes, otherwise: Vec::new() },
+                };
+                stack.last_mut().map_or(&mut nodes, |block| &mut block.nodes).push(node);
+            } else if tag.starts_with('#') {
+                return Err(format!("unknown block '{}'; expected #if or #each", tag));
+            } else {
+                let (path, filter) = match tag.split_once('|') {
+                    Some((path, filter)) => (path, Some(parse_filter(filter)?)),
+                    None => (tag, None),
+                };
+                let current = stack.last_mut().map_or(&mut nodes, |block| &mut block.nodes);
+                current.push(Node::Value { path: parse_path(path)?, filter });
+            }
+        }
+        if let Some(block) = stack.last() {
+            return Err(format!("unclosed #{} block", block.kind));
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+
+        Ok(Self { source: source.to_string(), nodes })
+    }
+
+    pub fn render(&self, data: &Value) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, &[Scope { value: data, index: None }], &mut out);
+        out
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Scope<'a> {
+    value: &'a Value,
+    /// Position in the enclosing #each
+    index: Option<usize>,
+}
+
+fn lookup<'a>(path: &[String], scopes: &[Scope<'a>]) -> Option<Value> {
+    let innermost = scopes.last()?;
+    match path {
+        [only] if only == "this" => return Some(innermost.value.clone()),
+        [only] if only == "@index" => return innermost.index.map(Value::from),
+        _ => {}
+    }
+    scopes.iter().rev().find_map(|scope| {
+        path.iter()
+            .try_fold(scope.value, |value, key| match value {
+                Value::Object(map) => map.get(key),
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => None,
+            })
+            .cloned()
+    })
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(b)) => *b,
+        Some(Value::Number(n)) => n.as_f64().is_some_and(|n| n != 0.0),
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Object(_)) => true,
+    }
+}
+
+fn format_value(value: &Value, filter: Option<Filter>) -> String {
+    let text = match (value, filter) {
+        (Value::Null, _) => String::new(),
+        (Value::Number(n), Some(Filter::Fixed(decimals))) => format!("{:.*}", decimals, n.as_f64().unwrap_or_default()),
+        (Value::String(s), _) => s.clone(),
+        (other, _) => other.to_string(),
+    };
+    match filter {
+        Some(Filter::Upper) => text.to_uppercase(),
+        Some(Filter::Lower) => text.to_lowercase(),
+        _ => text,
+    }
+}
+
+fn render_nodes(nodes: &[Node], scopes: &[Scope], out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value { path, filter } => {
+                if let Some(value) = lookup(path, scopes) {
+                    out.push_str(&format_value(&value, *filter));
+                }
+            }
+            Node::If { path, then, otherwise } => {
+                let branch = if truthy(lookup(path, scopes).as_ref()) { then } else { otherwise };
+                render_nodes(branch, scopes, out);
+            }
+            Node::Each { path, body } => {
+                let Some(Value::Array(items)) = lookup(path, scopes) else { continue };
+                for (index, item) in items.iter().enumerate() {
+                    let mut inner = scopes.to_vec();
+                    inner.push(Scope { value: item, index: Some(index) });
+                    render_nodes(body, &inner, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(source: &str, data: Value) -> String {
+        Template::parse(source).unwrap().render(&data)
+    }
+
+    fn parse_error(source: &str) -> String {
+        Template::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn values_resolve_dotted_paths_and_array_indices() {
+        let data = json!({ "pair": "SOL/USDC", "baseline": { "minutes": 15 }, "items": [{ "name": "first" }] });
+        assert_eq!(render("{{pair}} over {{ baseline.minutes }}m, {{items.0.name}}", data), "SOL/USDC over 15m, first");
+    }
+
+    #[test]
+    fn null_and_missing_values_render_empty() {
+        assert_eq!(render("[{{gone}}][{{nothing}}][{{pair.deeper}}]", json!({ "nothing": null, "pair": "SOL/USDC" })), "[][][]");
+    }
+
+    #[test]
+    fn filters_format_the_value() {
+        let data = json!({ "volume": 1234.5678, "pair": "sol/usdc", "venue": "Raydium" });
+        assert_eq!(render("{{volume | fixed:2}} {{volume|fixed:0}}", data.clone()), "1234.57 1235");
+        assert_eq!(render("{{pair | upper}} {{venue | lower}}", data), "SOL/USDC raydium");
+    }
+
+    #[test]
+    fn if_picks_a_branch_by_truthiness() {
+        let template = Template::parse("{{#if change}}up {{change}}{{else}}flat{{/if}}").unwrap();
+        for falsy in [json!(false), json!(null), json!(0), json!(""), json!([])] {
+            assert_eq!(template.render(&json!({ "change": falsy })), "flat");
+        }
+        assert_eq!(template.render(&json!({})), "flat");
+        assert_eq!(template.render(&json!({ "change": 3 })), "up 3");
+        assert_eq!(template.render(&json!({ "change": { "pct": 1 } })), "up {\"pct\":1}");
+        assert_eq!(render("{{#if on}}shown{{/if}}.", json!({ "on": false })), ".");
+    }
+
+    #[test]
+    fn each_renders_items_with_this_and_index() {
+        let data = json!({ "pairs": ["SOL/USDC", "JUP/SOL"] });
+        assert_eq!(render("{{#each pairs}}{{@index}}={{this}} {{/each}}", data), "0=SOL/USDC 1=JUP/SOL ");
+        assert_eq!(render("[{{#each pairs}}x{{/each}}]", json!({ "pairs": "not a list" })), "[]");
+    }
+
+    #[test]
+    fn paths_inside_each_resolve_against_the_item_then_outwards() {
+        let data = json!({
+            "currency": "USDC",
+            "pair": "outer",
+            "watched": [{ "pair": "SOL/USDC", "change": 2 }, { "pair": "JUP/SOL" }],
+        });
+        assert_eq!(
+            render("{{#each watched}}{{pair}} {{change}}{{currency}};{{/each}} {{pair}}", data),
+            "SOL/USDC 2USDC;JUP/SOL USDC; outer"
+        );
+    }
+
+    #[test]
+    fn blocks_nest() {
+        let data = json!({
+            "groups": [
+                { "name": "stables", "pairs": [{ "pair": "SOL/USDC", "hot": true }, { "pair": "SOL/USDT", "hot": false }] },
+                { "name": "empty", "pairs": [] },
+            ],
+        });
+        let source = "{{#each groups}}{{name}}:{{#if pairs}}{{#each pairs}}{{#if hot}}*{{else}}-{{/if}}{{pair}}@{{@index}} {{/each}}{{else}}none{{/if}};{{/each}}";
+        assert_eq!(render(source, data), "stables:*SOL/USDC@0 -SOL/USDT@1 ;empty:none;");
+    }
+
+    #[test]
+    fn text_without_tags_renders_as_is() {
+        assert_eq!(render("plain } text {", json!({})), "plain } text {");
+        assert_eq!(Template::parse("{{pair}}").unwrap().to_string(), "{{pair}}");
+    }
+
+    #[test]
+    fn malformed_templates_fail_to_parse() {
+        assert_eq!(parse_error("{{pair"), "unclosed '{{'");
+        assert_eq!(parse_error("{{#if on}}x"), "unclosed #if block");
+        assert_eq!(parse_error("{{#each items}}{{#if on}}x{{/if}}"), "unclosed #each block");
+        assert_eq!(parse_error("x{{/if}}"), "'{{/if}}' without an opening block");
+        assert_eq!(parse_error("{{#each items}}x{{/if}}"), "'{{/if}}' closes an #each");
+        assert_eq!(parse_error("{{else}}"), "'{{else}}' outside an #if");
+        assert_eq!(parse_error("{{#each items}}{{else}}{{/each}}"), "'{{else}}' outside an #if");
+        assert_eq!(parse_error("{{#if on}}a{{else}}b{{else}}c{{/if}}"), "'{{else}}' outside an #if");
+        assert_eq!(parse_error("{{#unless on}}x{{/unless}}"), "unknown block '#unless on'; expected #if or #each");
+        assert_eq!(parse_error("{{two words}}"), "invalid path 'two words'");
+        assert_eq!(parse_error("{{}}"), "invalid path ''");
+        assert_eq!(parse_error("{{#if }}x{{/if}}"), "unknown block '#if'; expected #if or #each");
+        assert_eq!(parse_error("{{pair | title}}"), "unknown filter 'title'; expected fixed:N, upper or lower");
+        assert_eq!(parse_error("{{volume | fixed:13}}"), "fixed takes 0 to 12 decimals, got '13'");
+        assert_eq!(parse_error("{{volume | fixed:two}}"), "fixed takes 0 to 12 decimals, got 'two'");
+    }
+}
@@ -0,0 +1,63 @@
+// Trade batching module - groups a connection's trades into one `trade_batch` frame per flush
+// interval, for clients following busy pairs (opt-in with ?batch_ms=)
+
+use tokio::time::{Duration, Instant};
+
+/// Accepted flush intervals for ?batch_ms=
+pub const MIN_BATCH_MS: u64 = 50;
+pub const MAX_BATCH_MS: u64 = 100;
+/// A batch is flushed early once it holds this many trades
+const MAX_BATCH_TRADES: usize = 500;
+
+/// Flush interval from ?batch_ms=
+pub fn parse_batch_ms(raw: &str) -> Result<Duration, String> {
+    raw.trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|ms| (MIN_BATCH_MS..=MAX_BATCH_MS).contains(ms))
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("batch_ms must be between {} and {}", MIN_BATCH_MS, MAX_BATCH_MS))
+}
+
+/// Trades waiting for the next flush, already in the connection's schema version
+pub struct TradeBatcher {
+    interval: Duration,
+    trades: Vec<String>,
+    /// When the pending batch is due; None while it's empty
+    deadline: Option<Instant>,
+}
+
+impl TradeBatcher {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            trades: Vec::new(),
+            deadline: None,
+        }
+    }
+
+    /// Add a trade; returns true when the batch is full and should be flushed now
+    pub fn push(&mut self, trade: String) -> bool {
+        self.deadline.get_or_insert_with(|| Instant::now() + self.interval);
+        self.trades.push(trade);
+        self.trades.len() >= MAX_BATCH_TRADES
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// The pending trades as one `trade_batch` message, oldest first; None when there are none
+    pub fn take(&mut self) -> Option<String> {
+        self.deadline = None;
+        if self.trades.is_empty() {
+            return None;
+        }
+        let trades = std::mem::take(&mut self.trades);
+        Some(format!(
+            r#"{{"type":"trade_batch","count":{},"trades":[{}]}}"#,
+            trades.len(),
+            trades.join(",")
+        ))
+    }
+}
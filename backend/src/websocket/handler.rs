@@ -1,64 +1,182 @@
 // WebSocket handler module
 
 use axum::{
-    extract::{ws::WebSocket, State, WebSocketUpgrade},
-    response::Response,
+    extract::{ws::WebSocket, Query, State, WebSocketUpgrade},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
 };
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{stream::SplitSink, Sink, SinkExt, StreamExt};
+use serde_json::json;
+use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::time::{sleep_until, Duration, Instant};
 use uuid::Uuid;
 
-use crate::websocket::manager::ConnectionManager;
+use crate::models::schema;
+use crate::services::data_plans::DataPlan;
+use crate::services::export::MAX_EXPORT_ROWS;
+use crate::services::feature_flags::FeatureOff;
+use crate::services::message_signing::ConnectionSigner;
+use crate::services::pair_mapping::{parse_pair, supported_pair};
+use crate::services::sampling::TradeSample;
+use crate::state::AppState;
+use crate::utils::chaos::{self, Fault, FaultPoint};
+use crate::utils::jwt;
+use crate::websocket::manager::{Subscription, MAX_SUBSCRIPTIONS_PER_CONNECTION};
+use crate::websocket::messages::{self, ErrorCode, OutboundMessage, ALL_PAIRS, GLOBAL_CHANNELS};
+use crate::websocket::batching::{parse_batch_ms, TradeBatcher};
+use crate::websocket::pause::{HeldMessages, PauseMode};
+
+/// Maximum number of settings keys stored per wallet
+const MAX_SETTINGS_KEYS: usize = 32;
+/// Maximum size of a single JSON-encoded settings value
+const MAX_SETTING_VALUE_BYTES: usize = 8 * 1024;
 
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
-    State(manager): State<Arc<ConnectionManager>>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
 ) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, manager))
+    // Clients may pin a wire schema version on the URL (?schema_version=1)
+    let schema_version = match schema::negotiate(params.get("schema_version").map(|s| s.as_str())) {
+        Ok(version) => version,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Unsupported schema version",
+                    "message": message
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    // Light consumers may take a deterministic 1-in-N sample of trades (?sample=1/N)
+    let sample = match params.get("sample").map(|raw| TradeSample::parse(raw)).transpose() {
+        Ok(sample) => sample,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Invalid sample",
+                    "message": message
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    // The API key's data plan sets how far behind real time the stream runs
+    // Browsers can't set headers on a WebSocket handshake, so ?api_key= works too
+    let api_key = headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .or(params.get("api_key").map(|s| s.as_str()));
+    let plan = match state.data_plans.for_api_key(api_key) {
+        Ok(plan) => plan,
+        Err(message) => {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({
+                    "error": "Invalid API key",
+                    "message": message
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    // Relays that need to prove provenance can have every broadcast signed (?sign=hmac|ed25519)
+    let signer = match params.get("sign").map(|scheme| state.message_signing.signer(scheme, api_key)).transpose() {
+        Ok(signer) => signer,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Invalid signing option",
+                    "message": message
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    // Clients on busy pairs can take trades in one `trade_batch` frame per interval (?batch_ms=50..100)
+    let batch = match params.get("batch_ms").map(|raw| parse_batch_ms(raw)).transpose() {
+        Ok(batch) => batch,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Invalid batch interval",
+                    "message": message
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    ws.on_upgrade(move |socket| handle_socket(socket, state, schema_version, sample, plan, signer, batch))
 }
 
-async fn handle_socket(socket: WebSocket, manager: Arc<ConnectionManager>) {
+async fn handle_socket(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    schema_version: u32,
+    sample: Option<TradeSample>,
+    plan: DataPlan,
+    signer: Option<ConnectionSigner>,
+    batch: Option<Duration>,
+) {
     let connection_id = Uuid::new_v4();
-    println!("🔌 New WebSocket connection: {}", connection_id);
+    println!("🔌 New WebSocket connection: {} (schema v{}, plan {}{}{}{})", connection_id, schema_version, plan.name,
+        sample.map(|s| format!(", sample {}", s)).unwrap_or_default(),
+        if signer.is_some() { ", signed" } else { "" },
+        batch.map(|b| format!(", batched {}ms", b.as_millis())).unwrap_or_default());
 
+    let manager = state.ws_manager.clone();
     let (mut sender, mut receiver) = socket.split();
-    let mut broadcast_rx = manager.add_connection(connection_id).await;
 
-    // Channel for ping/pong handling
-    let (ping_tx, mut ping_rx) = tokio::sync::mpsc::unbounded_channel();
+    // Channel for direct replies (pongs, acks/nacks, private pushes)
+    let (reply_tx, mut reply_rx) = tokio::sync::mpsc::unbounded_channel();
+    // Heartbeat pings skip the reply queue, so a backlog of replies or trades can't hold them back
+    let (ping_tx, mut ping_rx) = tokio::sync::mpsc::channel::<()>(1);
+    let mut receivers = manager.add_connection(connection_id, reply_tx.clone(), plan.delay).await;
+    let Some(mut pause_rx) = manager.pause_updates(connection_id).await else { return };
+
+    // Tell delayed connections up front that market data arrives late
+    if !plan.delay.is_zero() {
+        let notice = json!({
+            "type": "data_plan",
+            "plan": plan.name,
+            "delay_ms": plan.delay.as_millis() as u64,
+        });
+        let _ = sender.send(axum::extract::ws::Message::Text(notice.to_string().into())).await;
+    }
+
+    // Warn clients pinned to a deprecated schema version once on connect
+    if let Some(notice) = schema::deprecation_notice(schema_version) {
+        let _ = sender.send(axum::extract::ws::Message::Text(notice.to_string().into())).await;
+    }
 
     // Task to receive messages from client
     let manager_clone = manager.clone();
+    let state_clone = state.clone();
     let connection_id_clone = connection_id;
-    let ping_tx_clone = ping_tx.clone();
+    let reply_tx_clone = reply_tx.clone();
     
-    let receive_task = tokio::spawn(async move {
+    let mut receive_task = tokio::spawn(async move {
         while let Some(msg) = receiver.next().await {
             match msg {
                 Ok(axum::extract::ws::Message::Text(text)) => {
                     println!("📥 Received from {}: {}", connection_id_clone, text);
-                    // Handle client messages (e.g., pair selection)
-                    if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&text) {
-                        if let Some(msg_type) = msg.get("type").and_then(|v| v.as_str()) {
-                            if msg_type == "select_pair" {
-                                if let Some(pair) = msg.get("pair").and_then(|v| v.as_str()) {
-                                    println!("📊 Pair selection received: {}", pair);
-                                    let old_pair = manager_clone.get_selected_pair().await;
-                                    manager_clone.set_selected_pair(pair.to_string()).await;
-                                    let new_pair = manager_clone.get_selected_pair().await;
-                                    println!("✅ Pair updated: {} -> {}", old_pair, new_pair);
-                                } else {
-                                    eprintln!("⚠️  Pair selection message missing 'pair' field");
-                                }
-                            } else {
-                                println!("ℹ️  Received message type: {}", msg_type);
-                            }
-                        } else {
-                            eprintln!("⚠️  Received message missing 'type' field");
-                        }
-                    } else {
-                        eprintln!("⚠️  Failed to parse message as JSON: {}", text);
-                    }
+                    // Handle client messages (pair selection, subscriptions) and reply with ack/nack
+                    let reply = handle_client_message(&state_clone, connection_id_clone, &text).await;
+                    let _ = reply_tx_clone.send(axum::extract::ws::Message::Text(reply.into()));
                 }
                 Ok(axum::extract::ws::Message::Close(_)) => {
                     println!("🔌 Connection closed: {}", connection_id_clone);
@@ -66,10 +184,10 @@ async fn handle_socket(socket: WebSocket, manager: Arc<ConnectionManager>) {
                 }
                 Ok(axum::extract::ws::Message::Ping(data)) => {
                     // Send pong response via channel
-                    let _ = ping_tx_clone.send(axum::extract::ws::Message::Pong(data));
+                    let _ = reply_tx_clone.send(axum::extract::ws::Message::Pong(data));
                 }
                 Ok(axum::extract::ws::Message::Pong(_)) => {
-                    // Pong received, no action needed
+                    manager_clone.record_pong(connection_id_clone).await;
                 }
                 Err(e) => {
                     eprintln!("WebSocket error: {}", e);
@@ -81,71 +199,529 @@ async fn handle_socket(socket: WebSocket, manager: Arc<ConnectionManager>) {
         manager_clone.remove_connection(connection_id_clone).await;
     });
 
-    // Task to send messages to client (both broadcasts and pongs)
-    let send_task = tokio::spawn(async move {
+    // Task to send messages to client (both broadcasts and direct replies)
+    let manager_for_send = manager.clone();
+    let mut send_task = tokio::spawn(async move {
+        // Broadcasts held while the client has paused delivery, and when the pause started
+        let mut held: Option<(HeldMessages, std::time::Instant)> = None;
+        // Trades waiting for the next batch flush; a pending batch is always sent before anything else
+        let mut batcher = batch.map(TradeBatcher::new);
         loop {
-            tokio::select! {
-                // Handle broadcast messages
-                result = broadcast_rx.recv() => {
-                    match result {
-                        Ok(msg) => {
-                            // Log when messages are sent to client (only first few)
-                            static SEND_COUNT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
-                            let send_count = SEND_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                            if send_count < 5 {
-                                // Try to parse as trade to log it
-                                if let Ok(trade) = serde_json::from_str::<serde_json::Value>(&msg) {
-                                    if let (Some(id), Some(side), Some(amount), Some(price)) = (
-                                        trade.get("id").and_then(|v| v.as_str()),
-                                        trade.get("side").and_then(|v| v.as_str()),
-                                        trade.get("amount").and_then(|v| v.as_f64()),
-                                        trade.get("price").and_then(|v| v.as_f64()),
-                                    ) {
-                                        let base_symbol = trade.get("base_symbol").and_then(|v| v.as_str()).unwrap_or("?");
-                                        let quote_symbol = trade.get("quote_symbol").and_then(|v| v.as_str()).unwrap_or("?");
-                                        if side == "price" {
-                                            println!("📤 [WS-SEND] Sending price update to client {}: {} {} @ ${:.6} (ID: {})", 
-                                                connection_id, base_symbol, quote_symbol, price, &id[..16.min(id.len())]);
-                                        } else {
-                                            println!("📤 [WS-SEND] Sending trade to client {}: {} {:.6} {} @ ${:.6} (ID: {})", 
-                                                connection_id, side, amount, base_symbol, price, &id[..16.min(id.len())]);
-                                        }
-                                    }
+            let batch_deadline = batcher.as_ref().and_then(|b| b.deadline());
+            let outbound = tokio::select! {
+                // Polled in order: pause changes, heartbeat pings and batch flushes first, then direct replies
+                // (acks, errors, pongs), then trades, which always win over price ticks
+                biased;
+                changed = pause_rx.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    let mode = *pause_rx.borrow_and_update();
+                    match (mode, held.take()) {
+                        (Some(mode), None) => held = Some((HeldMessages::new(mode), std::time::Instant::now())),
+                        (None, Some((messages, paused_at))) => {
+                            // Replay in arrival order, then mark the end of the replay
+                            let (messages, dropped) = messages.release();
+                            let replayed = messages.len();
+                            // A batch pending from before the pause goes first
+                            let mut failed = !flush_batch(&mut sender, &mut batcher, &signer).await;
+                            for outbound in messages {
+                                if failed {
+                                    break;
                                 }
+                                let msg = render(&outbound, schema_version, &signer);
+                                failed = sender.send(axum::extract::ws::Message::Text(msg.into())).await.is_err();
                             }
-                            
-                            if sender.send(axum::extract::ws::Message::Text(msg.into())).await.is_err() {
-                                println!("❌ [WS-SEND] Failed to send message to client {}", connection_id);
+                            let resumed = json!({
+                                "type": "resumed",
+                                "replayed": replayed,
+                                "dropped": dropped,
+                                "paused_ms": paused_at.elapsed().as_millis() as u64,
+                            });
+                            if failed || sender.send(axum::extract::ws::Message::Text(resumed.to_string().into())).await.is_err() {
                                 break;
                             }
                         }
-                        Err(_) => {
-                            // Broadcast channel closed or lagged
+                        (_, current) => held = current,
+                    }
+                    continue;
+                }
+                Some(()) = ping_rx.recv() => {
+                    if sender.send(axum::extract::ws::Message::Ping(Default::default())).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                _ = sleep_until(batch_deadline.unwrap_or_else(Instant::now)), if batch_deadline.is_some() => {
+                    if !flush_batch(&mut sender, &mut batcher, &signer).await {
+                        break;
+                    }
+                    continue;
+                }
+                // Handle direct replies (pongs, acks/nacks)
+                Some(reply_msg) = reply_rx.recv() => {
+                    if !flush_batch(&mut sender, &mut batcher, &signer).await || sender.send(reply_msg).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                result = receivers.trades.recv() => {
+                    match result {
+                        Ok(outbound) => outbound,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            eprintln!("⚠️  [WS-SEND] Client {} lagged, {} trades dropped", connection_id, skipped);
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
                             println!("⚠️  [WS-SEND] Broadcast channel closed for client {}", connection_id);
                             break;
                         }
                     }
                 }
-                // Handle ping/pong
-                Some(pong_msg) = ping_rx.recv() => {
-                    if sender.send(pong_msg).await.is_err() {
-                        break;
+                result = receivers.prices.recv() => {
+                    match result {
+                        Ok(outbound) => outbound,
+                        // Stale ticks are superseded by newer ones, drop them silently
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => {
+                            println!("⚠️  [WS-SEND] Price channel closed for client {}", connection_id);
+                            break;
+                        }
                     }
                 }
+            };
+            
+            // Skip pairs/channels this client hasn't subscribed to
+            if !manager_for_send.should_deliver(connection_id, &outbound).await {
+                continue;
+            }
+            // Sampled connections only get the trades in their sample
+            if let (Some(sample), Some(trade_id)) = (&sample, &outbound.trade_id)
+                && !sample.includes(trade_id)
+            {
+                continue;
+            }
+            
+            // Paused clients get it when they resume
+            if let Some((messages, _)) = held.as_mut() {
+                messages.hold(outbound);
+                continue;
+            }
+            
+            // Batched connections collect trades until the flush; anything else goes out after the pending batch
+            if let Some(pending) = batcher.as_mut()
+                && outbound.channel == "trades"
+            {
+                let full = pending.push(convert(&outbound, schema_version));
+                if full && !flush_batch(&mut sender, &mut batcher, &signer).await {
+                    break;
+                }
+                continue;
+            }
+            if !flush_batch(&mut sender, &mut batcher, &signer).await {
+                break;
+            }
+            
+            let msg = render(&outbound, schema_version, &signer);
+            
+            // Log when messages are sent to client (only first few)
+            static SEND_COUNT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+            let send_count = SEND_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            if send_count < 5 {
+                // Try to parse as trade to log it
+                if let Ok(trade) = serde_json::from_str::<serde_json::Value>(&msg) {
+                    if let (Some(id), Some(side), Some(amount), Some(price)) = (
+                        trade.get("id").and_then(|v| v.as_str()),
+                        trade.get("side").and_then(|v| v.as_str()),
+                        trade.get("amount").and_then(|v| v.as_f64()),
+                        trade.get("price").and_then(|v| v.as_f64()),
+                    ) {
+                        let base_symbol = trade.get("base_symbol").and_then(|v| v.as_str()).unwrap_or("?");
+                        let quote_symbol = trade.get("quote_symbol").and_then(|v| v.as_str()).unwrap_or("?");
+                        if side == "price" {
+                            println!("📤 [WS-SEND] Sending price update to client {}: {} {} @ ${:.6} (ID: {})", 
+                                connection_id, base_symbol, quote_symbol, price, &id[..16.min(id.len())]);
+                        } else {
+                            println!("📤 [WS-SEND] Sending trade to client {}: {} {:.6} {} @ ${:.6} (ID: {})", 
+                                connection_id, side, amount, base_symbol, price, &id[..16.min(id.len())]);
+                        }
+                    }
+                }
+            }
+            
+            if !send_text(&mut sender, msg).await {
+                println!("❌ [WS-SEND] Failed to send message to client {}", connection_id);
+                break;
             }
         }
     });
 
-    // Wait for either task to complete
+    // Server pings on their own channel; a client that silently went away stops answering them.
+    // Runs apart from the send task, so a send stuck on a dead socket doesn't stop the reaping
+    let heartbeat = manager.heartbeat;
+    let manager_for_heartbeat = manager.clone();
+    let heartbeat_task = async move {
+        let mut ticks = tokio::time::interval_at(Instant::now() + heartbeat.interval, heartbeat.interval);
+        let mut last_ping: Option<Instant> = None;
+        let mut missed = 0;
+        loop {
+            ticks.tick().await;
+            let Some(last_pong) = manager_for_heartbeat.last_pong(connection_id).await else { return };
+            match last_ping {
+                Some(sent) if last_pong < sent => missed += 1,
+                _ => missed = 0,
+            }
+            if missed >= heartbeat.max_missed {
+                println!("💀 [WS-HEARTBEAT] Reaping {}: {} heartbeats unanswered", connection_id, missed);
+                return;
+            }
+            // A ping still queued is as good as a new one
+            if let Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) = ping_tx.try_send(()) {
+                return;
+            }
+            last_ping = Some(Instant::now());
+        }
+    };
+
+    // Wait for either task to complete (or the heartbeat to give up), then stop the other
     tokio::select! {
-        _ = receive_task => {
+        _ = &mut receive_task => {
             println!("📥 Receive task completed for {}", connection_id);
         }
-        _ = send_task => {
+        _ = &mut send_task => {
             println!("📤 Send task completed for {}", connection_id);
         }
+        _ = heartbeat_task => {}
     }
+    receive_task.abort();
+    send_task.abort();
 
     manager.remove_connection(connection_id).await;
 }
 
+
+/// A broadcast converted to the connection's pinned schema version if that's older (dual-emit)
+fn convert(outbound: &OutboundMessage, schema_version: u32) -> String {
+    if schema_version == schema::CURRENT_SCHEMA_VERSION {
+        outbound.payload.clone()
+    } else {
+        match serde_json::from_str::<serde_json::Value>(&outbound.payload) {
+            Ok(value) => schema::downgrade(value, schema_version).to_string(),
+            Err(_) => outbound.payload.clone(),
+        }
+    }
+}
+
+/// Wire form of a broadcast for one connection: converted, and signed if it asked for signatures
+fn render(outbound: &OutboundMessage, schema_version: u32, signer: &Option<ConnectionSigner>) -> String {
+    let msg = convert(outbound, schema_version);
+    match signer {
+        Some(signer) => signer.sign(&msg),
+        None => msg,
+    }
+}
+
+/// Send the pending trade batch, if any, signed as one message; false once the socket is gone
+async fn flush_batch(
+    sender: &mut SplitSink<WebSocket, axum::extract::ws::Message>,
+    batcher: &mut Option<TradeBatcher>,
+    signer: &Option<ConnectionSigner>,
+) -> bool {
+    let Some(batch) = batcher.as_mut().and_then(|b| b.take()) else { return true };
+    let msg = match signer {
+        Some(signer) => signer.sign(&batch),
+        None => batch,
+    };
+    send_text(sender, msg).await
+}
+
+/// Send a text frame through the WsSend fault point; false once the socket is gone
+/// Injected drops lose the message but keep the socket; injected errors fail it
+async fn send_text<S: Sink<axum::extract::ws::Message> + Unpin>(sender: &mut S, msg: String) -> bool {
+    match chaos::inject(FaultPoint::WsSend).await {
+        Ok(()) => sender.send(axum::extract::ws::Message::Text(msg.into())).await.is_ok(),
+        Err(Fault::Drop) => true,
+        Err(Fault::Error) => false,
+    }
+}
+
+/// Handle a text message from the client and build the ack/nack reply
+async fn handle_client_message(
+    state: &AppState,
+    connection_id: Uuid,
+    text: &str,
+) -> String {
+    let manager = &state.ws_manager;
+    let msg = match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(msg) => msg,
+        Err(_) => {
+            eprintln!("⚠️  Failed to parse message as JSON: {}", text);
+            return messages::nack("unknown", &serde_json::Value::Null, ErrorCode::InvalidMessage, "Message must be valid JSON");
+        }
+    };
+    
+    let request_id = msg.get("request_id").cloned().unwrap_or(serde_json::Value::Null);
+    let msg_type = match msg.get("type").and_then(|v| v.as_str()) {
+        Some(msg_type) => msg_type,
+        None => {
+            eprintln!("⚠️  Received message missing 'type' field");
+            return messages::nack("unknown", &request_id, ErrorCode::InvalidMessage, "Missing 'type' field");
+        }
+    };
+    
+    match msg_type {
+        "select_pair" => {
+            let raw_pair = match msg.get("pair").and_then(|v| v.as_str()) {
+                Some(pair) => pair,
+                None => {
+                    eprintln!("⚠️  Pair selection message missing 'pair' field");
+                    return messages::nack(msg_type, &request_id, ErrorCode::InvalidMessage, "Missing 'pair' field");
+                }
+            };
+            let Some(pair) = supported_pair(raw_pair) else {
+                return messages::nack(msg_type, &request_id, ErrorCode::UnknownPair, &format!("Unknown pair: {}", raw_pair));
+            };
+            println!("📊 Pair selection received: {}", pair);
+            let old_pair = manager.get_selected_pair().await;
+            manager.set_selected_pair(pair.to_string()).await;
+            println!("✅ Pair updated: {} -> {}", old_pair, pair);
+            messages::ack(msg_type, &request_id, json!({ "pair": pair }))
+        }
+        "subscribe" | "unsubscribe" => {
+            let channel = msg.get("channel").and_then(|v| v.as_str()).unwrap_or("trades");
+            let raw_pair = if GLOBAL_CHANNELS.contains(&channel) {
+                ALL_PAIRS
+            } else {
+                match msg.get("pair").and_then(|v| v.as_str()) {
+                    Some(pair) => pair,
+                    None => {
+                        return messages::nack(msg_type, &request_id, ErrorCode::InvalidMessage, "Missing 'pair' field");
+                    }
+                }
+            };
+            let pair = if raw_pair == ALL_PAIRS {
+                ALL_PAIRS.to_string()
+            } else {
+                match supported_pair(raw_pair) {
+                    Some(pair) => pair,
+                    None => {
+                        return messages::nack(msg_type, &request_id, ErrorCode::UnknownPair, &format!("Unknown pair: {}", raw_pair));
+                    }
+                }
+            };
+            
+            let subscription = Subscription {
+                channel: channel.to_string(),
+                pair: pair.clone(),
+            };
+            
+            if msg_type == "subscribe" {
+                match manager.subscribe(connection_id, subscription).await {
+                    Ok(()) => messages::ack(msg_type, &request_id, json!({ "channel": channel, "pair": pair })),
+                    Err(code) => {
+                        let message = match code {
+                            ErrorCode::UnknownChannel => format!("Unknown channel: {}", channel),
+                            ErrorCode::LimitExceeded => format!(
+                                "Subscription limit of {} reached", MAX_SUBSCRIPTIONS_PER_CONNECTION
+                            ),
+                            ErrorCode::Unauthorized => format!("Channel {} requires authentication", channel),
+                            _ => "Subscription failed".to_string(),
+                        };
+                        messages::nack(msg_type, &request_id, code, &message)
+                    }
+                }
+            } else if manager.unsubscribe(connection_id, &subscription).await {
+                messages::ack(msg_type, &request_id, json!({ "channel": channel, "pair": pair }))
+            } else {
+                messages::nack(msg_type, &request_id, ErrorCode::InvalidMessage, "Not subscribed")
+            }
+        }
+        "auth" => {
+            let token = match msg.get("token").and_then(|v| v.as_str()) {
+                Some(token) => token,
+                None => {
+                    return messages::nack(msg_type, &request_id, ErrorCode::InvalidMessage, "Missing 'token' field");
+                }
+            };
+            match jwt::verify_token(token) {
+                // Resource tokens (signed download URLs) can't be used as sessions
+                Ok(claims) if claims.scope.is_some() => {
+                    messages::nack(msg_type, &request_id, ErrorCode::Unauthorized, "Not a session token")
+                }
+                Ok(_) if state.revocations.is_revoked(token).await => {
+                    messages::nack(msg_type, &request_id, ErrorCode::Unauthorized, "Session expired or revoked")
+                }
+                // Logouts are recorded in revoked_sessions, which validate_session checks, so another replica's logout is seen here too
+                Ok(claims) => match state.clickhouse.validate_session(&claims.sub, token).await {
+                    Ok(true) => {
+                        manager.authenticate(connection_id, claims.sub.clone()).await;
+                        messages::ack(msg_type, &request_id, json!({ "wallet": claims.sub }))
+                    }
+                    Ok(false) => messages::nack(msg_type, &request_id, ErrorCode::Unauthorized, "Session expired or revoked"),
+                    Err(e) => {
+                        eprintln!("❌ Session validation failed: {}", e);
+                        messages::nack(msg_type, &request_id, ErrorCode::InternalError, "Failed to validate session")
+                    }
+                },
+                Err(e) => messages::nack(msg_type, &request_id, ErrorCode::Unauthorized, &format!("Invalid token: {}", e)),
+            }
+        }
+        "pause" => {
+            let mode = match PauseMode::from_message(&msg) {
+                Ok(mode) => mode,
+                Err(message) => return messages::nack(msg_type, &request_id, ErrorCode::InvalidMessage, &message),
+            };
+            match manager.set_pause(connection_id, Some(mode)).await {
+                Ok(()) => {
+                    let max_buffered = match mode {
+                        PauseMode::Buffer(max) => json!(max),
+                        PauseMode::Conflate => serde_json::Value::Null,
+                    };
+                    messages::ack(msg_type, &request_id, json!({ "mode": mode.name(), "max_buffered": max_buffered }))
+                }
+                Err(code) => messages::nack(msg_type, &request_id, code, "Already paused"),
+            }
+        }
+        "resume" => match manager.set_pause(connection_id, None).await {
+            Ok(()) => messages::ack(msg_type, &request_id, json!({})),
+            Err(code) => messages::nack(msg_type, &request_id, code, "Not paused"),
+        },
+        "settings" => handle_settings_message(state, connection_id, &msg, &request_id).await,
+        "export" => {
+            if let Err(off) = state.features.check("exports").await {
+                let message = match off {
+                    FeatureOff::NotOffered => "Exports are not enabled on this deployment".to_string(),
+                    FeatureOff::Paused(reason) => reason.unwrap_or_else(|| "Exports are temporarily disabled".to_string()),
+                };
+                return messages::nack(msg_type, &request_id, ErrorCode::FeatureDisabled, &message);
+            }
+            let raw_pair = msg.get("pair").and_then(|v| v.as_str()).unwrap_or("SOL/USDC");
+            let format = msg.get("format").and_then(|v| v.as_str()).unwrap_or("csv");
+            if format != "csv" {
+                return messages::nack(msg_type, &request_id, ErrorCode::InvalidMessage, "Only csv exports are supported");
+            }
+            let pair = match parse_pair(raw_pair) {
+                Some(pair) if pair.mints().is_some() => pair,
+                _ => return messages::nack(msg_type, &request_id, ErrorCode::UnknownPair, &format!("Unknown pair: {}", raw_pair)),
+            };
+            let limit = msg.get("limit").and_then(|v| v.as_u64()).map(|l| l as usize).unwrap_or(MAX_EXPORT_ROWS);
+            if limit > MAX_EXPORT_ROWS {
+                return messages::nack(msg_type, &request_id, ErrorCode::LimitExceeded,
+                    &format!("Exports are limited to {} rows", MAX_EXPORT_ROWS));
+            }
+            let sample = match msg.get("sample").and_then(|v| v.as_str()).map(TradeSample::parse).transpose() {
+                Ok(sample) => sample,
+                Err(message) => return messages::nack(msg_type, &request_id, ErrorCode::InvalidMessage, &message),
+            };
+            match state.exports.start_export(connection_id, pair.base.clone(), pair.quote.clone(), limit, sample).await {
+                Some(export_id) => messages::ack(msg_type, &request_id, json!({
+                    "export_id": export_id,
+                    "pair": pair,
+                    "sample": sample.map(|s| s.to_string()),
+                })),
+                None => messages::nack(msg_type, &request_id, ErrorCode::LimitExceeded, "An export is already running on this connection"),
+            }
+        }
+        _ => {
+            println!("ℹ️  Received message type: {}", msg_type);
+            messages::nack(msg_type, &request_id, ErrorCode::UnknownType, &format!("Unknown message type: {}", msg_type))
+        }
+    }
+}
+
+/// Pull or push the authenticated wallet's settings blobs
+/// Updates are fanned out to the wallet's other connections for cross-tab sync
+async fn handle_settings_message(
+    state: &AppState,
+    connection_id: Uuid,
+    msg: &serde_json::Value,
+    request_id: &serde_json::Value,
+) -> String {
+    let wallet = match state.ws_manager.wallet_for(connection_id).await {
+        Some(wallet) => wallet,
+        None => {
+            return messages::nack("settings", request_id, ErrorCode::Unauthorized, "Authenticate before using settings");
+        }
+    };
+    
+    match msg.get("action").and_then(|v| v.as_str()).unwrap_or("get") {
+        "get" => match state.clickhouse.get_preferences(&wallet).await {
+            Ok(preferences) => {
+                let settings: serde_json::Map<String, serde_json::Value> = preferences
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let value = serde_json::from_str(&value).unwrap_or(serde_json::Value::Null);
+                        (key, value)
+                    })
+                    .collect();
+                messages::ack("settings", request_id, json!({ "settings": settings }))
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to load settings for {}: {}", wallet, e);
+                messages::nack("settings", request_id, ErrorCode::InternalError, "Failed to load settings")
+            }
+        },
+        "set" => {
+            let key = match msg.get("key").and_then(|v| v.as_str()) {
+                Some(key) if !key.is_empty() && key.len() <= 64 => key,
+                _ => {
+                    return messages::nack("settings", request_id, ErrorCode::InvalidMessage, "Missing or invalid 'key' field");
+                }
+            };
+            let value = msg.get("value").cloned().unwrap_or(serde_json::Value::Null).to_string();
+            if value.len() > MAX_SETTING_VALUE_BYTES {
+                return messages::nack("settings", request_id, ErrorCode::LimitExceeded,
+                    &format!("Setting values are limited to {} bytes", MAX_SETTING_VALUE_BYTES));
+            }
+            
+            let existing = match state.clickhouse.get_preferences(&wallet).await {
+                Ok(preferences) => preferences,
+                Err(e) => {
+                    eprintln!("❌ Failed to load settings for {}: {}", wallet, e);
+                    return messages::nack("settings", request_id, ErrorCode::InternalError, "Failed to load settings");
+                }
+            };
+            if existing.len() >= MAX_SETTINGS_KEYS && !existing.iter().any(|(k, _)| k == key) {
+                return messages::nack("settings", request_id, ErrorCode::LimitExceeded,
+                    &format!("At most {} settings keys are allowed", MAX_SETTINGS_KEYS));
+            }
+            
+            if let Err(e) = state.clickhouse.store_preference(&wallet, key, &value).await {
+                eprintln!("❌ Failed to store setting {} for {}: {}", key, wallet, e);
+                return messages::nack("settings", request_id, ErrorCode::InternalError, "Failed to store setting");
+            }
+            
+            let update = json!({
+                "type": "settings_update",
+                "key": key,
+                "value": msg.get("value").cloned().unwrap_or(serde_json::Value::Null),
+            });
+            state.ws_manager.send_to_wallet(&wallet, &update.to_string(), Some(connection_id)).await;
+            messages::ack("settings", request_id, json!({ "key": key }))
+        }
+        other => messages::nack("settings", request_id, ErrorCode::InvalidMessage, &format!("Unknown settings action: {}", other)),
+    }
+}
+
+#[cfg(all(test, feature = "chaos"))]
+mod tests {
+    use super::*;
+    use crate::utils::chaos::{configure, FaultRates};
+    use axum::extract::ws::Message;
+
+    // One test drives the WsSend point, so its settings can't leak into another
+    #[tokio::test]
+    async fn send_faults_lose_the_message_or_fail_the_socket() {
+        let mut sent: Vec<Message> = Vec::new();
+
+        configure(FaultPoint::WsSend, Some(FaultRates::parse("drop=1").unwrap()));
+        assert!(send_text(&mut sent, "dropped".to_string()).await);
+        configure(FaultPoint::WsSend, Some(FaultRates::parse("error=1").unwrap()));
+        assert!(!send_text(&mut sent, "failed".to_string()).await);
+        assert!(sent.is_empty());
+
+        configure(FaultPoint::WsSend, None);
+        assert!(send_text(&mut sent, "delivered".to_string()).await);
+        assert_eq!(sent, [Message::Text("delivered".into())]);
+    }
+}
@@ -1,37 +1,161 @@
 // WebSocket connection manager module
 
-use std::collections::HashMap;
+use axum::extract::ws::Message;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, mpsc, watch, Mutex, RwLock};
+use tokio::time::{sleep_until, Duration, Instant};
 use uuid::Uuid;
 
+use crate::websocket::messages::{ErrorCode, OutboundMessage, ALL_PAIRS, CHANNELS, OPT_IN_CHANNELS, PRIVATE_CHANNELS};
+use crate::websocket::pause::PauseMode;
+
 pub type ConnectionId = Uuid;
-pub type ConnectionMap = Arc<RwLock<HashMap<ConnectionId, broadcast::Sender<String>>>>;
+pub type ConnectionMap = Arc<RwLock<HashMap<ConnectionId, ConnectionState>>>;
+
+/// Maximum number of (channel, pair) subscriptions a single connection may hold
+pub const MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 20;
+
+/// Real trades get a deep buffer so slow clients don't lose fills
+const TRADE_CHANNEL_CAPACITY: usize = 10_000;
+/// Price ticks and market overviews are superseded every few seconds, so only a few are buffered
+const PRICE_CHANNEL_CAPACITY: usize = 16;
+
+/// Server-initiated ping schedule; WS_HEARTBEAT_SECS and WS_MAX_MISSED_HEARTBEATS
+/// `max_missed` is how many pings a connection may leave unanswered before it's reaped
+#[derive(Debug, Clone, Copy)]
+pub struct Heartbeat {
+    pub interval: Duration,
+    pub max_missed: u32,
+}
+
+/// Price ticks and market overviews go on the shallow channel; everything else is a trade
+fn is_superseded(channel: &str) -> bool {
+    matches!(channel, "prices" | "markets")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Subscription {
+    pub channel: String,
+    pub pair: String,
+}
+
+/// Per-connection state
+pub struct ConnectionState {
+    pub subscriptions: HashSet<Subscription>,
+    /// Wallet pubkey once the connection has authenticated with a JWT
+    pub wallet: Option<String>,
+    /// Direct channel to this connection's send task (replies, private pushes)
+    pub direct_tx: mpsc::UnboundedSender<Message>,
+    /// Tells the send task to hold broadcasts (Some) or replay them and carry on (None)
+    pub pause_tx: watch::Sender<Option<PauseMode>>,
+    /// When the client last answered a heartbeat ping (or connected)
+    pub last_pong: Instant,
+}
+
+/// Broadcast receivers handed to each connection, one per priority class
+pub struct ConnectionReceivers {
+    pub trades: broadcast::Receiver<Arc<OutboundMessage>>,
+    pub prices: broadcast::Receiver<Arc<OutboundMessage>>,
+}
+
+/// Senders of one priority-class pair of broadcast channels
+#[derive(Clone)]
+struct Feed {
+    trades_tx: broadcast::Sender<Arc<OutboundMessage>>,
+    prices_tx: broadcast::Sender<Arc<OutboundMessage>>,
+}
+
+impl Feed {
+    fn new() -> Self {
+        let (trades_tx, _) = broadcast::channel(TRADE_CHANNEL_CAPACITY);
+        let (prices_tx, _) = broadcast::channel(PRICE_CHANNEL_CAPACITY);
+        Self { trades_tx, prices_tx }
+    }
+
+    fn subscribe(&self) -> ConnectionReceivers {
+        ConnectionReceivers {
+            trades: self.trades_tx.subscribe(),
+            prices: self.prices_tx.subscribe(),
+        }
+    }
+
+    fn send(&self, message: Arc<OutboundMessage>) -> Result<usize, broadcast::error::SendError<Arc<OutboundMessage>>> {
+        if is_superseded(&message.channel) {
+            self.prices_tx.send(message)
+        } else {
+            self.trades_tx.send(message)
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct ConnectionManager {
     connections: ConnectionMap,
-    broadcast_tx: broadcast::Sender<String>,
+    // Trades and synthetic price ticks use separate channels so a burst of
+    // ticks can never push real trades out of a lagging client's buffer
+    live: Feed,
+    /// Delayed copies of the live feed, one per data plan delay, started on first use
+    delayed: Arc<Mutex<HashMap<Duration, Feed>>>,
     selected_pair: Arc<RwLock<String>>,
+    pub heartbeat: Heartbeat,
+    /// Open Server-Sent Events streams, which read the feed without a registered connection
+    streams: Arc<AtomicUsize>,
+}
+
+/// Held by a Server-Sent Events stream; the stream stops counting as a client when it is dropped
+pub struct StreamGuard {
+    streams: Arc<AtomicUsize>,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.streams.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl ConnectionManager {
-    pub fn new() -> Self {
-        let (broadcast_tx, _) = broadcast::channel(1000);
-        
+    pub fn new(heartbeat: Heartbeat) -> Self {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
-            broadcast_tx,
+            live: Feed::new(),
+            delayed: Arc::new(Mutex::new(HashMap::new())),
             selected_pair: Arc::new(RwLock::new("SOL/USDC".to_string())), // Default pair
+            heartbeat,
+            streams: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    pub async fn add_connection(&self, id: ConnectionId) -> broadcast::Receiver<String> {
+    /// Register a connection; a non-zero `delay` (its data plan's) subscribes it to that delayed feed
+    pub async fn add_connection(&self, id: ConnectionId, direct_tx: mpsc::UnboundedSender<Message>, delay: Duration) -> ConnectionReceivers {
+        let receivers = if delay.is_zero() {
+            self.live.subscribe()
+        } else {
+            self.delayed_feed(delay).await.subscribe()
+        };
         let mut connections = self.connections.write().await;
-        let receiver = self.broadcast_tx.subscribe();
-        connections.insert(id, self.broadcast_tx.clone());
+        connections.insert(id, ConnectionState {
+            subscriptions: HashSet::new(),
+            wallet: None,
+            direct_tx,
+            pause_tx: watch::channel(None).0,
+            last_pong: Instant::now(),
+        });
         println!("✅ WebSocket connection added: {}", id);
-        receiver
+        receivers
+    }
+
+    /// Receivers for a Server-Sent Events stream (live, or `delay` behind like a connection on that plan)
+    /// Filtering is up to the stream; it counts as a client until the guard is dropped
+    pub async fn add_stream(&self, delay: Duration) -> (ConnectionReceivers, StreamGuard) {
+        let receivers = if delay.is_zero() {
+            self.live.subscribe()
+        } else {
+            self.delayed_feed(delay).await.subscribe()
+        };
+        self.streams.fetch_add(1, Ordering::Relaxed);
+        (receivers, StreamGuard { streams: self.streams.clone() })
     }
 
     pub async fn remove_connection(&self, id: ConnectionId) {
@@ -40,12 +164,12 @@ impl ConnectionManager {
         println!("❌ WebSocket connection removed: {}", id);
     }
 
-    pub async fn broadcast(&self, message: String) -> usize {
+    pub async fn broadcast(&self, message: OutboundMessage) -> usize {
         let connections = self.connections.read().await;
-        let count = connections.len();
+        let count = connections.len() + self.streams.load(Ordering::Relaxed);
         
         if count > 0 {
-            match self.broadcast_tx.send(message) {
+            match self.live.send(Arc::new(message)) {
                 Ok(_) => {
                     // Only log occasionally to reduce noise
                     static BROADCAST_COUNT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
@@ -70,10 +194,150 @@ impl ConnectionManager {
         count
     }
 
+    /// Feed running `delay` behind the live one, starting its delay line if this is the first connection on it
+    async fn delayed_feed(&self, delay: Duration) -> Feed {
+        let mut delayed = self.delayed.lock().await;
+        if let Some(feed) = delayed.get(&delay) {
+            return feed.clone();
+        }
+        let feed = Feed::new();
+        println!("⏱️  [ConnectionManager] Starting {}s delayed feed", delay.as_secs_f64());
+        tokio::spawn(run_delay_line(self.live.subscribe(), feed.clone(), delay));
+        delayed.insert(delay, feed.clone());
+        feed
+    }
+
     pub async fn connection_count(&self) -> usize {
         self.connections.read().await.len()
     }
 
+    /// Add a (channel, pair) subscription for a connection
+    pub async fn subscribe(&self, id: ConnectionId, subscription: Subscription) -> Result<(), ErrorCode> {
+        if !CHANNELS.contains(&subscription.channel.as_str()) {
+            return Err(ErrorCode::UnknownChannel);
+        }
+
+        let mut connections = self.connections.write().await;
+        let state = connections.get_mut(&id).ok_or(ErrorCode::InvalidMessage)?;
+
+        if PRIVATE_CHANNELS.contains(&subscription.channel.as_str()) && state.wallet.is_none() {
+            return Err(ErrorCode::Unauthorized);
+        }
+        if !state.subscriptions.contains(&subscription)
+            && state.subscriptions.len() >= MAX_SUBSCRIPTIONS_PER_CONNECTION
+        {
+            return Err(ErrorCode::LimitExceeded);
+        }
+
+        state.subscriptions.insert(subscription);
+        Ok(())
+    }
+
+    /// Remove a subscription; returns false if the connection wasn't subscribed
+    pub async fn unsubscribe(&self, id: ConnectionId, subscription: &Subscription) -> bool {
+        let mut connections = self.connections.write().await;
+        connections
+            .get_mut(&id)
+            .map(|state| state.subscriptions.remove(subscription))
+            .unwrap_or(false)
+    }
+
+    /// Live subscriber counts per (channel, pair)
+    /// Connections without subscriptions (firehose) are counted under ("*", "*")
+    pub async fn subscription_counts(&self) -> HashMap<(String, String), u32> {
+        let connections = self.connections.read().await;
+        let mut counts: HashMap<(String, String), u32> = HashMap::new();
+        for state in connections.values() {
+            if state.subscriptions.is_empty() {
+                *counts.entry((ALL_PAIRS.to_string(), ALL_PAIRS.to_string())).or_default() += 1;
+            }
+            for subscription in &state.subscriptions {
+                *counts.entry((subscription.channel.clone(), subscription.pair.clone())).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Whether a broadcast message should be delivered to a connection
+    /// Connections without subscriptions receive the full firehose; messages
+    /// without a pair (global channels) go to anyone subscribed to the channel; opt-in channels
+    /// (e.g. order book deltas) only go to their subscribers
+    pub async fn should_deliver(&self, id: ConnectionId, message: &OutboundMessage) -> bool {
+        let connections = self.connections.read().await;
+        match connections.get(&id) {
+            Some(state) if !state.subscriptions.is_empty() => {
+                state.subscriptions.iter().any(|s| {
+                    s.channel == message.channel
+                        && message.pair.as_ref().is_none_or(|pair| &s.pair == pair)
+                })
+            }
+            _ => !OPT_IN_CHANNELS.contains(&message.channel.as_str()),
+        }
+    }
+
+    /// Pause (Some) or resume (None) a connection's broadcasts
+    /// Fails with InvalidMessage when the connection is already in that state
+    pub async fn set_pause(&self, id: ConnectionId, mode: Option<PauseMode>) -> Result<(), ErrorCode> {
+        let connections = self.connections.read().await;
+        let state = connections.get(&id).ok_or(ErrorCode::InvalidMessage)?;
+        if state.pause_tx.borrow().is_some() == mode.is_some() {
+            return Err(ErrorCode::InvalidMessage);
+        }
+        state.pause_tx.send_replace(mode);
+        Ok(())
+    }
+
+    /// Pause changes for a connection's send task
+    pub async fn pause_updates(&self, id: ConnectionId) -> Option<watch::Receiver<Option<PauseMode>>> {
+        self.connections.read().await.get(&id).map(|state| state.pause_tx.subscribe())
+    }
+
+    /// Note a pong from the client
+    pub async fn record_pong(&self, id: ConnectionId) {
+        if let Some(state) = self.connections.write().await.get_mut(&id) {
+            state.last_pong = Instant::now();
+        }
+    }
+
+    /// When a connection last answered a ping; None once it's removed
+    pub async fn last_pong(&self, id: ConnectionId) -> Option<Instant> {
+        self.connections.read().await.get(&id).map(|state| state.last_pong)
+    }
+
+    /// Mark a connection as authenticated for a wallet
+    pub async fn authenticate(&self, id: ConnectionId, wallet: String) {
+        if let Some(state) = self.connections.write().await.get_mut(&id) {
+            println!("🔐 [ConnectionManager] Connection {} authenticated as {}", id, wallet);
+            state.wallet = Some(wallet);
+        }
+    }
+
+    /// Wallet a connection has authenticated as, if any
+    pub async fn wallet_for(&self, id: ConnectionId) -> Option<String> {
+        self.connections.read().await.get(&id).and_then(|state| state.wallet.clone())
+    }
+
+    /// Push a message to every connection authenticated as `wallet`, optionally skipping one
+    /// Returns the number of connections it was delivered to
+    pub async fn send_to_wallet(&self, wallet: &str, message: &str, except: Option<ConnectionId>) -> usize {
+        let connections = self.connections.read().await;
+        connections
+            .iter()
+            .filter(|(id, state)| Some(**id) != except && state.wallet.as_deref() == Some(wallet))
+            .filter(|(_, state)| state.direct_tx.send(Message::Text(message.to_string().into())).is_ok())
+            .count()
+    }
+
+    /// Push a message to a single connection; returns false if it's gone
+    pub async fn send_to_connection(&self, id: ConnectionId, message: &str) -> bool {
+        self.connections
+            .read()
+            .await
+            .get(&id)
+            .map(|state| state.direct_tx.send(Message::Text(message.to_string().into())).is_ok())
+            .unwrap_or(false)
+    }
+
     pub async fn set_selected_pair(&self, pair: String) {
         let mut selected = self.selected_pair.write().await;
         let old_pair = selected.clone();
@@ -86,3 +350,34 @@ impl ConnectionManager {
     }
 }
 
+/// Hold every live message for `delay`, then rebroadcast it on the delayed feed in arrival order
+/// One buffer per delay serves all of its connections
+async fn run_delay_line(mut source: ConnectionReceivers, feed: Feed, delay: Duration) {
+    let mut buffer: VecDeque<(Instant, Arc<OutboundMessage>)> = VecDeque::new();
+    loop {
+        let next_release = buffer.front().map(|(release_at, _)| *release_at);
+        tokio::select! {
+            result = source.trades.recv() => match result {
+                Ok(message) => buffer.push_back((Instant::now() + delay, message)),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("⚠️  [ConnectionManager] {}s delayed feed lagged, {} trades dropped", delay.as_secs_f64(), skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            result = source.prices.recv() => match result {
+                Ok(message) => buffer.push_back((Instant::now() + delay, message)),
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = sleep_until(next_release.unwrap_or_else(Instant::now)), if next_release.is_some() => {
+                let now = Instant::now();
+                while buffer.front().is_some_and(|(release_at, _)| *release_at <= now) {
+                    if let Some((_, message)) = buffer.pop_front() {
+                        // No subscribers right now is fine; the message just isn't seen
+                        let _ = feed.send(message);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+// WebSocket protocol message module
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Channels a client can subscribe to
+pub const CHANNELS: &[&str] = &["trades", "prices", "markets", "orderbook", "alerts", "whales", "candles", "loadtest"];
+
+/// Channels carrying every pair at once; subscribed without a pair
+pub const GLOBAL_CHANNELS: &[&str] = &["markets", "whales"];
+
+/// Channels only delivered to connections subscribed to them, never to the unsubscribed firehose
+pub const OPT_IN_CHANNELS: &[&str] = &["orderbook", "alerts", "whales", "candles", "loadtest"];
+
+/// Pair recorded for subscriptions to global channels
+pub const ALL_PAIRS: &str = "*";
+
+/// Channels that require an authenticated connection
+pub const PRIVATE_CHANNELS: &[&str] = &[];
+
+/// Machine-readable error codes returned in nack envelopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    UnknownPair,
+    UnknownChannel,
+    LimitExceeded,
+    Unauthorized,
+    InvalidMessage,
+    UnknownType,
+    FeatureDisabled,
+    InternalError,
+}
+
+/// Message routed through the broadcast channel, tagged so each connection can filter it
+#[derive(Debug, Clone)]
+pub struct OutboundMessage {
+    pub channel: String,
+    pub pair: Option<String>,
+    pub payload: String,
+    /// Trade id that sampled connections (?sample=1/N) hash to decide delivery; None is always delivered
+    pub trade_id: Option<String>,
+    /// Per-pair sequence of a trade message, so consumers can order and resume without parsing the payload
+    pub sequence: Option<u64>,
+}
+
+impl OutboundMessage {
+    pub fn new(channel: &str, pair: Option<String>, payload: String) -> Self {
+        Self {
+            channel: channel.to_string(),
+            pair,
+            payload,
+            trade_id: None,
+            sequence: None,
+        }
+    }
+
+    /// Tag a trade message so sampled connections can select it
+    pub fn with_trade_id(mut self, trade_id: &str) -> Self {
+        self.trade_id = Some(trade_id.to_string());
+        self
+    }
+
+    /// Tag a trade message with its sequence
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+}
+
+/// Positive acknowledgement for a client request
+pub fn ack(action: &str, request_id: &Value, details: Value) -> String {
+    let mut envelope = json!({
+        "type": "ack",
+        "action": action,
+        "request_id": request_id,
+    });
+    if let (Some(obj), Some(extra)) = (envelope.as_object_mut(), details.as_object()) {
+        for (key, value) in extra {
+            obj.insert(key.clone(), value.clone());
+        }
+    }
+    envelope.to_string()
+}
+
+/// Negative acknowledgement with an error code and human-readable message
+pub fn nack(action: &str, request_id: &Value, code: ErrorCode, message: &str) -> String {
+    json!({
+        "type": "nack",
+        "action": action,
+        "request_id": request_id,
+        "code": code,
+        "message": message,
+    })
+    .to_string()
+}
@@ -1,7 +1,10 @@
 // WebSocket module
 
+pub mod batching;
 pub mod handler;
 pub mod manager;
+pub mod messages;
+pub mod pause;
 
 pub use handler::websocket_handler;
 pub use manager::ConnectionManager;
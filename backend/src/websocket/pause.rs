@@ -0,0 +1,99 @@
+// Pause/resume module - holds a paused connection's broadcasts until it resumes, so a client can
+// stop the tape (e.g. while the user scrolls) without dropping the socket or its subscriptions
+
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::websocket::messages::OutboundMessage;
+
+/// Most messages a paused connection can hold in `buffer` mode, and the default
+pub const MAX_PAUSE_BUFFER: usize = 1_000;
+
+/// How a paused connection holds broadcasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseMode {
+    /// Keep every message in order, up to this many; beyond it the oldest are dropped
+    Buffer(usize),
+    /// Keep only the latest message per channel, pair and candle interval
+    Conflate,
+}
+
+impl PauseMode {
+    /// Mode from a `pause` message: `mode` is `buffer` (default) or `conflate`, and `max_buffered`
+    /// caps the buffer at up to MAX_PAUSE_BUFFER
+    pub fn from_message(msg: &Value) -> Result<Self, String> {
+        match msg.get("mode").and_then(|v| v.as_str()).unwrap_or("buffer") {
+            "buffer" => {
+                let max_buffered = match msg.get("max_buffered") {
+                    None => MAX_PAUSE_BUFFER,
+                    Some(value) => value
+                        .as_u64()
+                        .filter(|n| (1..=MAX_PAUSE_BUFFER as u64).contains(n))
+                        .ok_or_else(|| format!("max_buffered must be between 1 and {}", MAX_PAUSE_BUFFER))?
+                        as usize,
+                };
+                Ok(PauseMode::Buffer(max_buffered))
+            }
+            "conflate" => Ok(PauseMode::Conflate),
+            other => Err(format!("Unknown pause mode '{}'; expected buffer or conflate", other)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PauseMode::Buffer(_) => "buffer",
+            PauseMode::Conflate => "conflate",
+        }
+    }
+}
+
+/// Conflation key of a message: its channel and pair, plus the interval for candles
+fn conflation_key(message: &OutboundMessage) -> (String, Option<String>, Option<String>) {
+    let interval = (message.channel == "candles")
+        .then(|| serde_json::from_str::<Value>(&message.payload).ok())
+        .flatten()
+        .and_then(|payload| payload.get("interval").and_then(|v| v.as_str()).map(str::to_string));
+    (message.channel.clone(), message.pair.clone(), interval)
+}
+
+/// Broadcasts held while a connection is paused
+pub struct HeldMessages {
+    mode: PauseMode,
+    messages: VecDeque<Arc<OutboundMessage>>,
+    /// Messages dropped by the buffer cap or replaced by a newer one
+    dropped: u64,
+}
+
+impl HeldMessages {
+    pub fn new(mode: PauseMode) -> Self {
+        Self {
+            mode,
+            messages: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn hold(&mut self, message: Arc<OutboundMessage>) {
+        match self.mode {
+            PauseMode::Buffer(max_buffered) => {
+                if self.messages.len() >= max_buffered {
+                    self.messages.pop_front();
+                    self.dropped += 1;
+                }
+            }
+            PauseMode::Conflate => {
+                let key = conflation_key(&message);
+                let before = self.messages.len();
+                self.messages.retain(|held| conflation_key(held) != key);
+                self.dropped += (before - self.messages.len()) as u64;
+            }
+        }
+        self.messages.push_back(message);
+    }
+
+    /// Held messages in arrival order, and how many were dropped
+    pub fn release(self) -> (VecDeque<Arc<OutboundMessage>>, u64) {
+        (self.messages, self.dropped)
+    }
+}
@@ -3,7 +3,8 @@ import { flushSync } from 'react-dom';
 import { useNavigate } from 'react-router-dom';
 import { useWallet } from '@solana/wallet-adapter-react';
 import bs58 from 'bs58';
-import { authApi } from '@/lib/api';
+import { authApi, type AuthNonceResponse, type ChallengeFormat } from '@/lib/api';
+import { challengeBytes } from '@/lib/authChallenge';
 import { toast } from '@/hooks/use-toast';
 import { tradeWebSocket } from '@/lib/websocket';
 
@@ -11,9 +12,13 @@ interface AuthState {
   isAuthenticated: boolean;
   token: string | null;
   isLoading: boolean;
-  nonce: string | null;
+  nonce: AuthNonceResponse | null;
 }
 
+// `offchain` signs a Solana off-chain message, which hardware wallets such as Ledger require
+const CHALLENGE_FORMAT: ChallengeFormat =
+  import.meta.env.VITE_AUTH_CHALLENGE_FORMAT === 'offchain' ? 'offchain' : 'raw';
+
 export const usePhantomAuth = () => {
   const { publicKey, signMessage, connected } = useWallet();
   const navigate = useNavigate();
@@ -62,9 +67,9 @@ export const usePhantomAuth = () => {
       const fetchNonce = async () => {
         try {
           setAuthState((prev) => ({ ...prev, isLoading: true }));
-          const { nonce } = await authApi.getNonce();
+          const nonce = await authApi.getNonce(CHALLENGE_FORMAT);
           setAuthState((prev) => ({ ...prev, nonce, isLoading: false }));
-          console.log('✅ Nonce received:', nonce);
+          console.log('✅ Nonce received:', nonce.nonce);
         } catch (error) {
           console.error('❌ Failed to fetch nonce:', error);
           setAuthState((prev) => ({ ...prev, isLoading: false }));
@@ -94,20 +99,17 @@ export const usePhantomAuth = () => {
     setAuthState((prev) => ({ ...prev, isLoading: true }));
 
     try {
-      // Step 1: Get nonce (use existing if available, otherwise fetch new)
+      // Step 1: Get nonce (use existing if still valid, otherwise fetch new)
       let nonce = authState.nonce;
-      if (!nonce) {
-        const nonceResponse = await authApi.getNonce();
-        nonce = nonceResponse.nonce;
+      if (!nonce || new Date(nonce.expiresAt) <= new Date()) {
+        nonce = await authApi.getNonce(CHALLENGE_FORMAT);
         setAuthState((prev) => ({ ...prev, nonce }));
       }
 
-      console.log('📝 Using nonce for signing:', nonce);
+      console.log('📝 Using nonce for signing:', nonce.nonce, `(${nonce.challengeFormat})`);
 
       // Step 2: Create message to sign
-      const message = new TextEncoder().encode(
-        `Sign this message to authenticate with Trade: ${nonce}`
-      );
+      const message = challengeBytes(nonce, publicKey.toBytes());
 
       console.log('✍️ Requesting signature from Phantom wallet...');
 
@@ -124,7 +126,7 @@ export const usePhantomAuth = () => {
       const response = await authApi.verifySignature({
         publicKey: publicKey.toBase58(),
         signature: bs58.encode(signature),
-        nonce,
+        nonce: nonce.nonce,
       });
 
       console.log('📥 Backend response:', response);
@@ -192,6 +194,12 @@ export const usePhantomAuth = () => {
     // Disconnect WebSocket first
     tradeWebSocket.disconnect();
     
+    // Revoke the token on the server; signing out locally doesn't wait for it
+    const token = localStorage.getItem('auth_token');
+    if (token) {
+      authApi.logout(token).catch((error) => console.error('❌ Failed to revoke session:', error));
+    }
+    
     // Clear authentication state
     localStorage.removeItem('auth_token');
     localStorage.removeItem('auth_expires_at');
@@ -17,6 +17,9 @@ export interface Trade {
   total_value: number;
   dex_program: string;
   slot: number;
+  sequence?: number; // Monotonic per-pair ordering key: (slot << 16) | position within the slot
+  inverted?: boolean;
+  spread_bps?: number; // Stored in the opposite orientation and flipped to the requested pair
 }
 
 export interface OHLCVData {
@@ -28,8 +31,22 @@ export interface OHLCVData {
   volume: number;
 }
 
+export interface MidpricePoint {
+  timestamp: number; // unix ms
+  price: number;
+}
+
+export type ChallengeFormat = 'raw' | 'offchain';
+
 export interface AuthNonceResponse {
   nonce: string;
+  challengeFormat: ChallengeFormat;
+  // Text to sign; `offchain` challenges wrap it in a Solana off-chain message
+  challenge: string;
+  // Base58, only set for `offchain` challenges
+  applicationDomain?: string;
+  expiresAt: string;
+  expiresIn: number;
 }
 
 export interface AuthVerifyRequest {
@@ -41,12 +58,16 @@ export interface AuthVerifyRequest {
 export interface AuthVerifyResponse {
   token: string;
   expiresAt: string;
+  expiresIn: number;
+  lifetime: number;
+  // Last moment /auth/refresh will renew this sign-in
+  refreshUntil: string;
 }
 
 // Auth endpoints
 export const authApi = {
-  async getNonce(): Promise<AuthNonceResponse> {
-    const endpoint = '/auth/nonce';
+  async getNonce(challengeFormat: ChallengeFormat = 'raw'): Promise<AuthNonceResponse> {
+    const endpoint = `/auth/nonce?challengeFormat=${challengeFormat}`;
     const method = 'GET';
     
     try {
@@ -124,6 +145,62 @@ export const authApi = {
       throw error;
     }
   },
+
+  // Exchange a session token for a new one before it expires; the old token stops working
+  async refresh(token: string): Promise<AuthVerifyResponse> {
+    const endpoint = '/auth/refresh';
+    const method = 'POST';
+    
+    try {
+      transactionLogger.log('request', endpoint, method);
+      const response = await fetch(`${API_BASE_URL}${endpoint}`, {
+        method,
+        headers: {
+          Authorization: `Bearer ${token}`,
+        },
+      });
+      
+      if (!response.ok) {
+        const errorText = await response.text();
+        transactionLogger.log('error', endpoint, method, undefined, `HTTP ${response.status}: ${errorText}`);
+        throw new Error(`Failed to refresh session: ${response.status} ${errorText}`);
+      }
+      
+      const responseData = await response.json();
+      transactionLogger.log('response', endpoint, method, { ...responseData, token: '***' });
+      return responseData;
+    } catch (error) {
+      transactionLogger.log('error', endpoint, method, undefined, error instanceof Error ? error.message : String(error));
+      throw error;
+    }
+  },
+
+  // Revoke the session token on the server
+  async logout(token: string): Promise<void> {
+    const endpoint = '/auth/logout';
+    const method = 'POST';
+    
+    try {
+      transactionLogger.log('request', endpoint, method);
+      const response = await fetch(`${API_BASE_URL}${endpoint}`, {
+        method,
+        headers: {
+          Authorization: `Bearer ${token}`,
+        },
+      });
+      
+      if (!response.ok) {
+        const errorText = await response.text();
+        transactionLogger.log('error', endpoint, method, undefined, `HTTP ${response.status}: ${errorText}`);
+        throw new Error(`Failed to log out: ${response.status} ${errorText}`);
+      }
+      
+      transactionLogger.log('response', endpoint, method);
+    } catch (error) {
+      transactionLogger.log('error', endpoint, method, undefined, error instanceof Error ? error.message : String(error));
+      throw error;
+    }
+  },
 };
 
 // Trade endpoints
@@ -177,4 +254,23 @@ export const tradeApi = {
     
     return response.json();
   },
+
+  async getMidpriceHistory(pair: string, from?: number, to?: number): Promise<MidpricePoint[]> {
+    const params = new URLSearchParams({ pair });
+    if (from !== undefined) params.set('from', String(from));
+    if (to !== undefined) params.set('to', String(to));
+    const response = await fetch(`${API_BASE_URL}/api/midprice/history?${params}`, {
+      method: 'GET',
+      headers: {
+        'Content-Type': 'application/json',
+      },
+    });
+    
+    if (!response.ok) {
+      throw new Error('Failed to fetch mid-price history');
+    }
+    
+    const data = await response.json();
+    return data.points;
+  },
 };
@@ -0,0 +1,35 @@
+import bs58 from 'bs58';
+import type { AuthNonceResponse } from '@/lib/api';
+
+// Signing domain that prefixes every Solana off-chain message
+const OFFCHAIN_SIGNING_DOMAIN = new Uint8Array([0xff, ...new TextEncoder().encode('solana offchain')]);
+// Largest body Ledger will sign in the ASCII and UTF-8 formats
+const OFFCHAIN_LEDGER_MAX_LEN = 1212;
+
+/**
+ * Bytes the wallet signs for a challenge from /auth/nonce: the raw text, or for `offchain`
+ * a version 0 Solana off-chain message (the format Ledger's Solana app signs)
+ */
+export function challengeBytes(nonce: AuthNonceResponse, signer: Uint8Array): Uint8Array {
+  const body = new TextEncoder().encode(nonce.challenge);
+  if (nonce.challengeFormat !== 'offchain' || !nonce.applicationDomain) {
+    return body;
+  }
+
+  const format = body.length > OFFCHAIN_LEDGER_MAX_LEN ? 2 : body.every((b) => b >= 0x20 && b <= 0x7e) ? 0 : 1;
+  const header = new Uint8Array([
+    ...OFFCHAIN_SIGNING_DOMAIN,
+    0, // version
+    ...bs58.decode(nonce.applicationDomain),
+    format,
+    1, // signer count
+    ...signer,
+    body.length & 0xff,
+    body.length >> 8,
+  ]);
+
+  const message = new Uint8Array(header.length + body.length);
+  message.set(header);
+  message.set(body, header.length);
+  return message;
+}
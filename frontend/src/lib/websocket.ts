@@ -50,8 +50,18 @@ export class TradeWebSocket {
 
       this.ws.onmessage = (event) => {
         try {
-          const trade: Trade = JSON.parse(event.data);
-          
+          const data = JSON.parse(event.data);
+
+          // Control envelopes (ack/nack etc.) carry a `type` field; trades don't
+          if (data.type) {
+            if (data.type === 'nack') {
+              console.warn('⚠️ [WebSocket] Request rejected:', data.action, data.code, data.message);
+            }
+            return;
+          }
+
+          const trade: Trade = data;
+
           // Log received trades and price updates
           if (trade.side === 'price') {
             console.log('💰 [RECEIVE] Price update received:', {